@@ -0,0 +1,57 @@
+//! Evaluates every move in a position given as a move string.
+//!
+//! Run with `cargo run --example evaluate_position <moves> [milliseconds]`, e.g.
+//! `cargo run --example evaluate_position 4453 2000`.
+
+use std::{env, time::Duration};
+
+use rusty_connect_four::game_engine::{
+    game_manager::{GameManager, GameOver},
+    game_record::format_score,
+};
+
+/// How long the engine analyses the position if no time is given.
+const DEFAULT_ANALYSIS_TIME: u64 = 1000;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let moves = args.next().unwrap_or_default();
+    let analysis_time = args
+        .next()
+        .map(|arg| arg.parse().expect("The analysis time should be a number"))
+        .unwrap_or(DEFAULT_ANALYSIS_TIME);
+
+    let mut manager = match GameManager::from_moves(&moves) {
+        Ok(manager) => manager,
+        Err(error) => {
+            eprintln!("{}", error);
+            return;
+        }
+    };
+
+    if manager.is_game_over() != GameOver::NoWin {
+        println!("The game is already over: {:?}", manager.is_game_over());
+        return;
+    }
+
+    let generated = manager.try_generate_for(Duration::from_millis(analysis_time));
+    let move_scores = manager.get_move_scores();
+    let move_confidences = manager.get_move_confidences(&move_scores);
+
+    println!(
+        "Generated {} states, searching {} moves deep",
+        generated,
+        manager.size().depth
+    );
+
+    let mut columns: Vec<&u8> = move_scores.keys().collect();
+    columns.sort();
+    for column in columns {
+        println!(
+            "Column {}: {} ({:?})",
+            column + 1,
+            format_score(move_scores[column]),
+            move_confidences[column]
+        );
+    }
+}
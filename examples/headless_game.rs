@@ -0,0 +1,51 @@
+//! Plays a full game between two copies of the engine, without opening a window.
+//!
+//! Run with `cargo run --example headless_game [milliseconds per move]`.
+
+use std::{collections::HashMap, env, time::Duration};
+
+use rusty_connect_four::game_engine::{
+    game_manager::{GameManager, GameOver},
+    game_record::format_score,
+};
+
+/// How long the engine thinks about each move if no time is given.
+const DEFAULT_MOVE_TIME: u64 = 100;
+
+fn main() {
+    let move_time = env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("The move time should be a number"))
+        .unwrap_or(DEFAULT_MOVE_TIME);
+
+    let mut manager = GameManager::new_game();
+    let mut moves = String::new();
+
+    while manager.is_game_over() == GameOver::NoWin {
+        manager.try_generate_for(Duration::from_millis(move_time));
+
+        let (column, score) = best_move(&manager.get_move_scores()).expect("No moves left");
+        let player = if manager.get_turn() { "Blue" } else { "Red" };
+        println!(
+            "{} plays column {} ({})",
+            player,
+            column + 1,
+            format_score(score)
+        );
+
+        manager
+            .make_move(column)
+            .expect("The engine chose an invalid move");
+        moves.push_str(&(column + 1).to_string());
+    }
+
+    println!("{:?} after {}", manager.is_game_over(), moves);
+}
+
+/// Returns the highest scoring move, along with its score.
+fn best_move(move_scores: &HashMap<u8, isize>) -> Option<(u8, isize)> {
+    move_scores
+        .iter()
+        .max_by_key(|(_, score)| **score)
+        .map(|(column, score)| (*column, *score))
+}
@@ -0,0 +1,59 @@
+//! Scores every move in a position with Monte Carlo tree search instead of alpha-beta pruning.
+//!
+//! Run with `cargo run --example mcts_search <moves> [rollouts]`, e.g.
+//! `cargo run --example mcts_search 4453 20000`.
+
+use std::env;
+
+use rusty_connect_four::game_engine::{
+    game_manager::{GameManager, GameOver},
+    game_record::format_score,
+    monte_carlo::EngineAlgorithm,
+    win_probability::win_probability,
+};
+
+/// How many random games are played out if no number is given.
+const DEFAULT_ROLLOUTS: usize = 10000;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let moves = args.next().unwrap_or_default();
+    let rollouts = args
+        .next()
+        .map(|arg| {
+            arg.parse()
+                .expect("The number of rollouts should be a number")
+        })
+        .unwrap_or(DEFAULT_ROLLOUTS);
+
+    let mut manager = match GameManager::from_moves(&moves) {
+        Ok(manager) => manager,
+        Err(error) => {
+            eprintln!("{}", error);
+            return;
+        }
+    };
+
+    if manager.is_game_over() != GameOver::NoWin {
+        println!("The game is already over: {:?}", manager.is_game_over());
+        return;
+    }
+
+    manager.set_algorithm(EngineAlgorithm::Mcts);
+    let played = manager.run_rollouts(rollouts);
+    let move_scores = manager.get_move_scores();
+
+    println!("Played out {} random games", played);
+
+    let mut columns: Vec<&u8> = move_scores.keys().collect();
+    columns.sort();
+    for column in columns {
+        let score = move_scores[column];
+        println!(
+            "Column {}: {} ({:.0}% to win)",
+            column + 1,
+            format_score(score),
+            win_probability(score) * 100.0
+        );
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fmt, fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::{
+        game_manager::{GameManager, GameOver},
+        game_record::GameRecord,
+    },
+    user_interface::view_model::winning_line,
+};
+
+/// The extension used for saved game files.
+pub const GAME_FILE_EXTENSION: &str = "c4";
+
+/// The extension used for game files in the compact binary format.
+pub const BINARY_GAME_FILE_EXTENSION: &str = "c4b";
+
+/// The extension used for game files saved as JSON.
+pub const JSON_GAME_FILE_EXTENSION: &str = "json";
+
+/// The directory finished games are saved to by default.
+pub const DEFAULT_ARCHIVE_DIRECTORY: &str = "archive";
+
+/// A game that has been saved to the archive.
+#[derive(Debug)]
+pub struct ArchivedGame {
+    pub path: PathBuf,
+    pub record: GameRecord,
+}
+
+impl ArchivedGame {
+    /// Returns when the game was saved, in seconds since the Unix epoch, from its file name.
+    ///
+    /// Games saved by hand rather than by the archive have no time.
+    pub fn saved_at(&self) -> Option<u64> {
+        let name = self.path.file_stem()?.to_str()?;
+        let mut parts = name.strip_prefix("game_")?.split('_');
+
+        parts.next()?.parse().ok()
+    }
+}
+
+/// A place a position was reached within the archive.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PositionMatch {
+    /// The index of the game in the archive.
+    pub game: usize,
+    /// How many moves into the game the position was reached.
+    pub ply: usize,
+}
+
+/// What happened when a folder of games was imported into the archive.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// The archive files the new games were saved to.
+    pub imported: Vec<PathBuf>,
+    /// How many games were already in the archive, or earlier in the folder.
+    pub duplicates: usize,
+    /// The files that couldn't be read as games, and why.
+    pub invalid: Vec<(PathBuf, String)>,
+    /// Whether the import was cancelled before every file was checked.
+    pub cancelled: bool,
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} games, skipped {} duplicates and {} invalid files",
+            self.imported.len(),
+            self.duplicates,
+            self.invalid.len()
+        )?;
+        if self.cancelled {
+            write!(f, " before being cancelled")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How the games that opened in a column turned out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpeningResults {
+    /// Every game that opened in the column, including unfinished ones.
+    pub games: usize,
+    pub red_wins: usize,
+    pub blue_wins: usize,
+    pub draws: usize,
+}
+
+impl OpeningResults {
+    /// Returns the share of the finished games Red won, or None if none were finished.
+    pub fn red_win_rate(&self) -> Option<f32> {
+        let finished = self.red_wins + self.blue_wins + self.draws;
+
+        if finished == 0 {
+            None
+        } else {
+            Some(self.red_wins as f32 / finished as f32)
+        }
+    }
+}
+
+/// Totals over every game in the archive.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ArchiveStatistics {
+    pub games: usize,
+    /// How many winning lines each space was part of, as array[row][col] with row 0 at the top.
+    pub winning_cells: [[usize; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+    /// How the games turned out for each first move, by column numbered from 0.
+    pub first_moves: [OpeningResults; BOARD_WIDTH as usize],
+    /// When the most recent game was saved, in seconds since the Unix epoch.
+    pub last_saved: Option<u64>,
+}
+
+/// A collection of saved games, indexed by the positions reached in them.
+#[derive(Debug, Default)]
+pub struct Archive {
+    games: Vec<ArchivedGame>,
+    /// Maps position keys to where in the archive they were reached.
+    index: HashMap<u64, Vec<PositionMatch>>,
+}
+
+impl Archive {
+    /// Loads and indexes every game file in the given directory.
+    ///
+    /// Files that can't be parsed are skipped, and a missing directory is treated as
+    /// an empty archive.
+    pub fn open(directory: &Path) -> io::Result<Archive> {
+        let mut archive = Archive::default();
+
+        for path in game_files(directory)? {
+            if let Ok(record) = read_game_file(&path) {
+                archive.add(path, record);
+            }
+        }
+
+        Ok(archive)
+    }
+
+    /// Adds a game to the archive's index, without saving it.
+    fn add(&mut self, path: PathBuf, record: GameRecord) {
+        let game = self.games.len();
+
+        for (ply, key) in record.position_keys().into_iter().enumerate() {
+            self.index
+                .entry(key)
+                .or_default()
+                .push(PositionMatch { game, ply });
+        }
+
+        self.games.push(ArchivedGame { path, record });
+    }
+
+    /// Returns all of the games in the archive.
+    pub fn games(&self) -> &[ArchivedGame] {
+        &self.games
+    }
+
+    /// Totals up which spaces win games and how each first move turns out, over every game.
+    pub fn statistics(&self) -> ArchiveStatistics {
+        let mut statistics = ArchiveStatistics::default();
+
+        for game in &self.games {
+            statistics.games += 1;
+            statistics.last_saved = statistics.last_saved.max(game.saved_at());
+
+            let Ok(manager) = GameManager::from_moves(&game.record.moves_string()) else {
+                continue;
+            };
+            let position = manager.get_position();
+
+            if let Some(line) = winning_line(&position) {
+                for (column, row) in line {
+                    statistics.winning_cells[BOARD_HEIGHT as usize - 1 - row][column] += 1;
+                }
+            }
+
+            if let Some(first_move) = game.record.moves.first() {
+                let results = &mut statistics.first_moves[first_move.column as usize];
+
+                results.games += 1;
+                match manager.is_game_over() {
+                    GameOver::OneWins => results.red_wins += 1,
+                    GameOver::TwoWins => results.blue_wins += 1,
+                    GameOver::Tie => results.draws += 1,
+                    GameOver::NoWin => (),
+                }
+            }
+        }
+
+        statistics
+    }
+
+    /// Returns every place in the archive that a position was reached.
+    ///
+    /// The key should be a canonical key, so that mirrored positions are found too.
+    pub fn find_position(&self, key: u64) -> &[PositionMatch] {
+        self.index
+            .get(&key)
+            .map(|matches| &matches[..])
+            .unwrap_or(&[])
+    }
+
+    /// Saves a game into the given archive directory, creating it if necessary.
+    ///
+    /// Returns the path of the new game file.
+    pub fn save(directory: &Path, record: &GameRecord) -> io::Result<PathBuf> {
+        fs::create_dir_all(directory)?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+
+        // Making sure we never overwrite a game saved in the same second
+        let mut count = 0;
+        let mut path;
+        loop {
+            path = directory.join(format!(
+                "game_{}_{}.{}",
+                timestamp, count, GAME_FILE_EXTENSION
+            ));
+            if !path.exists() {
+                break;
+            }
+            count += 1;
+        }
+
+        fs::write(&path, record.to_text())?;
+
+        Ok(path)
+    }
+
+    /// Imports every game file in the source folder into the given archive directory.
+    ///
+    /// Each file is read and checked before being saved, and games with the same content as
+    /// one already in the archive, or earlier in the folder, are skipped. progress is called
+    /// with how many files have been checked out of the total before each file, and the
+    /// import is cancelled if it returns false.
+    pub fn import_folder(
+        source: &Path,
+        directory: &Path,
+        mut progress: impl FnMut(usize, usize) -> bool,
+    ) -> io::Result<ImportSummary> {
+        let files = game_files(source)?;
+        let archive = Archive::open(directory)?;
+        let mut summary = ImportSummary::default();
+
+        let mut hashes: HashSet<u64> = archive
+            .games()
+            .iter()
+            .map(|game| content_hash(&game.record))
+            .collect();
+
+        for (checked, path) in files.iter().enumerate() {
+            if !progress(checked, files.len()) {
+                summary.cancelled = true;
+                return Ok(summary);
+            }
+
+            match read_game_file(path) {
+                Ok(record) => {
+                    if hashes.insert(content_hash(&record)) {
+                        summary.imported.push(Archive::save(directory, &record)?);
+                    } else {
+                        summary.duplicates += 1;
+                    }
+                }
+                Err(error) => summary.invalid.push((path.clone(), error)),
+            }
+        }
+        progress(files.len(), files.len());
+
+        Ok(summary)
+    }
+}
+
+/// Returns the paths of the game files in the directory, in either format, sorted by name.
+///
+/// A missing directory is treated as an empty one.
+fn game_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .map(|extension| {
+                    extension == GAME_FILE_EXTENSION
+                        || extension == BINARY_GAME_FILE_EXTENSION
+                        || extension == JSON_GAME_FILE_EXTENSION
+                })
+                .unwrap_or(false)
+        })
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Hashes everything saved about a game, so the same game is recognised whichever format
+/// it was saved in.
+fn content_hash(record: &GameRecord) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    record.to_text().hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Reads a game file, in the binary format or as JSON if it has their extension and in the
+/// text format otherwise.
+pub fn read_game_file(path: &Path) -> Result<GameRecord, String> {
+    if has_extension(path, BINARY_GAME_FILE_EXTENSION) {
+        let bytes = fs::read(path).map_err(|error| error.to_string())?;
+        GameRecord::from_bytes(&bytes)
+    } else {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        match has_extension(path, JSON_GAME_FILE_EXTENSION) {
+            true => GameRecord::from_json(&text),
+            false => GameRecord::parse(&text),
+        }
+    }
+}
+
+/// Writes a game file, in the binary format or as JSON if it has their extension and in
+/// the text format otherwise.
+pub fn write_game_file(path: &Path, record: &GameRecord) -> Result<(), String> {
+    let result = if has_extension(path, BINARY_GAME_FILE_EXTENSION) {
+        fs::write(path, record.to_bytes())
+    } else if has_extension(path, JSON_GAME_FILE_EXTENSION) {
+        fs::write(path, record.to_json())
+    } else {
+        fs::write(path, record.to_text())
+    };
+
+    result.map_err(|error| error.to_string())
+}
+
+/// Returns whether the path has the extension.
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .map(|path_extension| path_extension == extension)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use crate::{
+        archive::{write_game_file, Archive, OpeningResults, PositionMatch, GAME_FILE_EXTENSION},
+        game_engine::{game_manager::GameManager, game_record::GameRecord},
+    };
+
+    #[test]
+    fn finds_positions() {
+        let directory = std::env::temp_dir().join("rusty_connect_four_finds_positions");
+        let _ = fs::remove_dir_all(&directory);
+
+        Archive::save(&directory, &GameRecord::from_moves("4453").unwrap()).unwrap();
+        Archive::save(&directory, &GameRecord::from_moves("4435").unwrap()).unwrap();
+        Archive::save(&directory, &GameRecord::from_moves("1234").unwrap()).unwrap();
+        write_game_file(
+            &directory.join("binary.c4b"),
+            &GameRecord::from_moves("4453").unwrap(),
+        )
+        .unwrap();
+        fs::write(directory.join("broken.c4"), "not a game").unwrap();
+        fs::write(directory.join("broken.c4b"), "not a game").unwrap();
+        fs::write(directory.join("notes.txt"), "4453").unwrap();
+
+        let archive = Archive::open(&directory).unwrap();
+        assert_eq!(archive.games().len(), 4);
+
+        // Mirrored positions are considered the same
+        let key = GameManager::from_moves("445").unwrap().position_key();
+        let matches = archive.find_position(key);
+        assert_eq!(matches.len(), 3);
+        for position_match in matches {
+            assert_eq!(position_match.ply, 3);
+            assert_ne!(
+                archive.games()[position_match.game].record.moves[0].column,
+                0
+            );
+        }
+
+        // Every game starts from the empty board
+        let key = GameManager::new_game().position_key();
+        assert_eq!(archive.find_position(key).len(), 4);
+        assert!(archive
+            .find_position(key)
+            .contains(&PositionMatch { game: 0, ply: 0 }));
+
+        let key = GameManager::from_moves("7777").unwrap().position_key();
+        assert!(archive.find_position(key).is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn statistics() {
+        let directory = std::env::temp_dir().join("rusty_connect_four_statistics");
+        let _ = fs::remove_dir_all(&directory);
+
+        // Red wins vertically in the first column, then across the bottom row
+        Archive::save(&directory, &GameRecord::from_moves("1212121").unwrap()).unwrap();
+        Archive::save(&directory, &GameRecord::from_moves("1122334").unwrap()).unwrap();
+        // Blue wins vertically in the last column, and an unfinished game opening in the middle
+        Archive::save(&directory, &GameRecord::from_moves("17172737").unwrap()).unwrap();
+        Archive::save(&directory, &GameRecord::from_moves("44").unwrap()).unwrap();
+
+        let statistics = Archive::open(&directory).unwrap().statistics();
+        assert_eq!(statistics.games, 4);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let last_saved = statistics.last_saved.unwrap();
+        assert!(last_saved <= now && now - last_saved < 60);
+
+        // The bottom left space was part of both of Red's wins
+        assert_eq!(statistics.winning_cells[5][0], 2);
+        assert_eq!(statistics.winning_cells[2][0], 1);
+        assert_eq!(statistics.winning_cells[5][3], 1);
+        assert_eq!(statistics.winning_cells[2][6], 1);
+        assert_eq!(statistics.winning_cells[0][0], 0);
+        let total: usize = statistics.winning_cells.iter().flatten().sum();
+        assert_eq!(total, 12);
+
+        assert_eq!(
+            statistics.first_moves[0],
+            OpeningResults {
+                games: 3,
+                red_wins: 2,
+                blue_wins: 1,
+                draws: 0
+            }
+        );
+        assert_eq!(statistics.first_moves[0].red_win_rate(), Some(2.0 / 3.0));
+        assert_eq!(statistics.first_moves[3].games, 1);
+        assert_eq!(statistics.first_moves[3].red_win_rate(), None);
+        assert_eq!(statistics.first_moves[6].games, 0);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn imports_folders() {
+        let source = std::env::temp_dir().join("rusty_connect_four_import_source");
+        let directory = std::env::temp_dir().join("rusty_connect_four_import_archive");
+        let _ = fs::remove_dir_all(&source);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&source).unwrap();
+
+        Archive::save(&directory, &GameRecord::from_moves("4453").unwrap()).unwrap();
+
+        let already_archived = GameRecord::from_moves("4453").unwrap();
+        write_game_file(&source.join("a.c4b"), &already_archived).unwrap();
+        write_game_file(
+            &source.join("b.c4"),
+            &GameRecord::from_moves("1234").unwrap(),
+        )
+        .unwrap();
+        write_game_file(
+            &source.join("c.json"),
+            &GameRecord::from_moves("1234").unwrap(),
+        )
+        .unwrap();
+        write_game_file(&source.join("d.c4"), &GameRecord::from_moves("7").unwrap()).unwrap();
+        fs::write(source.join("e.c4"), "8\n").unwrap();
+        fs::write(source.join("notes.txt"), "4453").unwrap();
+
+        let mut progress = Vec::new();
+        let summary = Archive::import_folder(&source, &directory, |checked, total| {
+            progress.push((checked, total));
+            true
+        })
+        .unwrap();
+        assert_eq!(summary.imported.len(), 2);
+        assert_eq!(summary.duplicates, 2);
+        assert_eq!(summary.invalid.len(), 1);
+        assert!(summary.invalid[0].0.ends_with("e.c4"));
+        assert!(!summary.cancelled);
+        assert_eq!(
+            summary.to_string(),
+            "Imported 2 games, skipped 2 duplicates and 1 invalid files"
+        );
+        assert_eq!(progress.first(), Some(&(0, 5)));
+        assert_eq!(progress.last(), Some(&(5, 5)));
+        assert_eq!(Archive::open(&directory).unwrap().games().len(), 3);
+        assert!(summary
+            .imported
+            .iter()
+            .all(|path| path.extension().unwrap() == GAME_FILE_EXTENSION));
+
+        // Importing again finds nothing new, and cancelling stops straight away
+        let summary = Archive::import_folder(&source, &directory, |_, _| true).unwrap();
+        assert!(summary.imported.is_empty());
+        let summary = Archive::import_folder(&source, &directory, |_, _| false).unwrap();
+        assert!(summary.cancelled);
+        assert!(summary.invalid.is_empty());
+
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
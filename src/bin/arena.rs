@@ -0,0 +1,19 @@
+//! Plays engines against each other headlessly, to measure whether a change to the engine
+//! is an improvement, e.g.
+//!
+//! ```text
+//! cargo run --release --bin arena -- depth=6 depth=6,center=0 --games 40
+//! ```
+//!
+//! It's the same as running `rusty_connect_four arena`, without building the app.
+
+use rusty_connect_four::cli;
+
+fn main() {
+    let args: Vec<String> = ["arena".to_owned()]
+        .into_iter()
+        .chain(std::env::args().skip(1))
+        .collect();
+
+    std::process::exit(cli::run(&args));
+}
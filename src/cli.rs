@@ -0,0 +1,834 @@
+use std::{path::Path, time::Duration};
+
+use crate::{
+    archive::{read_game_file, write_game_file, Archive, DEFAULT_ARCHIVE_DIRECTORY},
+    export::write_review,
+    game_engine::{
+        column_label::ColumnLabel,
+        game_record::{format_score, GameRecord, BLUNDER_THRESHOLD},
+        monte_carlo::EngineAlgorithm,
+        opening_book::OpeningBook,
+        puzzle::{load_puzzle_database, save_puzzle_database, DEFAULT_PUZZLE_DATABASE},
+        puzzle_miner::{mine_self_play, MIN_MOVES_TO_WIN},
+        rating::{
+            estimate_rating, play_game, random_opening, score_for_player_one, EngineConfig,
+            EngineSetup, MatchResults, BASELINES,
+        },
+        record_annotator::RecordAnnotator,
+        win_probability::{fit_scale, self_play_samples, CALIBRATED_SCALE},
+    },
+    user_interface::engine_interface::{serve_engine_over_stdio, ENGINE_SUBCOMMAND},
+};
+
+/// How long the engine thinks about each position when no movetime is given.
+const DEFAULT_MOVETIME: Duration = Duration::from_millis(500);
+
+/// How many moves into the game an opening book is built for, if not given.
+const DEFAULT_BOOK_PLIES: usize = 4;
+
+/// How many games are played against each baseline when estimating a rating, if not given.
+const DEFAULT_RATING_GAMES: usize = 4;
+
+/// How many games are played between the engines in the arena, if not given.
+const DEFAULT_ARENA_GAMES: usize = 20;
+
+/// How many self-play games the win probabilities are calibrated from, if not given.
+const DEFAULT_CALIBRATION_GAMES: usize = 600;
+
+/// How many self-play games are mined for puzzles, if not given.
+const DEFAULT_MINING_GAMES: usize = 100;
+
+/// How far ahead the engines mined for puzzles look, if not given. Shallow engines make
+/// more mistakes, which is where puzzles come from.
+const DEFAULT_MINING_DEPTH: usize = 4;
+
+/// Describes how to use the command line interface.
+const USAGE: &str = "Usage:
+    rusty_connect_four                                  Launches the app
+    rusty_connect_four <challenge link>                 Launches the app with a challenge,
+                                                        e.g. connect4://challenge/c1.4453..h
+    rusty_connect_four --kiosk [<challenge link>]       Launches the app fullscreen and locked
+                                                        down for public machines
+    rusty_connect_four --board <serial port> [<challenge link>]
+                                                        Launches the app mirroring the game onto
+                                                        a physical LED board, e.g. /dev/ttyUSB0
+    rusty_connect_four annotate <files>... [--movetime <time>]
+                                                        Annotates saved games with engine evaluations
+    rusty_connect_four book <file> [--plies <count>] [--movetime <time>]
+                                                        Builds an opening book of the positions
+                                                        fewer than plies moves in, adding to the
+                                                        book already in the file if there is one
+    rusty_connect_four calibrate [--games <count>]      Fits the mapping from scores to win
+                                                        probabilities from self-play games
+    rusty_connect_four mine-puzzles [--games <count>] [--depth <moves>] [--min-moves <moves>]
+                                    [--output <file>]   Mines self-play games for puzzles with a
+                                                        single winning move, adding them to the
+                                                        puzzle database (puzzles.txt by default)
+    rusty_connect_four convert <input> <output>         Converts a game between the text (.c4)
+                                                        and binary (.c4b) formats
+    rusty_connect_four export <input> <output>          Exports a review of a game, with diagrams,
+                                                        to an HTML (.html) or Markdown (.md) file
+    rusty_connect_four transcript <game> [--movetime <time>] [--threshold <score>]
+                                                        Prints a transcript of a game file or
+                                                        move string, with each move's evaluation
+                                                        and the blunders marked
+    rusty_connect_four import <folder>                  Imports the games in a folder into the
+                                                        archive, skipping duplicates
+    rusty_connect_four engine                           Runs an engine speaking the engine
+                                                        protocol over stdin and stdout
+    rusty_connect_four estimate-elo [--movetime <time>] [--games <count>]
+                                                        Estimates the engine's rating against
+                                                        fixed depth engines
+    rusty_connect_four arena <engine> <engine> [--games <count>]
+                                                        Plays two engines against each other,
+                                                        alternating colors, and compares them.
+                                                        Engines are described by comma separated
+                                                        settings, e.g. depth=6,algorithm=hybrid:
+                                                          depth, nodes or movetime=<time>
+                                                          algorithm=alphabeta, mcts or hybrid
+                                                          closeness, center, good-parity,
+                                                          bad-parity or double-threat=<weight>
+    The arena can also be run as its own binary, arena <engine> <engine> [--games <count>]";
+
+/// Runs the command line interface with the given arguments, excluding the program name.
+///
+/// Returns the exit code the process should exit with.
+pub fn run(args: &[String]) -> i32 {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("annotate") => annotate(&args[1..]),
+        Some("book") => book(&args[1..]),
+        Some("calibrate") => calibrate(&args[1..]),
+        Some("convert") => convert(&args[1..]),
+        Some("mine-puzzles") => mine_puzzles(&args[1..]),
+        Some("export") => export(&args[1..]),
+        Some("transcript") => transcript(&args[1..]),
+        Some("import") => import(&args[1..]),
+        Some("estimate-elo") | Some("--estimate-elo") => estimate_elo(&args[1..]),
+        Some("arena") => arena(&args[1..]),
+        Some(ENGINE_SUBCOMMAND) => {
+            serve_engine_over_stdio();
+            0
+        }
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            0
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    }
+}
+
+/// Annotates each of the given game files in place, and prints a summary of the blunders
+/// found in them.
+fn annotate(args: &[String]) -> i32 {
+    let mut files = Vec::new();
+    let mut movetime = DEFAULT_MOVETIME;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--movetime" {
+            match args.next().map(|time| parse_duration(time)) {
+                Some(Some(time)) => movetime = time,
+                _ => {
+                    eprintln!("--movetime expects a time such as 500ms or 2s");
+                    return 2;
+                }
+            }
+        } else {
+            files.push(arg);
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!("{}", USAGE);
+        return 2;
+    }
+
+    let mut failures = 0;
+    let mut total_blunders = 0;
+
+    for file in files {
+        match annotate_file(file, movetime) {
+            Ok(record) => {
+                let blunders = record.blunders();
+                total_blunders += blunders.len();
+
+                println!(
+                    "{}: {} moves, {} blunders",
+                    file,
+                    record.moves.len(),
+                    blunders.len()
+                );
+                for index in blunders {
+                    let recorded = &record.moves[index];
+                    let annotation = recorded.annotation.unwrap();
+
+                    println!(
+                        "    move {} by {}: played {} ({}), best was {} ({})",
+                        index + 1,
+                        if (record.first_ply() + index) % 2 == 0 {
+                            "Red"
+                        } else {
+                            "Blue"
+                        },
+                        ColumnLabel::new(recorded.column),
+                        format_score(annotation.score),
+                        ColumnLabel::new(annotation.best_column),
+                        format_score(annotation.best_score)
+                    );
+                }
+            }
+            Err(error) => {
+                failures += 1;
+                eprintln!("{}: {}", file, error);
+            }
+        }
+    }
+
+    println!("{} blunders found in total", total_blunders);
+
+    if failures > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Annotates a single game file in place, returning the annotated record.
+fn annotate_file(file: &str, movetime: Duration) -> Result<GameRecord, String> {
+    let path = Path::new(file);
+
+    let mut record = read_game_file(path)?;
+    record.annotate(movetime)?;
+
+    write_game_file(path, &record)?;
+
+    Ok(record)
+}
+
+/// Plays the engine against itself and fits the logistic scale that turns its scores into
+/// win probabilities, printing it next to the scale currently in use.
+fn calibrate(args: &[String]) -> i32 {
+    let games = match args {
+        [] => DEFAULT_CALIBRATION_GAMES,
+        [flag, count] if flag == "--games" => match count.parse() {
+            Ok(count) if count > 0 => count,
+            _ => {
+                eprintln!("--games expects a number of games");
+                return 2;
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    let mut samples = Vec::new();
+    for _ in 0..games {
+        match self_play_samples() {
+            Ok(game_samples) => samples.extend(game_samples),
+            Err(error) => {
+                eprintln!("{}", error);
+                return 1;
+            }
+        }
+    }
+
+    match fit_scale(&samples) {
+        Some(scale) => {
+            println!(
+                "Fit a scale of {:.1} from {} moves in {} games (currently {:.1})",
+                scale,
+                samples.len(),
+                games,
+                CALIBRATED_SCALE
+            );
+            0
+        }
+        None => {
+            eprintln!("Every move was a forced win or loss, so there's nothing to fit");
+            1
+        }
+    }
+}
+
+/// Mines self-play games for puzzles, and adds the ones it hasn't seen before to the puzzle
+/// database.
+fn mine_puzzles(args: &[String]) -> i32 {
+    let mut games = DEFAULT_MINING_GAMES;
+    let mut depth = DEFAULT_MINING_DEPTH;
+    let mut min_moves_to_win = MIN_MOVES_TO_WIN;
+    let mut output = DEFAULT_PUZZLE_DATABASE.to_owned();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => match args.next().map(|count| count.parse()) {
+                Some(Ok(count)) if count > 0 => games = count,
+                _ => {
+                    eprintln!("--games expects a number of games");
+                    return 2;
+                }
+            },
+            "--depth" => match args.next().map(|count| count.parse()) {
+                Some(Ok(count)) if count > 0 => depth = count,
+                _ => {
+                    eprintln!("--depth expects a number of moves");
+                    return 2;
+                }
+            },
+            "--min-moves" => match args.next().map(|count| count.parse()) {
+                Some(Ok(count)) if count > 0 => min_moves_to_win = count,
+                _ => {
+                    eprintln!("--min-moves expects a number of moves");
+                    return 2;
+                }
+            },
+            "--output" => match args.next() {
+                Some(file) => output = file.clone(),
+                None => {
+                    eprintln!("--output expects a file");
+                    return 2;
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let path = Path::new(&output);
+    let mut database = match load_puzzle_database(path) {
+        Ok(database) => database,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 1;
+        }
+    };
+
+    let players = [EngineConfig::FixedDepth(depth); 2];
+    let mined = match mine_self_play(games, players, min_moves_to_win) {
+        Ok(mined) => mined,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 1;
+        }
+    };
+
+    let known = database.len();
+    for entry in mined {
+        if database.iter().all(|known| known.moves != entry.moves) {
+            database.push(entry);
+        }
+    }
+
+    match save_puzzle_database(path, &database) {
+        Ok(()) => {
+            println!(
+                "Found {} new puzzles in {} games, {} in {}",
+                database.len() - known,
+                games,
+                database.len(),
+                path.display()
+            );
+            0
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Builds an opening book by searching each position, and adds it to the book file.
+fn book(args: &[String]) -> i32 {
+    let Some((file, args)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let mut plies = DEFAULT_BOOK_PLIES;
+    let mut movetime = DEFAULT_MOVETIME;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plies" => match args.next().map(|count| count.parse()) {
+                Some(Ok(count)) if count > 0 => plies = count,
+                _ => {
+                    eprintln!("--plies expects a number of moves");
+                    return 2;
+                }
+            },
+            "--movetime" => match args.next().map(|time| parse_duration(time)) {
+                Some(Some(time)) => movetime = time,
+                _ => {
+                    eprintln!("--movetime expects a time such as 500ms or 2s");
+                    return 2;
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let path = Path::new(file);
+    let mut book = if path.exists() {
+        match OpeningBook::load(path) {
+            Ok(book) => book,
+            Err(error) => {
+                eprintln!("{}", error);
+                return 1;
+            }
+        }
+    } else {
+        OpeningBook::built_in()
+    };
+
+    let built = OpeningBook::build(plies, movetime, |searched, total| {
+        eprint!("\rSearching position {}/{}", searched + 1, total);
+    });
+    eprintln!();
+    book.merge(&built);
+
+    match book.save(path) {
+        Ok(()) => {
+            println!("Saved {} positions to {}", book.len(), path.display());
+            0
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Prints an annotated transcript of a game, given as a game file or a move string.
+fn transcript(args: &[String]) -> i32 {
+    let mut game = None;
+    let mut movetime = DEFAULT_MOVETIME;
+    let mut threshold = BLUNDER_THRESHOLD;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--movetime" {
+            match args.next().map(|time| parse_duration(time)) {
+                Some(Some(time)) => movetime = time,
+                _ => {
+                    eprintln!("--movetime expects a time such as 500ms or 2s");
+                    return 2;
+                }
+            }
+        } else if arg == "--threshold" {
+            match args.next().map(|score| score.parse()) {
+                Some(Ok(score)) => threshold = score,
+                _ => {
+                    eprintln!("--threshold expects a score such as 300");
+                    return 2;
+                }
+            }
+        } else if game.is_none() {
+            game = Some(arg);
+        } else {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    }
+
+    let Some(game) = game else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let annotator = RecordAnnotator::new(movetime).with_blunder_threshold(threshold);
+    let result = match Path::new(game).is_file() {
+        true => {
+            read_game_file(Path::new(game)).and_then(|record| annotator.annotate_record(&record))
+        }
+        false => annotator.annotate(game),
+    };
+
+    match result {
+        Ok(transcript) => {
+            print!("{}", transcript);
+            0
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Converts a game file to another format, chosen by the output file's extension.
+fn convert(args: &[String]) -> i32 {
+    let [input, output] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let result = read_game_file(Path::new(input))
+        .and_then(|record| write_game_file(Path::new(output), &record));
+
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Exports a review of a game file, in the format chosen by the output file's extension.
+fn export(args: &[String]) -> i32 {
+    let [input, output] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let result = read_game_file(Path::new(input))
+        .and_then(|record| write_review(Path::new(output), &record));
+
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Imports a folder of game files into the archive, and prints a summary of what was found.
+fn import(args: &[String]) -> i32 {
+    let [folder] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let result = Archive::import_folder(
+        Path::new(folder),
+        Path::new(DEFAULT_ARCHIVE_DIRECTORY),
+        |_, _| true,
+    );
+
+    match result {
+        Ok(summary) => {
+            for (path, error) in &summary.invalid {
+                eprintln!("{}: {}", path.display(), error);
+            }
+            println!("{}", summary);
+            0
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Plays an engine thinking for the given movetime against each of the rating baselines,
+/// and prints its estimated rating.
+fn estimate_elo(args: &[String]) -> i32 {
+    let mut movetime = DEFAULT_MOVETIME;
+    let mut games = DEFAULT_RATING_GAMES;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--movetime" => match args.next().map(|time| parse_duration(time)) {
+                Some(Some(time)) => movetime = time,
+                _ => {
+                    eprintln!("--movetime expects a time such as 500ms or 2s");
+                    return 2;
+                }
+            },
+            "--games" => match args.next().map(|count| count.parse()) {
+                Some(Ok(count)) if count > 0 => games = count,
+                _ => {
+                    eprintln!("--games expects a number of games");
+                    return 2;
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let engine = EngineConfig::MoveTime(movetime);
+    let mut results = Vec::new();
+
+    for (depth, baseline_rating) in BASELINES {
+        let baseline = EngineConfig::FixedDepth(depth);
+        let mut total_score = 0.0;
+
+        for game in 0..games {
+            // Alternating who goes first, since going first is an advantage
+            let engine_first = game % 2 == 0;
+            let players = if engine_first {
+                [engine, baseline]
+            } else {
+                [baseline, engine]
+            };
+
+            let result = match play_game(players, &random_opening()) {
+                Ok(result) => result,
+                Err(error) => {
+                    eprintln!("{}", error);
+                    return 1;
+                }
+            };
+
+            let score = if engine_first {
+                score_for_player_one(result)
+            } else {
+                1.0 - score_for_player_one(result)
+            };
+            total_score += score;
+            results.push((baseline_rating, score));
+        }
+
+        println!(
+            "Depth {} baseline ({}): scored {}/{}",
+            depth, baseline_rating, total_score, games
+        );
+    }
+
+    let estimate = estimate_rating(&results).expect("At least one game is always played");
+    println!(
+        "Estimated rating: {:.0} ± {:.0} (95% confidence)",
+        estimate.rating, estimate.margin
+    );
+
+    0
+}
+
+/// Plays two engines against each other, and prints how the first engine did with the
+/// margins of error of its results.
+///
+/// Each random opening is played twice, with the engines swapping colors, so that neither
+/// gets the better of the openings or of going first.
+fn arena(args: &[String]) -> i32 {
+    let mut engines = Vec::new();
+    let mut games = DEFAULT_ARENA_GAMES;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => match args.next().map(|count| count.parse()) {
+                Some(Ok(count)) if count > 0 => games = count,
+                _ => {
+                    eprintln!("--games expects a number of games");
+                    return 2;
+                }
+            },
+            spec if engines.len() < 2 => match parse_engine_setup(spec) {
+                Ok(setup) => engines.push((spec, setup)),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    return 2;
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+    let [(first_spec, first), (second_spec, second)] = engines[..] else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    println!("A: {}", first_spec);
+    println!("B: {}", second_spec);
+
+    let mut results = MatchResults::default();
+    let mut opening = String::new();
+    for game in 0..games {
+        let first_moves_first = game % 2 == 0;
+        let players = if first_moves_first {
+            opening = random_opening();
+            [first, second]
+        } else {
+            [second, first]
+        };
+
+        let result = match play_game(players, &opening) {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("{}", error);
+                return 1;
+            }
+        };
+
+        let score = if first_moves_first {
+            score_for_player_one(result)
+        } else {
+            1.0 - score_for_player_one(result)
+        };
+        results.record(score);
+
+        println!(
+            "Game {}/{} from {}: A {} as {}",
+            game + 1,
+            games,
+            opening,
+            match score {
+                score if score > 0.5 => "won",
+                score if score < 0.5 => "lost",
+                _ => "drew",
+            },
+            if first_moves_first { "Red" } else { "Blue" }
+        );
+    }
+
+    println!(
+        "A won {}, drew {} and lost {} of {} games",
+        results.wins,
+        results.draws,
+        results.losses,
+        results.games()
+    );
+    let (score, margin) = results.score().expect("At least one game is always played");
+    println!(
+        "A's score: {:.1}% ± {:.1}% (95% confidence)",
+        score * 100.0,
+        margin * 100.0
+    );
+    let difference = results
+        .rating_difference()
+        .expect("At least one game is always played");
+    println!(
+        "A's rating compared to B: {:+.0} ± {:.0} (95% confidence)",
+        difference.rating, difference.margin
+    );
+
+    0
+}
+
+/// Parses an engine for the arena from comma separated settings, such as
+/// "nodes=20000,algorithm=mcts" or "movetime=200ms,center=0". Settings that aren't given
+/// are left as the app's engine has them, thinking for DEFAULT_MOVETIME.
+fn parse_engine_setup(spec: &str) -> Result<EngineSetup, String> {
+    let mut setup = EngineSetup::from(EngineConfig::MoveTime(DEFAULT_MOVETIME));
+
+    for setting in spec.split(',') {
+        let (name, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("Expected a setting such as depth=6, found {}", setting))?;
+        let count = || {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or_else(|| format!("{} expects a positive number", name))
+        };
+        let weight = || {
+            value
+                .parse::<isize>()
+                .map_err(|_| format!("{} expects a whole number weight", name))
+        };
+
+        match name {
+            "depth" => setup.budget = EngineConfig::FixedDepth(count()?),
+            "nodes" => setup.budget = EngineConfig::Nodes(count()?),
+            "movetime" => {
+                setup.budget = EngineConfig::MoveTime(
+                    parse_duration(value).ok_or("movetime expects a time such as 500ms or 2s")?,
+                )
+            }
+            "algorithm" => {
+                setup.algorithm = match value {
+                    "alphabeta" => EngineAlgorithm::AlphaBeta,
+                    "mcts" => EngineAlgorithm::Mcts,
+                    "hybrid" => EngineAlgorithm::Hybrid,
+                    _ => return Err("algorithm expects alphabeta, mcts or hybrid".to_owned()),
+                }
+            }
+            "closeness" => setup.weights.closeness_to_win = weight()?,
+            "center" => setup.weights.center_piece = weight()?,
+            "good-parity" => setup.weights.good_parity_threat = weight()?,
+            "bad-parity" => setup.weights.bad_parity_threat = weight()?,
+            "double-threat" => setup.weights.double_threat = weight()?,
+            _ => return Err(format!("Unknown engine setting {}", name)),
+        }
+    }
+
+    if matches!(setup.budget, EngineConfig::FixedDepth(_)) && setup.algorithm.uses_rollouts() {
+        return Err("depth only applies to the alphabeta algorithm".to_owned());
+    }
+
+    Ok(setup)
+}
+
+/// Parses a duration such as "500ms", "2s" or "1.5s". A plain number is taken as milliseconds.
+fn parse_duration(text: &str) -> Option<Duration> {
+    if let Some(millis) = text.strip_suffix("ms") {
+        millis.parse().ok().map(Duration::from_millis)
+    } else if let Some(secs) = text.strip_suffix('s') {
+        secs.parse::<f64>()
+            .ok()
+            .filter(|secs| *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+    } else {
+        text.parse().ok().map(Duration::from_millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game_engine::{
+        monte_carlo::EngineAlgorithm,
+        rating::{EngineConfig, EngineSetup},
+    };
+
+    use super::{parse_duration, parse_engine_setup, DEFAULT_MOVETIME};
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2s"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("fast"), None);
+    }
+
+    #[test]
+    fn parses_engine_setups() {
+        let setup = parse_engine_setup("depth=6,center=0,double-threat=-5").unwrap();
+        assert_eq!(setup.budget, EngineConfig::FixedDepth(6));
+        assert_eq!(setup.algorithm, EngineAlgorithm::AlphaBeta);
+        assert_eq!(setup.weights.center_piece, 0);
+        assert_eq!(setup.weights.double_threat, -5);
+
+        let setup = parse_engine_setup("algorithm=mcts,nodes=20000").unwrap();
+        assert_eq!(setup.budget, EngineConfig::Nodes(20000));
+        assert_eq!(setup.algorithm, EngineAlgorithm::Mcts);
+
+        // Settings left out are the app engine's
+        assert_eq!(
+            parse_engine_setup("movetime=2s").unwrap(),
+            EngineSetup::from(EngineConfig::MoveTime(Duration::from_secs(2)))
+        );
+        assert_eq!(
+            parse_engine_setup("algorithm=hybrid").unwrap().budget,
+            EngineConfig::MoveTime(DEFAULT_MOVETIME)
+        );
+
+        assert!(parse_engine_setup("depth=0").is_err());
+        assert!(parse_engine_setup("depth").is_err());
+        assert!(parse_engine_setup("speed=fast").is_err());
+        assert!(parse_engine_setup("algorithm=minimax").is_err());
+        assert!(parse_engine_setup("depth=4,algorithm=mcts").is_err());
+    }
+}
@@ -6,3 +6,10 @@ pub const BOARD_HEIGHT: u8 = 6;
 
 /// The width of the board.
 pub const BOARD_WIDTH: u8 = 7;
+
+/// The widest a board can be configured to be.
+pub const MAX_BOARD_WIDTH: u8 = 10;
+
+/// The tallest a board can be configured to be. Each column's pieces are stored as the bits
+/// of a u8, so this can't go past 8.
+pub const MAX_BOARD_HEIGHT: u8 = 8;
@@ -0,0 +1,370 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::{
+        column_label::ColumnLabel,
+        game_manager::GameManager,
+        game_record::{format_score, GameRecord, RecordedMove},
+        power_ups::parse_moves,
+    },
+};
+
+/// The directory game reviews are exported to by default.
+pub const DEFAULT_EXPORT_DIRECTORY: &str = "exports";
+
+/// The size of each space in a board diagram, in pixels.
+const DIAGRAM_CELL_SIZE: usize = 40;
+/// The radius of each piece in a board diagram, in pixels.
+const DIAGRAM_PIECE_RADIUS: usize = 16;
+
+/// The file formats a game review can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Html,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// Picks the format from a file's extension, e.g. "review.html".
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        match path.extension()?.to_str()? {
+            "html" | "htm" => Some(ExportFormat::Html),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    /// Returns the extension files in the format are saved with.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Writes a standalone review of a game, with its tags, every move of the main line along
+/// with its evaluation, comment and variations, and board diagrams as inline SVG.
+///
+/// A diagram is drawn after each blunder or commented move, and of the final position.
+pub fn export_review(record: &GameRecord, format: ExportFormat) -> Result<String, String> {
+    let title = match (record.get_tag("Red"), record.get_tag("Blue")) {
+        (Some(red), Some(blue)) => format!("{} vs {}", red, blue),
+        _ => "Connect Four game".to_owned(),
+    };
+    let title = escape(&title);
+
+    let mut text = String::new();
+    match format {
+        ExportFormat::Html => {
+            text.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+            text.push_str(&format!("<title>{}</title>\n</head>\n<body>\n", title));
+            text.push_str(&format!("<h1>{}</h1>\n", title));
+
+            if !record.tags.is_empty() {
+                text.push_str("<table>\n");
+                for (name, value) in &record.tags {
+                    text.push_str(&format!(
+                        "<tr><th>{}</th><td>{}</td></tr>\n",
+                        escape(name),
+                        escape(value)
+                    ));
+                }
+                text.push_str("</table>\n");
+            }
+
+            text.push_str("<h2>Moves</h2>\n<ol>\n");
+        }
+        ExportFormat::Markdown => {
+            text.push_str(&format!("# {}\n\n", title));
+
+            if !record.tags.is_empty() {
+                text.push_str("| Tag | Value |\n| --- | --- |\n");
+                for (name, value) in &record.tags {
+                    text.push_str(&format!("| {} | {} |\n", escape(name), escape(value)));
+                }
+                text.push('\n');
+            }
+
+            text.push_str("## Moves\n\n");
+        }
+    }
+
+    let mut moves = String::new();
+    for (index, recorded) in record.moves.iter().enumerate() {
+        moves.push_str(&ColumnLabel::new(recorded.column).to_string());
+
+        let summary = move_summary(index, recorded);
+        let comment = recorded.comment.as_deref().map(escape);
+        let variations: Vec<String> = recorded
+            .variations
+            .iter()
+            .map(|variation| variation_text(variation))
+            .collect();
+        let is_blunder = recorded
+            .annotation
+            .is_some_and(|annotation| annotation.is_blunder());
+        let diagram = if is_blunder || comment.is_some() {
+            Some(board_svg(&moves)?)
+        } else {
+            None
+        };
+
+        match format {
+            ExportFormat::Html => {
+                text.push_str(&format!("<li>{}", summary));
+                if let Some(comment) = comment {
+                    text.push_str(&format!("<p>{}</p>", comment));
+                }
+                if !variations.is_empty() {
+                    text.push_str(&format!("<p>Instead: {}</p>", variations.join("; ")));
+                }
+                if let Some(diagram) = diagram {
+                    text.push_str(&format!("\n{}\n", diagram));
+                }
+                text.push_str("</li>\n");
+            }
+            ExportFormat::Markdown => {
+                text.push_str(&format!("{}. {}\n", index + 1, summary));
+                if let Some(comment) = comment {
+                    text.push_str(&format!("\n    {}\n", comment));
+                }
+                if !variations.is_empty() {
+                    text.push_str(&format!("\n    Instead: {}\n", variations.join("; ")));
+                }
+                if let Some(diagram) = diagram {
+                    text.push_str(&format!("\n    {}\n", diagram));
+                }
+                text.push('\n');
+            }
+        }
+    }
+
+    let final_position = board_svg(&moves)?;
+    match format {
+        ExportFormat::Html => {
+            text.push_str("</ol>\n<h2>Final position</h2>\n");
+            text.push_str(&final_position);
+            text.push_str("\n</body>\n</html>\n");
+        }
+        ExportFormat::Markdown => {
+            text.push_str("## Final position\n\n");
+            text.push_str(&final_position);
+            text.push('\n');
+        }
+    }
+
+    Ok(text)
+}
+
+/// Exports a review of the game to the path, in the format given by its extension.
+pub fn write_review(path: &Path, record: &GameRecord) -> Result<(), String> {
+    let format = ExportFormat::from_path(path)
+        .ok_or_else(|| "Reviews can only be exported to .html or .md files".to_owned())?;
+    let text = export_review(record, format)?;
+
+    fs::write(path, text).map_err(|error| error.to_string())
+}
+
+/// Exports a review of the game into the given directory, creating it if necessary.
+///
+/// Returns the path of the new file.
+pub fn save_review(
+    directory: &Path,
+    record: &GameRecord,
+    format: ExportFormat,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+
+    // Making sure we never overwrite a review exported in the same second
+    let mut count = 0;
+    let mut path;
+    loop {
+        path = directory.join(format!(
+            "review_{}_{}.{}",
+            timestamp,
+            count,
+            format.extension()
+        ));
+        if !path.exists() {
+            break;
+        }
+        count += 1;
+    }
+
+    write_review(&path, record)?;
+
+    Ok(path)
+}
+
+/// Draws the board after the moves, as columns numbered from 1, as an SVG image. The last
+/// move is outlined.
+pub fn board_svg(moves: &str) -> Result<String, String> {
+    let position = GameManager::from_moves(moves)?.get_position();
+    let last_column = parse_moves(moves)?
+        .last()
+        .map(|last| last.column() as usize);
+
+    let width = BOARD_WIDTH as usize * DIAGRAM_CELL_SIZE;
+    let height = BOARD_HEIGHT as usize * DIAGRAM_CELL_SIZE;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+         viewBox=\"0 0 {0} {1}\"><rect width=\"{0}\" height=\"{1}\" rx=\"8\" fill=\"gold\"/>",
+        width, height
+    );
+
+    for column in 0..BOARD_WIDTH as usize {
+        // Rows are counted from the top, so the first piece found is the column's last move
+        let top_row = (0..BOARD_HEIGHT as usize).find(|row| position[*row][column] != 0);
+
+        for (row, pieces) in position.iter().enumerate() {
+            let fill = match pieces[column] {
+                0 => "white",
+                1 => "red",
+                _ => "blue",
+            };
+            let outline = if last_column == Some(column) && top_row == Some(row) {
+                " stroke=\"black\" stroke-width=\"3\""
+            } else {
+                ""
+            };
+
+            svg.push_str(&format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"{}/>",
+                column * DIAGRAM_CELL_SIZE + DIAGRAM_CELL_SIZE / 2,
+                row * DIAGRAM_CELL_SIZE + DIAGRAM_CELL_SIZE / 2,
+                DIAGRAM_PIECE_RADIUS,
+                fill,
+                outline
+            ));
+        }
+    }
+    svg.push_str("</svg>");
+
+    Ok(svg)
+}
+
+/// Describes a move of the main line, e.g. "Red 4 (3, best was 5 at 7) Blunder".
+fn move_summary(index: usize, recorded: &RecordedMove) -> String {
+    let player = if index.is_multiple_of(2) {
+        "Red"
+    } else {
+        "Blue"
+    };
+    let mut summary = format!("{} {}", player, ColumnLabel::new(recorded.column));
+
+    if let Some(annotation) = recorded.annotation {
+        if annotation.best_column == recorded.column {
+            summary.push_str(&format!(" ({})", format_score(annotation.score)));
+        } else {
+            summary.push_str(&format!(
+                " ({}, best was {} at {})",
+                format_score(annotation.score),
+                ColumnLabel::new(annotation.best_column),
+                format_score(annotation.best_score)
+            ));
+        }
+
+        if annotation.is_blunder() {
+            summary.push_str(" Blunder");
+        }
+    }
+
+    summary
+}
+
+/// Writes a variation's moves, with the variations within it in brackets, e.g. "3 4 (1)".
+fn variation_text(line: &[RecordedMove]) -> String {
+    let mut parts = Vec::new();
+
+    for recorded in line {
+        parts.push(ColumnLabel::new(recorded.column).to_string());
+        for variation in &recorded.variations {
+            parts.push(format!("({})", variation_text(variation)));
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Escapes text so it's shown as is in HTML, which Markdown can also contain.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        export::{board_svg, export_review, ExportFormat},
+        game_engine::game_record::{GameRecord, MoveAnnotation, MovePath},
+    };
+
+    #[test]
+    fn formats_from_paths() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("review.html")),
+            Some(ExportFormat::Html)
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("review.md")),
+            Some(ExportFormat::Markdown)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("review.c4")), None);
+    }
+
+    #[test]
+    fn diagrams() {
+        let svg = board_svg("44").unwrap();
+        assert_eq!(svg.matches("<circle").count(), 42);
+        assert_eq!(svg.matches("fill=\"red\"").count(), 1);
+        assert_eq!(svg.matches("fill=\"blue\"").count(), 1);
+        // Only the last move is outlined
+        assert_eq!(svg.matches("stroke=").count(), 1);
+        assert!(svg.contains("cy=\"180\" r=\"16\" fill=\"blue\" stroke"));
+
+        assert!(board_svg("8").is_err());
+    }
+
+    #[test]
+    fn reviews() {
+        let mut record = GameRecord::from_moves("445").unwrap();
+        record.set_tag("Red", "Human");
+        record.set_tag("Blue", "<Computer>");
+        record.moves[1].comment = Some("Mirrors Red".to_owned());
+        record.moves[2].annotation = Some(MoveAnnotation {
+            score: -200,
+            best_column: 2,
+            best_score: 200,
+        });
+        record.add_move(Some(&MovePath::main_line(1)), 2).unwrap();
+
+        let html = export_review(&record, ExportFormat::Html).unwrap();
+        assert!(html.contains("<title>Human vs &lt;Computer&gt;</title>"));
+        assert!(html.contains("<li>Blue 4<p>Mirrors Red</p>"));
+        assert!(html.contains("Red 5 (-200, best was 3 at 200) Blunder"));
+        assert!(html.contains("<p>Instead: 3</p>"));
+        // After the comment, the blunder, and of the final position
+        assert_eq!(html.matches("<svg").count(), 3);
+
+        let markdown = export_review(&record, ExportFormat::Markdown).unwrap();
+        assert!(markdown.starts_with("# Human vs &lt;Computer&gt;\n"));
+        assert!(markdown.contains("| Red | Human |"));
+        assert!(markdown.contains("2. Blue 4\n\n    Mirrors Red\n"));
+        assert_eq!(markdown.matches("<svg").count(), 3);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    mem::size_of,
+    ops::{Index, IndexMut},
+};
+
+use crate::game_engine::{
+    board::Board,
+    board_state::BoardState,
+    heuristics::HeuristicWeights,
+    power_ups::PowerUps,
+    transposition::{IsFlipped, TranspositionTable},
+    variant_rules::VariantRules,
+};
+
+/// Identifies a BoardState stored in an Arena.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// Returns the slot of the arena the node is stored in.
+    fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Stores every BoardState of a decision tree in one Vec, linked to each other by NodeId
+/// rather than by reference counted pointers.
+///
+/// Transpositions are looked up through the arena's table, so a position reached by several
+/// orders of moves, or its mirror image, is only stored once. The states that can no longer
+/// be reached once the game moves on are freed by trimming the arena, and their slots reused
+/// for the next states generated.
+#[derive(Debug, Default)]
+pub struct Arena {
+    nodes: Vec<BoardState>,
+    /// The slots of trimmed states, waiting to be reused.
+    free: Vec<NodeId>,
+    /// Every stored state, by the hash of its board and context.
+    table: TranspositionTable<NodeId>,
+    /// The rules every stored state is played under.
+    rules: VariantRules,
+    /// How the heuristic scores new states.
+    weights: HeuristicWeights,
+    /// How many boards have been looked up in the table since the counts were last reset.
+    lookups: usize,
+    /// How many of the lookups found a stored state.
+    hits: usize,
+}
+
+impl Arena {
+    /// Creates an empty arena for states played under the given rules.
+    pub fn with_rules(rules: VariantRules) -> Arena {
+        Arena {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the rules every stored state is played under.
+    pub fn rules(&self) -> VariantRules {
+        self.rules
+    }
+
+    /// Sets how the heuristic scores states. Only states stored afterwards are scored with
+    /// the new weights.
+    pub fn set_heuristic_weights(&mut self, weights: HeuristicWeights) {
+        self.weights = weights;
+    }
+
+    /// Returns how the heuristic scores new states.
+    pub fn heuristic_weights(&self) -> &HeuristicWeights {
+        &self.weights
+    }
+
+    /// Using a board, gets a corresponding BoardState transposition, storing a new one if
+    /// the board hasn't been seen before with the same player to move and power-ups left.
+    /// power_ups is None if the game isn't played with them.
+    ///
+    /// The IsFlipped return value represents whether the returned transposition is
+    /// horizontally flipped.
+    pub fn get_board_state(
+        &mut self,
+        board: Board,
+        turn: bool,
+        power_ups: Option<PowerUps>,
+    ) -> (NodeId, IsFlipped) {
+        let context = power_ups.map(|power_ups| (turn, power_ups));
+
+        self.lookups += 1;
+        if let Some((&id, is_flipped)) = self.table.get_transposed(&board, context) {
+            self.hits += 1;
+            assert_eq!(
+                self[id].get_turn(),
+                turn,
+                "board: {:?} turn: {} doesn't match turn of {:?}",
+                board,
+                turn,
+                self[id]
+            );
+
+            return (id, is_flipped);
+        }
+
+        // The board we're evaluating is not in the table, so construct a new BoardState
+        let board_state = BoardState::new(board, turn, power_ups, self.rules, &self.weights);
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id.index()] = board_state;
+                id
+            }
+            None => {
+                let id = NodeId(
+                    u32::try_from(self.nodes.len())
+                        .expect("The decision tree shouldn't outgrow a u32 of states"),
+                );
+                self.nodes.push(board_state);
+                id
+            }
+        };
+        self.table
+            .insert(&self.nodes[id.index()].board, context, id);
+
+        (id, IsFlipped::Normal)
+    }
+
+    /// Returns how many boards have been looked up since the counts were last reset, and how
+    /// many of them were already stored.
+    pub fn lookup_counts(&self) -> (usize, usize) {
+        (self.lookups, self.hits)
+    }
+
+    /// Starts counting lookups over, e.g. after a move.
+    pub fn reset_lookup_counts(&mut self) {
+        self.lookups = 0;
+        self.hits = 0;
+    }
+
+    /// Returns how many BoardStates are stored.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Returns the ids of every stored BoardState, in no particular order.
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.table.iter().map(|(_, id)| *id)
+    }
+
+    /// Returns whether each slot holds a BoardState that can be reached from the root,
+    /// including the root itself.
+    fn reachable(&self, root: NodeId) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        reachable[root.index()] = true;
+
+        let mut to_visit = vec![root];
+        while let Some(id) = to_visit.pop() {
+            for child in self[id].children.iter() {
+                if !reachable[child.state.index()] {
+                    reachable[child.state.index()] = true;
+                    to_visit.push(child.state);
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Returns the ids of the given BoardState and every BoardState that can be reached
+    /// from it, in no particular order.
+    pub fn descendants(&self, root: NodeId) -> Vec<NodeId> {
+        let reachable = self.reachable(root);
+
+        self.ids().filter(|id| reachable[id.index()]).collect()
+    }
+
+    /// Frees every BoardState that can't be reached from the root, e.g. the positions a move
+    /// just ruled out, and forgets them as parents.
+    pub fn trim(&mut self, root: NodeId) {
+        let reachable = self.reachable(root);
+
+        let mut already_free = vec![false; self.nodes.len()];
+        for id in self.free.iter() {
+            already_free[id.index()] = true;
+        }
+
+        self.table.retain(|id| reachable[id.index()]);
+        for (index, board_state) in self.nodes.iter_mut().enumerate() {
+            if reachable[index] {
+                board_state.retain_parents(|parent| reachable[parent.index()]);
+            } else if !already_free[index] {
+                *board_state = BoardState::default();
+                self.free.push(NodeId(index as u32));
+            }
+        }
+    }
+
+    /// Returns how many bytes the arena has allocated for its states and table, whether or
+    /// not they're in use. The table's share is only estimated, see
+    /// TranspositionTable::estimated_heap_size.
+    pub fn heap_size(&self) -> usize {
+        self.nodes.capacity() * size_of::<BoardState>()
+            + self.free.capacity() * size_of::<NodeId>()
+            + self.table.estimated_heap_size()
+            + self
+                .nodes
+                .iter()
+                .map(|board_state| board_state.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl Index<NodeId> for Arena {
+    type Output = BoardState;
+
+    fn index(&self, id: NodeId) -> &BoardState {
+        &self.nodes[id.index()]
+    }
+}
+
+impl IndexMut<NodeId> for Arena {
+    fn index_mut(&mut self, id: NodeId) -> &mut BoardState {
+        &mut self.nodes[id.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        arena::Arena,
+        board::Board,
+        board_state::BoardState,
+        power_ups::{Move, PowerUps},
+        transposition::IsFlipped,
+    };
+
+    #[test]
+    fn transposes() {
+        let board = Board::from_arrays([
+            [0, 0, 0, 0, 0, 0, 2],
+            [0, 0, 0, 0, 0, 0, 2],
+            [0, 0, 0, 1, 0, 0, 1],
+            [0, 2, 0, 1, 0, 2, 1],
+            [0, 1, 2, 1, 0, 1, 2],
+            [0, 1, 2, 1, 2, 1, 2],
+        ]);
+
+        let flipped_board = Board::from_arrays([
+            [2, 0, 0, 0, 0, 0, 0],
+            [2, 0, 0, 0, 0, 0, 0],
+            [1, 0, 0, 1, 0, 0, 0],
+            [1, 2, 0, 1, 0, 2, 0],
+            [2, 1, 0, 1, 2, 1, 0],
+            [2, 1, 2, 1, 2, 1, 0],
+        ]);
+
+        let mut arena = Arena::default();
+
+        let (state, state_is_flipped) = arena.get_board_state(board.clone(), false, None);
+        let (flipped, flipped_is_flipped) = arena.get_board_state(flipped_board, false, None);
+
+        assert_eq!(state, flipped);
+        assert_eq!(state_is_flipped, IsFlipped::Normal);
+        assert_eq!(flipped_is_flipped, IsFlipped::Flipped);
+
+        let (clone, clone_is_flipped) = arena.get_board_state(board, false, None);
+        assert_eq!(state, clone);
+        assert_eq!(clone_is_flipped, IsFlipped::Normal);
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn trim() {
+        let mut arena = Arena::default();
+        let (root, _) = arena.get_board_state(Board::default(), false, None);
+        BoardState::generate_children(&mut arena, root);
+        BoardState::backpropagate(&mut arena, root);
+
+        let children: Vec<_> = arena[root].children.iter().map(|c| c.state).collect();
+        for &child in children.iter() {
+            BoardState::generate_children(&mut arena, child);
+            BoardState::backpropagate(&mut arena, child);
+        }
+        // The root, its four distinct children, and the 25 distinct positions after them
+        assert_eq!(arena.len(), 1 + 4 + 25);
+
+        // Moving into the middle column rules out everything else
+        let (middle, _) = arena[root].narrow_possibilities(Move::Drop(3));
+        assert_eq!(arena.descendants(middle).len(), 1 + 4);
+        assert_eq!(arena.descendants(root).len(), arena.len());
+        arena.trim(middle);
+        assert_eq!(arena.len(), 1 + 4);
+        assert_eq!(arena.ids().count(), 5);
+        assert_eq!(arena[middle].board.get_height(3), 1);
+
+        // The trimmed slots are reused before the arena grows, here for a position that
+        // isn't symmetrical, so all seven of its children are distinct
+        let slots = arena.nodes.len();
+        let child = arena[middle].children[1].state;
+        BoardState::generate_children(&mut arena, child);
+        BoardState::backpropagate(&mut arena, child);
+        assert_eq!(arena.len(), 1 + 4 + 7);
+        assert_eq!(arena.nodes.len(), slots);
+    }
+
+    #[test]
+    fn power_up_contexts() {
+        // The same board is another position with power-ups left, or with the other player
+        // to move, which clearing a column can lead to
+        let board = Board::from_moves("44").unwrap();
+        let mut arena = Arena::default();
+        let (without, _) = arena.get_board_state(board.clone(), false, None);
+        let (with, _) = arena.get_board_state(board.clone(), false, Some(PowerUps::default()));
+        let (other_turn, _) = arena.get_board_state(board, true, Some(PowerUps::default()));
+
+        assert_ne!(without, with);
+        assert_ne!(with, other_turn);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena[with].get_power_ups(), Some(PowerUps::default()));
+        assert!(arena[other_turn].get_turn());
+    }
+}
@@ -1,4 +1,14 @@
-use crate::consts::{BOARD_HEIGHT, BOARD_WIDTH};
+use std::cmp::min;
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH, MAX_BOARD_WIDTH},
+    game_engine::{
+        board_config::BoardConfig,
+        column_label::{split_moves, ColumnLabel},
+        heuristics::{closeness_change, score_by_closeness_to_win},
+        win_check::{is_game_over, winning_columns, winning_line, GameOver},
+    },
+};
 
 /// An error state when accessing a nonexistant piece.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -8,14 +18,98 @@ pub struct OutOfBounds;
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct FullColumn;
 
+/// An error state when clearing a column that has no pieces in it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EmptyColumn;
+
+/// An error state when a move string doesn't describe a legal game.
+///
+/// Each variant holds the index of the offending move within the string.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InvalidMoves {
+    /// The move isn't a column number between 1 and the board's width.
+    NotAColumn(usize),
+    /// The column was already full when the move was made.
+    FullColumn(usize),
+    /// The move was made after the game had already ended.
+    GameAlreadyOver(usize),
+}
+
+/// Packs a board's columns into a single number, used to create board keys.
+///
+/// Each column takes up just enough bits for its height, then board_height bits for its
+/// pieces. Boards with too many columns to fit in 64 bits are folded in half, so their keys
+/// could collide.
+fn encode_key<'a>(
+    board_height: u8,
+    heights: impl Iterator<Item = &'a u8>,
+    bitmaps: impl Iterator<Item = &'a u8>,
+) -> u64 {
+    let height_bits = u8::BITS - board_height.leading_zeros();
+
+    let key = heights.zip(bitmaps).fold(0u128, |key, (height, bitmap)| {
+        (key << (height_bits + board_height as u32))
+            | ((*height as u128) << board_height)
+            | *bitmap as u128
+    });
+
+    key as u64 ^ (key >> 64) as u64
+}
+
 /// A connect four board.
-#[derive(Clone, Default, Debug, PartialEq, Eq)]
+///
+/// Boards are the standard size unless they're created with with_config. Only the first
+/// config.width columns of the arrays are used.
+#[derive(Clone, Default, Debug)]
 pub struct Board {
-    column_heights: [u8; BOARD_WIDTH as usize],
-    column_bitmaps: [u8; BOARD_WIDTH as usize],
+    config: BoardConfig,
+    column_heights: [u8; MAX_BOARD_WIDTH as usize],
+    column_bitmaps: [u8; MAX_BOARD_WIDTH as usize],
+    /// The closeness to win heuristic's score for the board, updated as pieces are dropped
+    /// so that scoring a board doesn't need to rescan it.
+    closeness_to_win: isize,
 }
 
+// Boards are equal when they hold the same position, whatever their cached scores
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+            && self.column_heights == other.column_heights
+            && self.column_bitmaps == other.column_bitmaps
+    }
+}
+
+impl Eq for Board {}
+
 impl Board {
+    /// Creates an empty board with the given dimensions.
+    pub fn with_config(config: BoardConfig) -> Board {
+        Board {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the board's dimensions, and how many in a row it takes to win on it.
+    pub fn config(&self) -> BoardConfig {
+        self.config
+    }
+
+    /// Returns how many columns the board has.
+    pub fn width(&self) -> u8 {
+        self.config.width
+    }
+
+    /// Returns how many rows the board has.
+    pub fn height(&self) -> u8 {
+        self.config.height
+    }
+
+    /// Returns how many pieces in a row it takes to win on the board.
+    pub fn number_to_win(&self) -> u8 {
+        self.config.number_to_win
+    }
+
     /// Gets a boolean representation of a piece given a column and row.
     ///
     /// Fails if the row requested is out of bounds.
@@ -32,21 +126,91 @@ impl Board {
     /// Fails if the column is already full.
     pub fn drop_piece(&mut self, col: u8, color: bool) -> Result<(), FullColumn> {
         let col_height = self.get_height(col);
-        if col_height < BOARD_HEIGHT {
+        if col_height < self.height() {
+            // Only the lines through the new piece can change
+            let change = closeness_change(self, col, col_height, color);
+
             self.column_bitmaps[col as usize] += (color as u8) << col_height;
             self.set_height(col, col_height + 1);
 
+            self.closeness_to_win = match change {
+                Some(change) => self.closeness_to_win + change,
+                None => score_by_closeness_to_win(self),
+            };
+
             Ok(())
         } else {
             Err(FullColumn)
         }
     }
 
+    /// Removes every piece from the given column, as if they fell out of the bottom.
+    ///
+    /// Pieces only ever rest on the piece below them, so the neighboring columns are left as
+    /// they are. Fails if the column is already empty.
+    pub fn clear_column(&mut self, col: u8) -> Result<(), EmptyColumn> {
+        if self.get_height(col) == 0 {
+            return Err(EmptyColumn);
+        }
+
+        self.column_bitmaps[col as usize] = 0;
+        self.set_height(col, 0);
+        self.closeness_to_win = score_by_closeness_to_win(self);
+
+        Ok(())
+    }
+
+    /// Returns the score the closeness to win heuristic gives the board, positive when true
+    /// is closer to a connect four.
+    pub fn closeness_to_win(&self) -> isize {
+        self.closeness_to_win
+    }
+
     /// Returns the height of the pieces in the given column.
     pub fn get_height(&self, col: u8) -> u8 {
         self.column_heights[col as usize]
     }
 
+    /// Returns the given color's pieces in the column as bits, the lowest bit being the
+    /// bottom row. Spaces above the column's pieces are never set.
+    pub fn get_color_bitmap(&self, col: u8, color: bool) -> u8 {
+        let filled = ((1u16 << self.get_height(col)) - 1) as u8;
+        let bitmap = self.column_bitmaps[col as usize];
+
+        if color {
+            bitmap & filled
+        } else {
+            !bitmap & filled
+        }
+    }
+
+    /// Returns every column the given color can drop a piece in, along with the board after
+    /// the drop, from left to right.
+    ///
+    /// Nothing can be dropped once the game is over, i.e. the other color has won or the
+    /// board is full.
+    pub fn legal_drops(&self, color: bool) -> impl Iterator<Item = (u8, Board)> + '_ {
+        let game_over = is_game_over(self, color) != GameOver::NoWin;
+
+        (0..self.width())
+            .filter(move |_| !game_over)
+            .filter_map(move |col| {
+                let mut board = self.clone();
+                board.drop_piece(col, color).ok().map(|_| (col, board))
+            })
+    }
+
+    /// Returns the columns where the given color could immediately win by dropping a piece.
+    pub fn winning_moves(&self, color: bool) -> Vec<u8> {
+        winning_columns(self, color)
+    }
+
+    /// Returns the spaces of a connect four the given color has made, as (column, row) with
+    /// rows counting up from the bottom, or None if they haven't made one.
+    pub fn winning_line(&self, color: bool) -> Option<Vec<(u8, u8)>> {
+        winning_line(self, color)
+    }
+
     /// Sets the height of the given column.
     fn set_height(&mut self, col: u8, height: u8) {
         self.column_heights[col as usize] = height;
@@ -54,7 +218,7 @@ impl Board {
 
     /// Returns the height of the highest column.
     pub fn get_max_height(&self) -> u8 {
-        (0..BOARD_WIDTH)
+        (0..self.width())
             .map(|col| self.get_height(col))
             .max()
             .unwrap()
@@ -62,8 +226,8 @@ impl Board {
 
     /// Returns if the board is full.
     pub fn is_full(&self) -> bool {
-        for col in 0..BOARD_WIDTH {
-            if self.get_height(col) != BOARD_HEIGHT {
+        for col in 0..self.width() {
+            if self.get_height(col) != self.height() {
                 return false;
             }
         }
@@ -72,29 +236,54 @@ impl Board {
 
     /// Gets an iterator over the board's contents. Used for hashing the board.
     pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
-        self.column_heights
-            .iter()
-            .chain(self.column_bitmaps.iter())
-            .map(|i| *i)
+        self.heights().iter().chain(self.bitmaps().iter()).copied()
     }
 
     /// Gets an iterator over the board's content reversed symetrically. Used for hashing the board.
     pub fn flipped_iter(&self) -> impl Iterator<Item = u8> + '_ {
-        self.column_heights
+        self.heights()
             .iter()
             .rev()
-            .chain(self.column_bitmaps.iter().rev())
-            .map(|i| *i)
+            .chain(self.bitmaps().iter().rev())
+            .copied()
+    }
+
+    /// Returns the heights of the columns the board uses.
+    fn heights(&self) -> &[u8] {
+        &self.column_heights[..self.width() as usize]
+    }
+
+    /// Returns the bitmaps of the columns the board uses.
+    fn bitmaps(&self) -> &[u8] {
+        &self.column_bitmaps[..self.width() as usize]
+    }
+
+    /// Returns a number uniquely identifying the position on this board.
+    ///
+    /// Unlike the hashes used by the transposition table, the key is stable between runs of the
+    /// program, so it can be saved. On the standard board each column takes up 3 bits for its
+    /// height and BOARD_HEIGHT bits for its pieces. Keys are only unique among boards of the
+    /// same size, and the largest boards' keys could collide.
+    pub fn key(&self) -> u64 {
+        encode_key(self.height(), self.heights().iter(), self.bitmaps().iter())
+    }
+
+    /// Returns a key that is the same for this board and its horizontally flipped version.
+    pub fn canonical_key(&self) -> u64 {
+        let flipped_key = encode_key(
+            self.height(),
+            self.heights().iter().rev(),
+            self.bitmaps().iter().rev(),
+        );
+
+        min(self.key(), flipped_key)
     }
 
     /// Flips this Board horizontally.
     pub fn flip(&mut self) {
-        for (i, val) in self.column_heights.into_iter().rev().enumerate() {
-            self.column_heights[i] = val;
-        }
-        for (i, val) in self.column_bitmaps.into_iter().rev().enumerate() {
-            self.column_bitmaps[i] = val;
-        }
+        let width = self.width() as usize;
+        self.column_heights[..width].reverse();
+        self.column_bitmaps[..width].reverse();
     }
 
     /// Used to initialize a board based on a 2d array.
@@ -118,29 +307,77 @@ impl Board {
         board
     }
 
+    /// Used to initialize a board by playing out a string of moves, e.g. "4445".
+    ///
+    /// Columns are numbered from 1, and player one is assumed to make the first move.
+    /// Whitespace is ignored, see split_moves for how the moves are split up. Fails if any
+    /// move is illegal.
+    pub fn from_moves(moves: &str) -> Result<Board, InvalidMoves> {
+        let mut board = Board::default();
+
+        for (index, written) in split_moves(moves).iter().enumerate() {
+            let turn = index % 2 == 1;
+
+            if is_game_over(&board, turn) != GameOver::NoWin {
+                return Err(InvalidMoves::GameAlreadyOver(index));
+            }
+
+            let col = match ColumnLabel::parse(written) {
+                Some(label) if label.index() < board.width() => label.index(),
+                _ => return Err(InvalidMoves::NotAColumn(index)),
+            };
+
+            if board.drop_piece(col, turn).is_err() {
+                return Err(InvalidMoves::FullColumn(index));
+            }
+        }
+
+        Ok(board)
+    }
+
     /// Used to get the current state of the board as a 2d array.
+    ///
+    /// Panics if the board isn't the standard size, see to_rows for other boards.
     pub fn to_arrays(&self) -> [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize] {
+        assert!(
+            self.config.is_standard(),
+            "Tried to get a {} board as arrays",
+            self.config
+        );
         let mut position = [[0; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
 
-        for row in 0..BOARD_HEIGHT {
-            for col in 0..BOARD_WIDTH {
-                position[(BOARD_HEIGHT - 1 - row) as usize][col as usize] =
-                    match self.get_piece(col, row) {
-                        Ok(piece) => piece as u8 + 1,
-                        Err(_) => 0,
-                    };
-            }
+        for (row, pieces) in self.to_rows().into_iter().enumerate() {
+            position[row].copy_from_slice(&pieces);
         }
 
         position
     }
+
+    /// Used to get the current state of a board of any size as rows[row][col], with the top
+    /// row first.
+    pub fn to_rows(&self) -> Vec<Vec<u8>> {
+        (0..self.height())
+            .rev()
+            .map(|row| {
+                (0..self.width())
+                    .map(|col| match self.get_piece(col, row) {
+                        Ok(piece) => piece as u8 + 1,
+                        Err(_) => 0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         consts::{BOARD_HEIGHT, BOARD_WIDTH},
-        game_engine::board::{Board, FullColumn, OutOfBounds},
+        game_engine::{
+            board::{Board, EmptyColumn, FullColumn, InvalidMoves, OutOfBounds},
+            board_config::BoardConfig,
+        },
     };
 
     #[test]
@@ -186,6 +423,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn from_moves() {
+        let board = Board::from_moves("4445 2").unwrap();
+
+        assert_eq!(
+            board,
+            Board::from_arrays([
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 1, 0, 0, 0],
+                [0, 0, 0, 2, 0, 0, 0],
+                [0, 1, 0, 1, 2, 0, 0],
+            ])
+        );
+
+        assert_eq!(Board::from_moves(""), Ok(Board::default()));
+        assert_eq!(Board::from_moves("448"), Err(InvalidMoves::NotAColumn(2)));
+        assert_eq!(Board::from_moves("40"), Err(InvalidMoves::NotAColumn(1)));
+        assert_eq!(Board::from_moves("4a"), Err(InvalidMoves::NotAColumn(1)));
+        assert_eq!(
+            Board::from_moves("4,4,10"),
+            Err(InvalidMoves::NotAColumn(2))
+        );
+        assert_eq!(Board::from_moves("4,4"), Board::from_moves("44"));
+        assert_eq!(
+            Board::from_moves("1111111"),
+            Err(InvalidMoves::FullColumn(6))
+        );
+        assert_eq!(
+            Board::from_moves("12121213"),
+            Err(InvalidMoves::GameAlreadyOver(7))
+        );
+    }
+
     #[test]
     fn get_piece() {
         let board = Board::from_arrays([
@@ -244,6 +516,25 @@ mod tests {
         assert_eq!(board.get_piece(3, BOARD_HEIGHT), Err(OutOfBounds));
     }
 
+    #[test]
+    fn clear_column() {
+        let mut board = Board::from_moves("4455667").unwrap();
+
+        assert_eq!(board.clear_column(4), Ok(()));
+        assert_eq!(board.get_height(4), 0);
+        assert_eq!(board.get_piece(4, 0), Err(OutOfBounds));
+        assert_eq!(board.get_color_bitmap(4, false), 0);
+        // The neighboring columns keep their pieces
+        assert_eq!(board.get_height(3), 2);
+        assert_eq!(board.get_height(5), 2);
+
+        // A cleared column fills up from the bottom again
+        assert_eq!(board.drop_piece(4, true), Ok(()));
+        assert_eq!(board.get_piece(4, 0), Ok(true));
+
+        assert_eq!(board.clear_column(0), Err(EmptyColumn));
+    }
+
     #[test]
     fn get_max_height() {
         let board = Board::from_arrays([
@@ -314,4 +605,97 @@ mod tests {
 
         assert_eq!(board, flipped_board);
     }
+
+    #[test]
+    fn board_equality() {
+        let board = Board::from_moves("4453").unwrap();
+        assert_eq!(board, Board::from_moves("4354").unwrap());
+        assert_ne!(board, Board::from_moves("4435").unwrap());
+
+        // Only the position counts, not the cached score
+        let mut stale = board.clone();
+        stale.closeness_to_win += 1;
+        assert_eq!(board, stale);
+    }
+
+    #[test]
+    fn board_keys() {
+        let board = Board::from_moves("4453").unwrap();
+        let mirrored_board = Board::from_moves("4435").unwrap();
+        let other_board = Board::from_moves("4455").unwrap();
+
+        assert_eq!(board.key(), Board::from_moves("4354").unwrap().key());
+        assert_ne!(board.key(), mirrored_board.key());
+        assert_eq!(board.canonical_key(), mirrored_board.canonical_key());
+        assert_ne!(board.canonical_key(), other_board.canonical_key());
+        assert_ne!(
+            Board::default().key(),
+            Board::from_moves("1").unwrap().key()
+        );
+
+        // Saved keys have to stay the same, the first column's height is the highest bits
+        assert_eq!(Board::from_moves("1").unwrap().key(), 1 << 60);
+    }
+
+    #[test]
+    fn configured_boards() {
+        let config = BoardConfig::new(10, 8, 4).unwrap();
+        let mut board = Board::with_config(config);
+        assert_eq!(board.config(), config);
+
+        for row in 0..8 {
+            board.drop_piece(9, row % 2 == 1).unwrap();
+        }
+        assert_eq!(board.drop_piece(9, false), Err(FullColumn));
+        assert_eq!(board.get_piece(9, 7), Ok(true));
+        assert_eq!(board.get_color_bitmap(9, true), 0b10101010);
+        assert_eq!(board.legal_drops(false).count(), 9);
+
+        let rows = board.to_rows();
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[0], [0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(rows[7], [0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let mut flipped = board.clone();
+        flipped.flip();
+        assert_eq!(flipped.get_height(0), 8);
+        assert_eq!(flipped.get_height(9), 0);
+        assert_ne!(board.key(), flipped.key());
+        assert_eq!(board.canonical_key(), flipped.canonical_key());
+
+        // Boards of different sizes are never the same board
+        assert_ne!(Board::with_config(config), Board::default());
+    }
+
+    #[test]
+    fn color_bitmaps() {
+        let board = Board::from_moves("4454").unwrap();
+
+        assert_eq!(board.get_color_bitmap(3, false), 0b001);
+        assert_eq!(board.get_color_bitmap(3, true), 0b110);
+        assert_eq!(board.get_color_bitmap(4, false), 0b1);
+        assert_eq!(board.get_color_bitmap(0, false), 0);
+        assert_eq!(board.get_color_bitmap(0, true), 0);
+
+        let full = Board::from_moves("111111").unwrap();
+        assert_eq!(full.get_color_bitmap(0, false), 0b010101);
+        assert_eq!(full.get_color_bitmap(0, true), 0b101010);
+    }
+
+    #[test]
+    fn legal_drops() {
+        let board = Board::from_moves("111111").unwrap();
+        let drops: Vec<(u8, Board)> = board.legal_drops(false).collect();
+        assert_eq!(drops.len(), BOARD_WIDTH as usize - 1);
+        assert_eq!(drops[0], (1, Board::from_moves("1111112").unwrap()));
+        assert!(drops.iter().all(|(col, _)| *col != 0));
+
+        // Red has already won, so Blue can't move
+        let won = Board::from_moves("1212121").unwrap();
+        assert_eq!(won.legal_drops(true).count(), 0);
+
+        let board = Board::from_moves("445566").unwrap();
+        assert_eq!(board.winning_moves(false), vec![2, 6]);
+        assert_eq!(board.winning_moves(true), Vec::<u8>::new());
+    }
 }
@@ -0,0 +1,156 @@
+use std::{cmp::Reverse, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::consts::{BOARD_HEIGHT, BOARD_WIDTH, MAX_BOARD_HEIGHT, MAX_BOARD_WIDTH, NUMBER_TO_WIN};
+
+/// The dimensions of a board, and how many pieces in a row it takes to win on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BoardConfig {
+    pub width: u8,
+    pub height: u8,
+    pub number_to_win: u8,
+}
+
+impl BoardConfig {
+    /// The standard seven column, six row board, played to four in a row.
+    pub const STANDARD: BoardConfig = BoardConfig {
+        width: BOARD_WIDTH,
+        height: BOARD_HEIGHT,
+        number_to_win: NUMBER_TO_WIN,
+    };
+
+    /// Popular larger boards, all played to four in a row, along with the standard board.
+    pub const VARIANTS: [BoardConfig; 4] = [
+        BoardConfig::STANDARD,
+        BoardConfig::four_in_a_row(8, 7),
+        BoardConfig::four_in_a_row(9, 7),
+        BoardConfig::four_in_a_row(10, 8),
+    ];
+
+    /// A board of the given size played to four in a row.
+    const fn four_in_a_row(width: u8, height: u8) -> BoardConfig {
+        BoardConfig {
+            width,
+            height,
+            number_to_win: NUMBER_TO_WIN,
+        }
+    }
+
+    /// Creates a config, checking that the engine can play on a board of that size.
+    ///
+    /// Boards can be from 4x4 up to MAX_BOARD_WIDTH x MAX_BOARD_HEIGHT, and it has to be
+    /// possible to fit number_to_win pieces in a row on them.
+    pub fn new(width: u8, height: u8, number_to_win: u8) -> Result<BoardConfig, String> {
+        if !(4..=MAX_BOARD_WIDTH).contains(&width) {
+            return Err(format!(
+                "Boards must be between 4 and {} columns wide",
+                MAX_BOARD_WIDTH
+            ));
+        }
+        if !(4..=MAX_BOARD_HEIGHT).contains(&height) {
+            return Err(format!(
+                "Boards must be between 4 and {} rows tall",
+                MAX_BOARD_HEIGHT
+            ));
+        }
+        if !(3..=width.max(height)).contains(&number_to_win) {
+            return Err(format!(
+                "A {}x{} board needs between 3 and {} in a row to win",
+                width,
+                height,
+                width.max(height)
+            ));
+        }
+
+        Ok(BoardConfig {
+            width,
+            height,
+            number_to_win,
+        })
+    }
+
+    /// Returns a board of the same size won by getting the given number of pieces in a row
+    /// instead, e.g. connect three on the standard board.
+    ///
+    /// Fails if that many in a row can't fit on the board, see new.
+    pub fn with_number_to_win(&self, number_to_win: u8) -> Result<BoardConfig, String> {
+        BoardConfig::new(self.width, self.height, number_to_win)
+    }
+
+    /// Returns whether this is the standard board.
+    pub fn is_standard(&self) -> bool {
+        *self == BoardConfig::STANDARD
+    }
+
+    /// Returns the board's columns from the middle outwards, which is the order the best
+    /// moves usually come in. Of two columns equally far from the middle, the one on the
+    /// right comes first.
+    pub fn columns_from_middle(&self) -> Vec<u8> {
+        let mut columns: Vec<u8> = (0..self.width).collect();
+        columns.sort_by_key(|column| {
+            (
+                (2 * *column as i8 - (self.width as i8 - 1)).abs(),
+                Reverse(*column),
+            )
+        });
+
+        columns
+    }
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        BoardConfig::STANDARD
+    }
+}
+
+impl fmt::Display for BoardConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}, {} in a row",
+            self.width, self.height, self.number_to_win
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::board_config::BoardConfig;
+
+    #[test]
+    fn configs() {
+        assert_eq!(BoardConfig::new(7, 6, 4), Ok(BoardConfig::STANDARD));
+        assert!(BoardConfig::default().is_standard());
+        assert!(BoardConfig::VARIANTS.iter().all(|config| BoardConfig::new(
+            config.width,
+            config.height,
+            4
+        ) == Ok(*config)));
+        assert_eq!(BoardConfig::VARIANTS[3].to_string(), "10x8, 4 in a row");
+
+        assert!(BoardConfig::new(11, 6, 4).is_err());
+        assert!(BoardConfig::new(7, 9, 4).is_err());
+        assert!(BoardConfig::new(3, 6, 3).is_err());
+        assert!(BoardConfig::new(7, 6, 8).is_err());
+        assert!(BoardConfig::new(7, 6, 2).is_err());
+
+        let connect_five = BoardConfig::STANDARD.with_number_to_win(5).unwrap();
+        assert_eq!(connect_five.to_string(), "7x6, 5 in a row");
+        assert!(!connect_five.is_standard());
+        assert!(BoardConfig::STANDARD.with_number_to_win(8).is_err());
+    }
+
+    #[test]
+    fn columns_from_middle() {
+        assert_eq!(
+            BoardConfig::STANDARD.columns_from_middle(),
+            [3, 4, 2, 5, 1, 6, 0]
+        );
+        assert_eq!(
+            BoardConfig::new(8, 7, 4).unwrap().columns_from_middle(),
+            [4, 3, 5, 2, 6, 1, 7, 0]
+        );
+    }
+}
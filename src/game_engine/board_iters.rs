@@ -1,9 +1,6 @@
 use std::cmp::{max, min};
 
-use crate::{
-    consts::{BOARD_HEIGHT, BOARD_WIDTH, NUMBER_TO_WIN},
-    game_engine::board::{Board, OutOfBounds},
-};
+use crate::game_engine::board::{Board, OutOfBounds};
 
 /// Iterates through a single horizontal strip of a board.
 ///
@@ -18,7 +15,7 @@ impl Iterator for HorizontalIter<'_> {
     type Item = Result<bool, OutOfBounds>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.col < BOARD_WIDTH {
+        if self.col < self.board.width() {
             let result = Some(self.board.get_piece(self.col, self.row));
             self.col += 1;
 
@@ -31,7 +28,7 @@ impl Iterator for HorizontalIter<'_> {
 
 impl ExactSizeIterator for HorizontalIter<'_> {
     fn len(&self) -> usize {
-        (BOARD_WIDTH - self.col) as usize
+        (self.board.width() - self.col) as usize
     }
 }
 
@@ -101,19 +98,21 @@ pub struct VerticalStripIter<'a> {
     board: &'a Board,
     col: u8,
     full: bool,
+    /// The length of the lines the strips are searched for.
+    window: u8,
 }
 
 impl<'a> Iterator for VerticalStripIter<'a> {
     type Item = VerticalIter<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.col < BOARD_WIDTH {
+        if self.col < self.board.width() {
             // Calculating the max_height that the new iterator should have
             let mut col_height = self.board.get_height(self.col);
 
             // We can also use this value to determine if we should skip a column
             // This changes based on if we're creating full iterators or not
-            if col_height == 0 || (!self.full && col_height < NUMBER_TO_WIN) {
+            if col_height == 0 || (!self.full && col_height < self.window) {
                 self.col += 1;
                 return self.next();
             }
@@ -122,7 +121,7 @@ impl<'a> Iterator for VerticalStripIter<'a> {
             //  empty pieces and then stop
             // If we don't want full iterators then we want to stop at the first empty piece
             if self.full {
-                col_height = min(col_height + NUMBER_TO_WIN - 1, BOARD_HEIGHT);
+                col_height = min(col_height + self.window - 1, self.board.height());
             }
 
             let result = Some(VerticalIter {
@@ -154,7 +153,7 @@ impl Iterator for UpwardDiagonalIter<'_> {
     type Item = Result<bool, OutOfBounds>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.col < BOARD_WIDTH && self.row < self.max_height {
+        if self.col < self.board.width() && self.row < self.max_height {
             let result = Some(self.board.get_piece(self.col, self.row));
             self.col += 1;
             self.row += 1;
@@ -168,7 +167,7 @@ impl Iterator for UpwardDiagonalIter<'_> {
 
 /// Iterates through the different upward diagonal strips of a board.
 ///
-/// Yields a UpwardDiagonalIter to each strip of size >= NUMBER_TO_WIN until the max_height
+/// Yields a UpwardDiagonalIter to each strip of size >= window until the max_height
 ///  of the board is reached.
 pub struct UpwardDiagonalStripIter<'a> {
     board: &'a Board,
@@ -176,6 +175,8 @@ pub struct UpwardDiagonalStripIter<'a> {
     col: u8,
     row: u8,
     full: bool,
+    /// The length of the lines the strips are searched for.
+    window: u8,
 }
 
 impl<'a> Iterator for UpwardDiagonalStripIter<'a> {
@@ -183,8 +184,9 @@ impl<'a> Iterator for UpwardDiagonalStripIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         // Stop iteration if either we go out of bounds,
-        //  or if the iterator isn't full and there aren't enough pieces for a connect four
-        if self.col + NUMBER_TO_WIN > BOARD_WIDTH || (!self.full && self.max_height < NUMBER_TO_WIN)
+        //  or if the iterator isn't full and there aren't enough pieces to fill a window
+        if self.col + self.window > self.board.width()
+            || (!self.full && self.max_height < self.window)
         {
             return None;
         }
@@ -208,7 +210,7 @@ impl<'a> Iterator for UpwardDiagonalStripIter<'a> {
 
 impl ExactSizeIterator for UpwardDiagonalIter<'_> {
     fn len(&self) -> usize {
-        min(self.max_height - self.row, BOARD_WIDTH - self.col) as usize
+        min(self.max_height - self.row, self.board.width() - self.col) as usize
     }
 }
 
@@ -248,7 +250,7 @@ impl ExactSizeIterator for DownwardDiagonalIter<'_> {
 
 /// Iterates through the different downward diagonal strips of a board.
 ///
-/// Yields a DownwardDiagonalIter to each strip of size >= NUMBER_TO_WIN until the max_height
+/// Yields a DownwardDiagonalIter to each strip of size >= window until the max_height
 ///  of the board is reached.
 pub struct DownwardDiagonalStripIter<'a> {
     board: &'a Board,
@@ -256,6 +258,8 @@ pub struct DownwardDiagonalStripIter<'a> {
     col: u8,
     row: u8,
     full: bool,
+    /// The length of the lines the strips are searched for.
+    window: u8,
 }
 
 impl<'a> Iterator for DownwardDiagonalStripIter<'a> {
@@ -263,8 +267,8 @@ impl<'a> Iterator for DownwardDiagonalStripIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         // Stop iteration if either we go out of bounds,
-        //  or if the iterator isn't full and there aren't enough pieces for a connect four
-        if self.col < NUMBER_TO_WIN || (!self.full && self.max_height < NUMBER_TO_WIN) {
+        //  or if the iterator isn't full and there aren't enough pieces to fill a window
+        if self.col < self.window || (!self.full && self.max_height < self.window) {
             return None;
         }
 
@@ -301,36 +305,57 @@ impl Board {
     ///
     /// Each VerticalIter exits early at the max_height of the board.
     ///
-    /// `full` determines if iterators are created for strips of size < NUMBER_TO_WIN.
+    /// `full` determines if iterators are created for strips of size < the board's number_to_win.
     /// `full` also determines how early the iterator will halt.
     pub fn vertical_strip_iter(&self, full: bool) -> VerticalStripIter {
+        self.vertical_strip_iter_with_window(full, self.number_to_win())
+    }
+
+    /// Returns an iterator that yields an iterator to each vertical strip of a board, for
+    /// finding lines of `window` pieces rather than the board's number_to_win.
+    ///
+    /// See vertical_strip_iter.
+    pub fn vertical_strip_iter_with_window(&self, full: bool, window: u8) -> VerticalStripIter<'_> {
         VerticalStripIter {
             board: self,
             col: 0,
             full,
+            window,
         }
     }
 
     /// Returns an iterator that yields an iterator to each upward diagonal strip of a board.
     ///
     /// Each UpwardDiagonalIter exits early at the max_height of the board and doesn't include
-    ///  strips less than size NUMBER_TO_WIN.
+    ///  strips shorter than the board's number_to_win.
     ///
-    /// `full` determines if iterators are created for strips of size < NUMBER_TO_WIN.
+    /// `full` determines if iterators are created for strips of size < the board's number_to_win.
     /// `full` also determines how early the iterator will halt.
     pub fn upward_diagonal_strip_iter(&self, full: bool) -> UpwardDiagonalStripIter {
+        self.upward_diagonal_strip_iter_with_window(full, self.number_to_win())
+    }
+
+    /// Returns an iterator that yields an iterator to each upward diagonal strip of a board,
+    /// for finding lines of `window` pieces rather than the board's number_to_win.
+    ///
+    /// See upward_diagonal_strip_iter.
+    pub fn upward_diagonal_strip_iter_with_window(
+        &self,
+        full: bool,
+        window: u8,
+    ) -> UpwardDiagonalStripIter<'_> {
         // Our max_height changes based on if we want full iterators that iterate
         //  until only empty rows are being reached, or if we want non full iterators
         //  that iterate until any empty rows are reached
         // We don't need to care about the case with an empty board
         let max_height = if full {
-            min(self.get_max_height() + NUMBER_TO_WIN - 1, BOARD_HEIGHT)
+            min(self.get_max_height() + window - 1, self.height())
         } else {
             self.get_max_height()
         };
 
         // The row that we start iterating through strips at
-        let starting_row = max((max_height as i8) - (NUMBER_TO_WIN as i8), 0i8) as u8;
+        let starting_row = max((max_height as i8) - (window as i8), 0i8) as u8;
 
         UpwardDiagonalStripIter {
             board: self,
@@ -338,36 +363,50 @@ impl Board {
             col: 0,
             row: starting_row,
             full,
+            window,
         }
     }
 
     /// Returns an iterator that yields an iterator to each downward diagonal strip of a board.
     ///
     /// Each DownwardDiagonalIter exits early at the max_height of the board and doesn't include
-    ///  strips less than size NUMBER_TO_WIN.
+    ///  strips shorter than the board's number_to_win.
     ///
-    /// `full` determines if iterators are created for strips of size < NUMBER_TO_WIN.
+    /// `full` determines if iterators are created for strips of size < the board's number_to_win.
     /// `full` also determines how early the iterator will halt.
     pub fn downward_diagonal_strip_iter(&self, full: bool) -> DownwardDiagonalStripIter {
+        self.downward_diagonal_strip_iter_with_window(full, self.number_to_win())
+    }
+
+    /// Returns an iterator that yields an iterator to each downward diagonal strip of a
+    /// board, for finding lines of `window` pieces rather than the board's number_to_win.
+    ///
+    /// See downward_diagonal_strip_iter.
+    pub fn downward_diagonal_strip_iter_with_window(
+        &self,
+        full: bool,
+        window: u8,
+    ) -> DownwardDiagonalStripIter<'_> {
         // Our max_height changes based on if we want full iterators that iterate
         //  until only empty rows are being reached, or if we want non full iterators
         //  that iterate until any empty rows are reached
         // We don't need to care about the case with an empty board
         let max_height = if full {
-            min(self.get_max_height() + NUMBER_TO_WIN - 1, BOARD_HEIGHT)
+            min(self.get_max_height() + window - 1, self.height())
         } else {
             self.get_max_height()
         };
 
         // The row that we start iterating through strips at
-        let starting_row = max((max_height as i8) - (NUMBER_TO_WIN as i8), 0i8) as u8;
+        let starting_row = max((max_height as i8) - (window as i8), 0i8) as u8;
 
         DownwardDiagonalStripIter {
             board: self,
             max_height,
-            col: BOARD_WIDTH,
+            col: self.width(),
             row: starting_row,
             full,
+            window,
         }
     }
 }
@@ -609,6 +648,62 @@ mod tests {
         assert_eq!(combined_strips, Vec::<Vec::<u8>>::new());
     }
 
+    #[test]
+    fn strip_iters_with_windows() {
+        let board = Board::from_arrays([
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [1, 2, 2, 0, 0, 0, 2],
+            [1, 2, 2, 0, 0, 1, 1],
+            [1, 1, 2, 0, 1, 2, 2],
+        ]);
+
+        // Nothing is four high, but there are lines of three to look for
+        assert_eq!(
+            super_collect(board.vertical_strip_iter_with_window(false, 3)),
+            vec![vec![1, 1, 1], vec![1, 2, 2], vec![2, 2, 2], vec![2, 1, 2]]
+        );
+        assert_eq!(
+            super_collect(board.upward_diagonal_strip_iter_with_window(false, 3)),
+            vec![
+                vec![1, 2, 2],
+                vec![1, 2, 0],
+                vec![2, 0, 0],
+                vec![0, 0, 0],
+                vec![1, 1, 2],
+            ]
+        );
+        assert_eq!(
+            super_collect(board.downward_diagonal_strip_iter_with_window(false, 3)),
+            vec![
+                vec![2, 1, 0],
+                vec![2, 0, 0],
+                vec![1, 0, 2],
+                vec![0, 2, 2],
+                vec![2, 2, 1],
+            ]
+        );
+
+        // Longer windows need more room
+        assert!(super_collect(board.upward_diagonal_strip_iter_with_window(false, 5)).is_empty());
+        let lengths: Vec<usize> = board
+            .vertical_strip_iter_with_window(true, 5)
+            .map(|strip| strip.len())
+            .collect();
+        assert_eq!(lengths, vec![6, 6, 6, 5, 6, 6]);
+        assert_eq!(
+            board
+                .upward_diagonal_strip_iter_with_window(true, 5)
+                .count(),
+            4
+        );
+        assert_eq!(
+            super_collect(board.vertical_strip_iter_with_window(false, 4)),
+            super_collect(board.vertical_strip_iter(false))
+        );
+    }
+
     #[test]
     fn vertical_strip_iter_full() {
         let board = Board::from_arrays([
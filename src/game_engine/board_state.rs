@@ -1,41 +1,41 @@
-use std::{
-    cell::RefCell,
-    rc::{Rc, Weak},
+use std::mem::size_of;
+
+use crate::game_engine::{
+    arena::{Arena, NodeId},
+    board::{Board, FullColumn},
+    heuristics::{score_power_ups, weighted_score, HeuristicWeights},
+    mate_distance::{loss_in, one_ply_back, win_in},
+    power_ups::{Move, PowerUpKind, PowerUps},
+    transposition::IsFlipped,
+    variant_rules::VariantRules,
+    win_check::GameOver,
 };
 
-use crate::{
-    consts::BOARD_WIDTH,
-    game_engine::{
-        board::{Board, FullColumn},
-        transposition::{IsFlipped, TranspositionTable},
-        win_check::{is_game_over, GameOver},
-    },
-};
-
-/// Used to optimize alpha-beta pruning by generating moves that are most likely to be good first
-const IDEAL_COLUMNS_FIRST: [u8; 7] = [3, 4, 2, 5, 1, 6, 0];
-
-#[derive(Default, Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ChildState {
-    pub state: Rc<RefCell<BoardState>>,
-    last_move: u8,
+    pub state: NodeId,
+    last_move: Move,
     is_flipped: IsFlipped,
 }
 
 impl ChildState {
     /// Gets the move that was played to reach this child.
-    pub fn get_last_move(&self) -> u8 {
+    pub fn get_last_move(&self) -> Move {
         self.last_move
     }
 
-    /// Corrects this child's last move and flipped state based on the fact that its parent has
-    /// flipped its orientation.
-    ///
-    /// Should only be used when the parent of this ChildState is the root of the decision tree and
-    /// has just flipped its orientation.
-    pub fn parent_flipped(&mut self) {
-        self.last_move = 6 - self.last_move;
-        self.is_flipped = self.is_flipped.flip();
+    /// Gets the column a piece was dropped down to reach this child, or None if a power-up
+    /// was spent instead.
+    pub fn get_drop(&self) -> Option<u8> {
+        match self.last_move {
+            Move::Drop(col) => Some(col),
+            Move::Clear(_) | Move::Anvil(_) => None,
+        }
+    }
+
+    /// Gets whether this child is stored as the mirror image of the board the move reached.
+    pub fn get_is_flipped(&self) -> IsFlipped {
+        self.is_flipped
     }
 }
 
@@ -45,121 +45,283 @@ impl ChildState {
 /// It has a number of other states:
 ///  is the game over, who has won, whose turn is it, etc.
 /// It also has a number of possible BoardStates which could result from
-///  this one, its children.
-#[derive(Default, Debug, PartialEq, Eq)]
+///  this one, its children, which are stored alongside it in an Arena.
+#[derive(Default, Debug)]
 pub struct BoardState {
     pub board: Board,
     pub children: Vec<ChildState>,
     turn: bool,
+    /// Which power-ups are left, or None if the game isn't played with them.
+    power_ups: Option<PowerUps>,
+    /// Who a finished game goes to, which the game over state and scores follow.
+    rules: VariantRules,
     game_over: GameOver,
+    /// The mini-max score of the decision tree below this state, kept up to date as the tree
+    ///  is generated. Positive values are favorable to true, negative to false.
+    score: isize,
+    /// The BoardStates this one has been generated as a child of.
+    parents: Vec<NodeId>,
 }
 
-impl BoardState {
-    /// Constructs a new BoardState.
-    pub fn new(board: Board, turn: bool) -> BoardState {
-        let game_over = is_game_over(&board, turn);
+impl PartialEq for BoardState {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.children == other.children
+            && self.turn == other.turn
+            && self.power_ups == other.power_ups
+            && self.game_over == other.game_over
+    }
+}
+
+impl Eq for BoardState {}
 
-        BoardState {
+impl BoardState {
+    /// Constructs a new BoardState, with the given power-ups left, played under the given
+    ///  rules and scored by the heuristic with the given weights.
+    pub fn new(
+        board: Board,
+        turn: bool,
+        power_ups: Option<PowerUps>,
+        rules: VariantRules,
+        weights: &HeuristicWeights,
+    ) -> BoardState {
+        let game_over = rules.is_game_over(&board, turn);
+        let mut board_state = BoardState {
             board,
             children: Vec::new(),
             turn,
+            power_ups,
+            rules,
             game_over,
+            score: 0,
+            parents: Vec::new(),
+        };
+        board_state.score = match game_over {
+            GameOver::NoWin => board_state.heuristic_score(weights),
+            GameOver::Tie => 0,
+            GameOver::OneWins => loss_in(0),
+            GameOver::TwoWins => win_in(0),
+        };
+
+        board_state
+    }
+
+    /// Records a newly expanded BoardState as the parent of its children, then updates the
+    ///  scores of it and every ancestor whose score depends on it.
+    ///
+    /// Only ancestors whose score actually changes have their own parents updated, so most
+    ///  expansions only touch a few states.
+    pub fn backpropagate(arena: &mut Arena, id: NodeId) {
+        for index in 0..arena[id].children.len() {
+            let child = arena[id].children[index].state;
+            arena[child].parents.push(id);
+        }
+
+        let mut to_update = vec![id];
+        while let Some(id) = to_update.pop() {
+            if BoardState::update_score(arena, id) {
+                to_update.extend(arena[id].parents.iter().copied());
+            }
         }
     }
 
-    /// Populates the children vector with new BoardStates.
-    pub fn generate_children(
-        &mut self,
-        table: &mut TranspositionTable<Weak<RefCell<BoardState>>>,
-    ) -> Vec<Rc<RefCell<BoardState>>> {
+    /// Recalculates a BoardState's score from its children's scores.
+    ///
+    /// Returns whether the score changed.
+    fn update_score(arena: &mut Arena, id: NodeId) -> bool {
+        let board_state = &arena[id];
+        let child_scores = board_state
+            .children
+            .iter()
+            .map(|child| one_ply_back(arena[child.state].score));
+        let score = if board_state.get_turn() {
+            child_scores.max()
+        } else {
+            child_scores.min()
+        };
+
+        match score {
+            Some(score) if score != board_state.score => {
+                arena[id].score = score;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the heuristic's guess at the score of the board, under the state's rules,
+    ///  with the given weights.
+    pub fn heuristic_score(&self, weights: &HeuristicWeights) -> isize {
+        self.rules
+            .heuristic(weighted_score(&self.board, self.turn, weights))
+            + score_power_ups(self.power_ups, weights)
+    }
+
+    /// Forgets the parents that fail the check, e.g. because they've been trimmed from the
+    /// arena.
+    pub fn retain_parents(&mut self, keep: impl FnMut(&NodeId) -> bool) {
+        self.parents.retain(keep);
+    }
+
+    /// Returns how many bytes this BoardState has allocated for its children and parents,
+    /// on top of its own size.
+    pub fn heap_size(&self) -> usize {
+        self.children.capacity() * size_of::<ChildState>()
+            + self.parents.capacity() * size_of::<NodeId>()
+    }
+
+    /// Populates a BoardState's children vector with new BoardStates, stored in the arena.
+    ///
+    /// Returns the children that need their own children generated.
+    pub fn generate_children(arena: &mut Arena, id: NodeId) -> Vec<NodeId> {
+        let board_state = &arena[id];
+
         // If this BoardState has an already won game, no children are generated
-        match self.is_game_over() {
+        match board_state.is_game_over() {
             GameOver::NoWin => (),
-            _ => return self.children.iter().map(|c| c.state.clone()).collect(),
+            _ => return board_state.children.iter().map(|c| c.state).collect(),
         }
 
         // Children can be already generated if a different transposition calulated them
-        if self.children.len() > 0 {
+        if !board_state.children.is_empty() {
             return Vec::new();
         }
 
-        let turn = self.get_turn();
-        let mut new_board = self.board.clone();
+        let turn = board_state.get_turn();
+        let power_ups = board_state.power_ups;
+        let board = board_state.board.clone();
+        let mut new_board = board.clone();
+        let mut children = Vec::new();
 
         // We attempt to generate a new BoardState for each column a piece
         //  can successfully be dropped down
-        for col in IDEAL_COLUMNS_FIRST.iter() {
-            if Err(FullColumn) == new_board.drop_piece(*col, turn) {
+        // Columns in the middle are tried first, since they're most likely to be good, which
+        //  helps alpha-beta pruning
+        for col in board.config().columns_from_middle() {
+            if Err(FullColumn) == new_board.drop_piece(col, turn) {
                 // If the column is full, we proceed to the next
                 continue;
             } else {
                 // We then add a new BoardState corresponding to the move just played
-                let (child_state, is_flipped) = table.get_board_state(new_board, !turn);
-                self.children.push(ChildState {
+                let (child_state, is_flipped) = arena.get_board_state(new_board, !turn, power_ups);
+                children.push(ChildState {
                     state: child_state,
-                    last_move: *col,
+                    last_move: Move::Drop(col),
                     is_flipped,
                 });
 
                 // We now refresh the board we're using
-                new_board = self.board.clone();
+                new_board = board.clone();
+            }
+        }
+
+        // Spending a power-up is tried after every drop, since it can only be done once
+        if let Some(mut new_power_ups) = power_ups.filter(|power_ups| power_ups.is_available(turn))
+        {
+            let power_up = match new_power_ups.kind() {
+                PowerUpKind::Clear => Move::Clear,
+                PowerUpKind::Anvil => Move::Anvil,
+            };
+            for col in board.config().columns_from_middle() {
+                if power_up(col)
+                    .play(&mut new_board, turn, &mut new_power_ups)
+                    .is_err()
+                {
+                    // The column is empty, so there's nothing to clear
+                    continue;
+                }
+
+                let (child_state, is_flipped) =
+                    arena.get_board_state(new_board, !turn, Some(new_power_ups));
+                children.push(ChildState {
+                    state: child_state,
+                    last_move: power_up(col),
+                    is_flipped,
+                });
+
+                new_board = board.clone();
+                new_power_ups = power_ups.unwrap_or_default();
             }
         }
 
-        self.children.iter().map(|c| c.state.clone()).collect()
+        let generated = children.iter().map(|c| c.state).collect();
+        arena[id].children = children;
+
+        generated
     }
 
-    /// Used to return the child BoardState corresponding to a particular move.
+    /// Used to return the child BoardState corresponding to a particular move, along with
+    /// whether the child is stored as the mirror image of the move's result.
     ///
-    /// Fails if the column chosen isn't an option, because it's full.
-    pub fn narrow_possibilities(self, col: u8) -> Rc<RefCell<BoardState>> {
-        for child in self.children {
-            if child.get_last_move() == col {
-                if child.is_flipped == IsFlipped::Flipped {
-                    // If the child is flipped, we need to unflip it and adjust the tree
-                    child.state.borrow_mut().board.flip();
-
-                    for grandchild in child.state.borrow_mut().children.iter_mut() {
-                        grandchild.parent_flipped();
-                    }
-                }
-
-                return child.state;
+    /// The child is left in its stored orientation, since it may be shared by other
+    /// transpositions. Callers are expected to track the orientation themselves.
+    ///
+    /// Fails if the move chosen isn't an option, e.g. because its column is full.
+    pub fn narrow_possibilities(&self, game_move: Move) -> (NodeId, IsFlipped) {
+        for child in self.children.iter() {
+            if child.get_last_move() == game_move {
+                return (child.state, child.is_flipped);
             }
         }
 
         panic!(
-            "This BoardState: {:?} was unable to find the col {} in its children!",
-            self.board, col
+            "This BoardState: {:?} was unable to find the move {} in its children!",
+            self.board, game_move
         );
     }
 
+    /// Returns the mini-max score of the decision tree generated so far below this state.
+    ///
+    /// Positive values are favorable to true, negative to false.
+    pub fn get_score(&self) -> isize {
+        self.score
+    }
+
     /// Returns whose turn it is.
     pub fn get_turn(&self) -> bool {
         self.turn
     }
 
+    /// Returns which power-ups are left, or None if the game isn't played with them.
+    pub fn get_power_ups(&self) -> Option<PowerUps> {
+        self.power_ups
+    }
+
+    /// Returns what tells this state apart from others with the same board in a
+    ///  transposition table. Once a column can be cleared the board alone doesn't tell whose
+    ///  turn it is, or which power-ups are left.
+    pub fn get_context(&self) -> Option<(bool, PowerUps)> {
+        self.power_ups.map(|power_ups| (self.turn, power_ups))
+    }
+
     /// Returns if the game is over and who won if it is.
     pub fn is_game_over(&self) -> GameOver {
         self.game_over
     }
 
     /// Returns how many moves into the game this board state is
+    ///
+    /// This counts the pieces on the board, so it falls short once a column has been cleared.
     pub fn get_depth(&self) -> u8 {
-        (0..BOARD_WIDTH).map(|col| self.board.get_height(col)).sum()
+        (0..self.board.width())
+            .map(|col| self.board.get_height(col))
+            .sum()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::RefCell, rc::Rc};
-
     use crate::{
         consts::BOARD_WIDTH,
         game_engine::{
+            arena::Arena,
             board::{Board, OutOfBounds},
-            board_state::{BoardState, GameOver, IDEAL_COLUMNS_FIRST},
-            transposition::TranspositionTable,
+            board_config::BoardConfig,
+            board_state::{BoardState, GameOver},
+            heuristics::HeuristicWeights,
+            power_ups::{Move, PowerUpKind, PowerUps},
+            transposition::IsFlipped,
+            variant_rules::VariantRules,
         },
     };
 
@@ -174,25 +336,26 @@ mod tests {
             [0, 0, 0, 1, 0, 0, 0],
         ]);
 
-        let mut board_state = BoardState::new(board, false);
-        let mut table = TranspositionTable::default();
-        board_state.generate_children(&mut table);
+        let mut arena = Arena::default();
+        let (id, _) = arena.get_board_state(board, false, None);
+        BoardState::generate_children(&mut arena, id);
+        let board_state = &arena[id];
 
         for (i, child) in board_state.children.iter().enumerate() {
             assert_eq!(
-                child.get_last_move() as usize,
-                IDEAL_COLUMNS_FIRST[i] as usize
+                child.get_drop().unwrap() as usize,
+                BoardConfig::STANDARD.columns_from_middle()[i] as usize
             );
-            assert_eq!(child.state.borrow().is_game_over(), GameOver::NoWin);
-            assert_eq!(child.state.borrow().get_turn(), true);
-            assert_eq!(child.state.borrow().children.len(), 0);
+            assert_eq!(arena[child.state].is_game_over(), GameOver::NoWin);
+            assert_eq!(arena[child.state].get_turn(), true);
+            assert_eq!(arena[child.state].children.len(), 0);
 
-            assert_eq!(child.state.borrow().board.get_piece(3, 0).unwrap(), false);
+            assert_eq!(arena[child.state].board.get_piece(3, 0).unwrap(), false);
         }
 
         assert_eq!(
             // Here the 0th child is really column 4, due to the alpha-beta move generation optimization
-            board_state.children[0].state.borrow().board.get_piece(3, 4),
+            arena[board_state.children[0].state].board.get_piece(3, 4),
             Ok(false)
         );
 
@@ -205,22 +368,21 @@ mod tests {
             [2, 2, 1, 1, 2, 1, 2],
         ]);
 
-        let mut board_state = BoardState::new(board, true);
-        let mut table = TranspositionTable::default();
-        board_state.generate_children(&mut table);
+        let mut arena = Arena::default();
+        let (id, _) = arena.get_board_state(board, true, None);
+        BoardState::generate_children(&mut arena, id);
+        let board_state = &arena[id];
 
         for child in board_state.children.iter() {
-            assert_eq!(child.get_last_move() as usize, 1);
-            assert_eq!(child.state.borrow().is_game_over(), GameOver::Tie);
-            assert_eq!(child.state.borrow().get_turn(), false);
-            assert_eq!(child.state.borrow().children.len(), 0);
+            assert_eq!(child.get_drop().unwrap() as usize, 1);
+            assert_eq!(arena[child.state].is_game_over(), GameOver::Tie);
+            assert_eq!(arena[child.state].get_turn(), false);
+            assert_eq!(arena[child.state].children.len(), 0);
 
             assert_eq!(
-                child
-                    .state
-                    .borrow()
+                arena[child.state]
                     .board
-                    .get_piece(child.get_last_move(), 5)
+                    .get_piece(child.get_drop().unwrap(), 5)
                     .unwrap(),
                 true
             );
@@ -235,22 +397,21 @@ mod tests {
             [2, 2, 1, 1, 2, 1, 2],
         ]);
 
-        let mut board_state = BoardState::new(board, false);
-        let mut table = TranspositionTable::default();
-        board_state.generate_children(&mut table);
+        let mut arena = Arena::default();
+        let (id, _) = arena.get_board_state(board, false, None);
+        BoardState::generate_children(&mut arena, id);
+        let board_state = &arena[id];
 
         for child in board_state.children.iter() {
-            assert_eq!(child.get_last_move() as usize, 1);
-            assert_eq!(child.state.borrow().is_game_over(), GameOver::OneWins);
-            assert_eq!(child.state.borrow().get_turn(), true);
-            assert_eq!(child.state.borrow().children.len(), 0);
+            assert_eq!(child.get_drop().unwrap() as usize, 1);
+            assert_eq!(arena[child.state].is_game_over(), GameOver::OneWins);
+            assert_eq!(arena[child.state].get_turn(), true);
+            assert_eq!(arena[child.state].children.len(), 0);
 
             assert_eq!(
-                child
-                    .state
-                    .borrow()
+                arena[child.state]
                     .board
-                    .get_piece(child.get_last_move(), 5)
+                    .get_piece(child.get_drop().unwrap(), 5)
                     .unwrap(),
                 false
             );
@@ -267,28 +428,27 @@ mod tests {
             [1, 1, 1, 0, 0, 2, 0],
         ]);
 
-        let mut board_state = BoardState::new(board, true);
-        let mut table = TranspositionTable::default();
-        board_state.generate_children(&mut table);
+        let mut arena = Arena::default();
+        let (id, _) = arena.get_board_state(board, true, None);
+        BoardState::generate_children(&mut arena, id);
+        let board_state = &arena[id];
 
         for child in board_state.children.iter() {
-            assert_eq!(child.state.borrow().is_game_over(), GameOver::NoWin);
-            assert_eq!(child.state.borrow().get_turn(), false);
-            assert_eq!(child.state.borrow().children.len(), 0);
+            assert_eq!(arena[child.state].is_game_over(), GameOver::NoWin);
+            assert_eq!(arena[child.state].get_turn(), false);
+            assert_eq!(arena[child.state].children.len(), 0);
 
-            let col = child.get_last_move();
+            let col = child.get_drop().unwrap();
             assert_eq!(
-                child
-                    .state
-                    .borrow()
+                arena[child.state]
                     .board
-                    .get_piece(col, child.state.borrow().board.get_height(col) - 1)
+                    .get_piece(col, arena[child.state].board.get_height(col) - 1)
                     .unwrap(),
                 true
             );
 
             if col != 0 {
-                assert_eq!(child.state.borrow().board.get_piece(0, 3), Err(OutOfBounds));
+                assert_eq!(arena[child.state].board.get_piece(0, 3), Err(OutOfBounds));
             }
         }
 
@@ -303,14 +463,15 @@ mod tests {
             [1, 1, 1, 1, 0, 0, 0],
         ]);
 
-        let mut board_state = BoardState::new(board, true);
-        let mut table = TranspositionTable::default();
+        let mut arena = Arena::default();
+        let (id, _) = arena.get_board_state(board, true, None);
 
-        for _ in board_state.generate_children(&mut table).iter() {
-            panic!("A winning game should never generate children!");
-        }
+        assert!(
+            BoardState::generate_children(&mut arena, id).is_empty(),
+            "A winning game should never generate children!"
+        );
 
-        assert_eq!(board_state.children.len(), 0);
+        assert_eq!(arena[id].children.len(), 0);
     }
 
     #[test]
@@ -325,24 +486,32 @@ mod tests {
         ]);
 
         for i in 0..BOARD_WIDTH {
-            let mut board_state: Rc<RefCell<BoardState>> =
-                RefCell::new(BoardState::new(board.clone(), false)).into();
-            let mut table = TranspositionTable::default();
-            board_state.borrow_mut().generate_children(&mut table);
+            let mut arena = Arena::default();
+            let (id, _) = arena.get_board_state(board.clone(), false, None);
+            BoardState::generate_children(&mut arena, id);
 
-            for child in board_state.borrow().children.iter() {
-                child.state.borrow_mut().generate_children(&mut table);
+            for child in arena[id].children.clone() {
+                BoardState::generate_children(&mut arena, child.state);
             }
 
             let mut board_clone = board.clone();
             board_clone.drop_piece(i, false).unwrap();
 
-            board_state = board_state.take().narrow_possibilities(i);
+            let (child, is_flipped) = arena[id].narrow_possibilities(Move::Drop(i));
+            let board_state = &arena[child];
 
-            assert_eq!(board_state.borrow().board, board_clone);
-            assert_eq!(board_state.borrow().is_game_over(), GameOver::NoWin);
-            assert_eq!(board_state.borrow().get_turn(), true);
-            assert_eq!(board_state.borrow().children.len(), 7);
+            // The board is symmetrical, so moves left of center reuse the mirrored children
+            // generated for moves right of center
+            if i < 3 {
+                assert_eq!(is_flipped, IsFlipped::Flipped);
+                board_clone.flip();
+            } else {
+                assert_eq!(is_flipped, IsFlipped::Normal);
+            }
+            assert_eq!(board_state.board, board_clone);
+            assert_eq!(board_state.is_game_over(), GameOver::NoWin);
+            assert_eq!(board_state.get_turn(), true);
+            assert_eq!(board_state.children.len(), 7);
         }
     }
 
@@ -358,16 +527,94 @@ mod tests {
             [0, 2, 1, 1, 2, 0, 1],
         ]);
 
-        let mut board_state = BoardState::new(board, true);
-        let mut table = TranspositionTable::default();
-        board_state.generate_children(&mut table);
+        let mut arena = Arena::default();
+        let (id, _) = arena.get_board_state(board, true, None);
+        BoardState::generate_children(&mut arena, id);
+        let board_state = &arena[id];
 
-        board_state.narrow_possibilities(6);
+        board_state.narrow_possibilities(Move::Drop(6));
+    }
+
+    #[test]
+    fn generate_power_up_children() {
+        // Both players have a piece in the middle column, and player one can clear it
+        let board = Board::from_moves("44").unwrap();
+        let mut arena = Arena::default();
+        let (id, _) = arena.get_board_state(board, false, Some(PowerUps::default()));
+        BoardState::generate_children(&mut arena, id);
+
+        let moves: Vec<Move> = arena[id]
+            .children
+            .iter()
+            .map(|child| child.get_last_move())
+            .collect();
+        assert_eq!(moves.len(), 8);
+        assert_eq!(moves.last(), Some(&Move::Clear(3)));
+
+        // Clearing leaves the empty board with player two to move, who can't clear it
+        let (cleared, _) = arena[id].narrow_possibilities(Move::Clear(3));
+        assert_eq!(arena[cleared].board, Board::default());
+        assert!(arena[cleared].get_turn());
+        assert!(!arena[cleared].get_power_ups().unwrap().is_available(false));
+        BoardState::generate_children(&mut arena, cleared);
+        assert!(arena[cleared]
+            .children
+            .iter()
+            .all(|child| child.get_drop().is_some()));
+
+        // Anvils crush the column and land in it
+        let board = Board::from_moves("44").unwrap();
+        let anvils = Some(PowerUps::new(PowerUpKind::Anvil));
+        let (id, _) = arena.get_board_state(board, false, anvils);
+        BoardState::generate_children(&mut arena, id);
+        let (anvil, _) = arena[id].narrow_possibilities(Move::Anvil(3));
+        assert_eq!(arena[anvil].board, Board::from_moves("4").unwrap());
+
+        // Without power-ups only drops are generated
+        let (id, _) = arena.get_board_state(Board::from_moves("44").unwrap(), false, None);
+        BoardState::generate_children(&mut arena, id);
+        assert_eq!(arena[id].children.len(), 7);
+    }
+
+    #[test]
+    fn misere() {
+        // Player one would get four in a row along the bottom by playing at either end
+        let board = Board::from_moves("445566").unwrap();
+        let mut arena = Arena::with_rules(VariantRules::Misere);
+        let (id, _) = arena.get_board_state(board.clone(), false, None);
+        BoardState::generate_children(&mut arena, id);
+        BoardState::backpropagate(&mut arena, id);
+
+        for child in arena[id].children.iter() {
+            let expected = match child.get_drop().unwrap() {
+                2 | 6 => GameOver::TwoWins,
+                _ => GameOver::NoWin,
+            };
+            assert_eq!(arena[child.state].is_game_over(), expected);
+        }
+        // Player one avoids the losing moves
+        assert!(arena[id].get_score() < isize::MAX);
+
+        // The heuristic is turned around, since lining pieces up is a liability
+        let weights = HeuristicWeights::default();
+        let standard =
+            BoardState::new(board.clone(), false, None, VariantRules::Standard, &weights);
+        let misere = BoardState::new(board, false, None, VariantRules::Misere, &weights);
+        assert_eq!(
+            misere.heuristic_score(&weights),
+            -standard.heuristic_score(&weights)
+        );
     }
 
     #[test]
     fn get_depth() {
-        let mut board_state = BoardState::new(Board::default(), false);
+        let mut board_state = BoardState::new(
+            Board::default(),
+            false,
+            None,
+            VariantRules::Standard,
+            &HeuristicWeights::default(),
+        );
 
         for i in 0..21 {
             assert_eq!(i, board_state.get_depth());
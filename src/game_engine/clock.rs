@@ -0,0 +1,151 @@
+//! Game clocks, where each player has a budget of time for the whole game that's topped up
+//! by an increment after every move they make.
+
+use std::{fmt, time::Duration};
+
+/// How many more moves a player is assumed to have to make when their time is budgeted.
+const MOVES_TO_PLAN_FOR: u32 = 10;
+
+/// How much time each player gets, written like "3+2" for three minutes each with two
+/// seconds added after every move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TimeControl {
+    /// How many minutes each player starts with.
+    pub minutes: u32,
+    /// How many seconds are added to a player's clock after each of their moves.
+    pub increment: u32,
+}
+
+impl TimeControl {
+    /// Parses a time control written like "3+2", or just "5" for no increment.
+    pub fn parse(text: &str) -> Result<TimeControl, String> {
+        let malformed = || format!("'{}' isn't a time control", text);
+        let (minutes, increment) = text.trim().split_once('+').unwrap_or((text.trim(), "0"));
+
+        let time_control = TimeControl {
+            minutes: minutes.trim().parse().map_err(|_| malformed())?,
+            increment: increment.trim().parse().map_err(|_| malformed())?,
+        };
+        if time_control.minutes == 0 && time_control.increment == 0 {
+            return Err(malformed());
+        }
+
+        Ok(time_control)
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}", self.minutes, self.increment)
+    }
+}
+
+/// Both players' clocks in a game played with a time control.
+///
+/// The clock doesn't keep time itself. Whoever runs the game times each move and charges
+/// it to the player who made it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GameClock {
+    /// How long each player has left, player one first.
+    remaining: [Duration; 2],
+    increment: Duration,
+}
+
+impl GameClock {
+    /// Starts a game's clocks, with both players given the time control's full time.
+    pub fn new(time_control: TimeControl) -> GameClock {
+        GameClock {
+            remaining: [Duration::from_secs(time_control.minutes as u64 * 60); 2],
+            increment: Duration::from_secs(time_control.increment as u64),
+        }
+    }
+
+    /// Returns how long the player has left, where player is 0 for player one and 1 for
+    /// player two.
+    pub fn remaining(&self, player: usize) -> Duration {
+        self.remaining[player]
+    }
+
+    /// Takes the time the player spent on a move off their clock, and adds the increment if
+    /// they didn't run out of time making it.
+    pub fn charge(&mut self, player: usize, spent: Duration) {
+        let remaining = &mut self.remaining[player];
+
+        *remaining = remaining.saturating_sub(spent);
+        if !remaining.is_zero() {
+            *remaining += self.increment;
+        }
+    }
+
+    /// Returns how long the player can afford to think about their next move.
+    ///
+    /// Their remaining time is spread over the moves they're likely to still have to make,
+    /// and they can count on the increment coming back, but never spend more than half of
+    /// what they have left.
+    pub fn search_budget(&self, player: usize) -> Duration {
+        let remaining = self.remaining[player];
+
+        (remaining / MOVES_TO_PLAN_FOR + self.increment).min(remaining / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game_engine::clock::{GameClock, TimeControl};
+
+    #[test]
+    fn time_controls() {
+        let blitz = TimeControl {
+            minutes: 3,
+            increment: 2,
+        };
+        assert_eq!(blitz.to_string(), "3+2");
+        assert_eq!(TimeControl::parse("3+2"), Ok(blitz));
+        assert_eq!(TimeControl::parse(" 3 + 2 "), Ok(blitz));
+        assert_eq!(
+            TimeControl::parse("5"),
+            Ok(TimeControl {
+                minutes: 5,
+                increment: 0
+            })
+        );
+
+        assert!(TimeControl::parse("0+0").is_err());
+        assert!(TimeControl::parse("3+").is_err());
+        assert!(TimeControl::parse("fast").is_err());
+    }
+
+    #[test]
+    fn charging_moves() {
+        let mut clock = GameClock::new(TimeControl {
+            minutes: 1,
+            increment: 2,
+        });
+        assert_eq!(clock.remaining(0), Duration::from_secs(60));
+
+        // The increment is added after the move, so a quick move gains time
+        clock.charge(0, Duration::from_secs(1));
+        assert_eq!(clock.remaining(0), Duration::from_secs(61));
+        assert_eq!(clock.remaining(1), Duration::from_secs(60));
+
+        // Running out of time isn't saved by the increment
+        clock.charge(1, Duration::from_secs(90));
+        assert_eq!(clock.remaining(1), Duration::ZERO);
+    }
+
+    #[test]
+    fn search_budgets() {
+        let mut clock = GameClock::new(TimeControl {
+            minutes: 3,
+            increment: 2,
+        });
+        assert_eq!(clock.search_budget(0), Duration::from_secs(20));
+
+        // Short on time, a player never risks more than half of it
+        clock.charge(0, Duration::from_secs(178));
+        assert_eq!(clock.remaining(0), Duration::from_secs(4));
+        assert_eq!(clock.search_budget(0), Duration::from_secs(2));
+    }
+}
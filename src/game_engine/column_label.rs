@@ -0,0 +1,164 @@
+use std::fmt;
+
+use crate::consts::MAX_BOARD_WIDTH;
+
+/// Separates the moves of a move string on boards too wide for every column to be written as
+/// a single digit, e.g. "4,4,10".
+pub const MOVE_SEPARATOR: char = ',';
+
+/// A column as it's shown to people, numbered from 1 from left to right.
+///
+/// Internally columns are numbered from 0, so anything shown to the user, written to a move
+/// string, or logged should go through a ColumnLabel to keep the numbering consistent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ColumnLabel {
+    index: u8,
+}
+
+impl ColumnLabel {
+    /// Returns the label of a column numbered from 0.
+    pub fn new(index: impl Into<usize>) -> ColumnLabel {
+        ColumnLabel {
+            index: index.into() as u8,
+        }
+    }
+
+    /// Parses a column from its number, e.g. "10" for the last column of the widest board.
+    pub fn parse(text: &str) -> Option<ColumnLabel> {
+        if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        match text.parse::<u8>() {
+            Ok(number) if (1..=MAX_BOARD_WIDTH).contains(&number) => {
+                Some(ColumnLabel { index: number - 1 })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the column numbered from 0.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the column numbered from 1, as the user sees it.
+    pub fn number(&self) -> u8 {
+        self.index + 1
+    }
+
+    /// Describes the column for a screen reader, e.g. "Column 4".
+    pub fn accessible_name(&self) -> String {
+        format!("Column {}", self.number())
+    }
+
+    /// Writes columns numbered from 0 as a move string, e.g. "4453".
+    ///
+    /// See join_moves for how moves past the 9th column are written.
+    pub fn move_string(columns: impl IntoIterator<Item = u8>) -> String {
+        join_moves(
+            columns
+                .into_iter()
+                .map(|column| ColumnLabel::new(column).to_string()),
+        )
+    }
+}
+
+/// Joins written moves into a move string.
+///
+/// Moves are written one after another while every column is a single digit, e.g. "4453",
+/// and separated by MOVE_SEPARATOR once a move is in the 10th column, e.g. "4,4,10".
+pub fn join_moves(moves: impl IntoIterator<Item = String>) -> String {
+    let moves: Vec<String> = moves.into_iter().collect();
+
+    if moves
+        .iter()
+        .any(|written| written.chars().filter(char::is_ascii_digit).count() > 1)
+    {
+        moves.join(&MOVE_SEPARATOR.to_string())
+    } else {
+        moves.concat()
+    }
+}
+
+/// Splits a move string into its moves, each of them a column number with any marker in
+/// front of it, e.g. "x4". Whitespace is ignored.
+///
+/// A move string with MOVE_SEPARATOR in it is split at each separator. Otherwise each move
+/// is a single digit, along with the marker before it.
+pub fn split_moves(moves: &str) -> Vec<String> {
+    let moves: String = moves.chars().filter(|c| !c.is_whitespace()).collect();
+    if moves.contains(MOVE_SEPARATOR) {
+        return moves.split(MOVE_SEPARATOR).map(str::to_owned).collect();
+    }
+
+    let mut split = Vec::new();
+    let mut current = String::new();
+    for character in moves.chars() {
+        current.push(character);
+        if character.is_ascii_digit() {
+            split.push(current.split_off(0));
+        }
+    }
+    // A marker with no column after it is still a move, just not a valid one
+    if !current.is_empty() {
+        split.push(current);
+    }
+
+    split
+}
+
+impl From<u8> for ColumnLabel {
+    fn from(index: u8) -> ColumnLabel {
+        ColumnLabel::new(index)
+    }
+}
+
+impl From<usize> for ColumnLabel {
+    fn from(index: usize) -> ColumnLabel {
+        ColumnLabel::new(index)
+    }
+}
+
+impl fmt::Display for ColumnLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.number())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::column_label::{join_moves, split_moves, ColumnLabel};
+
+    #[test]
+    fn labels() {
+        let middle = ColumnLabel::new(3u8);
+        assert_eq!(middle.to_string(), "4");
+        assert_eq!(middle.accessible_name(), "Column 4");
+        assert_eq!(ColumnLabel::parse("4"), Some(middle));
+        assert_eq!(ColumnLabel::from(3usize).index(), 3);
+
+        assert_eq!(ColumnLabel::parse("x"), None);
+
+        assert_eq!(ColumnLabel::move_string([3, 3, 4, 2]), "4453");
+    }
+
+    #[test]
+    fn wide_boards() {
+        let last = ColumnLabel::new(9u8);
+        assert_eq!(last.to_string(), "10");
+        assert_eq!(ColumnLabel::parse("10"), Some(last));
+        assert_eq!(ColumnLabel::parse("4"), Some(ColumnLabel::new(3u8)));
+        assert_eq!(ColumnLabel::parse("0"), None);
+        assert_eq!(ColumnLabel::parse("11"), None);
+        assert_eq!(ColumnLabel::parse("+4"), None);
+        assert_eq!(ColumnLabel::parse(""), None);
+
+        // Moves are only separated once a column needs two digits
+        assert_eq!(ColumnLabel::move_string([3, 9, 9]), "4,10,10");
+        assert_eq!(join_moves(["4".to_owned(), "x10".to_owned()]), "4,x10");
+        assert_eq!(split_moves("4,10, x10"), vec!["4", "10", "x10"]);
+        assert_eq!(split_moves("44 x45"), vec!["4", "4", "x4", "5"]);
+        assert_eq!(split_moves("4x"), vec!["4", "x"]);
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::game_engine::mate_distance::is_forced_loss;
+
+/// How far apart the scores of the reasonable moves have to be spread for the spread alone
+/// to make a position as sharp as it gets.
+const SHARP_SPREAD: f32 = 200.0;
+
+/// How many forcing moves make a position as sharp as it gets, as far as forcing moves go.
+const SHARP_FORCING_MOVES: usize = 3;
+
+/// The sharpness a position needs for each extra flame it's shown with.
+const FLAME_THRESHOLDS: [f32; 3] = [0.25, 0.5, 0.75];
+
+/// An estimate of how sharp a position is, meaning how easy it is to go wrong in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complexity {
+    /// From 0 for a calm position to 1 for the sharpest positions.
+    pub sharpness: f32,
+    /// The standard deviation of the scores of the moves that don't lose outright.
+    pub spread: f32,
+    /// How many of the moves either win outright or threaten to win on the next turn.
+    pub forcing_moves: usize,
+    /// How many of the moves don't lose outright.
+    pub reasonable_moves: usize,
+}
+
+impl Complexity {
+    /// Rates a position from the engine's move scores and how many of its moves are forcing.
+    pub fn rate(move_scores: &HashMap<u8, isize>, forcing_moves: usize) -> Complexity {
+        let reasonable_scores: Vec<f32> = move_scores
+            .values()
+            .filter(|score| !is_forced_loss(**score))
+            .map(|score| (*score).clamp(-(SHARP_SPREAD as isize), SHARP_SPREAD as isize) as f32)
+            .collect();
+        let reasonable_moves = reasonable_scores.len();
+
+        let spread = if reasonable_moves > 1 {
+            let mean = reasonable_scores.iter().sum::<f32>() / reasonable_moves as f32;
+            let variance = reasonable_scores
+                .iter()
+                .map(|score| (score - mean).powi(2))
+                .sum::<f32>()
+                / reasonable_moves as f32;
+
+            variance.sqrt()
+        } else {
+            0.0
+        };
+
+        // Having to find the only move that doesn't lose is as sharp as it gets
+        let only_move = if move_scores.len() > 1 && reasonable_moves == 1 {
+            1.0
+        } else {
+            0.0
+        };
+        let spread_part = (spread / SHARP_SPREAD).min(1.0);
+        let forcing_part =
+            forcing_moves.min(SHARP_FORCING_MOVES) as f32 / SHARP_FORCING_MOVES as f32;
+
+        Complexity {
+            sharpness: f32::max(only_move, 0.5 * spread_part + 0.5 * forcing_part),
+            spread,
+            forcing_moves,
+            reasonable_moves,
+        }
+    }
+
+    /// Returns how many flames the position is shown with, from 0 to 3.
+    pub fn flames(&self) -> usize {
+        FLAME_THRESHOLDS
+            .iter()
+            .filter(|threshold| self.sharpness >= **threshold)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::game_engine::{complexity::Complexity, mate_distance::loss_in};
+
+    #[test]
+    fn calm_and_sharp_positions() {
+        let calm = Complexity::rate(&(0..7).map(|column| (column, 10)).collect(), 0);
+        assert_eq!(calm.sharpness, 0.0);
+        assert_eq!(calm.flames(), 0);
+        assert_eq!(calm.reasonable_moves, 7);
+
+        // Only one move doesn't lose
+        let only_move = HashMap::from([(0, loss_in(1)), (1, loss_in(3)), (2, 5)]);
+        let only_move = Complexity::rate(&only_move, 0);
+        assert_eq!(only_move.sharpness, 1.0);
+        assert_eq!(only_move.flames(), 3);
+
+        // Widely spread scores and a couple of forcing moves
+        let spread = HashMap::from([(0, -150), (1, 0), (2, 150), (3, isize::MAX)]);
+        let spread = Complexity::rate(&spread, 2);
+        assert!(spread.sharpness > 0.5);
+        assert!(spread.flames() >= 2);
+
+        assert_eq!(Complexity::rate(&HashMap::new(), 0).sharpness, 0.0);
+    }
+}
@@ -0,0 +1,106 @@
+/// How many bits of a fixed-point number come after the binary point.
+pub const FRACTION_BITS: u32 = 16;
+
+/// One, in fixed point.
+pub const ONE: i64 = 1 << FRACTION_BITS;
+
+/// √2, in fixed point.
+pub const SQRT_2: i64 = 92682;
+
+/// The natural log of 2, with 32 bits after the binary point so that multiplying by it
+/// doesn't add to the error of log2.
+const LN_2: i64 = 2_977_044_472;
+
+/// How many bits after the binary point log2 keeps while squaring, as many as fit the
+/// square in a u128.
+const LOG_WORKING_BITS: u32 = 62;
+
+/// Returns the natural log of a positive integer, in fixed point.
+///
+/// Only integer arithmetic is used, so the result is the same on every platform, unlike
+/// f32::ln whose last bits depend on the platform's maths library.
+///
+/// Panics if n is 0.
+pub fn ln(n: u64) -> i64 {
+    (log2(n) * LN_2 + (1 << 31)) >> 32
+}
+
+/// Returns the square root of a non-negative fixed-point number, in fixed point, rounded
+/// down.
+///
+/// Panics if x is negative.
+pub fn sqrt(x: i64) -> i64 {
+    let x = u64::try_from(x).expect("Only non-negative numbers have square roots");
+
+    ((x as u128) << FRACTION_BITS).isqrt() as i64
+}
+
+/// Rounds a fixed-point number to the nearest integer, with halves rounded away from zero
+/// like f64::round.
+pub fn round(x: i64) -> i64 {
+    match x >= 0 {
+        true => (x + ONE / 2) >> FRACTION_BITS,
+        false => -((-x + ONE / 2) >> FRACTION_BITS),
+    }
+}
+
+/// Returns the base 2 log of a positive integer, in fixed point, rounded down.
+///
+/// The whole part is the position of the highest set bit. The fraction is found a bit at
+/// a time by squaring what's left, which doubles its log, and halving it whenever it
+/// reaches 2.
+fn log2(n: u64) -> i64 {
+    assert!(n > 0, "Only positive numbers have logs");
+
+    let whole = n.ilog2();
+    // What's left, normalised into [1, 2)
+    let mut x = match whole <= LOG_WORKING_BITS {
+        true => (n as u128) << (LOG_WORKING_BITS - whole),
+        false => (n as u128) >> (whole - LOG_WORKING_BITS),
+    };
+    let mut fraction = 0;
+
+    for bit in (0..FRACTION_BITS).rev() {
+        x = (x * x) >> LOG_WORKING_BITS;
+        if x >= 2 << LOG_WORKING_BITS {
+            x >>= 1;
+            fraction |= 1 << bit;
+        }
+    }
+
+    ((whole as i64) << FRACTION_BITS) | fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::fixed_point::{ln, log2, round, sqrt, ONE, SQRT_2};
+
+    #[test]
+    fn logs() {
+        assert_eq!(log2(1), 0);
+        assert_eq!(log2(8), 3 * ONE);
+        assert_eq!(log2(1 << 40), 40 * ONE);
+        assert_eq!(ln(1), 0);
+
+        for n in [2, 3, 10, 1000, 123_456_789, u64::MAX] {
+            let expected = (n as f64).ln() * ONE as f64;
+            assert!((ln(n) as f64 - expected).abs() <= 2.0, "ln({})", n);
+        }
+    }
+
+    #[test]
+    fn square_roots() {
+        assert_eq!(sqrt(0), 0);
+        assert_eq!(sqrt(4 * ONE), 2 * ONE);
+        assert_eq!(sqrt(ONE / 4), ONE / 2);
+        assert!((sqrt(2 * ONE) - SQRT_2).abs() <= 1);
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(round(ONE * 5 / 2), 3);
+        assert_eq!(round(-ONE * 5 / 2), -3);
+        assert_eq!(round(ONE / 3), 0);
+        assert_eq!(round(-ONE * 2 / 3), -1);
+    }
+}
@@ -1,32 +1,255 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    mem,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    consts::{BOARD_HEIGHT, BOARD_WIDTH, MAX_BOARD_WIDTH},
     game_engine::{
-        board::Board, board_state::BoardState, layer_generator::LayerGenerator,
-        transposition::TranspositionTable, tree_analysis::how_good_is, tree_size::calculate_size,
+        arena::{Arena, NodeId},
+        board::Board,
+        board_config::BoardConfig,
+        board_state::BoardState,
+        complexity::Complexity,
+        game_record::GameRecord,
+        hint::explain_move,
+        layer_generator::LayerGenerator,
+        mate_distance::{is_forced, is_forced_win},
+        monte_carlo::{MonteCarloTree, RolloutResults},
+        move_events::move_events,
+        opening_book::OpeningBook,
+        power_ups::{parse_moves, play_moves_with_config, write_moves, Move},
+        solver::solve_with_rules,
+        strength::{StrengthProfile, FULL_STRENGTH},
+        threats::threats,
+        transposition::TranspositionTable,
+        tree_analysis::{how_deep_is, how_good_is, SearchTables},
+        tree_size::calculate_size,
+        win_check::winning_columns,
     },
     log::PerfTimer,
 };
 
-// Reexport GameOver
-pub use crate::game_engine::{tree_size::TreeSize, win_check::GameOver};
+#[cfg(test)]
+use crate::game_engine::tree_snapshot::{snapshot, TreeSnapshot};
+
+// Reexport the types used by GameManager's interface
+pub use crate::game_engine::{
+    heuristics::HeuristicWeights,
+    hint::Hint,
+    monte_carlo::EngineAlgorithm,
+    move_events::MoveEvent,
+    power_ups::{PowerUpKind, PowerUps},
+    search_mask::SearchMask,
+    search_stats::SearchStats,
+    solver::WinDrawLoss,
+    threats::{Parity, Threat},
+    transposition::IsFlipped,
+    tree_analysis::ScoreConfidence,
+    tree_size::TreeSize,
+    variant_rules::VariantRules,
+    win_check::GameOver,
+};
+
+/// How many board states are generated between checks of the clock when generating
+/// for a set amount of time.
+const GENERATION_CHUNK_SIZE: usize = 1024;
 
+/// Plays a game of Connect Four, while building and analysing a decision tree of the
+/// positions that can follow.
+///
+/// Columns are numbered from 0, and positions are given as array[row][col] with 0 for an
+/// empty space, 1 for player one and 2 for player two.
+///
+/// # Examples
+///
+/// ```
+/// use rusty_connect_four::game_engine::game_manager::{GameManager, GameOver};
+///
+/// let mut manager = GameManager::new_game();
+/// manager.try_generate_x_states(1000);
+///
+/// manager.make_move(3).unwrap();
+/// assert!(manager.get_turn());
+/// assert_eq!(manager.get_position()[5][3], 1);
+/// assert_eq!(manager.is_game_over(), GameOver::NoWin);
+/// ```
 #[derive(Debug)]
 pub struct GameManager {
-    board_state: Rc<RefCell<BoardState>>,
+    /// The current position, at the root of the decision tree stored in the layer
+    /// generator's arena.
+    root: NodeId,
     layer_generator: LayerGenerator,
+    /// Whether the root of the decision tree is stored as the mirror image of the game's
+    /// board. Columns are mapped through this when moving between the two.
+    orientation: IsFlipped,
+    /// The scores found by the last call to get_move_scores, kept until the decision tree
+    /// changes so that asking again doesn't redo the whole tree walk.
+    ///
+    /// Cut off scores are stored as the bounds they are, so the principal variation can reuse
+    /// the table too. The scores are cleared whenever the tree grows, as the scores beneath a
+    /// state change along with its subtree, but the move ordering learned from them is kept
+    /// until a move is made.
+    score_table: RefCell<SearchTables>,
+    /// The time spent scoring moves with alpha-beta pruning since the last move.
+    evaluation_time: Cell<Duration>,
+    /// The book searches play from before falling back on the decision tree, if any.
+    opening_book: Option<Arc<OpeningBook>>,
+    /// The columns searches choose their move from.
+    search_mask: SearchMask,
+    /// How the moves of the current position are scored.
+    algorithm: EngineAlgorithm,
+    /// The Monte Carlo search of the current position, played out alongside the decision
+    /// tree when the algorithm uses rollouts.
+    monte_carlo: MonteCarloTree,
+    /// The reply being pondered while the player to move thinks, if any.
+    ponder: Option<Ponder>,
+    /// The events since they were last taken, oldest first.
+    events: Vec<MoveEvent>,
+    /// Whether a forced win has been reported from the current position.
+    forced_win_reported: bool,
+    /// Every move played since the empty board, or None if the game was started from a
+    /// position the moves leading to aren't known.
+    history: Option<Vec<Move>>,
+    /// The rating the engine plays at, FULL_STRENGTH or above for no limits.
+    strength: u16,
+    /// The seed a weakened engine's noise is drawn from, so each game misjudges differently.
+    noise_seed: u64,
+}
+
+/// The reply the engine expects the player to move to make, which it searches ahead of time
+/// while they think.
+#[derive(Debug)]
+struct Ponder {
+    /// The expected reply, in the game's orientation.
+    column: u8,
+    /// The Monte Carlo search of the position after the reply.
+    monte_carlo: MonteCarloTree,
+}
+
+/// Everything a front-end needs to show the game, taken from the GameManager at once so
+/// that the pieces always agree with each other.
+///
+/// # Examples
+///
+/// ```
+/// use rusty_connect_four::game_engine::game_manager::{GameManager, GameOver};
+///
+/// let state = GameManager::from_moves("4453").unwrap().state(None);
+/// assert_eq!(state.moves_played, 4);
+/// assert!(!state.turn);
+/// assert_eq!(state.rows[5][2], 2);
+/// assert_eq!(state.result, GameOver::NoWin);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameState {
+    pub config: BoardConfig,
+    /// The position as rows from the top, with 0 for an empty space, 1 for player one and 2
+    /// for player two.
+    pub rows: Vec<Vec<u8>>,
+    /// Whose turn it is, false for player one and true for player two.
+    pub turn: bool,
+    /// How many moves have been played, counting spent power-ups. Games started from a
+    /// position without its moves only count the pieces on the board.
+    pub moves_played: usize,
+    pub result: GameOver,
+    /// The engine's live scores for each column, for the player about to move, as they were
+    /// left by the decision tree generated so far.
+    pub move_scores: BTreeMap<u8, isize>,
+    /// How long each player has left on their clock, player one first, if the game is played
+    /// with a time control.
+    pub clock_times: Option<[Duration; 2]>,
+}
+
+/// The outcome of a search on a time budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The move the engine would play, or None if the game is over.
+    pub best_move: Option<u8>,
+    /// The score of the best move, from the point of view of the player about to move.
+    pub score: isize,
+    /// How many moves ahead every line was searched before the time ran out.
+    pub depth: usize,
+    /// The line of play the engine expects after the best move, starting with it.
+    pub principal_variation: Vec<u8>,
+    /// Whether the best move came from the opening book rather than the search.
+    pub from_book: bool,
 }
 
 impl GameManager {
     /// Starts a new game with an empty board.
     pub fn new_game() -> GameManager {
-        let mut table = TranspositionTable::default();
-        let (state, _) = table.get_board_state(Board::default(), false);
+        GameManager::start_from_board(Board::default())
+    }
+
+    /// Starts a new game on an empty board with the given dimensions, won by getting
+    /// number_to_win pieces in a row.
+    ///
+    /// Fails if the engine can't play on a board of that size, see BoardConfig::new.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::GameManager;
+    ///
+    /// let mut manager = GameManager::new_game_with_config(9, 7, 4).unwrap();
+    /// manager.make_move(8).unwrap();
+    /// assert_eq!(manager.get_rows()[6][8], 1);
+    ///
+    /// assert!(GameManager::new_game_with_config(20, 6, 4).is_err());
+    /// ```
+    pub fn new_game_with_config(
+        width: u8,
+        height: u8,
+        number_to_win: u8,
+    ) -> Result<GameManager, String> {
+        let config = BoardConfig::new(width, height, number_to_win)?;
+
+        Ok(GameManager::start_from_board(Board::with_config(config)))
+    }
+
+    /// Starts a new game from an empty board, with player one to move.
+    fn start_from_board(board: Board) -> GameManager {
+        let mut manager = GameManager::start_from_turn(board, false, VariantRules::Standard, None);
+        manager.history = Some(Vec::new());
+
+        manager
+    }
+
+    /// Starts a new game from a board, with the given player to move and power-ups left,
+    /// played under the given rules.
+    fn start_from_turn(
+        board: Board,
+        turn: bool,
+        rules: VariantRules,
+        power_ups: Option<PowerUps>,
+    ) -> GameManager {
+        let monte_carlo = MonteCarloTree::new(board.clone(), turn, rules, power_ups);
+        let mut arena = Arena::with_rules(rules);
+        let (root, _) = arena.get_board_state(board, turn, power_ups);
 
         GameManager {
-            board_state: state,
-            layer_generator: LayerGenerator::new(table),
+            root,
+            layer_generator: LayerGenerator::new(arena),
+            orientation: IsFlipped::Normal,
+            score_table: RefCell::default(),
+            evaluation_time: Cell::default(),
+            opening_book: None,
+            search_mask: SearchMask::ALL,
+            algorithm: EngineAlgorithm::AlphaBeta,
+            monte_carlo,
+            ponder: None,
+            events: Vec::new(),
+            forced_win_reported: false,
+            history: None,
+            strength: FULL_STRENGTH,
+            noise_seed: rand::random(),
         }
     }
 
@@ -37,18 +260,208 @@ impl GameManager {
         position: [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
         turn: bool,
     ) -> GameManager {
-        let mut table = TranspositionTable::default();
-        let (state, _) = table.get_board_state(Board::from_arrays(position), turn);
+        GameManager::start_from_turn(
+            Board::from_arrays(position),
+            turn,
+            VariantRules::Standard,
+            None,
+        )
+    }
 
-        GameManager {
-            board_state: state,
-            layer_generator: LayerGenerator::new(table),
+    /// Starts a new game from the position reached by playing out a string of moves.
+    ///
+    /// The moves are given as columns numbered from 1, e.g. "4445". Columns cleared with a
+    /// power-up are marked with an x, e.g. "4445x4", in which case the game is played with
+    /// power-ups.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::{GameManager, GameOver};
+    ///
+    /// let manager = GameManager::from_moves("4455667").unwrap();
+    /// assert_eq!(manager.is_game_over(), GameOver::OneWins);
+    ///
+    /// assert!(GameManager::from_moves("48").is_err());
+    /// ```
+    pub fn from_moves(moves: &str) -> Result<GameManager, String> {
+        GameManager::from_moves_with_rules(moves, VariantRules::Standard)
+    }
+
+    /// Starts a new game of a variant from the position reached by playing out a string of
+    /// moves, see from_moves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::{GameManager, GameOver, VariantRules};
+    ///
+    /// // Getting four in a row loses a misère game
+    /// let manager = GameManager::from_moves_with_rules("4455667", VariantRules::Misere).unwrap();
+    /// assert_eq!(manager.is_game_over(), GameOver::TwoWins);
+    /// assert_eq!(manager.rules(), VariantRules::Misere);
+    /// ```
+    pub fn from_moves_with_rules(moves: &str, rules: VariantRules) -> Result<GameManager, String> {
+        GameManager::from_moves_with_config(moves, rules, BoardConfig::STANDARD)
+    }
+
+    /// Starts a new game of a variant on a board with the given config from the position
+    /// reached by playing out a string of moves, see from_moves. This is how games of e.g.
+    /// connect three or connect five are played.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::{
+    ///     board_config::BoardConfig,
+    ///     game_manager::{GameManager, GameOver, VariantRules},
+    /// };
+    ///
+    /// // Three in a row along the bottom wins a game of connect three
+    /// let connect_three = BoardConfig::STANDARD.with_number_to_win(3).unwrap();
+    /// let manager =
+    ///     GameManager::from_moves_with_config("11223", VariantRules::Standard, connect_three)
+    ///         .unwrap();
+    /// assert_eq!(manager.is_game_over(), GameOver::OneWins);
+    /// ```
+    pub fn from_moves_with_config(
+        moves: &str,
+        rules: VariantRules,
+        config: BoardConfig,
+    ) -> Result<GameManager, String> {
+        let (board, turn, power_ups) = play_moves_with_config(moves, config)?;
+        let power_ups = (power_ups != PowerUps::default()).then_some(power_ups);
+
+        let mut manager = GameManager::start_from_turn(board, turn, rules, power_ups);
+        manager.history = Some(parse_moves(moves)?);
+
+        Ok(manager)
+    }
+
+    /// Starts a new game of a variant with an empty board.
+    pub fn new_game_with_rules(rules: VariantRules) -> GameManager {
+        let mut manager = GameManager::start_from_turn(Board::default(), false, rules, None);
+        manager.history = Some(Vec::new());
+
+        manager
+    }
+
+    /// Starts a new game from the position a record's main line ends in, see from_moves.
+    ///
+    /// Fails if the record's moves can't be played out.
+    pub fn from_record(record: &GameRecord) -> Result<GameManager, String> {
+        GameManager::from_moves(&record.moves_string())
+    }
+
+    /// Records the moves played so far, so that the game can be saved and resumed later
+    /// with from_record.
+    ///
+    /// Only standard games played from the empty board can be recorded, so this fails for
+    /// games started from a position, played under other rules or on other boards, or where
+    /// a column was cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::GameManager;
+    ///
+    /// let mut manager = GameManager::from_moves("44").unwrap();
+    /// manager.make_move(4).unwrap();
+    ///
+    /// let record = manager.to_record().unwrap();
+    /// assert_eq!(record.moves_string(), "445");
+    ///
+    /// let resumed = GameManager::from_record(&record).unwrap();
+    /// assert_eq!(resumed.get_position(), manager.get_position());
+    /// ```
+    pub fn to_record(&self) -> Result<GameRecord, String> {
+        if self.rules() != VariantRules::Standard || self.get_config() != BoardConfig::default() {
+            return Err("Only standard games can be recorded".to_owned());
         }
+
+        GameRecord::from_moves(&self.move_string()?)
+    }
+
+    /// Writes the moves played so far as a move string, which from_moves_with_config plays
+    /// back on a board of the same size.
+    ///
+    /// Fails for games started from a position, whose earlier moves aren't known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::{
+    ///     board_config::BoardConfig,
+    ///     game_manager::{GameManager, VariantRules},
+    /// };
+    ///
+    /// // Columns past the 9th take two digits, so the moves are separated
+    /// let wide = BoardConfig::new(10, 8, 4).unwrap();
+    /// let mut manager = GameManager::from_moves_with_config("", VariantRules::Standard, wide)
+    ///     .unwrap();
+    /// manager.make_move(9).unwrap();
+    /// manager.make_move(3).unwrap();
+    /// assert_eq!(manager.move_string().unwrap(), "10,4");
+    /// ```
+    pub fn move_string(&self) -> Result<String, String> {
+        match &self.history {
+            Some(history) => Ok(write_moves(history)),
+            None => Err("The moves leading to the position aren't known".to_owned()),
+        }
+    }
+
+    /// Returns the rules the game is played under.
+    pub fn rules(&self) -> VariantRules {
+        self.arena().rules()
     }
 
     /// Returns the current position of the game as array[row][col].
+    ///
+    /// Panics if the game isn't played on the standard board, see get_rows for other boards.
     pub fn get_position(&self) -> [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize] {
-        self.board_state.borrow().board.to_arrays()
+        self.oriented_board().to_arrays()
+    }
+
+    /// Returns the current position of a game on a board of any size as rows[row][col].
+    pub fn get_rows(&self) -> Vec<Vec<u8>> {
+        self.oriented_board().to_rows()
+    }
+
+    /// Returns the arena the decision tree is stored in.
+    fn arena(&self) -> &Arena {
+        self.layer_generator.arena()
+    }
+
+    /// Returns the current position, at the root of the decision tree.
+    fn root_state(&self) -> &BoardState {
+        &self.arena()[self.root]
+    }
+
+    /// Returns the game's board, the right way round.
+    fn oriented_board(&self) -> Board {
+        let mut board = self.root_state().board.clone();
+        if self.orientation == IsFlipped::Flipped {
+            board.flip();
+        }
+
+        board
+    }
+
+    /// Returns the dimensions of the board the game is played on, and how many in a row it
+    /// takes to win.
+    pub fn get_config(&self) -> BoardConfig {
+        self.root_state().board.config()
+    }
+
+    /// Returns whether the engine's decision tree is currently analysing the mirror image
+    /// of the game's board.
+    pub fn get_orientation(&self) -> IsFlipped {
+        self.orientation
+    }
+
+    /// Maps a column between the game's board and the decision tree's orientation.
+    fn map_column(&self, column: u8) -> u8 {
+        self.orientation.map_column(column, self.get_config().width)
     }
 
     /// Generates approximately x board states in the decision tree. Will generate less than
@@ -59,7 +472,7 @@ impl GameManager {
         let timer = PerfTimer::start(&format!("Generate {} states", x));
         let mut num_generated = 0;
 
-        while num_generated < x {
+        while num_generated < x && !self.at_strength_limit() {
             if let Some(num) = self.layer_generator.next() {
                 num_generated += num;
             } else {
@@ -67,101 +480,976 @@ impl GameManager {
             }
         }
 
+        if num_generated > 0 {
+            self.score_table.get_mut().scores.clear();
+        }
+
         timer.stop();
         num_generated
     }
 
+    /// Returns how many layers deep the decision tree goes, counting the root, the same as
+    /// the depth of size without measuring the rest of the tree.
+    fn tree_depth(&self) -> usize {
+        (self.layer_generator.deepest_depth() - self.root_state().get_depth() + 1) as usize
+    }
+
+    /// Returns whether the decision tree has grown as far as the engine's strength allows.
+    fn at_strength_limit(&self) -> bool {
+        let Some(profile) = StrengthProfile::for_rating(self.strength) else {
+            return false;
+        };
+        let Some(next_depth) = self.layer_generator.next_depth() else {
+            return false;
+        };
+
+        let moves_ahead = (next_depth - self.root_state().get_depth()) as usize;
+        moves_ahead >= profile.max_depth || self.arena().len() >= profile.max_nodes
+    }
+
+    /// Generates board states in the decision tree until the given amount of time has passed,
+    /// or the decision tree is completely explored.
+    ///
+    /// Returns the number of board states generated.
+    pub fn try_generate_for(&mut self, duration: Duration) -> usize {
+        let start = Instant::now();
+        let mut num_generated = 0;
+
+        while start.elapsed() < duration {
+            let current_generated = self.try_generate_x_states(GENERATION_CHUNK_SIZE);
+            num_generated += current_generated;
+
+            if current_generated < GENERATION_CHUNK_SIZE {
+                break;
+            }
+        }
+
+        num_generated
+    }
+
+    /// Generates board states until every position the given number of moves ahead is in
+    /// the decision tree, or the decision tree is completely explored.
+    ///
+    /// Returns the number of board states generated.
+    pub fn try_generate_to_depth(&mut self, depth: usize) -> usize {
+        let mut num_generated = 0;
+
+        // The tree's depth includes the root, and only goes past depth + 1 once the layer
+        // depth moves ahead has been finished
+        while self.tree_depth() <= depth + 1 {
+            let current_generated = self.try_generate_x_states(GENERATION_CHUNK_SIZE);
+            num_generated += current_generated;
+
+            if current_generated == 0 {
+                break;
+            }
+        }
+
+        num_generated
+    }
+
+    /// Sets the opening book that searches play from while it has a move for the position.
+    pub fn set_opening_book(&mut self, book: Arc<OpeningBook>) {
+        self.opening_book = Some(book);
+    }
+
+    /// Returns the opening book's move for the current position, if there is one.
+    ///
+    /// The book is only for the standard rules on the standard board, so it's never used in
+    /// other variants, on other boards or in games played with power-ups.
+    pub fn book_move(&self) -> Option<u8> {
+        if self.rules() != VariantRules::Standard
+            || !self.get_config().is_standard()
+            || self.power_ups().is_some()
+        {
+            return None;
+        }
+
+        self.opening_book
+            .as_ref()?
+            .best_move(&self.oriented_board())
+    }
+
+    /// Restricts the moves searches can choose from, e.g. for a puzzle or a handicap, until
+    /// it's set again.
+    ///
+    /// The mask is kept between moves, and applies to search_for, best_move and the first
+    /// move of get_principal_variation. It doesn't affect the scores of each move.
+    pub fn set_search_mask(&mut self, mask: SearchMask) {
+        self.search_mask = mask;
+    }
+
+    /// Returns the columns searches choose their move from.
+    pub fn search_mask(&self) -> SearchMask {
+        self.search_mask
+    }
+
+    /// Sets how the moves of each position are scored, until it's set again.
+    pub fn set_algorithm(&mut self, algorithm: EngineAlgorithm) {
+        self.algorithm = algorithm;
+    }
+
+    /// Returns how the moves of each position are scored.
+    pub fn algorithm(&self) -> EngineAlgorithm {
+        self.algorithm
+    }
+
+    /// Sets how the heuristic weighs each part of a position, until it's set again.
+    ///
+    /// The decision tree is started over from the current position, since the states
+    /// already in it were scored with the old weights.
+    pub fn set_heuristic_weights(&mut self, weights: HeuristicWeights) {
+        if weights == *self.heuristic_weights() {
+            return;
+        }
+        self.stop_pondering();
+        self.restart_tree(weights, self.power_ups());
+    }
+
+    /// Starts the decision tree over from the current position, scored with the given
+    /// weights and with the given power-ups left.
+    fn restart_tree(&mut self, weights: HeuristicWeights, power_ups: Option<PowerUps>) {
+        let mut arena = Arena::with_rules(self.rules());
+        arena.set_heuristic_weights(weights);
+        let (root, _) =
+            arena.get_board_state(self.root_state().board.clone(), self.get_turn(), power_ups);
+
+        self.root = root;
+        self.layer_generator = LayerGenerator::new(arena);
+        self.score_table.get_mut().scores.clear();
+    }
+
+    /// Returns how the heuristic weighs each part of a position.
+    pub fn heuristic_weights(&self) -> &HeuristicWeights {
+        self.arena().heuristic_weights()
+    }
+
+    /// Sets the rating the engine plays at, until it's set again. FULL_STRENGTH or above
+    /// plays without limits.
+    ///
+    /// Weaker engines stop growing the decision tree or playing rollouts once they've
+    /// looked as far ahead and explored as much as their rating allows, and the scores of
+    /// get_move_scores are blurred by noise. A tree already grown further is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::GameManager;
+    ///
+    /// let mut manager = GameManager::new_game();
+    /// manager.set_strength(800);
+    ///
+    /// // The weakest engines only look two moves ahead
+    /// manager.try_generate_to_depth(6);
+    /// assert_eq!(manager.size().depth, 3);
+    /// ```
+    pub fn set_strength(&mut self, rating: u16) {
+        self.strength = rating;
+    }
+
+    /// Returns the rating the engine plays at.
+    pub fn strength(&self) -> u16 {
+        self.strength
+    }
+
+    /// Sets which power-up the game is played with, letting each player clear a column or
+    /// drop their anvil once, or None to play without. Power-ups that have already been
+    /// spent stay spent.
+    ///
+    /// The decision tree is started over from the current position, since the states
+    /// already in it don't know about the power-ups.
+    pub fn set_power_ups(&mut self, kind: Option<PowerUpKind>) {
+        let power_ups = kind.map(|kind| self.power_ups().unwrap_or_default().with_kind(kind));
+
+        if power_ups != self.power_ups() {
+            self.stop_pondering();
+            self.restart_tree(*self.heuristic_weights(), power_ups);
+            self.restart_monte_carlo();
+        }
+    }
+
+    /// Returns which power-ups are left, or None if the game isn't played with them.
+    pub fn power_ups(&self) -> Option<PowerUps> {
+        self.root_state().get_power_ups()
+    }
+
+    /// Starts the Monte Carlo search over from the current position.
+    fn restart_monte_carlo(&mut self) {
+        self.monte_carlo = MonteCarloTree::new(
+            self.oriented_board(),
+            self.get_turn(),
+            self.rules(),
+            self.power_ups(),
+        );
+    }
+
+    /// Plays out the given number of Monte Carlo rollouts from the current position, or from
+    /// the position after the reply being pondered, whichever algorithm is in use.
+    ///
+    /// Returns the number of rollouts played, which is 0 once the game is over.
+    pub fn run_rollouts(&mut self, rollouts: usize) -> usize {
+        let timer = PerfTimer::start(&format!("Run {} rollouts", rollouts));
+        let profile = StrengthProfile::for_rating(self.strength);
+        let monte_carlo = match &mut self.ponder {
+            Some(ponder) => &mut ponder.monte_carlo,
+            None => &mut self.monte_carlo,
+        };
+        // A weakened engine only plays so many rollouts from each position
+        let rollouts = match profile {
+            Some(profile) => rollouts.min(
+                profile
+                    .max_nodes
+                    .saturating_sub(monte_carlo.rollouts() as usize),
+            ),
+            None => rollouts,
+        };
+        let num_played = monte_carlo.run_rollouts(rollouts);
+        timer.stop();
+
+        num_played
+    }
+
+    /// Returns the move the engine currently rates best among the given columns, or None if
+    /// none of them can be played.
+    ///
+    /// Only the decision tree generated so far is searched, and the search mask is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::GameManager;
+    ///
+    /// // Player one has an open three along the bottom, but isn't allowed to finish it
+    /// let mut manager = GameManager::from_moves("445566").unwrap();
+    /// manager.try_generate_to_depth(2);
+    ///
+    /// assert_eq!(manager.best_move_among(&[0, 6]), Some(6));
+    /// assert_eq!(manager.best_move_among(&[0]), Some(0));
+    /// assert_eq!(manager.best_move_among(&[]), None);
+    /// ```
+    pub fn best_move_among(&self, columns: &[u8]) -> Option<u8> {
+        self.principal_variation(1, SearchMask::only(columns))
+            .first()
+            .copied()
+    }
+
+    /// Searches with iterative deepening until the given amount of time has passed, growing
+    /// the decision tree one full layer at a time and scoring it with alpha-beta pruning after
+    /// each layer is finished.
+    ///
+    /// Returns the result of the deepest finished layer, so a layer the time runs out
+    /// partway through doesn't skew the scores. The search stops early once the game's
+    /// result is proven or the decision tree is completely explored.
+    ///
+    /// If the opening book has a move for the position, it's returned without searching.
+    /// Only the columns allowed by the search mask are chosen from, by the book or the
+    /// search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use rusty_connect_four::game_engine::game_manager::GameManager;
+    ///
+    /// let mut manager = GameManager::new_game();
+    /// let result = manager.search_for(Duration::from_millis(100));
+    ///
+    /// assert!(result.best_move.is_some());
+    /// assert_eq!(result.principal_variation.first(), result.best_move.as_ref());
+    /// ```
+    pub fn search_for(&mut self, duration: Duration) -> SearchResult {
+        let start = Instant::now();
+
+        if let Some(column) = self
+            .book_move()
+            .filter(|&column| self.search_mask.allows(column))
+        {
+            return SearchResult {
+                best_move: Some(column),
+                score: self.get_move_scores().get(&column).copied().unwrap_or(0),
+                depth: 0,
+                principal_variation: vec![column],
+                from_book: true,
+            };
+        }
+
+        // The deepest layer can still be growing, so only the ones above it count
+        let mut result = self.search_result(self.tree_depth().saturating_sub(2));
+
+        while !is_forced(result.score) && start.elapsed() < duration {
+            let depth = result.depth + 1;
+
+            // Growing the tree a chunk at a time, so the budget can't be overrun by much
+            let mut explored = false;
+            while self.tree_depth() <= depth + 1 && start.elapsed() < duration {
+                if self.try_generate_x_states(GENERATION_CHUNK_SIZE) == 0 {
+                    explored = true;
+                    break;
+                }
+            }
+            if !explored && self.tree_depth() <= depth + 1 {
+                break;
+            }
+
+            result = self.search_result(depth);
+            if explored {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Scores the decision tree as it is, for a search that's finished the given depth.
+    fn search_result(&self, depth: usize) -> SearchResult {
+        let principal_variation = self.get_principal_variation(depth.max(1));
+        let best_move = principal_variation.first().copied();
+        let score = best_move
+            .and_then(|column| self.get_move_scores().get(&column).copied())
+            .unwrap_or(0);
+
+        SearchResult {
+            best_move,
+            score,
+            depth,
+            principal_variation,
+            from_book: false,
+        }
+    }
+
     /// Drop a piece down the corresponding column.
     pub fn make_move(&mut self, col: u8) -> Result<(), String> {
+        self.play_move(Move::Drop(col))
+    }
+
+    /// Plays a move, moving the root of the decision tree down to the position it reaches.
+    fn play_move(&mut self, game_move: Move) -> Result<(), String> {
         let timer = PerfTimer::start("Make Move");
 
         // If the game is already won, no move is valid
-        if GameOver::NoWin != self.board_state.borrow().is_game_over() {
-            return Err(format!("Game is already over. Can't make move: {}", col));
+        if GameOver::NoWin != self.root_state().is_game_over() {
+            return Err(format!(
+                "Game is already over. Can't make move: {}",
+                game_move
+            ));
+        }
+
+        // We haven't yet generated the children of this board state
+        if self.root_state().children.is_empty() {
+            self.try_generate_x_states(1);
+
+            if self.root_state().children.is_empty() {
+                return Err(format!(
+                    "Was unable to generate children for the root. Can't make move: {}",
+                    game_move
+                ));
+            }
+        }
+
+        // The move in the decision tree's orientation
+        let engine_move = self
+            .orientation
+            .map_move(game_move, self.get_config().width);
+
+        let mut is_valid_move = false;
+        for child in self.root_state().children.iter() {
+            if child.get_last_move() == engine_move {
+                is_valid_move = true;
+            }
+        }
+
+        if !is_valid_move {
+            return Err(format!(
+                "The chosen column wasn't valid. Can't make move: {}",
+                game_move
+            ));
+        }
+
+        let events = match game_move {
+            Move::Drop(col) => move_events(&self.oriented_board(), self.get_turn(), col),
+            Move::Clear(_) | Move::Anvil(_) => Vec::new(),
+        };
+
+        let (child, child_orientation) = self.root_state().narrow_possibilities(engine_move);
+        self.root = child;
+        self.orientation = self.orientation.then(child_orientation);
+
+        // Trimming the positions the move ruled out from the tree
+        let sub_timer = PerfTimer::start("Make Move [Restart Layer Generator]");
+        self.layer_generator.restart(self.root);
+        sub_timer.stop();
+
+        self.score_table.get_mut().clear();
+        self.evaluation_time.set(Duration::ZERO);
+        // The rollouts played while pondering the move are kept
+        match self.ponder.take() {
+            Some(ponder) if Move::Drop(ponder.column) == game_move => {
+                self.monte_carlo = ponder.monte_carlo
+            }
+            _ => self.restart_monte_carlo(),
+        }
+        self.events.extend(events);
+        self.forced_win_reported = false;
+        if let Some(history) = &mut self.history {
+            history.push(game_move);
         }
 
-        // We haven't yet generated the children of this board state
-        if self.board_state.borrow().children.len() == 0 {
-            self.try_generate_x_states(1);
+        timer.stop();
+        Ok(())
+    }
+
+    /// Returns the events since they were last taken, oldest first, e.g. the threats each move
+    /// made has created or blocked.
+    ///
+    /// move_scores are the engine's current scores for the position, from get_move_scores,
+    /// which a forced win for the player to move is detected from. It's only reported once
+    /// per position.
+    pub fn take_events(&mut self, move_scores: &HashMap<u8, isize>) -> Vec<MoveEvent> {
+        let forced_win = move_scores.values().any(|score| is_forced_win(*score));
+        if forced_win && !self.forced_win_reported && self.is_game_over() == GameOver::NoWin {
+            self.forced_win_reported = true;
+            self.events.push(MoveEvent::ForcedWinDetected {
+                player: self.get_turn(),
+            });
+        }
+
+        mem::take(&mut self.events)
+    }
+
+    /// Starts pondering the reply the engine expects the player to move to make, i.e. the
+    /// move it scores best for them. Until the reply is made or pondering stops, the decision
+    /// tree only grows beneath the reply, and rollouts are played from the position after it.
+    ///
+    /// The work is kept if the reply is made, and thrown away if another move is.
+    ///
+    /// Returns the expected reply, or None if no moves have been scored to expect one from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::GameManager;
+    ///
+    /// let mut manager = GameManager::from_moves("112233").unwrap();
+    /// manager.try_generate_to_depth(2);
+    ///
+    /// // Player one is expected to finish their four along the bottom
+    /// assert_eq!(manager.start_pondering(), Some(3));
+    /// assert_eq!(manager.pondering(), Some(3));
+    ///
+    /// manager.make_move(3).unwrap();
+    /// assert_eq!(manager.pondering(), None);
+    /// ```
+    pub fn start_pondering(&mut self) -> Option<u8> {
+        self.stop_pondering();
+        // The reply has to be in the decision tree to grow beneath it
+        if self.root_state().children.is_empty() {
+            self.try_generate_x_states(1);
+        }
+
+        let in_tree = self.alpha_beta_scores();
+        let (column, _) = self
+            .get_move_scores()
+            .into_iter()
+            .filter(|(column, _)| in_tree.contains_key(column))
+            .max_by_key(|&(column, score)| (score, Reverse(column)))?;
+        let (child, _) = self
+            .root_state()
+            .narrow_possibilities(Move::Drop(self.map_column(column)));
+        self.layer_generator.focus(child);
+
+        let turn = self.get_turn();
+        let mut board = self.oriented_board();
+        board
+            .drop_piece(column, turn)
+            .expect("Only legal moves are scored");
+        self.ponder = Some(Ponder {
+            column,
+            monte_carlo: MonteCarloTree::new(board, !turn, self.rules(), self.power_ups()),
+        });
+
+        Some(column)
+    }
+
+    /// Stops pondering, going back to growing the whole decision tree.
+    pub fn stop_pondering(&mut self) {
+        if self.ponder.take().is_some() {
+            self.layer_generator.focus(self.root);
+        }
+    }
+
+    /// Returns the reply being pondered, if any.
+    pub fn pondering(&self) -> Option<u8> {
+        self.ponder.as_ref().map(|ponder| ponder.column)
+    }
+
+    /// Spends the current player's power-up to clear every piece out of a column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::{
+    ///     game_manager::GameManager, power_ups::PowerUpKind,
+    /// };
+    ///
+    /// let mut manager = GameManager::from_moves("445").unwrap();
+    /// assert!(manager.clear_column(3).is_err());
+    ///
+    /// manager.set_power_ups(Some(PowerUpKind::Clear));
+    /// manager.clear_column(3).unwrap();
+    /// assert_eq!(manager.get_position()[5][3], 0);
+    /// assert!(!manager.get_turn());
+    /// assert!(!manager.power_ups().unwrap().is_available(true));
+    /// ```
+    pub fn clear_column(&mut self, col: u8) -> Result<(), String> {
+        self.spend_power_up(Move::Clear(col))
+    }
+
+    /// Spends the current player's anvil, which crushes every piece in a column and lands
+    /// at the bottom of it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::{
+    ///     game_manager::GameManager, power_ups::PowerUpKind,
+    /// };
+    ///
+    /// let mut manager = GameManager::from_moves("445").unwrap();
+    /// manager.set_power_ups(Some(PowerUpKind::Anvil));
+    /// manager.drop_anvil(3).unwrap();
+    /// assert_eq!(manager.get_position()[5][3], 2);
+    /// assert_eq!(manager.get_position()[4][3], 0);
+    /// assert!(!manager.power_ups().unwrap().is_available(true));
+    /// ```
+    pub fn drop_anvil(&mut self, col: u8) -> Result<(), String> {
+        self.spend_power_up(Move::Anvil(col))
+    }
+
+    /// Plays a move spending the current player's power-up, moving the decision tree on to
+    /// the new position like make_move.
+    fn spend_power_up(&mut self, game_move: Move) -> Result<(), String> {
+        let mut power_ups = self
+            .power_ups()
+            .ok_or_else(|| "The game isn't played with power-ups".to_owned())?;
+        // Playing the move on a copy of the board explains why it can't be played
+        game_move.play(&mut self.oriented_board(), self.get_turn(), &mut power_ups)?;
+
+        self.play_move(game_move)
+    }
+
+    /// Returns the scores of spending the current player's power-up in each column, clearing
+    /// it or dropping their anvil in it, on the same scale as get_move_scores.
+    ///
+    /// Power-ups are searched alongside the drops, so they're scored the same way, and there
+    /// are none once the player has spent theirs.
+    pub fn get_power_up_scores(&self) -> HashMap<u8, isize> {
+        let alpha_beta_scores = self
+            .alpha_beta_move_scores()
+            .into_iter()
+            .filter(|(game_move, _)| game_move.power_up().is_some())
+            .map(|(game_move, score)| (game_move.column(), score))
+            .collect();
+
+        // Their noise is drawn apart from the drops into the same columns
+        self.blend_scores(
+            alpha_beta_scores,
+            self.monte_carlo.power_up_results(),
+            MAX_BOARD_WIDTH,
+        )
+    }
+
+    /// Returns a map of moves to their corresponding scores.
+    ///
+    /// Higher scores are better for the player about to make a move,
+    ///  lower scores are better for their opponent. A forced win or loss counts the plies
+    ///  from the move to the end of the game, as described in mate_distance, so a quicker
+    ///  win scores higher than a slower one.
+    ///
+    /// When the algorithm uses rollouts, each move that has been played out is scored by
+    /// its chance of winning instead, on the same calibrated scale win_probability converts
+    /// from. The hybrid algorithm keeps the wins and losses alpha-beta pruning has proven.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_manager::GameManager;
+    ///
+    /// // Player one has three in a row along the bottom
+    /// let mut manager = GameManager::from_moves("445566").unwrap();
+    /// manager.try_generate_x_states(100);
+    ///
+    /// let scores = manager.get_move_scores();
+    /// assert_eq!(scores[&6], isize::MAX);
+    /// ```
+    pub fn get_move_scores(&self) -> HashMap<u8, isize> {
+        self.blend_scores(self.alpha_beta_scores(), self.monte_carlo.move_results(), 0)
+    }
+
+    /// Swaps the rollouts' scores in for alpha-beta pruning's, if the algorithm uses them,
+    /// then blurs the scores with a weakened engine's noise, see get_move_scores.
+    ///
+    /// The noise for each column is drawn as if it were noise_offset columns further along.
+    fn blend_scores(
+        &self,
+        mut move_scores: HashMap<u8, isize>,
+        rollouts: HashMap<u8, RolloutResults>,
+        noise_offset: u8,
+    ) -> HashMap<u8, isize> {
+        if self.algorithm.uses_rollouts() {
+            for (column, results) in rollouts {
+                let proven = move_scores
+                    .get(&column)
+                    .is_some_and(|score| is_forced(*score));
+
+                if proven && self.algorithm == EngineAlgorithm::Hybrid {
+                    continue;
+                }
+                if let Some(score) = results.score() {
+                    move_scores.insert(column, score);
+                }
+            }
+        }
+
+        // A weakened engine misjudges the moves, though never a proven win or loss
+        if let Some(profile) = StrengthProfile::for_rating(self.strength) {
+            let key = self.oriented_board().key();
+
+            for (column, score) in move_scores.iter_mut() {
+                if !is_forced(*score) {
+                    let noise = profile.noise(self.noise_seed, key, *column + noise_offset);
+                    *score = score.saturating_add(noise);
+                }
+            }
+        }
+
+        move_scores
+    }
+
+    /// Returns the scores of each drop found by alpha-beta pruning over the decision tree.
+    fn alpha_beta_scores(&self) -> HashMap<u8, isize> {
+        self.alpha_beta_move_scores()
+            .into_iter()
+            .filter_map(|(game_move, score)| match game_move {
+                Move::Drop(col) => Some((col, score)),
+                Move::Clear(_) | Move::Anvil(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the scores of each move, power-ups included, found by alpha-beta pruning over
+    /// the decision tree.
+    fn alpha_beta_move_scores(&self) -> HashMap<Move, isize> {
+        let timer = PerfTimer::start("Get Move Scores");
+        let start = Instant::now();
+
+        let mut move_scores = HashMap::new();
+        let mut score_table = self.score_table.borrow_mut();
+
+        let arena = self.arena();
+        let root_state = self.root_state();
+        let whose_turn = root_state.get_turn();
+
+        for child in root_state.children.iter() {
+            let child_score = if whose_turn {
+                how_good_is(arena, child.state, &mut score_table)
+            } else {
+                -how_good_is(arena, child.state, &mut score_table)
+            };
+
+            let game_move = self
+                .orientation
+                .map_move(child.get_last_move(), self.get_config().width);
+            move_scores.insert(game_move, child_score);
+        }
+
+        self.evaluation_time
+            .set(self.evaluation_time.get() + start.elapsed());
+        timer.stop();
+        move_scores
+    }
+
+    /// Returns a map of moves to their scores as they were left by the last generated states,
+    /// without searching the decision tree.
+    ///
+    /// Scores are kept up to date as each state is generated, so this is cheap enough to call
+    /// while the tree is still growing. They're the same mini-max scores alpha-beta
+    /// pruning finds, before get_move_scores swaps in rollouts or adds a profile's noise.
+    pub fn get_live_move_scores(&self) -> HashMap<u8, isize> {
+        let root_state = self.root_state();
+        let whose_turn = root_state.get_turn();
+
+        root_state
+            .children
+            .iter()
+            .filter_map(|child| {
+                let score = self.arena()[child.state].get_score();
+                let child_score = if whose_turn { score } else { -score };
+
+                Some((self.map_column(child.get_drop()?), child_score))
+            })
+            .collect()
+    }
+
+    /// Returns the line of play the engine expects, up to max_length moves long, with each
+    /// player making what it currently considers their best move.
+    ///
+    /// The line is made of columns to drop pieces down, so it stops short of a power-up
+    /// rather than naming it as a drop. The first move is always a drop, see
+    /// get_power_up_scores for what spending a power-up instead would score.
+    ///
+    /// The first move is one of the columns allowed by the search mask.
+    pub fn get_principal_variation(&self, max_length: usize) -> Vec<u8> {
+        self.principal_variation(max_length, self.search_mask)
+    }
+
+    /// Returns the principal variation with its first move chosen from the columns the mask
+    /// allows.
+    fn principal_variation(&self, max_length: usize, mask: SearchMask) -> Vec<u8> {
+        let mut line = Vec::new();
+        let mut score_table = self.score_table.borrow_mut();
+
+        let arena = self.arena();
+        let mut state = self.root;
+        let mut orientation = self.orientation;
+        let width = self.get_config().width;
+
+        while line.len() < max_length {
+            let best_child = {
+                let borrowed_state = &arena[state];
+                let scored_children = borrowed_state
+                    .children
+                    .iter()
+                    .filter(|child| {
+                        !line.is_empty()
+                            || child.get_drop().is_some_and(|column| {
+                                mask.allows(orientation.map_column(column, width))
+                            })
+                    })
+                    .map(|child| (how_good_is(arena, child.state, &mut score_table), child));
+
+                // Scores are from player two's point of view
+                let best = if borrowed_state.get_turn() {
+                    scored_children.max_by_key(|(score, _)| *score)
+                } else {
+                    scored_children.min_by_key(|(score, _)| *score)
+                };
+
+                best.map(|(_, child)| (child.state, child.get_last_move(), child.get_is_flipped()))
+            };
+
+            let Some((child_state, last_move, child_orientation)) = best_child else {
+                break;
+            };
+            let Move::Drop(column) = last_move else {
+                break;
+            };
+
+            line.push(orientation.map_column(column, width));
+            orientation = orientation.then(child_orientation);
+            state = child_state;
+        }
+
+        line
+    }
+
+    /// Returns how much each of the given move scores can be trusted, based on how deeply
+    /// the decision tree beneath each move has been searched.
+    pub fn get_move_confidences(
+        &self,
+        move_scores: &HashMap<u8, isize>,
+    ) -> HashMap<u8, ScoreConfidence> {
+        let mut depth_table = TranspositionTable::<Option<usize>>::default();
+
+        self.root_state()
+            .children
+            .iter()
+            .filter_map(|child| {
+                let column = self.map_column(child.get_drop()?);
+                let score = *move_scores.get(&column)?;
+                let horizon = how_deep_is(self.arena(), child.state, &mut depth_table);
+
+                Some((column, ScoreConfidence::new(score, horizon)))
+            })
+            .collect()
+    }
+
+    /// Rates how sharp the current position is, given the engine's scores for its moves.
+    pub fn get_complexity(&self, move_scores: &HashMap<u8, isize>) -> Complexity {
+        let borrowed_state = self.root_state();
+        let board = &borrowed_state.board;
+        let turn = borrowed_state.get_turn();
+
+        // A forcing move either wins, or threatens to win next turn
+        let forcing_moves = (0..board.width())
+            .filter(|col| {
+                let mut new_board = board.clone();
+                new_board.drop_piece(*col, turn).is_ok()
+                    && (winning_columns(board, turn).contains(col)
+                        || !winning_columns(&new_board, turn).is_empty())
+            })
+            .count();
+
+        Complexity::rate(move_scores, forcing_moves)
+    }
+
+    /// Returns a key identifying the current position, which is the same for mirrored positions.
+    ///
+    /// Keys are stable between runs of the program, so they can be saved.
+    pub fn position_key(&self) -> u64 {
+        self.root_state().board.canonical_key()
+    }
+
+    /// Returns a snapshot of the game as it stands.
+    ///
+    /// The engine doesn't keep time, so whoever runs the game passes in the players' clock
+    /// times, if they have any.
+    pub fn state(&self, clock_times: Option<[Duration; 2]>) -> GameState {
+        let rows = self.get_rows();
+        let moves_played = match &self.history {
+            Some(history) => history.len(),
+            None => rows.iter().flatten().filter(|piece| **piece != 0).count(),
+        };
 
-            if self.board_state.borrow().children.len() == 0 {
-                return Err(format!(
-                    "Was unable to generate children for the root. Can't make move: {}",
-                    col
-                ));
-            }
+        GameState {
+            config: self.get_config(),
+            rows,
+            turn: self.get_turn(),
+            moves_played,
+            result: self.is_game_over(),
+            move_scores: self.get_live_move_scores().into_iter().collect(),
+            clock_times,
         }
+    }
 
-        let mut is_valid_col = false;
-        for child in self.board_state.borrow().children.iter() {
-            if child.get_last_move() == col {
-                is_valid_col = true;
+    /// Solves the position exactly, returning the result with perfect play for the player
+    /// about to move and how many moves it takes to reach it.
+    ///
+    /// Unlike the decision tree's scores this is proven, but it can take minutes in the first
+    /// few moves of the game, and misère games can only be solved near their end. A game
+    /// that's over is won, lost or drawn by the player to move in 0 moves.
+    /// Fails if the board is too large to solve.
+    pub fn solve_position(&self) -> Result<(WinDrawLoss, usize), String> {
+        match self.is_game_over() {
+            GameOver::NoWin => {
+                let board_state = self.root_state();
+                solve_with_rules(&board_state.board, board_state.get_turn(), self.rules())
             }
+            GameOver::Tie => Ok((WinDrawLoss::Draw, 0)),
+            // Under misère rules, the player who just moved can have lost
+            result if (result == GameOver::TwoWins) == self.get_turn() => Ok((WinDrawLoss::Win, 0)),
+            _ => Ok((WinDrawLoss::Loss, 0)),
         }
+    }
 
-        if !is_valid_col {
-            return Err(format!(
-                "The chosen column wasn't valid. Can't make move: {}",
-                col
-            ));
-        }
-
-        let sub_timer = PerfTimer::start("Make Move [Trim Tree]");
-        self.board_state
-            .replace(self.board_state.take().narrow_possibilities(col).take());
-        sub_timer.stop();
-
-        let sub_timer = PerfTimer::start("Make Move [Restart Layer Generator]");
-        self.layer_generator.restart();
-        sub_timer.stop();
-
-        timer.stop();
-        Ok(())
+    /// Returns whose turn it is, false for player one and true for player two.
+    pub fn get_turn(&self) -> bool {
+        self.root_state().get_turn()
     }
 
-    /// Returns a map of moves to their corresponding scores.
+    /// Returns the columns where the given player could immediately win by dropping a piece.
+    /// Under misère rules, these are the columns that would lose instead.
     ///
-    /// Higher scores are better for the player about to make a move,
-    ///  lower scores are better for their opponent.
-    pub fn get_move_scores(&self) -> HashMap<u8, isize> {
-        let timer = PerfTimer::start("Get Move Scores");
+    /// false is player one and true is player two.
+    pub fn get_winning_columns(&self, player: bool) -> Vec<u8> {
+        winning_columns(&self.root_state().board, player)
+            .into_iter()
+            .map(|column| self.map_column(column))
+            .collect()
+    }
 
-        let mut move_scores = HashMap::new();
-        let mut score_table = TranspositionTable::<isize>::default();
+    /// Returns every empty space that would complete a connect four for the given player,
+    /// whose parity tells whether it's on an odd or even row.
+    ///
+    /// false is player one and true is player two.
+    pub fn get_threats(&self, player: bool) -> Vec<Threat> {
+        threats(&self.root_state().board, player)
+            .into_iter()
+            .map(|threat| Threat {
+                column: self.map_column(threat.column),
+                ..threat
+            })
+            .collect()
+    }
 
-        let borrowed_board_state = self.board_state.borrow();
-        let child_iter = borrowed_board_state.children.iter();
-        let whose_turn = borrowed_board_state.get_turn();
+    /// Suggests the move the engine currently rates best for the player about to move, and
+    /// explains it by the threats it wins with, blocks or creates, or None if the game is
+    /// over.
+    ///
+    /// Threats mean something else under other variants, so their moves aren't explained.
+    pub fn get_hint(&self) -> Option<Hint> {
+        if self.is_game_over() != GameOver::NoWin {
+            return None;
+        }
 
-        for child in child_iter {
-            let child_score = if whose_turn {
-                how_good_is(&child.state.borrow(), &mut score_table)
-            } else {
-                // Some funky handling to avoid int overflow on negating isize::MIN
-                match how_good_is(&child.state.borrow(), &mut score_table) {
-                    isize::MIN => isize::MAX,
-                    isize::MAX => isize::MIN,
-                    score => -score,
-                }
-            };
+        let (column, explanation) = match self.rules() {
+            VariantRules::Standard => {
+                // Once a win is forced every move may score the same, but winning straight
+                //  away is the one worth teaching
+                let column = match self.get_winning_columns(self.get_turn()).first() {
+                    Some(&column) => column,
+                    None => *self.get_principal_variation(1).first()?,
+                };
+                let explanation = explain_move(&self.oriented_board(), self.get_turn(), column);
+                (column, explanation)
+            }
+            rules => (
+                *self.get_principal_variation(1).first()?,
+                format!("is the engine's best move under {} rules", rules.name()),
+            ),
+        };
+        Some(Hint {
+            column,
+            explanation,
+        })
+    }
 
-            move_scores.insert(child.get_last_move(), child_score);
-        }
+    /// Returns the spaces of the connect four that ended the game, as (column, row) with
+    /// rows counting up from the bottom, or an empty list if there isn't one.
+    ///
+    /// The player who just moved is checked first, since clearing a column with a power-up
+    /// can leave both players with a connect four.
+    pub fn get_winning_line(&self) -> Vec<(u8, u8)> {
+        let board = self.oriented_board();
+        let mover = !self.get_turn();
 
-        timer.stop();
-        move_scores
+        board
+            .winning_line(mover)
+            .or_else(|| board.winning_line(!mover))
+            .unwrap_or_default()
     }
 
     /// Returns whether the game is over, and if so who won.
     pub fn is_game_over(&self) -> GameOver {
-        self.board_state.borrow().is_game_over()
+        self.root_state().is_game_over()
+    }
+
+    /// Takes a snapshot of the decision tree, for comparing it with another one in tests.
+    #[cfg(test)]
+    pub fn snapshot(&self) -> TreeSnapshot {
+        snapshot(self.root, &self.layer_generator)
+    }
+
+    /// Returns how the search has gone since the last move, e.g. how many states it's
+    /// generating a second.
+    pub fn search_stats(&self) -> SearchStats {
+        SearchStats {
+            evaluation_time: self.evaluation_time.get(),
+            ..self.layer_generator.stats()
+        }
     }
 
     /// Returns the size and depth of the board.
     pub fn size(&self) -> TreeSize {
         let timer = PerfTimer::start("Get Size");
 
-        let to_return = calculate_size(self.board_state.clone(), &self.layer_generator);
+        let mut to_return = calculate_size(self.root, &self.layer_generator);
+        // The cached scores are dropped as the tree changes, but their table's capacity stays
+        to_return.memory += self.score_table.borrow().heap_size();
+        to_return.memory += self.monte_carlo.heap_size();
+        if let Some(ponder) = &self.ponder {
+            to_return.memory += ponder.monte_carlo.heap_size();
+        }
 
         timer.stop();
         to_return
@@ -170,11 +1458,30 @@ impl GameManager {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{
+        collections::HashMap,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
     use crate::game_engine::{
-        game_manager::GameManager, transposition::TranspositionTable, tree_analysis::how_good_is,
+        arena::{Arena, NodeId},
+        board_config::BoardConfig,
+        game_manager::GameManager,
+        heuristics::HeuristicWeights,
+        mate_distance::{is_forced, is_forced_loss, is_forced_win, loss_in, one_ply_back, win_in},
+        monte_carlo::EngineAlgorithm,
+        move_events::MoveEvent,
+        opening_book::OpeningBook,
+        power_ups::{choose_power_up, PowerUpKind},
+        search_mask::SearchMask,
+        solver::WinDrawLoss,
+        strength::{StrengthProfile, FULL_STRENGTH},
+        transposition::IsFlipped,
+        tree_analysis::{how_good_is, SearchTables},
+        variant_rules::VariantRules,
         win_check::GameOver,
+        win_probability::score_for_probability,
     };
 
     #[test]
@@ -193,6 +1500,192 @@ mod tests {
         assert_eq!(manager.get_position(), board_array);
     }
 
+    #[test]
+    fn from_moves() {
+        let manager = GameManager::from_moves("4445").unwrap();
+
+        assert_eq!(
+            manager.get_position(),
+            [
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 1, 0, 0, 0],
+                [0, 0, 0, 2, 0, 0, 0],
+                [0, 0, 0, 1, 2, 0, 0],
+            ]
+        );
+        assert!(!manager.get_turn());
+
+        let manager = GameManager::from_moves("1212121").unwrap();
+        assert!(manager.get_turn());
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+
+        assert!(GameManager::from_moves("4449").is_err());
+        assert!(GameManager::from_moves("12121213").is_err());
+    }
+
+    #[test]
+    fn moving_into_a_leaf() {
+        // Only the root's children are generated before each move, so each move lands on a leaf
+        let mut manager = GameManager::new_game();
+
+        for col in [3, 3, 2, 4, 3, 3] {
+            manager.try_generate_x_states(1);
+            manager.make_move(col).unwrap();
+
+            // Generating from a stale board state would panic, or add positions that
+            // can't follow this one
+            manager.try_generate_x_states(1000);
+            assert!(manager.size().depth >= 2);
+        }
+
+        assert_eq!(
+            manager.get_position(),
+            GameManager::from_moves("443544").unwrap().get_position()
+        );
+    }
+
+    #[test]
+    fn flipped_transposition_moves() {
+        let mut manager = GameManager::new_game();
+        manager.try_generate_x_states(5000);
+
+        // Column 7 is generated before column 1, so column 1 reuses its mirror image
+        manager.make_move(0).unwrap();
+        assert_eq!(manager.get_orientation(), IsFlipped::Flipped);
+        assert_eq!(
+            manager.get_position(),
+            GameManager::from_moves("1").unwrap().get_position()
+        );
+
+        manager.make_move(1).unwrap();
+        manager.make_move(0).unwrap();
+        assert_eq!(
+            manager.get_position(),
+            GameManager::from_moves("121").unwrap().get_position()
+        );
+        assert_eq!(
+            manager.get_winning_columns(false),
+            GameManager::from_moves("121")
+                .unwrap()
+                .get_winning_columns(false)
+        );
+    }
+
+    #[test]
+    fn wide_move_strings() {
+        let wide = BoardConfig::new(10, 8, 4).unwrap();
+        let mut manager = GameManager::new_game_with_config(10, 8, 4).unwrap();
+        for column in [9, 3, 9, 8, 0] {
+            manager.make_move(column).unwrap();
+        }
+
+        let moves = manager.move_string().unwrap();
+        assert_eq!(moves, "10,4,10,9,1");
+        let replayed =
+            GameManager::from_moves_with_config(&moves, VariantRules::Standard, wide).unwrap();
+        assert_eq!(replayed.get_rows(), manager.get_rows());
+        assert_eq!(replayed.get_turn(), manager.get_turn());
+        assert_eq!(replayed.move_string().unwrap(), moves);
+
+        // Games that stay within the first nine columns are written without separators
+        let manager = GameManager::from_moves_with_config("9,1", VariantRules::Standard, wide);
+        assert_eq!(manager.unwrap().move_string().unwrap(), "91");
+
+        // The 10th column is only on the board if it's that wide
+        assert!(GameManager::from_moves("4,10").is_err());
+        assert!(GameManager::from_moves_with_config("4,11", VariantRules::Standard, wide).is_err());
+    }
+
+    #[test]
+    fn configured_games() {
+        let mut manager = GameManager::new_game_with_config(8, 7, 4).unwrap();
+        manager.try_generate_x_states(5000);
+
+        // Red lines up four along the bottom, finishing on the right edge, which the engine
+        // may have stored as the mirror image of the left edge
+        for column in [4, 4, 5, 5, 6, 6] {
+            manager.make_move(column).unwrap();
+            manager.try_generate_x_states(1000);
+        }
+        assert_eq!(manager.get_winning_columns(false), vec![3, 7]);
+        manager.make_move(7).unwrap();
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+        assert_eq!(
+            manager.get_winning_line(),
+            vec![(4, 0), (5, 0), (6, 0), (7, 0)]
+        );
+
+        assert_eq!(manager.get_hint(), None);
+
+        let rows = manager.get_rows();
+        assert_eq!(rows.len(), 7);
+        assert_eq!(rows[6], [0, 0, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(rows[5], [0, 0, 0, 0, 2, 2, 2, 0]);
+
+        // Four in a row isn't enough when it takes five
+        let mut manager = GameManager::new_game_with_config(9, 6, 5).unwrap();
+        assert_eq!(manager.get_config().number_to_win, 5);
+        for column in [0, 0, 1, 1, 2, 2, 3, 3] {
+            manager.make_move(column).unwrap();
+        }
+        assert_eq!(manager.is_game_over(), GameOver::NoWin);
+        assert!(manager.get_winning_line().is_empty());
+        manager.make_move(4).unwrap();
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+        assert_eq!(manager.get_winning_line().len(), 5);
+
+        assert!(GameManager::new_game_with_config(7, 9, 4).is_err());
+
+        // Connect five on the standard board carries on past four in a row
+        let connect_five = BoardConfig::STANDARD.with_number_to_win(5).unwrap();
+        assert!(GameManager::from_moves("11223344").is_err());
+        let manager =
+            GameManager::from_moves_with_config("112233445", VariantRules::Standard, connect_five)
+                .unwrap();
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+
+        // And connect three is won sooner, which the engine sees coming
+        let connect_three = BoardConfig::STANDARD.with_number_to_win(3).unwrap();
+        let mut manager =
+            GameManager::from_moves_with_config("1122", VariantRules::Standard, connect_three)
+                .unwrap();
+        manager.try_generate_x_states(1000);
+        assert_eq!(manager.get_winning_columns(false), vec![2]);
+        assert!(is_forced_win(manager.get_move_scores()[&2]));
+    }
+
+    #[test]
+    fn hints() {
+        // Blue has to block Red's three in the first column
+        let mut manager = GameManager::from_moves("12121").unwrap();
+        manager.try_generate_x_states(1000);
+        let hint = manager.get_hint().unwrap();
+        assert_eq!(hint.column, 0);
+        assert_eq!(
+            hint.explanation,
+            "blocks the opponent's vertical threat at column 1"
+        );
+
+        // Every move wins, but the hint is to win straight away
+        let mut manager = GameManager::from_moves("445566").unwrap();
+        manager.try_generate_x_states(1000);
+        assert_eq!(
+            manager.get_hint().unwrap().explanation,
+            "wins with a horizontal connect four in column 3"
+        );
+
+        let mut manager =
+            GameManager::from_moves_with_rules("445566", VariantRules::Misere).unwrap();
+        manager.try_generate_x_states(1000);
+        assert!(manager
+            .get_hint()
+            .unwrap()
+            .explanation
+            .ends_with("under Misère rules"));
+    }
+
     #[test]
     fn generates_to_win() {
         let board_array = [
@@ -208,21 +1701,17 @@ mod tests {
 
         manager.try_generate_x_states(10000);
 
-        let state = manager.board_state;
-
         assert_eq!(
-            how_good_is(&state.borrow(), &mut TranspositionTable::<isize>::default()),
-            isize::MIN
+            how_good_is(manager.arena(), manager.root, &mut SearchTables::default()),
+            loss_in(7)
         );
 
         let mut manager = GameManager::start_from_position(board_array, true);
 
         manager.try_generate_x_states(10000);
 
-        let state = manager.board_state;
-
         assert_eq!(
-            how_good_is(&state.borrow(), &mut TranspositionTable::<isize>::default()),
+            how_good_is(manager.arena(), manager.root, &mut SearchTables::default()),
             0
         );
     }
@@ -285,7 +1774,8 @@ mod tests {
 
         let move_scores = manager.get_move_scores();
         let mut real_move_scores = HashMap::new();
-        real_move_scores.insert(5, isize::MAX);
+        // Playing there wins once the board fills up, six plies later
+        real_move_scores.insert(5, win_in(6));
         real_move_scores.insert(6, 0);
         assert_eq!(move_scores, real_move_scores);
 
@@ -313,9 +1803,9 @@ mod tests {
         let move_scores = manager.get_move_scores();
         for (col, score) in move_scores {
             if col == 3 {
-                assert_ne!(score, isize::MIN);
+                assert!(!is_forced_loss(score));
             } else {
-                assert_eq!(score, isize::MIN);
+                assert_eq!(score, loss_in(1));
             }
         }
 
@@ -325,10 +1815,565 @@ mod tests {
         let move_scores = manager.get_move_scores();
         for (col, score) in move_scores {
             if col == 3 {
-                assert_eq!(score, isize::MAX);
+                assert_eq!(score, win_in(0));
             } else {
-                assert_ne!(score, isize::MAX);
+                assert!(!is_forced_win(score));
             }
         }
     }
+
+    #[test]
+    fn principal_variation() {
+        // Red has an open three along the bottom, so Blue can only block one end
+        let mut manager = GameManager::from_moves("44556").unwrap();
+        manager.try_generate_x_states(1000);
+
+        let line = manager.get_principal_variation(8);
+        assert_eq!(line.len(), 2);
+        assert_eq!(manager.get_principal_variation(1).len(), 1);
+
+        for column in line {
+            manager.make_move(column).unwrap();
+        }
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+    }
+
+    #[test]
+    fn search_for() {
+        // Player one has an open three along the bottom, so the search stops as soon as it
+        // sees the win, rather than using up its time
+        let mut manager = GameManager::from_moves("445566").unwrap();
+        let start = Instant::now();
+        let result = manager.search_for(Duration::from_secs(5));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(result.score, isize::MAX);
+        assert_eq!(result.principal_variation[..1], [result.best_move.unwrap()]);
+
+        let mut manager = GameManager::new_game();
+        let start = Instant::now();
+        let result = manager.search_for(Duration::from_millis(300));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.depth >= 2);
+        assert!(manager.size().depth > result.depth + 1);
+        assert_eq!(result.principal_variation.len(), result.depth);
+
+        // Searching again picks up where the last search left off
+        let deeper = manager.search_for(Duration::from_millis(300));
+        assert!(deeper.depth >= result.depth);
+
+        // Nothing is left to search once the game is over
+        let mut manager = GameManager::from_moves("1212121").unwrap();
+        let result = manager.search_for(Duration::from_millis(100));
+        assert_eq!(result.best_move, None);
+        assert!(result.principal_variation.is_empty());
+    }
+
+    #[test]
+    fn search_mask() {
+        // Player one has an open three along the bottom, which a handicap keeps them from
+        // finishing
+        let mut manager = GameManager::from_moves("445566").unwrap();
+        manager.set_search_mask(SearchMask::only(&[0, 1, 3]));
+        let result = manager.search_for(Duration::from_millis(200));
+        assert!(matches!(result.best_move, Some(0 | 1 | 3)));
+        assert_eq!(
+            result.principal_variation.first(),
+            result.best_move.as_ref()
+        );
+
+        // The mask only restricts the first move of the line
+        manager.set_search_mask(SearchMask::only(&[0]));
+        let line = manager.get_principal_variation(3);
+        assert_eq!(line[0], 0);
+        assert_eq!(line.len(), 3);
+
+        // It's kept between moves, and works in the game's orientation when the tree is
+        // stored flipped
+        let mut manager = GameManager::new_game();
+        manager.make_move(6).unwrap();
+        manager.set_search_mask(SearchMask::only(&[1]));
+        manager.make_move(0).unwrap();
+        manager.try_generate_to_depth(2);
+        assert_eq!(manager.search_mask(), SearchMask::only(&[1]));
+        assert_eq!(manager.get_principal_variation(1), [1]);
+        assert_eq!(manager.best_move_among(&[5, 9]), Some(5));
+    }
+
+    #[test]
+    fn algorithms() {
+        // Player one has an open three along the bottom
+        let mut manager = GameManager::from_moves("445566").unwrap();
+        manager.set_algorithm(EngineAlgorithm::Mcts);
+        assert_eq!(manager.run_rollouts(2000), 2000);
+
+        // Rollouts score moves by their chance of winning, which is never certain
+        let scores = manager.get_move_scores();
+        assert_eq!(scores[&6], score_for_probability(1.0));
+        assert!(scores[&6] > scores[&0]);
+
+        // Once the decision tree has proven the win, the hybrid keeps it
+        manager.try_generate_to_depth(2);
+        manager.set_algorithm(EngineAlgorithm::Hybrid);
+        assert_eq!(manager.get_move_scores()[&6], isize::MAX);
+
+        // The rollouts are started over from the position after a move, leaving only the
+        // decision tree's scores until more are played
+        manager.make_move(0).unwrap();
+        assert_eq!(manager.algorithm(), EngineAlgorithm::Hybrid);
+        assert_eq!(manager.get_move_scores(), manager.alpha_beta_scores());
+    }
+
+    #[test]
+    fn heuristic_weights() {
+        let mut manager = GameManager::from_moves("445").unwrap();
+        manager.try_generate_x_states(1);
+        let scores = manager.get_move_scores();
+
+        let center_only = HeuristicWeights {
+            closeness_to_win: 0,
+            center_piece: 1,
+            good_parity_threat: 0,
+            bad_parity_threat: 0,
+            double_threat: 0,
+            unspent_power_up: 0,
+        };
+        manager.set_heuristic_weights(center_only);
+        assert_eq!(manager.heuristic_weights(), &center_only);
+
+        // The decision tree is started over from the same position
+        assert_eq!(manager.size().size, 1);
+        assert_eq!(
+            manager.get_position(),
+            GameManager::from_moves("445").unwrap().get_position()
+        );
+
+        // Only the move into the center column is worth anything now
+        manager.try_generate_x_states(1);
+        let center_scores = manager.get_move_scores();
+        assert_ne!(center_scores, scores);
+        assert_eq!(center_scores[&3], 1);
+        assert_eq!(center_scores[&0], 0);
+    }
+
+    #[test]
+    fn search_stats() {
+        let mut manager = GameManager::new_game();
+        assert_eq!(manager.search_stats().expanded, 0);
+
+        manager.try_generate_to_depth(4);
+        manager.get_move_scores();
+        let stats = manager.search_stats();
+        // The root was the first state looked up
+        assert_eq!(stats.new_states(), manager.size().size);
+        assert!(stats.expanded > 0 && stats.expanded < stats.new_states());
+        assert!(stats.hits > 0);
+        assert!(stats.frontier > 0);
+        assert!(stats.generation_time > Duration::ZERO);
+        assert!(stats.evaluation_time > Duration::ZERO);
+
+        // The stats start over with each move
+        manager.make_move(3).unwrap();
+        let stats = manager.search_stats();
+        assert_eq!((stats.expanded, stats.lookups), (0, 0));
+        assert_eq!(stats.evaluation_time, Duration::ZERO);
+    }
+
+    #[test]
+    fn strength() {
+        let mut manager = GameManager::new_game();
+        manager.set_strength(900);
+        let profile = StrengthProfile::for_rating(900).unwrap();
+
+        // The decision tree stops growing at the depth cap, however long it's given
+        manager.try_generate_for(Duration::from_millis(50));
+        assert_eq!(manager.size().depth, profile.max_depth + 1);
+        assert_eq!(manager.try_generate_x_states(100), 0);
+
+        // Rollouts stop at the node cap
+        manager.set_algorithm(EngineAlgorithm::Mcts);
+        assert_eq!(
+            manager.run_rollouts(profile.max_nodes * 2),
+            profile.max_nodes
+        );
+        assert_eq!(manager.run_rollouts(10), 0);
+
+        // The scores are blurred, but not by more than the profile's noise
+        manager.set_algorithm(EngineAlgorithm::AlphaBeta);
+        let noisy = manager.get_move_scores();
+        manager.set_strength(FULL_STRENGTH);
+        assert_eq!(manager.strength(), FULL_STRENGTH);
+        let exact = manager.get_move_scores();
+        assert_ne!(noisy, exact);
+        for (column, score) in exact {
+            assert!((noisy[&column] - score).abs() <= profile.noise);
+        }
+
+        // A full strength engine has no limits
+        assert!(manager.try_generate_x_states(100) > 0);
+    }
+
+    #[test]
+    fn power_ups() {
+        // Player one threatens both ends of a three along the second row, which player two
+        // can only stop by clearing away its middle
+        let mut manager = GameManager::from_moves("426345375").unwrap();
+        assert_eq!(manager.power_ups(), None);
+        manager.set_power_ups(Some(PowerUpKind::Clear));
+        manager.set_algorithm(EngineAlgorithm::Mcts);
+        manager.run_rollouts(3000);
+
+        let clear_scores = manager.get_power_up_scores();
+        let best_drop = manager.get_move_scores().into_values().max().unwrap();
+        assert!(clear_scores[&3] > best_drop);
+
+        manager.clear_column(3).unwrap();
+        assert_eq!(manager.get_rows()[5][3], 0);
+        assert!(manager.get_power_up_scores().is_empty());
+        // The column is empty now, but player one can still clear another
+        assert!(manager.clear_column(3).is_err());
+        assert!(manager.clear_column(2).is_ok());
+        assert!(manager.clear_column(5).is_err());
+
+        // The decision tree carries on searching drops from the new position
+        manager.try_generate_to_depth(2);
+        manager.make_move(4).unwrap();
+
+        // Clears in a move string are replayed, and spend the power-up
+        let manager = GameManager::from_moves("44556x5").unwrap();
+        assert_eq!(manager.get_rows()[5][4], 0);
+        assert_eq!(manager.get_rows()[5][5], 1);
+        assert!(!manager.get_turn());
+        let power_ups = manager.power_ups().unwrap();
+        assert!(power_ups.is_available(false));
+        assert!(!power_ups.is_available(true));
+
+        // An anvil landing at the bottom of the middle column completes four along it
+        let mut manager = GameManager::from_moves("14273747").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Anvil));
+        assert!(manager.clear_column(3).is_err());
+        manager.drop_anvil(3).unwrap();
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+        let manager = GameManager::from_moves("14273747a4").unwrap();
+        assert_eq!(manager.power_ups().unwrap().kind(), PowerUpKind::Anvil);
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+    }
+
+    #[test]
+    fn searching_power_ups() {
+        // Player one can only break through player two's piece in the 4th column, under their
+        // three along the bottom, with their anvil
+        let mut manager = GameManager::from_moves("142737").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Anvil));
+        manager.try_generate_to_depth(2);
+        assert_eq!(manager.get_power_up_scores()[&3], isize::MAX);
+        assert!(manager
+            .get_move_scores()
+            .values()
+            .all(|score| !is_forced(*score)));
+
+        manager.drop_anvil(3).unwrap();
+        assert_eq!(manager.is_game_over(), GameOver::OneWins);
+        assert!(manager.get_power_up_scores().is_empty());
+
+        // Spending a power-up carries on with the decision tree searched beneath it
+        let mut manager = GameManager::from_moves("44").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Clear));
+        manager.try_generate_to_depth(3);
+        manager.clear_column(3).unwrap();
+        assert!(manager.size().depth > 1);
+        assert_eq!(manager.get_rows()[5][3], 0);
+    }
+
+    #[test]
+    fn choosing_power_ups() {
+        // Player two can only stop player one's double threat by clearing the 4th column
+        let mut manager = GameManager::from_moves("426345375").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Clear));
+        manager.try_generate_to_depth(3);
+        let choice = choose_power_up(&manager.get_move_scores(), &manager.get_power_up_scores());
+        assert_eq!(choice, Some(3));
+
+        // Player one wins by crushing player two's piece with their anvil
+        let mut manager = GameManager::from_moves("142737").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Anvil));
+        manager.try_generate_to_depth(2);
+        let choice = choose_power_up(&manager.get_move_scores(), &manager.get_power_up_scores());
+        assert_eq!(choice, Some(3));
+
+        // The power-up is kept while a drop is as good
+        let mut manager = GameManager::from_moves("44").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Clear));
+        manager.try_generate_to_depth(3);
+        let choice = choose_power_up(&manager.get_move_scores(), &manager.get_power_up_scores());
+        assert_eq!(choice, None);
+    }
+
+    #[test]
+    fn pondering() {
+        let mut pondered = GameManager::new_game();
+        let mut unpondered = GameManager::new_game();
+        pondered.try_generate_to_depth(2);
+        unpondered.try_generate_to_depth(2);
+
+        let reply = pondered.start_pondering().unwrap();
+        pondered.try_generate_x_states(3000);
+        unpondered.try_generate_x_states(3000);
+
+        // The work spent beneath the expected reply is kept when it's made
+        pondered.make_move(reply).unwrap();
+        unpondered.make_move(reply).unwrap();
+        assert!(pondered.size().size > unpondered.size().size);
+        assert!(pondered.size().depth > unpondered.size().depth);
+
+        // Rollouts played from the position after the reply are kept too
+        let mut manager = GameManager::from_moves("4453").unwrap();
+        manager.set_algorithm(EngineAlgorithm::Mcts);
+        manager.run_rollouts(500);
+        let reply = manager.start_pondering().unwrap();
+        manager.run_rollouts(200);
+        assert_eq!(manager.monte_carlo.rollouts(), 500);
+        manager.make_move(reply).unwrap();
+        assert_eq!(manager.monte_carlo.rollouts(), 200);
+
+        // Any other move throws the pondering away, and the whole tree grows again
+        let reply = manager.start_pondering().unwrap();
+        manager.try_generate_x_states(500);
+        manager.make_move((reply + 1) % 7).unwrap();
+        assert_eq!(manager.pondering(), None);
+        assert_eq!(manager.monte_carlo.rollouts(), 0);
+        assert!(manager.try_generate_x_states(500) > 0);
+
+        manager.start_pondering().unwrap();
+        manager.stop_pondering();
+        assert_eq!(manager.pondering(), None);
+    }
+
+    #[test]
+    fn events() {
+        let mut manager = GameManager::from_moves("4455").unwrap();
+        manager.make_move(5).unwrap();
+        manager.make_move(2).unwrap();
+        assert_eq!(
+            manager.take_events(&HashMap::new()),
+            vec![
+                MoveEvent::ThreatCreated {
+                    player: false,
+                    columns: vec![2, 6],
+                },
+                MoveEvent::ThreatBlocked {
+                    player: true,
+                    column: 2,
+                },
+            ]
+        );
+
+        // Red's win is found by the search, and only reported once
+        manager.try_generate_to_depth(2);
+        let move_scores = manager.get_move_scores();
+        assert_eq!(
+            manager.take_events(&move_scores),
+            vec![MoveEvent::ForcedWinDetected { player: false }]
+        );
+        assert_eq!(manager.take_events(&move_scores), vec![]);
+    }
+
+    #[test]
+    fn sendable() {
+        // Without reference counted pointers in the tree, a game can be handed to another
+        // thread
+        fn assert_send<T: Send>() {}
+        assert_send::<GameManager>();
+    }
+
+    #[test]
+    fn restarted_generation() {
+        // Few enough spaces are left for the whole tree to be generated
+        let moves = "1365254712142162465645726765";
+        let mut continued = GameManager::from_moves(moves).unwrap();
+        let mut restarted = GameManager::from_moves(moves).unwrap();
+
+        // Restarting partway through a layer rescans the frontier in a different order
+        while continued.try_generate_x_states(300) > 0 {}
+        while restarted.try_generate_x_states(300) > 0 {
+            restarted.layer_generator.restart(restarted.root);
+        }
+        assert_eq!(continued.snapshot(), restarted.snapshot());
+        assert!(continued.snapshot().states > 1000, "{:?}", continued.size());
+
+        // Both trees are trimmed the same way by the same moves
+        for column in [0, 6, 0] {
+            continued.make_move(column).unwrap();
+            restarted.make_move(column).unwrap();
+            assert_eq!(continued.snapshot(), restarted.snapshot());
+        }
+    }
+
+    #[test]
+    fn opening_book() {
+        let mut book = OpeningBook::new();
+        book.insert("1", 1).unwrap();
+        let book = Arc::new(book);
+
+        let mut manager = GameManager::new_game();
+        manager.set_opening_book(book.clone());
+        assert_eq!(manager.book_move(), None);
+        assert!(!manager.search_for(Duration::from_millis(10)).from_book);
+
+        // The book's moves are for a position and its mirror image
+        manager.make_move(6).unwrap();
+        assert_eq!(manager.book_move(), Some(5));
+        let result = manager.search_for(Duration::from_secs(5));
+        assert!(result.from_book);
+        assert_eq!(result.best_move, Some(5));
+        assert_eq!(result.principal_variation, [5]);
+    }
+
+    #[test]
+    fn state() {
+        let mut manager = GameManager::new_game_with_config(8, 7, 4).unwrap();
+        manager.make_move(7).unwrap();
+        manager.try_generate_x_states(100);
+
+        let state = manager.state(None);
+        assert_eq!(state.config, manager.get_config());
+        assert_eq!(state.rows, manager.get_rows());
+        assert_eq!(state.rows[6][7], 1);
+        assert!(state.turn);
+        assert_eq!(state.moves_played, 1);
+        assert_eq!(state.result, GameOver::NoWin);
+        assert_eq!(
+            state.move_scores,
+            manager.get_live_move_scores().into_iter().collect()
+        );
+        assert_eq!(state.move_scores.len(), 8);
+
+        let state = GameManager::from_moves("1212121").unwrap().state(None);
+        assert_eq!(state.result, GameOver::OneWins);
+
+        // A cleared column takes pieces off the board, but the moves still count
+        let mut manager = GameManager::from_moves("4445").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Clear));
+        manager.clear_column(3).unwrap();
+        let clock_times = [Duration::from_secs(60), Duration::from_secs(45)];
+        let state = manager.state(Some(clock_times));
+        assert_eq!(state.moves_played, 5);
+        assert_eq!(state.clock_times, Some(clock_times));
+
+        // An anvil crushes the column down to a single piece
+        let mut manager = GameManager::from_moves("4445").unwrap();
+        manager.set_power_ups(Some(PowerUpKind::Anvil));
+        manager.drop_anvil(3).unwrap();
+        assert_eq!(manager.state(None).moves_played, 5);
+    }
+
+    #[test]
+    fn solve_position() {
+        let manager = GameManager::from_moves("44553").unwrap();
+        assert_eq!(manager.solve_position(), Ok((WinDrawLoss::Loss, 2)));
+
+        let manager = GameManager::from_moves("1212121").unwrap();
+        assert_eq!(manager.solve_position(), Ok((WinDrawLoss::Loss, 0)));
+
+        let manager = GameManager::new_game_with_config(10, 8, 4).unwrap();
+        assert!(manager.solve_position().is_err());
+    }
+
+    #[test]
+    fn cached_move_scores() {
+        let mut manager = GameManager::from_moves("4453").unwrap();
+        manager.try_generate_x_states(2000);
+
+        let scores = manager.get_move_scores();
+        assert!(manager.score_table.borrow().scores.len() > 0);
+        assert_eq!(manager.get_move_scores(), scores);
+
+        // The cached scores should match a fresh search
+        manager.score_table.borrow_mut().scores.clear();
+        assert_eq!(manager.get_move_scores(), scores);
+
+        // Growing the tree or moving throws the cache away
+        manager.try_generate_x_states(100);
+        assert_eq!(manager.score_table.borrow().scores.len(), 0);
+        manager.get_move_scores();
+        manager.make_move(3).unwrap();
+        assert_eq!(manager.score_table.borrow().scores.len(), 0);
+    }
+
+    #[test]
+    fn live_move_scores() {
+        // Player one has three in a row along the bottom
+        let mut manager = GameManager::from_moves("445566").unwrap();
+        assert_eq!(manager.get_live_move_scores().len(), 0);
+
+        manager.try_generate_x_states(100);
+        let scores = manager.get_live_move_scores();
+        assert_eq!(scores.len(), 7);
+        assert_eq!(scores[&6], isize::MAX);
+        assert_eq!(scores[&2], isize::MAX);
+
+        // The live scores should be the mini-max scores of the tree generated so far
+        let mut manager = GameManager::from_moves("4453").unwrap();
+        manager.try_generate_x_states(3000);
+        let scores = manager.get_live_move_scores();
+        for child in manager.root_state().children.iter() {
+            let column = manager.map_column(child.get_drop().unwrap());
+            assert_eq!(scores[&column], -minimax(manager.arena(), child.state));
+        }
+
+        // They stay up to date after a move
+        manager.make_move(3).unwrap();
+        manager.try_generate_x_states(3000);
+        let scores = manager.get_live_move_scores();
+        for child in manager.root_state().children.iter() {
+            let column = manager.map_column(child.get_drop().unwrap());
+            assert_eq!(scores[&column], minimax(manager.arena(), child.state));
+        }
+    }
+
+    #[test]
+    fn records() {
+        let mut manager = GameManager::new_game();
+        manager.make_move(3).unwrap();
+        manager.make_move(2).unwrap();
+        assert_eq!(manager.to_record().unwrap().moves_string(), "43");
+
+        // Only games whose moves are known, and that a record can describe, are recorded
+        let position = manager.get_position();
+        assert!(GameManager::start_from_position(position, false)
+            .to_record()
+            .is_err());
+        assert!(GameManager::new_game_with_rules(VariantRules::Misere)
+            .to_record()
+            .is_err());
+        assert!(GameManager::new_game_with_config(9, 7, 4)
+            .unwrap()
+            .to_record()
+            .is_err());
+        manager.set_power_ups(Some(PowerUpKind::Clear));
+        manager.clear_column(3).unwrap();
+        assert!(manager.to_record().is_err());
+    }
+
+    /// Scores a BoardState by searching its whole decision tree without any pruning.
+    fn minimax(arena: &Arena, id: NodeId) -> isize {
+        let board_state = &arena[id];
+        match board_state.is_game_over() {
+            GameOver::Tie => return 0,
+            GameOver::OneWins => return loss_in(0),
+            GameOver::TwoWins => return win_in(0),
+            GameOver::NoWin => (),
+        }
+
+        let scores = board_state
+            .children
+            .iter()
+            .map(|child| one_ply_back(minimax(arena, child.state)));
+        let score = if board_state.get_turn() {
+            scores.max()
+        } else {
+            scores.min()
+        };
+
+        score.unwrap_or_else(|| board_state.heuristic_score(arena.heuristic_weights()))
+    }
 }
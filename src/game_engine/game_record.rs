@@ -0,0 +1,1366 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts::BOARD_WIDTH,
+    game_engine::{
+        board::Board,
+        column_label::{split_moves, ColumnLabel},
+        game_manager::GameManager,
+        house_rules::{HouseRules, HOUSE_RULES_TAG},
+        mate_distance::{
+            format_mate_distance, is_forced_loss, is_forced_win, loss_in, parse_mate_distance,
+            win_in,
+        },
+        power_ups::uses_power_ups,
+        win_check::{is_game_over, GameOver},
+    },
+};
+
+/// How far the score of a move can fall short of the best move's score before the
+/// move is considered a blunder.
+pub const BLUNDER_THRESHOLD: isize = 300;
+
+/// The bytes every binary record starts with.
+const BINARY_MAGIC: &[u8; 2] = b"C4";
+/// The version of the binary format written by to_bytes.
+const BINARY_VERSION: u8 = 1;
+/// Fills the unused half of the last byte when a game has an odd number of moves.
+const MOVE_PADDING: u8 = 0xF;
+
+/// The name of the tag recording the version of the engine the game was played with.
+pub const ENGINE_VERSION_TAG: &str = "EngineVersion";
+/// The name of the tag recording the seed the computer's random choices were made with.
+pub const SEED_TAG: &str = "Seed";
+/// The name of the tag recording the settings the game was played with, as written by
+/// whichever front-end played it.
+pub const SETTINGS_TAG: &str = "Settings";
+/// The name of the tag recording the moves played before the record's first move, for
+/// games that started from a position other than the empty board.
+pub const SETUP_TAG: &str = "Setup";
+
+/// The engine's evaluation of a move that was played.
+///
+/// Scores are from the perspective of the player who made the move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MoveAnnotation {
+    /// The score of the move that was played.
+    pub score: isize,
+    /// The column the engine thought was best.
+    pub best_column: u8,
+    /// The score of the engine's preferred column.
+    pub best_score: isize,
+}
+
+impl MoveAnnotation {
+    /// Returns whether the move threw away a win, walked into a loss, or fell well short
+    /// of the best move.
+    pub fn is_blunder(&self) -> bool {
+        self.is_blunder_by(BLUNDER_THRESHOLD)
+    }
+
+    /// Returns whether the move threw away a win, walked into a loss, or fell more than
+    /// threshold short of the best move.
+    pub fn is_blunder_by(&self, threshold: isize) -> bool {
+        if is_forced_win(self.best_score) {
+            !is_forced_win(self.score)
+        } else if is_forced_loss(self.score) {
+            !is_forced_loss(self.best_score)
+        } else {
+            self.best_score.saturating_sub(self.score) > threshold
+        }
+    }
+}
+
+/// A single move in a recorded game.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RecordedMove {
+    /// The column the piece was dropped in, numbered from 0.
+    pub column: u8,
+    pub annotation: Option<MoveAnnotation>,
+    pub comment: Option<String>,
+    /// When the move was played, measured from the start of the game.
+    pub time: Option<Duration>,
+    /// Alternatives to this move, each a line of moves played instead of it.
+    pub variations: Vec<Vec<RecordedMove>>,
+}
+
+impl RecordedMove {
+    /// Creates an unannotated move in the given column, numbered from 0.
+    pub fn new(column: u8) -> RecordedMove {
+        RecordedMove {
+            column,
+            annotation: None,
+            comment: None,
+            time: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// Points at a move within a record's tree of variations.
+///
+/// The path starts with the index of a move in the main line. Every following pair of
+/// indices picks one of the previous move's variations, then a move within that variation.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct MovePath(Vec<usize>);
+
+impl MovePath {
+    /// Points at a move in the main line.
+    pub fn main_line(index: usize) -> MovePath {
+        MovePath(vec![index])
+    }
+
+    /// Creates a path from its steps, alternating between the index of a move and which of
+    /// its variations to enter, and ending with the index of a move.
+    pub fn from_steps(steps: Vec<usize>) -> MovePath {
+        assert!(steps.len() % 2 == 1, "Paths have to end at a move");
+
+        MovePath(steps)
+    }
+
+    /// Returns the path of the move after this one in the same line, which may not exist.
+    pub fn next(&self) -> MovePath {
+        let mut steps = self.0.clone();
+        *steps.last_mut().expect("Paths always point at a move") += 1;
+
+        MovePath(steps)
+    }
+
+    /// Returns the path of the move played before this one, or None for the first move.
+    pub fn previous(&self) -> Option<MovePath> {
+        let mut steps = self.0.clone();
+
+        loop {
+            let last = steps.len() - 1;
+            if steps[last] > 0 {
+                steps[last] -= 1;
+                return Some(MovePath(steps));
+            } else if last > 0 {
+                // The first move of a variation follows the move before the one it replaces
+                steps.truncate(last - 1);
+            } else {
+                return None;
+            }
+        }
+    }
+
+    /// Returns the index of the move within its line.
+    fn index(&self) -> usize {
+        *self.0.last().expect("Paths always point at a move")
+    }
+
+    /// Returns the steps leading to the line the move is in.
+    fn branches(&self) -> &[usize] {
+        &self.0[..self.0.len() - 1]
+    }
+
+    /// Returns how many variations deep the move is, 0 for the main line.
+    pub fn depth(&self) -> usize {
+        self.0.len() / 2
+    }
+}
+
+/// A record of a game, which can be saved to and loaded from a text file.
+///
+/// The text format has a line per tag, e.g. `[Red "Human"]`, followed by a line per move.
+/// Each move line has the column numbered from 1, an optional time it was played at, an
+/// optional engine annotation in braces, and an optional comment after a semicolon, e.g.
+/// `4 @12.5s {score 12, best 4 12} ; Red opens`. A move can be followed by variations played
+/// instead of it, each one's moves between a `(` line and a `)` line.
+///
+/// Records can also be stored as JSON, see to_json, or in a compact binary format, see
+/// to_bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct GameRecord {
+    /// Named details about the game, such as who was playing.
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl GameRecord {
+    /// Creates an unannotated record from a string of moves, e.g. "4445".
+    ///
+    /// Records only hold drops, so games where a power-up was spent clearing a column or
+    /// dropping an anvil can't be recorded.
+    pub fn from_moves(moves: &str) -> Result<GameRecord, String> {
+        GameManager::from_moves(moves)?;
+        if uses_power_ups(moves) {
+            return Err("Games where a power-up was spent can't be recorded".to_owned());
+        }
+
+        Ok(GameRecord {
+            tags: Vec::new(),
+            moves: split_moves(moves)
+                .iter()
+                .map(|written| RecordedMove::new(ColumnLabel::parse(written).unwrap().index()))
+                .collect(),
+        })
+    }
+
+    /// Returns the moves of the game as a string of columns numbered from 1, starting with
+    /// the setup's.
+    pub fn moves_string(&self) -> String {
+        let moves = ColumnLabel::move_string(self.moves.iter().map(|recorded| recorded.column));
+
+        format!("{}{}", self.setup(), moves)
+    }
+
+    /// Returns the moves played before the record's first move, as columns numbered from 1,
+    /// which are empty for games that started from the empty board.
+    pub fn setup(&self) -> &str {
+        self.get_tag(SETUP_TAG).unwrap_or("")
+    }
+
+    /// Returns how many moves were played before the record's first move.
+    pub fn first_ply(&self) -> usize {
+        self.setup().len()
+    }
+
+    /// Gets the value of a tag, if the record has it.
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of a tag, replacing any previous value.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_owned(),
+            None => self.tags.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    /// Returns the house rules the game was played under, which are the standard rules
+    /// if the record doesn't say.
+    pub fn house_rules(&self) -> Result<HouseRules, String> {
+        match self.get_tag(HOUSE_RULES_TAG) {
+            Some(value) => HouseRules::from_tag_value(value),
+            None => Ok(HouseRules::default()),
+        }
+    }
+
+    /// Records the house rules the game was played under.
+    pub fn set_house_rules(&mut self, rules: &HouseRules) {
+        self.set_tag(HOUSE_RULES_TAG, &rules.to_tag_value());
+    }
+
+    /// Records what it takes to play the game again: the version of the engine, the seed
+    /// the computer's random choices were made with, and the settings it was played with.
+    pub fn stamp(&mut self, seed: u64, settings: &str) {
+        self.set_tag(ENGINE_VERSION_TAG, env!("CARGO_PKG_VERSION"));
+        self.set_tag(SEED_TAG, &seed.to_string());
+        self.set_tag(SETTINGS_TAG, settings);
+    }
+
+    /// Returns the seed the computer's random choices were made with, if the record was
+    /// stamped with one.
+    pub fn seed(&self) -> Result<Option<u64>, String> {
+        self.get_tag(SEED_TAG)
+            .map(|seed| seed.parse().map_err(|_| format!("Invalid seed '{}'", seed)))
+            .transpose()
+    }
+
+    /// Returns a key for the position before the first move and after every move, which is
+    /// the same for mirrored positions.
+    ///
+    /// Stops early if the record contains a move into a full column.
+    pub fn position_keys(&self) -> Vec<u64> {
+        let Ok(mut board) = Board::from_moves(self.setup()) else {
+            return Vec::new();
+        };
+        let mut keys = vec![board.canonical_key()];
+
+        for (index, recorded) in self.moves.iter().enumerate() {
+            if board
+                .drop_piece(recorded.column, (self.first_ply() + index) % 2 == 1)
+                .is_err()
+            {
+                break;
+            }
+            keys.push(board.canonical_key());
+        }
+
+        keys
+    }
+
+    /// Parses a record from its text format.
+    ///
+    /// Fails if the text is malformed or the moves aren't legal.
+    pub fn parse(text: &str) -> Result<GameRecord, String> {
+        let mut record = GameRecord::default();
+        // The lines of the variations currently being read, innermost last
+        let mut open_lines: Vec<Vec<RecordedMove>> = Vec::new();
+
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: &str| format!("Line {}: {}", line_number + 1, message);
+            let current_line = open_lines.last_mut().unwrap_or(&mut record.moves);
+
+            if line.is_empty() {
+                continue;
+            } else if line.starts_with('[') {
+                let (name, value) = parse_tag(line).ok_or_else(|| error("malformed tag"))?;
+                record.tags.push((name, value));
+            } else if line == "(" {
+                if current_line.is_empty() {
+                    return Err(error("variation before any move"));
+                }
+                open_lines.push(Vec::new());
+            } else if line == ")" {
+                let variation = open_lines
+                    .pop()
+                    .ok_or_else(|| error("unopened variation"))?;
+                if variation.is_empty() {
+                    return Err(error("empty variation"));
+                }
+
+                let current_line = open_lines.last_mut().unwrap_or(&mut record.moves);
+                current_line
+                    .last_mut()
+                    .expect("Variations are only opened after a move")
+                    .variations
+                    .push(variation);
+            } else {
+                current_line.push(parse_move(line).map_err(|message| error(&message))?);
+            }
+        }
+
+        if !open_lines.is_empty() {
+            return Err("Unclosed variation".to_owned());
+        }
+
+        // Making sure the moves make up a legal game
+        record.check()?;
+
+        Ok(record)
+    }
+
+    /// Writes the record in its text format.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for (name, value) in self.tags.iter() {
+            text += &format!("[{} \"{}\"]\n", name, value.replace('"', "'"));
+        }
+        if !self.tags.is_empty() {
+            text += "\n";
+        }
+
+        write_line(&mut text, &self.moves, 0);
+
+        text
+    }
+
+    /// Reads a record from JSON, as written by to_json.
+    ///
+    /// Fails if the JSON is malformed or the moves aren't legal.
+    pub fn from_json(json: &str) -> Result<GameRecord, String> {
+        let json: JsonRecord = serde_json::from_str(json).map_err(|error| error.to_string())?;
+        let record = GameRecord {
+            tags: json
+                .tags
+                .into_iter()
+                .map(|tag| (tag.name, tag.value))
+                .collect(),
+            moves: json
+                .moves
+                .into_iter()
+                .map(JsonMove::into_recorded)
+                .collect::<Result<_, _>>()?,
+        };
+        record.check()?;
+
+        Ok(record)
+    }
+
+    /// Writes the record as JSON, with the same columns, scores and times as the text format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_connect_four::game_engine::game_record::GameRecord;
+    ///
+    /// let mut record = GameRecord::from_moves("44").unwrap();
+    /// record.set_tag("Red", "Human");
+    ///
+    /// let json = record.to_json();
+    /// assert!(json.contains("\"column\": 4"));
+    /// assert_eq!(GameRecord::from_json(&json).unwrap(), record);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let json = JsonRecord {
+            tags: self
+                .tags
+                .iter()
+                .map(|(name, value)| JsonTag {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+                .collect(),
+            moves: self.moves.iter().map(JsonMove::from_recorded).collect(),
+        };
+
+        serde_json::to_string_pretty(&json).expect("Records can always be written as JSON")
+    }
+
+    /// Makes sure the moves, including the setup and every variation, make up a legal game.
+    fn check(&self) -> Result<(), String> {
+        if uses_power_ups(self.setup()) {
+            return Err("Records can't set up games played with power-ups".to_owned());
+        }
+        Board::from_moves(self.setup())
+            .map_err(|error| format!("Illegal move in the setup: {:?}", error))?;
+
+        check_line(self.setup(), &self.moves)
+    }
+
+    /// Annotates every move, including the moves of variations, with the engine's
+    /// evaluation, letting the engine think for the given time at each position.
+    pub fn annotate(&mut self, movetime: Duration) -> Result<(), String> {
+        let setup = self.setup().to_owned();
+        annotate_line(&setup, &mut self.moves, movetime)
+    }
+
+    /// Returns the move the path points at.
+    pub fn get(&self, path: &MovePath) -> Option<&RecordedMove> {
+        self.line(path.branches())?.get(path.index())
+    }
+
+    /// Returns the moves leading up to and including the move the path points at, starting
+    /// with the setup's, as columns numbered from 1.
+    pub fn moves_to(&self, path: &MovePath) -> Option<String> {
+        let mut moves = Vec::new();
+        let mut line = &self.moves;
+
+        for step in path.0.chunks(2) {
+            let index = step[0];
+            moves.extend(line.get(..index)?.iter().map(|recorded| recorded.column));
+
+            match step.get(1) {
+                Some(variation) => line = line.get(index)?.variations.get(*variation)?,
+                None => moves.push(line.get(index)?.column),
+            }
+        }
+
+        Some(format!(
+            "{}{}",
+            self.setup(),
+            ColumnLabel::move_string(moves)
+        ))
+    }
+
+    /// Plays a move in the column after the move the path points at, or at the start of
+    /// the game if there's no path, returning the path of the new move.
+    ///
+    /// If the move was already played there, in the same line or one of its variations, the
+    /// existing move's path is returned. Otherwise the move continues the line if it's the
+    /// last move, or else starts a new variation. Fails if the move isn't legal.
+    pub fn add_move(&mut self, after: Option<&MovePath>, column: u8) -> Result<MovePath, String> {
+        let (mut steps, index) = match after {
+            Some(path) => (path.branches().to_vec(), path.index() + 1),
+            None => (Vec::new(), 0),
+        };
+        let moves_before = match after {
+            Some(path) => self
+                .moves_to(path)
+                .ok_or_else(|| "The move isn't in the game".to_owned())?,
+            None => self.setup().to_owned(),
+        };
+
+        let line = self
+            .line_mut(&steps)
+            .ok_or_else(|| "The move isn't in the game".to_owned())?;
+        if let Some(next) = line.get(index) {
+            if next.column == column {
+                steps.push(index);
+                return Ok(MovePath(steps));
+            }
+            if let Some(variation) = next
+                .variations
+                .iter()
+                .position(|variation| variation[0].column == column)
+            {
+                steps.extend([index, variation, 0]);
+                return Ok(MovePath(steps));
+            }
+        }
+
+        GameManager::from_moves(&format!("{}{}", moves_before, ColumnLabel::new(column)))?;
+
+        match line.get_mut(index) {
+            Some(next) => {
+                next.variations.push(vec![RecordedMove::new(column)]);
+                steps.extend([index, next.variations.len() - 1, 0]);
+            }
+            None => {
+                line.push(RecordedMove::new(column));
+                steps.push(index);
+            }
+        }
+
+        Ok(MovePath(steps))
+    }
+
+    /// Swaps the variation the move is in with the line it's an alternative to, returning
+    /// the move's new path.
+    ///
+    /// The rest of the line the variation replaced becomes a variation itself, in the
+    /// variation's place, so no moves are lost. Fails if the move is in the main line.
+    pub fn promote_variation(&mut self, path: &MovePath) -> Result<MovePath, String> {
+        let (mut steps, index, variation) = self.variation_of(path)?;
+
+        let line = self
+            .line_mut(&steps)
+            .expect("The variation's line was just found");
+        let replaced = &mut line[index];
+        let mut promoted = replaced.variations.remove(variation);
+        let mut alternatives = std::mem::take(&mut replaced.variations);
+
+        alternatives.insert(variation, line.split_off(index));
+        // Alternatives to the variation's first move are now alternatives to the same move
+        alternatives.append(&mut promoted[0].variations);
+        promoted[0].variations = alternatives;
+        line.append(&mut promoted);
+
+        steps.push(index + path.index());
+        Ok(MovePath(steps))
+    }
+
+    /// Removes the variation the move is in, along with any variations within it, returning
+    /// the path of the move it was an alternative to.
+    ///
+    /// Fails if the move is in the main line.
+    pub fn delete_variation(&mut self, path: &MovePath) -> Result<MovePath, String> {
+        let (mut steps, index, variation) = self.variation_of(path)?;
+
+        let line = self
+            .line_mut(&steps)
+            .expect("The variation's line was just found");
+        line[index].variations.remove(variation);
+
+        steps.push(index);
+        Ok(MovePath(steps))
+    }
+
+    /// Finds the variation the move is in, returning the steps to the line it branches from,
+    /// the index of the move it's an alternative to, and which of that move's variations it is.
+    fn variation_of(&self, path: &MovePath) -> Result<(Vec<usize>, usize, usize), String> {
+        if self.get(path).is_none() {
+            return Err("The move isn't in the game".to_owned());
+        }
+
+        match path.branches() {
+            [steps @ .., index, variation] => Ok((steps.to_vec(), *index, *variation)),
+            _ => Err("The move is already in the main line".to_owned()),
+        }
+    }
+
+    /// Returns the line of moves reached by following the steps of a path.
+    fn line(&self, steps: &[usize]) -> Option<&Vec<RecordedMove>> {
+        let mut line = &self.moves;
+        for step in steps.chunks(2) {
+            line = line.get(step[0])?.variations.get(*step.get(1)?)?;
+        }
+
+        Some(line)
+    }
+
+    /// Returns the line of moves reached by following the steps of a path, mutably.
+    fn line_mut(&mut self, steps: &[usize]) -> Option<&mut Vec<RecordedMove>> {
+        let mut line = &mut self.moves;
+        for step in steps.chunks(2) {
+            line = line.get_mut(step[0])?.variations.get_mut(*step.get(1)?)?;
+        }
+
+        Some(line)
+    }
+
+    /// Returns the indices of the moves that were annotated as blunders.
+    pub fn blunders(&self) -> Vec<usize> {
+        self.moves
+            .iter()
+            .enumerate()
+            .filter(|(_, recorded)| {
+                recorded
+                    .annotation
+                    .map(|annotation| annotation.is_blunder())
+                    .unwrap_or(false)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Writes the record in its compact binary format.
+    ///
+    /// The format is the magic bytes "C4", a version byte, the game's result as a GameOver,
+    /// a tag count followed by each tag's name and value (each prefixed by its length in
+    /// bytes), a move count, and finally the moves packed two to a byte, with the earlier
+    /// move in the high nibble. Annotations, comments and variations aren't stored, and overly long
+    /// tags are truncated to 255 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let result = match Board::from_moves(&self.moves_string()) {
+            Ok(board) => is_game_over(&board, (self.first_ply() + self.moves.len()) % 2 == 1),
+            Err(_) => GameOver::NoWin,
+        };
+
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.push(BINARY_VERSION);
+        bytes.push(result as u8);
+
+        bytes.push(self.tags.len().min(u8::MAX as usize) as u8);
+        for (name, value) in self.tags.iter().take(u8::MAX as usize) {
+            push_short_string(&mut bytes, name);
+            push_short_string(&mut bytes, value);
+        }
+
+        bytes.push(self.moves.len() as u8);
+        for pair in self.moves.chunks(2) {
+            let low = pair
+                .get(1)
+                .map(|recorded| recorded.column)
+                .unwrap_or(MOVE_PADDING);
+            bytes.push(pair[0].column << 4 | low);
+        }
+
+        bytes
+    }
+
+    /// Reads a record from its binary format, as written by to_bytes.
+    ///
+    /// Fails if the bytes are truncated or corrupt, were written by an unknown version of
+    /// the format, or the moves aren't legal.
+    pub fn from_bytes(bytes: &[u8]) -> Result<GameRecord, String> {
+        let mut reader = ByteReader { bytes };
+
+        if reader.take(BINARY_MAGIC.len())? != BINARY_MAGIC {
+            return Err("Not a binary game record".to_owned());
+        }
+        let version = reader.byte()?;
+        if version != BINARY_VERSION {
+            return Err(format!("Unsupported binary record version {}", version));
+        }
+        let result = reader.byte()?;
+        if result > GameOver::TwoWins as u8 {
+            return Err(format!("Invalid game result {}", result));
+        }
+
+        let mut record = GameRecord::default();
+
+        let tag_count = reader.byte()?;
+        for _ in 0..tag_count {
+            let name = reader.short_string()?;
+            let value = reader.short_string()?;
+            record.tags.push((name, value));
+        }
+
+        let move_count = reader.byte()? as usize;
+        let packed = reader.take(move_count.div_ceil(2))?;
+        for index in 0..move_count {
+            let byte = packed[index / 2];
+            let column = if index % 2 == 0 {
+                byte >> 4
+            } else {
+                byte & 0xF
+            };
+
+            if column >= BOARD_WIDTH {
+                return Err(format!("Invalid column {} in move {}", column, index + 1));
+            }
+            record.moves.push(RecordedMove::new(column));
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err("Unexpected bytes after the moves".to_owned());
+        }
+
+        record.check()?;
+        let board = Board::from_moves(&record.moves_string())
+            .map_err(|error| format!("Illegal move in the game: {:?}", error))?;
+        if is_game_over(&board, (record.first_ply() + move_count) % 2 == 1)
+            != GameOver::from(result)
+        {
+            return Err("The stored result doesn't match the moves".to_owned());
+        }
+
+        Ok(record)
+    }
+}
+
+/// Annotates a line of moves played after the given moves, along with its variations.
+fn annotate_line(
+    moves_before: &str,
+    line: &mut [RecordedMove],
+    movetime: Duration,
+) -> Result<(), String> {
+    let mut manager = GameManager::from_moves(moves_before)?;
+    let mut moves = moves_before.to_owned();
+
+    for recorded in line.iter_mut() {
+        for variation in recorded.variations.iter_mut() {
+            annotate_line(&moves, variation, movetime)?;
+        }
+
+        recorded.annotation = Some(evaluate_move(&mut manager, recorded.column, movetime)?);
+
+        manager.make_move(recorded.column)?;
+        moves += &ColumnLabel::new(recorded.column).to_string();
+    }
+
+    Ok(())
+}
+
+/// Has the engine evaluate playing the column in the manager's position, letting it think
+/// for the given time, without making the move.
+pub(crate) fn evaluate_move(
+    manager: &mut GameManager,
+    column: u8,
+    movetime: Duration,
+) -> Result<MoveAnnotation, String> {
+    manager.try_generate_for(movetime);
+
+    let move_scores = manager.get_move_scores();
+    let (best_column, best_score) = move_scores
+        .iter()
+        .map(|(column, score)| (*column, *score))
+        // Ties are broken in favor of the lowest column, to be deterministic
+        .max_by_key(|(column, score)| (*score, BOARD_WIDTH - column))
+        .ok_or_else(|| "No moves could be evaluated".to_owned())?;
+    let score = *move_scores
+        .get(&column)
+        .ok_or_else(|| format!("Column {} isn't playable", ColumnLabel::new(column)))?;
+
+    Ok(MoveAnnotation {
+        score,
+        best_column,
+        best_score,
+    })
+}
+
+/// Writes a line of moves in the text format, with each variation indented after the move
+/// it's an alternative to.
+fn write_line(text: &mut String, line: &[RecordedMove], depth: usize) {
+    let indent = "  ".repeat(depth);
+
+    for recorded in line {
+        *text += &indent;
+        *text += &ColumnLabel::new(recorded.column).to_string();
+
+        if let Some(time) = recorded.time {
+            *text += &format!(" @{}s", time.as_secs_f64());
+        }
+        if let Some(annotation) = recorded.annotation {
+            *text += &format!(
+                " {{score {}, best {} {}}}",
+                format_score(annotation.score),
+                ColumnLabel::new(annotation.best_column),
+                format_score(annotation.best_score)
+            );
+        }
+        if let Some(comment) = &recorded.comment {
+            *text += &format!(" ; {}", comment);
+        }
+        *text += "\n";
+
+        for variation in &recorded.variations {
+            *text += &format!("{}(\n", indent);
+            write_line(text, variation, depth + 1);
+            *text += &format!("{})\n", indent);
+        }
+    }
+}
+
+/// Makes sure a line of moves played after the given moves is legal, along with each of
+/// its variations.
+fn check_line(moves_before: &str, line: &[RecordedMove]) -> Result<(), String> {
+    let mut moves = moves_before.to_owned();
+
+    for recorded in line {
+        for variation in &recorded.variations {
+            check_line(&moves, variation)?;
+        }
+        moves += &ColumnLabel::new(recorded.column).to_string();
+    }
+
+    Board::from_moves(&moves)
+        .map(|_| ())
+        .map_err(|error| format!("Illegal move in the game: {:?}", error))
+}
+
+/// Pushes a string prefixed by its length, truncating it to fit in 255 bytes.
+fn push_short_string(bytes: &mut Vec<u8>, text: &str) {
+    let mut length = text.len().min(u8::MAX as usize);
+    while !text.is_char_boundary(length) {
+        length -= 1;
+    }
+
+    bytes.push(length as u8);
+    bytes.extend_from_slice(&text.as_bytes()[..length]);
+}
+
+/// Reads through the bytes of a binary record.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    /// Takes the next count bytes, failing if there aren't enough.
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < count {
+            return Err("The binary record ended unexpectedly".to_owned());
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    /// Takes the next byte.
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Takes a string prefixed by its length.
+    fn short_string(&mut self) -> Result<String, String> {
+        let length = self.byte()? as usize;
+
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| "A tag isn't valid text".to_owned())
+    }
+}
+
+/// Formats a score, writing forced wins and losses as how many moves they take, e.g. "W3".
+pub fn format_score(score: isize) -> String {
+    format_mate_distance(score).unwrap_or_else(|| score.to_string())
+}
+
+/// Parses a score written by format_score.
+///
+/// Older records wrote forced wins and losses as "win" and "loss", without saying how far
+/// away they were, which are read as the game ending straight away.
+pub fn parse_score(text: &str) -> Option<isize> {
+    match text {
+        "win" => Some(win_in(0)),
+        "loss" => Some(loss_in(0)),
+        text => parse_mate_distance(text).or_else(|| text.parse().ok()),
+    }
+}
+
+/// A record as it's written to JSON.
+#[derive(Serialize, Deserialize)]
+struct JsonRecord {
+    #[serde(default)]
+    tags: Vec<JsonTag>,
+    #[serde(default)]
+    moves: Vec<JsonMove>,
+}
+
+/// A tag as it's written to JSON, e.g. `{"name": "Red", "value": "Human"}`.
+#[derive(Serialize, Deserialize)]
+struct JsonTag {
+    name: String,
+    value: String,
+}
+
+/// A move as it's written to JSON, with its column numbered from 1 and its time in seconds.
+#[derive(Serialize, Deserialize)]
+struct JsonMove {
+    column: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    annotation: Option<JsonAnnotation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    variations: Vec<Vec<JsonMove>>,
+}
+
+/// An annotation as it's written to JSON, with its scores written as by format_score.
+#[derive(Serialize, Deserialize)]
+struct JsonAnnotation {
+    score: String,
+    best_column: u8,
+    best_score: String,
+}
+
+impl JsonMove {
+    /// Converts a recorded move, along with its variations, to be written to JSON.
+    fn from_recorded(recorded: &RecordedMove) -> JsonMove {
+        JsonMove {
+            column: recorded.column + 1,
+            time: recorded.time.map(|time| time.as_secs_f64()),
+            annotation: recorded.annotation.map(|annotation| JsonAnnotation {
+                score: format_score(annotation.score),
+                best_column: annotation.best_column + 1,
+                best_score: format_score(annotation.best_score),
+            }),
+            comment: recorded.comment.clone(),
+            variations: recorded
+                .variations
+                .iter()
+                .map(|variation| variation.iter().map(JsonMove::from_recorded).collect())
+                .collect(),
+        }
+    }
+
+    /// Converts a move read from JSON, along with its variations, back into a recorded move.
+    ///
+    /// Fails if a column, score or time is out of range.
+    fn into_recorded(self) -> Result<RecordedMove, String> {
+        let column = |column: u8| match column {
+            1..=BOARD_WIDTH => Ok(column - 1),
+            _ => Err(format!("'{}' isn't a column", column)),
+        };
+        let score = |score: &str| parse_score(score).ok_or(format!("'{}' isn't a score", score));
+
+        Ok(RecordedMove {
+            column: column(self.column)?,
+            annotation: match self.annotation {
+                Some(annotation) => Some(MoveAnnotation {
+                    score: score(&annotation.score)?,
+                    best_column: column(annotation.best_column)?,
+                    best_score: score(&annotation.best_score)?,
+                }),
+                None => None,
+            },
+            comment: self.comment,
+            time: match self.time {
+                Some(seconds) => Some(
+                    Duration::try_from_secs_f64(seconds)
+                        .map_err(|_| format!("'{}' isn't a time", seconds))?,
+                ),
+                None => None,
+            },
+            variations: self
+                .variations
+                .into_iter()
+                .map(|variation| {
+                    variation
+                        .into_iter()
+                        .map(JsonMove::into_recorded)
+                        .collect::<Result<_, _>>()
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Parses a tag line of the form `[Name "Value"]`.
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    Some((name.to_owned(), value.to_owned()))
+}
+
+/// Parses a move line of the form `4 @12.5s {score 12, best 4 12} ; comment`.
+fn parse_move(line: &str) -> Result<RecordedMove, String> {
+    let (line, comment) = match line.split_once(';') {
+        Some((line, comment)) => (line.trim(), Some(comment.trim().to_owned())),
+        None => (line, None),
+    };
+    let (column, annotation) = match line.split_once('{') {
+        Some((column, annotation)) => (column.trim(), Some(annotation)),
+        None => (line, None),
+    };
+    let (column, time) = match column.split_once('@') {
+        Some((column, time)) => (column.trim(), Some(parse_time(time.trim())?)),
+        None => (column, None),
+    };
+
+    let column = match column.parse::<u8>() {
+        Ok(column) if (1..=BOARD_WIDTH).contains(&column) => column - 1,
+        _ => return Err(format!("'{}' isn't a column", column)),
+    };
+
+    let annotation = match annotation {
+        Some(annotation) => Some(
+            parse_annotation(annotation)
+                .ok_or_else(|| format!("malformed annotation '{{{}'", annotation))?,
+        ),
+        None => None,
+    };
+
+    Ok(RecordedMove {
+        column,
+        annotation,
+        comment,
+        time,
+        variations: Vec::new(),
+    })
+}
+
+/// Parses the time a move was played at, in seconds, e.g. `12.5s`.
+fn parse_time(text: &str) -> Result<Duration, String> {
+    text.strip_suffix('s')
+        .and_then(|seconds| seconds.parse::<f64>().ok())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("'{}' isn't a time", text))
+}
+
+/// Parses the inside of an annotation, of the form `score 12, best 4 12}`.
+fn parse_annotation(text: &str) -> Option<MoveAnnotation> {
+    let text = text.trim().strip_suffix('}')?;
+    let (score, best) = text.split_once(',')?;
+
+    let score = parse_score(score.trim().strip_prefix("score ")?.trim())?;
+    let (best_column, best_score) = best.trim().strip_prefix("best ")?.trim().split_once(' ')?;
+    let best_column = best_column.parse::<u8>().ok()?.checked_sub(1)?;
+    let best_score = parse_score(best_score.trim())?;
+
+    Some(MoveAnnotation {
+        score,
+        best_column,
+        best_score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game_engine::{
+        game_record::{GameRecord, MoveAnnotation, MovePath, RecordedMove, SETUP_TAG},
+        house_rules::HouseRules,
+        mate_distance::loss_in,
+    };
+
+    #[test]
+    fn house_rules() {
+        let mut record = GameRecord::from_moves("44").unwrap();
+        assert_eq!(record.house_rules(), Ok(HouseRules::default()));
+
+        let rules = HouseRules {
+            announce_threats: true,
+            move_time_limit: Some(15),
+            time_control: None,
+        };
+        record.set_house_rules(&rules);
+
+        let parsed = GameRecord::parse(&record.to_text()).unwrap();
+        assert_eq!(parsed.house_rules(), Ok(rules));
+
+        record.set_tag("Rules", "Gravity off");
+        assert!(record.house_rules().is_err());
+    }
+
+    #[test]
+    fn stamp() {
+        let mut record = GameRecord::from_moves("44").unwrap();
+        assert_eq!(record.seed(), Ok(None));
+
+        record.stamp(
+            12345678901234567890,
+            "Red Human, Blue Computer, Hard, 3s delay",
+        );
+        let parsed = GameRecord::parse(&record.to_text()).unwrap();
+        assert_eq!(parsed.seed(), Ok(Some(12345678901234567890)));
+        assert_eq!(
+            parsed.get_tag("EngineVersion"),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(
+            parsed.get_tag("Settings"),
+            Some("Red Human, Blue Computer, Hard, 3s delay")
+        );
+
+        record.set_tag("Seed", "lucky");
+        assert!(record.seed().is_err());
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut record = GameRecord::from_moves("4453").unwrap();
+        record.set_tag("Red", "Human");
+        record.set_tag("Result", "Unfinished");
+
+        let bytes = record.to_bytes();
+        assert_eq!(
+            bytes,
+            b"C4\x01\x00\x02\x03Red\x05Human\x06Result\x0aUnfinished\x04\x33\x42"
+        );
+        assert_eq!(GameRecord::from_bytes(&bytes).unwrap(), record);
+
+        // An odd number of moves leaves padding in the last byte
+        let record = GameRecord::from_moves("1212121").unwrap();
+        assert!(GameRecord::from_moves("4445x4").is_err());
+        assert!(GameRecord::from_moves("4445a4").is_err());
+        let bytes = record.to_bytes();
+        assert_eq!(bytes, b"C4\x01\x02\x00\x07\x01\x01\x01\x0f");
+        assert_eq!(GameRecord::from_bytes(&bytes).unwrap(), record);
+
+        // Annotations and comments are dropped
+        let mut record = GameRecord::from_moves("4").unwrap();
+        record.moves[0].comment = Some("Center".to_owned());
+        let decoded = GameRecord::from_bytes(&record.to_bytes()).unwrap();
+        assert_eq!(decoded.moves[0].comment, None);
+        assert_eq!(decoded.moves[0].column, 3);
+    }
+
+    #[test]
+    fn binary_errors() {
+        let bytes = GameRecord::from_moves("4453").unwrap().to_bytes();
+
+        assert!(GameRecord::from_bytes(b"").is_err());
+        assert!(GameRecord::from_bytes(b"XX\x01\x00\x00\x00").is_err());
+        // Unknown version
+        assert!(GameRecord::from_bytes(b"C4\x02\x00\x00\x00").is_err());
+        // Truncated
+        assert!(GameRecord::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        // Trailing garbage
+        assert!(GameRecord::from_bytes(&[&bytes[..], &[0]].concat()).is_err());
+        // Result doesn't match the moves
+        assert!(GameRecord::from_bytes(b"C4\x01\x02\x00\x02\x33").is_err());
+        // Column out of range
+        assert!(GameRecord::from_bytes(b"C4\x01\x00\x00\x01\x7f").is_err());
+        // Overfilled column
+        assert!(GameRecord::from_bytes(b"C4\x01\x00\x00\x07\x00\x00\x00\x0f").is_err());
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut record = GameRecord::from_moves("4453").unwrap();
+        record.set_tag("Red", "Human");
+        record.set_tag("Blue", "Computer \"Hard\"");
+        record.moves[1].annotation = Some(MoveAnnotation {
+            score: -12,
+            best_column: 2,
+            best_score: isize::MAX,
+        });
+        record.moves[2].comment = Some("Red builds a central stack.".to_owned());
+
+        let text = record.to_text();
+        assert_eq!(
+            text,
+            "[Red \"Human\"]\n[Blue \"Computer 'Hard'\"]\n\n4\n4 {score -12, best 3 W1}\n5 ; Red builds a central stack.\n3\n"
+        );
+
+        let parsed = GameRecord::parse(&text).unwrap();
+        assert_eq!(parsed.get_tag("Blue"), Some("Computer 'Hard'"));
+        assert_eq!(parsed.moves, record.moves);
+        assert_eq!(parsed.moves_string(), "4453");
+    }
+
+    #[test]
+    fn times() {
+        let mut record = GameRecord::from_moves("44").unwrap();
+        record.moves[0].time = Some(Duration::from_millis(2500));
+        record.moves[1].time = Some(Duration::from_secs(7));
+
+        let text = record.to_text();
+        assert_eq!(text, "4 @2.5s\n4 @7s\n");
+        assert_eq!(GameRecord::parse(&text).unwrap(), record);
+
+        assert!(GameRecord::parse("4 @soon\n").is_err());
+        assert!(GameRecord::parse("4 @-1s\n").is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut record = GameRecord::from_moves("4453").unwrap();
+        record.set_tag("Red", "Human");
+        record.moves[0].time = Some(Duration::from_millis(1500));
+        record.moves[1].annotation = Some(MoveAnnotation {
+            score: -12,
+            best_column: 2,
+            best_score: isize::MAX,
+        });
+        record.moves[2].comment = Some("Red builds a central stack.".to_owned());
+        record.add_move(Some(&MovePath::main_line(2)), 0).unwrap();
+
+        let json = record.to_json();
+        assert!(json.contains("\"best_score\": \"W1\""));
+        assert_eq!(GameRecord::from_json(&json).unwrap(), record);
+
+        assert!(GameRecord::from_json("4453").is_err());
+        assert!(GameRecord::from_json(r#"{"moves": [{"column": 8}]}"#).is_err());
+        assert!(GameRecord::from_json(r#"{"moves": [{"column": 1}]}"#).is_ok());
+        let overfilled = format!("{{\"moves\": [{}]}}", ["{\"column\": 1}"; 7].join(", "));
+        assert!(GameRecord::from_json(&overfilled).is_err());
+    }
+
+    #[test]
+    fn setup() {
+        let mut record = GameRecord::from_moves("53").unwrap();
+        record.set_tag(SETUP_TAG, "44");
+        assert_eq!(record.first_ply(), 2);
+        assert_eq!(record.moves_string(), "4453");
+        assert_eq!(record.moves_to(&MovePath::main_line(0)).unwrap(), "445");
+        assert_eq!(
+            record.position_keys(),
+            GameRecord::from_moves("4453").unwrap().position_keys()[2..]
+        );
+
+        let parsed = GameRecord::parse(&record.to_text()).unwrap();
+        assert_eq!(parsed.moves_string(), "4453");
+
+        // The moves have to carry on legally from the setup
+        assert!(GameRecord::parse("[Setup \"111111\"]\n\n1\n").is_err());
+        assert!(GameRecord::parse("[Setup \"9\"]\n\n1\n").is_err());
+    }
+
+    #[test]
+    fn variations() {
+        let mut record = GameRecord::from_moves("445").unwrap();
+        let second = MovePath::main_line(1);
+
+        // Replaying the game's own move follows the main line
+        assert_eq!(
+            record.add_move(Some(&second), 4),
+            Ok(MovePath::main_line(2))
+        );
+
+        // A different move starts a variation, which is found again when replayed
+        let branch = record.add_move(Some(&second), 2).unwrap();
+        assert_eq!(branch.depth(), 1);
+        assert_eq!(record.add_move(Some(&second), 2), Ok(branch.clone()));
+        assert_eq!(record.moves_to(&branch).unwrap(), "443");
+
+        // Continuing the variation, and branching within it
+        let continued = record.add_move(Some(&branch), 3).unwrap();
+        assert_eq!(record.moves_to(&continued).unwrap(), "4434");
+        let nested = record.add_move(Some(&branch), 0).unwrap();
+        assert_eq!(nested.depth(), 2);
+        assert_eq!(record.moves_to(&nested).unwrap(), "4431");
+        assert_eq!(record.get(&nested).unwrap().column, 0);
+
+        // Stepping back out of the variations
+        assert_eq!(nested.previous(), Some(branch.clone()));
+        assert_eq!(branch.previous(), Some(second));
+        assert_eq!(MovePath::main_line(0).previous(), None);
+
+        // Extending the main line
+        assert_eq!(
+            record.add_move(Some(&MovePath::main_line(2)), 3),
+            Ok(MovePath::main_line(3))
+        );
+        assert_eq!(record.moves_string(), "4454");
+
+        let text = record.to_text();
+        assert_eq!(text, "4\n4\n5\n(\n  3\n  4\n  (\n    1\n  )\n)\n4\n");
+        assert_eq!(GameRecord::parse(&text).unwrap(), record);
+
+        // Illegal moves can't be added
+        let mut record = GameRecord::from_moves("111111").unwrap();
+        assert!(record.add_move(Some(&MovePath::main_line(5)), 0).is_err());
+    }
+
+    #[test]
+    fn promoting_and_deleting_variations() {
+        // The second move has two variations, and the first of them has one of its own
+        let mut record =
+            GameRecord::parse("4\n4\n5\n(\n  3\n  4\n  (\n    1\n  )\n)\n(\n  7\n)\n").unwrap();
+        let nested = MovePath::from_steps(vec![2, 0, 1, 0, 0]);
+        assert_eq!(record.moves_to(&nested).unwrap(), "4431");
+
+        // Promoting the nested variation swaps it with the move it replaced
+        let promoted = record.promote_variation(&nested).unwrap();
+        assert_eq!(promoted, MovePath::from_steps(vec![2, 0, 1]));
+        assert_eq!(record.moves_to(&promoted).unwrap(), "4431");
+        assert_eq!(
+            record.moves_to(&MovePath::from_steps(vec![2, 0, 1, 0, 0])),
+            Some("4434".to_owned())
+        );
+
+        // Then into the main line, keeping the old main line and the other variation
+        let promoted = record.promote_variation(&promoted).unwrap();
+        assert_eq!(promoted, MovePath::main_line(3));
+        assert_eq!(record.moves_string(), "4431");
+        assert_eq!(
+            record.to_text(),
+            "4\n4\n3\n(\n  5\n)\n(\n  7\n)\n1\n(\n  4\n)\n"
+        );
+        assert!(record.promote_variation(&promoted).is_err());
+
+        // Deleting a variation leaves the others in place
+        let deleted = record
+            .delete_variation(&MovePath::from_steps(vec![2, 1, 0]))
+            .unwrap();
+        assert_eq!(deleted, MovePath::main_line(2));
+        assert_eq!(record.to_text(), "4\n4\n3\n(\n  5\n)\n1\n(\n  4\n)\n");
+        assert!(record.delete_variation(&MovePath::main_line(0)).is_err());
+        assert!(record
+            .delete_variation(&MovePath::from_steps(vec![2, 5, 0]))
+            .is_err());
+    }
+
+    #[test]
+    fn variation_parse_errors() {
+        assert!(GameRecord::parse("(\n4\n)\n").is_err());
+        assert!(GameRecord::parse("4\n(\n").is_err());
+        assert!(GameRecord::parse("4\n)\n").is_err());
+        assert!(GameRecord::parse("4\n(\n)\n").is_err());
+        // The variation replaces the second move, so this fills the first column
+        assert!(GameRecord::parse("1\n1\n1\n1\n1\n1\n(\n  1\n)\n").is_ok());
+        assert!(GameRecord::parse("1\n1\n1\n1\n1\n1\n2\n(\n  1\n)\n").is_err());
+    }
+
+    #[test]
+    fn position_keys() {
+        let record = GameRecord::from_moves("4453").unwrap();
+        let mirrored_record = GameRecord::from_moves("4435").unwrap();
+
+        let keys = record.position_keys();
+        assert_eq!(keys.len(), 5);
+        assert_eq!(keys, mirrored_record.position_keys());
+        assert_ne!(keys[3], keys[4]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(GameRecord::parse("[Red \"Human\"\n4\n").is_err());
+        assert!(GameRecord::parse("8\n").is_err());
+        assert!(GameRecord::parse("4 {score 12}\n").is_err());
+        assert!(GameRecord::parse("1\n1\n1\n1\n1\n1\n1\n").is_err());
+        assert_eq!(
+            GameRecord::parse("4 {score loss, best 4 loss}\n")
+                .unwrap()
+                .moves,
+            vec![RecordedMove {
+                column: 3,
+                annotation: Some(MoveAnnotation {
+                    score: loss_in(0),
+                    best_column: 3,
+                    best_score: loss_in(0),
+                }),
+                comment: None,
+                time: None,
+                variations: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn blunders() {
+        let annotation = |score, best_score| MoveAnnotation {
+            score,
+            best_column: 0,
+            best_score,
+        };
+
+        assert!(annotation(0, isize::MAX).is_blunder());
+        assert!(annotation(isize::MIN, 0).is_blunder());
+        assert!(annotation(-500, 0).is_blunder());
+        assert!(!annotation(isize::MIN, isize::MIN).is_blunder());
+        assert!(!annotation(isize::MAX, isize::MAX).is_blunder());
+        assert!(!annotation(-50, 0).is_blunder());
+    }
+
+    #[test]
+    fn annotates_missed_win() {
+        // Red can win on column 1 at their fourth move, but plays column 7 instead
+        let mut record = GameRecord::from_moves("1212127").unwrap();
+        record.annotate(Duration::from_millis(10)).unwrap();
+
+        let missed_win = record.moves[6].annotation.unwrap();
+        assert_eq!(missed_win.best_column, 0);
+        assert_eq!(missed_win.best_score, isize::MAX);
+        assert!(record.blunders().contains(&6));
+    }
+}
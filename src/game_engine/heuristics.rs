@@ -1,12 +1,16 @@
 use crate::{
-    consts::NUMBER_TO_WIN,
-    game_engine::board::{Board, OutOfBounds},
+    consts::MAX_BOARD_WIDTH,
+    game_engine::{
+        board::{Board, OutOfBounds},
+        power_ups::PowerUps,
+        threats::{threats, Parity},
+    },
 };
 
 /// Used to define how much better an X in a row is to a X-1 in a row.
 pub const SCALING_HEURISTIC: isize = 10;
 
-/// A circular buffer used to iterate through all sets of four pieces
+/// A circular buffer used to iterate through all sets of window pieces
 ///  in a given iterator.
 ///
 /// It automatically tracks how many of each piece type are within the
@@ -15,7 +19,9 @@ struct CircleBuffer<T>
 where
     T: Iterator<Item = Result<bool, OutOfBounds>>,
 {
-    buffer: [Result<bool, OutOfBounds>; NUMBER_TO_WIN as usize],
+    buffer: [Result<bool, OutOfBounds>; MAX_BOARD_WIDTH as usize],
+    /// How many of the buffer's entries are used, the board's number_to_win.
+    window: usize,
     iter: T,
     index: usize,
     piece_counts: [u32; 2],
@@ -25,24 +31,26 @@ impl<T> CircleBuffer<T>
 where
     T: Iterator<Item = Result<bool, OutOfBounds>>,
 {
-    /// Creates a CircleBuffer using a board iterator.
-    fn new(mut iter: T) -> CircleBuffer<T> {
-        let mut buffer = [Err(OutOfBounds); NUMBER_TO_WIN as usize];
+    /// Creates a CircleBuffer using a board iterator, for lines of window pieces.
+    fn new(mut iter: T, window: u8) -> CircleBuffer<T> {
+        let window = window as usize;
+        let mut buffer = [Err(OutOfBounds); MAX_BOARD_WIDTH as usize];
         let mut piece_counts = [0; 2];
 
         // Initializing the buffer
         // We leave off the last entry, which will be filled when we call next for the first time
-        for i in 0..NUMBER_TO_WIN as usize {
+        for entry in buffer.iter_mut().take(window) {
             // If the iterator is less than
             let piece = iter.next().unwrap_or(Err(OutOfBounds));
             if let Ok(value) = piece {
                 piece_counts[value as usize] += 1;
             }
-            buffer[i] = piece;
+            *entry = piece;
         }
 
         CircleBuffer {
             buffer,
+            window,
             iter,
             index: 0,
             piece_counts,
@@ -71,7 +79,7 @@ where
 
             // Now we can officially overwrite the old piece and increment the index
             self.buffer[self.index] = piece;
-            self.index = (self.index + 1) % NUMBER_TO_WIN as usize;
+            self.index = (self.index + 1) % self.window;
 
             Some(())
         } else {
@@ -81,8 +89,21 @@ where
     }
 }
 
+/// Scores a window of pieces based on how many of each color it has.
+fn score_window([false_pieces, true_pieces]: [u32; 2]) -> isize {
+    if false_pieces > 0 && true_pieces == 0 {
+        // If false has pieces that aren't blocked from a connect four via true
+        -SCALING_HEURISTIC.pow(false_pieces - 1)
+    } else if true_pieces > 0 && false_pieces == 0 {
+        // If true has pieces that aren't blocked from a connect four via false
+        SCALING_HEURISTIC.pow(true_pieces - 1)
+    } else {
+        0
+    }
+}
+
 /// Scores the contents of a circle_buffer iterator based on how many X in a row it
-///  has for all X < NUMBER_TO_WIN.
+///  has for all X < number_to_win.
 fn score_circle_buffer<T>(mut circle_buffer: CircleBuffer<T>) -> isize
 where
     T: Iterator<Item = Result<bool, OutOfBounds>>,
@@ -91,16 +112,9 @@ where
 
     // This is essentially a do while loop
     // It is structured this way so that it always iterates at least once
-    // This important for circle buffers with < NUMBER_TO_WIN iterators
+    // This important for circle buffers with < number_to_win iterators
     loop {
-        let [false_pieces, true_pieces] = &circle_buffer.piece_counts;
-        if false_pieces > &0 && true_pieces == &0 {
-            // If false has pieces that aren't blocked from a connect four via true
-            score -= SCALING_HEURISTIC.pow(false_pieces - 1);
-        } else if true_pieces > &0 && false_pieces == &0 {
-            // If true has pieces that aren't blocked from a connect four via false
-            score += SCALING_HEURISTIC.pow(true_pieces - 1);
-        }
+        score += score_window(circle_buffer.piece_counts);
 
         if None == circle_buffer.next() {
             break;
@@ -115,67 +129,244 @@ where
 ///
 /// This is judged by finding how many X in a rows there are, with bigger Xs
 ///  leading to a higher score.
-fn score_by_closeness_to_win(board: &Board) -> isize {
+///
+/// Boards keep this score up to date as pieces are dropped, see Board::closeness_to_win.
+pub(crate) fn score_by_closeness_to_win(board: &Board) -> isize {
     let mut score = 0;
 
     // First we can calculate scores along the horizontal strips
     for iter in board.horizontal_strip_iter() {
-        score += score_circle_buffer(CircleBuffer::new(iter));
+        score += score_circle_buffer(CircleBuffer::new(iter, board.number_to_win()));
     }
 
     // Next we can calculate scores along the vertical strips
     for iter in board.vertical_strip_iter(true) {
-        score += score_circle_buffer(CircleBuffer::new(iter));
+        score += score_circle_buffer(CircleBuffer::new(iter, board.number_to_win()));
     }
 
     // Next we can calculate scores along the upward diagonal strips
     for iter in board.upward_diagonal_strip_iter(true) {
-        score += score_circle_buffer(CircleBuffer::new(iter));
+        score += score_circle_buffer(CircleBuffer::new(iter, board.number_to_win()));
     }
 
     // Next we can calculate scores along the downward diagonal strips
     for iter in board.downward_diagonal_strip_iter(true) {
-        score += score_circle_buffer(CircleBuffer::new(iter));
+        score += score_circle_buffer(CircleBuffer::new(iter, board.number_to_win()));
     }
 
     score
 }
 
-/// Heuristically determines how good a given board state is.
+/// Returns how much dropping a piece of the given color in the given space would change
+///  score_by_closeness_to_win, by rescoring only the lines of number_to_win spaces through it.
+///
+/// Returns None if the board is narrower or shorter than number_to_win, where
+///  score_by_closeness_to_win also scores the strips too short to hold a line.
+pub(crate) fn closeness_change(board: &Board, col: u8, row: u8, color: bool) -> Option<isize> {
+    let window = board.number_to_win() as usize;
+    if window > board.width() as usize || window > board.height() as usize {
+        return None;
+    }
+
+    let in_bounds = |col: i16, row: i16| {
+        (0..board.width() as i16).contains(&col) && (0..board.height() as i16).contains(&row)
+    };
+
+    let mut change = 0;
+    for (col_step, row_step) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+        // The spaces along the line close enough to share a window with the new piece
+        let mut line = [Err(OutOfBounds); 2 * MAX_BOARD_WIDTH as usize];
+        let mut length = 0;
+        let mut space = 0;
+        for step in 1 - window as i16..window as i16 {
+            let (line_col, line_row) = (col as i16 + step * col_step, row as i16 + step * row_step);
+            if in_bounds(line_col, line_row) {
+                if step == 0 {
+                    space = length;
+                }
+                line[length] = board.get_piece(line_col as u8, line_row as u8);
+                length += 1;
+            }
+        }
+
+        // Sliding a window along the line, rescoring each one that covers the space
+        let mut piece_counts = [0; 2];
+        for index in 0..length {
+            if let Ok(value) = line[index] {
+                piece_counts[value as usize] += 1;
+            }
+            if index >= window {
+                if let Ok(value) = line[index - window] {
+                    piece_counts[value as usize] -= 1;
+                }
+            }
+
+            if index + 1 >= window && index >= space && index < space + window {
+                let mut dropped_counts = piece_counts;
+                dropped_counts[color as usize] += 1;
+                change += score_window(dropped_counts) - score_window(piece_counts);
+            }
+        }
+    }
+
+    Some(change)
+}
+
+/// How much each term of the composite heuristic is worth, so they can be tuned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeuristicWeights {
+    /// Multiplies the score for X in a rows.
+    pub closeness_to_win: isize,
+    /// Each piece in the center column, or the two center columns of an even width board.
+    pub center_piece: isize,
+    /// Each threat on a row of the parity its player gets to fill once the board fills up.
+    pub good_parity_threat: isize,
+    /// Each threat on a row of the other parity.
+    pub bad_parity_threat: isize,
+    /// A player with two or more threats they could play into right now.
+    pub double_threat: isize,
+    /// A player who hasn't spent their power-up yet, in games played with them.
+    pub unspent_power_up: isize,
+}
+
+impl Default for HeuristicWeights {
+    fn default() -> Self {
+        HeuristicWeights {
+            closeness_to_win: 1,
+            center_piece: 3,
+            good_parity_threat: 40,
+            bad_parity_threat: 10,
+            double_threat: 500,
+            unspent_power_up: 60,
+        }
+    }
+}
+
+/// Scores how many pieces each player has in the center column(s), which take part in
+///  the most connect fours.
+fn score_center_control(board: &Board) -> isize {
+    let mut score = 0;
+
+    for column in [(board.width() - 1) / 2, board.width() / 2] {
+        for row in 0..board.get_height(column) {
+            match board.get_piece(column, row) {
+                Ok(true) => score += 1,
+                Ok(false) => score -= 1,
+                Err(_) => (),
+            }
+        }
+        // A single center column is only counted once
+        if board.width() % 2 == 1 {
+            break;
+        }
+    }
+
+    score
+}
+
+/// Scores each player's threats by parity, and whether they have a double threat.
+///
+/// The player to move gets to fill the odd rows once the board fills up if an even number
+///  of pieces have been played, and the even rows otherwise. That comes from pairing up the
+///  spaces left in each column, which only works out when every column has an even number of
+///  spaces, so on a board of odd height neither parity suits either player, and every threat
+///  is only worth as much as one of the bad parity.
+fn score_threats(board: &Board, turn: bool, weights: &HeuristicWeights) -> isize {
+    let parity_counts = board.height().is_multiple_of(2);
+    let pieces: u32 = (0..board.width())
+        .map(|column| board.get_height(column) as u32)
+        .sum();
+    let turn_parity = if pieces.is_multiple_of(2) {
+        Parity::Odd
+    } else {
+        Parity::Even
+    };
+
+    let mut score = 0;
+    for color in [false, true] {
+        let mut color_score = 0;
+        let mut playable = 0;
+
+        for threat in threats(board, color) {
+            color_score += if parity_counts && (threat.parity() == turn_parity) == (color == turn) {
+                weights.good_parity_threat
+            } else {
+                weights.bad_parity_threat
+            };
+            playable += threat.playable as usize;
+        }
+        if playable >= 2 {
+            color_score += weights.double_threat;
+        }
+
+        score += if color { color_score } else { -color_score };
+    }
+
+    score
+}
+
+/// Scores which players still have their power-up to spend, or 0 if the game isn't played
+///  with them.
+///
+/// A power-up is worth having whatever the rules, so unlike the rest of the heuristic this
+///  isn't turned around for misère games.
+pub fn score_power_ups(power_ups: Option<PowerUps>, weights: &HeuristicWeights) -> isize {
+    let Some(power_ups) = power_ups else {
+        return 0;
+    };
+
+    let unspent = power_ups.is_available(true) as isize - power_ups.is_available(false) as isize;
+    weights.unspent_power_up * unspent
+}
+
+/// Heuristically determines how good a given board state is, weighting each term of the
+///  heuristic by the given weights.
 ///
 /// Positive values are favorable to true, negative to false.
-pub fn how_good_is_board(board: &Board) -> isize {
-    // TODO: Find a heuristic that doesn't multi count 2 1 1 1 0 0 0 for 1s
-    score_by_closeness_to_win(board)
+pub fn weighted_score(board: &Board, turn: bool, weights: &HeuristicWeights) -> isize {
+    // TODO: Find a closeness to win score that doesn't multi count 2 1 1 1 0 0 0 for 1s
+    weights.closeness_to_win * board.closeness_to_win()
+        + weights.center_piece * score_center_control(board)
+        + score_threats(board, turn, weights)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::game_engine::{
-        board::{Board, OutOfBounds},
-        heuristics::score_circle_buffer,
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        consts::NUMBER_TO_WIN,
+        game_engine::{
+            board::{Board, OutOfBounds},
+            board_config::BoardConfig,
+            heuristics::score_circle_buffer,
+            power_ups::{play_moves, PowerUps},
+        },
     };
 
-    use super::{score_by_closeness_to_win, CircleBuffer};
+    use super::{
+        score_by_closeness_to_win, score_center_control, score_power_ups, weighted_score,
+        CircleBuffer, HeuristicWeights,
+    };
 
     const OOB: Result<bool, OutOfBounds> = Err(OutOfBounds);
 
     #[test]
     fn circle_buffer() {
         let iter = [].into_iter();
-        let mut cb = CircleBuffer::new(iter);
+        let mut cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(&cb.piece_counts, &[0, 0]);
         assert_eq!(cb.next(), None);
 
         let iter = [Ok(true), OOB, Ok(false)].into_iter();
-        let mut cb = CircleBuffer::new(iter);
+        let mut cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(&cb.piece_counts, &[1, 1]);
         assert_eq!(cb.next(), None);
 
         let iter = [Ok(true), Ok(true), OOB, OOB].into_iter();
-        let mut cb = CircleBuffer::new(iter);
+        let mut cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(&cb.piece_counts, &[0, 2]);
         assert_eq!(cb.next(), None);
@@ -196,7 +387,7 @@ mod tests {
             OOB,
         ]
         .into_iter();
-        let mut cb = CircleBuffer::new(iter);
+        let mut cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(&cb.piece_counts, &[1, 1]);
         assert_eq!(cb.next(), Some(()));
@@ -223,17 +414,17 @@ mod tests {
     #[test]
     fn scoring_circle_buffer() {
         let iter = [].into_iter();
-        let cb = CircleBuffer::new(iter);
+        let cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(score_circle_buffer(cb), 0);
 
         let iter = [Ok(true), OOB, Ok(false)].into_iter();
-        let cb = CircleBuffer::new(iter);
+        let cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(score_circle_buffer(cb), 0);
 
         let iter = [Ok(true), Ok(true), OOB, OOB].into_iter();
-        let cb = CircleBuffer::new(iter);
+        let cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(score_circle_buffer(cb), 10);
 
@@ -253,7 +444,7 @@ mod tests {
             OOB,
         ]
         .into_iter();
-        let cb = CircleBuffer::new(iter);
+        let cb = CircleBuffer::new(iter, NUMBER_TO_WIN);
 
         assert_eq!(score_circle_buffer(cb), -209);
     }
@@ -282,4 +473,122 @@ mod tests {
 
         assert_eq!(score_by_closeness_to_win(&board), 0);
     }
+
+    #[test]
+    fn incremental_closeness() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut configs = BoardConfig::VARIANTS.to_vec();
+        // Boards too narrow or short for a line, which are rescanned instead
+        configs.push(BoardConfig::new(4, 8, 5).unwrap());
+        configs.push(BoardConfig::new(10, 4, 6).unwrap());
+
+        for config in configs {
+            for _ in 0..20 {
+                let mut board = Board::with_config(config);
+                let mut color = false;
+
+                while !board.is_full() {
+                    let col = rng.gen_range(0..board.width());
+                    if board.drop_piece(col, color).is_ok() {
+                        color = !color;
+                        assert_eq!(board.closeness_to_win(), score_by_closeness_to_win(&board));
+                    }
+                }
+
+                board.clear_column(rng.gen_range(0..board.width())).unwrap();
+                assert_eq!(board.closeness_to_win(), score_by_closeness_to_win(&board));
+            }
+        }
+    }
+
+    #[test]
+    fn scoring_center_control() {
+        assert_eq!(score_center_control(&Board::from_moves("").unwrap()), 0);
+        assert_eq!(score_center_control(&Board::from_moves("4").unwrap()), -1);
+        assert_eq!(score_center_control(&Board::from_moves("44").unwrap()), 0);
+        assert_eq!(score_center_control(&Board::from_moves("414").unwrap()), -2);
+        assert_eq!(score_center_control(&Board::from_moves("1415").unwrap()), 1);
+    }
+
+    #[test]
+    fn scoring_threats() {
+        let none = HeuristicWeights {
+            closeness_to_win: 0,
+            center_piece: 0,
+            good_parity_threat: 0,
+            bad_parity_threat: 0,
+            double_threat: 0,
+            unspent_power_up: 0,
+        };
+
+        // The threat on top of the 4th column is on an even row, which is bad for the first
+        //  player, who gets the odd rows
+        let board = Board::from_moves("41414").unwrap();
+        let bad_parity = HeuristicWeights {
+            bad_parity_threat: 1,
+            ..none
+        };
+        let good_parity = HeuristicWeights {
+            good_parity_threat: 1,
+            ..none
+        };
+        assert_eq!(weighted_score(&board, true, &bad_parity), -1);
+        assert_eq!(weighted_score(&board, true, &good_parity), 0);
+
+        // If it were the first player's move with an odd number of pieces played, they would
+        //  get the even rows instead
+        assert_eq!(weighted_score(&board, false, &bad_parity), 0);
+        assert_eq!(weighted_score(&board, false, &good_parity), -1);
+
+        let board = Board::from_moves("41513").unwrap();
+        let double_threat = HeuristicWeights {
+            double_threat: 1,
+            ..none
+        };
+        assert_eq!(weighted_score(&board, true, &double_threat), -1);
+        assert_eq!(
+            weighted_score(&Board::from_moves("4151").unwrap(), false, &double_threat),
+            0
+        );
+    }
+
+    #[test]
+    fn scoring_threats_on_odd_heights() {
+        let weights = HeuristicWeights {
+            closeness_to_win: 0,
+            center_piece: 0,
+            good_parity_threat: 1,
+            bad_parity_threat: 10,
+            double_threat: 0,
+            unspent_power_up: 0,
+        };
+
+        // The same threat as on the standard board, on top of the 4th column, which no
+        //  parity suits on a board with 7 rows
+        let mut board = Board::with_config(BoardConfig::new(9, 7, 4).unwrap());
+        for (index, col) in [3, 0, 3, 0, 3].into_iter().enumerate() {
+            board.drop_piece(col, index % 2 == 1).unwrap();
+        }
+        assert_eq!(weighted_score(&board, true, &weights), -10);
+        assert_eq!(weighted_score(&board, false, &weights), -10);
+
+        // An even height board of the same width still weighs it by its parity
+        let mut board = Board::with_config(BoardConfig::new(9, 8, 4).unwrap());
+        for (index, col) in [3, 0, 3, 0, 3].into_iter().enumerate() {
+            board.drop_piece(col, index % 2 == 1).unwrap();
+        }
+        assert_eq!(weighted_score(&board, true, &weights), -10);
+        assert_eq!(weighted_score(&board, false, &weights), -1);
+    }
+
+    #[test]
+    fn scoring_power_ups() {
+        let weights = HeuristicWeights::default();
+        assert_eq!(score_power_ups(None, &weights), 0);
+
+        // Only player two has their clear left
+        let (_, _, power_ups) = play_moves("44x4").unwrap();
+        assert_eq!(score_power_ups(Some(power_ups), &weights), 60);
+        assert_eq!(score_power_ups(Some(PowerUps::default()), &weights), 0);
+    }
 }
@@ -0,0 +1,135 @@
+//! Explains suggested moves to human players, by the immediate threats they win with, block
+//! or create.
+
+use crate::game_engine::{
+    board::Board,
+    column_label::ColumnLabel,
+    threats::threats,
+    win_check::{winning_direction, Direction},
+};
+
+/// A move suggested to the player about to move, along with why it's good.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    /// The column to play, numbered from 0.
+    pub column: u8,
+    /// What playing the column does, e.g. "blocks the opponent's vertical threat at
+    /// column 3".
+    pub explanation: String,
+}
+
+/// Explains what dropping a piece in the column does for the player, looking only at the
+/// threats it wins with, blocks or creates. Columns are named from 1.
+///
+/// Winning is mentioned over blocking, and blocking over new threats.
+pub fn explain_move(board: &Board, player: bool, column: u8) -> String {
+    let label = ColumnLabel::new(column);
+
+    if let Some(direction) = winning_direction(board, player, column) {
+        return format!(
+            "wins with a {} connect four in column {}",
+            direction_name(direction),
+            label
+        );
+    }
+    if let Some(direction) = winning_direction(board, !player, column) {
+        return format!(
+            "blocks the opponent's {} threat at column {}",
+            direction_name(direction),
+            label
+        );
+    }
+
+    let mut after = board.clone();
+    if after.drop_piece(column, player).is_err() {
+        return format!("column {} is full", label);
+    }
+    if winning_direction(&after, !player, column).is_some() {
+        return format!(
+            "holds out the longest, though the opponent can win on top of it in column {}",
+            label
+        );
+    }
+
+    // Threats are compared by their spaces, since the move can make old threats playable
+    let old_threats: Vec<(u8, u8)> = threats(board, player)
+        .into_iter()
+        .map(|threat| (threat.column, threat.row))
+        .collect();
+    let new_threats: Vec<_> = threats(&after, player)
+        .into_iter()
+        .filter(|threat| !old_threats.contains(&(threat.column, threat.row)))
+        .collect();
+    let playable: Vec<u8> = new_threats
+        .iter()
+        .filter(|threat| threat.playable)
+        .map(|threat| threat.column)
+        .collect();
+
+    match (&playable[..], new_threats.first()) {
+        ([first, second, ..], _) => format!(
+            "creates a double threat in columns {} and {}",
+            ColumnLabel::new(*first),
+            ColumnLabel::new(*second)
+        ),
+        ([threat], _) => match winning_direction(&after, player, *threat) {
+            Some(direction) => format!(
+                "sets up a {} threat at column {}",
+                direction_name(direction),
+                ColumnLabel::new(*threat)
+            ),
+            None => format!("sets up a threat at column {}", ColumnLabel::new(*threat)),
+        },
+        ([], Some(threat)) => format!(
+            "sets up a threat at column {} for later in the game",
+            ColumnLabel::new(threat.column)
+        ),
+        ([], None) => "makes no immediate threats, but leaves the best position".to_owned(),
+    }
+}
+
+/// Returns the name of a connect four's direction, as used in explanations.
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Vertical => "vertical",
+        Direction::Horizontal => "horizontal",
+        Direction::Diagonal => "diagonal",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{board::Board, hint::explain_move};
+
+    #[test]
+    fn explains_tactics() {
+        let board = Board::from_moves("445566").unwrap();
+        assert_eq!(
+            explain_move(&board, false, 6),
+            "wins with a horizontal connect four in column 7"
+        );
+
+        let board = Board::from_moves("12121").unwrap();
+        assert_eq!(
+            explain_move(&board, true, 0),
+            "blocks the opponent's vertical threat at column 1"
+        );
+
+        let board = Board::from_moves("4141").unwrap();
+        assert_eq!(
+            explain_move(&board, false, 3),
+            "sets up a vertical threat at column 4"
+        );
+
+        let board = Board::from_moves("4151").unwrap();
+        assert_eq!(
+            explain_move(&board, false, 2),
+            "creates a double threat in columns 2 and 6"
+        );
+
+        assert_eq!(
+            explain_move(&Board::from_moves("").unwrap(), false, 3),
+            "makes no immediate threats, but leaves the best position"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::game_engine::clock::TimeControl;
+
+/// The name of the GameRecord tag that house rules are stored in.
+pub const HOUSE_RULES_TAG: &str = "Rules";
+
+/// The tag value used when no house rules are in play.
+const STANDARD_RULES: &str = "Standard";
+/// The tag value for the announce_threats rule.
+const ANNOUNCE_THREATS: &str = "Announce threats";
+/// The suffix of the tag value for the move_time_limit rule, e.g. "30s per move".
+const TIME_LIMIT_SUFFIX: &str = "s per move";
+/// The suffix of the tag value for the time_control rule, e.g. "3+2 clock".
+const TIME_CONTROL_SUFFIX: &str = " clock";
+
+/// Optional rules that players can agree to play with, on top of the standard rules.
+///
+/// None of these rules change which moves are legal on the board, so the engine ignores
+/// them. They're enforced by whoever is running the game.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct HouseRules {
+    /// A player has to announce a move that threatens to win on their next turn, and an
+    /// unannounced threat isn't allowed to be played.
+    pub announce_threats: bool,
+    /// How many seconds a player has to make each move before they lose on time.
+    pub move_time_limit: Option<u32>,
+    /// How much time each player has for the whole game before they lose on time.
+    pub time_control: Option<TimeControl>,
+}
+
+impl HouseRules {
+    /// Returns whether players can lose on time.
+    pub fn is_timed(&self) -> bool {
+        self.move_time_limit.is_some() || self.time_control.is_some()
+    }
+
+    /// Returns whether these are just the standard rules.
+    pub fn is_standard(&self) -> bool {
+        *self == HouseRules::default()
+    }
+
+    /// Describes the rules in the form stored in a GameRecord's tag,
+    /// e.g. "Announce threats, 30s per move, 3+2 clock".
+    pub fn to_tag_value(&self) -> String {
+        let mut rules = Vec::new();
+
+        if self.announce_threats {
+            rules.push(ANNOUNCE_THREATS.to_owned());
+        }
+        if let Some(seconds) = self.move_time_limit {
+            rules.push(format!("{}{}", seconds, TIME_LIMIT_SUFFIX));
+        }
+        if let Some(time_control) = self.time_control {
+            rules.push(format!("{}{}", time_control, TIME_CONTROL_SUFFIX));
+        }
+
+        if rules.is_empty() {
+            STANDARD_RULES.to_owned()
+        } else {
+            rules.join(", ")
+        }
+    }
+
+    /// Parses rules written by to_tag_value.
+    pub fn from_tag_value(value: &str) -> Result<HouseRules, String> {
+        let mut rules = HouseRules::default();
+
+        for rule in value.split(',').map(|rule| rule.trim()) {
+            if rule == STANDARD_RULES || rule.is_empty() {
+                continue;
+            } else if rule == ANNOUNCE_THREATS {
+                rules.announce_threats = true;
+            } else if let Some(seconds) = rule.strip_suffix(TIME_LIMIT_SUFFIX) {
+                let seconds = seconds
+                    .parse()
+                    .map_err(|_| format!("'{}' isn't a time limit", rule))?;
+                rules.move_time_limit = Some(seconds);
+            } else if let Some(time_control) = rule.strip_suffix(TIME_CONTROL_SUFFIX) {
+                rules.time_control = Some(TimeControl::parse(time_control)?);
+            } else {
+                return Err(format!("Unknown house rule '{}'", rule));
+            }
+        }
+
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{clock::TimeControl, house_rules::HouseRules};
+
+    #[test]
+    fn tag_round_trip() {
+        let standard = HouseRules::default();
+        assert!(standard.is_standard());
+        assert_eq!(standard.to_tag_value(), "Standard");
+        assert_eq!(HouseRules::from_tag_value("Standard"), Ok(standard));
+
+        let rules = HouseRules {
+            announce_threats: true,
+            move_time_limit: Some(30),
+            time_control: None,
+        };
+        assert!(!rules.is_standard());
+        assert_eq!(rules.to_tag_value(), "Announce threats, 30s per move");
+        assert_eq!(
+            HouseRules::from_tag_value("Announce threats, 30s per move"),
+            Ok(rules)
+        );
+
+        let clock = HouseRules {
+            time_control: Some(TimeControl {
+                minutes: 3,
+                increment: 2,
+            }),
+            ..HouseRules::default()
+        };
+        assert!(clock.is_timed());
+        assert_eq!(clock.to_tag_value(), "3+2 clock");
+        assert_eq!(HouseRules::from_tag_value("3+2 clock"), Ok(clock));
+        assert!(HouseRules::from_tag_value("3+two clock").is_err());
+
+        assert!(HouseRules::from_tag_value("Pop out").is_err());
+        assert!(HouseRules::from_tag_value("fast per move").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::{
+        board::Board,
+        win_check::{winning_columns, winning_direction, Direction},
+    },
+};
+
+/// How likely the player is to skip looking for wins and threats altogether on a move.
+const ONE_PLY_OVERSIGHT_CHANCE: f64 = 0.1;
+
+/// How much the player is drawn to each column, favoring the center like most people do.
+const COLUMN_PREFERENCE: [usize; BOARD_WIDTH as usize] = [1, 2, 3, 4, 3, 2, 1];
+
+/// Returns how likely the player is to notice a connect four in the given direction.
+fn notice_chance(direction: Direction) -> f64 {
+    match direction {
+        Direction::Vertical => 0.95,
+        Direction::Horizontal => 0.8,
+        Direction::Diagonal => 0.5,
+    }
+}
+
+/// Chooses a move the way a beginner might, using the engine's move scores as a guide.
+///
+/// Better scoring moves and central columns are more likely to be picked. The player takes
+/// wins and blocks threats they notice, but diagonal lines are missed far more often than
+/// vertical ones, and now and then they don't look for threats at all.
+///
+/// The position is given as array[row][col], and turn is false for player one and true for
+/// player two. Returns None if there are no moves to choose from.
+pub fn choose_human_like_move(
+    position: [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+    turn: bool,
+    move_scores: &HashMap<u8, isize>,
+    rng: &mut impl Rng,
+) -> Option<u8> {
+    let board = Board::from_arrays(position);
+
+    // Highest scoring moves at the end
+    let mut sorted_moves = move_scores
+        .iter()
+        .map(|(column, score)| (*score, *column))
+        .collect::<Vec<(isize, u8)>>();
+    sorted_moves.sort();
+
+    if !rng.gen_bool(ONE_PLY_OVERSIGHT_CHANCE) {
+        // Taking a win if one is spotted
+        for (_, column) in sorted_moves.iter().rev() {
+            if notices_win(&board, turn, *column, rng) {
+                return Some(*column);
+            }
+        }
+
+        // Blocking the opponent's win if it's spotted
+        for column in winning_columns(&board, !turn) {
+            if move_scores.contains_key(&column) && notices_win(&board, !turn, column, rng) {
+                return Some(column);
+            }
+        }
+
+        // Steering clear of moves that are spotted letting the opponent win on top of them
+        let safe_moves: Vec<(isize, u8)> = sorted_moves
+            .iter()
+            .filter(|(_, column)| {
+                let mut new_board = board.clone();
+                new_board.drop_piece(*column, turn).is_err()
+                    || !notices_win(&new_board, !turn, *column, rng)
+            })
+            .copied()
+            .collect();
+        if !safe_moves.is_empty() {
+            sorted_moves = safe_moves;
+        }
+    }
+
+    let weighted_moves: Vec<(usize, u8)> = sorted_moves
+        .into_iter()
+        .enumerate()
+        .map(|(index, (_, column))| ((index + 1) * COLUMN_PREFERENCE[column as usize], column))
+        .collect();
+
+    weighted_moves
+        .choose_weighted(rng, |(weight, _)| *weight)
+        .ok()
+        .map(|(_, column)| *column)
+}
+
+/// Returns whether the player notices that the given color could win by playing the column.
+fn notices_win(board: &Board, color: bool, column: u8, rng: &mut impl Rng) -> bool {
+    winning_direction(board, color, column)
+        .map(|direction| rng.gen_bool(notice_chance(direction)))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::game_engine::{game_manager::GameManager, human_policy::choose_human_like_move};
+
+    /// Counts how often each column is chosen over many seeded games from the position.
+    fn choice_counts(moves: &str, move_scores: &HashMap<u8, isize>) -> HashMap<u8, usize> {
+        let manager = GameManager::from_moves(moves).unwrap();
+        let mut counts = HashMap::new();
+
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let column = choose_human_like_move(
+                manager.get_position(),
+                manager.get_turn(),
+                move_scores,
+                &mut rng,
+            )
+            .unwrap();
+
+            *counts.entry(column).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn usually_takes_obvious_wins() {
+        let move_scores = (0..7)
+            .map(|column| (column, if column == 0 { isize::MAX } else { 0 }))
+            .collect();
+        let counts = choice_counts("121212", &move_scores);
+
+        assert!(counts[&0] > 160);
+    }
+
+    #[test]
+    fn misses_diagonal_threats_more_often() {
+        // Blue threatens to win vertically in the last column
+        let move_scores = (0..7)
+            .map(|column| (column, if column == 6 { 0 } else { isize::MIN }))
+            .collect();
+        let vertical_blocks = choice_counts("172717", &move_scores)[&6];
+
+        // Blue threatens to win diagonally in the first column
+        let move_scores = (0..7)
+            .map(|column| (column, if column == 0 { 0 } else { isize::MIN }))
+            .collect();
+        let diagonal_blocks = choice_counts("217422163315", &move_scores)[&0];
+
+        assert!(vertical_blocks > diagonal_blocks);
+        assert!(vertical_blocks > 150);
+    }
+
+    #[test]
+    fn no_moves() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let position = GameManager::new_game().get_position();
+
+        assert_eq!(
+            choose_human_like_move(position, false, &HashMap::new(), &mut rng),
+            None
+        );
+    }
+}
@@ -1,13 +1,11 @@
-use std::{
-    cell::RefCell,
-    cmp::max,
-    collections::HashMap,
-    rc::{Rc, Weak},
-};
+use std::{cmp::min, mem::size_of, time::Instant};
 
 use crate::{
     game_engine::{
-        board_state::BoardState, transposition::TranspositionTable, win_check::GameOver,
+        arena::{Arena, NodeId},
+        board_state::BoardState,
+        search_stats::SearchStats,
+        win_check::GameOver,
     },
     log::PerfTimer,
 };
@@ -15,21 +13,28 @@ use crate::{
 /// Iterator used to generate a BoardState decision tree. Each iteration will
 /// return how many new board states were generated.
 ///
+/// The generator owns the Arena the decision tree is stored in.
+///
 /// Iteration will stop when the decision tree is complete.
 #[derive(Debug)]
 pub struct LayerGenerator {
     // TODO: Could these be HashSets? Prevent duplicate calls to generate_children
-    generation_1: Vec<Rc<RefCell<BoardState>>>,
-    generation_2: Vec<Rc<RefCell<BoardState>>>,
+    generation_1: Vec<NodeId>,
+    generation_2: Vec<NodeId>,
     generation_1_is_new: bool,
-    table: TranspositionTable<Weak<RefCell<BoardState>>>,
+    arena: Arena,
+    /// The depth of the deepest BoardState generated so far.
+    deepest: u8,
+    /// How generation has gone since the stats were last reset. Lookups and the frontier
+    ///  are filled in when the stats are asked for.
+    stats: SearchStats,
 }
 
 impl LayerGenerator {
     /// Gets the newest of the two stored generations.
     ///
     /// The new generation will be the one at the bottom of the decision tree.
-    fn get_new_generation(&mut self) -> &mut Vec<Rc<RefCell<BoardState>>> {
+    fn get_new_generation(&mut self) -> &mut Vec<NodeId> {
         if self.generation_1_is_new {
             &mut self.generation_1
         } else {
@@ -40,7 +45,7 @@ impl LayerGenerator {
     /// Gets the previous of the two stored generations.
     ///
     /// The previous generation will be the next-to-last layer of the decision tree.
-    fn get_previous_generation(&mut self) -> &mut Vec<Rc<RefCell<BoardState>>> {
+    fn get_previous_generation(&mut self) -> &mut Vec<NodeId> {
         if self.generation_1_is_new {
             &mut self.generation_2
         } else {
@@ -48,43 +53,125 @@ impl LayerGenerator {
         }
     }
 
-    /// Gets the sizes of the internal buffers.
-    pub fn buffer_size(&self) -> usize {
-        self.generation_1.len() + self.generation_2.len()
+    /// Returns how many bytes the generator has allocated for its buffers and arena.
+    pub fn heap_size(&self) -> usize {
+        let buffers = self.generation_1.capacity() + self.generation_2.capacity();
+
+        buffers * size_of::<NodeId>() + self.arena.heap_size()
+    }
+
+    /// Returns the BoardStates waiting to have their children generated, from both
+    /// generations.
+    #[cfg(test)]
+    pub fn frontier(&self) -> impl Iterator<Item = &BoardState> + '_ {
+        self.generation_1
+            .iter()
+            .chain(self.generation_2.iter())
+            .map(|id| &self.arena[*id])
+    }
+
+    /// Returns a reference to the Arena the decision tree is stored in.
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    /// Returns the depth of the next BoardState to have its children generated, or None once
+    ///  the decision tree is complete.
+    pub fn next_depth(&self) -> Option<u8> {
+        let (previous_generation, new_generation) = if self.generation_1_is_new {
+            (&self.generation_2, &self.generation_1)
+        } else {
+            (&self.generation_1, &self.generation_2)
+        };
+
+        previous_generation
+            .last()
+            .or(new_generation.last())
+            .map(|id| self.arena[*id].get_depth())
+    }
+
+    /// Returns the depth of the deepest BoardState generated so far, without having to look
+    ///  through the whole arena for it.
+    pub fn deepest_depth(&self) -> u8 {
+        self.deepest
     }
 
-    /// Returns a reference to the TranspositionTable used to generate BoardStates.
-    pub fn table_ref(&self) -> &TranspositionTable<Weak<RefCell<BoardState>>> {
-        &self.table
+    /// Finds the depth of the deepest BoardState stored in the arena.
+    fn find_deepest(arena: &Arena) -> u8 {
+        arena
+            .ids()
+            .map(|id| arena[id].get_depth())
+            .max()
+            .unwrap_or_default()
     }
 
-    /// Constructs a new LayerGenerator for a given BoardState.
-    pub fn new(table: TranspositionTable<Weak<RefCell<BoardState>>>) -> LayerGenerator {
-        assert_ne!(table.len(), 0);
+    /// Constructs a new LayerGenerator for the decision tree stored in the arena.
+    pub fn new(arena: Arena) -> LayerGenerator {
+        assert_ne!(arena.len(), 0);
 
-        let (previous_generation, new_generation) = LayerGenerator::get_bottom_two_layers(&table);
+        let (previous_generation, new_generation) =
+            LayerGenerator::get_bottom_two_layers(&arena, arena.ids());
 
         LayerGenerator {
             generation_1: previous_generation,
             generation_2: new_generation,
             generation_1_is_new: false,
-            table,
+            deepest: LayerGenerator::find_deepest(&arena),
+            arena,
+            stats: SearchStats::default(),
         }
     }
 
-    /// Restarts the LayerGeneration process, rescanning the tranposition table.
-    pub fn restart(&mut self) {
-        let timer = PerfTimer::start("Restart Layer Generator [Clean]");
-        // In order to determine which leaves aren't in use we need to remove our own
-        //  references to them.
+    /// Returns how generation has gone since the stats were last reset, leaving the
+    ///  evaluation time for the caller to fill in.
+    pub fn stats(&self) -> SearchStats {
+        let (lookups, hits) = self.arena.lookup_counts();
+
+        SearchStats {
+            lookups,
+            hits,
+            frontier: self.generation_1.len() + self.generation_2.len(),
+            ..self.stats
+        }
+    }
+
+    /// Starts the stats over, e.g. after a move.
+    pub fn reset_stats(&mut self) {
+        self.stats = SearchStats::default();
+        self.arena.reset_lookup_counts();
+    }
+
+    /// Restarts the LayerGeneration process from a new root, trimming every BoardState that
+    ///  can't be reached from it and rescanning the rest. The stats are started over.
+    pub fn restart(&mut self, root: NodeId) {
+        self.reset_stats();
+        let timer = PerfTimer::start("Restart Layer Generator [Trim]");
         self.generation_1.clear();
         self.generation_2.clear();
-        self.table.clean();
+        self.arena.trim(root);
+        self.deepest = LayerGenerator::find_deepest(&self.arena);
         timer.stop();
 
         let timer = PerfTimer::start("Restart Layer Generator [Get Bottom Two Layers]");
         let (previous_generation, new_generation) =
-            LayerGenerator::get_bottom_two_layers(&self.table);
+            LayerGenerator::get_bottom_two_layers(&self.arena, self.arena.ids());
+        timer.stop();
+
+        self.generation_1 = previous_generation;
+        self.generation_2 = new_generation;
+        self.generation_1_is_new = false;
+    }
+
+    /// Carries on generating from the BoardStates beneath the given one alone, e.g. to deepen
+    ///  the line the game is expected to take, without trimming anything.
+    ///
+    /// Focusing back on the root goes back to generating the whole tree.
+    pub fn focus(&mut self, root: NodeId) {
+        let timer = PerfTimer::start("Focus Layer Generator");
+        let (previous_generation, new_generation) = LayerGenerator::get_bottom_two_layers(
+            &self.arena,
+            self.arena.descendants(root).into_iter(),
+        );
         timer.stop();
 
         self.generation_1 = previous_generation;
@@ -92,57 +179,39 @@ impl LayerGenerator {
         self.generation_1_is_new = false;
     }
 
-    /// Finds the BoardStates at the bottom of the decision tree and returns
-    ///  vectors to them.
+    /// Finds the BoardStates at the bottom of the decision tree, among the given ids, and
+    ///  returns vectors to them.
     ///
-    /// Helper function for use in creating a new LayerGenerator.
+    /// Helper function for use in creating a new LayerGenerator. The shallowest leaves are
+    ///  generated from first, so a tree that was searched deeper down one branch (e.g. while
+    ///  pondering) fills back in before the deep branch carries on.
     ///
     /// Returns a tuple of (previous_generation, new_generation).
     fn get_bottom_two_layers(
-        table: &TranspositionTable<Weak<RefCell<BoardState>>>,
-    ) -> (Vec<Rc<RefCell<BoardState>>>, Vec<Rc<RefCell<BoardState>>>) {
-        let mut depth_sorted_nodes: HashMap<u8, Vec<Rc<RefCell<BoardState>>>> = HashMap::new();
-        let mut max_depth = 0;
-
-        for (_, weak_ref) in table.iter() {
-            if let Some(board_state) = weak_ref.upgrade() {
-                if board_state.borrow().children.len() > 0
-                    || board_state.borrow().is_game_over() != GameOver::NoWin
-                {
-                    continue;
-                }
-
-                let current_depth = board_state.borrow().get_depth();
-                max_depth = max(current_depth, max_depth);
-
-                if current_depth == max_depth || current_depth + 1 == max_depth {
-                    if let Some(depth_array) = depth_sorted_nodes.get_mut(&current_depth) {
-                        depth_array.push(board_state);
-                    } else {
-                        depth_sorted_nodes.insert(current_depth, vec![board_state]);
-                    }
-                }
+        arena: &Arena,
+        ids: impl Iterator<Item = NodeId>,
+    ) -> (Vec<NodeId>, Vec<NodeId>) {
+        let mut leaves: Vec<(u8, NodeId)> = Vec::new();
+        let mut min_depth = u8::MAX;
+
+        for id in ids {
+            let board_state = &arena[id];
+            if !board_state.children.is_empty() || board_state.is_game_over() != GameOver::NoWin {
+                continue;
             }
-        }
 
-        let mut previous_generation;
-        let mut new_generation;
-        if max_depth > 0 {
-            previous_generation = depth_sorted_nodes
-                .remove(&(max_depth - 1))
-                .unwrap_or(Vec::new());
-            new_generation = depth_sorted_nodes.remove(&max_depth).unwrap();
-
-            if previous_generation.len() == 0 {
-                previous_generation = new_generation;
-                new_generation = Vec::new();
-            }
-        } else {
-            // Max Depth = 0 when starting a new game or at the end of a game
-            previous_generation = depth_sorted_nodes.remove(&max_depth).unwrap_or(Vec::new());
-            new_generation = Vec::new();
+            let current_depth = board_state.get_depth();
+            min_depth = min(current_depth, min_depth);
+            leaves.push((current_depth, id));
         }
 
+        let (previous_generation, new_generation): (Vec<_>, Vec<_>) = leaves
+            .into_iter()
+            .partition(|(depth, _)| *depth == min_depth);
+
+        let previous_generation = previous_generation.into_iter().map(|(_, id)| id).collect();
+        let new_generation = new_generation.into_iter().map(|(_, id)| id).collect();
+
         (previous_generation, new_generation)
     }
 }
@@ -153,11 +222,25 @@ impl Iterator for LayerGenerator {
     fn next(&mut self) -> Option<Self::Item> {
         // If there are still BoardStates in the previous generation, we can
         //  continue computing from there
-        if let Some(board_state) = self.get_previous_generation().pop() {
-            let generated_children = board_state.borrow_mut().generate_children(&mut self.table);
+        if let Some(id) = self.get_previous_generation().pop() {
+            let start = Instant::now();
+            let (lookups, _) = self.arena.lookup_counts();
+            let generated_children = BoardState::generate_children(&mut self.arena, id);
             let num_generated = generated_children.len();
+            for child in generated_children.iter() {
+                self.deepest = self.deepest.max(self.arena[*child].get_depth());
+            }
+
+            if num_generated > 0 {
+                BoardState::backpropagate(&mut self.arena, id);
+            }
 
             self.get_new_generation().extend(generated_children);
+            // States whose children were already generated don't look anything up
+            if self.arena.lookup_counts().0 > lookups {
+                self.stats.expanded += 1;
+            }
+            self.stats.generation_time += start.elapsed();
 
             Some(num_generated)
         } else if self.get_new_generation().len() > 0 {
@@ -182,26 +265,25 @@ impl Iterator for LayerGenerator {
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::RefCell, rc::Rc};
-
     use crate::{
         consts::BOARD_WIDTH,
         game_engine::{
-            board::Board, board_state::BoardState, layer_generator::LayerGenerator,
-            transposition::TranspositionTable,
+            arena::Arena, board::Board, layer_generator::LayerGenerator, search_stats::SearchStats,
         },
     };
 
     #[test]
     fn layer_generator() {
-        let board_state = Rc::new(RefCell::new(BoardState::default()));
-        let first_generation = vec![board_state.clone()];
+        let mut arena = Arena::default();
+        let (root, _) = arena.get_board_state(Board::default(), false, None);
 
         let mut layer_generator = LayerGenerator {
-            generation_1: first_generation,
+            generation_1: vec![root],
             generation_2: Vec::new(),
             generation_1_is_new: false,
-            table: TranspositionTable::default(),
+            deepest: 0,
+            arena,
+            stats: SearchStats::default(),
         };
 
         assert!(layer_generator.next().is_some());
@@ -229,22 +311,20 @@ mod tests {
             [0, 0, 0, 0, 0, 0, 1],
         ]);
 
-        assert_eq!(
-            // Here the 5th child is really column 7, due to the alpha-beta move generation optimization
-            board_state.borrow().children[5].state.borrow().children[5]
-                .state
-                .borrow()
-                .board,
-            last_board
-        );
+        // Here the 5th child is really column 7, due to the alpha-beta move generation optimization
+        let arena = layer_generator.arena();
+        let child = arena[root].children[5].state;
+        assert_eq!(arena[arena[child].children[5].state].board, last_board);
 
-        let board_state = Rc::new(RefCell::new(BoardState::default()));
-        let first_generation = vec![board_state];
+        let mut arena = Arena::default();
+        let (root, _) = arena.get_board_state(Board::default(), false, None);
         let mut layer_generator = LayerGenerator {
-            generation_1: first_generation,
+            generation_1: vec![root],
             generation_2: Vec::new(),
             generation_1_is_new: false,
-            table: TranspositionTable::default(),
+            deepest: 0,
+            arena,
+            stats: SearchStats::default(),
         };
 
         for _ in 0..10_000 {
@@ -256,10 +336,10 @@ mod tests {
 
     #[test]
     fn get_bottom_two_layers() {
-        let mut table = TranspositionTable::default();
-        let (root, _) = table.get_board_state(Board::default(), false);
+        let mut arena = Arena::default();
+        arena.get_board_state(Board::default(), false, None);
 
-        let (previous, new) = LayerGenerator::get_bottom_two_layers(&table);
+        let (previous, new) = LayerGenerator::get_bottom_two_layers(&arena, arena.ids());
 
         assert_eq!(previous.len(), 1);
         assert_eq!(new.len(), 0);
@@ -268,7 +348,9 @@ mod tests {
             generation_1: previous,
             generation_2: new,
             generation_1_is_new: false,
-            table,
+            deepest: 0,
+            arena,
+            stats: SearchStats::default(),
         };
         layer_generator.next();
 
@@ -278,7 +360,10 @@ mod tests {
             BOARD_WIDTH as usize
         );
 
-        let (previous, new) = LayerGenerator::get_bottom_two_layers(&layer_generator.table);
+        let (previous, new) = LayerGenerator::get_bottom_two_layers(
+            &layer_generator.arena,
+            layer_generator.arena.ids(),
+        );
 
         assert_eq!(previous.len(), (BOARD_WIDTH / 2 + 1) as usize);
         assert_eq!(new.len(), 0);
@@ -287,7 +372,9 @@ mod tests {
             generation_1: previous,
             generation_2: new,
             generation_1_is_new: false,
-            table: layer_generator.table,
+            deepest: layer_generator.deepest,
+            arena: layer_generator.arena,
+            stats: SearchStats::default(),
         };
         for _ in 0..(BOARD_WIDTH / 2 + 1) {
             layer_generator.next();
@@ -299,7 +386,10 @@ mod tests {
             (BOARD_WIDTH * 4) as usize
         );
 
-        let (previous, new) = LayerGenerator::get_bottom_two_layers(&layer_generator.table);
+        let (previous, new) = LayerGenerator::get_bottom_two_layers(
+            &layer_generator.arena,
+            layer_generator.arena.ids(),
+        );
 
         assert_eq!(previous.len(), (BOARD_WIDTH * BOARD_WIDTH / 2 + 1) as usize);
         assert_eq!(new.len(), 0);
@@ -308,28 +398,79 @@ mod tests {
             generation_1: previous,
             generation_2: new,
             generation_1_is_new: false,
-            table: layer_generator.table,
+            deepest: layer_generator.deepest,
+            arena: layer_generator.arena,
+            stats: SearchStats::default(),
         };
 
         for _ in 0..100_000 {
             layer_generator.next();
         }
 
-        let previous_depth = layer_generator.get_previous_generation()[0]
-            .borrow()
-            .get_depth();
-        for previous_state in layer_generator.get_previous_generation().iter() {
-            assert_eq!(previous_state.borrow().get_depth(), previous_depth);
+        let previous_generation = layer_generator.get_previous_generation().clone();
+        let new_generation = layer_generator.get_new_generation().clone();
+        let depth = |id| layer_generator.arena[id].get_depth();
+
+        let previous_depth = depth(previous_generation[0]);
+        for previous_state in previous_generation.iter() {
+            assert_eq!(depth(*previous_state), previous_depth);
         }
 
-        let new_depth = layer_generator.get_new_generation()[0].borrow().get_depth();
-        for new_state in layer_generator.get_new_generation().iter() {
-            assert_eq!(new_state.borrow().get_depth(), new_depth);
+        let new_depth = depth(new_generation[0]);
+        for new_state in new_generation.iter() {
+            assert_eq!(depth(*new_state), new_depth);
         }
 
         assert_eq!(previous_depth + 1, new_depth);
+    }
+
+    #[test]
+    fn focus() {
+        let mut arena = Arena::default();
+        let (root, _) = arena.get_board_state(Board::default(), false, None);
+        let mut generator = LayerGenerator::new(arena);
+        for _ in 0..8 {
+            generator.next();
+        }
 
-        drop(root);
+        // Only the positions beneath the focused child are generated from
+        let child = generator.arena()[root].children[0].state;
+        generator.focus(child);
+        for _ in 0..50 {
+            generator.next();
+        }
+        let descendants = generator.arena().descendants(child);
+        assert!(generator.frontier().count() > 0);
+        assert!(generator.frontier().all(|state| descendants
+            .iter()
+            .any(|id| generator.arena()[*id] == *state)));
+
+        // Focusing back on the root fills in the rest of the tree first, without dropping
+        //  the leaves beneath the child
+        generator.focus(root);
+        let shallowest = generator.get_previous_generation().clone();
+        assert!(!shallowest.is_empty());
+        assert!(shallowest.iter().all(|id| !descendants.contains(id)));
+        assert!(generator.frontier().any(|state| descendants
+            .iter()
+            .any(|id| generator.arena()[*id] == *state)));
+    }
+
+    #[test]
+    fn deepest_depth() {
+        let mut arena = Arena::default();
+        arena.get_board_state(Board::from_moves("44").unwrap(), false, None);
+        let mut generator = LayerGenerator::new(arena);
+        assert_eq!(generator.deepest_depth(), 2);
+
+        for _ in 0..500 {
+            generator.next();
+            assert_eq!(
+                generator.deepest_depth(),
+                LayerGenerator::find_deepest(generator.arena())
+            );
+        }
+        assert!(generator.deepest_depth() > 4);
     }
 
     #[test]
@@ -343,15 +484,13 @@ mod tests {
             [2, 2, 1, 1, 2, 1, 2],
         ]);
 
-        let mut table = TranspositionTable::default();
-        let (root, _) = table.get_board_state(board, true);
+        let mut arena = Arena::default();
+        arena.get_board_state(board, true, None);
 
-        let mut generator = LayerGenerator::new(table);
+        let mut generator = LayerGenerator::new(arena);
 
         assert_eq!(generator.next(), Some(1));
 
-        drop(root);
-
         let board = Board::from_arrays([
             [0, 0, 0, 0, 0, 0, 2],
             [0, 0, 0, 0, 0, 0, 2],
@@ -361,16 +500,14 @@ mod tests {
             [0, 0, 0, 0, 0, 0, 1],
         ]);
 
-        let mut table = TranspositionTable::default();
-        let (root, _) = table.get_board_state(board, true);
+        let mut arena = Arena::default();
+        arena.get_board_state(board, true, None);
 
-        let mut generator = LayerGenerator::new(table);
+        let mut generator = LayerGenerator::new(arena);
 
         for _ in 0..(7 + 49 + 343) {
             let num_generated = generator.next().unwrap();
             assert!(num_generated == 6 || num_generated == 0);
         }
-
-        drop(root);
     }
 }
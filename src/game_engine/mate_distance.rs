@@ -0,0 +1,151 @@
+//! Scores for forced wins and losses, which count the plies until the game ends, so that a
+//! quicker win scores better than a slower one, and a slower loss better than a quicker one.
+//!
+//! A win in d plies scores `isize::MAX - d` and a loss in d plies `-(isize::MAX - d)`, with
+//! 0 plies meaning the game is already over. Whose win it is depends on whose point of view
+//! the score is from, e.g. player two's in the decision tree. isize::MIN is below every
+//! score, so searches can start from it.
+
+/// How many plies a forced line can take and still be told apart from the heuristic, far
+/// more than any board has room for.
+const LONGEST_FORCED_LINE: usize = 1 << 16;
+
+/// Returns the score of a win the given number of plies away.
+pub fn win_in(plies: usize) -> isize {
+    isize::MAX - plies as isize
+}
+
+/// Returns the score of a loss the given number of plies away.
+pub fn loss_in(plies: usize) -> isize {
+    -win_in(plies)
+}
+
+/// Returns how many plies away the end of the game is, if the score is a forced win or
+/// loss.
+pub fn plies_to_end(score: isize) -> Option<usize> {
+    if score == isize::MIN {
+        return None;
+    }
+
+    let plies = (isize::MAX - score.abs()) as usize;
+    (plies < LONGEST_FORCED_LINE).then_some(plies)
+}
+
+/// Returns whether the score is a forced win.
+pub fn is_forced_win(score: isize) -> bool {
+    score > 0 && plies_to_end(score).is_some()
+}
+
+/// Returns whether the score is a forced loss.
+pub fn is_forced_loss(score: isize) -> bool {
+    score < 0 && plies_to_end(score).is_some()
+}
+
+/// Returns whether the score is a forced win or loss.
+pub fn is_forced(score: isize) -> bool {
+    plies_to_end(score).is_some()
+}
+
+/// Moves a state's score to its parent, one ply further from the end of the game.
+pub(crate) fn one_ply_back(score: isize) -> isize {
+    match plies_to_end(score) {
+        Some(_) if score > 0 => score - 1,
+        Some(_) => score + 1,
+        None => score,
+    }
+}
+
+/// Moves a parent's search bound to its child, one ply closer to the end of the game, the
+/// inverse of one_ply_back.
+pub(crate) fn one_ply_forward(bound: isize) -> isize {
+    match plies_to_end(bound) {
+        Some(_) if bound > 0 => bound.saturating_add(1),
+        Some(_) => bound.saturating_sub(1),
+        None => bound,
+    }
+}
+
+/// Writes a move's score as how many moves until the game is won or lost, counting the
+/// move itself, e.g. "W3" or "L5", if it's a forced win or loss.
+pub fn format_mate_distance(score: isize) -> Option<String> {
+    let moves = plies_to_end(score)? / 2 + 1;
+
+    Some(if score > 0 {
+        format!("W{}", moves)
+    } else {
+        format!("L{}", moves)
+    })
+}
+
+/// Parses a score written by format_mate_distance.
+pub fn parse_mate_distance(text: &str) -> Option<isize> {
+    let (outcome, moves) = text.split_at_checked(1)?;
+    let moves: usize = moves.parse().ok().filter(|moves| *moves > 0)?;
+
+    // A win comes on the player's own move, and a loss on their opponent's
+    match outcome {
+        "W" => Some(win_in(2 * (moves - 1))),
+        "L" => Some(loss_in(2 * moves - 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::mate_distance::{
+        format_mate_distance, is_forced, is_forced_loss, is_forced_win, loss_in, one_ply_back,
+        one_ply_forward, parse_mate_distance, plies_to_end, win_in,
+    };
+
+    #[test]
+    fn forced_results() {
+        assert_eq!(win_in(0), isize::MAX);
+        assert_eq!(loss_in(0), -isize::MAX);
+        assert_eq!(plies_to_end(win_in(4)), Some(4));
+        assert_eq!(plies_to_end(loss_in(3)), Some(3));
+
+        // Quicker wins and slower losses are better
+        assert!(win_in(2) > win_in(8));
+        assert!(loss_in(8) > loss_in(2));
+        assert!(loss_in(0) > isize::MIN);
+
+        assert!(is_forced_win(win_in(6)) && !is_forced_loss(win_in(6)));
+        assert!(is_forced_loss(loss_in(6)) && !is_forced_win(loss_in(6)));
+        assert!(!is_forced(0));
+        assert!(!is_forced(12_345));
+        assert!(!is_forced(isize::MIN));
+    }
+
+    #[test]
+    fn moving_between_plies() {
+        assert_eq!(one_ply_back(win_in(0)), win_in(1));
+        assert_eq!(one_ply_back(loss_in(2)), loss_in(3));
+        assert_eq!(one_ply_back(120), 120);
+        assert_eq!(one_ply_back(isize::MIN), isize::MIN);
+
+        for score in [win_in(3), loss_in(5), -40] {
+            assert_eq!(one_ply_forward(one_ply_back(score)), score);
+        }
+        assert_eq!(one_ply_forward(isize::MAX), isize::MAX);
+        assert_eq!(one_ply_forward(isize::MIN), isize::MIN);
+    }
+
+    #[test]
+    fn labels() {
+        // Winning with the move itself, or on the player's next move
+        assert_eq!(format_mate_distance(win_in(0)).as_deref(), Some("W1"));
+        assert_eq!(format_mate_distance(win_in(2)).as_deref(), Some("W2"));
+        // Losing to the opponent's reply, or to their move after that
+        assert_eq!(format_mate_distance(loss_in(1)).as_deref(), Some("L1"));
+        assert_eq!(format_mate_distance(loss_in(9)).as_deref(), Some("L5"));
+        assert_eq!(format_mate_distance(35), None);
+
+        for score in [win_in(0), win_in(4), loss_in(1), loss_in(9)] {
+            let label = format_mate_distance(score).unwrap();
+            assert_eq!(parse_mate_distance(&label), Some(score));
+        }
+        assert_eq!(parse_mate_distance("W0"), None);
+        assert_eq!(parse_mate_distance("X3"), None);
+        assert_eq!(parse_mate_distance("W"), None);
+    }
+}
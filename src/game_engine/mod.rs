@@ -1,10 +1,38 @@
-mod board;
+mod arena;
+pub mod board;
+pub mod board_config;
 mod board_iters;
 mod board_state;
+pub mod clock;
+pub mod column_label;
+pub mod complexity;
+mod fixed_point;
 pub mod game_manager;
+pub mod game_record;
 mod heuristics;
+pub mod hint;
+pub mod house_rules;
+pub mod human_policy;
 mod layer_generator;
+pub mod mate_distance;
+pub mod monte_carlo;
+pub mod move_events;
+pub mod opening_book;
+pub mod power_ups;
+pub mod puzzle;
+pub mod puzzle_miner;
+pub mod rating;
+pub mod record_annotator;
+pub mod search_mask;
+pub mod search_stats;
+pub mod solver;
+pub mod strength;
+pub mod threats;
 mod transposition;
 mod tree_analysis;
 mod tree_size;
+#[cfg(test)]
+mod tree_snapshot;
+pub mod variant_rules;
 mod win_check;
+pub mod win_probability;
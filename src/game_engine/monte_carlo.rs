@@ -0,0 +1,472 @@
+use std::{collections::HashMap, mem::size_of};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::game_engine::{
+    board::Board,
+    fixed_point::{self, ONE},
+    power_ups::{legal_moves, winning_anvils, Move, PowerUpKind, PowerUps},
+    variant_rules::VariantRules,
+    win_check::{winning_columns, GameOver},
+    win_probability::score_for_odds,
+};
+
+/// How strongly selection favors moves that have been tried less, the UCB1 exploration
+/// constant of √2, in fixed point.
+const EXPLORATION: i64 = fixed_point::SQRT_2;
+
+/// How the engine evaluates the moves of a position.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum EngineAlgorithm {
+    /// Alpha-beta pruning over the decision tree, scored by the heuristics.
+    #[default]
+    AlphaBeta,
+    /// Monte Carlo tree search, scoring moves by how often random games played out from them
+    /// are won.
+    Mcts,
+    /// Monte Carlo tree search, except for the wins and losses alpha-beta pruning has proven.
+    Hybrid,
+}
+
+impl EngineAlgorithm {
+    /// Every algorithm, in the order they're offered to the user.
+    pub const ALL: [EngineAlgorithm; 3] = [
+        EngineAlgorithm::AlphaBeta,
+        EngineAlgorithm::Mcts,
+        EngineAlgorithm::Hybrid,
+    ];
+
+    /// Describes the algorithm for the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EngineAlgorithm::AlphaBeta => "Alpha-beta",
+            EngineAlgorithm::Mcts => "Monte Carlo",
+            EngineAlgorithm::Hybrid => "Hybrid",
+        }
+    }
+
+    /// Returns whether the algorithm plays out random games.
+    pub fn uses_rollouts(&self) -> bool {
+        *self != EngineAlgorithm::AlphaBeta
+    }
+}
+
+/// How the rollouts played through a position turned out, for the player who moved into it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RolloutResults {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl RolloutResults {
+    /// Returns how many rollouts have been played through the position.
+    pub fn visits(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the fraction of the rollouts that were won, counting a draw as half a win, or
+    /// None if there haven't been any.
+    pub fn win_probability(&self) -> Option<f64> {
+        match self.visits() {
+            0 => None,
+            visits => Some((self.wins as f64 + self.draws as f64 / 2.0) / visits as f64),
+        }
+    }
+
+    /// Returns the results as a score on the calibrated scale of win_probability, or None if
+    /// there haven't been any rollouts.
+    ///
+    /// Unlike converting win_probability, this only uses integers, so the same rollouts get
+    /// the same score on every platform.
+    pub fn score(&self) -> Option<isize> {
+        match self.visits() {
+            0 => None,
+            _ => Some(score_for_odds(
+                (2 * self.wins + self.draws).into(),
+                (2 * self.losses + self.draws).into(),
+            )),
+        }
+    }
+
+    /// Returns the UCB1 value of the position in fixed point, trading off how well its
+    /// rollouts have gone against how rarely it has been tried compared to its siblings.
+    ///
+    /// Selection is worked out with integers only, so that the same seed searches the same
+    /// tree on every platform.
+    fn upper_confidence_bound(&self, parent_visits: u32) -> i64 {
+        let visits = i64::from(self.visits());
+        let mean = (2 * i64::from(self.wins) + i64::from(self.draws)) * ONE / (2 * visits);
+        let exploration = fixed_point::sqrt(fixed_point::ln(parent_visits.into()) / visits);
+
+        mean + EXPLORATION * exploration / ONE
+    }
+
+    /// Records the outcome of a rollout, for the player who moved into the position.
+    fn record(&mut self, outcome: GameOver, mover: bool) {
+        match (outcome, mover) {
+            (GameOver::OneWins, false) | (GameOver::TwoWins, true) => self.wins += 1,
+            (GameOver::OneWins, true) | (GameOver::TwoWins, false) => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+}
+
+/// A position in the search tree.
+#[derive(Debug)]
+struct Node {
+    board: Board,
+    /// Whose turn it is, false for player one and true for player two.
+    turn: bool,
+    game_over: GameOver,
+    /// Which power-ups are left, or None if the game isn't played with them.
+    power_ups: Option<PowerUps>,
+    results: RolloutResults,
+    /// The moves that have been expanded, and the index of the node each leads to.
+    children: Vec<(Move, usize)>,
+    /// The legal moves that haven't been expanded yet.
+    untried: Vec<Move>,
+}
+
+impl Node {
+    fn new(board: Board, turn: bool, rules: VariantRules, power_ups: Option<PowerUps>) -> Node {
+        let game_over = rules.is_game_over(&board, turn);
+        let untried = legal_moves(&board, turn, power_ups);
+
+        Node {
+            board,
+            turn,
+            game_over,
+            power_ups,
+            results: RolloutResults::default(),
+            children: Vec::new(),
+            untried,
+        }
+    }
+}
+
+/// Evaluates a position by Monte Carlo tree search.
+///
+/// Each rollout selects a path down the tree by UCB1, expands one new position at the end of
+/// it, plays a random game out from there, and records the result along the path. Moves are
+/// columns in the orientation of the board the tree was started from.
+///
+/// When the game is played with power-ups, clearing a column is searched like any other
+/// move, but the random games only drop pieces.
+#[derive(Debug)]
+pub struct MonteCarloTree {
+    /// Every position searched, starting with the root.
+    nodes: Vec<Node>,
+    rules: VariantRules,
+    rng: StdRng,
+}
+
+impl MonteCarloTree {
+    /// Starts a search from the given position, played under the given rules, and with the
+    /// given power-ups left if the game is played with them. The random games played out are
+    /// seeded from the position, so searching it again plays the same games.
+    pub fn new(
+        board: Board,
+        turn: bool,
+        rules: VariantRules,
+        power_ups: Option<PowerUps>,
+    ) -> MonteCarloTree {
+        let rng = StdRng::seed_from_u64(board.key());
+
+        MonteCarloTree {
+            nodes: vec![Node::new(board, turn, rules, power_ups)],
+            rules,
+            rng,
+        }
+    }
+
+    /// Plays out the given number of rollouts, returning how many were played.
+    ///
+    /// None are played once the game is over.
+    pub fn run_rollouts(&mut self, rollouts: usize) -> usize {
+        if self.nodes[0].game_over != GameOver::NoWin {
+            return 0;
+        }
+
+        for _ in 0..rollouts {
+            self.rollout();
+        }
+        rollouts
+    }
+
+    /// Returns how many rollouts have been played from the root.
+    pub fn rollouts(&self) -> u32 {
+        self.nodes[0].results.visits()
+    }
+
+    /// Returns the results of the rollouts through each of the root's drops.
+    pub fn move_results(&self) -> HashMap<u8, RolloutResults> {
+        self.root_results(|game_move| matches!(game_move, Move::Drop(_)))
+    }
+
+    /// Returns the results of the rollouts through each column the player about to move
+    /// could spend their power-up in, clearing it or dropping their anvil.
+    pub fn power_up_results(&self) -> HashMap<u8, RolloutResults> {
+        self.root_results(|game_move| game_move.power_up().is_some())
+    }
+
+    /// Returns the results of the rollouts through the root's moves that match the filter,
+    /// by column.
+    fn root_results(&self, filter: impl Fn(&Move) -> bool) -> HashMap<u8, RolloutResults> {
+        self.nodes[0]
+            .children
+            .iter()
+            .filter(|(game_move, _)| filter(game_move))
+            .map(|(game_move, child)| (game_move.column(), self.nodes[*child].results))
+            .collect()
+    }
+
+    /// Returns the chance of winning with each of the root's moves that has been tried, for
+    /// the player about to move, counting a draw as half a win.
+    pub fn move_win_probabilities(&self) -> HashMap<u8, f64> {
+        self.move_results()
+            .into_iter()
+            .filter_map(|(col, results)| Some((col, results.win_probability()?)))
+            .collect()
+    }
+
+    /// Returns how many bytes the tree has allocated.
+    pub fn heap_size(&self) -> usize {
+        self.nodes.capacity() * size_of::<Node>()
+            + self
+                .nodes
+                .iter()
+                .map(|node| {
+                    node.children.capacity() * size_of::<(Move, usize)>()
+                        + node.untried.capacity() * size_of::<Move>()
+                })
+                .sum::<usize>()
+    }
+
+    /// Plays out a single rollout.
+    fn rollout(&mut self) {
+        // Selection, down through the positions whose moves have all been expanded
+        let mut path = vec![0];
+        let mut current = 0;
+        while self.nodes[current].untried.is_empty() && !self.nodes[current].children.is_empty() {
+            let parent_visits = self.nodes[current].results.visits();
+            current = self.nodes[current]
+                .children
+                .iter()
+                .map(|(_, child)| *child)
+                .max_by_key(|child| {
+                    self.nodes[*child]
+                        .results
+                        .upper_confidence_bound(parent_visits)
+                })
+                .expect("The node has children");
+            path.push(current);
+        }
+
+        // Expansion, of one of the moves that hasn't been tried yet
+        if !self.nodes[current].untried.is_empty() {
+            let node = &mut self.nodes[current];
+            let index = self.rng.gen_range(0..node.untried.len());
+            let game_move = node.untried.swap_remove(index);
+
+            let mut board = node.board.clone();
+            let mut power_ups = node.power_ups.unwrap_or_default();
+            game_move
+                .play(&mut board, node.turn, &mut power_ups)
+                .expect("Untried moves are legal");
+            let child = Node::new(
+                board,
+                !node.turn,
+                self.rules,
+                node.power_ups.map(|_| power_ups),
+            );
+
+            let child_index = self.nodes.len();
+            self.nodes[current].children.push((game_move, child_index));
+            self.nodes.push(child);
+            path.push(child_index);
+            current = child_index;
+        }
+
+        // Simulation, of a random game from the new position
+        let outcome = self.simulate(current);
+
+        // Backpropagation, crediting each position to the player who moved into it
+        for index in path {
+            let mover = !self.nodes[index].turn;
+            self.nodes[index].results.record(outcome, mover);
+        }
+    }
+
+    /// Plays random moves from the position until the game is over, returning how it ended.
+    ///
+    /// Four in a row is never passed up under the standard rules, or played when there's
+    /// another move under misère rules, so the games look a little less like flailing.
+    /// Power-ups are otherwise left unspent, except for an anvil that would win outright.
+    fn simulate(&mut self, index: usize) -> GameOver {
+        let node = &self.nodes[index];
+        let mut board = node.board.clone();
+        let mut turn = node.turn;
+        let mut game_over = node.game_over;
+        let anvils = node
+            .power_ups
+            .filter(|power_ups| power_ups.kind() == PowerUpKind::Anvil);
+
+        while game_over == GameOver::NoWin {
+            let completing = winning_columns(&board, turn);
+            if completing.is_empty()
+                && self.rules == VariantRules::Standard
+                && anvils.is_some_and(|anvils| anvils.is_available(turn))
+                && !winning_anvils(&board, turn).is_empty()
+            {
+                return match turn {
+                    false => GameOver::OneWins,
+                    true => GameOver::TwoWins,
+                };
+            }
+
+            let mut moves: Vec<u8> = (0..board.width())
+                .filter(|col| board.get_height(*col) < board.height())
+                .collect();
+            match self.rules {
+                VariantRules::Standard if !completing.is_empty() => moves = completing,
+                VariantRules::Misere if moves.len() > completing.len() => {
+                    moves.retain(|col| !completing.contains(col))
+                }
+                _ => (),
+            }
+
+            let col = *moves
+                .choose(&mut self.rng)
+                .expect("A game that isn't over has a legal move");
+            board
+                .drop_piece(col, turn)
+                .expect("Only legal moves are chosen");
+            turn = !turn;
+            game_over = self.rules.is_game_over(&board, turn);
+        }
+
+        game_over
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        board::Board,
+        fixed_point::ONE,
+        monte_carlo::{MonteCarloTree, RolloutResults},
+        power_ups::PowerUps,
+        variant_rules::VariantRules,
+        win_probability::score_for_probability,
+    };
+
+    #[test]
+    fn rollout_results() {
+        let results = RolloutResults {
+            wins: 3,
+            draws: 2,
+            losses: 5,
+        };
+        assert_eq!(results.visits(), 10);
+        assert_eq!(results.win_probability(), Some(0.4));
+        assert_eq!(RolloutResults::default().win_probability(), None);
+
+        // Scores agree with converting the chance of winning
+        assert_eq!(results.score(), Some(score_for_probability(0.4)));
+        assert_eq!(RolloutResults::default().score(), None);
+        let won = RolloutResults {
+            wins: 10,
+            ..Default::default()
+        };
+        assert_eq!(won.score(), Some(score_for_probability(1.0)));
+
+        // Selection favors the better results, and the less tried
+        let ucb = |wins, losses| {
+            RolloutResults {
+                wins,
+                draws: 0,
+                losses,
+            }
+            .upper_confidence_bound(100)
+        };
+        assert!(ucb(6, 4) > ucb(4, 6));
+        assert!(ucb(3, 2) > ucb(6, 4));
+        let expected = 0.6 + (2.0 * 100f64.ln() / 10.0).sqrt();
+        assert!((ucb(6, 4) as f64 / ONE as f64 - expected).abs() < 0.001);
+    }
+
+    #[test]
+    fn finds_wins() {
+        // Player one has an open three along the bottom, and can win at either end
+        let board = Board::from_moves("445566").unwrap();
+        let mut tree = MonteCarloTree::new(board, false, VariantRules::Standard, None);
+        assert_eq!(tree.run_rollouts(2000), 2000);
+        assert_eq!(tree.rollouts(), 2000);
+
+        let probabilities = tree.move_win_probabilities();
+        assert_eq!(probabilities[&2], 1.0);
+        assert_eq!(probabilities[&6], 1.0);
+
+        // A winning move is the one tried the most
+        let results = tree.move_results();
+        let most_visited = results
+            .iter()
+            .max_by_key(|(_, results)| results.visits())
+            .unwrap();
+        assert!([2, 6].contains(most_visited.0));
+
+        // Under misère rules the same moves lose
+        let board = Board::from_moves("445566").unwrap();
+        let mut tree = MonteCarloTree::new(board, false, VariantRules::Misere, None);
+        tree.run_rollouts(2000);
+        let probabilities = tree.move_win_probabilities();
+        assert_eq!(probabilities[&2], 0.0);
+        assert_eq!(probabilities[&6], 0.0);
+    }
+
+    #[test]
+    fn clears_columns() {
+        // Player one threatens both ends of a three along the second row, which no drop can
+        // stop, but player two can clear away its middle
+        let board = Board::from_moves("426345375").unwrap();
+        let mut tree = MonteCarloTree::new(
+            board.clone(),
+            true,
+            VariantRules::Standard,
+            Some(PowerUps::default()),
+        );
+        tree.run_rollouts(3000);
+
+        let best_drop = tree
+            .move_results()
+            .values()
+            .filter_map(|results| results.win_probability())
+            .fold(0.0, f64::max);
+        let clears = tree.power_up_results();
+        assert_eq!(clears.len(), 6);
+        assert!(clears[&3].win_probability().unwrap() > best_drop);
+
+        // Nothing can be cleared without power-ups
+        let mut tree = MonteCarloTree::new(board, true, VariantRules::Standard, None);
+        tree.run_rollouts(100);
+        assert!(tree.power_up_results().is_empty());
+    }
+
+    #[test]
+    fn repeatable() {
+        let board = Board::from_moves("4453").unwrap();
+        let mut first = MonteCarloTree::new(board.clone(), false, VariantRules::Standard, None);
+        let mut second = MonteCarloTree::new(board, false, VariantRules::Standard, None);
+        first.run_rollouts(500);
+        second.run_rollouts(500);
+        assert_eq!(first.move_results(), second.move_results());
+
+        // Nothing can be played out once the game is over
+        let won = Board::from_moves("4455667").unwrap();
+        let mut tree = MonteCarloTree::new(won, true, VariantRules::Standard, None);
+        assert_eq!(tree.run_rollouts(10), 0);
+        assert!(tree.move_results().is_empty());
+    }
+}
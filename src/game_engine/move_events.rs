@@ -0,0 +1,158 @@
+use crate::game_engine::{
+    board::Board,
+    win_check::{is_game_over, winning_columns, GameOver},
+};
+
+/// Something notable about a move or position, reported by the engine as moves are made and
+/// positions are searched, so that every front-end describes the game the same way.
+///
+/// Threats are spaces a player could win in on their next turn. Under misère rules they're
+/// the spaces that would lose instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveEvent {
+    /// The player's move gave them new threats, in the given columns.
+    ThreatCreated { player: bool, columns: Vec<u8> },
+    /// The player dropped a piece in the column their opponent could have won in.
+    ThreatBlocked { player: bool, column: u8 },
+    /// The player could have won in the column, but played elsewhere.
+    WinMissed { player: bool, column: u8 },
+    /// The player left their opponent's threat in the column open.
+    BlockMissed { player: bool, column: u8 },
+    /// The search proved that the player to move can force a win.
+    ForcedWinDetected { player: bool },
+    /// The player has to move, but every move they have hands their opponent a win they
+    /// didn't have before.
+    ZugzwangReached { player: bool },
+}
+
+/// Returns the events of the player dropping a piece in the column, including any reached
+/// in the position after it. A move that ends the game has none.
+///
+/// board is the position before the move, in the same orientation as the column.
+pub fn move_events(board: &Board, player: bool, column: u8) -> Vec<MoveEvent> {
+    let mut events = Vec::new();
+
+    let own_wins = winning_columns(board, player);
+    let opponent_wins = winning_columns(board, !player);
+
+    let mut after = board.clone();
+    if after.drop_piece(column, player).is_err() || is_game_over(&after, !player) != GameOver::NoWin
+    {
+        return events;
+    }
+
+    if let Some(&win) = own_wins.first() {
+        events.push(MoveEvent::WinMissed {
+            player,
+            column: win,
+        });
+    }
+    if opponent_wins.contains(&column) {
+        events.push(MoveEvent::ThreatBlocked { player, column });
+    } else if let Some(&block) = opponent_wins.first() {
+        events.push(MoveEvent::BlockMissed {
+            player,
+            column: block,
+        });
+    }
+
+    let new_threats: Vec<u8> = winning_columns(&after, player)
+        .into_iter()
+        .filter(|col| !own_wins.contains(col))
+        .collect();
+    if !new_threats.is_empty() {
+        events.push(MoveEvent::ThreatCreated {
+            player,
+            columns: new_threats,
+        });
+    }
+    if is_zugzwang(&after, !player) {
+        events.push(MoveEvent::ZugzwangReached { player: !player });
+    }
+
+    events
+}
+
+/// Returns whether the player is in zugzwang: there's nothing they need to win or block, yet
+/// every move they could make gives their opponent somewhere to win.
+fn is_zugzwang(board: &Board, player: bool) -> bool {
+    if !winning_columns(board, player).is_empty() || !winning_columns(board, !player).is_empty() {
+        return false;
+    }
+
+    let mut drops = board.legal_drops(player).peekable();
+    drops.peek().is_some() && drops.all(|(_, after)| !winning_columns(&after, !player).is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        board::Board,
+        move_events::{is_zugzwang, move_events, MoveEvent},
+        win_check::winning_columns,
+    };
+
+    #[test]
+    fn threats() {
+        // Red's third piece along the bottom opens up both ends
+        let board = Board::from_moves("4455").unwrap();
+        assert_eq!(
+            move_events(&board, false, 5),
+            vec![MoveEvent::ThreatCreated {
+                player: false,
+                columns: vec![2, 6],
+            }]
+        );
+
+        // Red misses the win, and later on Blue can only block one end
+        let board = Board::from_moves("445566").unwrap();
+        assert_eq!(
+            move_events(&board, false, 0),
+            vec![MoveEvent::WinMissed {
+                player: false,
+                column: 2,
+            }]
+        );
+        let board = Board::from_moves("4455666").unwrap();
+        assert_eq!(
+            move_events(&board, true, 2),
+            vec![
+                MoveEvent::ThreatBlocked {
+                    player: true,
+                    column: 2,
+                },
+                // Blue's own three along the second row can now be finished
+                MoveEvent::ThreatCreated {
+                    player: true,
+                    columns: vec![2],
+                },
+            ]
+        );
+
+        // Nothing is reported for a move that ends the game
+        let board = Board::from_moves("445566").unwrap();
+        assert_eq!(move_events(&board, false, 2), vec![]);
+    }
+
+    #[test]
+    fn zugzwang() {
+        // Only the third column is open, and Blue's piece there would let Red win above it
+        let board = Board::from_arrays([
+            [1, 2, 0, 2, 1, 1, 1],
+            [2, 2, 0, 2, 1, 2, 1],
+            [1, 1, 0, 1, 1, 1, 2],
+            [2, 2, 0, 2, 2, 1, 1],
+            [1, 2, 0, 2, 1, 2, 2],
+            [2, 1, 1, 2, 1, 1, 2],
+        ]);
+        assert!(is_zugzwang(&board, true));
+
+        let mut after = board.clone();
+        after.drop_piece(2, true).unwrap();
+        assert_eq!(winning_columns(&after, false), vec![2]);
+
+        // A player with a win to take or a threat to block isn't in zugzwang
+        assert!(!is_zugzwang(&Board::from_moves("445566").unwrap(), false));
+        assert!(!is_zugzwang(&Board::from_moves("4455667").unwrap(), true));
+    }
+}
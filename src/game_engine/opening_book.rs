@@ -0,0 +1,312 @@
+//! A book of opening moves the engine plays without searching, stored in a compact binary
+//! file so it can be built offline and extended.
+//!
+//! Positions are stored by their canonical key, so a position and its mirror image share an
+//! entry, with the move given in the orientation of whichever of the two has the smaller key.
+//! Only the standard board has a book.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+    time::Duration,
+};
+
+use crate::game_engine::{
+    board::Board, board_config::BoardConfig, column_label::ColumnLabel, game_manager::GameManager,
+    win_check::GameOver,
+};
+
+/// The bytes every opening book file starts with.
+const BOOK_MAGIC: &[u8; 6] = b"C4BOOK";
+/// The version of the format written by to_bytes.
+const BOOK_VERSION: u8 = 1;
+/// The size of each entry, its position's key followed by the move's column.
+const ENTRY_SIZE: usize = 9;
+
+/// The book that ships with the engine, as written by to_bytes. It was built with
+/// `rusty_connect_four book <file> --plies 8 --movetime 50ms`.
+const BUILT_IN_BOOK: &[u8] = include_bytes!("opening_book.bin");
+/// How many moves into the game the built-in book covers, see build.
+pub const BUILT_IN_PLIES: usize = 8;
+
+/// Opening moves, each the move to play in a position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    moves: BTreeMap<u64, u8>,
+}
+
+impl OpeningBook {
+    /// Creates an empty book.
+    pub fn new() -> OpeningBook {
+        Default::default()
+    }
+
+    /// Returns the book that ships with the engine, which has a move for every position
+    /// fewer than BUILT_IN_PLIES moves into the game.
+    pub fn built_in() -> OpeningBook {
+        OpeningBook::from_bytes(BUILT_IN_BOOK).expect("The built-in book is well formed")
+    }
+
+    /// Builds a book by searching every position fewer than plies moves into the game for
+    /// movetime each.
+    ///
+    /// progress is called with how many positions have been searched and how many there are.
+    pub fn build(plies: usize, movetime: Duration, mut progress: impl FnMut(usize, usize)) -> Self {
+        let positions = opening_positions(plies);
+
+        let mut book = OpeningBook::new();
+        for (index, moves) in positions.iter().enumerate() {
+            progress(index, positions.len());
+
+            let mut manager = GameManager::from_moves(moves).expect("Generated moves are legal");
+            if manager.is_game_over() != GameOver::NoWin {
+                continue;
+            }
+            if let Some(column) = manager.search_for(movetime).best_move {
+                book.insert(moves, column)
+                    .expect("Searched moves are legal");
+            }
+        }
+
+        book
+    }
+
+    /// Adds the column to play, numbered from 0, after the moves, replacing any move the
+    /// book already had for the position.
+    pub fn insert(&mut self, moves: &str, column: u8) -> Result<(), String> {
+        let board = Board::from_moves(moves).map_err(|error| format!("{:?}", error))?;
+        let mut after = board.clone();
+        after
+            .drop_piece(column, moves.len() % 2 == 1)
+            .map_err(|_| format!("Column {} is full", ColumnLabel::new(column)))?;
+
+        let (key, column) = canonical_move(&board, column);
+        self.moves.insert(key, column);
+        Ok(())
+    }
+
+    /// Returns the column to play on the board, numbered from 0, if the book has a move for
+    /// it.
+    pub fn best_move(&self, board: &Board) -> Option<u8> {
+        if !board.config().is_standard() {
+            return None;
+        }
+
+        let stored = *self.moves.get(&board.canonical_key())?;
+        // Mirroring is its own inverse, so mapping the stored move back is the same mapping
+        let (_, column) = canonical_move(board, stored);
+        Some(column)
+    }
+
+    /// Adds every move in the other book, replacing this book's moves where both have one.
+    pub fn merge(&mut self, other: &OpeningBook) {
+        self.moves.extend(&other.moves);
+    }
+
+    /// Returns how many positions the book has a move for.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Returns whether the book has no moves.
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Writes the book in its binary format.
+    ///
+    /// The format is the magic bytes "C4BOOK", a version byte, the board's width and height,
+    /// the number of entries as a little endian u32, and then each entry as its position's
+    /// canonical key as a little endian u64 followed by the column to play, sorted by key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = BOOK_MAGIC.to_vec();
+        bytes.push(BOOK_VERSION);
+        bytes.push(BoardConfig::STANDARD.width);
+        bytes.push(BoardConfig::STANDARD.height);
+        bytes.extend((self.moves.len() as u32).to_le_bytes());
+
+        for (key, column) in &self.moves {
+            bytes.extend(key.to_le_bytes());
+            bytes.push(*column);
+        }
+
+        bytes
+    }
+
+    /// Reads a book from its binary format, as written by to_bytes.
+    ///
+    /// Fails if the bytes are truncated or corrupt, or were written by an unknown version
+    /// of the format or for another board.
+    pub fn from_bytes(bytes: &[u8]) -> Result<OpeningBook, String> {
+        let header_size = BOOK_MAGIC.len() + 3 + 4;
+        if bytes.len() < header_size || &bytes[..BOOK_MAGIC.len()] != BOOK_MAGIC {
+            return Err("Not an opening book".to_owned());
+        }
+
+        let (header, entries) = bytes[BOOK_MAGIC.len()..].split_at(header_size - BOOK_MAGIC.len());
+        if header[0] != BOOK_VERSION {
+            return Err(format!("Unsupported opening book version {}", header[0]));
+        }
+        if header[1] != BoardConfig::STANDARD.width || header[2] != BoardConfig::STANDARD.height {
+            return Err(format!(
+                "The opening book is for a {}x{} board",
+                header[1], header[2]
+            ));
+        }
+        let count = u32::from_le_bytes([header[3], header[4], header[5], header[6]]) as usize;
+        if entries.len() != count * ENTRY_SIZE {
+            return Err("The opening book is truncated or has extra bytes".to_owned());
+        }
+
+        let mut book = OpeningBook::new();
+        for entry in entries.chunks(ENTRY_SIZE) {
+            let key = u64::from_le_bytes(entry[..8].try_into().expect("Entries are 9 bytes"));
+            let column = entry[8];
+            if column >= BoardConfig::STANDARD.width {
+                return Err(format!("Invalid column {} in the opening book", column));
+            }
+
+            book.moves.insert(key, column);
+        }
+
+        Ok(book)
+    }
+
+    /// Reads a book from a file.
+    pub fn load(path: &Path) -> Result<OpeningBook, String> {
+        let bytes = fs::read(path)
+            .map_err(|error| format!("Couldn't read {} - {}", path.display(), error))?;
+
+        OpeningBook::from_bytes(&bytes)
+    }
+
+    /// Writes the book to a file.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_bytes())
+            .map_err(|error| format!("Couldn't write {} - {}", path.display(), error))
+    }
+}
+
+/// Returns a move string reaching each position fewer than plies moves into the game, with
+/// only one of each position and its mirror image.
+fn opening_positions(plies: usize) -> Vec<String> {
+    // Each position is only listed once, whichever move order reaches it
+    let mut positions = vec![String::new()];
+    let mut seen = HashSet::new();
+    let mut frontier = vec![String::new()];
+    for _ in 1..plies {
+        let mut next = Vec::new();
+        for moves in frontier {
+            for column in 0..BoardConfig::STANDARD.width {
+                let moves = format!("{}{}", moves, ColumnLabel::new(column));
+                let Ok(board) = Board::from_moves(&moves) else {
+                    continue;
+                };
+
+                if seen.insert(board.canonical_key()) {
+                    next.push(moves);
+                }
+            }
+        }
+
+        positions.extend(next.iter().cloned());
+        frontier = next;
+    }
+
+    positions
+}
+
+/// Returns the board's canonical key, along with the column mapped into the orientation of
+/// whichever of the board and its mirror image the key belongs to.
+fn canonical_move(board: &Board, column: u8) -> (u64, u8) {
+    let key = board.canonical_key();
+    if key == board.key() {
+        (key, column)
+    } else {
+        (key, board.width() - 1 - column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game_engine::{
+        board::Board,
+        opening_book::{opening_positions, OpeningBook, BUILT_IN_PLIES},
+        win_check::{is_game_over, GameOver},
+    };
+
+    #[test]
+    fn moves() {
+        let book = OpeningBook::new();
+        assert_eq!(book.best_move(&Board::default()), None);
+
+        // Mirror images share their moves
+        let mut book = OpeningBook::new();
+        book.insert("1", 1).unwrap();
+        assert_eq!(book.best_move(&Board::from_moves("1").unwrap()), Some(1));
+        assert_eq!(book.best_move(&Board::from_moves("7").unwrap()), Some(5));
+        assert_eq!(book.len(), 1);
+
+        assert!(book.insert("444444", 3).is_err());
+        assert!(book.insert("8", 3).is_err());
+
+        // Other boards don't have a book
+        let board = Board::with_config(crate::game_engine::board_config::BoardConfig::VARIANTS[1]);
+        assert_eq!(OpeningBook::built_in().best_move(&board), None);
+    }
+
+    #[test]
+    fn built_in() {
+        let book = OpeningBook::built_in();
+
+        // Connect Four is solved, and only playing in the middle column first wins by force
+        assert_eq!(book.best_move(&Board::default()), Some(3));
+
+        // Every position the book promises to cover has a move, apart from games already won
+        let positions: Vec<Board> = opening_positions(BUILT_IN_PLIES)
+            .iter()
+            .map(|moves| (Board::from_moves(moves).unwrap(), moves.len() % 2 == 1))
+            .filter(|(board, turn)| is_game_over(board, *turn) == GameOver::NoWin)
+            .map(|(board, _)| board)
+            .collect();
+        assert!(positions
+            .iter()
+            .all(|board| book.best_move(board).is_some()));
+        assert_eq!(book.len(), positions.len());
+    }
+
+    #[test]
+    fn bytes() {
+        let mut book = OpeningBook::built_in();
+        book.insert("44", 2).unwrap();
+        book.insert("4453", 6).unwrap();
+
+        let bytes = book.to_bytes();
+        assert_eq!(OpeningBook::from_bytes(&bytes), Ok(book));
+
+        assert!(OpeningBook::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(OpeningBook::from_bytes(b"C4BOOK").is_err());
+        let mut other_version = bytes.clone();
+        other_version[6] = 2;
+        assert!(OpeningBook::from_bytes(&other_version).is_err());
+        let mut bad_column = bytes;
+        *bad_column.last_mut().unwrap() = 7;
+        assert!(OpeningBook::from_bytes(&bad_column).is_err());
+    }
+
+    #[test]
+    fn build() {
+        let mut searched = 0;
+        let book = OpeningBook::build(2, Duration::from_millis(10), |_, _| searched += 1);
+
+        // The empty board, and the four first moves that aren't mirror images
+        assert_eq!(searched, 5);
+        assert_eq!(book.len(), 5);
+        for moves in ["", "1", "2", "3", "4", "5", "6", "7"] {
+            assert!(book.best_move(&Board::from_moves(moves).unwrap()).is_some());
+        }
+    }
+}
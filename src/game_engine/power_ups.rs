@@ -0,0 +1,510 @@
+use std::{cmp::Reverse, collections::HashMap, fmt};
+
+use crate::{
+    consts::MAX_BOARD_WIDTH,
+    game_engine::{
+        board::Board,
+        board_config::BoardConfig,
+        column_label::{join_moves, split_moves, ColumnLabel},
+        win_check::{is_game_over, GameOver},
+    },
+};
+
+/// The character that marks a column being cleared in a move string, e.g. "44x4" clears the
+/// middle column after two pieces have been dropped in it.
+pub const CLEAR_MARKER: char = 'x';
+/// The character that marks an anvil being dropped in a move string, e.g. "44a4" crushes
+/// the two pieces in the middle column and leaves the anvil at the bottom of it.
+pub const ANVIL_MARKER: char = 'a';
+/// Every character that marks a power-up being spent in a move string.
+pub const POWER_UP_MARKERS: [char; 2] = [CLEAR_MARKER, ANVIL_MARKER];
+
+/// Which power-up each player gets once a game.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    /// Clears every piece out of a column.
+    #[default]
+    Clear,
+    /// A disc that crushes every piece beneath it, landing at the bottom of the column.
+    Anvil,
+}
+
+impl PowerUpKind {
+    /// Every kind of power-up, in the order they're offered to the user.
+    pub const ALL: [PowerUpKind; 2] = [PowerUpKind::Clear, PowerUpKind::Anvil];
+
+    /// Names the power-up for the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUpKind::Clear => "Column clear",
+            PowerUpKind::Anvil => "Anvil disc",
+        }
+    }
+
+    /// Returns the move that spends the power-up in the column.
+    pub fn spend(&self, col: u8) -> Move {
+        match self {
+            PowerUpKind::Clear => Move::Clear(col),
+            PowerUpKind::Anvil => Move::Anvil(col),
+        }
+    }
+}
+
+/// A move in a game that can be played with power-ups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    /// Drops a piece down the column.
+    Drop(u8),
+    /// Spends the player's power-up to clear every piece out of the column.
+    Clear(u8),
+    /// Spends the player's anvil, which clears every piece out of the column and then lands
+    /// at the bottom of it as one of their pieces.
+    Anvil(u8),
+}
+
+impl Move {
+    /// Returns the column the move is made in, numbered from 0.
+    pub fn column(&self) -> u8 {
+        match self {
+            Move::Drop(col) | Move::Clear(col) | Move::Anvil(col) => *col,
+        }
+    }
+
+    /// Returns the same kind of move, made in another column.
+    pub fn with_column(&self, col: u8) -> Move {
+        match self {
+            Move::Drop(_) => Move::Drop(col),
+            Move::Clear(_) => Move::Clear(col),
+            Move::Anvil(_) => Move::Anvil(col),
+        }
+    }
+
+    /// Returns the kind of power-up the move spends, or None for a drop.
+    pub fn power_up(&self) -> Option<PowerUpKind> {
+        match self {
+            Move::Drop(_) => None,
+            Move::Clear(_) => Some(PowerUpKind::Clear),
+            Move::Anvil(_) => Some(PowerUpKind::Anvil),
+        }
+    }
+
+    /// Makes the move on the board for the given player, spending their power-up if it's a
+    /// clear or an anvil.
+    ///
+    /// Fails if the move isn't legal, e.g. the power-up was already spent or is of another
+    /// kind.
+    pub fn play(
+        &self,
+        board: &mut Board,
+        turn: bool,
+        power_ups: &mut PowerUps,
+    ) -> Result<(), String> {
+        if is_game_over(board, turn) != GameOver::NoWin {
+            return Err(format!(
+                "The game is already over, so {} can't be played",
+                self
+            ));
+        }
+
+        match self {
+            Move::Drop(col) => board
+                .drop_piece(*col, turn)
+                .map_err(|_| format!("Column {} is full", ColumnLabel::new(*col))),
+            Move::Clear(col) | Move::Anvil(col) => {
+                if self.power_up() != Some(power_ups.kind()) {
+                    return Err(format!(
+                        "The game isn't played with {} power-ups",
+                        self.power_up().unwrap_or_default().name().to_lowercase()
+                    ));
+                }
+                if !power_ups.is_available(turn) {
+                    return Err("The power-up has already been spent".to_owned());
+                }
+                board
+                    .clear_column(*col)
+                    .map_err(|_| format!("Column {} is already empty", ColumnLabel::new(*col)))?;
+                if let Move::Anvil(_) = self {
+                    board
+                        .drop_piece(*col, turn)
+                        .expect("A cleared column has room for the anvil");
+                }
+                power_ups.spend(turn);
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Move::Drop(col) => write!(f, "{}", ColumnLabel::new(*col)),
+            Move::Clear(col) => write!(f, "{}{}", CLEAR_MARKER, ColumnLabel::new(*col)),
+            Move::Anvil(col) => write!(f, "{}{}", ANVIL_MARKER, ColumnLabel::new(*col)),
+        }
+    }
+}
+
+/// Tracks which players have spent their power-up, which each of them can use once a game
+/// to clear a column or drop their anvil.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PowerUps {
+    /// The power-up both players get.
+    kind: PowerUpKind,
+    /// Whether each player has spent their power-up, player one first.
+    spent: [bool; 2],
+}
+
+impl PowerUps {
+    /// Gives each player a power-up of the given kind.
+    pub fn new(kind: PowerUpKind) -> PowerUps {
+        PowerUps {
+            kind,
+            spent: [false; 2],
+        }
+    }
+
+    /// Returns the same power-ups spent, but of another kind.
+    pub fn with_kind(&self, kind: PowerUpKind) -> PowerUps {
+        PowerUps { kind, ..*self }
+    }
+
+    /// Returns the kind of power-up the players get.
+    pub fn kind(&self) -> PowerUpKind {
+        self.kind
+    }
+
+    /// Returns whether the player can still spend their power-up.
+    pub fn is_available(&self, player: bool) -> bool {
+        !self.spent[player as usize]
+    }
+
+    /// Records that the player has spent their power-up.
+    fn spend(&mut self, player: bool) {
+        self.spent[player as usize] = true;
+    }
+}
+
+/// Returns every move the player could make, from left to right with the drops first.
+///
+/// Power-ups can only be spent when power_ups is given and the player hasn't spent theirs,
+/// and only on a column with pieces in it. Nothing can be played once the game is over.
+pub fn legal_moves(board: &Board, turn: bool, power_ups: Option<PowerUps>) -> Vec<Move> {
+    let mut moves: Vec<Move> = board
+        .legal_drops(turn)
+        .map(|(col, _)| Move::Drop(col))
+        .collect();
+
+    let Some(power_ups) = power_ups.filter(|power_ups| power_ups.is_available(turn)) else {
+        return moves;
+    };
+    if !moves.is_empty() {
+        let kind = power_ups.kind();
+        moves.extend(
+            (0..board.width())
+                .filter(|col| board.get_height(*col) > 0)
+                .map(|col| kind.spend(col)),
+        );
+    }
+
+    moves
+}
+
+/// Returns the column the player should spend their power-up in, if spending it there scores
+/// higher than their best drop.
+///
+/// Both maps are scored for the player about to move, as GameManager's get_move_scores and
+/// get_power_up_scores are. A tie goes to the drop, so the power-up is kept for later, and
+/// the leftmost of equally scored power-ups is chosen.
+pub fn choose_power_up(
+    move_scores: &HashMap<u8, isize>,
+    power_up_scores: &HashMap<u8, isize>,
+) -> Option<u8> {
+    let best_drop = move_scores.values().max().copied().unwrap_or(isize::MIN);
+
+    power_up_scores
+        .iter()
+        .filter(|(_, score)| **score > best_drop)
+        .max_by_key(|(column, score)| (**score, Reverse(**column)))
+        .map(|(column, _)| *column)
+}
+
+/// Returns the columns where the player could immediately win by dropping their anvil,
+/// which can only complete a line through the bottom row.
+pub fn winning_anvils(board: &Board, turn: bool) -> Vec<u8> {
+    let winner = match turn {
+        false => GameOver::OneWins,
+        true => GameOver::TwoWins,
+    };
+
+    (0..board.width())
+        // Crushing a column the player already holds the bottom of can't make a new line
+        .filter(|col| board.get_piece(*col, 0) == Ok(!turn))
+        .filter(|col| {
+            let mut new_board = board.clone();
+            new_board.clear_column(*col).is_ok()
+                && new_board.drop_piece(*col, turn).is_ok()
+                && is_game_over(&new_board, !turn) == winner
+        })
+        .collect()
+}
+
+/// Returns the columns with an anvil at the bottom after the moves, which stays there until
+/// the column is cleared again.
+pub fn anvil_columns(moves: &[Move]) -> Vec<u8> {
+    let mut columns = Vec::new();
+    for game_move in moves {
+        match game_move {
+            Move::Drop(_) => (),
+            Move::Clear(col) => columns.retain(|column| column != col),
+            Move::Anvil(col) => {
+                if !columns.contains(col) {
+                    columns.push(*col);
+                }
+            }
+        }
+    }
+
+    columns
+}
+
+/// Returns whether a move string spends a power-up anywhere.
+pub fn uses_power_ups(moves: &str) -> bool {
+    moves.contains(POWER_UP_MARKERS)
+}
+
+/// Parses a move string, where columns are numbered from 1, a cleared column is marked with
+/// CLEAR_MARKER and a dropped anvil with ANVIL_MARKER, e.g. "44x4a5". On boards wider than 9
+/// columns the moves are separated by MOVE_SEPARATOR, e.g. "4,10,x10". Whitespace is ignored.
+///
+/// Fails naming the first move that isn't a column. Columns past the edge of the board are
+/// only caught once the moves are played.
+pub fn parse_moves(moves: &str) -> Result<Vec<Move>, String> {
+    let mut parsed = Vec::new();
+
+    for written in split_moves(moves) {
+        let (label, power_up) = if let Some(label) = written.strip_prefix(CLEAR_MARKER) {
+            (label, Some(PowerUpKind::Clear))
+        } else if let Some(label) = written.strip_prefix(ANVIL_MARKER) {
+            (label, Some(PowerUpKind::Anvil))
+        } else {
+            (written.as_str(), None)
+        };
+        let Some(label) = ColumnLabel::parse(label) else {
+            return Err(format!(
+                "Move {} isn't a column between 1 and {}",
+                parsed.len() + 1,
+                MAX_BOARD_WIDTH
+            ));
+        };
+
+        parsed.push(match power_up {
+            Some(PowerUpKind::Clear) => Move::Clear(label.index()),
+            Some(PowerUpKind::Anvil) => Move::Anvil(label.index()),
+            None => Move::Drop(label.index()),
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// Writes moves as a move string that parse_moves reads back, e.g. "44x4".
+pub fn write_moves(moves: &[Move]) -> String {
+    join_moves(moves.iter().map(Move::to_string))
+}
+
+/// Plays out a move string from the empty board, returning the board, whose turn it is and
+/// which power-ups have been spent.
+///
+/// Player one is assumed to make the first move. Fails naming the first illegal move.
+pub fn play_moves(moves: &str) -> Result<(Board, bool, PowerUps), String> {
+    play_moves_with_config(moves, BoardConfig::STANDARD)
+}
+
+/// Plays out a move string from the empty board with the given config, see play_moves.
+///
+/// The power-ups are of the kind the first one spent is, so a game can't mix kinds.
+pub fn play_moves_with_config(
+    moves: &str,
+    config: BoardConfig,
+) -> Result<(Board, bool, PowerUps), String> {
+    let moves = parse_moves(moves)?;
+    let mut board = Board::with_config(config);
+    let mut power_ups = PowerUps::new(moves.iter().find_map(Move::power_up).unwrap_or_default());
+    let mut turn = false;
+
+    for (index, game_move) in moves.into_iter().enumerate() {
+        if game_move.column() >= board.width() {
+            return Err(format!(
+                "Move {} isn't a column between 1 and {}",
+                index + 1,
+                board.width()
+            ));
+        }
+        if is_game_over(&board, turn) != GameOver::NoWin {
+            return Err(format!(
+                "Move {} is played after the game is over",
+                index + 1
+            ));
+        }
+        game_move
+            .play(&mut board, turn, &mut power_ups)
+            .map_err(|error| match game_move {
+                Move::Drop(_) => format!("Move {} is played in a full column", index + 1),
+                Move::Clear(_) | Move::Anvil(_) => {
+                    format!("Move {} can't be played: {}", index + 1, error)
+                }
+            })?;
+        turn = !turn;
+    }
+
+    Ok((board, turn, power_ups))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::game_engine::{
+        board::Board,
+        power_ups::{
+            anvil_columns, choose_power_up, legal_moves, parse_moves, play_moves, uses_power_ups,
+            winning_anvils, write_moves, Move, PowerUpKind, PowerUps,
+        },
+    };
+
+    #[test]
+    fn notation() {
+        let moves = parse_moves("44 x4 5").unwrap();
+        assert_eq!(
+            moves,
+            vec![Move::Drop(3), Move::Drop(3), Move::Clear(3), Move::Drop(4)]
+        );
+        assert_eq!(write_moves(&moves), "44x45");
+
+        assert!(parse_moves("4x").is_err());
+        assert!(parse_moves("4,x11").is_err());
+        assert_eq!(
+            parse_moves("4,x10").unwrap(),
+            vec![Move::Drop(3), Move::Clear(9)]
+        );
+        assert!(parse_moves("4y4").is_err());
+
+        let moves = parse_moves("44a4").unwrap();
+        assert_eq!(moves.last(), Some(&Move::Anvil(3)));
+        assert_eq!(moves.last().unwrap().to_string(), "a4");
+        assert!(uses_power_ups("44a4"));
+        assert!(uses_power_ups("44x4"));
+        assert!(!uses_power_ups("444"));
+    }
+
+    #[test]
+    fn playing_clears() {
+        let (board, turn, power_ups) = play_moves("4445x4").unwrap();
+        assert_eq!(board.get_height(3), 0);
+        assert_eq!(board.get_height(4), 1);
+        assert!(turn);
+        assert!(!power_ups.is_available(false));
+        assert!(power_ups.is_available(true));
+
+        // Each player can only clear once, and only a column with pieces in it
+        assert!(play_moves("4445x46x5").is_err());
+        assert!(play_moves("4445x4x5").is_ok());
+        assert!(play_moves("x1").is_err());
+        assert!(play_moves("4455667x7").is_err());
+
+        // Columns past the edge of the board can't be played
+        assert!(play_moves("448").is_err());
+        assert!(play_moves("44x8").is_err());
+
+        // Board::from_moves agrees on games without clears
+        let (board, turn, _) = play_moves("4453").unwrap();
+        assert_eq!(board, Board::from_moves("4453").unwrap());
+        assert!(!turn);
+    }
+
+    #[test]
+    fn generating_moves() {
+        let board = Board::from_moves("44").unwrap();
+        assert_eq!(legal_moves(&board, false, None).len(), 7);
+        assert_eq!(
+            legal_moves(&board, false, Some(PowerUps::default())).last(),
+            Some(&Move::Clear(3))
+        );
+        assert_eq!(
+            legal_moves(&board, false, Some(PowerUps::default())).len(),
+            8
+        );
+
+        // Only player two has cleared, so player one can still clear the fifth column
+        let (board, turn, power_ups) = play_moves("445x4").unwrap();
+        assert_eq!(legal_moves(&board, turn, Some(power_ups)).len(), 7 + 1);
+        let (board, turn, power_ups) = play_moves("445x4x5").unwrap();
+        assert_eq!(legal_moves(&board, turn, Some(power_ups)).len(), 7);
+
+        let won = Board::from_moves("4455667").unwrap();
+        assert!(legal_moves(&won, true, Some(PowerUps::default())).is_empty());
+
+        // Anvils take the place of clears
+        let board = Board::from_moves("44").unwrap();
+        let anvils = Some(PowerUps::new(PowerUpKind::Anvil));
+        assert_eq!(
+            legal_moves(&board, false, anvils).last(),
+            Some(&Move::Anvil(3))
+        );
+        assert!(!legal_moves(&board, false, anvils).contains(&Move::Clear(3)));
+    }
+
+    #[test]
+    fn playing_anvils() {
+        // The anvil crushes both pieces in the middle column, landing as player one's piece
+        let (board, turn, power_ups) = play_moves("4454a4").unwrap();
+        assert_eq!(board.get_height(3), 1);
+        assert_eq!(board.get_piece(3, 0), Ok(false));
+        assert_eq!(board.get_height(4), 1);
+        assert!(turn);
+        assert_eq!(power_ups.kind(), PowerUpKind::Anvil);
+        assert!(!power_ups.is_available(false));
+        assert!(power_ups.is_available(true));
+
+        // Each player gets one anvil, and a game can't mix anvils with clears
+        assert!(play_moves("44a4a4").is_ok());
+        assert!(play_moves("44a45a4").is_err());
+        assert!(play_moves("44a4x4").is_err());
+        assert!(play_moves("a1").is_err());
+
+        // An anvil stays at the bottom until its column is cleared
+        let moves = parse_moves("44a45a55a4").unwrap();
+        assert_eq!(anvil_columns(&moves), vec![3, 4]);
+        assert_eq!(anvil_columns(&moves[..3]), vec![3]);
+        let moves = parse_moves("44a45x4").unwrap();
+        assert!(anvil_columns(&moves).is_empty());
+    }
+
+    #[test]
+    fn winning_with_anvils() {
+        // Player one has three along the bottom, with player two's piece blocking the fourth
+        // space, and their own piece on top of it
+        let board = Board::from_moves("1427374").unwrap();
+        assert_eq!(board.get_piece(3, 0), Ok(true));
+        assert_eq!(winning_anvils(&board, false), vec![3]);
+        assert!(winning_anvils(&board, true).is_empty());
+    }
+
+    #[test]
+    fn choosing_power_ups() {
+        let move_scores = HashMap::from([(2, 10), (3, 40)]);
+        assert_eq!(choose_power_up(&move_scores, &HashMap::new()), None);
+        assert_eq!(
+            choose_power_up(&move_scores, &HashMap::from([(1, 30), (5, 40)])),
+            None
+        );
+        assert_eq!(
+            choose_power_up(&move_scores, &HashMap::from([(1, 30), (5, 50), (6, 50)])),
+            Some(5)
+        );
+        assert_eq!(PowerUpKind::Clear.spend(5), Move::Clear(5));
+        assert_eq!(PowerUpKind::Anvil.spend(5), Move::Anvil(5));
+    }
+}
@@ -0,0 +1,522 @@
+//! Tactics puzzles, where the player to move can force a win in a set number of moves, and
+//! the levels a puzzle rush ramps up through.
+//!
+//! Puzzles are found by playing random games, without taking any immediate wins, until the
+//! solver proves a position is a forced win that takes exactly the number of moves wanted.
+//! Only boards the solver can handle can have puzzles.
+//!
+//! Puzzles on the standard board can also be kept in a puzzle database, a text file with a
+//! puzzle per line such as `44536654 3 2 double-threat`. The fields are the moves leading to
+//! the position, how many moves the win takes, the winning column and the puzzle's themes,
+//! separated by commas or `-` if it has none.
+
+use std::{fmt, fs, io, path::Path};
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::game_engine::{
+    board::Board,
+    board_config::BoardConfig,
+    column_label::ColumnLabel,
+    power_ups::{play_moves, PowerUps},
+    solver::{can_solve, solve, WinDrawLoss},
+    win_check::{is_game_over, GameOver},
+};
+
+/// The puzzle database puzzle rushes draw from, if it exists.
+pub const DEFAULT_PUZZLE_DATABASE: &str = "puzzles.txt";
+
+/// Written in place of an empty move string or a puzzle without themes.
+const EMPTY_FIELD: &str = "-";
+
+/// How many random games are played looking for a puzzle before giving up.
+const MAX_ATTEMPTS: usize = 200;
+
+/// The levels of a puzzle rush, from the first puzzle on. The last level is kept once it's
+/// reached.
+pub const RUSH_LEVELS: [PuzzleLevel; 5] = [
+    PuzzleLevel::new(BoardConfig::STANDARD, 1),
+    PuzzleLevel::new(BoardConfig::STANDARD, 2),
+    PuzzleLevel::new(BoardConfig::VARIANTS[1], 2),
+    PuzzleLevel::new(BoardConfig::STANDARD, 3),
+    PuzzleLevel::new(BoardConfig::VARIANTS[1], 3),
+];
+
+/// How many puzzles have to be solved at each level of a puzzle rush before moving up.
+pub const PUZZLES_PER_LEVEL: usize = 3;
+
+/// How hard a puzzle is, by the board it's on and how many moves the win takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PuzzleLevel {
+    pub config: BoardConfig,
+    /// How many moves the player to move needs to force the win, counting the winning move.
+    pub moves_to_win: usize,
+}
+
+/// A position where the player to move can force a win.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    board: Board,
+    /// Whose turn it is, false for player one and true for player two.
+    turn: bool,
+    /// How many moves the player to move needs to force the win, counting the winning move.
+    moves_to_win: usize,
+}
+
+/// A tactic a puzzle turns on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PuzzleTheme {
+    /// The winner makes two threats at once, so only one can be blocked.
+    DoubleThreat,
+    /// The loser is left with nothing to block, but every move they have gives the winner
+    /// somewhere to win.
+    ParitySqueeze,
+}
+
+/// A puzzle in a puzzle database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleEntry {
+    /// The moves leading to the position, as columns numbered from 1.
+    pub moves: String,
+    /// How many moves the player to move needs to force the win, counting the winning move.
+    pub moves_to_win: usize,
+    /// The only column that wins, numbered from 0.
+    pub solution: u8,
+    pub themes: Vec<PuzzleTheme>,
+}
+
+/// What happened after a move was played in a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleProgress {
+    /// The move won the game.
+    Solved,
+    /// The move let the win slip, or made it take longer.
+    Missed,
+    /// The move kept the win on track, and the other player replied in the given column.
+    Continues(u8),
+}
+
+impl PuzzleLevel {
+    /// Creates a level of puzzles on the board, winning in the given number of moves.
+    pub const fn new(config: BoardConfig, moves_to_win: usize) -> PuzzleLevel {
+        PuzzleLevel {
+            config,
+            moves_to_win,
+        }
+    }
+
+    /// Returns the level of a puzzle rush's next puzzle, after the given number of puzzles
+    /// were solved.
+    pub fn for_rush(solved: usize) -> PuzzleLevel {
+        RUSH_LEVELS[(solved / PUZZLES_PER_LEVEL).min(RUSH_LEVELS.len() - 1)]
+    }
+}
+
+impl PuzzleTheme {
+    /// Returns the theme's name, as written in puzzle databases.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PuzzleTheme::DoubleThreat => "double-threat",
+            PuzzleTheme::ParitySqueeze => "parity-squeeze",
+        }
+    }
+
+    /// Parses a theme's name.
+    pub fn from_name(name: &str) -> Option<PuzzleTheme> {
+        [PuzzleTheme::DoubleThreat, PuzzleTheme::ParitySqueeze]
+            .into_iter()
+            .find(|theme| theme.name() == name)
+    }
+}
+
+impl fmt::Display for PuzzleTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl PuzzleEntry {
+    /// Writes the entry as a line of a puzzle database, e.g. `44536654 3 2 double-threat`.
+    pub fn to_line(&self) -> String {
+        let moves = if self.moves.is_empty() {
+            EMPTY_FIELD
+        } else {
+            &self.moves
+        };
+        let themes = match self.themes.is_empty() {
+            true => EMPTY_FIELD.to_owned(),
+            false => {
+                let names: Vec<&str> = self.themes.iter().map(PuzzleTheme::name).collect();
+                names.join(",")
+            }
+        };
+
+        format!(
+            "{} {} {} {}",
+            moves,
+            self.moves_to_win,
+            ColumnLabel::new(self.solution),
+            themes
+        )
+    }
+
+    /// Parses a line written by to_line.
+    pub fn parse_line(line: &str) -> Result<PuzzleEntry, String> {
+        let malformed = || format!("Malformed puzzle '{}'", line);
+
+        let [moves, moves_to_win, solution, themes] =
+            line.split_whitespace().collect::<Vec<_>>()[..]
+        else {
+            return Err(malformed());
+        };
+
+        Ok(PuzzleEntry {
+            moves: match moves {
+                EMPTY_FIELD => String::new(),
+                moves => moves.to_owned(),
+            },
+            moves_to_win: moves_to_win.parse().map_err(|_| malformed())?,
+            solution: ColumnLabel::parse(solution)
+                .filter(|label| label.index() < BoardConfig::STANDARD.width)
+                .ok_or_else(malformed)?
+                .index(),
+            themes: match themes {
+                EMPTY_FIELD => Vec::new(),
+                themes => themes
+                    .split(',')
+                    .map(|name| PuzzleTheme::from_name(name).ok_or_else(malformed))
+                    .collect::<Result<_, _>>()?,
+            },
+        })
+    }
+
+    /// Returns whether the entry's puzzle belongs to the level.
+    pub fn is_level(&self, level: PuzzleLevel) -> bool {
+        level.config == BoardConfig::STANDARD && self.moves_to_win == level.moves_to_win
+    }
+}
+
+/// Loads the puzzles in a puzzle database.
+///
+/// A missing database is treated as having no puzzles, and lines that can't be parsed are
+/// skipped.
+pub fn load_puzzle_database(path: &Path) -> io::Result<Vec<PuzzleEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| PuzzleEntry::parse_line(line).ok())
+        .collect())
+}
+
+/// Saves the puzzles as a puzzle database, replacing the file if there is one.
+pub fn save_puzzle_database(path: &Path, entries: &[PuzzleEntry]) -> io::Result<()> {
+    let lines: Vec<String> = entries.iter().map(PuzzleEntry::to_line).collect();
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+impl Puzzle {
+    /// Creates a puzzle from the position reached by playing out a string of moves on the
+    /// standard board, given as columns numbered from 1.
+    ///
+    /// Fails if the moves aren't legal, or the player to move can't force a win.
+    pub fn from_moves(moves: &str) -> Result<Puzzle, String> {
+        let (board, turn, power_ups) = play_moves(moves)?;
+        if power_ups != PowerUps::default() {
+            return Err("Puzzles can't have cleared columns".to_owned());
+        }
+
+        match wins_in(&board, turn)? {
+            Some(moves_to_win) => Ok(Puzzle {
+                board,
+                turn,
+                moves_to_win,
+            }),
+            None => Err(format!("There's no forced win after '{}'", moves)),
+        }
+    }
+
+    /// Finds a random puzzle of the given level.
+    ///
+    /// Fails if the board is too large to solve, or no puzzle turned up after MAX_ATTEMPTS
+    /// random games.
+    pub fn generate(level: PuzzleLevel, rng: &mut impl Rng) -> Result<Puzzle, String> {
+        if level.moves_to_win == 0 {
+            return Err("Puzzles take at least one move to win".to_owned());
+        }
+        if !can_solve(level.config) {
+            return Err(format!("A {} board is too large for puzzles", level.config));
+        }
+        let cells = level.config.width as usize * level.config.height as usize;
+        for _ in 0..MAX_ATTEMPTS {
+            let mut board = Board::with_config(level.config);
+            let mut turn = false;
+
+            for moves_played in 0..cells {
+                // Early positions take too long to solve, and are rarely forced wins
+                if moves_played >= cells * 2 / 3
+                    && wins_in(&board, turn)? == Some(level.moves_to_win)
+                {
+                    return Ok(Puzzle {
+                        board,
+                        turn,
+                        moves_to_win: level.moves_to_win,
+                    });
+                }
+
+                let winning = board.winning_moves(turn);
+                let quiet: Vec<u8> = board
+                    .legal_drops(turn)
+                    .map(|(col, _)| col)
+                    .filter(|col| !winning.contains(col))
+                    .collect();
+                let Some(&col) = quiet.choose(rng) else {
+                    break;
+                };
+
+                board
+                    .drop_piece(col, turn)
+                    .expect("Legal drops should have space");
+                turn = !turn;
+            }
+        }
+
+        Err(format!(
+            "Couldn't find a win in {} on a {} board",
+            level.moves_to_win, level.config
+        ))
+    }
+
+    /// Returns the position to solve.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns whose turn it is, false for player one and true for player two.
+    pub fn turn(&self) -> bool {
+        self.turn
+    }
+
+    /// Returns how many moves are left to force the win, counting the winning move.
+    pub fn moves_to_win(&self) -> usize {
+        self.moves_to_win
+    }
+
+    /// Returns every column that wins, however long it takes, from left to right.
+    pub fn winning_columns(&self) -> Vec<u8> {
+        self.board
+            .legal_drops(self.turn)
+            .filter(|(col, board)| {
+                self.board.winning_moves(self.turn).contains(col)
+                    || matches!(solve(board, !self.turn), Ok((WinDrawLoss::Loss, _)))
+            })
+            .map(|(col, _)| col)
+            .collect()
+    }
+
+    /// Returns the columns that win as quickly as possible, from left to right.
+    pub fn solutions(&self) -> Vec<u8> {
+        self.board
+            .legal_drops(self.turn)
+            .filter(|(col, board)| self.is_quickest_win(*col, board))
+            .map(|(col, _)| col)
+            .collect()
+    }
+
+    /// Plays the column for the player solving the puzzle and, if the win is still on
+    /// track, the reply that holds out longest for the other player.
+    ///
+    /// Fails if the column is full or the puzzle is already over.
+    pub fn play(&mut self, col: u8) -> Result<PuzzleProgress, String> {
+        let mut board = self.board.clone();
+        if self.moves_to_win == 0 || board.drop_piece(col, self.turn).is_err() {
+            return Err(format!("Can't play in column {}", col + 1));
+        }
+
+        if !self.is_quickest_win(col, &board) {
+            self.moves_to_win = 0;
+            return Ok(PuzzleProgress::Missed);
+        }
+        if self.moves_to_win == 1 {
+            self.moves_to_win = 0;
+            return Ok(PuzzleProgress::Solved);
+        }
+
+        // Every reply loses, so the best holds out the longest
+        let (reply, reply_board) = board
+            .legal_drops(!self.turn)
+            .max_by_key(|(_, reply_board)| wins_in(reply_board, self.turn).ok().flatten())
+            .expect("The game should carry on after a move that doesn't win");
+
+        self.board = reply_board;
+        self.moves_to_win -= 1;
+        Ok(PuzzleProgress::Continues(reply))
+    }
+
+    /// Returns whether the column, played to give the board, wins in the moves left.
+    fn is_quickest_win(&self, col: u8, board: &Board) -> bool {
+        if self.board.winning_moves(self.turn).contains(&col) {
+            return self.moves_to_win == 1;
+        }
+
+        match solve(board, !self.turn) {
+            Ok((WinDrawLoss::Loss, plies)) => (plies + 1).div_ceil(2) == self.moves_to_win,
+            _ => false,
+        }
+    }
+}
+
+/// Returns how many moves the player to move needs to force a win, or None if they can't.
+///
+/// Fails if the board is too large to solve, or the game is already over.
+fn wins_in(board: &Board, turn: bool) -> Result<Option<usize>, String> {
+    if is_game_over(board, turn) != GameOver::NoWin {
+        return Err("The game is already over".to_owned());
+    }
+    if !board.winning_moves(turn).is_empty() {
+        return Ok(Some(1));
+    }
+
+    match solve(board, turn)? {
+        (WinDrawLoss::Win, plies) => Ok(Some(plies.div_ceil(2))),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::game_engine::{
+        board::Board,
+        board_config::BoardConfig,
+        puzzle::{
+            load_puzzle_database, save_puzzle_database, Puzzle, PuzzleEntry, PuzzleLevel,
+            PuzzleProgress, PuzzleTheme, PUZZLES_PER_LEVEL, RUSH_LEVELS,
+        },
+    };
+
+    /// A five column board where Red, to move, has two in a row in the middle of the
+    /// bottom row, so making it an open three wins in two.
+    fn open_two() -> Puzzle {
+        let mut board = Board::with_config(BoardConfig::new(5, 4, 4).unwrap());
+        for (col, color) in [(1, false), (1, true), (2, false), (2, true)] {
+            board.drop_piece(col, color).unwrap();
+        }
+
+        Puzzle {
+            board,
+            turn: false,
+            moves_to_win: 2,
+        }
+    }
+
+    #[test]
+    fn solves_a_puzzle() {
+        let mut puzzle = open_two();
+        assert_eq!(puzzle.solutions(), vec![3]);
+
+        let Ok(PuzzleProgress::Continues(reply)) = puzzle.play(3) else {
+            panic!("Playing a solution should carry on the puzzle");
+        };
+        assert_eq!(
+            puzzle.board().get_height(reply),
+            if reply == 3 { 2 } else { 1 }
+        );
+        assert_eq!(puzzle.moves_to_win(), 1);
+
+        let solutions = puzzle.solutions();
+        assert!(!solutions.is_empty());
+        assert_eq!(puzzle.play(solutions[0]), Ok(PuzzleProgress::Solved));
+        assert!(puzzle.play(0).is_err());
+    }
+
+    #[test]
+    fn misses() {
+        let mut puzzle = open_two();
+        assert_eq!(puzzle.play(0), Ok(PuzzleProgress::Missed));
+        assert_eq!(puzzle.moves_to_win(), 0);
+    }
+
+    #[test]
+    fn generates_puzzles() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for level in [RUSH_LEVELS[0], RUSH_LEVELS[2]] {
+            let puzzle = Puzzle::generate(level, &mut rng).unwrap();
+            assert_eq!(puzzle.board().config(), level.config);
+            assert_eq!(puzzle.moves_to_win(), level.moves_to_win);
+            assert!(!puzzle.solutions().is_empty());
+        }
+
+        let too_large = PuzzleLevel::new(BoardConfig::VARIANTS[3], 1);
+        assert!(Puzzle::generate(too_large, &mut rng).is_err());
+    }
+
+    #[test]
+    fn rush_levels() {
+        assert_eq!(PuzzleLevel::for_rush(0), RUSH_LEVELS[0]);
+        assert_eq!(PuzzleLevel::for_rush(PUZZLES_PER_LEVEL), RUSH_LEVELS[1]);
+        assert_eq!(PuzzleLevel::for_rush(1000), RUSH_LEVELS[4]);
+    }
+
+    #[test]
+    fn puzzles_from_moves() {
+        let puzzle = Puzzle::from_moves("6735577566566333536511772447111132").unwrap();
+        assert!(!puzzle.turn());
+        assert_eq!(puzzle.moves_to_win(), 2);
+        assert_eq!(puzzle.winning_columns(), vec![1]);
+        assert_eq!(puzzle.solutions(), vec![1]);
+
+        // Player one has already won
+        assert!(Puzzle::from_moves("4455667").is_err());
+        assert!(Puzzle::from_moves("44x4").is_err());
+    }
+
+    #[test]
+    fn database_round_trip() {
+        let path = std::env::temp_dir().join("rusty_connect_four_puzzle_database.txt");
+
+        let entries = vec![
+            PuzzleEntry {
+                moves: "6735577566566333536511772447111132".to_owned(),
+                moves_to_win: 2,
+                solution: 1,
+                themes: vec![PuzzleTheme::DoubleThreat],
+            },
+            PuzzleEntry {
+                moves: "3642343344434366661561755511551122".to_owned(),
+                moves_to_win: 3,
+                solution: 1,
+                themes: vec![PuzzleTheme::ParitySqueeze, PuzzleTheme::DoubleThreat],
+            },
+            PuzzleEntry {
+                moves: String::new(),
+                moves_to_win: 21,
+                solution: 3,
+                themes: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            entries[1].to_line(),
+            "3642343344434366661561755511551122 3 2 parity-squeeze,double-threat"
+        );
+        assert_eq!(entries[2].to_line(), "- 21 4 -");
+
+        save_puzzle_database(&path, &entries).unwrap();
+        assert_eq!(load_puzzle_database(&path).unwrap(), entries);
+
+        assert!(PuzzleEntry::parse_line("4453 2 8 -").is_err());
+        assert!(PuzzleEntry::parse_line("4453 2 3 pin").is_err());
+        assert!(entries[0].is_level(RUSH_LEVELS[1]));
+        assert!(!entries[0].is_level(RUSH_LEVELS[2]));
+
+        fs::remove_file(&path).unwrap();
+        assert!(load_puzzle_database(&path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+//! Mines self-play games for puzzles: positions where the player to move has exactly one
+//! winning move, and the win takes them several moves to force.
+//!
+//! Each position is checked with the solver, so only the second half of a game is mined,
+//! where solving is quick. Puzzles are tagged with the tactics that come up along the
+//! winning line, by playing it out with the loser holding out as long as they can.
+
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::{
+        column_label::ColumnLabel,
+        move_events::{move_events, MoveEvent},
+        power_ups::play_moves,
+        puzzle::{Puzzle, PuzzleEntry, PuzzleProgress, PuzzleTheme},
+        rating::{play_game_moves, EngineConfig, OPENING_MOVES},
+    },
+};
+
+/// The fewest moves a mined puzzle's win can take by default, counting the winning move.
+pub const MIN_MOVES_TO_WIN: usize = 3;
+
+/// How many moves into a game the mining starts, since earlier positions take too long to
+/// solve.
+const FIRST_MINED_PLY: usize = (BOARD_WIDTH as usize * BOARD_HEIGHT as usize) / 2;
+
+/// Finds the puzzles in a game, given as a move string of columns numbered from 1.
+///
+/// A position is a puzzle if the player to move has exactly one winning move, and the win
+/// takes at least min_moves_to_win moves. The position after the game's last move isn't
+/// considered, since the game is over.
+///
+/// Fails if the moves aren't legal.
+pub fn mine_game(moves: &str, min_moves_to_win: usize) -> Result<Vec<PuzzleEntry>, String> {
+    play_moves(moves)?;
+    let mut entries = Vec::new();
+
+    for ply in FIRST_MINED_PLY..moves.len() {
+        let position = &moves[..ply];
+        // Most positions aren't forced wins at all
+        let Ok(puzzle) = Puzzle::from_moves(position) else {
+            continue;
+        };
+        if puzzle.moves_to_win() < min_moves_to_win {
+            continue;
+        }
+
+        let [solution] = puzzle.winning_columns()[..] else {
+            continue;
+        };
+        entries.push(PuzzleEntry {
+            moves: position.to_owned(),
+            moves_to_win: puzzle.moves_to_win(),
+            solution,
+            themes: themes(puzzle),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Plays games between the two engines from random openings, and mines them for puzzles
+/// like mine_game. A position that turns up in more than one game is only kept once.
+pub fn mine_self_play(
+    games: usize,
+    players: [EngineConfig; 2],
+    min_moves_to_win: usize,
+) -> Result<Vec<PuzzleEntry>, String> {
+    let mut rng = rand::thread_rng();
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+
+    for _ in 0..games {
+        let opening =
+            ColumnLabel::move_string((0..OPENING_MOVES).map(|_| rng.gen_range(0..BOARD_WIDTH)));
+        let (_, moves) = play_game_moves(players, &opening)?;
+
+        for entry in mine_game(&moves, min_moves_to_win)? {
+            if seen.insert(entry.moves.clone()) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Returns the themes of the puzzle, found by playing out the winning line.
+fn themes(mut puzzle: Puzzle) -> Vec<PuzzleTheme> {
+    let mut themes = Vec::new();
+    let mut add = |theme| {
+        if !themes.contains(&theme) {
+            themes.push(theme);
+        }
+    };
+
+    while let Some(&column) = puzzle.solutions().first() {
+        let board = puzzle.board().clone();
+        let player = puzzle.turn();
+
+        let mut after = board.clone();
+        // The winning move itself can leave more lines open, but there's no threat left
+        if puzzle.moves_to_win() > 1
+            && after.drop_piece(column, player).is_ok()
+            && after.winning_moves(player).len() >= 2
+        {
+            add(PuzzleTheme::DoubleThreat);
+        }
+        if move_events(&board, player, column)
+            .iter()
+            .any(|event| matches!(event, MoveEvent::ZugzwangReached { .. }))
+        {
+            add(PuzzleTheme::ParitySqueeze);
+        }
+
+        match puzzle.play(column) {
+            Ok(PuzzleProgress::Continues(_)) => (),
+            _ => break,
+        }
+    }
+
+    themes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        puzzle::{PuzzleEntry, PuzzleTheme},
+        puzzle_miner::{mine_game, MIN_MOVES_TO_WIN},
+    };
+
+    #[test]
+    fn finds_double_threats() {
+        // Red's only win is to make two threats at once
+        let position = "6735577566566333536511772447111132";
+        let entries = mine_game(&format!("{}2", position), 2).unwrap();
+
+        let entry = entries
+            .iter()
+            .find(|entry| entry.moves == position)
+            .expect("The position should be a puzzle");
+        assert_eq!(
+            *entry,
+            PuzzleEntry {
+                moves: position.to_owned(),
+                moves_to_win: 2,
+                solution: 1,
+                themes: vec![PuzzleTheme::DoubleThreat],
+            }
+        );
+
+        // The win is too short to be mined by default
+        let entries = mine_game(&format!("{}2", position), MIN_MOVES_TO_WIN).unwrap();
+        assert!(entries.iter().all(|entry| entry.moves != position));
+    }
+
+    #[test]
+    fn finds_parity_squeezes() {
+        let position = "3642343344434366661561755511551122";
+        let entries = mine_game(&format!("{}2", position), MIN_MOVES_TO_WIN).unwrap();
+
+        let entry = entries
+            .iter()
+            .find(|entry| entry.moves == position)
+            .expect("The position should be a puzzle");
+        assert_eq!(entry.moves_to_win, 3);
+        assert_eq!(entry.solution, 1);
+        assert!(entry.themes.contains(&PuzzleTheme::ParitySqueeze));
+
+        assert!(mine_game("48", MIN_MOVES_TO_WIN).is_err());
+    }
+}
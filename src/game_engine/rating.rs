@@ -0,0 +1,430 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::{
+    consts::BOARD_WIDTH,
+    game_engine::{
+        column_label::ColumnLabel, game_manager::GameManager, heuristics::HeuristicWeights,
+        monte_carlo::EngineAlgorithm, win_check::GameOver,
+    },
+};
+
+/// The fixed depth engines a configuration is rated against, and the ratings they're
+/// anchored at.
+///
+/// Each two moves of lookahead is anchored 300 points higher. The ratings are only meaningful
+/// compared to each other, not to ratings of people.
+pub const BASELINES: [(usize, f64); 4] = [(2, 800.0), (4, 1100.0), (6, 1400.0), (8, 1700.0)];
+
+/// How many random moves each rating game starts with, so that games between the same
+/// engines aren't all identical.
+pub const OPENING_MOVES: usize = 2;
+
+/// The lowest and highest ratings that can be estimated, since a configuration that wins or
+/// loses every game doesn't have a finite rating.
+const RATING_RANGE: (f64, f64) = (0.0, 3000.0);
+
+/// How many standard errors either side of the estimate cover 95% of the possible ratings.
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// How many states or rollouts an engine thinking for a movetime adds between checks of
+/// the time.
+const THINKING_CHUNK_SIZE: usize = 1024;
+
+/// How an engine taking part in a rating game decides how much to think about each move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EngineConfig {
+    /// Thinks for the given time, like the app's engine.
+    MoveTime(Duration),
+    /// Looks exactly the given number of moves ahead.
+    FixedDepth(usize),
+    /// Adds the given number of states to the decision tree, or plays that many rollouts
+    /// for the Monte Carlo algorithms.
+    Nodes(usize),
+}
+
+/// An engine taking part in a game, along with how much it thinks about each move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EngineSetup {
+    pub budget: EngineConfig,
+    pub algorithm: EngineAlgorithm,
+    pub weights: HeuristicWeights,
+}
+
+impl From<EngineConfig> for EngineSetup {
+    /// Sets up the default engine, thinking as much as the config says.
+    fn from(budget: EngineConfig) -> Self {
+        EngineSetup {
+            budget,
+            algorithm: EngineAlgorithm::default(),
+            weights: HeuristicWeights::default(),
+        }
+    }
+}
+
+impl EngineSetup {
+    /// Starts a game for the engine from the position reached by the opening's moves.
+    fn start_game(&self, opening: &str) -> Result<GameManager, String> {
+        let mut manager = GameManager::from_moves(opening)?;
+        manager.set_algorithm(self.algorithm);
+        manager.set_heuristic_weights(self.weights);
+
+        Ok(manager)
+    }
+
+    /// Thinks about the manager's position as much as the budget allows.
+    ///
+    /// The hybrid algorithm splits its budget between states and rollouts, and a fixed
+    /// depth only ever grows the decision tree.
+    fn think(&self, manager: &mut GameManager) {
+        match self.budget {
+            EngineConfig::MoveTime(movetime) => {
+                let start = Instant::now();
+
+                while start.elapsed() < movetime && self.grow(manager, THINKING_CHUNK_SIZE) {}
+            }
+            EngineConfig::FixedDepth(depth) => {
+                manager.try_generate_to_depth(depth);
+            }
+            EngineConfig::Nodes(amount) => {
+                self.grow(manager, amount);
+            }
+        }
+    }
+
+    /// Adds the given number of states to the decision tree, or plays that many rollouts,
+    /// as the algorithm needs.
+    ///
+    /// Returns false once there's nothing left to explore.
+    fn grow(&self, manager: &mut GameManager, amount: usize) -> bool {
+        let (nodes, rollouts) = match self.algorithm {
+            EngineAlgorithm::AlphaBeta => (amount, 0),
+            EngineAlgorithm::Mcts => (0, amount),
+            EngineAlgorithm::Hybrid => (amount / 2, amount - amount / 2),
+        };
+
+        let mut unexplored = false;
+        if nodes > 0 {
+            unexplored |= manager.try_generate_x_states(nodes) >= nodes;
+        }
+        if rollouts > 0 {
+            unexplored |= manager.run_rollouts(rollouts) == rollouts;
+        }
+
+        unexplored
+    }
+
+    /// Chooses a move in the manager's position, always picking the highest scoring move.
+    fn choose_move(&self, manager: &mut GameManager) -> Result<u8, String> {
+        self.think(manager);
+
+        manager
+            .get_move_scores()
+            .into_iter()
+            // Ties are broken in favor of the lowest column, to be deterministic
+            .max_by_key(|(column, score)| (*score, BOARD_WIDTH - column))
+            .map(|(column, _)| column)
+            .ok_or_else(|| "No moves could be evaluated".to_owned())
+    }
+}
+
+/// A rating estimated from a set of games, along with the margin of error either side of it
+/// that the true rating is 95% likely to be within.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatingEstimate {
+    pub rating: f64,
+    pub margin: f64,
+}
+
+/// Returns a random opening of OPENING_MOVES moves, as a move string.
+pub fn random_opening() -> String {
+    let mut rng = rand::thread_rng();
+
+    ColumnLabel::move_string((0..OPENING_MOVES).map(|_| rng.gen_range(0..BOARD_WIDTH)))
+}
+
+/// Plays out a game between two engines from the position reached by the opening's moves,
+/// with players[0] playing as player one.
+///
+/// Returns how the game ended.
+pub fn play_game<T: Into<EngineSetup>>(players: [T; 2], opening: &str) -> Result<GameOver, String> {
+    play_game_moves(players, opening).map(|(result, _)| result)
+}
+
+/// Plays out a game like play_game.
+///
+/// Returns how the game ended, along with every move of the game including the opening's,
+/// as a move string.
+pub fn play_game_moves<T: Into<EngineSetup>>(
+    players: [T; 2],
+    opening: &str,
+) -> Result<(GameOver, String), String> {
+    let players = players.map(Into::into);
+    // Each engine keeps its own decision tree between moves
+    let mut managers = [
+        players[0].start_game(opening)?,
+        players[1].start_game(opening)?,
+    ];
+    let mut moves = opening.to_owned();
+
+    loop {
+        let game_state = managers[0].is_game_over();
+        if game_state != GameOver::NoWin {
+            return Ok((game_state, moves));
+        }
+
+        let player = managers[0].get_turn() as usize;
+        let column = players[player].choose_move(&mut managers[player])?;
+
+        for manager in managers.iter_mut() {
+            manager.make_move(column)?;
+        }
+        moves.push_str(&ColumnLabel::new(column).to_string());
+    }
+}
+
+/// Returns the score of a game for player one, 1 for a win, 0.5 for a tie and 0 for a loss.
+pub fn score_for_player_one(result: GameOver) -> f64 {
+    match result {
+        GameOver::OneWins => 1.0,
+        GameOver::TwoWins => 0.0,
+        GameOver::Tie | GameOver::NoWin => 0.5,
+    }
+}
+
+/// The results of a match between two engines, from the first engine's point of view.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchResults {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl MatchResults {
+    /// Records a game the first engine scored the given score in, 1 for a win, 0.5 for a
+    /// tie and 0 for a loss.
+    pub fn record(&mut self, score: f64) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    /// Returns how many games have been recorded.
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the first engine's average score, along with the margin of error either side
+    /// of it that its true average score is 95% likely to be within.
+    ///
+    /// Returns None if no games have been recorded.
+    pub fn score(&self) -> Option<(f64, f64)> {
+        if self.games() == 0 {
+            return None;
+        }
+
+        let games = self.games() as f64;
+        let score = (self.wins as f64 + self.draws as f64 / 2.0) / games;
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+
+        Some((score, CONFIDENCE_Z * (variance / games).sqrt()))
+    }
+
+    /// Estimates how much higher the first engine is rated than the second.
+    ///
+    /// Returns None if no games have been recorded.
+    pub fn rating_difference(&self) -> Option<RatingEstimate> {
+        // The second engine is anchored in the middle of the range, so the difference can go
+        // either way
+        let anchor = (RATING_RANGE.0 + RATING_RANGE.1) / 2.0;
+        let results: Vec<_> = [(self.wins, 1.0), (self.draws, 0.5), (self.losses, 0.0)]
+            .into_iter()
+            .flat_map(|(games, score)| vec![(anchor, score); games])
+            .collect();
+
+        estimate_rating(&results).map(|estimate| RatingEstimate {
+            rating: estimate.rating - anchor,
+            ..estimate
+        })
+    }
+}
+
+/// Returns the expected score of a player against an opponent, using the Elo formula.
+fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
+
+/// Estimates the rating that best explains the given results, each of which is an
+/// opponent's rating and the score achieved against them.
+///
+/// Returns None if there are no results.
+pub fn estimate_rating(results: &[(f64, f64)]) -> Option<RatingEstimate> {
+    if results.is_empty() {
+        return None;
+    }
+
+    let total_score: f64 = results.iter().map(|(_, score)| score).sum();
+    let total_expected = |rating: f64| -> f64 {
+        results
+            .iter()
+            .map(|(opponent, _)| expected_score(rating, *opponent))
+            .sum()
+    };
+
+    // The expected score only grows with the rating, so a binary search finds the rating
+    // where it matches the actual score
+    let (mut low, mut high) = RATING_RANGE;
+    for _ in 0..64 {
+        let middle = (low + high) / 2.0;
+
+        if total_expected(middle) < total_score {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    let rating = (low + high) / 2.0;
+
+    // The standard error comes from how sharply the likelihood peaks around the estimate
+    let scale = 10f64.ln() / 400.0;
+    let information: f64 = results
+        .iter()
+        .map(|(opponent, _)| {
+            let expected = expected_score(rating, *opponent);
+            scale * scale * expected * (1.0 - expected)
+        })
+        .sum();
+
+    Some(RatingEstimate {
+        rating,
+        margin: CONFIDENCE_Z / information.sqrt(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        game_manager::GameManager,
+        heuristics::HeuristicWeights,
+        monte_carlo::EngineAlgorithm,
+        rating::{
+            estimate_rating, play_game, play_game_moves, score_for_player_one, EngineConfig,
+            EngineSetup, MatchResults,
+        },
+        win_check::GameOver,
+    };
+
+    #[test]
+    fn even_results_match_the_opponent() {
+        let estimate = estimate_rating(&[(1000.0, 1.0), (1000.0, 0.0)]).unwrap();
+
+        assert!((estimate.rating - 1000.0).abs() < 1.0);
+        assert!(estimate.margin > 0.0);
+
+        assert_eq!(estimate_rating(&[]), None);
+    }
+
+    #[test]
+    fn better_results_give_higher_ratings() {
+        let mostly_wins = estimate_rating(&[(1000.0, 1.0), (1000.0, 1.0), (1000.0, 0.5)]);
+        let mostly_losses = estimate_rating(&[(1000.0, 0.0), (1000.0, 0.0), (1000.0, 0.5)]);
+
+        assert!(mostly_wins.unwrap().rating > 1000.0);
+        assert!(mostly_losses.unwrap().rating < 1000.0);
+
+        // More games narrow the margin of error
+        let few = estimate_rating(&[(1000.0, 1.0), (1000.0, 0.0)]).unwrap();
+        let many = estimate_rating(&[(1000.0, 1.0), (1000.0, 0.0)].repeat(8)).unwrap();
+        assert!(many.margin < few.margin);
+    }
+
+    #[test]
+    fn games_are_played_to_the_end() {
+        let players = [EngineConfig::FixedDepth(1), EngineConfig::FixedDepth(2)];
+        let result = play_game(players, "44").unwrap();
+
+        assert_ne!(result, GameOver::NoWin);
+        assert_eq!(score_for_player_one(GameOver::Tie), 0.5);
+
+        // An already finished game is over before it starts
+        let result = play_game(players, "4455667").unwrap();
+        assert_eq!(result, GameOver::OneWins);
+
+        let (result, moves) = play_game_moves(players, "44").unwrap();
+        assert!(moves.starts_with("44"));
+        assert_eq!(
+            GameManager::from_moves(&moves).unwrap().is_game_over(),
+            result
+        );
+    }
+
+    #[test]
+    fn engine_setups_play_games() {
+        let mcts = EngineSetup {
+            budget: EngineConfig::Nodes(200),
+            algorithm: EngineAlgorithm::Mcts,
+            weights: HeuristicWeights::default(),
+        };
+        let no_center = EngineSetup {
+            budget: EngineConfig::Nodes(200),
+            algorithm: EngineAlgorithm::Hybrid,
+            weights: HeuristicWeights {
+                center_piece: 0,
+                ..Default::default()
+            },
+        };
+
+        let (result, moves) = play_game_moves([mcts, no_center], "44").unwrap();
+        assert_ne!(result, GameOver::NoWin);
+        assert_eq!(
+            GameManager::from_moves(&moves).unwrap().is_game_over(),
+            result
+        );
+
+        let depth = EngineSetup::from(EngineConfig::FixedDepth(2));
+        assert_eq!(depth.algorithm, EngineAlgorithm::AlphaBeta);
+        assert_ne!(play_game([depth, mcts], "").unwrap(), GameOver::NoWin);
+    }
+
+    #[test]
+    fn match_results() {
+        let mut results = MatchResults::default();
+        assert_eq!(results.score(), None);
+        assert_eq!(results.rating_difference(), None);
+
+        for score in [1.0, 0.5, 0.0, 1.0] {
+            results.record(score);
+        }
+        assert_eq!(
+            results,
+            MatchResults {
+                wins: 2,
+                draws: 1,
+                losses: 1
+            }
+        );
+        assert_eq!(results.games(), 4);
+
+        let (score, margin) = results.score().unwrap();
+        assert_eq!(score, 0.625);
+        assert!(margin > 0.0 && margin < 1.0);
+        assert!(results.rating_difference().unwrap().rating > 0.0);
+
+        // Evenly matched engines are rated the same
+        let even = MatchResults {
+            wins: 3,
+            draws: 2,
+            losses: 3,
+        };
+        assert_eq!(even.score().unwrap().0, 0.5);
+        assert!(even.rating_difference().unwrap().rating.abs() < 1.0);
+    }
+}
@@ -0,0 +1,205 @@
+use std::{fmt, time::Duration};
+
+use crate::game_engine::{
+    column_label::ColumnLabel,
+    game_manager::GameManager,
+    game_record::{evaluate_move, format_score, GameRecord, MoveAnnotation, BLUNDER_THRESHOLD},
+};
+
+/// Replays games with the engine, evaluating every move and marking the blunders, to give
+/// an annotated transcript of the game.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use rusty_connect_four::game_engine::record_annotator::RecordAnnotator;
+///
+/// // Red lets Blue win straight away
+/// let transcript = RecordAnnotator::new(Duration::from_millis(20))
+///     .annotate("441")
+///     .unwrap();
+/// assert_eq!(transcript.moves.len(), 3);
+/// assert!(transcript.to_string().starts_with("1. Red 4 {score"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RecordAnnotator {
+    /// How long the engine thinks about each position.
+    movetime: Duration,
+    /// How far a move can fall short of the best move's score before it's a blunder.
+    blunder_threshold: isize,
+}
+
+/// A move of an annotated transcript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnnotatedMove {
+    /// The column the piece was dropped in, numbered from 0.
+    pub column: u8,
+    /// Who played the move, false for player one and true for player two.
+    pub player: bool,
+    /// The engine's evaluation of the move and of its best alternative.
+    pub annotation: MoveAnnotation,
+    /// Whether the score swing from the best alternative was above the threshold.
+    pub is_blunder: bool,
+}
+
+/// A game's moves along with the engine's evaluation of each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedTranscript {
+    /// How many moves were played before the transcript's first move.
+    pub first_ply: usize,
+    pub moves: Vec<AnnotatedMove>,
+}
+
+impl RecordAnnotator {
+    /// Creates an annotator that lets the engine think for the given time at each position,
+    /// marking blunders by the same threshold as MoveAnnotation::is_blunder.
+    pub fn new(movetime: Duration) -> RecordAnnotator {
+        RecordAnnotator {
+            movetime,
+            blunder_threshold: BLUNDER_THRESHOLD,
+        }
+    }
+
+    /// Sets how far a move can fall short of the best move's score before it's marked as a
+    /// blunder. Throwing away a win or walking into a loss is always a blunder.
+    pub fn with_blunder_threshold(mut self, threshold: isize) -> RecordAnnotator {
+        self.blunder_threshold = threshold;
+        self
+    }
+
+    /// Annotates the game played out by a string of moves, given as columns numbered from 1,
+    /// e.g. "4453".
+    ///
+    /// Fails if the moves aren't legal, or a column was cleared.
+    pub fn annotate(&self, moves: &str) -> Result<AnnotatedTranscript, String> {
+        self.annotate_record(&GameRecord::from_moves(moves)?)
+    }
+
+    /// Annotates the main line of a record, replaying it from its setup. Any annotations
+    /// the record already has are ignored.
+    pub fn annotate_record(&self, record: &GameRecord) -> Result<AnnotatedTranscript, String> {
+        let mut manager = GameManager::from_moves(record.setup())?;
+        let mut moves = Vec::new();
+
+        for recorded in &record.moves {
+            let annotation = evaluate_move(&mut manager, recorded.column, self.movetime)?;
+            moves.push(AnnotatedMove {
+                column: recorded.column,
+                player: manager.get_turn(),
+                annotation,
+                is_blunder: annotation.is_blunder_by(self.blunder_threshold),
+            });
+
+            manager.make_move(recorded.column)?;
+        }
+
+        Ok(AnnotatedTranscript {
+            first_ply: record.first_ply(),
+            moves,
+        })
+    }
+}
+
+impl AnnotatedTranscript {
+    /// Returns the numbers of the moves that were blunders, counting from the first move of
+    /// the game.
+    pub fn blunders(&self) -> Vec<usize> {
+        self.moves
+            .iter()
+            .enumerate()
+            .filter(|(_, annotated)| annotated.is_blunder)
+            .map(|(index, _)| self.first_ply + index + 1)
+            .collect()
+    }
+}
+
+impl fmt::Display for AnnotatedTranscript {
+    /// Writes a line per move, e.g. `7. Red 1?? {score loss, best 3 win}`, with blunders
+    /// marked by `??`, followed by a line listing the blunders.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |player: bool| if player { "Blue" } else { "Red" };
+
+        for (index, annotated) in self.moves.iter().enumerate() {
+            let annotation = annotated.annotation;
+            writeln!(
+                f,
+                "{}. {} {}{} {{score {}, best {} {}}}",
+                self.first_ply + index + 1,
+                name(annotated.player),
+                ColumnLabel::new(annotated.column),
+                if annotated.is_blunder { "??" } else { "" },
+                format_score(annotation.score),
+                ColumnLabel::new(annotation.best_column),
+                format_score(annotation.best_score)
+            )?;
+        }
+
+        let blunders: Vec<String> = self
+            .moves
+            .iter()
+            .enumerate()
+            .filter(|(_, annotated)| annotated.is_blunder)
+            .map(|(index, annotated)| {
+                let number = self.first_ply + index + 1;
+                format!("{} by {}", number, name(annotated.player))
+            })
+            .collect();
+        match blunders.is_empty() {
+            true => writeln!(f, "No blunders"),
+            false => writeln!(f, "Blunders: {}", blunders.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game_engine::{
+        game_record::{GameRecord, SETUP_TAG},
+        record_annotator::RecordAnnotator,
+    };
+
+    #[test]
+    fn marks_blunders() {
+        // Blue leaves Red's three in the middle column open, and Red doesn't take the win
+        let annotator = RecordAnnotator::new(Duration::from_millis(20));
+        let transcript = annotator.annotate("4141412").unwrap();
+
+        assert_eq!(transcript.moves.len(), 7);
+        assert_eq!(transcript.blunders(), vec![6, 7]);
+        let missed = transcript.moves[6];
+        assert!(!missed.player);
+        assert_eq!(missed.annotation.best_column, 3);
+        assert_eq!(missed.annotation.best_score, isize::MAX);
+
+        let text = transcript.to_string();
+        assert!(text.starts_with("1. Red 4 {score "));
+        assert!(text.contains("\n7. Red 2?? {score "));
+        assert!(text.ends_with("Blunders: 6 by Blue, 7 by Red\n"));
+
+        // A threshold no swing can reach still marks the missed wins
+        let transcript = annotator
+            .with_blunder_threshold(isize::MAX)
+            .annotate("4141412")
+            .unwrap();
+        assert_eq!(transcript.blunders(), vec![6, 7]);
+    }
+
+    #[test]
+    fn replays_from_setup() {
+        let mut record = GameRecord::from_moves("412").unwrap();
+        record.set_tag(SETUP_TAG, "4141");
+
+        let transcript = RecordAnnotator::new(Duration::from_millis(20))
+            .annotate_record(&record)
+            .unwrap();
+        assert_eq!(transcript.first_ply, 4);
+        assert_eq!(transcript.blunders(), vec![6, 7]);
+        assert!(transcript.to_string().starts_with("5. Red 4 {score "));
+
+        assert!(RecordAnnotator::new(Duration::ZERO).annotate("48").is_err());
+    }
+}
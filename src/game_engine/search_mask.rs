@@ -0,0 +1,53 @@
+/// The columns a search is allowed to choose its move from, e.g. for a puzzle or a handicap.
+///
+/// Only the move being chosen is restricted, the replies searched beneath it are not. Columns
+/// are numbered from 0, in the game's orientation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMask {
+    /// A bit for each column, set if the column is allowed.
+    columns: u32,
+}
+
+impl Default for SearchMask {
+    fn default() -> SearchMask {
+        SearchMask::ALL
+    }
+}
+
+impl SearchMask {
+    /// Allows every column.
+    pub const ALL: SearchMask = SearchMask { columns: u32::MAX };
+
+    /// Allows only the given columns.
+    pub fn only(columns: &[u8]) -> SearchMask {
+        SearchMask {
+            columns: columns
+                .iter()
+                .filter(|&&column| column < u32::BITS as u8)
+                .fold(0, |mask, column| mask | 1 << column),
+        }
+    }
+
+    /// Returns whether the column is allowed.
+    pub fn allows(&self, column: u8) -> bool {
+        column < u32::BITS as u8 && self.columns & 1 << column != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::search_mask::SearchMask;
+
+    #[test]
+    fn allowed_columns() {
+        let mask = SearchMask::only(&[0, 3, 6]);
+        assert!(mask.allows(0));
+        assert!(mask.allows(3));
+        assert!(!mask.allows(1));
+        assert!(!mask.allows(40));
+
+        assert!(SearchMask::ALL.allows(9));
+        assert_eq!(SearchMask::default(), SearchMask::ALL);
+        assert!(!SearchMask::only(&[]).allows(0));
+    }
+}
@@ -0,0 +1,85 @@
+//! Measures how the engine's search of the current position is going, for debugging and
+//! tuning it.
+
+use std::time::Duration;
+
+/// How the search has gone since the last move was made.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SearchStats {
+    /// How many states had their children generated.
+    pub expanded: usize,
+    /// How many times a board was looked up in the transposition table, which is done for
+    /// every child generated.
+    pub lookups: usize,
+    /// How many of the lookups found a state that was already stored.
+    pub hits: usize,
+    /// How many states are waiting in the layer generator's buffers to be expanded.
+    pub frontier: usize,
+    /// The time spent growing the decision tree.
+    pub generation_time: Duration,
+    /// The time spent scoring moves with alpha-beta pruning.
+    pub evaluation_time: Duration,
+}
+
+impl SearchStats {
+    /// Returns how many states were added to the decision tree, rather than found already
+    /// stored.
+    pub fn new_states(&self) -> usize {
+        self.lookups - self.hits
+    }
+
+    /// Returns how many states were added to the decision tree each second spent growing it.
+    pub fn nodes_per_second(&self) -> f64 {
+        match self.generation_time.as_secs_f64() {
+            secs if secs > 0.0 => self.new_states() as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns how many new states each expanded state added on average, which is less than
+    /// the number of legal moves once transpositions are shared.
+    pub fn branching_factor(&self) -> f64 {
+        match self.expanded {
+            0 => 0.0,
+            expanded => self.new_states() as f64 / expanded as f64,
+        }
+    }
+
+    /// Returns the fraction of transposition table lookups that found a stored state.
+    pub fn hit_rate(&self) -> f64 {
+        match self.lookups {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game_engine::search_stats::SearchStats;
+
+    #[test]
+    fn derived_stats() {
+        let stats = SearchStats {
+            expanded: 100,
+            lookups: 400,
+            hits: 100,
+            frontier: 250,
+            generation_time: Duration::from_millis(500),
+            evaluation_time: Duration::from_millis(20),
+        };
+
+        assert_eq!(stats.new_states(), 300);
+        assert_eq!(stats.nodes_per_second(), 600.0);
+        assert_eq!(stats.branching_factor(), 3.0);
+        assert_eq!(stats.hit_rate(), 0.25);
+
+        // Nothing is divided by zero before the search starts
+        let empty = SearchStats::default();
+        assert_eq!(empty.nodes_per_second(), 0.0);
+        assert_eq!(empty.branching_factor(), 0.0);
+        assert_eq!(empty.hit_rate(), 0.0);
+    }
+}
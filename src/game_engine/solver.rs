@@ -0,0 +1,568 @@
+//! An exact solver, which proves whether a position is won, drawn or lost with perfect play
+//! and how long that takes, rather than estimating it like the decision tree.
+//!
+//! Positions are held as bitboards, with each column taking up height + 1 bits so that the
+//! spare bit at the top keeps lines from wrapping between columns. Only boards whose bits fit
+//! in a u64 can be solved, which covers the standard board and the 8x7 variant.
+//!
+//! The search is negamax with alpha-beta pruning, narrowed to null windows to find the exact
+//! score. Scores are from the point of view of the player to move, and count how many of that
+//! player's pieces would be left unplayed when the game ends, so quicker wins score higher.
+//!
+//! Misère games, where four in a row loses, can't be pruned the same way, so they're solved
+//! by a plain negamax over every line of play instead. That's only quick enough near the
+//! end of a game.
+
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::{
+    consts::MAX_BOARD_WIDTH,
+    game_engine::{board::Board, board_config::BoardConfig, variant_rules::VariantRules},
+};
+
+/// How many entries the solver's transposition table has. A prime, so that keys spread
+/// evenly over it.
+const TABLE_SIZE: usize = 2_097_143;
+
+/// The result of a game with perfect play, for the player about to move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WinDrawLoss {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Solves the board for the player about to move, false being player one and true player
+/// two.
+///
+/// Returns the result with perfect play, where the winner wins as quickly as possible and the
+/// loser holds out as long as possible, along with how many moves it takes to reach the
+/// result. A drawn game takes until the board is full.
+///
+/// Fails if the board is too large to solve, or the game is already over.
+pub fn solve(board: &Board, turn: bool) -> Result<(WinDrawLoss, usize), String> {
+    let position = solvable_position(board, turn)?;
+    let cells = position.layout.cells();
+
+    let mut solver = Solver::new(board.config());
+    let score = solver.solve(&position);
+
+    let result = match score {
+        0 => (WinDrawLoss::Draw, cells - position.moves),
+        1.. => (WinDrawLoss::Win, plies_to_win(cells, position.moves, score)),
+        _ => (
+            WinDrawLoss::Loss,
+            plies_to_win(cells, position.moves + 1, -score) + 1,
+        ),
+    };
+
+    Ok(result)
+}
+
+/// Solves the board for the player about to move like solve, but under the given rules.
+pub fn solve_with_rules(
+    board: &Board,
+    turn: bool,
+    rules: VariantRules,
+) -> Result<(WinDrawLoss, usize), String> {
+    match rules {
+        VariantRules::Standard => solve(board, turn),
+        VariantRules::Misere => {
+            let position = solvable_position(board, turn)?;
+
+            let mut solver = MisereSolver::new(board.config());
+            let rank = solver.negamax(&position);
+
+            Ok(match rank {
+                0 => (WinDrawLoss::Draw, position.layout.cells() - position.moves),
+                1.. => (WinDrawLoss::Win, (solver.win - rank) as usize),
+                _ => (WinDrawLoss::Loss, (rank + solver.win) as usize),
+            })
+        }
+    }
+}
+
+/// Returns whether boards with the given config are small enough to solve.
+pub fn can_solve(config: BoardConfig) -> bool {
+    config.width as u32 * (config.height as u32 + 1) <= u64::BITS
+}
+
+/// Converts the board into a position the solver can search, failing if the board is too
+/// large to fit in a bitboard or the game is already over.
+fn solvable_position(board: &Board, turn: bool) -> Result<Position, String> {
+    let config = board.config();
+    if !can_solve(config) {
+        return Err(format!("A {} board is too large to solve", config));
+    }
+
+    let position = Position::from_board(board, turn);
+    if position.moves == position.layout.cells()
+        || position.layout.is_aligned(position.current ^ position.mask)
+    {
+        return Err("The game is already over".to_owned());
+    }
+
+    Ok(position)
+}
+
+/// Returns how many moves it takes the player to move, with the given number of moves
+/// played, to win with the given score.
+fn plies_to_win(cells: usize, moves: usize, score: isize) -> usize {
+    // A score is how many of the winner's pieces are left when they win, which narrows down
+    // how many moves were played before the winning one to two, only one of which is theirs
+    let before = cells + 1 - 2 * score as usize;
+    let before = before - (before - moves) % 2;
+
+    before - moves + 1
+}
+
+/// Where a board's cells are in its bitboards.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    config: BoardConfig,
+    /// A bit at the bottom of each column.
+    bottom: u64,
+    /// Every cell on the board, leaving out the spare bit above each column.
+    board: u64,
+}
+
+impl Layout {
+    fn new(config: BoardConfig) -> Layout {
+        let mut layout = Layout {
+            config,
+            bottom: 0,
+            board: 0,
+        };
+        for column in 0..config.width {
+            layout.bottom |= layout.bottom_mask(column);
+            layout.board |= layout.column_mask(column);
+        }
+
+        layout
+    }
+
+    /// Returns how many cells the board has.
+    fn cells(&self) -> usize {
+        self.config.width as usize * self.config.height as usize
+    }
+
+    /// Returns the bit at the bottom of the column.
+    fn bottom_mask(&self, column: u8) -> u64 {
+        1 << (column as u32 * (self.config.height as u32 + 1))
+    }
+
+    /// Returns every cell in the column.
+    fn column_mask(&self, column: u8) -> u64 {
+        ((1 << self.config.height) - 1) * self.bottom_mask(column)
+    }
+
+    /// Returns how far apart neighbouring cells are in each direction: vertical, horizontal
+    /// and both diagonals.
+    fn directions(&self) -> [u32; 4] {
+        let height = self.config.height as u32;
+        [1, height + 1, height, height + 2]
+    }
+
+    /// Returns whether the pieces have number_to_win in a row.
+    fn is_aligned(&self, pieces: u64) -> bool {
+        self.directions().into_iter().any(|direction| {
+            (1..self.config.number_to_win as u32).fold(pieces, |aligned, step| {
+                aligned & shift(pieces, step as i32 * direction as i32)
+            }) != 0
+        })
+    }
+
+    /// Returns every cell, empty or not, that would give the pieces number_to_win in a row.
+    fn winning_cells(&self, pieces: u64) -> u64 {
+        let number_to_win = self.config.number_to_win as i32;
+        let mut cells = 0;
+
+        for direction in self.directions() {
+            // The cell can be anywhere in the line, with the rest of it taken by pieces
+            for start in 0..number_to_win {
+                cells |= (-start..number_to_win - start)
+                    .filter(|step| *step != 0)
+                    .fold(u64::MAX, |line, step| {
+                        line & shift(pieces, step * direction as i32)
+                    });
+            }
+        }
+
+        cells & self.board
+    }
+}
+
+/// Moves every piece to the cell the given number of bits above it, or below it if the
+/// number is negative, as seen from that cell. Pieces that move off the ends are dropped.
+fn shift(pieces: u64, bits: i32) -> u64 {
+    if bits >= 0 {
+        pieces.checked_shr(bits as u32).unwrap_or(0)
+    } else {
+        pieces.checked_shl(-bits as u32).unwrap_or(0)
+    }
+}
+
+/// A position as bitboards.
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    layout: Layout,
+    /// The pieces of the player about to move.
+    current: u64,
+    /// Every piece on the board.
+    mask: u64,
+    moves: usize,
+}
+
+impl Position {
+    fn from_board(board: &Board, turn: bool) -> Position {
+        let layout = Layout::new(board.config());
+        let mut position = Position {
+            layout,
+            current: 0,
+            mask: 0,
+            moves: 0,
+        };
+
+        for column in 0..board.width() {
+            let offset = layout.bottom_mask(column).trailing_zeros();
+            position.current |= (board.get_color_bitmap(column, turn) as u64) << offset;
+            position.mask |= (((1u16 << board.get_height(column)) - 1) as u64) << offset;
+            position.moves += board.get_height(column) as usize;
+        }
+
+        position
+    }
+
+    /// Returns the empty cells that pieces would land in if dropped now.
+    fn playable(&self) -> u64 {
+        self.mask.wrapping_add(self.layout.bottom) & self.layout.board
+    }
+
+    /// Drops a piece in the cell, which must be playable, and passes the turn.
+    fn play(&mut self, cell: u64) {
+        self.current ^= self.mask;
+        self.mask |= cell;
+        self.moves += 1;
+    }
+
+    /// Returns a number unique to the position, which is the same for its mirror image.
+    fn key(&self) -> u64 {
+        let key = self.current.wrapping_add(self.mask);
+
+        let bits = self.layout.config.height as u32 + 1;
+        let column = (1 << bits) - 1;
+        let mirrored = (0..self.layout.config.width as u32).fold(0, |mirrored, index| {
+            (mirrored << bits) | ((key >> (index * bits)) & column)
+        });
+
+        key.min(mirrored)
+    }
+}
+
+/// Searches positions, remembering the bounds found for them.
+struct Solver {
+    /// The columns in the order they're tried when moves look equally good.
+    column_order: Vec<u8>,
+    keys: Vec<u64>,
+    /// An upper bound on each position's score, offset so that 0 means the entry is empty.
+    upper_bounds: Vec<i8>,
+}
+
+impl Solver {
+    fn new(config: BoardConfig) -> Solver {
+        Solver {
+            column_order: config.columns_from_middle(),
+            keys: vec![0; TABLE_SIZE],
+            upper_bounds: vec![0; TABLE_SIZE],
+        }
+    }
+
+    /// Finds the exact score of a position that isn't over, by narrowing the range it could be
+    /// in with null window searches.
+    fn solve(&mut self, position: &Position) -> isize {
+        let cells = position.layout.cells() as isize;
+        let mut min = -(cells - position.moves as isize) / 2;
+        let mut max = (cells + 1 - position.moves as isize) / 2;
+
+        while min < max {
+            // Searches closer to zero prune more, so the first ones check who wins
+            let mut middle = min + (max - min) / 2;
+            if middle <= 0 && min / 2 < middle {
+                middle = min / 2;
+            } else if middle >= 0 && max / 2 > middle {
+                middle = max / 2;
+            }
+
+            let score = self.negamax(position, middle, middle + 1);
+            if score <= middle {
+                max = score;
+            } else {
+                min = score;
+            }
+        }
+
+        min
+    }
+
+    /// Returns the position's score if it's between alpha and beta. Otherwise returns a
+    /// bound on the score beyond whichever of them it's outside of.
+    fn negamax(&mut self, position: &Position, mut alpha: isize, mut beta: isize) -> isize {
+        let layout = position.layout;
+        let cells = layout.cells() as isize;
+        let moves = position.moves as isize;
+        let playable = position.playable();
+
+        if layout.winning_cells(position.current) & playable != 0 {
+            return (cells + 1 - moves) / 2;
+        }
+
+        // Block the opponent's wins, and don't play beneath them
+        let opponent = position.current ^ position.mask;
+        let threats = layout.winning_cells(opponent);
+        let mut candidates = playable;
+        let forced = playable & threats;
+        if forced != 0 {
+            if forced.count_ones() > 1 {
+                return -(cells - moves) / 2;
+            }
+            candidates = forced;
+        }
+        candidates &= !(threats >> 1);
+        if candidates == 0 {
+            return -(cells - moves) / 2;
+        }
+
+        // Neither player can win in the last two moves once those are ruled out
+        if moves >= cells - 2 {
+            return 0;
+        }
+
+        // The opponent can't win on their next move, so the score is at least this
+        let min = -(cells - 2 - moves) / 2;
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        let key = position.key();
+        let index = (key % TABLE_SIZE as u64) as usize;
+        let mut max = (cells - 1 - moves) / 2;
+        if self.keys[index] == key && self.upper_bounds[index] != 0 {
+            max = self.upper_bounds[index] as isize + min_score(cells) - 1;
+        }
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        // Moves that set up the most wins are tried first
+        let mut moves = [(0, 0); MAX_BOARD_WIDTH as usize];
+        let mut count = 0;
+        for column in &self.column_order {
+            let cell = candidates & layout.column_mask(*column);
+            if cell != 0 {
+                let wins = layout.winning_cells(position.current | cell) & !(position.mask | cell);
+                moves[count] = (wins.count_ones(), cell);
+                count += 1;
+            }
+        }
+        moves[..count].sort_by_key(|(wins, _)| Reverse(*wins));
+
+        for (_, cell) in &moves[..count] {
+            let mut child = *position;
+            child.play(*cell);
+            let score = -self.negamax(&child, -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        self.keys[index] = key;
+        self.upper_bounds[index] = (alpha - min_score(cells) + 1) as i8;
+        alpha
+    }
+}
+
+/// Searches misère positions, where whoever gets four in a row loses, remembering the
+/// exact rank of each one.
+///
+/// A rank is from the point of view of the player to move. A win in n moves ranks win - n,
+/// a loss in n moves ranks n - win, and a draw ranks 0, so quicker wins and slower losses
+/// rank higher.
+struct MisereSolver {
+    column_order: Vec<u8>,
+    /// Ranks above every loss and below every win, one more than the board has cells.
+    win: isize,
+    ranks: HashMap<u64, isize>,
+}
+
+impl MisereSolver {
+    fn new(config: BoardConfig) -> MisereSolver {
+        MisereSolver {
+            column_order: config.columns_from_middle(),
+            win: (config.width as isize * config.height as isize) + 1,
+            ranks: HashMap::new(),
+        }
+    }
+
+    /// Returns the exact rank of a position that isn't over.
+    fn negamax(&mut self, position: &Position) -> isize {
+        let key = position.key();
+        if let Some(rank) = self.ranks.get(&key) {
+            return *rank;
+        }
+
+        let layout = position.layout;
+        let playable = position.playable();
+        let mut best = isize::MIN;
+
+        for column in self.column_order.clone() {
+            let cell = playable & layout.column_mask(column);
+            if cell == 0 {
+                continue;
+            }
+
+            let rank = if layout.is_aligned(position.current | cell) {
+                // Getting four in a row loses straight away
+                1 - self.win
+            } else if position.moves + 1 == layout.cells() {
+                0
+            } else {
+                let mut child = *position;
+                child.play(cell);
+
+                // The child's result is the opponent's, one move further away
+                match self.negamax(&child) {
+                    0 => 0,
+                    rank if rank > 0 => 1 - rank,
+                    rank => -1 - rank,
+                }
+            };
+            best = best.max(rank);
+        }
+
+        self.ranks.insert(key, best);
+        best
+    }
+}
+
+/// Returns a score no position on a board with the given number of cells can be below.
+fn min_score(cells: isize) -> isize {
+    -cells / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        board::Board,
+        board_config::BoardConfig,
+        solver::{solve, solve_with_rules, WinDrawLoss},
+        variant_rules::VariantRules,
+        win_check::GameOver,
+    };
+
+    /// Solves the board under the rules by trying every line of play, returning the score
+    /// the solver would give it.
+    fn brute_force(board: &Board, turn: bool, rules: VariantRules) -> (WinDrawLoss, usize) {
+        let mut best: Option<(WinDrawLoss, usize)> = None;
+        for (_, child) in board.legal_drops(turn) {
+            let result = match rules.is_game_over(&child, !turn) {
+                GameOver::NoWin => match brute_force(&child, !turn, rules) {
+                    (WinDrawLoss::Win, plies) => (WinDrawLoss::Loss, plies + 1),
+                    (WinDrawLoss::Draw, plies) => (WinDrawLoss::Draw, plies + 1),
+                    (WinDrawLoss::Loss, plies) => (WinDrawLoss::Win, plies + 1),
+                },
+                GameOver::Tie => (WinDrawLoss::Draw, 1),
+                winner if (winner == GameOver::TwoWins) == turn => (WinDrawLoss::Win, 1),
+                _ => (WinDrawLoss::Loss, 1),
+            };
+
+            // Win quickly, and lose slowly
+            let rank = |(result, plies): (WinDrawLoss, usize)| match result {
+                WinDrawLoss::Win => 1000 - plies as isize,
+                WinDrawLoss::Draw => 0,
+                WinDrawLoss::Loss => plies as isize - 1000,
+            };
+            if best.is_none_or(|best| rank(result) > rank(best)) {
+                best = Some(result);
+            }
+        }
+
+        best.unwrap()
+    }
+
+    #[test]
+    fn wins_and_losses() {
+        // Player one wins by completing the bottom row
+        let board = Board::from_moves("445566").unwrap();
+        assert_eq!(solve(&board, false), Ok((WinDrawLoss::Win, 1)));
+
+        // Player two can only block one end of the three in a row
+        let board = Board::from_moves("4455").unwrap();
+        assert_eq!(solve(&board, false), Ok((WinDrawLoss::Win, 3)));
+        let board = Board::from_moves("44553").unwrap();
+        assert_eq!(solve(&board, true), Ok((WinDrawLoss::Loss, 2)));
+
+        // Games that are over, and boards too large to fit in a bitboard, can't be solved
+        assert!(solve(&Board::from_moves("1212121").unwrap(), true).is_err());
+        assert!(solve(&Board::with_config(BoardConfig::VARIANTS[2]), false).is_err());
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for moves in [
+            "62352673232646611533624214375411",
+            "41251664715213456754671541465777",
+            "51642713771171647635371266632224",
+            "64425313164145315636737437777415",
+        ] {
+            let board = Board::from_moves(moves).unwrap();
+            let turn = moves.len() % 2 == 1;
+            assert_eq!(
+                solve(&board, turn).unwrap(),
+                brute_force(&board, turn, VariantRules::Standard),
+                "{}",
+                moves
+            );
+        }
+    }
+
+    #[test]
+    fn misere() {
+        let board = Board::from_moves("445566").unwrap();
+        assert_eq!(
+            solve_with_rules(&board, false, VariantRules::Standard),
+            solve(&board, false)
+        );
+
+        for moves in [
+            "62352673232646611533624214375411",
+            "41251664715213456754671541465777",
+            "51642713771171647635371266632224",
+            "64425313164145315636737437777415",
+        ] {
+            let board = Board::from_moves(moves).unwrap();
+            let turn = moves.len() % 2 == 1;
+            assert_eq!(
+                solve_with_rules(&board, turn, VariantRules::Misere).unwrap(),
+                brute_force(&board, turn, VariantRules::Misere),
+                "{}",
+                moves
+            );
+        }
+    }
+
+    #[test]
+    fn small_board() {
+        // Three in a row on a 4x4 board is a win for the first player
+        let board = Board::with_config(BoardConfig::new(4, 4, 3).unwrap());
+        assert_eq!(solve(&board, false), Ok((WinDrawLoss::Win, 9)));
+    }
+}
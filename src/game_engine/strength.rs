@@ -0,0 +1,119 @@
+//! Weakens the engine for lower difficulties, by capping how far ahead it looks and how much
+//! it explores, and by blurring its evaluations, so that weaker engines genuinely think less
+//! rather than blundering on purpose.
+
+use crate::game_engine::rating::BASELINES;
+
+/// The rating at and above which the engine plays at full strength.
+pub const FULL_STRENGTH: u16 = 2000;
+
+/// The lowest rating the engine can be weakened to. Lower ratings play like it.
+pub const MIN_STRENGTH: u16 = 600;
+
+/// How many rating points each extra move of lookahead is worth, matching the baselines the
+/// ratings are anchored to.
+const RATING_PER_MOVE: i32 = 150;
+
+/// How many states the weakest engine explores. Each 100 rating points doubles it.
+const MIN_NODES: usize = 250;
+
+/// The most the weakest engine's evaluations are blurred by, about a third of an open three.
+const MAX_NOISE: isize = 150;
+
+/// How much an engine of a given strength thinks about each position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrengthProfile {
+    /// The most moves ahead the decision tree is grown.
+    pub max_depth: usize,
+    /// The most states the decision tree is grown to, or rollouts played from a position.
+    pub max_nodes: usize,
+    /// The most each move's score is moved up or down by.
+    pub noise: isize,
+}
+
+impl StrengthProfile {
+    /// Returns how much an engine of the given rating thinks, anchored like the rating
+    /// baselines so a weakened engine plays close to its rating.
+    ///
+    /// Returns None at full strength, which thinks as much as it has time for.
+    pub fn for_rating(rating: u16) -> Option<StrengthProfile> {
+        if rating >= FULL_STRENGTH {
+            return None;
+        }
+        let rating = rating.max(MIN_STRENGTH);
+
+        let (base_depth, base_rating) = BASELINES[0];
+        let extra_moves = (rating as i32 - base_rating as i32).div_euclid(RATING_PER_MOVE);
+        let max_depth = (base_depth as i32 + extra_moves).max(1) as usize;
+
+        Some(StrengthProfile {
+            max_depth,
+            max_nodes: MIN_NODES << ((rating - MIN_STRENGTH) / 100),
+            noise: MAX_NOISE * (FULL_STRENGTH - rating) as isize
+                / (FULL_STRENGTH - MIN_STRENGTH) as isize,
+        })
+    }
+
+    /// Returns the noise added to the score of a move in a position, from -noise to noise.
+    ///
+    /// The noise only depends on the seed, the position's key and the column, so a move's
+    /// score doesn't jump around as the engine keeps thinking about it.
+    pub fn noise(&self, seed: u64, key: u64, column: u8) -> isize {
+        if self.noise == 0 {
+            return 0;
+        }
+
+        // The splitmix64 finalizer, which spreads each input bit across the whole output
+        let mut hash =
+            seed ^ key.rotate_left(17) ^ (column as u64).wrapping_mul(0x9E3779B97F4A7C15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D049BB133111EB);
+        hash ^= hash >> 31;
+
+        (hash % (2 * self.noise as u64 + 1)) as isize - self.noise
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::strength::{StrengthProfile, FULL_STRENGTH, MIN_STRENGTH};
+
+    #[test]
+    fn profiles() {
+        assert_eq!(StrengthProfile::for_rating(FULL_STRENGTH), None);
+        assert_eq!(StrengthProfile::for_rating(u16::MAX), None);
+
+        // The rating baselines' depths are kept
+        assert_eq!(StrengthProfile::for_rating(800).unwrap().max_depth, 2);
+        assert_eq!(StrengthProfile::for_rating(1100).unwrap().max_depth, 4);
+        assert_eq!(StrengthProfile::for_rating(1700).unwrap().max_depth, 8);
+
+        // Anything weaker than the weakest profile plays like it
+        let weakest = StrengthProfile::for_rating(MIN_STRENGTH).unwrap();
+        assert_eq!(StrengthProfile::for_rating(0), Some(weakest));
+        assert_eq!(weakest.max_depth, 1);
+
+        // Stronger engines think more and blur their evaluations less
+        let strong = StrengthProfile::for_rating(FULL_STRENGTH - 1).unwrap();
+        assert!(strong.max_depth > weakest.max_depth);
+        assert!(strong.max_nodes > weakest.max_nodes);
+        assert!(strong.noise < weakest.noise);
+    }
+
+    #[test]
+    fn noise() {
+        let profile = StrengthProfile::for_rating(900).unwrap();
+        let noises: Vec<_> = (0..100).map(|key| profile.noise(7, key, 3)).collect();
+
+        assert!(noises.iter().all(|noise| noise.abs() <= profile.noise));
+        assert!(noises.iter().any(|noise| *noise > 0));
+        assert!(noises.iter().any(|noise| *noise < 0));
+        assert_eq!(profile.noise(7, 12, 3), noises[12]);
+
+        let exact = StrengthProfile {
+            noise: 0,
+            ..profile
+        };
+        assert_eq!(exact.noise(7, 12, 3), 0);
+    }
+}
@@ -0,0 +1,157 @@
+//! Finds each player's threats, the empty spaces that would complete a connect four, and
+//! sorts them by the parity of their row.
+
+use crate::game_engine::board::Board;
+
+/// An empty space that would give a player a connect four if they had a piece there.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Threat {
+    pub column: u8,
+    /// The row of the space, counting up from the bottom of the board.
+    pub row: u8,
+    /// Whether a piece dropped in the column would land in the space right now.
+    pub playable: bool,
+}
+
+/// Whether a threat is on an odd or even row, counting the bottom row as row 1.
+///
+/// Once the board fills up, player one gets to fill the odd rows and player two the even
+/// rows, so each player's threats on their own rows are the ones that tend to win games.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Parity {
+    Odd,
+    Even,
+}
+
+impl Parity {
+    /// Returns the name of the parity, e.g. "odd".
+    pub fn name(self) -> &'static str {
+        match self {
+            Parity::Odd => "odd",
+            Parity::Even => "even",
+        }
+    }
+
+    /// Returns the player whose threats are strongest on rows of this parity, false for
+    /// player one and true for player two.
+    pub fn favours(self) -> bool {
+        self == Parity::Even
+    }
+}
+
+impl Threat {
+    /// Returns whether the threat is on an odd or even row.
+    pub fn parity(&self) -> Parity {
+        // Rows are stored from 0, so the bottom row, row 1, is stored as row 0
+        match self.row % 2 {
+            0 => Parity::Odd,
+            _ => Parity::Even,
+        }
+    }
+}
+
+/// Returns every empty space that would complete a connect four for the given color.
+pub fn threats(board: &Board, color: bool) -> Vec<Threat> {
+    let mut threats = Vec::new();
+
+    for column in 0..board.width() {
+        let height = board.get_height(column);
+
+        for row in height..board.height() {
+            if completes_line(board, color, column, row) {
+                threats.push(Threat {
+                    column,
+                    row,
+                    playable: row == height,
+                });
+            }
+        }
+    }
+
+    threats
+}
+
+/// Returns whether a piece of the given color in the given space would be part of a
+/// connect four.
+fn completes_line(board: &Board, color: bool, column: u8, row: u8) -> bool {
+    [(1, 0), (0, 1), (1, 1), (1, -1)]
+        .into_iter()
+        .any(|(col_step, row_step)| {
+            let in_a_row = 1
+                + count_in_direction(board, color, column, row, col_step, row_step)
+                + count_in_direction(board, color, column, row, -col_step, -row_step);
+
+            in_a_row >= board.number_to_win()
+        })
+}
+
+/// Counts the pieces of the given color in a row, starting next to the given space and
+/// heading in the given direction.
+fn count_in_direction(
+    board: &Board,
+    color: bool,
+    column: u8,
+    row: u8,
+    col_step: i8,
+    row_step: i8,
+) -> u8 {
+    let mut count = 0;
+    let mut col = column as i8 + col_step;
+    let mut row = row as i8 + row_step;
+
+    while (0..board.width() as i8).contains(&col)
+        && (0..board.height() as i8).contains(&row)
+        && matches!(board.get_piece(col as u8, row as u8), Ok(piece) if piece == color)
+    {
+        count += 1;
+        col += col_step;
+        row += row_step;
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        board::Board,
+        threats::{threats, Parity, Threat},
+    };
+
+    #[test]
+    fn threat_spaces() {
+        // Both players have three in a row, with Blue's on top of Red's
+        let board = Board::from_moves("445566").unwrap();
+
+        let threat = |column, row, playable| Threat {
+            column,
+            row,
+            playable,
+        };
+        assert_eq!(
+            threats(&board, false),
+            vec![threat(2, 0, true), threat(6, 0, true)]
+        );
+        assert_eq!(
+            threats(&board, true),
+            vec![threat(2, 1, false), threat(6, 1, false)]
+        );
+
+        assert_eq!(threats(&Board::default(), false), vec![]);
+    }
+
+    #[test]
+    fn threat_parities() {
+        let board = Board::from_moves("445566").unwrap();
+
+        let red = threats(&board, false);
+        assert!(red.iter().all(|threat| threat.parity() == Parity::Odd));
+        let blue = threats(&board, true);
+        assert!(blue.iter().all(|threat| threat.parity() == Parity::Even));
+
+        // Red's odd threats are the ones that suit Red
+        assert!(!Parity::Odd.favours());
+        assert!(Parity::Even.favours());
+        assert_eq!(Parity::Even.name(), "even");
+    }
+}
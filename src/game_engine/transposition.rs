@@ -1,14 +1,13 @@
 use std::{
-    cell::RefCell,
     collections::{hash_map::DefaultHasher, HashMap},
     hash::{Hash, Hasher},
-    rc::{Rc, Weak},
+    mem::size_of,
 };
 
-use crate::game_engine::{board::Board, board_state::BoardState};
+use crate::game_engine::{board::Board, power_ups::Move};
 
 /// Represents whether a transposition has had its X axis flipped.
-#[derive(PartialEq, Eq, Debug, Default, Clone)]
+#[derive(PartialEq, Eq, Debug, Default, Clone, Copy)]
 pub enum IsFlipped {
     #[default]
     Normal,
@@ -23,38 +22,75 @@ impl IsFlipped {
             IsFlipped::Flipped => IsFlipped::Normal,
         }
     }
+
+    /// Combines two orientations, such that applying the result is the same as applying
+    /// both of them one after the other.
+    pub fn then(&self, other: IsFlipped) -> IsFlipped {
+        match other {
+            IsFlipped::Normal => *self,
+            IsFlipped::Flipped => self.flip(),
+        }
+    }
+
+    /// Maps a column of a board with the given width into this orientation. Mapping a column
+    /// twice returns the original column.
+    pub fn map_column(&self, column: u8, width: u8) -> u8 {
+        match *self {
+            IsFlipped::Normal => column,
+            IsFlipped::Flipped => width - 1 - column,
+        }
+    }
+
+    /// Maps the column a move is made in, see map_column.
+    pub fn map_move(&self, game_move: Move, width: u8) -> Move {
+        game_move.with_column(self.map_column(game_move.column(), width))
+    }
 }
 
-/// A table with weak references to every board state that has been created. Will consider symmetrical board
+/// A table of values for board states, e.g. their scores. Will consider symmetrical board
 /// states to be the same.
-#[derive(Default, Debug)]
+///
+/// Boards are looked up along with a context, whatever else tells apart positions with the
+/// same board, e.g. whose turn it is once columns can be cleared, or () if nothing does.
+#[derive(Debug)]
 pub struct TranspositionTable<T> {
     table: HashMap<u64, T>,
 }
 
-/// Used to get the normal hash of a board.
-fn normal_hash(board: &Board) -> u64 {
+// Derived, this would need the values to have a default too
+impl<T> Default for TranspositionTable<T> {
+    fn default() -> Self {
+        TranspositionTable {
+            table: HashMap::new(),
+        }
+    }
+}
+
+/// Used to get the normal hash of a board in a context.
+fn normal_hash(board: &Board, context: &impl Hash) -> u64 {
     let mut hasher = DefaultHasher::new();
     board.iter().collect::<Vec<u8>>().hash(&mut hasher);
+    context.hash(&mut hasher);
     hasher.finish()
 }
 
-/// Used to get the hash of a flipped board.
-fn flipped_hash(board: &Board) -> u64 {
+/// Used to get the hash of a flipped board in a context.
+fn flipped_hash(board: &Board, context: &impl Hash) -> u64 {
     let mut hasher = DefaultHasher::new();
     board.flipped_iter().collect::<Vec<u8>>().hash(&mut hasher);
+    context.hash(&mut hasher);
     hasher.finish()
 }
 
 impl<T> TranspositionTable<T> {
-    /// Gets a value in the table corresponding to a board.
-    pub fn get_transposed(&mut self, board: &Board) -> Option<(&T, IsFlipped)> {
-        let normal = normal_hash(&board);
+    /// Gets a value in the table corresponding to a board in a context.
+    pub fn get_transposed(&mut self, board: &Board, context: impl Hash) -> Option<(&T, IsFlipped)> {
+        let normal = normal_hash(board, &context);
         if let Some(value) = self.table.get(&normal) {
             return Some((value, IsFlipped::Normal));
         }
 
-        let flipped = flipped_hash(&board);
+        let flipped = flipped_hash(board, &context);
         if let Some(value) = self.table.get(&flipped) {
             return Some((value, IsFlipped::Flipped));
         }
@@ -62,9 +98,9 @@ impl<T> TranspositionTable<T> {
         None
     }
 
-    /// Inserts a key value pair into the transposition table.
-    pub fn insert(&mut self, board: &Board, value: T) {
-        self.table.insert(normal_hash(board), value);
+    /// Inserts a value into the transposition table for a board in a context.
+    pub fn insert(&mut self, board: &Board, context: impl Hash, value: T) {
+        self.table.insert(normal_hash(board, &context), value);
     }
 
     /// Gets an iterator to the contents of the transposition table.
@@ -76,43 +112,24 @@ impl<T> TranspositionTable<T> {
     pub fn len(&self) -> usize {
         self.table.len()
     }
-}
 
-impl TranspositionTable<Weak<RefCell<BoardState>>> {
-    /// Using a board, gets a corresponding BoardState transposition.
+    /// Estimates how many bytes the table has allocated, from how many entries it has room
+    /// for.
     ///
-    /// The IsFlipped return value represents whether the returned transposition is horizontally flipped.
-    pub fn get_board_state(
-        &mut self,
-        board: Board,
-        turn: bool,
-    ) -> (Rc<RefCell<BoardState>>, IsFlipped) {
-        if let Some((board_state_weak, is_flipped)) = self.get_transposed(&board) {
-            if let Some(board_state) = board_state_weak.upgrade() {
-                assert_eq!(
-                    board_state.borrow().get_turn(),
-                    turn,
-                    "board: {:?} turn: {} doesn't match turn of {:?}",
-                    board,
-                    turn,
-                    board_state.borrow()
-                );
-
-                return (board_state, is_flipped);
-            }
-        }
-
-        // The board we're evaluating is not in the Transposition table, so construct a new BoardState
-        let board_state = Rc::new(RefCell::new(BoardState::new(board, turn)));
-        let normal = normal_hash(&board_state.borrow().board);
-        self.table.insert(normal, Rc::downgrade(&board_state));
+    /// HashMap doesn't expose its allocation, which also has some spare buckets and control
+    /// bytes, so the real size is somewhat larger.
+    pub fn estimated_heap_size(&self) -> usize {
+        self.table.capacity() * size_of::<(u64, T)>()
+    }
 
-        (board_state, IsFlipped::Normal)
+    /// Keeps only the entries whose value passes the check.
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        self.table.retain(|_, value| keep(value));
     }
 
-    /// Removes unreachable board states from the transposition table.
-    pub fn clean(&mut self) {
-        self.table.retain(|_, r| r.strong_count() != 0);
+    /// Removes every entry from the table.
+    pub fn clear(&mut self) {
+        self.table.clear();
     }
 }
 
@@ -120,99 +137,72 @@ impl TranspositionTable<Weak<RefCell<BoardState>>> {
 mod tests {
     use crate::game_engine::{
         board::Board,
+        power_ups::Move,
         transposition::{IsFlipped, TranspositionTable},
     };
 
     #[test]
-    fn transposes() {
-        let board = Board::from_arrays([
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 1, 0, 0, 1],
-            [0, 2, 0, 1, 0, 2, 1],
-            [0, 1, 2, 1, 0, 1, 2],
-            [0, 1, 2, 1, 2, 1, 2],
-        ]);
-
-        let flipped_board = Board::from_arrays([
-            [2, 0, 0, 0, 0, 0, 0],
-            [2, 0, 0, 0, 0, 0, 0],
-            [1, 0, 0, 1, 0, 0, 0],
-            [1, 2, 0, 1, 0, 2, 0],
-            [2, 1, 0, 1, 2, 1, 0],
-            [2, 1, 2, 1, 2, 1, 0],
-        ]);
-
-        let mut table = TranspositionTable::default();
-
-        let (state, state_is_flipped) = table.get_board_state(board, false);
-        let (flipped, flipped_is_flipped) = table.get_board_state(flipped_board, false);
-
-        assert_eq!(state, flipped);
-        assert_eq!(state_is_flipped, IsFlipped::Normal);
-        assert_eq!(flipped_is_flipped, IsFlipped::Flipped);
-
-        let board = Board::from_arrays([
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 1, 0, 0, 1],
-            [0, 2, 0, 1, 0, 2, 1],
-            [0, 1, 2, 1, 0, 1, 2],
-            [0, 1, 2, 1, 2, 1, 2],
-        ]);
-
-        let (clone, clone_is_flipped) = table.get_board_state(board, false);
-        assert_eq!(state, clone);
-        assert_eq!(clone_is_flipped, IsFlipped::Normal);
+    fn orientation_mapping() {
+        assert_eq!(IsFlipped::Normal.map_column(1, 7), 1);
+        assert_eq!(IsFlipped::Flipped.map_column(1, 7), 5);
+        assert_eq!(IsFlipped::Flipped.map_column(3, 7), 3);
+        assert_eq!(IsFlipped::Flipped.map_column(1, 8), 6);
+        assert_eq!(
+            IsFlipped::Flipped.map_column(IsFlipped::Flipped.map_column(0, 7), 7),
+            0
+        );
+        assert_eq!(
+            IsFlipped::Flipped.map_move(Move::Clear(1), 7),
+            Move::Clear(5)
+        );
+
+        assert_eq!(IsFlipped::Normal.then(IsFlipped::Normal), IsFlipped::Normal);
+        assert_eq!(
+            IsFlipped::Normal.then(IsFlipped::Flipped),
+            IsFlipped::Flipped
+        );
+        assert_eq!(
+            IsFlipped::Flipped.then(IsFlipped::Normal),
+            IsFlipped::Flipped
+        );
+        assert_eq!(
+            IsFlipped::Flipped.then(IsFlipped::Flipped),
+            IsFlipped::Normal
+        );
     }
 
     #[test]
-    fn new_reference() {
-        let board = Board::from_arrays([
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 1, 0, 0, 1],
-            [0, 2, 0, 1, 0, 2, 1],
-            [0, 1, 2, 1, 0, 1, 2],
-            [0, 1, 2, 1, 2, 1, 2],
-        ]);
+    fn retains() {
+        let board = Board::from_moves("4453").unwrap();
+        let mut flipped_board = board.clone();
+        flipped_board.flip();
 
         let mut table = TranspositionTable::default();
-
-        let (state, _) = table.get_board_state(board, false);
-        drop(state);
-
-        let flipped_board = Board::from_arrays([
-            [2, 0, 0, 0, 0, 0, 0],
-            [2, 0, 0, 0, 0, 0, 0],
-            [1, 0, 0, 1, 0, 0, 0],
-            [1, 2, 0, 1, 0, 2, 0],
-            [2, 1, 0, 1, 2, 1, 0],
-            [2, 1, 2, 1, 2, 1, 0],
-        ]);
-
-        let (_, is_flipped) = table.get_board_state(flipped_board, false);
-        assert_eq!(is_flipped, IsFlipped::Normal);
+        table.insert(&board, (), 1);
+        table.insert(&Board::from_moves("44").unwrap(), (), 2);
+        assert_eq!(
+            table.get_transposed(&flipped_board, ()),
+            Some((&1, IsFlipped::Flipped))
+        );
+
+        table.retain(|value| *value == 2);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get_transposed(&board, ()), None);
     }
 
     #[test]
-    fn clean_table() {
-        let board = Board::from_arrays([
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 0, 0, 0, 2],
-            [0, 0, 0, 1, 0, 0, 1],
-            [0, 2, 0, 1, 0, 2, 1],
-            [0, 1, 2, 1, 0, 1, 2],
-            [0, 1, 2, 1, 2, 1, 2],
-        ]);
+    fn contexts() {
+        let board = Board::from_moves("4453").unwrap();
+        let mut flipped_board = board.clone();
+        flipped_board.flip();
 
+        // The same board with the other player to move is another position
         let mut table = TranspositionTable::default();
-
-        let (state, _) = table.get_board_state(board, false);
-        drop(state);
-
-        table.clean();
-
-        assert_eq!(table.table.len(), 0);
+        table.insert(&board, false, 1);
+        assert_eq!(table.get_transposed(&board, true), None);
+        assert_eq!(
+            table.get_transposed(&flipped_board, false),
+            Some((&1, IsFlipped::Flipped))
+        );
     }
 }
@@ -1,103 +1,408 @@
 use std::{
-    cmp::{max, min},
-    isize::{MAX, MIN},
+    cmp::{max, min, Reverse},
+    mem::size_of,
 };
 
-use crate::game_engine::{
-    board_state::BoardState, heuristics::how_good_is_board, transposition::TranspositionTable,
-    win_check::GameOver,
+use crate::{
+    consts::MAX_BOARD_WIDTH,
+    game_engine::{
+        arena::{Arena, NodeId},
+        board_state::{BoardState, ChildState},
+        mate_distance::{is_forced, loss_in, one_ply_back, one_ply_forward, win_in},
+        power_ups::Move,
+        transposition::TranspositionTable,
+        win_check::GameOver,
+    },
 };
 
+/// How many moves deep a search has to look before its score is considered fully settled.
+const SETTLED_DEPTH: usize = 8;
+
+/// How much a score can be trusted, based on how much of the decision tree backs it up.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScoreConfidence {
+    /// The score is exact, either a forced result or every line has been played out.
+    Proven,
+    /// Every line has been searched at least this many moves deep before falling back
+    /// on the heuristic.
+    Searched(usize),
+    /// The score is only the heuristic's guess at the position itself.
+    Heuristic,
+}
+
+impl ScoreConfidence {
+    /// Determines the confidence of a score, given how many moves deep every line
+    /// beneath it has been searched, or None if every line reaches the end of the game.
+    pub fn new(score: isize, horizon: Option<usize>) -> ScoreConfidence {
+        match horizon {
+            _ if is_forced(score) => ScoreConfidence::Proven,
+            None => ScoreConfidence::Proven,
+            Some(0) => ScoreConfidence::Heuristic,
+            Some(depth) => ScoreConfidence::Searched(depth),
+        }
+    }
+
+    /// Returns how settled the score is, from 0.0 for a bare heuristic to 1.0 for a
+    /// proven result.
+    pub fn shade(&self) -> f32 {
+        match *self {
+            ScoreConfidence::Proven => 1.0,
+            ScoreConfidence::Searched(depth) => {
+                min(depth, SETTLED_DEPTH) as f32 / SETTLED_DEPTH as f32
+            }
+            ScoreConfidence::Heuristic => 0.0,
+        }
+    }
+}
+
+/// Whether a cached score is exact, or only bounds the real score because the search
+/// beneath it was cut off.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScoreBound {
+    Exact,
+    /// The real score is at least this good for player two.
+    Lower,
+    /// The real score is at most this good for player two.
+    Upper,
+}
+
+/// A score cached in the transposition table, along with how it was found.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScoreEntry {
+    pub score: isize,
+    /// How many moves deep the search beneath the state went, or None if it searched the
+    /// whole of the decision tree beneath it.
+    pub depth: Option<usize>,
+    pub bound: ScoreBound,
+    /// The move to the best child found, in the orientation of the board the entry was
+    /// stored for, if the state's children were searched.
+    pub best_move: Option<Move>,
+}
+
+impl ScoreEntry {
+    /// Returns whether the entry was searched at least as deep as a search to the given
+    /// depth would go, so its score can stand in for that search's.
+    fn covers(&self, depth: Option<usize>) -> bool {
+        match (self.depth, depth) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(searched), Some(wanted)) => searched >= wanted,
+        }
+    }
+}
+
+/// Moves that caused cut offs in earlier searches, used to try the children most likely to
+///  cause another one first. Children that tie keep the order they were generated in,
+///  middle columns first.
+#[derive(Debug, Default)]
+pub struct MoveOrdering {
+    /// Up to two moves that caused a cut off, the most recent first, for each number of
+    ///  pieces on the board.
+    killers: Vec<[Option<Move>; 2]>,
+    /// How much each player's drops into each column have caused cut offs, weighted
+    ///  towards the ones with more of the game left beneath them. Power-ups are only spent
+    ///  once, so they have no history to learn from.
+    history: [[usize; MAX_BOARD_WIDTH as usize]; 2],
+}
+
+impl MoveOrdering {
+    /// Returns how early a move should be tried, with the best move the transposition table
+    ///  remembers first, then the killer moves, then the rest by their history.
+    fn priority(
+        &self,
+        ply: usize,
+        turn: bool,
+        game_move: Move,
+        hinted_move: Option<Move>,
+    ) -> usize {
+        let killers = self.killers.get(ply).copied().unwrap_or_default();
+
+        if hinted_move == Some(game_move) {
+            usize::MAX
+        } else if killers[0] == Some(game_move) {
+            usize::MAX - 1
+        } else if killers[1] == Some(game_move) {
+            usize::MAX - 2
+        } else if let Move::Drop(column) = game_move {
+            min(self.history[turn as usize][column as usize], usize::MAX - 3)
+        } else {
+            0
+        }
+    }
+
+    /// Remembers a move that caused a cut off, with the given number of plies left to
+    ///  search beneath it.
+    fn record_cut_off(&mut self, ply: usize, turn: bool, game_move: Move, remaining: usize) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(game_move) {
+            *killers = [Some(game_move), killers[0]];
+        }
+
+        if let Move::Drop(column) = game_move {
+            let history = &mut self.history[turn as usize][column as usize];
+            *history = history.saturating_add(remaining * remaining);
+        }
+    }
+
+    /// Returns how many bytes the killer moves have allocated.
+    pub fn heap_size(&self) -> usize {
+        self.killers.capacity() * size_of::<[Option<Move>; 2]>()
+    }
+}
+
+/// The tables alpha-beta pruning fills in, which later searches of the same decision tree
+///  can reuse.
+#[derive(Debug, Default)]
+pub struct SearchTables {
+    pub scores: TranspositionTable<ScoreEntry>,
+    pub ordering: MoveOrdering,
+}
+
+impl SearchTables {
+    /// Returns roughly how many bytes the tables have allocated, see
+    ///  TranspositionTable::estimated_heap_size.
+    pub fn heap_size(&self) -> usize {
+        self.scores.estimated_heap_size() + self.ordering.heap_size()
+    }
+
+    /// Forgets everything the tables have learned.
+    pub fn clear(&mut self) {
+        self.scores.clear();
+        self.ordering = MoveOrdering::default();
+    }
+}
+
 /// Analyses a BoardState to determine how good it is based off of its
 ///  entire decision tree.
-pub fn how_good_is(board_state: &BoardState, table: &mut TranspositionTable<isize>) -> isize {
-    board_state.alpha_beta_pruning(MIN, MAX, table)
+pub fn how_good_is(arena: &Arena, id: NodeId, tables: &mut SearchTables) -> isize {
+    how_good_is_to_depth(arena, id, None, tables)
+}
+
+/// Analyses a BoardState like how_good_is, but only looking the given number of moves
+///  ahead, with the states at that depth scored by the heuristic. A depth of None searches
+///  the entire decision tree.
+pub fn how_good_is_to_depth(
+    arena: &Arena,
+    id: NodeId,
+    depth: Option<usize>,
+    tables: &mut SearchTables,
+) -> isize {
+    arena[id].alpha_beta_pruning(arena, depth, isize::MIN, isize::MAX, tables)
+}
+
+/// Finds how many moves deep every line of a BoardState's decision tree has been searched.
+///
+/// Returns None if every line has been played out until the end of the game.
+pub fn how_deep_is(
+    arena: &Arena,
+    id: NodeId,
+    table: &mut TranspositionTable<Option<usize>>,
+) -> Option<usize> {
+    arena[id].horizon(arena, table)
 }
 
 impl BoardState {
-    /// An implementation of alpha-beta pruning, a faster version of the mini-max algorithm.
+    /// Finds the depth of the shallowest node whose children haven't been generated.
+    fn horizon(
+        &self,
+        arena: &Arena,
+        table: &mut TranspositionTable<Option<usize>>,
+    ) -> Option<usize> {
+        // Finished games don't need searching any further
+        if self.is_game_over() != GameOver::NoWin {
+            return None;
+        }
+
+        if let Some((horizon, _)) = table.get_transposed(&self.board, self.get_context()) {
+            return *horizon;
+        }
+
+        let horizon = if self.children.is_empty() {
+            Some(0)
+        } else {
+            self.children
+                .iter()
+                .filter_map(|child| arena[child.state].horizon(arena, table))
+                .min()
+                .map(|depth| depth + 1)
+        };
+
+        table.insert(&self.board, self.get_context(), horizon);
+        horizon
+    }
+
+    /// An implementation of alpha-beta pruning, a faster version of the mini-max algorithm,
+    ///  searching depth moves deep, or the whole decision tree if depth is None.
+    ///
+    /// Scores are cached along with the depth they were searched to and whether the search
+    ///  was cut off, so a cached score is only reused by searches it's deep enough for, and a
+    ///  cut off score only narrows the bounds.
+    ///
+    /// Children are searched in the order the tables suggest, so cut offs come sooner.
     fn alpha_beta_pruning(
         &self,
+        arena: &Arena,
+        depth: Option<usize>,
         mut alpha: isize,
         mut beta: isize,
-        mut table: &mut TranspositionTable<isize>,
+        tables: &mut SearchTables,
     ) -> isize {
         // If the game is over, we can return a score based on who won
         match self.is_game_over() {
             GameOver::Tie => return 0,
-            GameOver::OneWins => return MIN,
-            GameOver::TwoWins => return MAX,
+            GameOver::OneWins => return loss_in(0),
+            GameOver::TwoWins => return win_in(0),
             _ => (),
         }
 
-        // Check the transposition table for the value of this node
-        if let Some((score, _)) = table.get_transposed(&self.board) {
-            return *score;
+        // Check the transposition table for the value of this node. Even when the score
+        //  can't be used, the best move it found is worth trying first
+        let mut hinted_move = None;
+        if let Some((entry, is_flipped)) = tables
+            .scores
+            .get_transposed(&self.board, self.get_context())
+        {
+            hinted_move = entry
+                .best_move
+                .map(|game_move| is_flipped.map_move(game_move, self.board.width()));
+
+            if entry.covers(depth) {
+                match entry.bound {
+                    ScoreBound::Exact => return entry.score,
+                    ScoreBound::Lower => alpha = max(alpha, entry.score),
+                    ScoreBound::Upper => beta = min(beta, entry.score),
+                }
+                if alpha >= beta {
+                    return entry.score;
+                }
+            }
         }
 
-        // If the BoardState is a terminal node we can use our heuristic
-        if self.children.len() == 0 {
-            let score = how_good_is_board(&self.board);
-            table.insert(&self.board, score);
+        // If the BoardState is a terminal node we can use our heuristic. Without children
+        //  it's as deep as the decision tree goes, however deep the search wanted to go
+        if self.children.is_empty() || depth == Some(0) {
+            let score = self.heuristic_score(arena.heuristic_weights());
+            let entry = ScoreEntry {
+                score,
+                depth: if self.children.is_empty() {
+                    None
+                } else {
+                    depth
+                },
+                bound: ScoreBound::Exact,
+                best_move: None,
+            };
+            tables.scores.insert(&self.board, self.get_context(), entry);
             return score;
         }
 
         // Otherwise we can proceed with alpha-beta pruning the child nodes
-        if self.get_turn() {
-            // We are the maximizing player
-            let mut value = MIN;
-            for child in self.children.iter() {
-                value = max(
-                    value,
-                    child
-                        .state
-                        .borrow()
-                        .alpha_beta_pruning(alpha, beta, &mut table),
-                );
-
-                if value >= beta {
-                    break;
-                }
+        let (searched_alpha, searched_beta) = (alpha, beta);
+        let turn = self.get_turn();
+        let ply = self.get_depth() as usize;
+        let child_depth = depth.map(|depth| depth - 1);
 
-                alpha = max(alpha, value);
-            }
+        let mut order: Vec<&ChildState> = self.children.iter().collect();
+        order.sort_by_key(|child| {
+            Reverse(
+                tables
+                    .ordering
+                    .priority(ply, turn, child.get_last_move(), hinted_move),
+            )
+        });
 
-            table.insert(&self.board, value);
-            return value;
-        } else {
-            // We are the minimizing player
-            let mut value = MAX;
-            for child in self.children.iter() {
-                value = min(
-                    value,
-                    child
-                        .state
-                        .borrow()
-                        .alpha_beta_pruning(alpha, beta, &mut table),
-                );
-
-                if value <= alpha {
-                    break;
-                }
+        let mut best = (
+            if turn { isize::MIN } else { isize::MAX },
+            order[0].get_last_move(),
+        );
+        for child in order {
+            let score = self.child_score(arena, child.state, child_depth, alpha, beta, tables);
 
-                beta = min(beta, value);
+            // Player two is maximizing, and player one minimizing
+            if (turn && score > best.0) || (!turn && score < best.0) {
+                best = (score, child.get_last_move());
+            }
+            if turn {
+                alpha = max(alpha, best.0);
+            } else {
+                beta = min(beta, best.0);
             }
 
-            table.insert(&self.board, value);
-            return value;
+            if alpha >= beta {
+                let remaining = depth.unwrap_or(self.board_spaces_left());
+                tables
+                    .ordering
+                    .record_cut_off(ply, turn, child.get_last_move(), remaining);
+                break;
+            }
         }
+        let (value, best_move) = best;
+
+        // A score outside the bounds the children were searched with was cut off short of
+        //  the real score
+        let bound = if value <= searched_alpha {
+            ScoreBound::Upper
+        } else if value >= searched_beta {
+            ScoreBound::Lower
+        } else {
+            ScoreBound::Exact
+        };
+        tables.scores.insert(
+            &self.board,
+            self.get_context(),
+            ScoreEntry {
+                score: value,
+                depth,
+                bound,
+                best_move: Some(best_move),
+            },
+        );
+        value
+    }
+
+    /// Returns how many more pieces fit on the board.
+    fn board_spaces_left(&self) -> usize {
+        self.board.width() as usize * self.board.height() as usize - self.get_depth() as usize
+    }
+
+    /// Scores a child from this BoardState's point of view, a ply further from the end of
+    /// the game, searching it with the bounds moved a ply closer to it.
+    fn child_score(
+        &self,
+        arena: &Arena,
+        child: NodeId,
+        depth: Option<usize>,
+        alpha: isize,
+        beta: isize,
+        tables: &mut SearchTables,
+    ) -> isize {
+        one_ply_back(arena[child].alpha_beta_pruning(
+            arena,
+            depth,
+            one_ply_forward(alpha),
+            one_ply_forward(beta),
+            tables,
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::isize::{MAX, MIN};
-
     use crate::game_engine::{
-        board::Board, layer_generator::LayerGenerator, transposition::TranspositionTable,
+        arena::Arena,
+        board::Board,
+        layer_generator::LayerGenerator,
+        mate_distance::{is_forced, loss_in},
+        power_ups::{Move, PowerUpKind, PowerUps},
+        transposition::TranspositionTable,
     };
 
-    use super::how_good_is;
+    use super::{
+        how_deep_is, how_good_is, how_good_is_to_depth, MoveOrdering, ScoreConfidence, SearchTables,
+    };
 
     #[test]
     fn alpha_beta_pruning() {
@@ -110,20 +415,17 @@ mod tests {
             [0, 1, 1, 1, 0, 0, 0],
         ]);
 
-        let mut table = TranspositionTable::default();
-        let (board_state, _) = table.get_board_state(board, false);
-        let mut generator = LayerGenerator::new(table);
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(board, false, None);
+        let mut generator = LayerGenerator::new(arena);
 
         for _ in 0..1000 {
             generator.next();
         }
 
         assert_eq!(
-            how_good_is(
-                &board_state.borrow(),
-                &mut TranspositionTable::<isize>::default()
-            ),
-            MIN
+            how_good_is(generator.arena(), board_state, &mut SearchTables::default()),
+            loss_in(1)
         );
 
         let board = Board::from_arrays([
@@ -135,29 +437,67 @@ mod tests {
             [0, 1, 1, 0, 2, 2, 1],
         ]);
 
-        let mut table = TranspositionTable::default();
-        let (board_state, _) = table.get_board_state(board, true);
-        let mut generator = LayerGenerator::new(table);
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(board, true, None);
+        let mut generator = LayerGenerator::new(arena);
 
         for _ in 0..1000 {
             generator.next();
         }
 
-        assert_ne!(
-            how_good_is(
-                &board_state.borrow(),
-                &mut TranspositionTable::<isize>::default()
-            ),
-            MIN
+        assert!(!is_forced(how_good_is(
+            generator.arena(),
+            board_state,
+            &mut SearchTables::default()
+        )));
+
+        let board = Board::from_arrays([
+            [1, 2, 2, 1, 1, 0, 0],
+            [1, 2, 1, 2, 1, 2, 0],
+            [1, 2, 1, 2, 1, 2, 0],
+            [2, 1, 2, 1, 2, 1, 0],
+            [2, 1, 2, 1, 2, 1, 0],
+            [2, 1, 2, 1, 2, 1, 0],
+        ]);
+
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(board, false, None);
+        let mut generator = LayerGenerator::new(arena);
+
+        for _ in 0..1000 {
+            generator.next();
+        }
+
+        assert_eq!(
+            how_good_is(generator.arena(), board_state, &mut SearchTables::default()),
+            loss_in(7)
         );
-        assert_ne!(
-            how_good_is(
-                &board_state.borrow(),
-                &mut TranspositionTable::<isize>::default()
-            ),
-            MAX
+
+        let board = Board::from_arrays([
+            [1, 2, 2, 1, 1, 0, 0],
+            [1, 2, 1, 2, 1, 2, 0],
+            [1, 2, 1, 2, 1, 2, 0],
+            [2, 1, 2, 1, 2, 1, 0],
+            [2, 1, 2, 1, 2, 1, 0],
+            [2, 1, 2, 1, 2, 1, 0],
+        ]);
+
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(board, true, None);
+        let mut generator = LayerGenerator::new(arena);
+
+        for _ in 0..1000 {
+            generator.next();
+        }
+
+        assert_eq!(
+            how_good_is(generator.arena(), board_state, &mut SearchTables::default()),
+            0
         );
+    }
 
+    #[test]
+    fn cached_scores() {
         let board = Board::from_arrays([
             [1, 2, 2, 1, 1, 0, 0],
             [1, 2, 1, 2, 1, 2, 0],
@@ -167,22 +507,171 @@ mod tests {
             [2, 1, 2, 1, 2, 1, 0],
         ]);
 
-        let mut table = TranspositionTable::default();
-        let (board_state, _) = table.get_board_state(board, false);
-        let mut generator = LayerGenerator::new(table);
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(board, false, None);
+        let mut generator = LayerGenerator::new(arena);
+
+        for _ in 0..1000 {
+            generator.next();
+        }
+        let arena = generator.arena();
+
+        // A shallow score isn't reused by a search of the whole tree
+        let mut table = SearchTables::default();
+        assert!(!is_forced(how_good_is_to_depth(
+            arena,
+            board_state,
+            Some(1),
+            &mut table
+        )));
+        assert_eq!(how_good_is(arena, board_state, &mut table), loss_in(7));
+
+        // Though a score of the whole tree stands in for a shallower search
+        assert_eq!(
+            how_good_is_to_depth(arena, board_state, Some(1), &mut table),
+            loss_in(7)
+        );
+
+        // Cut off scores left in the table don't change the scores of the states beneath
+        let mut shared_table = SearchTables::default();
+        how_good_is(arena, board_state, &mut shared_table);
+        for child in arena[board_state].children.iter() {
+            assert_eq!(
+                how_good_is(arena, child.state, &mut shared_table),
+                how_good_is(arena, child.state, &mut SearchTables::default())
+            );
+        }
+    }
+
+    #[test]
+    fn move_ordering() {
+        let mut ordering = MoveOrdering::default();
+        ordering.record_cut_off(5, true, Move::Drop(2), 3);
+        ordering.record_cut_off(5, true, Move::Drop(6), 1);
+        ordering.record_cut_off(7, true, Move::Drop(1), 4);
+
+        // The remembered best move comes first, then the killers, the most recent first
+        assert!(
+            ordering.priority(5, true, Move::Drop(0), Some(Move::Drop(0)))
+                > ordering.priority(5, true, Move::Drop(6), Some(Move::Drop(0)))
+        );
+        assert!(
+            ordering.priority(5, true, Move::Drop(6), None)
+                > ordering.priority(5, true, Move::Drop(2), None)
+        );
+        assert!(
+            ordering.priority(5, true, Move::Drop(2), None)
+                > ordering.priority(5, true, Move::Drop(1), None)
+        );
+
+        // Then the moves that have caused the most cut offs, weighted by how much was left
+        assert!(
+            ordering.priority(6, true, Move::Drop(1), None)
+                > ordering.priority(6, true, Move::Drop(2), None)
+        );
+        assert_eq!(ordering.priority(6, false, Move::Drop(1), None), 0);
+
+        // Power-ups can be killers, but have no history
+        ordering.record_cut_off(6, true, Move::Clear(1), 4);
+        assert!(
+            ordering.priority(6, true, Move::Clear(1), None)
+                > ordering.priority(6, true, Move::Drop(1), None)
+        );
+        assert_eq!(ordering.priority(7, true, Move::Clear(1), None), 0);
+
+        // The best move found is remembered in the table
+        let board = Board::from_arrays([
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 2, 2, 2, 0, 0, 0],
+            [0, 1, 1, 1, 0, 0, 0],
+        ]);
+
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(board, false, None);
+        let mut generator = LayerGenerator::new(arena);
+
+        for _ in 0..1000 {
+            generator.next();
+        }
+
+        let mut tables = SearchTables::default();
+        how_good_is(generator.arena(), board_state, &mut tables);
+
+        let board_state = &generator.arena()[board_state];
+        let board = &board_state.board;
+        let (entry, is_flipped) = tables
+            .scores
+            .get_transposed(board, board_state.get_context())
+            .unwrap();
+        let best_move = is_flipped.map_move(entry.best_move.unwrap(), board.width());
+        assert!(board.winning_moves(false).contains(&best_move.column()));
+    }
+
+    #[test]
+    fn searching_power_ups() {
+        // Player one has three along the bottom, blocked by player two's piece in the 4th
+        //  column, which only their anvil can crush
+        let board = Board::from_moves("142737").unwrap();
+        let anvils = Some(PowerUps::new(PowerUpKind::Anvil));
+
+        let mut arena = Arena::default();
+        let (with_anvils, _) = arena.get_board_state(board.clone(), false, anvils);
+        let (without, _) = arena.get_board_state(board, false, None);
+        let mut generator = LayerGenerator::new(arena);
 
         for _ in 0..1000 {
             generator.next();
         }
 
+        let mut tables = SearchTables::default();
         assert_eq!(
-            how_good_is(
-                &board_state.borrow(),
-                &mut TranspositionTable::<isize>::default()
+            how_good_is_to_depth(generator.arena(), with_anvils, Some(2), &mut tables),
+            loss_in(1)
+        );
+        assert!(!is_forced(how_good_is_to_depth(
+            generator.arena(),
+            without,
+            Some(2),
+            &mut tables
+        )));
+
+        let arena = generator.arena();
+        let (entry, _) = tables
+            .scores
+            .get_transposed(&arena[with_anvils].board, arena[with_anvils].get_context())
+            .unwrap();
+        assert_eq!(entry.best_move, Some(Move::Anvil(3)));
+    }
+
+    #[test]
+    fn search_depth() {
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(Board::default(), false, None);
+        let mut generator = LayerGenerator::new(arena);
+
+        assert_eq!(
+            how_deep_is(
+                generator.arena(),
+                board_state,
+                &mut TranspositionTable::default()
+            ),
+            Some(0)
+        );
+
+        generator.next();
+        assert_eq!(
+            how_deep_is(
+                generator.arena(),
+                board_state,
+                &mut TranspositionTable::default()
             ),
-            MIN
+            Some(1)
         );
 
+        // A nearly finished game can be played out completely
         let board = Board::from_arrays([
             [1, 2, 2, 1, 1, 0, 0],
             [1, 2, 1, 2, 1, 2, 0],
@@ -192,20 +681,43 @@ mod tests {
             [2, 1, 2, 1, 2, 1, 0],
         ]);
 
-        let mut table = TranspositionTable::default();
-        let (board_state, _) = table.get_board_state(board, true);
-        let mut generator = LayerGenerator::new(table);
+        let mut arena = Arena::default();
+        let (board_state, _) = arena.get_board_state(board, true, None);
+        let mut generator = LayerGenerator::new(arena);
 
         for _ in 0..1000 {
             generator.next();
         }
 
         assert_eq!(
-            how_good_is(
-                &board_state.borrow(),
-                &mut TranspositionTable::<isize>::default()
+            how_deep_is(
+                generator.arena(),
+                board_state,
+                &mut TranspositionTable::default()
             ),
-            0
+            None
+        );
+    }
+
+    #[test]
+    fn score_confidence() {
+        assert_eq!(
+            ScoreConfidence::new(isize::MAX, Some(0)),
+            ScoreConfidence::Proven
         );
+        assert_eq!(ScoreConfidence::new(12, None), ScoreConfidence::Proven);
+        assert_eq!(
+            ScoreConfidence::new(12, Some(0)),
+            ScoreConfidence::Heuristic
+        );
+        assert_eq!(
+            ScoreConfidence::new(12, Some(3)),
+            ScoreConfidence::Searched(3)
+        );
+
+        assert_eq!(ScoreConfidence::Heuristic.shade(), 0.0);
+        assert_eq!(ScoreConfidence::Searched(4).shade(), 0.5);
+        assert_eq!(ScoreConfidence::Searched(20).shade(), 1.0);
+        assert_eq!(ScoreConfidence::Proven.shade(), 1.0);
     }
 }
@@ -1,14 +1,4 @@
-use std::{
-    cell::RefCell,
-    cmp::max,
-    mem::size_of,
-    rc::{Rc, Weak},
-};
-
-use crate::game_engine::{
-    board_state::{BoardState, ChildState},
-    layer_generator::LayerGenerator,
-};
+use crate::game_engine::{arena::NodeId, layer_generator::LayerGenerator};
 
 /// Contains different numerical details about the size of a
 /// decision tree.
@@ -20,46 +10,78 @@ pub struct TreeSize {
 }
 
 /// Calculates numerical details about a decision tree.
-pub fn calculate_size(root: Rc<RefCell<BoardState>>, generator: &LayerGenerator) -> TreeSize {
-    let mut depth = 0;
-    let mut size = 0;
-    let mut memory = 0;
-
-    for (_, weak_ref) in generator.table_ref().iter() {
-        // Size of the reference in the table
-        memory += size_of::<u64>(); // key
-        memory += size_of::<Weak<RefCell<BoardState>>>(); // value
-
-        // Size of the reference as a child
-        if weak_ref.strong_count() > 0 {
-            memory += size_of::<BoardState>();
-            memory += size_of::<ChildState>() * weak_ref.strong_count();
-
-            size += weak_ref.strong_count();
-
-            let current_depth = weak_ref.upgrade().unwrap().borrow().get_depth();
-            depth = max(current_depth, depth);
-        }
-    }
-
-    size -= generator.buffer_size();
+///
+/// The size is how many distinct positions are stored, each transposition counted once.
+/// The memory is what the tree and generator have allocated, counting the spare capacity of
+/// their Vecs and tables. It doesn't include the allocator's own bookkeeping.
+pub fn calculate_size(root: NodeId, generator: &LayerGenerator) -> TreeSize {
+    let arena = generator.arena();
 
     TreeSize {
-        depth: (depth - root.borrow().get_depth() + 1) as usize,
-        size,
-        memory,
+        depth: (generator.deepest_depth() - arena[root].get_depth() + 1) as usize,
+        size: arena.len(),
+        memory: generator.heap_size(),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::RefCell, rc::Rc};
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        collections::HashSet,
+    };
 
     use crate::game_engine::{
-        board::Board, board_state::BoardState, layer_generator::LayerGenerator,
-        transposition::TranspositionTable, tree_size::calculate_size,
+        arena::{Arena, NodeId},
+        board::Board,
+        game_manager::GameManager,
+        layer_generator::LayerGenerator,
+        tree_size::calculate_size,
     };
 
+    /// Keeps a running total of the bytes each thread has allocated, so a test can measure
+    /// its own allocations while other tests run alongside it.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATED.try_with(|total| total.set(total.get() + layout.size() as isize));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            let _ = ALLOCATED.try_with(|total| total.set(total.get() - layout.size() as isize));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn accurate_memory() {
+        for states in [1000, 20_000, 100_000] {
+            let before = ALLOCATED.with(Cell::get);
+            let mut manager = GameManager::new_game();
+            manager.try_generate_x_states(states);
+            manager.get_move_scores();
+            let allocated = (ALLOCATED.with(Cell::get) - before) as f64;
+
+            let memory = manager.size().memory as f64;
+            assert!(
+                (memory - allocated).abs() <= allocated * 0.1,
+                "calculated: {}, allocated: {}",
+                memory,
+                allocated
+            );
+        }
+    }
+
     #[test]
     fn correct_size() {
         let board_array = [
@@ -71,64 +93,50 @@ mod tests {
             [0, 1, 2, 0, 2, 1, 2],
         ];
 
-        let mut table = TranspositionTable::default();
-        let (root, _) = table.get_board_state(Board::from_arrays(board_array), false);
+        let mut arena = Arena::default();
+        let (root, _) = arena.get_board_state(Board::from_arrays(board_array), false, None);
 
-        let mut generator = LayerGenerator::new(table);
+        let mut generator = LayerGenerator::new(arena);
         for _ in 0..(1 + 6 + 36) {
             generator.next();
         }
 
-        let stats = calculate_size(root.clone(), &generator);
-
-        let (depth, size) = calculate_from_root(root.clone());
-        assert_eq!(stats.depth, depth);
-        assert!(
-            stats.size <= size + 1,
-            "calculated: {}, manually: {}",
-            stats.size,
-            size
+        let stats = calculate_size(root, &generator);
+        assert_eq!(
+            (stats.depth, stats.size),
+            calculate_from_root(&generator, root)
         );
 
         for _ in 0..1000 {
             generator.next();
         }
 
-        let stats = calculate_size(root.clone(), &generator);
-
-        let (depth, size) = calculate_from_root(root.clone());
-        assert_eq!(stats.depth, depth);
-        assert!(
-            stats.size < size,
-            "calculated: {}, manually: {}",
-            stats.size,
-            size
+        // Transpositions are only counted once
+        let stats = calculate_size(root, &generator);
+        assert_eq!(
+            (stats.depth, stats.size),
+            calculate_from_root(&generator, root)
         );
+        assert!(stats.size > 1000);
     }
 
-    fn calculate_from_root(root: Rc<RefCell<BoardState>>) -> (usize, usize) {
+    /// Walks the tree layer by layer, returning its depth and how many distinct positions
+    /// are in it.
+    fn calculate_from_root(generator: &LayerGenerator, root: NodeId) -> (usize, usize) {
+        let arena = generator.arena();
+        let mut seen = HashSet::from([root]);
         let mut current_layer = vec![root];
-        let mut next_layer = Vec::new();
-
-        let mut size = 0;
         let mut depth = 0;
-        while let Some(current_node) = current_layer.pop() {
-            size += 1;
-            next_layer.extend(
-                current_node
-                    .borrow()
-                    .children
-                    .iter()
-                    .map(|n| n.state.clone()),
-            );
 
-            if current_layer.len() == 0 {
-                current_layer = next_layer;
-                next_layer = Vec::new();
-                depth += 1;
-            }
+        while !current_layer.is_empty() {
+            depth += 1;
+            current_layer = current_layer
+                .iter()
+                .flat_map(|id| arena[*id].children.iter().map(|child| child.state))
+                .filter(|id| seen.insert(*id))
+                .collect();
         }
 
-        (depth, size)
+        (depth, seen.len())
     }
 }
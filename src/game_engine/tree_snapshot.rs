@@ -0,0 +1,120 @@
+//! Deterministic snapshots of the decision tree, for tests that check two ways of building or
+//! maintaining a tree end up with the same one, e.g. restarting the layer generator against
+//! carrying on with its frontier.
+//!
+//! A snapshot only depends on the positions in the tree, their scores and how they're linked,
+//! not the order they were generated in. The order decides which of a position and its mirror
+//! image is stored first, so both are treated as the same position.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::game_engine::{
+    arena::{Arena, NodeId},
+    layer_generator::LayerGenerator,
+};
+
+/// A summary of a decision tree and the layer generator growing it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TreeSnapshot {
+    /// A hash of every BoardState reachable from the root, along with its children.
+    pub hash: u64,
+    /// How many distinct BoardStates are reachable from the root.
+    pub states: usize,
+    /// The positions the layer generator has yet to generate children for, as sorted
+    /// canonical keys without duplicates.
+    pub frontier: Vec<u64>,
+}
+
+/// Takes a snapshot of the tree beneath root, and of the generator growing it.
+pub fn snapshot(root: NodeId, generator: &LayerGenerator) -> TreeSnapshot {
+    let mut hashes = HashMap::new();
+    let hash = hash_state(generator.arena(), root, &mut hashes);
+
+    let mut frontier: Vec<u64> = generator
+        .frontier()
+        .map(|board_state| board_state.board.canonical_key())
+        .collect();
+    frontier.sort_unstable();
+    frontier.dedup();
+
+    TreeSnapshot {
+        hash,
+        states: hashes.len(),
+        frontier,
+    }
+}
+
+/// Hashes a BoardState along with everything beneath it.
+///
+/// Hashes are remembered by the BoardState's id, so transpositions shared between several
+/// parents are only walked once.
+fn hash_state(arena: &Arena, id: NodeId, hashes: &mut HashMap<NodeId, u64>) -> u64 {
+    if let Some(hash) = hashes.get(&id) {
+        return *hash;
+    }
+
+    let borrowed = &arena[id];
+    // A mirrored tree generates the same children in the mirrored order
+    let mut children: Vec<u64> = borrowed
+        .children
+        .iter()
+        .map(|child| hash_state(arena, child.state, hashes))
+        .collect();
+    children.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    borrowed.board.canonical_key().hash(&mut hasher);
+    borrowed.get_turn().hash(&mut hasher);
+    (borrowed.is_game_over() as u8).hash(&mut hasher);
+    borrowed.get_score().hash(&mut hasher);
+    children.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    hashes.insert(id, hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{
+        arena::{Arena, NodeId},
+        board::Board,
+        layer_generator::LayerGenerator,
+        tree_snapshot::snapshot,
+    };
+
+    /// Starts generating the tree for the position after the moves.
+    fn tree_for(moves: &str) -> (NodeId, LayerGenerator) {
+        let mut arena = Arena::default();
+        let (root, _) = arena.get_board_state(Board::from_moves(moves).unwrap(), false, None);
+
+        (root, LayerGenerator::new(arena))
+    }
+
+    #[test]
+    fn mirrored_trees() {
+        let (root, mut generator) = tree_for("4453");
+        let (mirrored_root, mut mirrored_generator) = tree_for("4435");
+
+        let empty = snapshot(root, &generator);
+        assert_eq!(empty.states, 1);
+        assert_eq!(empty, snapshot(mirrored_root, &mirrored_generator));
+
+        // Generating the root's children, and then each of theirs
+        for _ in 0..1 + 7 {
+            generator.next();
+            mirrored_generator.next();
+        }
+        let generated = snapshot(root, &generator);
+        assert_eq!(generated, snapshot(mirrored_root, &mirrored_generator));
+        assert_ne!(generated.hash, empty.hash);
+        assert!(!generated.frontier.is_empty());
+
+        // A different position has a different tree
+        let (other_root, other_generator) = tree_for("4454");
+        assert_ne!(empty, snapshot(other_root, &other_generator));
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game_engine::{
+    board::Board,
+    win_check::{is_game_over, GameOver},
+};
+
+/// Variants of Connect Four that change who a finished game goes to, while keeping the same
+/// moves legal and ending the game in the same places.
+///
+/// Unlike house rules, the engine has to know which variant it's playing, since it changes
+/// what every position is worth.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum VariantRules {
+    /// Getting four in a row wins.
+    #[default]
+    Standard,
+    /// Misère, where getting four in a row loses.
+    Misere,
+}
+
+impl VariantRules {
+    /// Every variant, in the order they're offered to the user.
+    pub const ALL: [VariantRules; 2] = [VariantRules::Standard, VariantRules::Misere];
+
+    /// Names the variant for the user, and in the settings a game was played with.
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariantRules::Standard => "Standard",
+            VariantRules::Misere => "Misère",
+        }
+    }
+
+    /// Describes how the variant is won, e.g. "Four in a row loses".
+    pub fn description(&self) -> &'static str {
+        match self {
+            VariantRules::Standard => "Four in a row wins",
+            VariantRules::Misere => "Four in a row loses",
+        }
+    }
+
+    /// Parses a variant written by name.
+    pub fn from_tag_value(value: &str) -> Result<VariantRules, String> {
+        VariantRules::ALL
+            .into_iter()
+            .find(|variant| variant.name() == value.trim())
+            .ok_or_else(|| format!("Unknown variant '{}'", value))
+    }
+
+    /// Interprets a result found by win_check, which always goes to the player who got four
+    /// in a row, under these rules.
+    pub fn result(&self, game_over: GameOver) -> GameOver {
+        match (self, game_over) {
+            (VariantRules::Misere, GameOver::OneWins) => GameOver::TwoWins,
+            (VariantRules::Misere, GameOver::TwoWins) => GameOver::OneWins,
+            (_, game_over) => game_over,
+        }
+    }
+
+    /// Gets whether the game is over for a given Board under these rules, and if so who won.
+    pub fn is_game_over(&self, board: &Board, turn: bool) -> GameOver {
+        self.result(is_game_over(board, turn))
+    }
+
+    /// Converts one of the heuristic's scores, which reward getting closer to four in a row,
+    /// into a score under these rules.
+    pub fn heuristic(&self, score: isize) -> isize {
+        match self {
+            VariantRules::Standard => score,
+            VariantRules::Misere => score.checked_neg().unwrap_or(isize::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::{board::Board, variant_rules::VariantRules, win_check::GameOver};
+
+    #[test]
+    fn misere_results() {
+        // Player one has just got four in a row along the bottom
+        let board = Board::from_moves("4455667").unwrap();
+        assert_eq!(
+            VariantRules::Standard.is_game_over(&board, true),
+            GameOver::OneWins
+        );
+        assert_eq!(
+            VariantRules::Misere.is_game_over(&board, true),
+            GameOver::TwoWins
+        );
+
+        let full = Board::from_moves("121212343434565656717171232323454545676767").unwrap();
+        assert_eq!(
+            VariantRules::Misere.is_game_over(&full, false),
+            GameOver::Tie
+        );
+
+        assert_eq!(VariantRules::Misere.heuristic(12), -12);
+        assert_eq!(VariantRules::Misere.heuristic(isize::MIN), isize::MAX);
+        assert_eq!(VariantRules::Standard.heuristic(12), 12);
+    }
+
+    #[test]
+    fn tag_values() {
+        for variant in VariantRules::ALL {
+            assert_eq!(VariantRules::from_tag_value(variant.name()), Ok(variant));
+        }
+        assert!(VariantRules::from_tag_value("Pop out").is_err());
+    }
+}
@@ -1,11 +1,10 @@
-use crate::{
-    consts::NUMBER_TO_WIN,
-    game_engine::board::{Board, OutOfBounds},
-};
+use serde::{Deserialize, Serialize};
+
+use crate::game_engine::board::{Board, OutOfBounds};
 
 /// This represents whether the game is over, and if so how
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq, Default, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Default, Copy, Clone, Serialize, Deserialize)]
 pub enum GameOver {
     #[default]
     NoWin,
@@ -40,6 +39,80 @@ pub fn is_game_over(board: &Board, turn: bool) -> GameOver {
     }
 }
 
+/// Returns the columns where the given color could immediately win by dropping a piece.
+pub fn winning_columns(board: &Board, color: bool) -> Vec<u8> {
+    (0..board.width())
+        .filter(|col| {
+            let mut new_board = board.clone();
+            new_board.drop_piece(*col, color).is_ok() && has_color_won(&new_board, color)
+        })
+        .collect()
+}
+
+/// Returns the spaces of a connect four the given color has made, as (column, row) with
+/// rows counting up from the bottom of the board, or None if they haven't made one.
+///
+/// If the color has several, the one found first is returned, searching the columns from
+/// the left and each column from the bottom.
+pub fn winning_line(board: &Board, color: bool) -> Option<Vec<(u8, u8)>> {
+    let number_to_win = board.number_to_win() as i8;
+    let is_color = |col: i8, row: i8| {
+        (0..board.width() as i8).contains(&col)
+            && (0..board.height() as i8).contains(&row)
+            && matches!(board.get_piece(col as u8, row as u8), Ok(piece) if piece == color)
+    };
+
+    for column in 0..board.width() as i8 {
+        for row in 0..board.get_height(column as u8) as i8 {
+            for (col_step, row_step) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                let line: Vec<(i8, i8)> = (0..number_to_win)
+                    .map(|step| (column + col_step * step, row + row_step * step))
+                    .collect();
+
+                if line.iter().all(|&(col, row)| is_color(col, row)) {
+                    return Some(
+                        line.into_iter()
+                            .map(|(col, row)| (col as u8, row as u8))
+                            .collect(),
+                    );
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// The direction of the line of pieces in a connect four.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    Vertical,
+    Horizontal,
+    Diagonal,
+}
+
+/// Returns the direction of the connect four the given color would make by dropping a piece
+/// in the given column, or None if the move wouldn't win.
+///
+/// A move that wins in several directions at once counts as the most obvious of them,
+/// vertical being the most obvious and diagonal the least.
+pub fn winning_direction(board: &Board, color: bool, col: u8) -> Option<Direction> {
+    let mut new_board = board.clone();
+    new_board.drop_piece(col, color).ok()?;
+
+    if has_color_won_vertically(&new_board, color) {
+        Some(Direction::Vertical)
+    } else if has_color_won_horizontally(&new_board, color) {
+        Some(Direction::Horizontal)
+    } else if has_color_won_upward_diagonally(&new_board, color)
+        || has_color_won_downward_diagonally(&new_board, color)
+    {
+        Some(Direction::Diagonal)
+    } else {
+        None
+    }
+}
+
 /// Returns whether the given color has won in the given board state.
 fn has_color_won(board: &Board, color: bool) -> bool {
     // Figuring out what row the highest piece is in
@@ -52,7 +125,7 @@ fn has_color_won(board: &Board, color: bool) -> bool {
     }
 
     // We can skip the other checks if there's not yet pieces stacked four high
-    if highest_row >= NUMBER_TO_WIN {
+    if highest_row >= board.number_to_win() {
         // Checking for the other possible connect fours
         if has_color_won_vertically(board, color)
             || has_color_won_upward_diagonally(board, color)
@@ -67,26 +140,52 @@ fn has_color_won(board: &Board, color: bool) -> bool {
 
 /// Helper function to check for horizontal connect fours.
 fn has_color_won_horizontally(board: &Board, color: bool) -> bool {
-    check_strips(board.horizontal_strip_iter(), color)
+    check_strips(board.horizontal_strip_iter(), color, board.number_to_win())
 }
 
 /// Helper function to check for vertical connect fours.
+///
+/// Each column's pieces are already stored as bits, so rather than walking the column this
+/// checks for number_to_win set bits in a row in the color's bitmap.
 fn has_color_won_vertically(board: &Board, color: bool) -> bool {
-    check_strips(board.vertical_strip_iter(false), color)
+    let number_to_win = board.number_to_win();
+
+    (0..board.width()).any(|col| {
+        // Columns that aren't yet tall enough can't hold a connect four
+        if board.get_height(col) < number_to_win {
+            return false;
+        }
+
+        // Each shift and mask leaves the bits that have one more of the color's pieces
+        // stacked on top of them
+        let bitmap = board.get_color_bitmap(col, color);
+        let in_a_row =
+            (1..number_to_win).fold(bitmap, |in_a_row, shift| in_a_row & (bitmap >> shift));
+
+        in_a_row != 0
+    })
 }
 
 /// Helper function to check for upward diagonal connect fours.
 fn has_color_won_upward_diagonally(board: &Board, color: bool) -> bool {
-    check_strips(board.upward_diagonal_strip_iter(false), color)
+    check_strips(
+        board.upward_diagonal_strip_iter(false),
+        color,
+        board.number_to_win(),
+    )
 }
 
 /// Helper function to check for downward diagonal connect fours.
 fn has_color_won_downward_diagonally(board: &Board, color: bool) -> bool {
-    check_strips(board.downward_diagonal_strip_iter(false), color)
+    check_strips(
+        board.downward_diagonal_strip_iter(false),
+        color,
+        board.number_to_win(),
+    )
 }
 
-/// Helper function to check a strip iterator for connect fours.
-fn check_strips<T, U>(mut strip_iter: T, color: bool) -> bool
+/// Helper function to check a strip iterator for number_to_win pieces in a row.
+fn check_strips<T, U>(mut strip_iter: T, color: bool, number_to_win: u8) -> bool
 where
     T: Iterator<Item = U>,
     U: ExactSizeIterator + Iterator<Item = Result<bool, OutOfBounds>>,
@@ -100,12 +199,12 @@ where
             in_a_row = increment_if_matching(in_a_row, piece, color);
 
             // If there are four in a row, then we can return true
-            if in_a_row == NUMBER_TO_WIN {
+            if in_a_row == number_to_win {
                 return true;
             }
 
             // And if there aren't enough pieces left to make a connect four, we can break early
-            if in_a_row + (strip.len() as u8) < NUMBER_TO_WIN {
+            if in_a_row + (strip.len() as u8) < number_to_win {
                 break;
             }
         }
@@ -134,11 +233,18 @@ fn increment_if_matching(in_a_row: u8, piece: Result<bool, OutOfBounds>, color:
 
 #[cfg(test)]
 mod tests {
-    use crate::game_engine::{
-        board::Board,
-        win_check::{
-            has_color_won, has_color_won_downward_diagonally, has_color_won_horizontally,
-            has_color_won_upward_diagonally, has_color_won_vertically,
+    use rand::Rng;
+
+    use crate::{
+        consts::BOARD_WIDTH,
+        game_engine::{
+            board::Board,
+            win_check::{
+                check_strips, has_color_won, has_color_won_downward_diagonally,
+                has_color_won_horizontally, has_color_won_upward_diagonally,
+                has_color_won_vertically, winning_columns, winning_direction, winning_line,
+                Direction,
+            },
         },
     };
 
@@ -256,6 +362,35 @@ mod tests {
         assert!(has_color_won(&board, true));
     }
 
+    #[test]
+    fn vertical_wins_match_strip_check() {
+        let mut rng = rand::thread_rng();
+
+        // Filling boards at random, checking every position along the way against walking
+        // each column's strip of pieces
+        for _ in 0..200 {
+            let mut board = Board::default();
+            let mut color = false;
+
+            while !board.is_full() {
+                let col = rng.gen_range(0..BOARD_WIDTH);
+                if board.drop_piece(col, color).is_err() {
+                    continue;
+                }
+                color = !color;
+
+                for player in [false, true] {
+                    assert_eq!(
+                        has_color_won_vertically(&board, player),
+                        check_strips(board.vertical_strip_iter(false), player, 4),
+                        "{:?}",
+                        board.to_arrays()
+                    );
+                }
+            }
+        }
+    }
+
     #[test]
     fn upward_diagonal_wins() {
         let board = Board::from_arrays([
@@ -367,4 +502,80 @@ mod tests {
         assert!(has_color_won_downward_diagonally(&board, true));
         assert!(has_color_won(&board, true));
     }
+
+    #[test]
+    fn finds_winning_columns() {
+        let board = Board::from_arrays([
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 2, 2, 2, 0, 0, 0],
+            [0, 1, 1, 1, 0, 0, 0],
+        ]);
+
+        assert_eq!(winning_columns(&board, false), vec![0, 4]);
+        assert_eq!(winning_columns(&board, true), Vec::<u8>::new());
+
+        let board = Board::from_arrays([
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 2, 0, 0, 0],
+            [0, 1, 1, 2, 0, 0, 0],
+            [0, 1, 1, 2, 0, 0, 1],
+        ]);
+
+        assert_eq!(winning_columns(&board, false), Vec::<u8>::new());
+        assert_eq!(winning_columns(&board, true), vec![3]);
+    }
+
+    #[test]
+    fn win_directions() {
+        let board = Board::from_moves("121212").unwrap();
+        assert_eq!(
+            winning_direction(&board, false, 0),
+            Some(Direction::Vertical)
+        );
+        assert_eq!(winning_direction(&board, false, 3), None);
+
+        let board = Board::from_moves("112233").unwrap();
+        assert_eq!(
+            winning_direction(&board, false, 3),
+            Some(Direction::Horizontal)
+        );
+
+        let board = Board::from_moves("1223343474").unwrap();
+        assert_eq!(
+            winning_direction(&board, false, 3),
+            Some(Direction::Diagonal)
+        );
+        assert_eq!(
+            winning_direction(&board, true, 4),
+            Some(Direction::Horizontal)
+        );
+    }
+
+    #[test]
+    fn winning_lines() {
+        let board = Board::from_moves("4455667").unwrap();
+        assert_eq!(
+            winning_line(&board, false),
+            Some(vec![(3, 0), (4, 0), (5, 0), (6, 0)])
+        );
+        assert_eq!(winning_line(&board, true), None);
+
+        let board = Board::from_moves("12234334544").unwrap();
+        assert_eq!(
+            winning_line(&board, false),
+            Some(vec![(0, 0), (1, 1), (2, 2), (3, 3)])
+        );
+
+        // The mirrored line is found from its leftmost piece
+        let board = Board::from_moves("76654554344").unwrap();
+        assert_eq!(
+            winning_line(&board, false),
+            Some(vec![(3, 3), (4, 2), (5, 1), (6, 0)])
+        );
+    }
 }
@@ -0,0 +1,232 @@
+use rand::Rng;
+
+use crate::{
+    consts::BOARD_WIDTH,
+    game_engine::{
+        column_label::ColumnLabel,
+        fixed_point,
+        game_manager::{GameManager, GameOver},
+        mate_distance::{is_forced, is_forced_loss, is_forced_win},
+    },
+};
+
+/// How many points of score change the odds of winning by a factor of e, as fit by
+/// `rusty_connect_four calibrate` from around 900 depth 6 self-play games.
+pub const CALIBRATED_SCALE: f64 = 250.0;
+
+/// How far ahead the engines look in calibration games.
+const CALIBRATION_DEPTH: usize = 6;
+
+/// How many random moves each calibration game starts with, so the games cover a wider
+/// range of positions than the engine would reach on its own.
+const CALIBRATION_OPENING_MOVES: usize = 4;
+
+/// The range of scales the fit searches.
+const SCALE_RANGE: (f64, f64) = (1.0, 10_000.0);
+
+/// Keeps fitted probabilities away from 0 and 1, where the log likelihood is infinite.
+const PROBABILITY_EPSILON: f64 = 1e-9;
+
+/// The longest odds score_for_odds tells apart, matching PROBABILITY_EPSILON.
+const ODDS_LIMIT: u64 = 1_000_000_000;
+
+/// The bars sparklines are drawn with, from the lowest chance of winning to the highest.
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Converts one of the engine's scores into the chance of winning, counting a draw as half
+/// a win, using the calibrated logistic mapping.
+pub fn win_probability(score: isize) -> f64 {
+    win_probability_with_scale(score, CALIBRATED_SCALE)
+}
+
+/// Converts a chance of winning back into a score on the calibrated scale, the inverse of
+/// win_probability. A chance of 0 or 1 is kept finite, since it only proves a win or loss
+/// when it comes from a search.
+pub fn score_for_probability(probability: f64) -> isize {
+    let probability = probability.clamp(PROBABILITY_EPSILON, 1.0 - PROBABILITY_EPSILON);
+
+    (CALIBRATED_SCALE * (probability / (1.0 - probability)).ln()).round() as isize
+}
+
+/// Converts the odds of winning into a score on the calibrated scale, like
+/// score_for_probability but with integers only, so that it's the same on every platform.
+///
+/// The odds are given as how much weighs for winning and against it, e.g. 3 and 1 for a 75%
+/// chance. Odds of a sure win or loss are kept finite like score_for_probability does.
+pub fn score_for_odds(odds_for: u64, odds_against: u64) -> isize {
+    let limit = fixed_point::ln(ODDS_LIMIT);
+    let log_odds = match (odds_for, odds_against) {
+        (0, 0) => 0,
+        (_, 0) => limit,
+        (0, _) => -limit,
+        _ => (fixed_point::ln(odds_for) - fixed_point::ln(odds_against)).clamp(-limit, limit),
+    };
+
+    fixed_point::round(log_odds * CALIBRATED_SCALE as i64) as isize
+}
+
+/// Writes a score as a percentage chance of winning, e.g. "73%".
+pub fn format_win_probability(score: isize) -> String {
+    format!("{:.0}%", win_probability(score) * 100.0)
+}
+
+/// Draws chances of winning as a line of bars, one for each of the latest length chances
+/// from oldest to newest, so the swings of a game can be seen in a single line of text.
+pub fn sparkline(probabilities: &[f64], length: usize) -> String {
+    let start = probabilities.len().saturating_sub(length);
+
+    probabilities[start..]
+        .iter()
+        .map(|probability| {
+            let bar = (probability.clamp(0.0, 1.0) * SPARKLINE_BARS.len() as f64) as usize;
+            SPARKLINE_BARS[bar.min(SPARKLINE_BARS.len() - 1)]
+        })
+        .collect()
+}
+
+/// Converts a score into the chance of winning using the given logistic scale. Forced wins
+/// and losses are certain.
+fn win_probability_with_scale(score: isize, scale: f64) -> f64 {
+    match score {
+        score if is_forced_win(score) => 1.0,
+        score if is_forced_loss(score) || score == isize::MIN => 0.0,
+        score => 1.0 / (1.0 + (-(score as f64) / scale).exp()),
+    }
+}
+
+/// Plays a game between two engines looking CALIBRATION_DEPTH moves ahead, from a random
+/// opening, returning the score of every move played along with how the game turned out for
+/// the player who played it, 1 for a win, 0.5 for a draw and 0 for a loss.
+pub fn self_play_samples() -> Result<Vec<(isize, f64)>, String> {
+    let mut rng = rand::thread_rng();
+    let opening = ColumnLabel::move_string(
+        (0..CALIBRATION_OPENING_MOVES).map(|_| rng.gen_range(0..BOARD_WIDTH)),
+    );
+
+    let mut manager = GameManager::from_moves(&opening)?;
+    let mut scores = Vec::new();
+
+    while manager.is_game_over() == GameOver::NoWin {
+        manager.try_generate_to_depth(CALIBRATION_DEPTH);
+
+        let (column, score) = manager
+            .get_move_scores()
+            .into_iter()
+            .max_by_key(|(column, score)| (*score, BOARD_WIDTH - column))
+            .ok_or_else(|| "No moves could be evaluated".to_owned())?;
+
+        scores.push((manager.get_turn(), score));
+        manager.make_move(column)?;
+    }
+
+    let result = manager.is_game_over();
+    Ok(scores
+        .into_iter()
+        .map(|(player, score)| {
+            let outcome = match (result, player) {
+                (GameOver::OneWins, false) | (GameOver::TwoWins, true) => 1.0,
+                (GameOver::OneWins, true) | (GameOver::TwoWins, false) => 0.0,
+                _ => 0.5,
+            };
+
+            (score, outcome)
+        })
+        .collect())
+}
+
+/// Finds the logistic scale that best predicts the outcomes from the scores, by maximum
+/// likelihood. Forced wins and losses say nothing about the scale, so they're ignored.
+///
+/// Returns None if there are no samples with a finite score.
+pub fn fit_scale(samples: &[(isize, f64)]) -> Option<f64> {
+    let samples: Vec<(isize, f64)> = samples
+        .iter()
+        .copied()
+        .filter(|(score, _)| !is_forced(*score) && *score != isize::MIN)
+        .collect();
+    if samples.is_empty() {
+        return None;
+    }
+
+    let log_likelihood = |scale: f64| -> f64 {
+        samples
+            .iter()
+            .map(|(score, outcome)| {
+                let probability = win_probability_with_scale(*score, scale)
+                    .clamp(PROBABILITY_EPSILON, 1.0 - PROBABILITY_EPSILON);
+
+                outcome * probability.ln() + (1.0 - outcome) * (1.0 - probability).ln()
+            })
+            .sum()
+    };
+
+    // The likelihood has a single peak, so a ternary search over the log of the scale
+    // finds it
+    let (mut low, mut high) = (SCALE_RANGE.0.ln(), SCALE_RANGE.1.ln());
+    for _ in 0..100 {
+        let third = (high - low) / 3.0;
+
+        if log_likelihood((low + third).exp()) < log_likelihood((high - third).exp()) {
+            low += third;
+        } else {
+            high -= third;
+        }
+    }
+
+    Some(((low + high) / 2.0).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_engine::win_probability::{
+        fit_scale, format_win_probability, score_for_probability, self_play_samples, sparkline,
+        win_probability, win_probability_with_scale,
+    };
+
+    #[test]
+    fn probabilities() {
+        assert_eq!(win_probability(0), 0.5);
+        assert_eq!(win_probability(isize::MAX), 1.0);
+        assert_eq!(win_probability(isize::MIN), 0.0);
+        assert!(win_probability(100) > 0.5);
+        assert!((win_probability(100) + win_probability(-100) - 1.0).abs() < 1e-9);
+
+        assert_eq!(format_win_probability(0), "50%");
+        assert_eq!(format_win_probability(isize::MAX), "100%");
+
+        assert_eq!(score_for_probability(0.5), 0);
+        assert_eq!(score_for_probability(win_probability(120)), 120);
+        assert!(score_for_probability(1.0) < isize::MAX);
+        assert!(score_for_probability(0.0) > isize::MIN);
+    }
+
+    #[test]
+    fn sparklines() {
+        assert_eq!(sparkline(&[0.0, 0.3, 0.5, 0.8, 1.0], 10), "▁▃▅▇█");
+        // Only the latest chances are drawn
+        assert_eq!(sparkline(&[0.0, 0.3, 0.5, 0.8, 1.0], 2), "▇█");
+        assert_eq!(sparkline(&[], 10), "");
+    }
+
+    #[test]
+    fn fits_scales() {
+        // Outcomes that follow a scale of 50 exactly, as fractional wins
+        let samples: Vec<(isize, f64)> = (-200..=200)
+            .step_by(10)
+            .map(|score| (score, win_probability_with_scale(score, 50.0)))
+            .collect();
+        let scale = fit_scale(&samples).unwrap();
+        assert!((scale - 50.0).abs() < 0.1, "Fit a scale of {}", scale);
+
+        assert_eq!(fit_scale(&[(isize::MAX, 1.0)]), None);
+    }
+
+    #[test]
+    fn self_play() {
+        let samples = self_play_samples().unwrap();
+        assert!(!samples.is_empty());
+        assert!(samples
+            .iter()
+            .all(|(_, outcome)| [0.0, 0.5, 1.0].contains(outcome)));
+    }
+}
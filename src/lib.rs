@@ -1,4 +1,33 @@
-mod consts;
+//! A Connect Four engine, and the egui app built around it.
+//!
+//! The engine lives in [`game_engine`], with [`game_engine::game_manager::GameManager`] as its
+//! entry point. It can be used without the app, e.g. to play a game headlessly:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use rusty_connect_four::game_engine::game_manager::{GameManager, GameOver};
+//!
+//! let mut manager = GameManager::new_game();
+//!
+//! while manager.is_game_over() == GameOver::NoWin {
+//!     manager.try_generate_for(Duration::from_millis(5));
+//!
+//!     let scores = manager.get_move_scores();
+//!     let (&column, _) = scores.iter().max_by_key(|(_, score)| **score).unwrap();
+//!     manager.make_move(column).unwrap();
+//! }
+//! ```
+//!
+//! More complete programs are in the `examples` directory: `headless_game` plays a full game,
+//! `evaluate_position` scores every move in a position and `mcts_search` does the same with
+//! Monte Carlo tree search. `cargo build --examples` builds them all.
+
+pub mod archive;
+pub mod cli;
+pub mod consts;
+pub mod export;
 pub mod game_engine;
 pub mod log;
+pub mod profile;
 pub mod user_interface;
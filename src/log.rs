@@ -7,6 +7,7 @@ pub enum LogType {
     MaxMemHit,
     Performance,
     MoveScores,
+    Error,
 }
 
 const TESTING: bool = false;
@@ -17,6 +18,7 @@ const DETAIL: bool = false;
 const MAX_MEM_HIT: bool = true;
 const PERFORMANCE: bool = false;
 const MOVE_SCORES: bool = true;
+const ERROR: bool = true;
 
 pub fn log_message(log_type: LogType, msg: String) {
     let should_print = match log_type {
@@ -26,6 +28,7 @@ pub fn log_message(log_type: LogType, msg: String) {
         LogType::MaxMemHit => MAX_MEM_HIT,
         LogType::Performance => PERFORMANCE,
         LogType::MoveScores => MOVE_SCORES,
+        LogType::Error => ERROR,
     };
 
     if should_print && !TESTING {
@@ -1,69 +1,1909 @@
+#[cfg(feature = "gui")]
 use std::{
     collections::HashMap,
-    sync::mpsc::{channel, Receiver, Sender},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use egui::{Id, Pos2};
+#[cfg(feature = "gui")]
+use egui::{
+    Align2, CollapsingHeader, Color32, Id, Key, Pos2, RichText, Slider, TextEdit, Vec2, Visuals,
+};
 
+use rusty_connect_four::cli;
+#[cfg(feature = "gui")]
 use rusty_connect_four::{
+    archive::{read_game_file, write_game_file, Archive, DEFAULT_ARCHIVE_DIRECTORY},
+    export::{save_review, ExportFormat, DEFAULT_EXPORT_DIRECTORY},
+    game_engine::{
+        column_label::ColumnLabel,
+        complexity::Complexity,
+        game_manager::{GameManager, Parity},
+        game_record::{GameRecord, SETTINGS_TAG},
+        power_ups::{anvil_columns, parse_moves, play_moves_with_config, Move},
+        win_probability::win_probability,
+    },
     log::{log_message, LogType},
+    profile::DEFAULT_PROFILE_DIRECTORY,
     user_interface::{
-        board::Board,
-        engine_interface::{async_engine_process, EngineMessage, TreeSize, UIMessage},
-        settings::{Settings, PlayerType},
-        turn_manager::TurnManager,
+        animation_scheduler::{Animation, AnimationScheduler},
+        archive_search::ArchiveSearch,
+        archive_stats::ArchiveStats,
+        attract::{AttractMode, IdleTimer},
+        board::{board_zoom, set_board_zoom, set_piece_colors, Board, PieceState, MAX_ZOOM},
+        bookmarks::Bookmarks,
+        challenge::Challenge,
+        commentary::Commentary,
+        engine_interface::{
+            EngineAlgorithm, EngineBackend, EngineHandle, EngineMessage, GameOver, Hint,
+            PowerUpKind, ScoreConfidence, SearchStats, TreeSize, UIMessage, VariantRules,
+            WATCHDOG_TIMEOUT,
+        },
+        exhibition::Exhibition,
+        frame_budget::{Deferred, FrameBudget},
+        game_server::{ClientMessage, Color, GameServer, ServerEvent, ServerMessage},
+        importer::Importer,
+        locale::Locale,
+        notation_input::{LoadedPosition, NotationInput},
+        physical_board::{position_commands, BoardEvent, PhysicalBoard},
+        puzzle_rush::PuzzleRush,
+        qr_code::render_qr_code,
+        review::Review,
+        rules_reference::RulesReference,
+        screenshot_import::ScreenshotImport,
+        session::SessionLayout,
+        settings::{
+            Difficulty, Matchup, PieceColors, PlayerType, ScoreDisplay, Settings,
+            ThinkingIndicator, DEFAULT_ATTRACT_MINUTES, WIN_LENGTHS,
+        },
+        teacher::{Teacher, TeacherAction},
+        turn_manager::{thinking_column, TurnEvent, TurnManager},
     },
 };
 
+#[cfg(feature = "overlay")]
+use rusty_connect_four::user_interface::overlay::{
+    OverlayServer, OverlayState, DEFAULT_OVERLAY_PORT,
+};
+
+#[cfg(feature = "gui")]
+/// The height of the panel below the board used for entering move strings.
+const NOTATION_PANEL_HEIGHT: f32 = 32.0;
+#[cfg(feature = "gui")]
+/// The width of the panel beside the board showing the commentary feed.
+const COMMENTARY_PANEL_WIDTH: f32 = 240.0;
+
+#[cfg(feature = "gui")]
+/// The window's title, which exhibitions add a summary of the game to.
+const WINDOW_TITLE: &str = "Connect 4 Engine";
+
+#[cfg(feature = "gui")]
+/// How many seconds each engine thinks per move when an exhibition starts.
+const EXHIBITION_DELAY: f32 = 1.0;
+
+#[cfg(feature = "gui")]
+/// The file a game is saved to part way through, to be resumed later.
+const SAVED_GAME_FILE: &str = "saved_game.json";
+
+#[cfg(feature = "gui")]
 /// Stores the current state of the application.
 pub struct App {
     board: Board,
-    sender: Sender<UIMessage>,
-    receiver: Receiver<EngineMessage>,
+    engine: EngineHandle,
     settings: Settings,
     turn_manager: TurnManager,
     tree_size: TreeSize,
+    /// How the engine's search of the current position has gone, for the debug panel.
+    search_stats: SearchStats,
     move_scores: HashMap<u8, isize>,
+    move_confidences: HashMap<u8, ScoreConfidence>,
+    /// The engine's scores for spending the current player's power-up in each column.
+    power_up_scores: HashMap<u8, isize>,
+    notation_input: NotationInput,
+    commentary: Commentary,
+    archive_search: ArchiveSearch,
+    archive_stats: ArchiveStats,
+    bookmarks: Bookmarks,
+    importer: Importer,
+    screenshot_import: ScreenshotImport,
+    rules_reference: RulesReference,
+    /// Whether the settings window is open.
+    settings_open: bool,
+    /// Shown beside the controls, e.g. explaining why the house rules stopped the last move.
+    status_message: Option<String>,
+    /// How the game just finished, shown in the game over dialog until it's closed.
+    game_result: Option<String>,
+    /// The pieces of the connect four that ended the game, if one did.
+    winning_line: Vec<(u8, u8)>,
+    /// The move the engine suggested to the human about to move, shown until they move.
+    hint: Option<Hint>,
+    /// The moves played so far, as a string of columns numbered from 1.
+    moves: String,
+    /// When each move was played, measured from the start of the game.
+    move_times: Vec<Duration>,
+    /// When the game started, or would have if it was resumed from a save.
+    game_started: Instant,
+    /// An engine vs engine game being shown instead of the regular game.
+    exhibition: Option<Exhibition>,
+    /// A game being reviewed instead of the regular game.
+    review: Option<Review>,
+    /// Puzzles being solved against the clock instead of the regular game.
+    puzzle_rush: Option<PuzzleRush>,
+    /// The title the window was last given, so it's only set again when it changes.
+    window_title: String,
+    /// Demo games being played because the app was left alone, shown instead of everything
+    /// else until there's any input.
+    attract: Option<AttractMode>,
+    idle_timer: IdleTimer,
+    teacher: Teacher,
+    /// A challenge link being pasted in, before it's accepted.
+    challenge_input: String,
+    /// Whether the current position's challenge link is shown as a QR code.
+    show_challenge_qr_code: bool,
+    /// The theme the user chose, or None if they've stuck with the default.
+    dark_mode: Option<bool>,
+    /// The piece colors being picked, which only replace the settings' colors once the two
+    /// can be told apart.
+    piece_color_choice: PieceColors,
+    /// Why the picked piece colors haven't been applied.
+    piece_color_warning: Option<String>,
+    frame_budget: FrameBudget,
+    animation_scheduler: AnimationScheduler,
+    /// Each player's threats, how many are playable, and how many are on rows of the parity
+    /// that suits them, for the moves played.
+    threat_counts: Deferred<String, [(usize, usize, usize); 2]>,
+    /// How sharp the position is, for the moves played and their move scores.
+    complexity: Deferred<(String, HashMap<u8, isize>), Complexity>,
+    /// Whether the app is locked down for a public machine, with no settings, analysis, or
+    /// way to quit.
+    kiosk: bool,
+    /// The LED board the game is mirrored onto, whose buttons play moves like clicks do.
+    physical_board: Option<PhysicalBoard>,
+    /// The game server online games are found on and played over, if connected to one.
+    game_server: Option<GameServer>,
+    /// The id of the game being played on the game server, if there is one.
+    online_game: Option<String>,
+    /// The seeks others have open on the game server, as their id and who's seeking.
+    open_seeks: Vec<(String, String)>,
+    /// A move from the game server that arrived before it could be played, e.g. while the
+    /// human's piece was still falling.
+    pending_remote_move: Option<usize>,
+    /// The server giving streaming overlays the state of the game, if it could be started.
+    #[cfg(feature = "overlay")]
+    overlay: Option<OverlayServer>,
 }
 
+#[cfg(feature = "gui")]
 impl App {
-    /// Sets the initial state of the application.
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        // Setting up the engine interface in another thread
-        let (my_sender, engine_receiver) = channel();
-        let (engine_sender, my_receiver) = channel();
-
-        let ctx_clone = cc.egui_ctx.clone();
+    /// Sets the initial state of the application, starting with the challenge if given one.
+    ///
+    /// The layout the app was last left in is restored. In kiosk mode anything that would
+    /// show the engine's analysis is turned off, whatever the layout. If given the serial
+    /// port of a physical board, the game is mirrored onto it, and if given the URL of a
+    /// game server, games can be found on it.
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        challenge: Option<Challenge>,
+        kiosk: bool,
+        physical_board: Option<PathBuf>,
+        game_server: Option<String>,
+    ) -> Self {
+        let layout = SessionLayout::load(cc.storage);
+        let mut settings = Settings::new();
+        layout.apply_to(&mut settings);
+        if kiosk {
+            settings.show_hints = false;
+            settings.show_evaluation_bar = false;
+            settings.show_threats = false;
+            settings.show_sharpness = false;
+            settings.teacher_mode = false;
+        }
+        if let Some(dark_mode) = layout.dark_mode {
+            cc.egui_ctx.set_visuals(if dark_mode {
+                Visuals::dark()
+            } else {
+                Visuals::light()
+            });
+        }
+        set_piece_colors(&cc.egui_ctx, settings.piece_colors);
+        let piece_color_choice = settings.piece_colors;
 
-        std::thread::spawn(move || {
-            async_engine_process(ctx_clone, engine_sender, engine_receiver);
-        });
+        // Setting up the engine interface in another thread or process
+        let ctx = cc.egui_ctx.clone();
+        let engine = EngineHandle::spawn(move || ctx.request_repaint(), settings.engine_backend);
+        engine.send(UIMessage::SetStrength(settings.difficulty.rating()));
 
         // Other set-up
-        let settings = Settings::new();
-        let turn_manager = TurnManager::new(settings.players);
+        let mut turn_manager = TurnManager::new(settings.players);
+        turn_manager.reset_clock(&settings);
         let mut board = Board::new(Id::new("Board"), Pos2 { x: 0.0, y: 0.0 });
-        if settings.players[0] == PlayerType::Computer {
+        if settings.players[0] != PlayerType::Human {
             board.lock();
         }
+        let (physical_board, status_message) = match physical_board {
+            Some(path) => {
+                let ctx = cc.egui_ctx.clone();
+                match PhysicalBoard::connect(&path, move || ctx.request_repaint()) {
+                    Ok(physical_board) => (Some(physical_board), None),
+                    Err(error) => (None, Some(error)),
+                }
+            }
+            None => (None, None),
+        };
+        let (game_server, status_message) = match game_server {
+            Some(url) => {
+                let ctx = cc.egui_ctx.clone();
+                match GameServer::connect(&url, move || ctx.request_repaint()) {
+                    Ok(game_server) => (Some(game_server), status_message),
+                    Err(error) => (None, Some(error)),
+                }
+            }
+            None => (None, status_message),
+        };
 
-        Self {
+        let mut app = Self {
             board,
-            sender: my_sender,
-            receiver: my_receiver,
+            engine,
             settings,
             turn_manager,
             tree_size: Default::default(),
+            search_stats: Default::default(),
             move_scores: HashMap::new(),
+            move_confidences: HashMap::new(),
+            power_up_scores: HashMap::new(),
+            notation_input: NotationInput::new(),
+            commentary: Commentary::new(),
+            archive_search: ArchiveSearch::new(PathBuf::from(DEFAULT_ARCHIVE_DIRECTORY)),
+            archive_stats: ArchiveStats::new(PathBuf::from(DEFAULT_ARCHIVE_DIRECTORY)),
+            bookmarks: Bookmarks::new(PathBuf::from(DEFAULT_PROFILE_DIRECTORY)),
+            importer: Importer::new(PathBuf::from(DEFAULT_ARCHIVE_DIRECTORY)),
+            screenshot_import: ScreenshotImport::new(),
+            rules_reference: RulesReference {
+                open: layout.rules_open,
+            },
+            settings_open: false,
+            status_message,
+            game_result: None,
+            winning_line: Vec::new(),
+            hint: None,
+            moves: String::new(),
+            move_times: Vec::new(),
+            game_started: Instant::now(),
+            exhibition: None,
+            review: None,
+            puzzle_rush: None,
+            window_title: WINDOW_TITLE.to_owned(),
+            attract: None,
+            idle_timer: IdleTimer::new(),
+            teacher: Teacher::new(),
+            challenge_input: String::new(),
+            show_challenge_qr_code: layout.show_challenge_qr_code,
+            dark_mode: layout.dark_mode,
+            piece_color_choice,
+            piece_color_warning: None,
+            frame_budget: FrameBudget::new(),
+            animation_scheduler: AnimationScheduler::new(),
+            threat_counts: Deferred::new(),
+            complexity: Deferred::new(),
+            kiosk,
+            physical_board,
+            game_server,
+            online_game: None,
+            open_seeks: Vec::new(),
+            pending_remote_move: None,
+            #[cfg(feature = "overlay")]
+            overlay: OverlayServer::start(DEFAULT_OVERLAY_PORT)
+                .map_err(|error| log_message(LogType::Error, error))
+                .ok(),
+        };
+
+        app.sync_physical_board();
+        if let Some(challenge) = challenge {
+            app.accept_challenge(challenge);
+        }
+
+        app
+    }
+
+    /// Sets up a challenge's position and settings, with the human playing the side to move.
+    fn accept_challenge(&mut self, challenge: Challenge) {
+        let Some(loaded) = self.notation_input.load(&challenge.moves) else {
+            return;
+        };
+
+        let human = match loaded.current_player {
+            PieceState::PlayerTwo => 1,
+            _ => 0,
+        };
+        self.settings.players = [PlayerType::Computer; 2];
+        self.settings.players[human] = PlayerType::Human;
+        self.settings.house_rules = challenge.house_rules;
+        self.settings.difficulty = challenge.difficulty;
+        self.engine
+            .send(UIMessage::SetStrength(self.settings.difficulty.rating()));
+        self.turn_manager = TurnManager::new(self.settings.players);
+
+        self.load_position(loaded);
+    }
+
+    /// Starts a new practice game against the computer, with hints and threats shown.
+    fn start_practice(&mut self) {
+        self.settings.start_practice();
+        self.turn_manager = TurnManager::new(self.settings.players);
+
+        self.new_game();
+    }
+
+    /// Takes back the human's last move, along with the computer's reply, if the turn
+    /// manager allows it.
+    fn take_back(&mut self) {
+        if self.teacher.is_teaching() {
+            return;
+        }
+        let Some(moves) = self.turn_manager.takeback(&self.moves, &self.settings) else {
+            return;
+        };
+
+        if let Some(loaded) = self.notation_input.load(&moves) {
+            self.load_position(loaded);
+        }
+    }
+
+    /// Finishes the frame, timing it and scheduling the next frame of any animations.
+    fn end_frame(&mut self, ctx: &egui::Context) {
+        self.frame_budget.end_frame(ctx);
+        self.animation_scheduler
+            .end_frame(ctx, self.settings.power_saving);
+    }
+
+    /// Starts the demo if the app has been left alone on an empty board for long enough,
+    /// otherwise makes sure the app wakes up to check again once it could have been.
+    fn check_idle(&mut self, ctx: &egui::Context) {
+        let Some(minutes) = self.settings.attract_minutes else {
+            return;
+        };
+        if !self.moves.is_empty()
+            || self.online_game.is_some()
+            || self.exhibition.is_some()
+            || self.review.is_some()
+            || self.puzzle_rush.is_some()
+        {
+            return;
+        }
+
+        let delay = Duration::from_secs_f32(minutes * 60.0);
+        let idle_for = self.idle_timer.idle_for();
+        if idle_for >= delay {
+            self.attract = Some(AttractMode::new(ctx));
+        } else {
+            ctx.request_repaint_after(delay - idle_for);
+        }
+    }
+
+    /// Renders a color picker for each player's pieces. Colors are applied to every board as
+    /// they're picked, unless they're too similar to tell apart.
+    fn render_piece_colors(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.label("Pieces:");
+        let mut changed = ui
+            .color_edit_button_srgb(&mut self.piece_color_choice.player_one)
+            .on_hover_text("Player one's pieces")
+            .changed();
+        changed |= ui
+            .color_edit_button_srgb(&mut self.piece_color_choice.player_two)
+            .on_hover_text("Player two's pieces")
+            .changed();
+        if ui
+            .small_button("Swap")
+            .on_hover_text("Swap the players' colors")
+            .clicked()
+        {
+            self.piece_color_choice = self.piece_color_choice.swapped();
+            changed = true;
+        }
+        if ui
+            .small_button("Reset")
+            .on_hover_text("Go back to red and blue")
+            .clicked()
+        {
+            self.piece_color_choice = PieceColors::default();
+            changed = true;
+        }
+
+        if changed {
+            match self.piece_color_choice.validate() {
+                Ok(()) => {
+                    self.settings.piece_colors = self.piece_color_choice;
+                    set_piece_colors(ctx, self.piece_color_choice);
+                    self.piece_color_warning = None;
+                }
+                Err(error) => self.piece_color_warning = Some(error),
+            }
+        }
+
+        if let Some(warning) = &self.piece_color_warning {
+            ui.label(RichText::new(warning).color(Color32::RED));
+        }
+    }
+
+    /// Renders the window for setting up a match: who plays each side, how the computer
+    /// plays, and how the game looks.
+    fn render_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.settings_open;
+        let previous_players = self.settings.players;
+
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.heading("Players");
+                // Practice games are always a human against the computer
+                // Online games are set up by the server
+                let fixed = self.settings.practice || self.online_game.is_some();
+                ui.add_enabled_ui(!fixed, |ui| {
+                    match Matchup::of(self.settings.players) {
+                        Some(mut matchup) => {
+                            egui::ComboBox::from_label("Matchup")
+                                .selected_text(matchup.name())
+                                .show_ui(ui, |ui| {
+                                    for option in Matchup::ALL {
+                                        ui.selectable_value(&mut matchup, option, option.name());
+                                    }
+                                });
+                            self.settings.players = matchup.players();
+                        }
+                        None => {
+                            ui.label("Playing someone on the game server");
+                        }
+                    }
+
+                    if ui
+                        .button("Swap who goes first")
+                        .on_hover_text("Swap the players, so the other one plays red")
+                        .clicked()
+                    {
+                        self.settings.players.swap(0, 1);
+                    }
+                });
+
+                if self.game_server.is_some() {
+                    ui.separator();
+                    ui.heading("Online");
+                    self.render_online(ui);
+                }
+
+                ui.separator();
+                ui.heading("Computer");
+                let previous_difficulty = self.settings.difficulty;
+                egui::ComboBox::from_label("Difficulty")
+                    .selected_text(format!("{:?}", self.settings.difficulty))
+                    .show_ui(ui, |ui| {
+                        for difficulty in Difficulty::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.difficulty,
+                                difficulty,
+                                format!("{:?}", difficulty),
+                            );
+                        }
+                    });
+                if self.settings.difficulty != previous_difficulty {
+                    self.engine
+                        .send(UIMessage::SetStrength(self.settings.difficulty.rating()));
+                }
+                ui.add(
+                    Slider::new(&mut self.settings.delay, 0.0..=10.0)
+                        .text("Delay")
+                        .suffix("s"),
+                )
+                .on_hover_text("How long the computer thinks before each move");
+
+                ui.separator();
+                ui.heading("Colors");
+                let mut dark_mode = ctx.style().visuals.dark_mode;
+                if ui.checkbox(&mut dark_mode, "Dark mode").changed() {
+                    ctx.set_visuals(if dark_mode {
+                        Visuals::dark()
+                    } else {
+                        Visuals::light()
+                    });
+                    self.dark_mode = Some(dark_mode);
+                }
+                ui.horizontal(|ui| self.render_piece_colors(ui, ctx));
+            });
+        self.settings_open = open;
+
+        if self.settings.players != previous_players
+            && self
+                .turn_manager
+                .players_changed(&mut self.board, &self.settings)
+        {
+            // Pondering searches beneath the human's expected reply, which no longer applies
+            if self.turn_manager.awaits_human_move() {
+                self.ponder();
+            } else if self.settings.pondering {
+                self.engine.send(UIMessage::StopPondering);
+            }
+        }
+    }
+
+    /// Renders the link sharing the current position as a challenge, and a field for
+    /// accepting someone else's.
+    fn render_challenge(&mut self, ui: &mut egui::Ui) {
+        CollapsingHeader::new("Challenge").show(ui, |ui| {
+            let link = Challenge {
+                moves: self.moves.clone(),
+                house_rules: self.settings.house_rules,
+                difficulty: self.settings.difficulty,
+            }
+            .to_link();
+
+            ui.horizontal(|ui| {
+                if ui.button("Copy challenge link").clicked() {
+                    ui.output_mut(|output| output.copied_text = link.clone());
+                }
+                ui.checkbox(&mut self.show_challenge_qr_code, "QR code");
+            });
+            if self.show_challenge_qr_code {
+                render_qr_code(ui, &link, COMMENTARY_PANEL_WIDTH - 40.0);
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut self.challenge_input)
+                        .hint_text("Paste a challenge link")
+                        .desired_width(140.0),
+                );
+                if ui.button("Accept").clicked() {
+                    match Challenge::parse(&self.challenge_input) {
+                        Ok(challenge) => {
+                            self.challenge_input.clear();
+                            self.accept_challenge(challenge);
+                        }
+                        Err(error) => self.status_message = Some(error),
+                    }
+                }
+            });
+        });
+    }
+
+    /// Renders how the engine's search of the current position is going, for debugging and
+    /// tuning the engine.
+    fn render_search_stats(&self, ui: &mut egui::Ui) {
+        CollapsingHeader::new("Search statistics").show(ui, |ui| {
+            let stats = &self.search_stats;
+            let locale = &self.settings.locale;
+
+            ui.label(format!(
+                "{} positions per second",
+                locale.format_integer(stats.nodes_per_second() as usize)
+            ));
+            ui.label(format!(
+                "Effective branching factor: {}",
+                locale.format_decimal(stats.branching_factor(), 2)
+            ))
+            .on_hover_text("How many new positions each expanded position added");
+            ui.label(format!(
+                "Transposition hits: {}",
+                locale.format_percent(stats.hit_rate())
+            ));
+            ui.label(format!(
+                "Positions waiting to be expanded: {}",
+                locale.format_integer(stats.frontier)
+            ));
+            ui.label(format!(
+                "Generating: {}, evaluating: {}",
+                locale.format_duration(stats.generation_time, 2),
+                locale.format_duration(stats.evaluation_time, 2)
+            ));
+        });
+    }
+
+    /// Replaces the current game with a position set up from a move string.
+    fn load_position(&mut self, loaded: LoadedPosition) {
+        self.board
+            .set_position(loaded.position, loaded.current_player);
+        self.move_scores.clear();
+        self.move_confidences.clear();
+        self.power_up_scores.clear();
+        self.commentary.clear();
+        self.status_message = None;
+        self.game_result = None;
+        self.winning_line.clear();
+        self.hint = None;
+
+        // Only the moves the new position shares with the old one keep their times
+        let moves: String = loaded.moves.split_whitespace().collect();
+        let shared = self
+            .moves
+            .chars()
+            .zip(moves.chars())
+            .take_while(|(old, new)| old == new)
+            .count();
+        self.move_times.truncate(shared);
+        if moves.is_empty() {
+            self.game_started = Instant::now();
+        }
+        self.moves = moves;
+        self.turn_manager.reset_clock(&self.settings);
+        self.turn_manager.position_loaded(
+            loaded.current_player,
+            self.settings.variant.result(loaded.game_state),
+            &mut self.board,
+            &self.settings,
+        );
+
+        self.engine.send(if self.moves.is_empty() {
+            UIMessage::ResetGame
+        } else {
+            UIMessage::LoadPosition(loaded.moves)
+        });
+        self.sync_physical_board();
+    }
+
+    /// Makes the physical board, if there is one, show the moves played.
+    fn sync_physical_board(&self) {
+        if let (Some(physical_board), Ok(commands)) =
+            (&self.physical_board, position_commands(&self.moves))
+        {
+            physical_board.send(commands);
+        }
+    }
+
+    /// Lights up the last move played on the physical board, if there is one, along with
+    /// any connect four it made.
+    fn mirror_last_move(&self) {
+        if let (Some(physical_board), Ok(commands)) =
+            (&self.physical_board, position_commands(&self.moves))
+        {
+            // The board already shows every move but the last, after clearing it
+            physical_board.send(commands.into_iter().skip(self.moves.len()));
+        }
+    }
+
+    /// Has the human play the column, as long as it's their turn and the column isn't full.
+    fn play_column(&mut self, column: usize, ctx: &egui::Context) {
+        if !self.board.accepts_drop(column) || self.teacher.is_teaching() {
+            return;
+        }
+        if let Err(message) = self
+            .turn_manager
+            .try_human_move(self.creates_threat(column), &self.settings)
+        {
+            self.status_message = Some(message);
+            return;
+        }
+        if let Err(error) = self
+            .board
+            .drop_piece(ctx, column, self.turn_manager.current_player)
+        {
+            self.status_message = Some(error);
+            return;
+        }
+        self.status_message = None;
+        self.board.lock();
+
+        if self.settings.teacher_mode {
+            self.teacher
+                .move_played(&self.moves, column, &self.move_scores);
+        }
+        self.engine.send(UIMessage::MakeMove(column));
+        self.send_to_online_game(|game| ClientMessage::Move { game, column });
+    }
+
+    /// Gives the streaming overlay, if it's being served, the state of the game.
+    #[cfg(feature = "overlay")]
+    fn publish_overlay(&self) {
+        let Some(overlay) = &self.overlay else {
+            return;
+        };
+
+        let players = self.settings.players.map(|player| format!("{:?}", player));
+        match OverlayState::new(
+            &self.moves,
+            &self.move_scores,
+            players,
+            self.turn_manager.time_remaining(&self.settings),
+        ) {
+            Ok(state) => overlay.publish(&state),
+            Err(error) => log_message(LogType::Error, error),
+        }
+    }
+
+    /// Shows each player's chance of winning, by the engine's scores, in a bar beside the
+    /// board and above each column.
+    fn render_evaluation(&self, ui: &egui::Ui) {
+        let Some(&best_score) = self.move_scores.values().max() else {
+            return;
+        };
+
+        let chances: Vec<(usize, f32)> = self
+            .move_scores
+            .iter()
+            .map(|(column, score)| (*column as usize, win_probability(*score) as f32))
+            .collect();
+        self.board.render_win_chances(ui, &chances);
+
+        // The scores are for the player about to move
+        let chance = win_probability(best_score) as f32;
+        let red_chance = match self.turn_manager.current_player {
+            PieceState::PlayerTwo => 1.0 - chance,
+            _ => chance,
+        };
+        self.board.render_evaluation_bar(ui, red_chance);
+    }
+
+    /// Handles what's happened on the physical board, playing pressed columns just like
+    /// clicked ones.
+    fn process_physical_board(&mut self, ctx: &egui::Context) {
+        while let Some(event) = self
+            .physical_board
+            .as_ref()
+            .and_then(PhysicalBoard::try_recv)
+        {
+            match event {
+                BoardEvent::ColumnPressed(column) => self.play_column(column, ctx),
+                BoardEvent::NewGamePressed => self.new_game(),
+                BoardEvent::Disconnected(error) => {
+                    self.status_message = Some(format!("Lost the physical board - {}", error));
+                    self.physical_board = None;
+                }
+            }
+        }
+    }
+
+    /// Renders a button for seeking a game on the game server, and the seeks others have
+    /// open there, any of which can be accepted.
+    fn render_online(&mut self, ui: &mut egui::Ui) {
+        let Some(game_server) = &self.game_server else {
+            return;
+        };
+
+        ui.add_enabled_ui(self.online_game.is_none(), |ui| {
+            if ui
+                .button("Seek a game")
+                .on_hover_text("Play whoever else the server finds looking for a game")
+                .clicked()
+            {
+                game_server.send(&ClientMessage::Seek);
+                self.status_message = Some("Looking for a game...".to_owned());
+            }
+
+            let mut accepted = None;
+            for (seek, player) in &self.open_seeks {
+                ui.horizontal(|ui| {
+                    ui.label(player);
+                    if ui.button("Accept").clicked() {
+                        accepted = Some(seek.clone());
+                    }
+                });
+            }
+            if let Some(seek) = accepted {
+                game_server.send(&ClientMessage::Accept { seek });
+            }
+        });
+    }
+
+    /// Sends a message about the game being played on the game server, if there is one,
+    /// made from the game's id.
+    fn send_to_online_game(&self, message: impl FnOnce(String) -> ClientMessage) {
+        if let (Some(game_server), Some(game)) = (&self.game_server, &self.online_game) {
+            game_server.send(&message(game.clone()));
+        }
+    }
+
+    /// Handles what the game server has sent, starting the games it sets up and playing
+    /// the opponent's moves.
+    fn process_game_server(&mut self, ctx: &egui::Context) {
+        while let Some(event) = self.game_server.as_ref().and_then(GameServer::try_recv) {
+            match event {
+                ServerEvent::Message(ServerMessage::Seek { seek, player }) => {
+                    self.open_seeks.push((seek, player))
+                }
+                ServerEvent::Message(ServerMessage::Start { game, color }) => {
+                    self.start_online_game(game, color)
+                }
+                ServerEvent::Message(ServerMessage::Move { game, column })
+                    if self.online_game.as_ref() == Some(&game) =>
+                {
+                    if self.pending_remote_move.replace(column).is_some() {
+                        log_message(
+                            LogType::Error,
+                            "The server sent a move before the last one was played".to_owned(),
+                        );
+                    }
+                }
+                ServerEvent::Message(ServerMessage::Result { game, result })
+                    if self.online_game.as_ref() == Some(&game) =>
+                {
+                    self.end_online_game();
+                    self.status_message = Some(format!("The server ended the game - {}", result));
+                }
+                ServerEvent::Message(ServerMessage::Error { message }) => {
+                    self.status_message = Some(format!("The game server says - {}", message))
+                }
+                // Moves and results of games that aren't being played here
+                ServerEvent::Message(_) => (),
+                ServerEvent::Disconnected(error) => {
+                    self.end_online_game();
+                    self.game_server = None;
+                    self.open_seeks.clear();
+                    self.status_message = Some(format!("Lost the game server - {}", error));
+                }
+            }
+        }
+
+        // The opponent's move waits for the engine to take the human's, and for its piece
+        //  to land
+        if !self.turn_manager.awaits_remote_move() || self.board.is_piece_falling() {
+            return;
+        }
+        let Some(column) = self.pending_remote_move.take() else {
+            return;
+        };
+        if let Err(error) = self
+            .board
+            .drop_piece(ctx, column, self.turn_manager.current_player)
+        {
+            self.status_message = Some(format!("The server's move couldn't be played - {}", error));
+            return;
+        }
+        self.turn_manager.remote_move_played();
+        self.engine.send(UIMessage::MakeMove(column));
+    }
+
+    /// Starts a game the game server has set up, with the human playing the color against
+    /// someone on the server.
+    fn start_online_game(&mut self, game: String, color: Color) {
+        self.settings.practice = false;
+        self.settings.players = [PlayerType::Remote; 2];
+        self.settings.players[color.index()] = PlayerType::Human;
+        self.turn_manager = TurnManager::new(self.settings.players);
+        self.online_game = Some(game);
+        self.open_seeks.clear();
+        self.pending_remote_move = None;
+
+        self.new_game();
+    }
+
+    /// Stops playing on the game server, with the computer taking over from the opponent
+    /// if the game isn't over.
+    fn end_online_game(&mut self) {
+        if self.online_game.take().is_none() {
+            return;
+        }
+        self.pending_remote_move = None;
+        for player in &mut self.settings.players {
+            if *player == PlayerType::Remote {
+                *player = PlayerType::Computer;
+            }
+        }
+        self.turn_manager
+            .players_changed(&mut self.board, &self.settings);
+    }
+
+    /// Replaces the engine with a new one using the current backend, and catches it up on
+    /// the game so far.
+    ///
+    /// A move the old engine never acknowledged is taken back, since it isn't in the moves.
+    fn restart_engine(&mut self, ctx: &egui::Context) {
+        let engine_ctx = ctx.clone();
+        self.engine = EngineHandle::spawn(
+            move || engine_ctx.request_repaint(),
+            self.settings.engine_backend,
+        );
+        self.engine
+            .send(UIMessage::SetPowerSaving(self.settings.power_saving));
+        self.engine
+            .send(UIMessage::SetAlgorithm(self.settings.engine_algorithm));
+        self.engine
+            .send(UIMessage::SetVariant(self.settings.variant));
+        self.engine
+            .send(UIMessage::SetWinLength(self.settings.number_to_win));
+        self.engine
+            .send(UIMessage::SetPowerUps(self.settings.enabled_power_up()));
+        self.engine
+            .send(UIMessage::SetStrength(self.settings.difficulty.rating()));
+        self.resync_position();
+
+        // A lesson can't go on without the engine's line, which the old engine took with it
+        self.teacher = Teacher::new();
+    }
+
+    /// Puts the board back to the moves the engine has acknowledged and shows what went
+    /// wrong, after the board and the engine have fallen out of step.
+    fn recover(&mut self, error: String) {
+        log_message(LogType::Error, error.clone());
+        self.resync_position();
+        self.status_message = Some(error);
+    }
+
+    /// Sets the board and the engine up with the moves played so far, and starts the turn of
+    /// whoever is to move.
+    fn resync_position(&mut self) {
+        let state = GameManager::from_moves_with_config(
+            &self.moves,
+            self.settings.variant,
+            self.settings.board_config(),
+        )
+        .expect("The moves played should be legal")
+        .state(self.turn_manager.clock_times());
+        let current_player = if state.turn {
+            PieceState::PlayerTwo
+        } else {
+            PieceState::PlayerOne
+        };
+
+        self.board.set_position(&state.rows, current_player);
+        self.move_scores.clear();
+        self.move_confidences.clear();
+        self.power_up_scores.clear();
+        self.turn_manager.position_loaded(
+            current_player,
+            state.result,
+            &mut self.board,
+            &self.settings,
+        );
+
+        self.engine
+            .send(UIMessage::LoadPosition(self.moves.clone()));
+        self.ponder();
+    }
+
+    /// Has the engine search beneath the reply it expects while a human thinks, if pondering
+    /// is on.
+    fn ponder(&self) {
+        if self.settings.pondering && self.turn_manager.awaits_human_move() {
+            self.engine.send(UIMessage::Ponder);
+        }
+    }
+
+    /// Shows which players still have their power-up, and lets a human about to move spend
+    /// theirs clearing a column or dropping their anvil.
+    fn render_power_ups(&mut self, ui: &mut egui::Ui) {
+        let Ok((board, turn, power_ups)) =
+            play_moves_with_config(&self.moves, self.settings.board_config())
+        else {
+            return;
+        };
+
+        for (name, player) in [("Red", false), ("Blue", true)] {
+            ui.label(if power_ups.is_available(player) {
+                format!("{}'s power-up is ready", name)
+            } else {
+                format!("{} has spent their power-up", name)
+            });
+        }
+
+        if !power_ups.is_available(turn)
+            || !self.turn_manager.awaits_human_move()
+            || self.board.is_piece_falling()
+        {
+            return;
+        }
+        let (prompt, power_up, description): (_, fn(u8) -> Move, _) = match power_ups.kind() {
+            PowerUpKind::Clear => (
+                "Clear a column:",
+                Move::Clear,
+                "Spend your power-up clearing every piece out of column",
+            ),
+            PowerUpKind::Anvil => (
+                "Drop your anvil:",
+                Move::Anvil,
+                "Drop your anvil, crushing every piece beneath it, in column",
+            ),
+        };
+        ui.label(prompt);
+        for column in 0..board.width() {
+            let label = ColumnLabel::new(column);
+            if ui
+                .add_enabled(
+                    board.get_height(column) > 0,
+                    egui::Button::new(label.to_string()),
+                )
+                .on_hover_text(format!("{} {}", description, label))
+                .clicked()
+            {
+                self.spend_power_up(power_up(column));
+            }
+        }
+    }
+
+    /// Spends the current player's power-up, by loading the position with the move that
+    /// spends it played.
+    fn spend_power_up(&mut self, power_up: Move) {
+        let moves = format!("{}{}", self.moves, power_up);
+        if let Some(loaded) = self.notation_input.load(&moves) {
+            self.load_position(loaded);
+        }
+    }
+
+    /// Shows how many spaces would complete a connect four for each player, how many of
+    /// those could be played right now, and how many are on the rows that suit the player.
+    fn render_threat_counter(&mut self, ui: &mut egui::Ui) {
+        let (moves, config) = (&self.moves, self.settings.board_config());
+        let Some(counts) = self
+            .threat_counts
+            .get(moves.clone(), &mut self.frame_budget, || {
+                let manager =
+                    GameManager::from_moves_with_config(moves, VariantRules::Standard, config)
+                        .expect("The moves played should be legal");
+
+                [false, true].map(|player| {
+                    let threats = manager.get_threats(player);
+                    let playable = threats.iter().filter(|threat| threat.playable).count();
+                    let suited = threats
+                        .iter()
+                        .filter(|threat| threat.parity().favours() == player)
+                        .count();
+
+                    (threats.len(), playable, suited)
+                })
+            })
+        else {
+            return;
+        };
+
+        // Red fills the odd rows once the board fills up, and Blue the even rows
+        for ((name, parity), (threats, playable, suited)) in
+            [("Red", Parity::Odd), ("Blue", Parity::Even)]
+                .into_iter()
+                .zip(counts)
+        {
+            ui.label(format!(
+                "{} threats: {} ({} playable, {} {})",
+                name,
+                threats,
+                playable,
+                suited,
+                parity.name()
+            ))
+            .on_hover_text(format!(
+                "{}'s threats on {} rows, counting up from the bottom, tend to win late in \
+                 the game",
+                name,
+                parity.name()
+            ));
+        }
+    }
+
+    /// Shows that the computer is thinking, with a dot for each third of its thinking time
+    /// used up, along with how far it's searched so far.
+    fn render_thinking_status(&self, ui: &mut egui::Ui) {
+        let Some(progress) = self.turn_manager.thinking_progress(&self.settings) else {
+            return;
+        };
+        let dots = (progress * 3.0).ceil().max(1.0) as usize;
+
+        ui.label(format!(
+            "Computer is thinking{:<3} depth {}, {} positions",
+            ".".repeat(dots),
+            self.tree_size.depth,
+            self.settings.locale.format_integer(self.tree_size.size),
+        ));
+    }
+
+    /// Shows how sharp the current position is as up to three flames, with the details of
+    /// the rating when hovered.
+    fn render_sharpness(&mut self, ui: &mut egui::Ui) {
+        let (moves, move_scores) = (&self.moves, &self.move_scores);
+        let config = self.settings.board_config();
+        let Some(complexity) = self.complexity.get(
+            (moves.clone(), move_scores.clone()),
+            &mut self.frame_budget,
+            || {
+                GameManager::from_moves_with_config(moves, VariantRules::Standard, config)
+                    .expect("The moves played should be legal")
+                    .get_complexity(move_scores)
+            },
+        ) else {
+            return;
+        };
+
+        let flames = match complexity.flames() {
+            0 => "Calm".to_owned(),
+            flames => "🔥".repeat(flames),
+        };
+        let locale = self.settings.locale;
+        ui.label(flames).on_hover_text(format!(
+            "Sharpness {}: {} reasonable moves with scores spread by {}, {} forcing moves",
+            locale.format_decimal(complexity.sharpness as f64, 2),
+            complexity.reasonable_moves,
+            locale.format_decimal(complexity.spread as f64, 0),
+            complexity.forcing_moves
+        ));
+    }
+
+    /// Returns whether the current player dropping a piece in the column would let them win
+    /// on their next turn. Only checked when the house rules require threats to be announced.
+    fn creates_threat(&self, column: usize) -> bool {
+        if !self.settings.house_rules.announce_threats {
+            return false;
+        }
+
+        let player = matches!(self.turn_manager.current_player, PieceState::PlayerTwo);
+        GameManager::from_moves_with_config(
+            &format!("{}{}", self.moves, ColumnLabel::new(column)),
+            VariantRules::Standard,
+            self.settings.board_config(),
+        )
+        .map(|manager| !manager.get_winning_columns(player).is_empty())
+        .unwrap_or(false)
+    }
+
+    /// Saves the finished game, and shows how it finished along with the engine's winning
+    /// line, the connect four that ended it if one did.
+    fn finish_game(&mut self, result: &str, winning_line: Vec<(u8, u8)>) {
+        self.save_game(result);
+        self.send_to_online_game(|game| ClientMessage::Result {
+            game,
+            result: result.to_owned(),
+        });
+        self.end_online_game();
+
+        self.game_result = Some(result.to_owned());
+        self.winning_line = winning_line;
+    }
+
+    /// Starts a new game with the same settings.
+    fn new_game(&mut self) {
+        if let Some(loaded) = self.notation_input.load("") {
+            self.load_position(loaded);
+        }
+    }
+
+    /// Starts a new game with the same settings, with the players swapping sides.
+    fn rematch(&mut self) {
+        self.settings.players.swap(0, 1);
+        self.turn_manager = TurnManager::new(self.settings.players);
+        self.new_game();
+    }
+
+    /// Renders the dialog shown once the game is over, offering a rematch or a new game.
+    fn render_game_over(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.game_result.clone() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("Game over")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.heading(format!("{}!", result));
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Rematch")
+                        .on_hover_text("Play again with the players swapping sides")
+                        .clicked()
+                    {
+                        action = Some(true);
+                    }
+                    if ui.button("New game").clicked() {
+                        action = Some(false);
+                    }
+                });
+            });
+
+        match action {
+            Some(true) => self.rematch(),
+            Some(false) => self.new_game(),
+            None if !open => self.game_result = None,
+            None => (),
         }
     }
+
+    /// Saves the finished game to the archive, along with its commentary and the house rules
+    /// it was played under.
+    fn save_game(&self, result: &str) {
+        let mut record = match self.record_game() {
+            Ok(record) => record,
+            Err(error) => {
+                log_message(LogType::Error, format!("Couldn't record game - {}", error));
+                return;
+            }
+        };
+        record.set_tag("Result", result);
+
+        if let Err(error) = Archive::save(&PathBuf::from(DEFAULT_ARCHIVE_DIRECTORY), &record) {
+            log_message(LogType::Error, format!("Couldn't save game - {}", error));
+        }
+    }
+
+    /// Saves the game so far, so that it can be resumed later.
+    fn save_for_later(&self) -> Result<(), String> {
+        write_game_file(Path::new(SAVED_GAME_FILE), &self.record_game()?)
+    }
+
+    /// Replaces the current game with the one saved for later, along with the settings and
+    /// house rules it was played with.
+    fn resume_saved_game(&mut self, ctx: &egui::Context) -> Result<(), String> {
+        let record = read_game_file(Path::new(SAVED_GAME_FILE))?;
+        let mut settings = self.settings.clone();
+        if let Some(value) = record.get_tag(SETTINGS_TAG) {
+            settings.apply_tag_value(value)?;
+        }
+        settings.house_rules = record.house_rules()?;
+        // Making sure the game can be played on before anything is replaced
+        GameManager::from_moves_with_config(
+            &record.moves_string(),
+            settings.variant,
+            settings.board_config(),
+        )?;
+
+        self.settings = settings;
+        self.turn_manager = TurnManager::new(self.settings.players);
+        // Clocks aren't saved, so a resumed game's clocks start over
+        self.turn_manager.reset_clock(&self.settings);
+        self.commentary.clear();
+        self.status_message = None;
+        self.moves = record.moves_string();
+        self.move_times = record
+            .moves
+            .iter()
+            .map(|recorded| recorded.time.unwrap_or_default())
+            .collect();
+        let elapsed = self.move_times.last().copied().unwrap_or_default();
+        self.game_started = Instant::now()
+            .checked_sub(elapsed)
+            .unwrap_or_else(Instant::now);
+
+        self.restart_engine(ctx);
+        self.sync_physical_board();
+
+        Ok(())
+    }
+
+    /// Records the game so far, along with its commentary, move times and the house rules
+    /// it's being played with.
+    fn record_game(&self) -> Result<GameRecord, String> {
+        let mut record = GameRecord::from_moves(&self.moves)?;
+
+        record.set_tag("Red", &format!("{:?}", self.settings.players[0]));
+        record.set_tag("Blue", &format!("{:?}", self.settings.players[1]));
+        record.set_house_rules(&self.settings.house_rules);
+        record.stamp(self.turn_manager.seed(), &self.settings.to_tag_value());
+
+        for (index, recorded) in record.moves.iter_mut().enumerate() {
+            recorded.comment = self.commentary.comment_for(index + 1);
+            recorded.time = self.move_times.get(index).copied();
+        }
+
+        Ok(record)
+    }
 }
 
+#[cfg(feature = "gui")]
 impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    /// Saves the layout the app is in, so it reopens the same way.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // A kiosk's forced settings shouldn't carry over to the next regular session
+        if self.kiosk {
+            return;
+        }
+
+        SessionLayout {
+            rules_open: self.rules_reference.open,
+            show_challenge_qr_code: self.show_challenge_qr_code,
+            dark_mode: self.dark_mode,
+            show_hints: self.settings.show_hints,
+            show_evaluation_bar: self.settings.show_evaluation_bar,
+            show_threats: self.settings.show_threats,
+            show_sharpness: self.settings.show_sharpness,
+            score_display: self.settings.score_display,
+            thinking_indicator: self.settings.thinking_indicator,
+            preview_computer_move: self.settings.preview_computer_move,
+            piece_colors: self.settings.piece_colors,
+            attract_minutes: self.settings.attract_minutes,
+            locale: self.settings.locale,
+        }
+        .save(storage);
+    }
+
+    /// Keeps a kiosk's window from being closed, e.g. with Alt+F4.
+    fn on_close_event(&mut self) -> bool {
+        !self.kiosk
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.frame_budget.start_frame();
+
+        let had_input = self.idle_timer.update(ctx);
+        if let Some(attract) = &mut self.attract {
+            if had_input {
+                // Dropping the demo hangs up on its engines, shutting them down. The input
+                //  that woke the app up shouldn't also play a move, so the game is only
+                //  shown from the next frame
+                self.attract = None;
+                ctx.request_repaint();
+            } else {
+                attract.update(ctx, &mut self.animation_scheduler);
+            }
+
+            self.end_frame(ctx);
+            return;
+        }
+        self.check_idle(ctx);
+
+        // Exhibitions can be followed from the title bar while the app is minimised
+        let window_title = match &self.exhibition {
+            Some(exhibition) => format!("{} - {}", WINDOW_TITLE, exhibition.title_summary()),
+            None => WINDOW_TITLE.to_owned(),
+        };
+        if window_title != self.window_title {
+            frame.set_window_title(&window_title);
+            self.window_title = window_title;
+        }
+
+        if let Some(exhibition) = &mut self.exhibition {
+            let mut stop_exhibition = false;
+
+            egui::TopBottomPanel::bottom("Exhibition Controls").show(ctx, |ui| {
+                stop_exhibition = ui.button("Back to game").clicked();
+            });
+            egui::SidePanel::right("Exhibition")
+                .exact_width(COMMENTARY_PANEL_WIDTH)
+                .show(ctx, |ui| {
+                    exhibition.render_controls(ui, self.settings.locale);
+
+                    ui.separator();
+
+                    exhibition.render_commentary(ui);
+                });
+            egui::CentralPanel::default().show(ctx, |ui| {
+                exhibition.update(ctx, ui, &mut self.animation_scheduler);
+            });
+
+            if stop_exhibition {
+                // Dropping the exhibition hangs up on its engines, shutting them down
+                self.exhibition = None;
+            }
+            self.end_frame(ctx);
+            return;
+        }
+
+        if let Some(review) = &mut self.review {
+            let mut stop_review = false;
+            let mut reproduction = None;
+
+            egui::TopBottomPanel::bottom("Review Controls").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    stop_review = ui.button("Back to game").clicked();
+
+                    if ui.button("Reproduce this game").clicked() {
+                        match Exhibition::reproduce(ctx, review.record()) {
+                            Ok(exhibition) => reproduction = Some(exhibition),
+                            Err(error) => review
+                                .set_status_message(format!("Couldn't reproduce game - {}", error)),
+                        }
+                    }
+
+                    if ui.button("Save to archive").clicked() {
+                        let directory = PathBuf::from(DEFAULT_ARCHIVE_DIRECTORY);
+                        if let Err(error) = Archive::save(&directory, review.record()) {
+                            log_message(LogType::Error, format!("Couldn't save game - {}", error));
+                        }
+                    }
+
+                    for (label, format) in [
+                        ("Export as HTML", ExportFormat::Html),
+                        ("Export as Markdown", ExportFormat::Markdown),
+                    ] {
+                        if ui.button(label).clicked() {
+                            let directory = PathBuf::from(DEFAULT_EXPORT_DIRECTORY);
+                            let message = match save_review(&directory, review.record(), format) {
+                                Ok(path) => format!("Exported review to {}", path.display()),
+                                Err(error) => format!("Couldn't export review - {}", error),
+                            };
+                            review.set_status_message(message);
+                        }
+                    }
+                });
+            });
+            egui::SidePanel::right("Review")
+                .exact_width(COMMENTARY_PANEL_WIDTH)
+                .show(ctx, |ui| {
+                    review.render_controls(ui);
+                });
+            egui::CentralPanel::default().show(ctx, |ui| {
+                review.update(ctx, ui, self.settings.score_display);
+            });
+
+            if let Some(exhibition) = reproduction {
+                self.exhibition = Some(exhibition);
+                stop_review = true;
+            }
+            if stop_review {
+                // Dropping the review hangs up on its engine, shutting it down
+                self.review = None;
+            }
+            self.end_frame(ctx);
+            return;
+        }
+
+        if let Some(puzzle_rush) = &mut self.puzzle_rush {
+            let mut stop_puzzle_rush = false;
+            let mut play_again = false;
+
+            egui::TopBottomPanel::bottom("Puzzle Rush Controls").show(ctx, |ui| {
+                stop_puzzle_rush = ui.button("Back to game").clicked();
+            });
+            egui::SidePanel::right("Puzzle Rush")
+                .exact_width(COMMENTARY_PANEL_WIDTH)
+                .show(ctx, |ui| {
+                    play_again = puzzle_rush.render_controls(ui);
+                });
+            egui::CentralPanel::default().show(ctx, |ui| {
+                puzzle_rush.update(ctx, ui);
+            });
+
+            if play_again {
+                *puzzle_rush = PuzzleRush::new(ctx, PathBuf::from(DEFAULT_PROFILE_DIRECTORY));
+            }
+            if stop_puzzle_rush {
+                self.puzzle_rush = None;
+            }
+            self.end_frame(ctx);
+            return;
+        }
+
+        // Practice games take back a move with a single key
+        if self.settings.practice
+            && !ctx.wants_keyboard_input()
+            && ctx.input(|input| input.key_pressed(Key::Backspace))
+        {
+            self.take_back();
+        }
+
+        if !self.kiosk {
+            egui::TopBottomPanel::top("Menu").show(ctx, |ui| {
+                egui::menu::bar(ui, |ui| {
+                    if ui.button("Settings").clicked() {
+                        self.settings_open = !self.settings_open;
+                    }
+                });
+            });
+            self.render_settings_window(ctx);
+        }
+
+        egui::TopBottomPanel::bottom("Notation").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if self.kiosk {
+                    if ui.button("New game").clicked() {
+                        self.new_game();
+                    }
+                    return;
+                }
+
+                if ui.button("Watch an exhibition").clicked() {
+                    self.exhibition = Some(Exhibition::new(
+                        ctx,
+                        [Difficulty::Hard, Difficulty::Hard],
+                        EXHIBITION_DELAY,
+                    ));
+                }
+                if ui
+                    .button("Puzzle rush")
+                    .on_hover_text("Solve as many puzzles as you can in three minutes")
+                    .clicked()
+                {
+                    self.puzzle_rush = Some(PuzzleRush::new(
+                        ctx,
+                        PathBuf::from(DEFAULT_PROFILE_DIRECTORY),
+                    ));
+                }
+                if ui.button("Review this game").clicked() {
+                    match self.record_game() {
+                        Ok(record) => self.review = Some(Review::new(ctx, record)),
+                        Err(error) => self.status_message = Some(error),
+                    }
+                }
+                if ui
+                    .button("Save game")
+                    .on_hover_text("Save the game so far, to resume it later")
+                    .clicked()
+                {
+                    self.status_message = Some(match self.save_for_later() {
+                        Ok(()) => format!("Saved the game to {}", SAVED_GAME_FILE),
+                        Err(error) => format!("Couldn't save game - {}", error),
+                    });
+                }
+                if ui.button("Resume saved game").clicked() {
+                    if let Err(error) = self.resume_saved_game(ctx) {
+                        self.status_message = Some(format!("Couldn't resume game - {}", error));
+                    }
+                }
+
+                if ui
+                    .add_enabled(
+                        self.turn_manager.awaits_human_move() && !self.teacher.is_teaching(),
+                        egui::Button::new("Hint"),
+                    )
+                    .on_hover_text("Ask the computer for a move, and why it's good")
+                    .clicked()
+                {
+                    self.engine.send(UIMessage::RequestHint);
+                }
+                if !self.settings.practice {
+                    if ui
+                        .button("Practice")
+                        .on_hover_text(
+                            "Start a game against the computer with hints and threats shown, \
+                             where the computer explains its moves",
+                        )
+                        .clicked()
+                    {
+                        self.start_practice();
+                    }
+                } else {
+                    if ui
+                        .button("Take back")
+                        .on_hover_text("Take back your last move (Backspace)")
+                        .clicked()
+                    {
+                        self.take_back();
+                    }
+                    if ui.button("Stop practicing").clicked() {
+                        self.settings.practice = false;
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if !self.kiosk && ui.button("Rules").clicked() {
+                    self.rules_reference.open = !self.rules_reference.open;
+                }
+                if self.settings.house_rules.announce_threats
+                    && ui.button("Announce threat").clicked()
+                {
+                    self.turn_manager.announce_threat();
+                }
+                let locale = self.settings.locale;
+                if let Some([red, blue]) = self.turn_manager.clock_times() {
+                    // Tenths of a second are shown once a player is short on time
+                    let clock = |time: Duration| {
+                        if time < Duration::from_secs(10) {
+                            locale.format_duration(time, 1)
+                        } else {
+                            locale.format_duration(Duration::from_secs(time.as_secs()), 0)
+                        }
+                    };
+                    ui.label(format!("Red {} · Blue {}", clock(red), clock(blue)));
+                }
+                if let (Some(_), Some(seconds)) = (
+                    self.settings.house_rules.move_time_limit,
+                    self.turn_manager.time_remaining(&self.settings),
+                ) {
+                    ui.label(format!(
+                        "{} left",
+                        locale.format_duration(Duration::from_secs_f32(seconds.ceil()), 0)
+                    ));
+                }
+                if let (true, Some(timing)) = (
+                    self.settings.house_rules.is_timed(),
+                    self.turn_manager.last_computer_move(),
+                ) {
+                    ui.label(format!(
+                        "Computer's last move took {}",
+                        locale.format_duration(timing.thinking, 1)
+                    ))
+                    .on_hover_text(format!(
+                        "Its piece then spent {} animating, which didn't count against its clock",
+                        locale.format_duration(timing.animation, 1)
+                    ));
+                }
+                if self.settings.thinking_indicator == ThinkingIndicator::StatusDots {
+                    self.render_thinking_status(ui);
+                }
+                if self.settings.show_sharpness && !self.move_scores.is_empty() {
+                    self.render_sharpness(ui);
+                }
+                if self.settings.variant != VariantRules::Standard {
+                    ui.label(
+                        RichText::new(format!(
+                            "{}: {}",
+                            self.settings.variant.name(),
+                            self.settings.variant.description().to_lowercase()
+                        ))
+                        .strong(),
+                    );
+                }
+                if let Some(hint) = &self.hint {
+                    ui.label(format!(
+                        "Try column {}, which {}",
+                        ColumnLabel::new(hint.column),
+                        hint.explanation
+                    ));
+                }
+                if let Some(message) = &self.status_message {
+                    ui.label(RichText::new(message).color(Color32::RED));
+                }
+
+                if self.kiosk {
+                    return;
+                }
+                egui::ComboBox::from_label("Thinking indicator")
+                    .selected_text(self.settings.thinking_indicator.name())
+                    .show_ui(ui, |ui| {
+                        for indicator in ThinkingIndicator::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.thinking_indicator,
+                                indicator,
+                                indicator.name(),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label("Number format")
+                    .selected_text(self.settings.locale.name())
+                    .show_ui(ui, |ui| {
+                        for locale in Locale::ALL {
+                            ui.selectable_value(&mut self.settings.locale, locale, locale.name());
+                        }
+                    });
+            });
+
+            if self.settings.show_threats {
+                ui.horizontal(|ui| self.render_threat_counter(ui));
+            }
+            if self.settings.power_ups {
+                ui.horizontal(|ui| self.render_power_ups(ui));
+            }
+            if self.kiosk {
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                // Practice games always show their analysis, and take back mistakes instead
+                //  of teaching them
+                ui.add_enabled_ui(!self.settings.practice, |ui| {
+                    ui.checkbox(&mut self.settings.show_hints, "Show move hints");
+                    ui.checkbox(&mut self.settings.teacher_mode, "Teacher mode");
+                    ui.checkbox(&mut self.settings.show_threats, "Count threats");
+                });
+                ui.checkbox(
+                    &mut self.settings.show_evaluation_bar,
+                    "Show evaluation bar",
+                );
+                ui.checkbox(&mut self.settings.show_sharpness, "Show sharpness");
+                ui.checkbox(
+                    &mut self.settings.preview_computer_move,
+                    "Preview computer's move",
+                )
+                .on_hover_text("Highlight the column the computer is leaning towards as it thinks");
+                let mut show_win_probability =
+                    self.settings.score_display == ScoreDisplay::WinProbability;
+                if ui
+                    .checkbox(&mut show_win_probability, "Show win chances")
+                    .on_hover_text("Show evaluations as the chance of winning instead of scores")
+                    .changed()
+                {
+                    self.settings.score_display = if show_win_probability {
+                        ScoreDisplay::WinProbability
+                    } else {
+                        ScoreDisplay::Score
+                    };
+                }
+
+                let mut attract = self.settings.attract_minutes.is_some();
+                if ui
+                    .checkbox(&mut attract, "Demo when idle")
+                    .on_hover_text(
+                        "Play demo games when the app is left alone before a game starts, e.g. \
+                         on a kiosk",
+                    )
+                    .changed()
+                {
+                    self.settings.attract_minutes = attract.then_some(DEFAULT_ATTRACT_MINUTES);
+                }
+                if let Some(minutes) = &mut self.settings.attract_minutes {
+                    ui.add(Slider::new(minutes, 1.0..=30.0).text("Minutes"));
+                }
+
+                let mut zoom = board_zoom(ctx);
+                if ui
+                    .add(Slider::new(&mut zoom, 1.0..=MAX_ZOOM).text("Zoom"))
+                    .on_hover_text(
+                        "Magnifies the board. + and - zoom too, and scrolling or dragging with \
+                         the middle mouse button looks around the board.",
+                    )
+                    .changed()
+                {
+                    set_board_zoom(ctx, zoom);
+                }
+                if ui
+                    .checkbox(&mut self.settings.power_saving, "Power saving")
+                    .changed()
+                {
+                    self.engine
+                        .send(UIMessage::SetPowerSaving(self.settings.power_saving));
+                }
+                if ui
+                    .checkbox(&mut self.settings.pondering, "Pondering")
+                    .on_hover_text(
+                        "Lets the computer keep thinking on your time, about the move it \
+                         expects you to make.",
+                    )
+                    .changed()
+                {
+                    if self.settings.pondering {
+                        self.ponder();
+                    } else {
+                        self.engine.send(UIMessage::StopPondering);
+                    }
+                }
+
+                let previous_algorithm = self.settings.engine_algorithm;
+                egui::ComboBox::from_label("Engine")
+                    .selected_text(self.settings.engine_algorithm.name())
+                    .show_ui(ui, |ui| {
+                        for algorithm in EngineAlgorithm::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.engine_algorithm,
+                                algorithm,
+                                algorithm.name(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Monte Carlo plays out random games to rate each move, so its \
+                         evaluations are shown as chances of winning",
+                    );
+                if self.settings.engine_algorithm != previous_algorithm {
+                    self.engine
+                        .send(UIMessage::SetAlgorithm(self.settings.engine_algorithm));
+                    // Rollout scores only mean anything as chances of winning
+                    if self.settings.engine_algorithm.uses_rollouts() {
+                        self.settings.score_display = ScoreDisplay::WinProbability;
+                    }
+                }
+
+                let previous_variant = self.settings.variant;
+                egui::ComboBox::from_label("Variant")
+                    .selected_text(self.settings.variant.name())
+                    .show_ui(ui, |ui| {
+                        for variant in VariantRules::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.variant,
+                                variant,
+                                variant.name(),
+                            )
+                            .on_hover_text(variant.description());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Changing the variant starts a new game");
+                if self.settings.variant != previous_variant {
+                    self.engine
+                        .send(UIMessage::SetVariant(self.settings.variant));
+                    self.new_game();
+                }
+                let previous_number_to_win = self.settings.number_to_win;
+                egui::ComboBox::from_label("Win length")
+                    .selected_text(format!("Connect {}", self.settings.number_to_win))
+                    .show_ui(ui, |ui| {
+                        for number_to_win in WIN_LENGTHS {
+                            ui.selectable_value(
+                                &mut self.settings.number_to_win,
+                                number_to_win,
+                                format!("Connect {}", number_to_win),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("How many pieces in a row win. Changing this starts a new game");
+                if self.settings.number_to_win != previous_number_to_win {
+                    self.engine
+                        .send(UIMessage::SetWinLength(self.settings.number_to_win));
+                    self.new_game();
+                }
+                let previous_power_up = self.settings.enabled_power_up();
+                ui.checkbox(&mut self.settings.power_ups, "Power-ups")
+                    .on_hover_text(
+                        "Each player can clear every piece out of one column a game. Changing \
+                         this starts a new game",
+                    );
+                let mut anvils = self.settings.power_up_kind == PowerUpKind::Anvil;
+                if ui
+                    .add_enabled(
+                        self.settings.power_ups,
+                        egui::Checkbox::new(&mut anvils, "Anvil discs"),
+                    )
+                    .on_hover_text(
+                        "Each player's power-up is an anvil instead, which crushes every piece \
+                         beneath it and lands at the bottom of the column",
+                    )
+                    .changed()
+                {
+                    self.settings.power_up_kind = match anvils {
+                        true => PowerUpKind::Anvil,
+                        false => PowerUpKind::Clear,
+                    };
+                }
+                if self.settings.enabled_power_up() != previous_power_up {
+                    self.engine
+                        .send(UIMessage::SetPowerUps(self.settings.enabled_power_up()));
+                    self.new_game();
+                }
+
+                let mut separate_process = self.settings.engine_backend == EngineBackend::Process;
+                if ui
+                    .checkbox(&mut separate_process, "Engine in separate process")
+                    .changed()
+                {
+                    self.settings.engine_backend = if separate_process {
+                        EngineBackend::Process
+                    } else {
+                        EngineBackend::Thread
+                    };
+                    self.restart_engine(ctx);
+                }
+
+                if let Some(loaded) = self.notation_input.render(ui) {
+                    self.load_position(loaded);
+                }
+            });
+        });
+
+        self.render_game_over(ctx);
+
+        // Kiosks keep to the game, without the rules editor or analysis
+        if !self.kiosk {
+            self.rules_reference
+                .render(ctx, &mut self.settings.house_rules);
+
+            egui::SidePanel::right("Commentary")
+                .exact_width(COMMENTARY_PANEL_WIDTH)
+                .show(ctx, |ui| {
+                    self.commentary.render(ui);
+
+                    ui.separator();
+
+                    if let Some(moves) = self.archive_search.render(ui, &self.moves) {
+                        if let Some(loaded) = self.notation_input.load(&moves) {
+                            self.load_position(loaded);
+                        }
+                    }
+                    self.archive_stats.render(ui, self.settings.locale);
+                    self.importer.render(ui, ctx);
+                    if let Some(moves) =
+                        self.screenshot_import
+                            .render(ui, ctx, self.settings.piece_colors)
+                    {
+                        match GameRecord::from_moves(&moves) {
+                            Ok(record) => {
+                                let mut review = Review::new(ctx, record);
+                                review.show_last_move();
+                                self.review = Some(review);
+                            }
+                            Err(error) => self.status_message = Some(error),
+                        }
+                    }
+
+                    ui.separator();
+
+                    if let Some(moves) = self.bookmarks.render(
+                        ui,
+                        &self.moves,
+                        &self.move_scores,
+                        self.settings.score_display,
+                    ) {
+                        if let Some(loaded) = self.notation_input.load(&moves) {
+                            self.load_position(loaded);
+                        }
+                    }
+
+                    ui.separator();
+
+                    self.render_challenge(ui);
+                    self.render_search_stats(ui);
+                });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // Communicating with the engine
-            if let Ok(message) = self.receiver.try_recv() {
+            if let Some(message) = self.engine.try_recv() {
                 log_message(
                     LogType::AsyncMessage,
                     format!("EngineMessage Received - {:?}", message),
@@ -71,34 +1911,110 @@ impl eframe::App for App {
 
                 match message {
                     EngineMessage::MoveReceipt {
+                        column,
                         game_state,
                         move_scores,
+                        move_confidences,
                         tree_size,
+                        orientation_flipped,
+                        events,
+                        winning_line,
                     } => {
+                        self.hint = None;
+                        if orientation_flipped && self.settings.animate_flips {
+                            self.board.animate_flip(ctx);
+                        }
+
+                        self.commentary.move_made(
+                            &self.moves,
+                            column,
+                            &self.move_scores,
+                            game_state,
+                            &events,
+                        );
+                        self.teacher.events_received(&events);
+                        let mover = match self.turn_manager.current_player {
+                            PieceState::PlayerTwo => 1,
+                            _ => 0,
+                        };
+                        if self.settings.practice
+                            && self.settings.players[mover] == PlayerType::Computer
+                        {
+                            self.commentary.explain_move(
+                                &self.moves,
+                                column,
+                                &self.move_scores,
+                                self.settings.score_display,
+                            );
+                        }
+                        self.moves.push_str(&ColumnLabel::new(column).to_string());
+                        self.move_times.push(self.game_started.elapsed());
+                        self.mirror_last_move();
+                        match game_state {
+                            GameOver::OneWins => self.finish_game("Red wins", winning_line),
+                            GameOver::TwoWins => self.finish_game("Blue wins", winning_line),
+                            GameOver::Tie => self.finish_game("Draw", winning_line),
+                            GameOver::NoWin => (),
+                        }
+
                         self.tree_size = tree_size;
                         self.move_scores = move_scores;
+                        self.move_confidences = move_confidences;
+                        self.power_up_scores.clear();
 
-                        self.turn_manager.move_receipt(
-                            game_state,
+                        self.turn_manager
+                            .move_receipt(game_state, &mut self.board, &self.settings);
+                        // The receipt's scores are the engine's first look at the new
+                        //  position, which the computer's move can be previewed from
+                        self.turn_manager.update_received(
+                            &self.move_scores,
+                            &self.power_up_scores,
                             ctx,
                             &mut self.board,
                             &self.settings,
                         );
+                        if self.settings.players[mover] == PlayerType::Computer {
+                            self.ponder();
+                        }
+                    }
+                    EngineMessage::InvalidMove(error) => {
+                        self.recover(format!("The engine rejected the move - {}", error))
+                    }
+                    // Failures are handled by the watchdog below
+                    EngineMessage::BackendFailed(_) | EngineMessage::EngineCrashed(_) => (),
+                    // Games never ask for analysis, only the review screen does
+                    EngineMessage::AnalysisResult { .. } => (),
+                    // A hint that arrives after the human has moved is no use to them
+                    EngineMessage::Hint(hint) => {
+                        if self.turn_manager.awaits_human_move() {
+                            self.hint = hint;
+                        }
                     }
-                    EngineMessage::InvalidMove(error) => panic!("{}", error),
                     EngineMessage::Update {
                         move_scores,
+                        move_confidences,
                         tree_size,
+                        power_up_scores,
+                        principal_variation,
+                        events,
+                        search_stats,
                     } => {
                         self.tree_size = tree_size;
+                        self.search_stats = search_stats;
                         self.move_scores = move_scores;
+                        self.move_confidences = move_confidences;
+                        self.power_up_scores = power_up_scores;
+                        self.commentary.events_found(&self.moves, &events);
 
                         self.turn_manager.update_received(
                             &self.move_scores,
+                            &self.power_up_scores,
                             ctx,
                             &mut self.board,
                             &self.settings,
                         );
+                        self.teacher
+                            .line_received(&principal_variation, &mut self.board);
 
                         log_message(
                             LogType::EngineUpdate,
@@ -108,46 +2024,269 @@ impl eframe::App for App {
                             ),
                         );
 
-                        let mut col_score_array: Vec<(&u8, &isize)> = self.move_scores.iter().collect();
+                        let mut col_score_array: Vec<(&u8, &isize)> =
+                            self.move_scores.iter().collect();
                         col_score_array.sort();
-                        let score_array: Vec<&isize> = col_score_array.iter().map(|(_, s)| *s).collect();
+                        let score_array: Vec<String> = col_score_array
+                            .iter()
+                            .map(|(col, s)| format!("{}: {}", ColumnLabel::new(**col), s))
+                            .collect();
 
-                        log_message(
-                            LogType::MoveScores,
-                            format!("{:?}", score_array),
-                        );
+                        log_message(LogType::MoveScores, score_array.join(", "));
                     }
                 }
             }
 
-            self.turn_manager
-                .process_turn(ctx, &mut self.board, &self.settings, &self.sender);
+            // Restarting the engine if it has crashed or stalled
+            if let Some(reason) = self.engine.check(WATCHDOG_TIMEOUT) {
+                log_message(
+                    LogType::Error,
+                    format!(
+                        "Restarting the engine - {} (backend: {:?}, moves: '{}', tree size: {:?})",
+                        reason,
+                        self.engine.backend(),
+                        self.moves,
+                        self.tree_size
+                    ),
+                );
+                self.status_message = Some(format!("{}, so it was restarted", reason));
+                self.restart_engine(ctx);
+            }
+            // Making sure the watchdog gets to check on an engine that has gone quiet
+            ctx.request_repaint_after(WATCHDOG_TIMEOUT);
+
+            // Explaining the human's last mistake, with the game paused until they're done
+            if self.teacher.wants_update() {
+                self.engine.send(UIMessage::RequestUpdate);
+            }
+            match self
+                .teacher
+                .render(ctx, &mut self.board, self.settings.score_display)
+            {
+                Some(TeacherAction::TakeBack(moves)) => {
+                    if let Some(loaded) = self.notation_input.load(&moves) {
+                        self.load_position(loaded);
+                    }
+                }
+                Some(TeacherAction::Continue) => self.turn_manager.position_loaded(
+                    self.turn_manager.current_player,
+                    GameOver::NoWin,
+                    &mut self.board,
+                    &self.settings,
+                ),
+                None => (),
+            }
+
+            let turn_event = if self.teacher.is_teaching() {
+                None
+            } else {
+                match self.turn_manager.process_turn(
+                    ctx,
+                    &mut self.board,
+                    &self.settings,
+                    &self.engine,
+                ) {
+                    Ok(turn_event) => turn_event,
+                    Err(error) => {
+                        self.recover(error);
+                        None
+                    }
+                }
+            };
+            match turn_event {
+                Some(TurnEvent::TimedOut(loser)) => {
+                    let (loser, winner) = match loser {
+                        PieceState::PlayerTwo => ("Blue", "Red"),
+                        _ => ("Red", "Blue"),
+                    };
+                    self.status_message = Some(format!("{} ran out of time", loser));
+                    self.finish_game(&format!("{} wins on time", winner), Vec::new());
+                }
+                Some(TurnEvent::PowerUpSpent(power_up)) => self.spend_power_up(power_up),
+                None => (),
+            }
+            let thinking = self
+                .turn_manager
+                .thinking_progress(&self.settings)
+                .is_some()
+                && (self.settings.thinking_indicator != ThinkingIndicator::None
+                    || self.settings.preview_computer_move);
+            // Keeping the clock ticking and the indicator filling even when nothing else is
+            //  happening
+            if thinking {
+                self.animation_scheduler
+                    .request(Animation::ThinkingIndicator);
+            }
+            if self.turn_manager.time_remaining(&self.settings).is_some() {
+                self.animation_scheduler.request(Animation::Clock);
+            }
 
             // Generating the UI
+            let mut clicked_column = None;
             for (column, response) in self.board.render(ctx, ui) {
                 if response.clicked() {
-                    self.board
-                        .drop_piece(ctx, column, self.turn_manager.current_player);
-                    self.board.lock();
-
-                    self.sender
-                        .send(UIMessage::MakeMove(column))
-                        .expect(format!("Sending MakeMove({}) failed", column).as_str());
+                    clicked_column = Some(column);
                 }
             }
+            if self.settings.preview_computer_move {
+                if let Some(column) = self.turn_manager.previewed_column() {
+                    self.board.render_column_preview(ui, column);
+                }
+            }
+            if let Some(hint) = &self.hint {
+                self.board.render_column_preview(ui, hint.column as usize);
+            }
+            if self.settings.thinking_indicator == ThinkingIndicator::ProgressRing {
+                if let Some(progress) = self.turn_manager.thinking_progress(&self.settings) {
+                    self.board.render_thinking_ring(
+                        ui,
+                        thinking_column(&self.move_scores),
+                        progress,
+                    );
+                }
+            }
+            if self.settings.enabled_power_up() == Some(PowerUpKind::Anvil) {
+                let anvils = parse_moves(&self.moves)
+                    .map(|moves| anvil_columns(&moves))
+                    .unwrap_or_default();
+                self.board.render_anvils(ui, &anvils);
+            }
+            // The connect four is ringed once its last piece has landed
+            if !self.board.is_piece_falling() {
+                self.board.render_winning_line(ui, &self.winning_line);
+            }
+            if let Some(column) = clicked_column {
+                self.play_column(column, ctx);
+            }
+            self.process_physical_board(ctx);
+            self.process_game_server(ctx);
+
+            if self.settings.show_hints {
+                let hints: Vec<(usize, String, f32)> = self
+                    .move_scores
+                    .iter()
+                    .map(|(column, score)| {
+                        let confidence = self
+                            .move_confidences
+                            .get(column)
+                            .map(|confidence| confidence.shade())
+                            .unwrap_or(0.0);
+
+                        (
+                            *column as usize,
+                            self.settings.score_display.format(*score),
+                            confidence,
+                        )
+                    })
+                    .collect();
+
+                self.board.render_hints(ui, &hints);
+            }
+            if self.settings.show_evaluation_bar {
+                self.render_evaluation(ui);
+            }
         });
+
+        #[cfg(feature = "overlay")]
+        self.publish_overlay();
+        self.end_frame(ctx);
     }
 }
 
-/// Runs the application.
+/// Runs the application, or the command line interface if any arguments were given.
+///
+/// The application is also run when the only argument is a challenge link, starting with
+/// the challenge, and --kiosk can be given along with either to lock the application down,
+/// as can --board with a serial port to mirror the game onto a physical board, and
+/// --server with the ws:// URL of a game server to play people on. Without the
+/// gui feature there is no application, so the command line interface always
+/// runs.
 fn main() {
-    let mut native_options = eframe::NativeOptions::default();
-    native_options.initial_window_size = Some(Board::board_size());
+    // Only the GUI's own flags are taken out of the arguments
+    #[cfg_attr(not(feature = "gui"), allow(unused_mut))]
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    #[cfg(feature = "gui")]
+    let kiosk = match args.iter().position(|arg| arg == "--kiosk") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    #[cfg(feature = "gui")]
+    let physical_board = match args.iter().position(|arg| arg == "--board") {
+        Some(index) if index + 1 < args.len() => {
+            let port = args.remove(index + 1);
+            args.remove(index);
+            Some(PathBuf::from(port))
+        }
+        Some(_) => {
+            eprintln!("--board expects the board's serial port, e.g. /dev/ttyUSB0");
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    #[cfg(feature = "gui")]
+    let game_server = match args.iter().position(|arg| arg == "--server") {
+        Some(index) if index + 1 < args.len() => {
+            let url = args.remove(index + 1);
+            args.remove(index);
+            Some(url)
+        }
+        Some(_) => {
+            eprintln!("--server expects the game server's URL, e.g. ws://localhost:8080");
+            std::process::exit(2);
+        }
+        None => None,
+    };
+
+    #[cfg(feature = "gui")]
+    if let [link] = &args[..] {
+        if Challenge::is_challenge(link) {
+            match Challenge::parse(link) {
+                Ok(challenge) => run_app(Some(challenge), kiosk, physical_board, game_server),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+    }
+
+    if !cfg!(feature = "gui") || !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
+    #[cfg(feature = "gui")]
+    run_app(None, kiosk, physical_board, game_server);
+}
+
+/// Opens the application's window, returning once it's closed. Kiosks are fullscreen.
+#[cfg(feature = "gui")]
+fn run_app(
+    challenge: Option<Challenge>,
+    kiosk: bool,
+    physical_board: Option<PathBuf>,
+    game_server: Option<String>,
+) {
+    let native_options = eframe::NativeOptions {
+        fullscreen: kiosk,
+        initial_window_size: Some(
+            Board::board_size()
+                + Vec2 {
+                    x: COMMENTARY_PANEL_WIDTH,
+                    y: NOTATION_PANEL_HEIGHT * 2.0,
+                },
+        ),
+        ..Default::default()
+    };
 
     eframe::run_native(
-        "Connect 4 Engine",
+        WINDOW_TITLE,
         native_options,
-        Box::new(|cc| Box::new(App::new(cc))),
+        Box::new(move |cc| Box::new(App::new(cc, challenge, kiosk, physical_board, game_server))),
     )
     .unwrap();
 }
@@ -0,0 +1,188 @@
+use std::{fs, io, path::Path};
+
+use crate::game_engine::game_record::{format_score, parse_score};
+
+/// The directory the user's profile is saved to by default.
+pub const DEFAULT_PROFILE_DIRECTORY: &str = "profile";
+
+/// The name of the file within the profile directory that bookmarks are saved to.
+const BOOKMARKS_FILE: &str = "bookmarks.txt";
+
+/// The name of the file within the profile directory that the puzzle rush high score is
+/// saved to.
+const PUZZLE_RUSH_FILE: &str = "puzzle_rush.txt";
+
+/// Written in place of an empty move string or a missing evaluation.
+const EMPTY_FIELD: &str = "-";
+
+/// A position the user bookmarked while analysing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    /// The position's canonical key, which is the same for mirrored positions.
+    pub key: u64,
+    /// The moves leading to the position, as columns numbered from 1.
+    pub moves: String,
+    /// The engine's evaluation of the best move when the bookmark was saved, if it had one.
+    pub eval: Option<isize>,
+    pub note: String,
+}
+
+impl Bookmark {
+    /// Writes the bookmark as a single line, e.g. `1234 4453 12 Tricky opening`.
+    fn to_line(&self) -> String {
+        let moves = if self.moves.is_empty() {
+            EMPTY_FIELD
+        } else {
+            &self.moves
+        };
+        let eval = self
+            .eval
+            .map(format_score)
+            .unwrap_or_else(|| EMPTY_FIELD.to_owned());
+        let note = self.note.replace(['\n', '\r'], " ");
+
+        format!("{} {} {} {}", self.key, moves, eval, note)
+            .trim_end()
+            .to_owned()
+    }
+
+    /// Parses a line written by to_line.
+    fn parse_line(line: &str) -> Result<Bookmark, String> {
+        let malformed = || format!("Malformed bookmark '{}'", line);
+
+        let mut fields = line.splitn(4, ' ');
+        let (Some(key), Some(moves), Some(eval)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(malformed());
+        };
+
+        Ok(Bookmark {
+            key: key.parse().map_err(|_| malformed())?,
+            moves: match moves {
+                EMPTY_FIELD => String::new(),
+                moves => moves.to_owned(),
+            },
+            eval: match eval {
+                EMPTY_FIELD => None,
+                eval => Some(parse_score(eval).ok_or_else(malformed)?),
+            },
+            note: fields.next().unwrap_or("").to_owned(),
+        })
+    }
+}
+
+/// Loads the bookmarks saved in the given profile directory.
+///
+/// A missing bookmarks file is treated as having no bookmarks, and lines that can't be
+/// parsed are skipped.
+pub fn load_bookmarks(directory: &Path) -> io::Result<Vec<Bookmark>> {
+    let path = directory.join(BOOKMARKS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| Bookmark::parse_line(line).ok())
+        .collect())
+}
+
+/// Saves the bookmarks into the given profile directory, creating it if necessary and
+/// replacing any bookmarks saved before.
+pub fn save_bookmarks(directory: &Path, bookmarks: &[Bookmark]) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+
+    let lines: Vec<String> = bookmarks.iter().map(Bookmark::to_line).collect();
+    fs::write(directory.join(BOOKMARKS_FILE), lines.join("\n") + "\n")
+}
+
+/// Loads the most puzzles solved in a puzzle rush, saved in the given profile directory.
+///
+/// A missing or unreadable high score is treated as 0.
+pub fn load_puzzle_rush_high_score(directory: &Path) -> usize {
+    fs::read_to_string(directory.join(PUZZLE_RUSH_FILE))
+        .ok()
+        .and_then(|text| text.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Saves the most puzzles solved in a puzzle rush into the given profile directory,
+/// creating it if necessary.
+pub fn save_puzzle_rush_high_score(directory: &Path, high_score: usize) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+
+    fs::write(
+        directory.join(PUZZLE_RUSH_FILE),
+        format!("{}\n", high_score),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::profile::{
+        load_bookmarks, load_puzzle_rush_high_score, save_bookmarks, save_puzzle_rush_high_score,
+        Bookmark, BOOKMARKS_FILE, PUZZLE_RUSH_FILE,
+    };
+
+    #[test]
+    fn bookmarks_round_trip() {
+        let directory = std::env::temp_dir().join("rusty_connect_four_bookmarks_round_trip");
+        let _ = fs::remove_dir_all(&directory);
+
+        assert!(load_bookmarks(&directory).unwrap().is_empty());
+
+        let bookmarks = vec![
+            Bookmark {
+                key: 1234,
+                moves: "4453".to_owned(),
+                eval: Some(-12),
+                note: "Tricky opening\nwith a second line".to_owned(),
+            },
+            Bookmark {
+                key: 1,
+                moves: String::new(),
+                eval: None,
+                note: String::new(),
+            },
+            Bookmark {
+                key: 99,
+                moves: "444".to_owned(),
+                eval: Some(isize::MAX),
+                note: "Winning".to_owned(),
+            },
+        ];
+        save_bookmarks(&directory, &bookmarks).unwrap();
+
+        let loaded = load_bookmarks(&directory).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[0].note, "Tricky opening with a second line");
+        assert_eq!(loaded[1..], bookmarks[1..]);
+
+        // Broken lines are skipped
+        fs::write(
+            directory.join(BOOKMARKS_FILE),
+            "not a bookmark\n5 4 win Fine\n",
+        )
+        .unwrap();
+        let loaded = load_bookmarks(&directory).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].eval, Some(isize::MAX));
+    }
+
+    #[test]
+    fn puzzle_rush_high_score() {
+        let directory = std::env::temp_dir().join("rusty_connect_four_puzzle_rush_high_score");
+        let _ = fs::remove_dir_all(&directory);
+
+        assert_eq!(load_puzzle_rush_high_score(&directory), 0);
+
+        save_puzzle_rush_high_score(&directory, 17).unwrap();
+        assert_eq!(load_puzzle_rush_high_score(&directory), 17);
+
+        fs::write(directory.join(PUZZLE_RUSH_FILE), "lots").unwrap();
+        assert_eq!(load_puzzle_rush_high_score(&directory), 0);
+    }
+}
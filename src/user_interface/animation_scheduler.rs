@@ -0,0 +1,78 @@
+use std::{collections::HashMap, time::Duration};
+
+use egui::Context;
+
+/// How often any animation is redrawn at most when power saving.
+const POWER_SAVING_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The animations that keep the app redrawing when nothing else is happening.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Animation {
+    /// The clocks counting down during a timed move.
+    Clock,
+    /// The indicator filling up, or the previewed move drifting, while the computer thinks.
+    ThinkingIndicator,
+}
+
+impl Animation {
+    /// How long the animation goes between frames unless it's given another tick rate.
+    fn default_tick_interval(&self) -> Duration {
+        match self {
+            Animation::Clock => Duration::from_millis(250),
+            Animation::ThinkingIndicator => Duration::from_millis(100),
+        }
+    }
+}
+
+/// Gathers up the repaints animations ask for over a frame into a single request for the
+/// soonest of them, rather than each animation asking for its own.
+///
+/// Each animation has its own tick rate, so one that only needs redrawing now and then
+/// doesn't repaint the app every frame.
+#[derive(Default)]
+pub struct AnimationScheduler {
+    tick_intervals: HashMap<Animation, Duration>,
+    /// How long until the soonest repaint asked for this frame, if any were.
+    next_repaint: Option<Duration>,
+}
+
+impl AnimationScheduler {
+    /// Creates a scheduler with every animation at its default tick rate.
+    pub fn new() -> AnimationScheduler {
+        Default::default()
+    }
+
+    /// Returns how long the animation goes between frames.
+    pub fn tick_interval(&self, animation: Animation) -> Duration {
+        self.tick_intervals
+            .get(&animation)
+            .copied()
+            .unwrap_or_else(|| animation.default_tick_interval())
+    }
+
+    /// Sets how long the animation goes between frames.
+    pub fn set_tick_interval(&mut self, animation: Animation, interval: Duration) {
+        self.tick_intervals.insert(animation, interval);
+    }
+
+    /// Asks for the animation's next frame.
+    pub fn request(&mut self, animation: Animation) {
+        let interval = self.tick_interval(animation);
+        self.next_repaint = Some(
+            self.next_repaint
+                .map_or(interval, |next| next.min(interval)),
+        );
+    }
+
+    /// Requests one repaint for every animation that asked for a frame, slowed down when
+    /// power saving.
+    pub fn end_frame(&mut self, ctx: &Context, power_saving: bool) {
+        if let Some(next_repaint) = self.next_repaint.take() {
+            ctx.request_repaint_after(if power_saving {
+                next_repaint.max(POWER_SAVING_TICK_INTERVAL)
+            } else {
+                next_repaint
+            });
+        }
+    }
+}
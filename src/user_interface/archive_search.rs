@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use egui::{CollapsingHeader, ScrollArea, Ui};
+
+use crate::{archive::Archive, game_engine::game_manager::GameManager};
+
+/// A game in the archive that reached the searched for position.
+struct SearchResult {
+    /// The name of the game's file.
+    name: String,
+    /// All of the moves in the game.
+    moves: String,
+    /// How many moves into the game the position was reached.
+    ply: usize,
+}
+
+/// Searches the archive for games that reached the current position.
+pub struct ArchiveSearch {
+    directory: PathBuf,
+    /// The games found by the last search, or why the search failed.
+    results: Option<Result<Vec<SearchResult>, String>>,
+}
+
+impl ArchiveSearch {
+    /// Creates an ArchiveSearch over the archive in the given directory.
+    pub fn new(directory: PathBuf) -> ArchiveSearch {
+        ArchiveSearch {
+            directory,
+            results: None,
+        }
+    }
+
+    /// Searches the archive for games that reached the position after the given moves.
+    ///
+    /// The archive is reopened for every search, so newly saved games are included.
+    fn search(&mut self, moves: &str) {
+        let key = match GameManager::from_moves(moves) {
+            Ok(manager) => manager.position_key(),
+            Err(error) => {
+                self.results = Some(Err(error));
+                return;
+            }
+        };
+
+        let archive = match Archive::open(&self.directory) {
+            Ok(archive) => archive,
+            Err(error) => {
+                self.results = Some(Err(error.to_string()));
+                return;
+            }
+        };
+
+        let results = archive
+            .find_position(key)
+            .iter()
+            .map(|position_match| {
+                let game = &archive.games()[position_match.game];
+
+                SearchResult {
+                    name: game
+                        .path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    moves: game.record.moves_string(),
+                    ply: position_match.ply,
+                }
+            })
+            .collect();
+
+        self.results = Some(Ok(results));
+    }
+
+    /// Renders the search button and the games found by the last search.
+    ///
+    /// current_moves are the moves leading to the position to search for. Returns the moves
+    /// of a game if the user chooses to jump to it.
+    pub fn render(&mut self, ui: &mut Ui, current_moves: &str) -> Option<String> {
+        let mut chosen_game = None;
+
+        CollapsingHeader::new("Archive").show(ui, |ui| {
+            if ui.button("Find this position in my games").clicked() {
+                self.search(current_moves);
+            }
+
+            match &self.results {
+                Some(Ok(results)) => {
+                    ui.label(format!("{} games reached this position", results.len()));
+
+                    ScrollArea::vertical()
+                        .id_source("Archive Results")
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for result in results {
+                                let link = format!("{} (move {})", result.name, result.ply);
+
+                                if ui.link(link).on_hover_text(&result.moves).clicked() {
+                                    chosen_game = Some(result.moves.clone());
+                                }
+                            }
+                        });
+                }
+                Some(Err(error)) => {
+                    ui.label(format!("Couldn't search the archive: {}", error));
+                }
+                None => (),
+            }
+        });
+
+        chosen_game
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use egui::{Align2, CollapsingHeader, Color32, FontId, Rect, Sense, Ui, Vec2};
+
+use crate::{
+    archive::{Archive, ArchiveStatistics},
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::column_label::ColumnLabel,
+    user_interface::locale::Locale,
+};
+
+/// The size of each space of the heatmaps.
+const CELL_SIZE: f32 = 36.0;
+
+/// Shows which spaces most often win games in the archive, and how each first move turns out.
+pub struct ArchiveStats {
+    directory: PathBuf,
+    /// The statistics of the archive when they were last computed, or why they couldn't be.
+    statistics: Option<Result<ArchiveStatistics, String>>,
+}
+
+impl ArchiveStats {
+    /// Creates an ArchiveStats over the archive in the given directory.
+    pub fn new(directory: PathBuf) -> ArchiveStats {
+        ArchiveStats {
+            directory,
+            statistics: None,
+        }
+    }
+
+    /// Renders the button to compute the statistics, and the heatmaps once they have been.
+    pub fn render(&mut self, ui: &mut Ui, locale: Locale) {
+        CollapsingHeader::new("Statistics").show(ui, |ui| {
+            if ui.button("Analyse my games").clicked() {
+                self.statistics = Some(
+                    Archive::open(&self.directory)
+                        .map(|archive| archive.statistics())
+                        .map_err(|error| error.to_string()),
+                );
+            }
+
+            match &self.statistics {
+                Some(Ok(statistics)) => render_statistics(ui, statistics, locale),
+                Some(Err(error)) => {
+                    ui.label(format!("Couldn't open the archive: {}", error));
+                }
+                None => (),
+            }
+        });
+    }
+}
+
+/// Renders how often each space was part of a winning line, over a board shaped grid, and
+/// Red's win rate for each first move underneath.
+fn render_statistics(ui: &mut Ui, statistics: &ArchiveStatistics, locale: Locale) {
+    let games = locale.format_integer(statistics.games);
+    ui.label(match statistics.last_saved {
+        Some(last_saved) => format!(
+            "{} games, the latest saved on {}",
+            games,
+            locale.format_date(last_saved)
+        ),
+        None => format!("{} games", games),
+    });
+
+    ui.label("Spaces in winning lines");
+    let most = statistics
+        .winning_cells
+        .iter()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let rect = allocate_grid(ui, BOARD_HEIGHT as usize);
+    for (row, counts) in statistics.winning_cells.iter().enumerate() {
+        for (column, count) in counts.iter().enumerate() {
+            let heat = *count as f32 / most as f32;
+            let color = Color32::from_rgb(40 + (215.0 * heat) as u8, 40 + (120.0 * heat) as u8, 40);
+
+            paint_cell(ui, rect, row, column, color, &locale.format_integer(*count));
+        }
+    }
+
+    ui.label("Red's win rate by first move");
+    let rect = allocate_grid(ui, 1);
+    for (column, results) in statistics.first_moves.iter().enumerate() {
+        let (color, text) = match results.red_win_rate() {
+            Some(rate) => (
+                Color32::from_rgb((200.0 * rate) as u8, 40, (200.0 * (1.0 - rate)) as u8),
+                locale.format_percent(rate as f64),
+            ),
+            None => (Color32::from_gray(40), "-".to_owned()),
+        };
+
+        let cell = paint_cell(ui, rect, 0, column, color, &text);
+        ui.interact(cell, ui.id().with(("First move", column)), Sense::hover())
+            .on_hover_text(format!(
+                "Opening in column {}: {} games, {} Red wins, {} Blue wins, {} draws",
+                ColumnLabel::new(column),
+                locale.format_integer(results.games),
+                locale.format_integer(results.red_wins),
+                locale.format_integer(results.blue_wins),
+                locale.format_integer(results.draws)
+            ));
+    }
+}
+
+/// Allocates space for a grid as wide as the board with the given number of rows.
+fn allocate_grid(ui: &mut Ui, rows: usize) -> Rect {
+    let size = Vec2::new(BOARD_WIDTH as f32, rows as f32) * CELL_SIZE;
+
+    ui.allocate_exact_size(size, Sense::hover()).0
+}
+
+/// Fills a space of a grid with a color and labels it, returning the space's rect.
+fn paint_cell(ui: &Ui, grid: Rect, row: usize, column: usize, color: Color32, text: &str) -> Rect {
+    let min = grid.min + Vec2::new(column as f32, row as f32) * CELL_SIZE;
+    let cell = Rect::from_min_size(min, Vec2::splat(CELL_SIZE)).shrink(1.0);
+
+    let painter = ui.painter();
+    painter.rect_filled(cell, 2.0, color);
+    painter.text(
+        cell.center(),
+        Align2::CENTER_CENTER,
+        text,
+        FontId::proportional(CELL_SIZE / 3.0),
+        Color32::WHITE,
+    );
+
+    cell
+}
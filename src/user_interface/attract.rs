@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+use egui::{Context, RichText};
+
+use crate::user_interface::{
+    animation_scheduler::AnimationScheduler, engine_interface::GameOver, exhibition::Exhibition,
+    settings::Difficulty,
+};
+
+/// How many seconds each engine thinks per move in the demo, much quicker than a regular
+/// exhibition so there's always something happening.
+const DEMO_MOVE_DELAY: f32 = 0.5;
+/// How long a finished demo game stays on screen before the next one starts.
+const DEMO_RESTART_DELAY: Duration = Duration::from_secs(6);
+/// The width of the panel the demo's commentary is shown in.
+const COMMENTARY_WIDTH: f32 = 250.0;
+
+/// Keeps track of how long it's been since the user last did anything.
+pub struct IdleTimer {
+    last_input: Instant,
+}
+
+impl IdleTimer {
+    /// Starts timing from now.
+    pub fn new() -> IdleTimer {
+        IdleTimer {
+            last_input: Instant::now(),
+        }
+    }
+
+    /// Checks the frame's input, starting the timer over if there was any.
+    ///
+    /// Returns whether there was any input, e.g. moving the mouse or pressing a key.
+    pub fn update(&mut self, ctx: &Context) -> bool {
+        let active = ctx.input(|input| !input.events.is_empty() || input.pointer.any_down());
+        if active {
+            self.last_input = Instant::now();
+        }
+
+        active
+    }
+
+    /// Returns how long it's been since there was any input.
+    pub fn idle_for(&self) -> Duration {
+        self.last_input.elapsed()
+    }
+}
+
+impl Default for IdleTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A demo shown while the app is left alone, where two engines play sped up exhibition
+/// games with commentary, one after another.
+pub struct AttractMode {
+    exhibition: Exhibition,
+    /// When the current game ended, if it has.
+    finished_at: Option<Instant>,
+}
+
+impl AttractMode {
+    /// Starts the first demo game.
+    pub fn new(ctx: &Context) -> AttractMode {
+        AttractMode {
+            exhibition: Exhibition::new(ctx, [Difficulty::Hard; 2], DEMO_MOVE_DELAY),
+            finished_at: None,
+        }
+    }
+
+    /// Renders the demo over the whole window, starting a new game a little while after
+    /// each one ends.
+    pub fn update(&mut self, ctx: &Context, animations: &mut AnimationScheduler) {
+        egui::TopBottomPanel::bottom("Demo").show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(RichText::new("Demo game - touch anything to play").heading());
+            });
+        });
+        egui::SidePanel::right("Demo Commentary")
+            .exact_width(COMMENTARY_WIDTH)
+            .show(ctx, |ui| {
+                self.exhibition.render_commentary(ui);
+            });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.exhibition.update(ctx, ui, animations);
+        });
+
+        if self.exhibition.game_state() == GameOver::NoWin {
+            return;
+        }
+
+        let finished_at = *self.finished_at.get_or_insert_with(Instant::now);
+        let shown_for = finished_at.elapsed();
+        if shown_for >= DEMO_RESTART_DELAY {
+            self.exhibition.restart();
+            self.finished_at = None;
+        } else {
+            ctx.request_repaint_after(DEMO_RESTART_DELAY - shown_for);
+        }
+    }
+}
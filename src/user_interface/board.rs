@@ -1,16 +1,52 @@
-use egui::{Color32, Context, Id, Painter, Pos2, Rect, Response, Sense, Shape, Stroke, Ui, Vec2};
-
-use crate::consts::{BOARD_HEIGHT, BOARD_WIDTH};
+use std::f32::consts::TAU;
+
+use egui::{
+    Align2, Color32, Context, FontId, Id, Key, Painter, Pos2, Rect, Response, Sense, Shape, Stroke,
+    Ui, Vec2, WidgetInfo, WidgetType,
+};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::{board_config::BoardConfig, column_label::ColumnLabel},
+    user_interface::{
+        board_geometry::{BoardGeometry, Magnifier, HALF_SPACING, PIECE_SPACING},
+        settings::PieceColors,
+        view_model::FALL_TIME_PER_ROW,
+    },
+};
 
 /// The size a piece takes up.
 const PIECE_RADIUS: f32 = 38.0;
-/// The space between pieces.
-const PIECE_SPACING: f32 = 90.0;
-/// Half of the piece spacing, used for centering things.
-const HALF_SPACING: f32 = PIECE_SPACING / 2.0;
 
-/// How fast a piece falls down a single row.
-const FALLING_SPEED: f32 = 0.12;
+/// How long the pieces take to slide into place when the board is mirrored.
+const FLIP_SPEED: f32 = 0.4;
+/// The font size of the move hints shown above the board.
+const HINT_SIZE: f32 = 14.0;
+/// How bright the ring on each piece is, compared to the rest of the piece.
+const ACCENT_SHADE: f32 = 0.55;
+/// How thick the ring shown while the computer is thinking is.
+const THINKING_RING_WIDTH: f32 = 6.0;
+/// How many straight lines make up a full thinking ring.
+const THINKING_RING_SEGMENTS: usize = 60;
+/// How long the column preview takes to drift over to a different column, in seconds.
+const PREVIEW_DRIFT_TIME: f32 = 0.8;
+/// How strongly the column preview is tinted with the player's color.
+const PREVIEW_SHADE: f32 = 0.15;
+/// How wide the evaluation bar beside the board is.
+const EVALUATION_BAR_WIDTH: f32 = 16.0;
+/// How far the evaluation bar is from the side of the board.
+const EVALUATION_BAR_GAP: f32 = 12.0;
+/// How thick the win chance bars shown above the move hints are.
+const WIN_CHANCE_BAR_HEIGHT: f32 = 4.0;
+/// How thick the rings around the pieces of a connect four are.
+const WINNING_LINE_WIDTH: f32 = 6.0;
+/// The color of the anvil drawn over an anvil disc.
+const ANVIL_COLOR: Color32 = Color32::from_rgb(70, 70, 80);
+
+/// The most the board can be magnified, compared to its usual size.
+pub const MAX_ZOOM: f32 = 4.0;
+/// How much the zoom changes each time + or - is pressed.
+const ZOOM_STEP: f32 = 0.25;
 
 /// The set of points for triangles used to display the background.
 const BACKGROUND_TRIANGLES: [[Pos2; 3]; 4] = [
@@ -106,22 +142,26 @@ struct Piece {
 }
 
 impl Piece {
-    /// Paints a piece onto the board.
-    fn render_piece(&self, painter: &Painter) {
-        let (color, accent_color) = match self.state {
+    /// Paints a piece onto the board, in its player's color.
+    fn render_piece(&self, painter: &Painter, colors: &PieceColors, magnifier: &Magnifier) {
+        let [red, green, blue] = match self.state {
             PieceState::Empty => return,
-            PieceState::PlayerOne => (Color32::RED, Color32::DARK_RED),
-            PieceState::PlayerTwo => (Color32::BLUE, Color32::DARK_BLUE),
+            PieceState::PlayerOne => colors.player_one,
+            PieceState::PlayerTwo => colors.player_two,
         };
+        let color = Color32::from_rgb(red, green, blue);
+        let [red, green, blue] =
+            [red, green, blue].map(|channel| (channel as f32 * ACCENT_SHADE) as u8);
+        let accent_color = Color32::from_rgb(red, green, blue);
 
-        let center = Pos2 {
+        let center = magnifier.position(Pos2 {
             x: self.piece_position.x + HALF_SPACING,
             y: self.piece_position.y + HALF_SPACING,
-        };
-        painter.circle_filled(center, PIECE_RADIUS, color);
+        });
+        painter.circle_filled(center, magnifier.length(PIECE_RADIUS), color);
 
-        let accent_radius = PIECE_RADIUS * 2.0 / 3.0;
-        let accent_width = PIECE_RADIUS / 6.0;
+        let accent_radius = magnifier.length(PIECE_RADIUS * 2.0 / 3.0);
+        let accent_width = magnifier.length(PIECE_RADIUS / 6.0);
         painter.circle_stroke(
             center,
             accent_radius,
@@ -136,17 +176,17 @@ impl Piece {
     ///
     /// A piece hole consists of four triangles, plus a border used to
     /// smooth the edges of the triangles into a circular shape.
-    fn render_background(&self, painter: &Painter) {
-        let center = Pos2 {
+    fn render_background(&self, painter: &Painter, magnifier: &Magnifier) {
+        let center = magnifier.position(Pos2 {
             x: self.board_position.x + HALF_SPACING,
             y: self.board_position.y + HALF_SPACING,
-        };
+        });
 
         painter.circle_stroke(
             center,
-            PIECE_RADIUS,
+            magnifier.length(PIECE_RADIUS),
             Stroke {
-                width: 2.0 * (HALF_SPACING - PIECE_RADIUS),
+                width: magnifier.length(2.0 * (HALF_SPACING - PIECE_RADIUS)),
                 color: Color32::YELLOW,
             },
         );
@@ -156,6 +196,7 @@ impl Piece {
             for point in path.iter_mut() {
                 point.x += self.board_position.x;
                 point.y += self.board_position.y;
+                *point = magnifier.position(*point);
             }
 
             let shape = Shape::convex_polygon(path.into(), Color32::YELLOW, Stroke::NONE);
@@ -164,9 +205,45 @@ impl Piece {
     }
 }
 
+/// The id the piece colors are kept under in egui's memory.
+fn piece_colors_id() -> Id {
+    Id::new("Piece Colors")
+}
+
+/// Sets the colors every board draws its pieces in, including the pieces already played,
+/// from the next frame on.
+pub fn set_piece_colors(ctx: &Context, colors: PieceColors) {
+    ctx.data_mut(|data| data.insert_temp(piece_colors_id(), colors));
+}
+
+/// Returns the colors pieces are drawn in, as last set by set_piece_colors.
+fn piece_colors(ctx: &Context) -> PieceColors {
+    ctx.data_mut(|data| data.get_temp(piece_colors_id()))
+        .unwrap_or_default()
+}
+
+/// The id the board zoom is kept under in egui's memory.
+fn board_zoom_id() -> Id {
+    Id::new("Board Zoom")
+}
+
+/// Sets how much every board is magnified, from 1.0 for its usual size up to MAX_ZOOM.
+///
+/// The zoom is kept in egui's memory, so it's restored the next time the app is opened.
+pub fn set_board_zoom(ctx: &Context, zoom: f32) {
+    ctx.data_mut(|data| data.insert_persisted(board_zoom_id(), zoom.clamp(1.0, MAX_ZOOM)));
+}
+
+/// Returns how much every board is magnified, as last set by set_board_zoom.
+pub fn board_zoom(ctx: &Context) -> f32 {
+    ctx.data_mut(|data| data.get_persisted(board_zoom_id()))
+        .unwrap_or(1.0)
+}
+
 /// A column of the board.
 struct Column {
-    pieces: [Piece; BOARD_HEIGHT as usize],
+    /// The column's pieces, from the top of the board down.
+    pieces: Vec<Piece>,
     id: Id,
     /// The rectangular region that the column occupies.
     rect: Rect,
@@ -175,78 +252,47 @@ struct Column {
 }
 
 impl Column {
-    /// Creates a column, given a position that will serve as its
-    /// upper left corner.
-    fn new(id: Id, position: Pos2) -> Column {
-        let mut new_column = Column {
+    /// Creates an empty column with room for the given number of pieces, at the given index
+    /// of the board's layout.
+    fn new(id: Id, geometry: &BoardGeometry, index: usize, rows: u8) -> Column {
+        let rect = geometry.column_rect(index);
+        let pieces = (0..rows)
+            .map(|row| Piece {
+                state: PieceState::Empty,
+                board_position: geometry.piece_position(index, row as f32),
+                piece_position: rect.min,
+            })
+            .collect();
+
+        Column {
+            pieces,
             id,
-            pieces: Default::default(),
-            rect: Rect {
-                min: position,
-                max: Pos2 {
-                    x: position.x + PIECE_SPACING,
-                    y: position.y + PIECE_SPACING * (BOARD_HEIGHT as f32),
-                },
-            },
+            rect,
             height: 0,
-        };
-
-        let mut piece_pos = position;
-        for i in 0..(BOARD_HEIGHT as usize) {
-            piece_pos.y = new_column.get_y_position_of_piece(i as f32);
-
-            new_column.pieces[i] = Piece {
-                state: PieceState::Empty,
-                board_position: piece_pos.clone(),
-                piece_position: position,
-            };
         }
-
-        new_column
     }
 
     /// Renders a column and all the pieces contained in the column.
-    fn render(&self, ui: &mut Ui) {
-        let painter = ui.painter();
-
+    fn render(&self, painter: &Painter, colors: &PieceColors, magnifier: &Magnifier) {
         for piece in self.pieces.iter() {
-            piece.render_piece(painter);
+            piece.render_piece(painter, colors, magnifier);
         }
         for piece in self.pieces.iter() {
-            piece.render_background(painter);
+            piece.render_background(painter, magnifier);
         }
     }
 
-    /// Returns a response that allows for click and hover checking.
+    /// Returns a response that allows for click and hover checking, over the part of the
+    /// column that's in view.
     ///
     /// Will only have click checking if the column isn't full.
-    fn response(&self, ui: &mut Ui) -> Response {
+    fn response(&self, ui: &mut Ui, magnifier: &Magnifier, view: Rect) -> Response {
         let mut sense = Sense::hover();
-        if self.height < BOARD_HEIGHT as usize {
+        if self.height < self.pieces.len() {
             sense = sense.union(Sense::click());
         }
 
-        ui.interact(self.rect, self.id, sense)
-    }
-
-    /// Returns the y position that a piece should occupy given that it is
-    /// in a particular row of the column.
-    fn get_y_position_of_piece(&self, row: f32) -> f32 {
-        row * PIECE_SPACING + self.rect.min.y
-    }
-}
-
-impl Default for Column {
-    fn default() -> Self {
-        Self {
-            pieces: Default::default(),
-            id: Id::new(""),
-            rect: Rect {
-                min: Pos2::default(),
-                max: Pos2::default(),
-            },
-            height: 0,
-        }
+        ui.interact(magnifier.rect(self.rect).intersect(view), self.id, sense)
     }
 }
 
@@ -258,11 +304,14 @@ struct ColumnId {
     index: usize,
 }
 
-/// A game board, consisting of six rows and seven columns.
+/// A game board, consisting of six rows and seven columns unless it's created with
+/// with_config.
 pub struct Board {
-    columns: [Column; BOARD_WIDTH as usize],
+    columns: Vec<Column>,
+    config: BoardConfig,
     id: Id,
-    rect: Rect, // TODO: Possibly just change this to the position of the upper left corner
+    /// Where the board's columns and pieces go, before it's magnified.
+    geometry: BoardGeometry,
     /// A piece that floats above the board to show where the user is pointing.
     floater: Piece,
     // TODO: Consolidate the following fields into some sort of state machine
@@ -272,39 +321,43 @@ pub struct Board {
     locked: bool,
     /// Contains the indices of a piece that is falling down the board.
     falling_piece: Option<[usize; 2]>,
+    /// Whether the pieces are sliding into place after the board was mirrored.
+    flipping: bool,
+    /// The zoom the board was last rendered at.
+    zoom: f32,
+    /// How far the magnified board has been moved up and to the left, to show the part
+    /// being looked at.
+    pan: Vec2,
 }
 
 impl Board {
     /// Creates a new board given an Id and its upper left corner.
     pub fn new(id: Id, position: Pos2) -> Board {
-        let mut columns: [Column; (BOARD_WIDTH as usize)] = Default::default();
+        Board::with_config(id, position, BoardConfig::STANDARD)
+    }
 
-        for i in 0..columns.len() {
-            columns[i] = Column::new(
-                Id::new(ColumnId {
-                    board_id: id,
-                    index: i,
-                }),
-                Pos2 {
-                    x: position.x + PIECE_SPACING * (i as f32),
-                    y: position.y + PIECE_SPACING,
-                },
-            );
-        }
+    /// Creates a new board with the given dimensions, given an Id and its upper left corner.
+    pub fn with_config(id: Id, position: Pos2, config: BoardConfig) -> Board {
+        let geometry = BoardGeometry::new(position, config);
+        let columns = (0..config.width as usize)
+            .map(|i| {
+                Column::new(
+                    Id::new(ColumnId {
+                        board_id: id,
+                        index: i,
+                    }),
+                    &geometry,
+                    i,
+                    config.height,
+                )
+            })
+            .collect();
 
         Board {
             columns,
+            config,
             id,
-            rect: Rect {
-                min: Pos2 {
-                    x: position.x,
-                    y: position.y + PIECE_SPACING,
-                },
-                max: Pos2 {
-                    x: position.x + PIECE_SPACING * (BOARD_WIDTH as f32),
-                    y: position.y + PIECE_SPACING * (BOARD_HEIGHT as f32 + 1.0),
-                },
-            },
+            geometry,
             floater: Piece {
                 state: PieceState::PlayerOne,
                 board_position: position,
@@ -313,6 +366,9 @@ impl Board {
             locked: false,
             animating_floater: false,
             falling_piece: None,
+            flipping: false,
+            zoom: 1.0,
+            pan: Vec2::ZERO,
         }
     }
 
@@ -328,35 +384,99 @@ impl Board {
     ) -> impl Iterator<Item = (usize, Response)> {
         // Updating the position of a piece that is falling
         self.update_falling_piece(ctx);
+        // Updating the positions of pieces sliding back after a flip
+        self.update_flip(ctx);
+        // Updating the part of the board in view
+        self.update_magnifier(ctx, ui);
 
         // Paint columns
+        let colors = piece_colors(ctx);
+        let magnifier = self.magnifier();
+        let painter = ui.painter_at(self.view());
         for column in self.columns.iter() {
-            column.render(ui);
+            column.render(&painter, &colors, &magnifier);
         }
         // Paint floater
         if self.animating_floater && self.falling_piece.is_none() {
-            self.floater.render_piece(ui.painter());
+            self.floater.render_piece(&painter, &colors, &magnifier);
         }
 
         if self.locked || self.falling_piece.is_some() {
             // We don't want a locked board to be interactive
             Vec::new().into_iter()
         } else {
-            self.process_column_responses(ui, ctx)
+            self.process_column_responses(ui, ctx, &painter, &colors)
         }
     }
 
+    /// Returns the part of the screen the board is shown in, including the row above it,
+    /// however much it's magnified.
+    fn view(&self) -> Rect {
+        self.geometry.view()
+    }
+
+    /// Returns how the board's layout is currently shown on the screen.
+    fn magnifier(&self) -> Magnifier {
+        Magnifier::new(&self.geometry, self.zoom, self.pan)
+    }
+
+    /// Follows changes to the zoom, and lets the user zoom with + and -, and look around
+    /// the magnified board by scrolling or dragging with the middle mouse button.
+    fn update_magnifier(&mut self, ctx: &Context, ui: &Ui) {
+        if !ctx.wants_keyboard_input() {
+            let steps = ctx.input(|input| {
+                input.num_presses(Key::PlusEquals) as f32 - input.num_presses(Key::Minus) as f32
+            });
+            if steps != 0.0 {
+                set_board_zoom(ctx, board_zoom(ctx) + steps * ZOOM_STEP);
+            }
+        }
+
+        // Zooming in and out around the middle of what's in view
+        let zoom = board_zoom(ctx);
+        if zoom != self.zoom {
+            let size = self.geometry.size();
+            let middle = (self.pan + size / 2.0) / self.zoom;
+            self.pan = middle * zoom - size / 2.0;
+            self.zoom = zoom;
+        }
+
+        if self.zoom > 1.0 && ui.rect_contains_pointer(self.view()) {
+            self.pan -= ctx.input(|input| {
+                let mut delta = input.scroll_delta;
+                if input.pointer.middle_down() {
+                    delta += input.pointer.delta();
+                }
+                delta
+            });
+        }
+
+        // The magnified board should always fill the view
+        let max_pan = self.geometry.size() * (self.zoom - 1.0);
+        self.pan = self.pan.clamp(Vec2::ZERO, max_pan);
+    }
+
     /// Processes the column's responses and turns them into an iterator.
     fn process_column_responses(
         &mut self,
         ui: &mut Ui,
         ctx: &Context,
+        painter: &Painter,
+        colors: &PieceColors,
     ) -> std::vec::IntoIter<(usize, Response)> {
         let mut currently_hovering = false;
         let mut responses = Vec::new();
+        let magnifier = self.magnifier();
+        let view = self.view();
 
         for (index, column) in self.columns.iter().enumerate() {
-            let response = column.response(ui);
+            let response = column.response(ui, &magnifier, view);
+            response.widget_info(|| {
+                WidgetInfo::labeled(
+                    WidgetType::Button,
+                    ColumnLabel::new(index).accessible_name(),
+                )
+            });
 
             if response.hovered() {
                 currently_hovering = true;
@@ -364,7 +484,7 @@ impl Board {
                 // Animate the floater over the hovered column
                 self.floater.piece_position.x = ctx.animate_value_with_time(
                     self.id,
-                    self.rect.min.x + PIECE_SPACING * (index as f32),
+                    self.geometry.column_rect(index).min.x,
                     0.25,
                 );
             }
@@ -374,7 +494,7 @@ impl Board {
 
         // Paint the floater if the user is interacting with the board
         if currently_hovering {
-            self.floater.render_piece(ui.painter());
+            self.floater.render_piece(painter, colors, &magnifier);
         }
 
         responses.into_iter()
@@ -383,7 +503,7 @@ impl Board {
     /// If there is a falling piece, updates its position.
     fn update_falling_piece(&mut self, ctx: &Context) {
         if let Some([column, row]) = self.falling_piece {
-            let final_y_position = self.columns[column].get_y_position_of_piece(row as f32);
+            let final_y_position = self.geometry.piece_y(row as f32);
 
             let current_y_position = ctx.animate_value_with_time(
                 Id::new(ColumnId {
@@ -392,7 +512,7 @@ impl Board {
                 }),
                 final_y_position,
                 // + 1.0 for the fact that the piece is falling from above the board
-                FALLING_SPEED * (row as f32 + 1.0),
+                FALL_TIME_PER_ROW * (row as f32 + 1.0),
             );
 
             self.columns[column].pieces[row].piece_position.y = current_y_position;
@@ -403,6 +523,277 @@ impl Board {
         }
     }
 
+    /// If the board is being mirrored, slides each column's pieces towards their column.
+    fn update_flip(&mut self, ctx: &Context) {
+        if !self.flipping {
+            return;
+        }
+
+        let mut finished = true;
+        for (index, column) in self.columns.iter_mut().enumerate() {
+            let final_x_position = column.rect.min.x;
+            let current_x_position = ctx.animate_value_with_time(
+                Board::flip_id(self.id, index),
+                final_x_position,
+                FLIP_SPEED,
+            );
+
+            for piece in column.pieces.iter_mut() {
+                piece.piece_position.x = current_x_position;
+            }
+
+            if current_x_position != final_x_position {
+                finished = false;
+            }
+        }
+
+        self.flipping = !finished;
+    }
+
+    /// The id used to animate a column's pieces when a board is mirrored.
+    fn flip_id(board_id: Id, index: usize) -> Id {
+        board_id.with(("Flip", index))
+    }
+
+    /// Animates the board mirroring itself, with each column's pieces sliding over from
+    /// the opposite side of the board.
+    ///
+    /// The pieces end up where they started, this only shows that the position is the
+    /// mirror image of another.
+    pub fn animate_flip(&mut self, ctx: &Context) {
+        for index in 0..self.columns.len() {
+            let mirrored_x_position = self.columns[self.columns.len() - 1 - index].rect.min.x;
+            ctx.animate_value_with_time(Board::flip_id(self.id, index), mirrored_x_position, 0.0);
+        }
+
+        self.flipping = true;
+    }
+
+    /// Paints a hint in the space above each of the given columns, faded according to how
+    /// confident the hint is, from 0.0 to 1.0.
+    ///
+    /// Each hint is also described to screen readers along with its column.
+    pub fn render_hints(&self, ui: &Ui, hints: &[(usize, String, f32)]) {
+        let view = self.view();
+        let painter = ui.painter_at(view);
+        let magnifier = self.magnifier();
+        let hint_size = magnifier.length(HINT_SIZE);
+
+        for (column, text, confidence) in hints {
+            let position = magnifier.position(Pos2 {
+                x: self.geometry.column_rect(*column).center().x,
+                y: self.geometry.board_rect().min.y,
+            });
+
+            let hint_rect = Rect::from_center_size(
+                position - Vec2::new(0.0, hint_size / 2.0),
+                Vec2::new(magnifier.length(PIECE_SPACING), hint_size),
+            );
+            ui.interact(
+                hint_rect.intersect(view),
+                self.id.with(("Hint", column)),
+                Sense::hover(),
+            )
+            .widget_info(|| {
+                WidgetInfo::labeled(
+                    WidgetType::Label,
+                    format!(
+                        "{} hint: {}",
+                        ColumnLabel::new(*column).accessible_name(),
+                        text
+                    ),
+                )
+            });
+            // Even the least confident hints should stay readable
+            let alpha = 80.0 + 175.0 * confidence.clamp(0.0, 1.0);
+
+            painter.text(
+                position,
+                Align2::CENTER_BOTTOM,
+                text,
+                FontId::proportional(hint_size),
+                Color32::from_white_alpha(alpha as u8),
+            );
+        }
+    }
+
+    /// Paints a small bar above each column's move hint, in the color of the player about to
+    /// move, filled in from the left as far as their chance of winning by playing that
+    /// column goes, from 0.0 to 1.0.
+    pub fn render_win_chances(&self, ui: &Ui, chances: &[(usize, f32)]) {
+        let Some(color) = self.floater_color(ui.ctx()) else {
+            return;
+        };
+
+        let painter = ui.painter_at(self.view());
+        let magnifier = self.magnifier();
+        let width = magnifier.length(HALF_SPACING);
+        let height = magnifier.length(WIN_CHANCE_BAR_HEIGHT);
+
+        for (column, chance) in chances {
+            // Sitting on top of the hint text, so the two can be shown together
+            let position = magnifier.position(Pos2 {
+                x: self.geometry.column_rect(*column).center().x,
+                y: self.geometry.board_rect().min.y - HINT_SIZE - WIN_CHANCE_BAR_HEIGHT,
+            });
+            let bar = Rect::from_center_size(position, Vec2::new(width, height));
+
+            painter.rect_filled(bar, 0.0, color.gamma_multiply(0.25));
+            let mut filled = bar;
+            filled.set_width(width * chance.clamp(0.0, 1.0));
+            painter.rect_filled(filled, 0.0, color);
+        }
+    }
+
+    /// Paints a bar beside the board, split between the two players' colors by player
+    /// one's chance of winning, from 0.0 to 1.0, with player one's share at the bottom.
+    pub fn render_evaluation_bar(&self, ui: &Ui, player_one_chance: f32) {
+        let colors = piece_colors(ui.ctx());
+        let color = |[red, green, blue]: [u8; 3]| Color32::from_rgb(red, green, blue);
+        let chance = player_one_chance.clamp(0.0, 1.0);
+
+        // The bar stays beside the board however much it's magnified
+        let board_rect = self.geometry.board_rect();
+        let bar = Rect::from_min_max(
+            Pos2::new(self.view().max.x + EVALUATION_BAR_GAP, board_rect.min.y),
+            Pos2::new(
+                self.view().max.x + EVALUATION_BAR_GAP + EVALUATION_BAR_WIDTH,
+                board_rect.max.y,
+            ),
+        );
+        let split = bar.max.y - bar.height() * chance;
+
+        let painter = ui.painter();
+        painter.rect_filled(
+            Rect::from_min_max(bar.min, Pos2::new(bar.max.x, split)),
+            0.0,
+            color(colors.player_two),
+        );
+        painter.rect_filled(
+            Rect::from_min_max(Pos2::new(bar.min.x, split), bar.max),
+            0.0,
+            color(colors.player_one),
+        );
+
+        let description = format!("Red's chance of winning: {:.0}%", chance * 100.0);
+        let response = ui
+            .interact(bar, self.id.with("Evaluation Bar"), Sense::hover())
+            .on_hover_text(&description);
+        response.widget_info(|| WidgetInfo::labeled(WidgetType::Label, &description));
+    }
+
+    /// Paints a ring in the space above the column, in the color of the player about to
+    /// move, filled in clockwise from the top as far as progress goes, from 0.0 to 1.0.
+    ///
+    /// Used to show which column the computer is leaning towards while it thinks, and how
+    /// long it has left to think.
+    pub fn render_thinking_ring(&self, ui: &Ui, column: usize, progress: f32) {
+        let Some(color) = self.floater_color(ui.ctx()) else {
+            return;
+        };
+
+        let painter = ui.painter_at(self.view());
+        let magnifier = self.magnifier();
+        let center = magnifier.position(self.geometry.above_column(column));
+        let radius = magnifier.length(PIECE_RADIUS);
+        let width = magnifier.length(THINKING_RING_WIDTH);
+
+        painter.circle_stroke(
+            center,
+            radius,
+            Stroke::new(width, color.gamma_multiply(0.25)),
+        );
+
+        let filled_segments = (THINKING_RING_SEGMENTS as f32 * progress.clamp(0.0, 1.0)) as usize;
+        let points: Vec<Pos2> = (0..=filled_segments)
+            .map(|segment| {
+                let angle = TAU * segment as f32 / THINKING_RING_SEGMENTS as f32 - TAU / 4.0;
+                center + radius * Vec2::angled(angle)
+            })
+            .collect();
+        painter.add(Shape::line(points, Stroke::new(width, color)));
+    }
+
+    /// Rings the pieces of a connect four, given as (column, row) with rows counting up from
+    /// the bottom of the board like the engine's, e.g. from win_check::winning_line.
+    pub fn render_winning_line(&self, ui: &Ui, spaces: &[(u8, u8)]) {
+        let painter = ui.painter_at(self.view());
+        let magnifier = self.magnifier();
+        let stroke = Stroke::new(magnifier.length(WINNING_LINE_WIDTH), Color32::GOLD);
+
+        for &(column, row) in spaces {
+            let row_from_top = self.config.height - 1 - row;
+            let center = magnifier.position(
+                self.geometry
+                    .piece_center(column as usize, row_from_top as f32),
+            );
+            painter.circle_stroke(center, magnifier.length(PIECE_RADIUS), stroke);
+        }
+    }
+
+    /// Draws an anvil over the bottom piece of each of the columns, numbered from 0, to tell
+    /// anvil discs apart from the ordinary pieces of the same player.
+    pub fn render_anvils(&self, ui: &Ui, columns: &[u8]) {
+        let painter = ui.painter_at(self.view());
+        let magnifier = self.magnifier();
+        let size = magnifier.length(PIECE_RADIUS);
+
+        for &column in columns {
+            let center = magnifier.position(
+                self.geometry
+                    .piece_center(column as usize, (self.config.height - 1) as f32),
+            );
+            // A wide face on a narrow waist on a wide foot
+            for (offset, width, height) in [(-0.3, 1.1, 0.3), (0.0, 0.4, 0.3), (0.3, 0.8, 0.3)] {
+                painter.rect_filled(
+                    Rect::from_center_size(
+                        center + Vec2::new(0.0, offset * size),
+                        Vec2::new(width * size, height * size),
+                    ),
+                    0.0,
+                    ANVIL_COLOR,
+                );
+            }
+        }
+    }
+
+    /// Faintly highlights the column, in the color of the player about to move, drifting
+    /// over to it from the last column highlighted.
+    ///
+    /// Used to preview the column the computer is leaning towards while it thinks, and to
+    /// show the engine's preferred column when analysing a game.
+    pub fn render_column_preview(&self, ui: &Ui, column: usize) {
+        let Some(color) = self.floater_color(ui.ctx()) else {
+            return;
+        };
+
+        let column_rect = self.columns[column].rect;
+        let x = ui.ctx().animate_value_with_time(
+            self.id.with("Column Preview"),
+            column_rect.min.x,
+            PREVIEW_DRIFT_TIME,
+        );
+        let rect = column_rect.translate(Vec2::new(x - column_rect.min.x, 0.0));
+
+        let magnifier = self.magnifier();
+        ui.painter_at(self.view()).rect_filled(
+            magnifier.rect(rect),
+            magnifier.length(HALF_SPACING),
+            color.gamma_multiply(PREVIEW_SHADE),
+        );
+    }
+
+    /// Returns the color of the player about to move, if there is one.
+    fn floater_color(&self, ctx: &Context) -> Option<Color32> {
+        let [red, green, blue] = match self.floater.state {
+            PieceState::Empty => return None,
+            PieceState::PlayerOne => piece_colors(ctx).player_one,
+            PieceState::PlayerTwo => piece_colors(ctx).player_two,
+        };
+
+        Some(Color32::from_rgb(red, green, blue))
+    }
+
     /// Makes the board non-interactable.
     pub fn lock(&mut self) {
         self.locked = true;
@@ -420,7 +811,7 @@ impl Board {
     pub fn animate_floater(&mut self, ctx: &Context, column: usize, time: f32) -> bool {
         self.animating_floater = true;
 
-        let final_position_x = self.rect.min.x + PIECE_SPACING * (column as f32);
+        let final_position_x = self.geometry.column_rect(column).min.x;
         let current_position_x = ctx.animate_value_with_time(self.id, final_position_x, time);
 
         self.floater.piece_position.x = current_position_x;
@@ -437,18 +828,33 @@ impl Board {
     }
 
     /// Drops a piece down the given column.
-    pub fn drop_piece(&mut self, ctx: &Context, column: usize, player: PieceState) {
+    ///
+    /// Returns an error, leaving the board as it was, if another piece is still falling or
+    /// the column can't take the piece.
+    pub fn drop_piece(
+        &mut self,
+        ctx: &Context,
+        column: usize,
+        player: PieceState,
+    ) -> Result<(), String> {
         if self.falling_piece.is_some() {
-            panic!("Tried to drop a piece down the board, when one was already falling!");
+            return Err("Another piece is still falling".to_owned());
+        }
+        if matches!(player, PieceState::Empty) {
+            return Err("Only a player's piece can be dropped".to_owned());
         }
+        let Some(target) = self.columns.get(column) else {
+            return Err(format!("There's no column {}", column + 1));
+        };
 
-        let height = self.columns[column].height;
+        let height = target.height;
+        let rows = target.pieces.len();
 
-        if height >= (BOARD_HEIGHT as usize) {
-            panic!("Trying to drop a piece down a full column: {}", column);
+        if height >= rows {
+            return Err(format!("Column {} is full", ColumnLabel::new(column)));
         }
 
-        let row_index = (BOARD_HEIGHT as usize) - 1 - height;
+        let row_index = rows - 1 - height;
         self.columns[column].pieces[row_index].state = player;
         self.columns[column].height += 1;
 
@@ -461,20 +867,107 @@ impl Board {
                 index: column,
             }),
             // -1.0 due to the fact that the piece is falling from above the board
-            self.columns[column].get_y_position_of_piece(-1.0),
+            self.geometry.piece_y(-1.0),
             0.0,
         );
 
         // The floater represents the current player, so this indicates that it's
         // the next player's move
         self.floater.state = player.reverse();
+        Ok(())
     }
 
-    /// Returns a vector representing the width and height of a board.
-    pub fn board_size() -> Vec2 {
-        Vec2 {
-            x: PIECE_SPACING * (BOARD_WIDTH as f32),
-            y: PIECE_SPACING * (BOARD_HEIGHT as f32 + 1.0),
+    /// Returns whether a piece could be dropped down the column by clicking it right now.
+    pub fn accepts_drop(&self, column: usize) -> bool {
+        !self.locked
+            && self.falling_piece.is_none()
+            && self.columns[column].height < self.columns[column].pieces.len()
+    }
+
+    /// Returns whether a dropped piece is still falling into place.
+    pub fn is_piece_falling(&self) -> bool {
+        self.falling_piece.is_some()
+    }
+
+    /// Replaces the pieces on the board with the given position, without animating them.
+    ///
+    /// The position is given as array[row][col], e.g. from GameManager::get_position, or
+    /// GameManager::get_rows for boards that aren't the standard size. The floater will be
+    /// set to next_player.
+    pub fn set_position<R: AsRef<[u8]>>(
+        &mut self,
+        position: impl AsRef<[R]>,
+        next_player: PieceState,
+    ) {
+        let position = position.as_ref();
+
+        self.falling_piece = None;
+        self.flipping = false;
+
+        for (col, column) in self.columns.iter_mut().enumerate() {
+            column.height = 0;
+
+            for (row, piece) in column.pieces.iter_mut().enumerate() {
+                let value = position[row].as_ref()[col];
+                piece.state = match value {
+                    0 => PieceState::Empty,
+                    1 => PieceState::PlayerOne,
+                    _ => PieceState::PlayerTwo,
+                };
+                piece.piece_position = piece.board_position;
+
+                if value != 0 {
+                    column.height += 1;
+                }
+            }
+        }
+
+        self.floater.state = next_player;
+    }
+
+    /// Returns the pieces on a standard board as array[row][col], the inverse of
+    /// set_position.
+    ///
+    /// Panics if the board isn't the standard size, see get_rows for other boards.
+    pub fn get_position(&self) -> [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize] {
+        let mut position = [[0; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+
+        for (row, pieces) in self.get_rows().into_iter().enumerate() {
+            position[row].copy_from_slice(&pieces);
         }
+
+        position
+    }
+
+    /// Returns the pieces on a board of any size as rows[row][col].
+    pub fn get_rows(&self) -> Vec<Vec<u8>> {
+        (0..self.config.height as usize)
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .map(|column| match column.pieces[row].state {
+                        PieceState::Empty => 0,
+                        PieceState::PlayerOne => 1,
+                        PieceState::PlayerTwo => 2,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the dimensions of the board.
+    pub fn config(&self) -> BoardConfig {
+        self.config
+    }
+
+    /// Returns a vector representing the width and height of a standard board.
+    pub fn board_size() -> Vec2 {
+        Board::board_size_for(BoardConfig::STANDARD)
+    }
+
+    /// Returns a vector representing the width and height of a board with the given
+    /// dimensions, including the row above it.
+    pub fn board_size_for(config: BoardConfig) -> Vec2 {
+        BoardGeometry::new(Pos2::ZERO, config).size()
     }
 }
@@ -0,0 +1,221 @@
+use egui::{Pos2, Rect, Vec2};
+
+use crate::game_engine::board_config::BoardConfig;
+
+/// The space between pieces.
+pub const PIECE_SPACING: f32 = 90.0;
+/// Half of the piece spacing, used for centering things.
+pub const HALF_SPACING: f32 = PIECE_SPACING / 2.0;
+
+/// Where everything on a board goes, before it's magnified.
+///
+/// The board is laid out from its top left corner, with a row's worth of space above it for
+/// the floater and anything shown over the columns. Rows are counted from the top of the
+/// board down, and can be fractional or negative for pieces partway through falling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardGeometry {
+    /// The top left corner of the board, including the row above it.
+    origin: Pos2,
+    config: BoardConfig,
+}
+
+impl BoardGeometry {
+    /// Lays out a board with the given dimensions from its top left corner.
+    pub fn new(origin: Pos2, config: BoardConfig) -> BoardGeometry {
+        BoardGeometry { origin, config }
+    }
+
+    /// Returns the top left corner of the board, including the row above it.
+    pub fn origin(&self) -> Pos2 {
+        self.origin
+    }
+
+    /// Returns the width and height of the board, including the row above it.
+    pub fn size(&self) -> Vec2 {
+        Vec2 {
+            x: PIECE_SPACING * (self.config.width as f32),
+            y: PIECE_SPACING * (self.config.height as f32 + 1.0),
+        }
+    }
+
+    /// Returns the space the board takes up, including the row above it.
+    pub fn view(&self) -> Rect {
+        Rect::from_min_size(self.origin, self.size())
+    }
+
+    /// Returns the space the board's pieces take up, leaving out the row above it.
+    pub fn board_rect(&self) -> Rect {
+        Rect::from_min_max(
+            self.origin + Vec2::new(0.0, PIECE_SPACING),
+            self.origin + self.size(),
+        )
+    }
+
+    /// Returns the space a column takes up, leaving out the row above it.
+    pub fn column_rect(&self, column: usize) -> Rect {
+        let board_rect = self.board_rect();
+
+        Rect::from_min_size(
+            Pos2 {
+                x: board_rect.min.x + PIECE_SPACING * (column as f32),
+                y: board_rect.min.y,
+            },
+            Vec2::new(PIECE_SPACING, board_rect.height()),
+        )
+    }
+
+    /// Returns the y position of the top of a piece in the given row. Row -1.0 is the row
+    /// above the board.
+    pub fn piece_y(&self, row: f32) -> f32 {
+        self.board_rect().min.y + PIECE_SPACING * row
+    }
+
+    /// Returns the top left corner of a piece in the given column and row.
+    pub fn piece_position(&self, column: usize, row: f32) -> Pos2 {
+        Pos2 {
+            x: self.column_rect(column).min.x,
+            y: self.piece_y(row),
+        }
+    }
+
+    /// Returns the center of a piece in the given column and row.
+    pub fn piece_center(&self, column: usize, row: f32) -> Pos2 {
+        self.piece_position(column, row) + Vec2::splat(HALF_SPACING)
+    }
+
+    /// Returns the center of the space above the column.
+    pub fn above_column(&self, column: usize) -> Pos2 {
+        self.piece_center(column, -1.0)
+    }
+
+    /// Returns the column under the point, counting the space above the board, if there is
+    /// one.
+    pub fn column_at(&self, point: Pos2) -> Option<usize> {
+        if !self.view().contains(point) {
+            return None;
+        }
+
+        let column = ((point.x - self.origin.x) / PIECE_SPACING) as usize;
+        Some(column.min(self.config.width as usize - 1))
+    }
+}
+
+/// Maps the board's layout onto the screen, magnifying it from its top left corner and then
+/// panning to the part of it being looked at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Magnifier {
+    /// The top left corner of the board, including the row above it.
+    origin: Pos2,
+    zoom: f32,
+    /// How far the magnified board has been moved up and to the left.
+    pan: Vec2,
+}
+
+impl Magnifier {
+    /// Magnifies the board's layout by zoom, and then pans it up and to the left.
+    pub fn new(geometry: &BoardGeometry, zoom: f32, pan: Vec2) -> Magnifier {
+        Magnifier {
+            origin: geometry.origin(),
+            zoom,
+            pan,
+        }
+    }
+
+    /// Returns where a point of the board's layout is shown on the screen.
+    pub fn position(&self, position: Pos2) -> Pos2 {
+        self.origin + (position - self.origin) * self.zoom - self.pan
+    }
+
+    /// Returns the point of the board's layout shown at a point on the screen, the inverse
+    /// of position.
+    pub fn layout_position(&self, position: Pos2) -> Pos2 {
+        self.origin + (position - self.origin + self.pan) / self.zoom
+    }
+
+    /// Returns how long a length in the board's layout is shown on the screen.
+    pub fn length(&self, length: f32) -> f32 {
+        length * self.zoom
+    }
+
+    /// Returns where a rectangle of the board's layout is shown on the screen.
+    pub fn rect(&self, rect: Rect) -> Rect {
+        Rect::from_min_max(self.position(rect.min), self.position(rect.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Pos2, Rect, Vec2};
+
+    use crate::{
+        game_engine::board_config::BoardConfig,
+        user_interface::board_geometry::{BoardGeometry, Magnifier, PIECE_SPACING},
+    };
+
+    fn standard() -> BoardGeometry {
+        BoardGeometry::new(Pos2::new(10.0, 20.0), BoardConfig::STANDARD)
+    }
+
+    #[test]
+    fn layout() {
+        let geometry = standard();
+        assert_eq!(geometry.size(), Vec2::new(630.0, 630.0));
+        assert_eq!(
+            geometry.board_rect(),
+            Rect::from_min_max(Pos2::new(10.0, 110.0), Pos2::new(640.0, 650.0))
+        );
+        assert_eq!(
+            geometry.column_rect(2),
+            Rect::from_min_max(Pos2::new(190.0, 110.0), Pos2::new(280.0, 650.0))
+        );
+
+        assert_eq!(geometry.piece_y(0.0), 110.0);
+        assert_eq!(geometry.piece_y(-1.0), 20.0);
+        assert_eq!(geometry.piece_y(2.5), 110.0 + 2.5 * PIECE_SPACING);
+        assert_eq!(geometry.piece_position(6, 5.0), Pos2::new(550.0, 560.0));
+        assert_eq!(geometry.piece_center(0, 0.0), Pos2::new(55.0, 155.0));
+        assert_eq!(geometry.above_column(3), Pos2::new(325.0, 65.0));
+
+        let larger = BoardGeometry::new(Pos2::ZERO, BoardConfig::VARIANTS[3]);
+        assert_eq!(larger.size(), Vec2::new(900.0, 810.0));
+        assert_eq!(larger.column_rect(9).max, Pos2::new(900.0, 810.0));
+    }
+
+    #[test]
+    fn hit_testing() {
+        let geometry = standard();
+        assert_eq!(geometry.column_at(Pos2::new(10.0, 20.0)), Some(0));
+        assert_eq!(geometry.column_at(Pos2::new(325.0, 400.0)), Some(3));
+        // The right and bottom edges still belong to the board
+        assert_eq!(geometry.column_at(Pos2::new(640.0, 650.0)), Some(6));
+
+        assert_eq!(geometry.column_at(Pos2::new(9.0, 400.0)), None);
+        assert_eq!(geometry.column_at(Pos2::new(325.0, 651.0)), None);
+    }
+
+    #[test]
+    fn magnifying() {
+        let geometry = standard();
+        let magnifier = Magnifier::new(&geometry, 2.0, Vec2::new(100.0, 50.0));
+
+        assert_eq!(
+            magnifier.position(Pos2::new(10.0, 20.0)),
+            Pos2::new(-90.0, -30.0)
+        );
+        assert_eq!(
+            magnifier.position(Pos2::new(110.0, 120.0)),
+            Pos2::new(110.0, 170.0)
+        );
+        assert_eq!(magnifier.length(PIECE_SPACING), 180.0);
+
+        let point = Pos2::new(300.0, 200.0);
+        assert_eq!(magnifier.layout_position(magnifier.position(point)), point);
+        // Clicks on the magnified board land on the column shown under them
+        let column = geometry.column_at(magnifier.layout_position(Pos2::new(110.0, 170.0)));
+        assert_eq!(column, Some(1));
+
+        let unmagnified = Magnifier::new(&geometry, 1.0, Vec2::ZERO);
+        let rect = geometry.column_rect(4);
+        assert_eq!(unmagnified.rect(rect), rect);
+    }
+}
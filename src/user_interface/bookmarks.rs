@@ -0,0 +1,135 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use egui::{CollapsingHeader, ScrollArea, TextEdit, Ui};
+
+use crate::{
+    game_engine::game_manager::GameManager,
+    profile::{load_bookmarks, save_bookmarks, Bookmark},
+    user_interface::settings::ScoreDisplay,
+};
+
+/// Lets the user bookmark positions with a note, and jump back to them later.
+///
+/// Bookmarks are saved to the profile as soon as they change.
+pub struct Bookmarks {
+    directory: PathBuf,
+    bookmarks: Vec<Bookmark>,
+    /// The note for the next bookmark.
+    note: String,
+    /// Why the bookmarks couldn't be loaded or saved.
+    error: Option<String>,
+}
+
+impl Bookmarks {
+    /// Creates a Bookmarks panel, loading the bookmarks saved in the given profile directory.
+    pub fn new(directory: PathBuf) -> Bookmarks {
+        let (bookmarks, error) = match load_bookmarks(&directory) {
+            Ok(bookmarks) => (bookmarks, None),
+            Err(error) => (Vec::new(), Some(error.to_string())),
+        };
+
+        Bookmarks {
+            directory,
+            bookmarks,
+            note: String::new(),
+            error,
+        }
+    }
+
+    /// Bookmarks the position after the given moves, along with the engine's current
+    /// evaluation of its best move.
+    fn add(&mut self, moves: &str, move_scores: &HashMap<u8, isize>) {
+        let key = match GameManager::from_moves(moves) {
+            Ok(manager) => manager.position_key(),
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        };
+
+        self.bookmarks.push(Bookmark {
+            key,
+            moves: moves.to_owned(),
+            eval: move_scores.values().max().copied(),
+            note: std::mem::take(&mut self.note),
+        });
+        self.save();
+    }
+
+    /// Saves the bookmarks to the profile.
+    fn save(&mut self) {
+        self.error = save_bookmarks(&self.directory, &self.bookmarks)
+            .err()
+            .map(|error| error.to_string());
+    }
+
+    /// Renders the bookmark button and the saved bookmarks.
+    ///
+    /// current_moves are the moves leading to the position being analysed, and move_scores
+    /// are the engine's scores for it, shown as score_display chooses. Returns the moves of a
+    /// bookmark if the user chooses to jump to it.
+    pub fn render(
+        &mut self,
+        ui: &mut Ui,
+        current_moves: &str,
+        move_scores: &HashMap<u8, isize>,
+        score_display: ScoreDisplay,
+    ) -> Option<String> {
+        let mut chosen_bookmark = None;
+
+        CollapsingHeader::new("Bookmarks").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut self.note)
+                        .hint_text("Note")
+                        .desired_width(120.0),
+                );
+                if ui.button("Bookmark").clicked() {
+                    self.add(current_moves, move_scores);
+                }
+            });
+
+            if let Some(error) = &self.error {
+                ui.label(format!("Couldn't save the bookmarks: {}", error));
+            }
+
+            let mut removed = None;
+            ScrollArea::vertical()
+                .id_source("Bookmarks")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (index, bookmark) in self.bookmarks.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let name = if bookmark.note.is_empty() {
+                                format!("Move {}", bookmark.moves.len())
+                            } else {
+                                bookmark.note.clone()
+                            };
+                            let details = format!(
+                                "Moves: {}\nEval when saved: {}",
+                                bookmark.moves,
+                                bookmark
+                                    .eval
+                                    .map(|eval| score_display.format(eval))
+                                    .unwrap_or_else(|| "unknown".to_owned())
+                            );
+
+                            if ui.link(name).on_hover_text(details).clicked() {
+                                chosen_bookmark = Some(bookmark.moves.clone());
+                            }
+                            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                                removed = Some(index);
+                            }
+                        });
+                    }
+                });
+
+            if let Some(index) = removed {
+                self.bookmarks.remove(index);
+                self.save();
+            }
+        });
+
+        chosen_bookmark
+    }
+}
@@ -0,0 +1,220 @@
+//! Challenges are positions shared along with the settings to play them under, so someone
+//! can be asked "can you win this?".
+//!
+//! A challenge is written as a short code that's safe to put in a URL, made up of fields
+//! separated by dots, e.g. `c1.4453.a30.h`. The fields are the format version, the moves
+//! leading to the position, the house rules (`a` for announce threats, the move time limit
+//! in seconds and `k` followed by a time control like `3-2`, if any) and the computer's
+//! difficulty. Links are the code behind
+//! CHALLENGE_LINK_PREFIX, but a code is also found at the end of any URL's path or in its
+//! `challenge` query parameter.
+
+use crate::{
+    game_engine::{
+        clock::TimeControl,
+        game_manager::{GameManager, GameOver},
+        house_rules::HouseRules,
+    },
+    user_interface::settings::Difficulty,
+};
+
+/// The start of a link to a challenge, which the app is launched with.
+pub const CHALLENGE_LINK_PREFIX: &str = "connect4://challenge/";
+
+/// The first field of every challenge code, so the format can change later.
+const CHALLENGE_VERSION: &str = "c1";
+
+/// A position to win from, with the settings the computer plays under.
+///
+/// Whoever accepts the challenge plays the side that's about to move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    /// The moves leading to the position, as columns numbered from 1.
+    pub moves: String,
+    pub house_rules: HouseRules,
+    pub difficulty: Difficulty,
+}
+
+impl Challenge {
+    /// Writes the challenge as a code, e.g. `c1.4453.a30.h`.
+    pub fn to_code(&self) -> String {
+        let mut rules = String::new();
+        if self.house_rules.announce_threats {
+            rules.push('a');
+        }
+        if let Some(seconds) = self.house_rules.move_time_limit {
+            rules.push_str(&seconds.to_string());
+        }
+        // A plus sign would be read as a space in a link's query
+        if let Some(time_control) = self.house_rules.time_control {
+            rules.push_str(&format!(
+                "k{}-{}",
+                time_control.minutes, time_control.increment
+            ));
+        }
+
+        let difficulty = match self.difficulty {
+            Difficulty::Easy => "e",
+            Difficulty::Medium => "m",
+            Difficulty::Hard => "h",
+            Difficulty::HumanLike => "u",
+        };
+
+        format!(
+            "{}.{}.{}.{}",
+            CHALLENGE_VERSION, self.moves, rules, difficulty
+        )
+    }
+
+    /// Writes the challenge as a link the app can be launched with.
+    pub fn to_link(&self) -> String {
+        format!("{}{}", CHALLENGE_LINK_PREFIX, self.to_code())
+    }
+
+    /// Parses a challenge from a code or a link containing one.
+    ///
+    /// The moves have to be legal and leave a game that isn't over yet.
+    pub fn parse(text: &str) -> Result<Challenge, String> {
+        let code = find_code(text.trim());
+
+        let [CHALLENGE_VERSION, moves, rules, difficulty] = code.split('.').collect::<Vec<_>>()[..]
+        else {
+            return Err(format!("'{}' isn't a challenge", text));
+        };
+
+        if GameManager::from_moves(moves)?.is_game_over() != GameOver::NoWin {
+            return Err("The game is already over in this challenge".to_owned());
+        }
+
+        let malformed = || format!("'{}' aren't house rules", rules);
+        let announce_threats = rules.starts_with('a');
+        let (seconds, time_control) = rules
+            .trim_start_matches('a')
+            .split_once('k')
+            .unwrap_or((rules.trim_start_matches('a'), ""));
+        let house_rules = HouseRules {
+            announce_threats,
+            move_time_limit: if seconds.is_empty() {
+                None
+            } else {
+                Some(seconds.parse().map_err(|_| malformed())?)
+            },
+            time_control: if time_control.is_empty() {
+                None
+            } else {
+                Some(
+                    TimeControl::parse(&time_control.replacen('-', "+", 1))
+                        .map_err(|_| malformed())?,
+                )
+            },
+        };
+
+        let difficulty = match difficulty {
+            "e" => Difficulty::Easy,
+            "m" => Difficulty::Medium,
+            "h" => Difficulty::Hard,
+            "u" => Difficulty::HumanLike,
+            _ => return Err(format!("'{}' isn't a difficulty", difficulty)),
+        };
+
+        Ok(Challenge {
+            moves: moves.to_owned(),
+            house_rules,
+            difficulty,
+        })
+    }
+
+    /// Returns whether the text looks like it's meant to be a challenge, rather than
+    /// e.g. a command line argument.
+    pub fn is_challenge(text: &str) -> bool {
+        find_code(text.trim()).starts_with(CHALLENGE_VERSION)
+    }
+}
+
+/// Finds the code within a link, or returns the text as it is if it isn't a link.
+fn find_code(text: &str) -> &str {
+    if let Some((_, query)) = text.split_once('?') {
+        if let Some(code) = query
+            .split('&')
+            .find_map(|parameter| parameter.strip_prefix("challenge="))
+        {
+            return code;
+        }
+    }
+
+    let path = text.split(['?', '#']).next().unwrap_or(text);
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game_engine::{clock::TimeControl, house_rules::HouseRules},
+        user_interface::{challenge::Challenge, settings::Difficulty},
+    };
+
+    #[test]
+    fn challenges_round_trip() {
+        let challenge = Challenge {
+            moves: "4453".to_owned(),
+            house_rules: HouseRules {
+                announce_threats: true,
+                move_time_limit: Some(30),
+                time_control: None,
+            },
+            difficulty: Difficulty::Hard,
+        };
+        assert_eq!(challenge.to_code(), "c1.4453.a30.h");
+        assert_eq!(challenge.to_link(), "connect4://challenge/c1.4453.a30.h");
+
+        for text in [
+            challenge.to_code(),
+            challenge.to_link(),
+            "https://example.com/play?mode=web&challenge=c1.4453.a30.h".to_owned(),
+            "https://example.com/challenge/c1.4453.a30.h/".to_owned(),
+        ] {
+            assert!(Challenge::is_challenge(&text));
+            assert_eq!(Challenge::parse(&text), Ok(challenge.clone()));
+        }
+
+        let standard = Challenge {
+            moves: String::new(),
+            house_rules: HouseRules::default(),
+            difficulty: Difficulty::HumanLike,
+        };
+        assert_eq!(standard.to_code(), "c1...u");
+        assert_eq!(Challenge::parse(&standard.to_code()), Ok(standard));
+
+        let blitz = Challenge {
+            moves: "4".to_owned(),
+            house_rules: HouseRules {
+                announce_threats: false,
+                move_time_limit: Some(20),
+                time_control: Some(TimeControl {
+                    minutes: 3,
+                    increment: 2,
+                }),
+            },
+            difficulty: Difficulty::Medium,
+        };
+        assert_eq!(blitz.to_code(), "c1.4.20k3-2.m");
+        assert_eq!(Challenge::parse(&blitz.to_code()), Ok(blitz));
+    }
+
+    #[test]
+    fn bad_challenges() {
+        assert!(!Challenge::is_challenge("annotate"));
+        assert!(Challenge::parse("c1.4453.a30").is_err());
+        assert!(Challenge::parse("c2.4453..h").is_err());
+        assert!(Challenge::parse("c1.8..h").is_err());
+        assert!(Challenge::parse("c1.4453.x.h").is_err());
+        assert!(Challenge::parse("c1.4453.k3-x.h").is_err());
+        assert!(Challenge::parse("c1.4453..z").is_err());
+
+        // Red has already won
+        assert!(Challenge::parse("c1.1212121..h").is_err());
+    }
+}
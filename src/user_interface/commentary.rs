@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use egui::{CollapsingHeader, ScrollArea, Ui};
+
+use crate::{
+    consts::BOARD_WIDTH,
+    game_engine::{
+        column_label::ColumnLabel,
+        game_manager::GameManager,
+        mate_distance::{is_forced_loss, is_forced_win},
+    },
+    user_interface::{
+        engine_interface::{GameOver, MoveEvent},
+        settings::ScoreDisplay,
+    },
+};
+
+/// How much a move's score can fall short of the best move's score before it's
+/// called out as an inaccuracy.
+const INACCURACY_THRESHOLD: isize = 100;
+
+/// A running textual commentary on the game, generated from the engine's evaluations
+/// and the events it reports.
+#[derive(Default)]
+pub struct Commentary {
+    /// Each remark, along with the number of the move it was made about.
+    entries: Vec<(usize, String)>,
+}
+
+impl Commentary {
+    /// Creates a new, empty Commentary.
+    pub fn new() -> Commentary {
+        Default::default()
+    }
+
+    /// Removes all of the commentary, used when a new position is set up.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns all of the remarks made about a move, joined together, if there are any.
+    ///
+    /// Moves are numbered from 1.
+    pub fn comment_for(&self, move_number: usize) -> Option<String> {
+        let remarks = self
+            .entries
+            .iter()
+            .filter(|(number, _)| *number == move_number)
+            .map(|(_, remark)| remark.as_str())
+            .collect::<Vec<&str>>();
+
+        if remarks.is_empty() {
+            None
+        } else {
+            Some(remarks.join(" "))
+        }
+    }
+
+    /// Comments on a move that has just been made.
+    ///
+    /// previous_moves is the move string leading up to the move, move_scores are the
+    /// engine's scores for the position the move was made in, and events are the ones the
+    /// engine reported for the move.
+    pub fn move_made(
+        &mut self,
+        previous_moves: &str,
+        column: usize,
+        move_scores: &HashMap<u8, isize>,
+        game_state: GameOver,
+        events: &[MoveEvent],
+    ) {
+        let before = match GameManager::from_moves(previous_moves) {
+            Ok(manager) => manager,
+            Err(_) => return,
+        };
+        let mover = before.get_turn();
+        let name = player_name(mover);
+        let move_number = move_number(previous_moves);
+
+        let mut remarks = Vec::new();
+
+        match game_state {
+            GameOver::OneWins | GameOver::TwoWins => {
+                remarks.push(format!(
+                    "{} connects four on column {}!",
+                    name,
+                    ColumnLabel::new(column)
+                ));
+            }
+            GameOver::Tie => remarks.push("The board is full, it's a draw.".to_owned()),
+            GameOver::NoWin => {
+                // Threats are the most notable thing about a move, so we only fall back to
+                //  the evaluation and then the shape of the move if there aren't any
+                remarks.extend(events.iter().map(describe_event));
+
+                if remarks.is_empty() {
+                    remarks.extend(describe_eval_swing(move_scores, column, name));
+                }
+                if remarks.is_empty() {
+                    remarks.extend(describe_shape(&before, column, mover));
+                }
+            }
+        }
+
+        for remark in remarks {
+            self.entries.push((move_number, remark));
+        }
+    }
+
+    /// Comments on what the engine has found in the position since the last move was made,
+    /// e.g. a forced win. Nothing is said before the first move.
+    ///
+    /// moves is the move string leading up to the position.
+    pub fn events_found(&mut self, moves: &str, events: &[MoveEvent]) {
+        let last_move = move_number(moves) - 1;
+        if last_move == 0 {
+            return;
+        }
+
+        for event in events {
+            self.entries.push((last_move, describe_event(event)));
+        }
+    }
+
+    /// Explains why the computer chose its move, from the engine's scores for the position
+    /// it was made in, shown as score_display chooses.
+    pub fn explain_move(
+        &mut self,
+        previous_moves: &str,
+        column: usize,
+        move_scores: &HashMap<u8, isize>,
+        score_display: ScoreDisplay,
+    ) {
+        let Ok(before) = GameManager::from_moves(previous_moves) else {
+            return;
+        };
+        let Some(chosen_score) = move_scores.get(&(column as u8)) else {
+            return;
+        };
+        let name = player_name(before.get_turn());
+        let label = ColumnLabel::new(column);
+
+        let runner_up = move_scores
+            .iter()
+            .filter(|(other, _)| **other as usize != column)
+            .max_by_key(|(_, score)| **score);
+        let remark = match runner_up {
+            None => format!("{} plays column {}, the only move left.", name, label),
+            Some((other, other_score)) if other_score > chosen_score => format!(
+                "{} plays column {} ({}), though column {} ({}) was stronger.",
+                name,
+                label,
+                score_display.format(*chosen_score),
+                ColumnLabel::new(*other),
+                score_display.format(*other_score)
+            ),
+            Some((other, other_score)) => format!(
+                "{} plays column {}, its best move ({}), ahead of column {} ({}).",
+                name,
+                label,
+                score_display.format(*chosen_score),
+                ColumnLabel::new(*other),
+                score_display.format(*other_score)
+            ),
+        };
+
+        self.entries.push((move_number(previous_moves), remark));
+    }
+
+    /// Renders the commentary in a collapsible feed, with the newest entries at the bottom.
+    pub fn render(&self, ui: &mut Ui) {
+        CollapsingHeader::new("Commentary")
+            .default_open(true)
+            .show(ui, |ui| {
+                ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for (move_number, remark) in self.entries.iter() {
+                            ui.label(format!("{}. {}", move_number, remark));
+                        }
+                    });
+            });
+    }
+}
+
+/// Returns the number of the move made after the previous moves, counting from 1.
+fn move_number(previous_moves: &str) -> usize {
+    previous_moves
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count()
+        + 1
+}
+
+/// Returns the name used to refer to a player in the commentary.
+fn player_name(player: bool) -> &'static str {
+    match player {
+        false => "Red",
+        true => "Blue",
+    }
+}
+
+/// Describes one of the events the engine reported.
+fn describe_event(event: &MoveEvent) -> String {
+    match event {
+        MoveEvent::WinMissed { player, column } => format!(
+            "{} misses a win on column {}!",
+            player_name(*player),
+            ColumnLabel::new(*column)
+        ),
+        MoveEvent::ThreatBlocked { player, column } => format!(
+            "{} blocks column {}.",
+            player_name(*player),
+            ColumnLabel::new(*column)
+        ),
+        MoveEvent::BlockMissed { player, column } => format!(
+            "{} misses the block on column {}!",
+            player_name(*player),
+            ColumnLabel::new(*column)
+        ),
+        MoveEvent::ThreatCreated { player, columns } => {
+            let columns: Vec<String> = columns
+                .iter()
+                .map(|col| ColumnLabel::new(*col).to_string())
+                .collect();
+
+            match columns.len() {
+                1 => format!(
+                    "{} threatens to win on column {}.",
+                    player_name(*player),
+                    columns[0]
+                ),
+                _ => format!(
+                    "{} sets up a double threat on columns {}!",
+                    player_name(*player),
+                    columns.join(" and ")
+                ),
+            }
+        }
+        MoveEvent::ZugzwangReached { player } => format!(
+            "{} is in zugzwang, every move hands {} a win!",
+            player_name(*player),
+            player_name(!*player)
+        ),
+        MoveEvent::ForcedWinDetected { player } => {
+            format!(
+                "The engine has found a forced win for {}.",
+                player_name(*player)
+            )
+        }
+    }
+}
+
+/// Describes how the move compared to the engine's evaluation of the alternatives.
+fn describe_eval_swing(
+    move_scores: &HashMap<u8, isize>,
+    column: usize,
+    name: &str,
+) -> Option<String> {
+    let chosen_score = *move_scores.get(&(column as u8))?;
+    let best_score = *move_scores.values().max()?;
+
+    if is_forced_win(best_score) && !is_forced_win(chosen_score) {
+        Some(format!("{} lets a forced win slip away.", name))
+    } else if is_forced_loss(chosen_score) && !is_forced_loss(best_score) {
+        Some(format!("{} walks into a forced loss.", name))
+    } else if best_score.saturating_sub(chosen_score) > INACCURACY_THRESHOLD {
+        Some(format!("{} plays an inaccuracy.", name))
+    } else {
+        None
+    }
+}
+
+/// Describes the shape a quiet move builds on the board.
+fn describe_shape(before: &GameManager, column: usize, mover: bool) -> Option<String> {
+    let name = player_name(mover);
+    let position = before.get_position();
+    let mover_piece = mover as u8 + 1;
+
+    // The row the new piece lands in, counting down from the top of the board
+    let landing_row = position.iter().rposition(|row| row[column] == 0)?;
+    let piece_below = position.get(landing_row + 1).map(|row| row[column]);
+
+    if column != BOARD_WIDTH as usize / 2 {
+        None
+    } else if piece_below == Some(mover_piece) {
+        Some(format!("{} builds a central stack.", name))
+    } else {
+        Some(format!("{} takes the center column.", name))
+    }
+}
@@ -1,36 +1,103 @@
 use std::{
+    cell::{Cell, RefCell},
     collections::HashMap,
-    sync::mpsc::{Receiver, Sender},
-    time::Instant,
+    env,
+    io::{self, BufRead, BufReader, Write},
+    panic::{self, AssertUnwindSafe},
+    process::{Command, Stdio},
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant},
 };
 
-use egui::Context;
-
-pub use crate::game_engine::game_manager::{GameOver, TreeSize};
+pub use crate::game_engine::game_manager::{
+    EngineAlgorithm, GameOver, Hint, MoveEvent, PowerUpKind, ScoreConfidence, SearchStats,
+    TreeSize, VariantRules,
+};
 use crate::{
-    game_engine::game_manager::GameManager,
+    game_engine::{board_config::BoardConfig, game_manager::GameManager, strength::FULL_STRENGTH},
     log::{log_message, LogType},
+    user_interface::engine_protocol::{
+        parse_engine_message, parse_ui_message, write_engine_message, write_ui_message,
+    },
 };
 
+/// The command line argument that makes the program run as an engine process.
+pub const ENGINE_SUBCOMMAND: &str = "engine";
+
 /// Stores what the maximum amount of memory we will allow to be used by the engine.
 const MAX_MEMORY_USAGE: usize = 256 * 1024 * 1024;
 /// Stores how many nodes we will generate at once. Higher numbers are more
 /// performant, but makes the interface less responsive.
 const GENERATED_NODES_PER_ITERATION: usize = 128 * 1024;
+/// Stores how many Monte Carlo rollouts we will play out at once, taking about as long as
+/// generating a batch of nodes.
+const ROLLOUTS_PER_ITERATION: usize = 4 * 1024;
+/// How long the UI waits for the engine to respond to a move or update request before
+/// deciding the engine has stalled.
+pub const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long the engine keeps growing its tree without hearing from the UI before it goes
+/// idle, when power saving.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long the engine rests between each batch of generated nodes, when power saving.
+const POWER_SAVING_PAUSE: Duration = Duration::from_millis(100);
+/// The most moves of the expected line of play sent along with each update.
+const PRINCIPAL_VARIATION_LENGTH: usize = 8;
+/// How long the engine searches a position it's asked to analyze before sending its first
+/// result, after which it carries on searching as usual.
+const ANALYSIS_TIME: Duration = Duration::from_millis(250);
 
 /// Messages that the engine can send to the UI.
 #[derive(Debug)]
 pub enum EngineMessage {
     MoveReceipt {
+        column: usize,
         game_state: GameOver,
         move_scores: HashMap<u8, isize>,
+        move_confidences: HashMap<u8, ScoreConfidence>,
         tree_size: TreeSize,
+        /// Whether the move led to a transposition the engine had stored as a mirror image,
+        /// flipping the orientation of its analysis.
+        orientation_flipped: bool,
+        /// What the move did, e.g. the threats it created or blocked.
+        events: Vec<MoveEvent>,
+        /// The spaces of the connect four that ended the game, as (column, row) with rows
+        /// counting up from the bottom, or empty if the move didn't make one.
+        winning_line: Vec<(u8, u8)>,
     },
     InvalidMove(String),
+    /// The engine's backend stopped working, e.g. because its process crashed.
+    BackendFailed(String),
+    /// The engine panicked, with the panic's message. It's sent by the engine's supervisor
+    /// as the engine's last message.
+    EngineCrashed(String),
     Update {
         move_scores: HashMap<u8, isize>,
+        move_confidences: HashMap<u8, ScoreConfidence>,
         tree_size: TreeSize,
+        /// The scores of spending the current player's power-up in each column, on the same
+        /// scale as move_scores, or empty if they have none to spend.
+        power_up_scores: HashMap<u8, isize>,
+        /// The line of play the engine expects from the current position, as columns.
+        principal_variation: Vec<u8>,
+        /// What the engine has found since its last message, e.g. a forced win.
+        events: Vec<MoveEvent>,
+        /// How the search of the current position has gone, for debugging.
+        search_stats: SearchStats,
     },
+    /// The engine's first look at a position it was asked to analyze.
+    AnalysisResult {
+        /// The moves leading to the position analyzed, so that results for a position the
+        /// UI has since moved on from can be told apart.
+        moves: String,
+        move_scores: HashMap<u8, isize>,
+        move_confidences: HashMap<u8, ScoreConfidence>,
+        tree_size: TreeSize,
+        /// The column the engine prefers, if there's any move to make.
+        best_column: Option<u8>,
+    },
+    /// The move suggested in reply to RequestHint, or None if the game is over.
+    Hint(Option<Hint>),
 }
 
 /// Messages that the UI can send to the engine.
@@ -38,7 +105,305 @@ pub enum EngineMessage {
 pub enum UIMessage {
     MakeMove(usize),
     ResetGame,
+    LoadPosition(String),
     RequestUpdate,
+    /// Sets whether the engine should throttle itself to save energy.
+    SetPowerSaving(bool),
+    /// Sets how the engine scores moves, kept across new games.
+    SetAlgorithm(EngineAlgorithm),
+    /// Sets the variant played, from the next new game or loaded position on.
+    SetVariant(VariantRules),
+    /// Sets how many pieces in a row win on the standard board, from the next new game or
+    /// loaded position on.
+    SetWinLength(u8),
+    /// Sets which power-up games are played with, or None for none, kept across new games.
+    SetPowerUps(Option<PowerUpKind>),
+    /// Sets the rating the engine plays at, kept across new games. FULL_STRENGTH or above
+    /// plays without limits.
+    SetStrength(u16),
+    /// Starts searching beneath the reply the engine expects, on the opponent's time, until
+    /// the next move is made.
+    Ponder,
+    /// Stops pondering, going back to searching every reply.
+    StopPondering,
+    /// Loads the position reached by the moves, like LoadPosition, and replies with an
+    /// AnalysisResult once the engine has had a first look at it.
+    AnalyzePosition(String),
+    /// Asks for the move the engine suggests to the player about to move, replied to with
+    /// a Hint explaining it.
+    RequestHint,
+}
+
+/// Where an engine runs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum EngineBackend {
+    /// In a thread of the UI's process.
+    #[default]
+    Thread,
+    /// In a separate child process, speaking the engine protocol over stdio, so that a crash
+    /// or running out of memory can't take the UI down with it.
+    Process,
+}
+
+/// Spawns a new engine using the given backend.
+///
+/// poke is called whenever the engine sends a message, e.g. to wake up the UI.
+///
+/// Returns the channels used to send messages to and receive messages from the engine.
+pub fn spawn_engine(
+    poke: impl Fn() + Send + Clone + 'static,
+    backend: EngineBackend,
+) -> (Sender<UIMessage>, Receiver<EngineMessage>) {
+    match backend {
+        EngineBackend::Thread => spawn_thread_engine(poke),
+        EngineBackend::Process => spawn_process_engine(poke.clone()).unwrap_or_else(|error| {
+            log_message(
+                LogType::Error,
+                format!(
+                    "Couldn't start the engine process, using a thread - {}",
+                    error
+                ),
+            );
+            spawn_thread_engine(poke)
+        }),
+    }
+}
+
+/// The UI's connection to an engine, which watches the engine for signs that it has
+/// stalled or crashed.
+///
+/// Unlike the raw channels, sending to a dead engine doesn't panic, so the UI can restart it.
+pub struct EngineHandle {
+    sender: Sender<UIMessage>,
+    receiver: Receiver<EngineMessage>,
+    backend: EngineBackend,
+    /// When the oldest message still waiting on a response from the engine was sent.
+    awaiting_since: Cell<Option<Instant>>,
+    /// Why the engine stopped working, if it has.
+    failure: RefCell<Option<String>>,
+}
+
+impl EngineHandle {
+    /// Spawns a new engine using the given backend, calling poke whenever it sends a message.
+    pub fn spawn(poke: impl Fn() + Send + Clone + 'static, backend: EngineBackend) -> EngineHandle {
+        let (sender, receiver) = spawn_engine(poke, backend);
+
+        EngineHandle {
+            sender,
+            receiver,
+            backend,
+            awaiting_since: Cell::new(None),
+            failure: RefCell::new(None),
+        }
+    }
+
+    /// Returns the backend the engine is running on.
+    pub fn backend(&self) -> EngineBackend {
+        self.backend
+    }
+
+    /// Sends a message to the engine, noting if the engine has hung up.
+    pub fn send(&self, message: UIMessage) {
+        let expects_response = matches!(
+            message,
+            UIMessage::MakeMove(_)
+                | UIMessage::RequestUpdate
+                | UIMessage::AnalyzePosition(_)
+                | UIMessage::RequestHint
+        );
+
+        if let Err(error) = self.sender.send(message) {
+            self.fail(format!("The engine hung up before receiving {:?}", error.0));
+        } else if expects_response && self.awaiting_since.get().is_none() {
+            self.awaiting_since.set(Some(Instant::now()));
+        }
+    }
+
+    /// Receives a message from the engine if one is waiting, noting if the engine has hung up.
+    pub fn try_recv(&self) -> Option<EngineMessage> {
+        match self.receiver.try_recv() {
+            Ok(message) => {
+                self.awaiting_since.set(None);
+
+                match &message {
+                    EngineMessage::BackendFailed(error) => self.fail(error.clone()),
+                    EngineMessage::EngineCrashed(error) => {
+                        self.fail(format!("The engine crashed - {}", error))
+                    }
+                    _ => (),
+                }
+                Some(message)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.fail("The engine hung up".to_owned());
+                None
+            }
+        }
+    }
+
+    /// Returns why the engine needs restarting, if it has failed or has kept the UI waiting
+    /// for longer than the timeout.
+    pub fn check(&self, timeout: Duration) -> Option<String> {
+        if let Some(failure) = self.failure() {
+            return Some(failure);
+        }
+
+        match self.awaiting_since.get() {
+            Some(since) if since.elapsed() > timeout => Some(format!(
+                "The engine hasn't responded in {:.1} seconds",
+                since.elapsed().as_secs_f32()
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns why the engine stopped working, if it has crashed or hung up.
+    pub fn failure(&self) -> Option<String> {
+        self.failure.borrow().clone()
+    }
+
+    /// Records the first reason the engine stopped working.
+    fn fail(&self, reason: String) {
+        self.failure.borrow_mut().get_or_insert(reason);
+    }
+}
+
+/// Spawns a new engine in its own thread.
+fn spawn_thread_engine(
+    poke: impl Fn() + Send + 'static,
+) -> (Sender<UIMessage>, Receiver<EngineMessage>) {
+    let (ui_sender, engine_receiver) = channel();
+    let (engine_sender, ui_receiver) = channel();
+
+    thread::spawn(move || {
+        supervise_engine(poke, engine_sender, engine_receiver);
+    });
+
+    (ui_sender, ui_receiver)
+}
+
+/// Spawns a new engine in a child process, with a thread each for writing messages to it
+/// and reading its responses.
+fn spawn_process_engine(
+    poke: impl Fn() + Send + 'static,
+) -> io::Result<(Sender<UIMessage>, Receiver<EngineMessage>)> {
+    let mut child = Command::new(env::current_exe()?)
+        .arg(ENGINE_SUBCOMMAND)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("Child process has no stdin");
+    let stdout = child.stdout.take().expect("Child process has no stdout");
+
+    let (ui_sender, engine_receiver) = channel::<UIMessage>();
+    let (engine_sender, ui_receiver) = channel();
+
+    thread::spawn(move || {
+        // Write failures mean the process has died, which the reading thread reports. We keep
+        // draining messages so that the UI can still send them.
+        for message in engine_receiver {
+            let _ = writeln!(stdin, "{}", write_ui_message(&message));
+        }
+
+        // Dropping stdin lets the process know the UI has hung up, shutting it down
+    });
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            match parse_engine_message(&line) {
+                Ok(message) => {
+                    if engine_sender.send(message).is_err() {
+                        break;
+                    }
+                    poke();
+                }
+                // Anything that isn't a message is the process's own logging
+                Err(_) => log_message(LogType::Detail, format!("Engine process: {}", line)),
+            }
+        }
+
+        let status = match child.wait() {
+            Ok(status) => status.to_string(),
+            Err(error) => error.to_string(),
+        };
+        let _ = engine_sender.send(EngineMessage::BackendFailed(format!(
+            "The engine process stopped ({})",
+            status
+        )));
+        poke();
+    });
+
+    Ok((ui_sender, ui_receiver))
+}
+
+/// Runs an engine that reads UI messages from stdin and writes its messages to stdout,
+/// for the UI to use as a process backend. Returns once stdin is closed.
+pub fn serve_engine_over_stdio() {
+    let (ui_sender, engine_receiver) = channel();
+    let (engine_sender, ui_receiver) = channel();
+
+    thread::spawn(move || {
+        for line in io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            match parse_ui_message(&line) {
+                Ok(message) => {
+                    if ui_sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        supervise_engine(|| (), engine_sender, engine_receiver);
+    });
+
+    let mut stdout = io::stdout();
+    for message in ui_receiver {
+        let line = write_engine_message(&message);
+
+        if writeln!(stdout, "{}", line)
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Runs an engine with async_engine_process, telling the UI with an EngineCrashed message
+/// if it panics, rather than just hanging up, so the UI can restart it.
+pub fn supervise_engine(
+    poke: impl Fn(),
+    sender: Sender<EngineMessage>,
+    receiver: Receiver<UIMessage>,
+) {
+    let engine_sender = sender.clone();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        async_engine_process(&poke, engine_sender, receiver)
+    }));
+
+    if let Err(payload) = result {
+        let reason = payload
+            .downcast_ref::<&str>()
+            .map(|reason| reason.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "an unknown panic".to_owned());
+
+        // The UI may have already hung up, which is what stopped the engine
+        let _ = sender.send(EngineMessage::EngineCrashed(reason));
+        poke();
+    }
 }
 
 /// A process meant to be run asynchronously from the UI.
@@ -46,31 +411,51 @@ pub enum UIMessage {
 /// This process will communicate with the engine according to the
 /// messages sent to it from the UI, and will also handle generating
 /// new nodes in the engine's decision tree in the downtime.
+///
+/// poke is called whenever a message is sent, to get the UI to respond to it promptly.
 pub fn async_engine_process(
-    ctx: Context,
+    poke: impl Fn(),
     sender: Sender<EngineMessage>,
     receiver: Receiver<UIMessage>,
 ) {
     // Setting the initial state of the process
     let mut manager = GameManager::new_game();
+    let mut algorithm = EngineAlgorithm::default();
+    let mut rules = VariantRules::default();
+    let mut config = BoardConfig::STANDARD;
+    let mut power_ups = None;
+    let mut strength = FULL_STRENGTH;
     let mut tree_size: TreeSize = TreeSize::default();
     let mut tree_complete = false;
     let mut time_since_last_update = Instant::now();
+    let mut power_saving = false;
+    let mut time_since_last_message = Instant::now();
 
     loop {
         let possible_message = match receiver.try_recv() {
             // If there's a message in the channel we want to address it
             Ok(message) => Some(message),
+            // If the UI has hung up, there's no one left to generate board states for
+            Err(TryRecvError::Disconnected) => break,
             // Otherwise we need to choose whether to generate board states or wait
-            Err(_) => {
-                if tree_size.memory >= MAX_MEMORY_USAGE || tree_complete {
+            Err(TryRecvError::Empty) => {
+                let idle = power_saving && time_since_last_message.elapsed() > IDLE_TIMEOUT;
+
+                if tree_size.memory >= MAX_MEMORY_USAGE
+                    || tree_complete
+                    || idle
+                    || manager.is_game_over() != GameOver::NoWin
+                {
                     log_message(
                         LogType::MaxMemHit,
-                        format!("Max Memory Hit -  tree complete: {}", tree_complete),
+                        format!(
+                            "Max Memory Hit -  tree complete: {}, idle: {}",
+                            tree_complete, idle
+                        ),
                     );
 
-                    send_update(&sender, &manager, &mut tree_size);
-                    poke_main_thread(&ctx);
+                    send_update(&sender, &mut manager, &tree_size);
+                    poke();
 
                     // If our tree is as big as we'll let it be already, we can block the thread
                     // and wait for a message
@@ -85,6 +470,10 @@ pub fn async_engine_process(
                     log_message(LogType::Detail, "Growing tree".to_owned());
                     grow_tree(&mut manager, &mut tree_complete, &mut tree_size);
 
+                    if power_saving {
+                        thread::sleep(POWER_SAVING_PAUSE);
+                    }
+
                     None
                 }
             }
@@ -95,25 +484,129 @@ pub fn async_engine_process(
                 LogType::AsyncMessage,
                 format!("UIMessage Received - {:?}", message),
             );
+            time_since_last_message = Instant::now();
 
             match message {
                 UIMessage::MakeMove(column) => {
+                    if let Some(reply) = manager.pondering() {
+                        log_message(
+                            LogType::Detail,
+                            format!(
+                                "Ponder {} - expected {}, got {}",
+                                if reply as usize == column {
+                                    "hit"
+                                } else {
+                                    "miss"
+                                },
+                                reply,
+                                column
+                            ),
+                        );
+                    }
                     let response = try_make_move(&mut manager, column, &mut tree_size);
+                    // The pondered subtree may have been complete without the rest being
+                    tree_complete = false;
 
                     sender.send(response).expect(
                         format!("Sending response to MakeMove({}) failed", column).as_str(),
                     );
-                    poke_main_thread(&ctx);
+                    poke();
                     time_since_last_update = Instant::now();
                 }
                 UIMessage::ResetGame => {
-                    manager = GameManager::new_game();
+                    manager = GameManager::from_moves_with_config("", rules, config)
+                        .expect("An empty board is always a legal position");
+                    manager.set_algorithm(algorithm);
+                    manager.set_power_ups(power_ups);
+                    manager.set_strength(strength);
                     tree_size = TreeSize::default();
                     tree_complete = false;
                 }
+                UIMessage::LoadPosition(moves) => {
+                    match GameManager::from_moves_with_config(&moves, rules, config) {
+                        Ok(new_manager) => {
+                            manager = new_manager;
+                            manager.set_algorithm(algorithm);
+                            manager.set_power_ups(power_ups);
+                            manager.set_strength(strength);
+                            tree_size = TreeSize::default();
+                            tree_complete = false;
+                        }
+                        Err(error_message) => {
+                            sender
+                                .send(EngineMessage::InvalidMove(error_message))
+                                .expect("Sending response to LoadPosition failed");
+                            poke();
+                        }
+                    }
+                }
+                UIMessage::AnalyzePosition(moves) => {
+                    let response = match GameManager::from_moves_with_config(&moves, rules, config)
+                    {
+                        Ok(new_manager) => {
+                            manager = new_manager;
+                            manager.set_algorithm(algorithm);
+                            manager.set_power_ups(power_ups);
+                            manager.set_strength(strength);
+                            tree_size = TreeSize::default();
+                            tree_complete = false;
+
+                            analyze(&mut manager, moves, &mut tree_complete, &mut tree_size)
+                        }
+                        Err(error_message) => EngineMessage::InvalidMove(error_message),
+                    };
+
+                    sender
+                        .send(response)
+                        .expect("Sending response to AnalyzePosition failed");
+                    poke();
+                    time_since_last_update = Instant::now();
+                }
+                UIMessage::SetPowerSaving(enabled) => power_saving = enabled,
+                UIMessage::SetAlgorithm(new_algorithm) => {
+                    algorithm = new_algorithm;
+                    manager.set_algorithm(algorithm);
+                }
+                UIMessage::SetVariant(new_rules) => rules = new_rules,
+                UIMessage::SetWinLength(number_to_win) => {
+                    match BoardConfig::STANDARD.with_number_to_win(number_to_win) {
+                        Ok(new_config) => config = new_config,
+                        Err(error_message) => {
+                            sender
+                                .send(EngineMessage::InvalidMove(error_message))
+                                .expect("Sending response to SetWinLength failed");
+                            poke();
+                        }
+                    }
+                }
+                UIMessage::SetPowerUps(kind) => {
+                    power_ups = kind;
+                    manager.set_power_ups(power_ups);
+                }
+                UIMessage::SetStrength(rating) => {
+                    strength = rating;
+                    manager.set_strength(strength);
+                    // A stronger engine can carry on where a weaker one stopped
+                    tree_complete = false;
+                }
+                UIMessage::Ponder => {
+                    manager.start_pondering();
+                    tree_complete = false;
+                }
+                UIMessage::StopPondering => {
+                    manager.stop_pondering();
+                    tree_complete = false;
+                }
                 UIMessage::RequestUpdate => {
-                    send_update(&sender, &manager, &mut tree_size);
-                    poke_main_thread(&ctx);
+                    send_update(&sender, &mut manager, &tree_size);
+                    poke();
+                    time_since_last_update = Instant::now();
+                }
+                UIMessage::RequestHint => {
+                    sender
+                        .send(EngineMessage::Hint(manager.get_hint()))
+                        .expect("Sending response to RequestHint failed");
+                    poke();
                     time_since_last_update = Instant::now();
                 }
             }
@@ -128,19 +621,39 @@ pub fn async_engine_process(
         if time_since_last_update.elapsed().as_secs() > 1 {
             log_message(LogType::AsyncMessage, "Sending periodic update".to_owned());
 
-            send_update(&sender, &manager, &mut tree_size);
-            poke_main_thread(&ctx);
+            send_update(&sender, &mut manager, &tree_size);
+            poke();
 
             time_since_last_update = Instant::now();
         }
     }
 }
 
-/// 'Pokes' the main thread to get it to rerender.
-///
-/// Used to ensure the UI responds to a message in a timely fashion.
-fn poke_main_thread(ctx: &Context) {
-    ctx.request_repaint();
+/// Searches the position for ANALYSIS_TIME, or until there's nothing left to search, and
+/// returns the result. moves are the moves leading to the position.
+fn analyze(
+    manager: &mut GameManager,
+    moves: String,
+    tree_complete: &mut bool,
+    tree_size: &mut TreeSize,
+) -> EngineMessage {
+    let start = Instant::now();
+    while start.elapsed() < ANALYSIS_TIME
+        && !*tree_complete
+        && tree_size.memory < MAX_MEMORY_USAGE
+        && manager.is_game_over() == GameOver::NoWin
+    {
+        grow_tree(manager, tree_complete, tree_size);
+    }
+
+    let move_scores = manager.get_move_scores();
+    EngineMessage::AnalysisResult {
+        moves,
+        move_confidences: manager.get_move_confidences(&move_scores),
+        move_scores,
+        tree_size: *tree_size,
+        best_column: manager.get_principal_variation(1).first().copied(),
+    }
 }
 
 /// Tries to make a move, and returns a response corresponding to if it was successful.
@@ -149,33 +662,65 @@ fn try_make_move(
     column: usize,
     tree_size: &mut TreeSize,
 ) -> EngineMessage {
+    let previous_orientation = manager.get_orientation();
+
     match manager.make_move(column as u8) {
         Ok(()) => {
             *tree_size = manager.size();
+            let move_scores = manager.get_move_scores();
 
             EngineMessage::MoveReceipt {
+                column,
                 game_state: manager.is_game_over(),
-                move_scores: manager.get_move_scores(),
+                move_confidences: manager.get_move_confidences(&move_scores),
+                events: manager.take_events(&move_scores),
+                move_scores,
                 tree_size: *tree_size,
+                orientation_flipped: manager.get_orientation() != previous_orientation,
+                winning_line: manager.get_winning_line(),
             }
         }
         Err(error_message) => EngineMessage::InvalidMove(error_message),
     }
 }
 
-/// Grows the size of the decision tree.
+/// Grows the size of the decision tree, or plays out more rollouts, as the algorithm needs.
+///
+/// The hybrid algorithm splits its time between the two, and its rollouts stop along with
+/// the tree once every move is proven.
 fn grow_tree(manager: &mut GameManager, tree_complete: &mut bool, tree_size: &mut TreeSize) {
-    let current_generated = manager.try_generate_x_states(GENERATED_NODES_PER_ITERATION);
-    *tree_complete = current_generated < GENERATED_NODES_PER_ITERATION;
+    let (nodes, rollouts) = match manager.algorithm() {
+        EngineAlgorithm::AlphaBeta => (GENERATED_NODES_PER_ITERATION, 0),
+        EngineAlgorithm::Mcts => (0, ROLLOUTS_PER_ITERATION),
+        EngineAlgorithm::Hybrid => (
+            GENERATED_NODES_PER_ITERATION / 2,
+            ROLLOUTS_PER_ITERATION / 2,
+        ),
+    };
+
+    if nodes > 0 {
+        let current_generated = manager.try_generate_x_states(nodes);
+        *tree_complete = current_generated < nodes;
+    }
+    if rollouts > 0 && manager.run_rollouts(rollouts) == 0 {
+        *tree_complete = true;
+    }
     *tree_size = manager.size();
 }
 
 /// Sends an update to the UI of the current engine state.
-fn send_update(sender: &Sender<EngineMessage>, manager: &GameManager, tree_size: &TreeSize) {
+fn send_update(sender: &Sender<EngineMessage>, manager: &mut GameManager, tree_size: &TreeSize) {
+    let move_scores = manager.get_move_scores();
+
     sender
         .send(EngineMessage::Update {
-            move_scores: manager.get_move_scores(),
+            move_confidences: manager.get_move_confidences(&move_scores),
+            events: manager.take_events(&move_scores),
+            move_scores,
             tree_size: *tree_size,
+            power_up_scores: manager.get_power_up_scores(),
+            principal_variation: manager.get_principal_variation(PRINCIPAL_VARIATION_LENGTH),
+            search_stats: manager.search_stats(),
         })
         .expect(format!("Sending update failed!").as_str());
 }
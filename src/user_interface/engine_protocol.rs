@@ -0,0 +1,825 @@
+//! Converts messages to and from the line based text protocol used to talk to an engine
+//! running in another process.
+//!
+//! Every message is a single line, starting with a command word. UI messages look like
+//! `move 3`, `newgame`, `position 4453`, `update`, `powersave on`, `algorithm mcts`,
+//! `variant misere`, `winlength 5`, `powerups anvil`, `ponder start`, `analyze 4453` and
+//! `hint`, while engine messages look like `update depth 4 size 2401 memory 65536 scores
+//! 3:12:s4,4:win:p line 3,3,4 stats 40,160,20,90,1500,300`, `receipt 3 NoWin flipped false
+//! depth 4 ... events threat:1:2+6`, `analysis 4453 best 3 depth 4 ...`, `hint 0 blocks the
+//! opponent's vertical threat at column 1` or `crashed attempt to subtract with overflow`.
+//! A receipt for a move that made a connect four lists its spaces after the flip, e.g.
+//! `winning 3:0,4:0,5:0,6:0`. An update lists the scores of spending the current player's
+//! power-up before its stats, e.g. `powerups 3:W1,5:12`, while they have one to spend.
+//! Columns are numbered from 0.
+
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    game_engine::game_record::{format_score, parse_score},
+    user_interface::engine_interface::{
+        EngineAlgorithm, EngineMessage, GameOver, Hint, MoveEvent, PowerUpKind, ScoreConfidence,
+        SearchStats, TreeSize, UIMessage, VariantRules,
+    },
+};
+
+/// Stands in for the moves of an analysis result when there are none.
+const START_POSITION: &str = "start";
+/// Stands in for the best column of an analysis result when there's no move to make.
+const NO_COLUMN: &str = "none";
+
+/// Writes a UI message as a line of the protocol, without the newline.
+pub fn write_ui_message(message: &UIMessage) -> String {
+    match message {
+        UIMessage::MakeMove(column) => format!("move {}", column),
+        UIMessage::ResetGame => "newgame".to_owned(),
+        UIMessage::LoadPosition(moves) => format!("position {}", moves).trim_end().to_owned(),
+        UIMessage::RequestUpdate => "update".to_owned(),
+        UIMessage::SetPowerSaving(enabled) => {
+            format!("powersave {}", if *enabled { "on" } else { "off" })
+        }
+        UIMessage::SetAlgorithm(algorithm) => format!(
+            "algorithm {}",
+            match algorithm {
+                EngineAlgorithm::AlphaBeta => "alphabeta",
+                EngineAlgorithm::Mcts => "mcts",
+                EngineAlgorithm::Hybrid => "hybrid",
+            }
+        ),
+        UIMessage::SetVariant(rules) => format!(
+            "variant {}",
+            match rules {
+                VariantRules::Standard => "standard",
+                VariantRules::Misere => "misere",
+            }
+        ),
+        UIMessage::SetWinLength(number_to_win) => format!("winlength {}", number_to_win),
+        UIMessage::SetPowerUps(kind) => format!(
+            "powerups {}",
+            match kind {
+                None => "off",
+                Some(PowerUpKind::Clear) => "on",
+                Some(PowerUpKind::Anvil) => "anvil",
+            }
+        ),
+        UIMessage::SetStrength(rating) => format!("strength {}", rating),
+        UIMessage::Ponder => "ponder start".to_owned(),
+        UIMessage::StopPondering => "ponder stop".to_owned(),
+        UIMessage::AnalyzePosition(moves) => format!("analyze {}", moves).trim_end().to_owned(),
+        UIMessage::RequestHint => "hint".to_owned(),
+    }
+}
+
+/// Parses a line of the protocol written by write_ui_message.
+pub fn parse_ui_message(line: &str) -> Result<UIMessage, String> {
+    let (command, argument) = split_command(line);
+
+    match (command, argument) {
+        ("move", column) => column
+            .parse()
+            .map(UIMessage::MakeMove)
+            .map_err(|_| format!("'{}' isn't a column", column)),
+        ("newgame", _) => Ok(UIMessage::ResetGame),
+        ("position", moves) => Ok(UIMessage::LoadPosition(moves.to_owned())),
+        ("update", _) => Ok(UIMessage::RequestUpdate),
+        ("powersave", "on") => Ok(UIMessage::SetPowerSaving(true)),
+        ("powersave", "off") => Ok(UIMessage::SetPowerSaving(false)),
+        ("algorithm", "alphabeta") => Ok(UIMessage::SetAlgorithm(EngineAlgorithm::AlphaBeta)),
+        ("algorithm", "mcts") => Ok(UIMessage::SetAlgorithm(EngineAlgorithm::Mcts)),
+        ("algorithm", "hybrid") => Ok(UIMessage::SetAlgorithm(EngineAlgorithm::Hybrid)),
+        ("variant", "standard") => Ok(UIMessage::SetVariant(VariantRules::Standard)),
+        ("variant", "misere") => Ok(UIMessage::SetVariant(VariantRules::Misere)),
+        ("winlength", number_to_win) => number_to_win
+            .parse()
+            .map(UIMessage::SetWinLength)
+            .map_err(|_| format!("'{}' isn't a number of pieces", number_to_win)),
+        ("powerups", "on") => Ok(UIMessage::SetPowerUps(Some(PowerUpKind::Clear))),
+        ("powerups", "anvil") => Ok(UIMessage::SetPowerUps(Some(PowerUpKind::Anvil))),
+        ("powerups", "off") => Ok(UIMessage::SetPowerUps(None)),
+        ("strength", rating) => rating
+            .parse()
+            .map(UIMessage::SetStrength)
+            .map_err(|_| format!("'{}' isn't a rating", rating)),
+        ("ponder", "start") => Ok(UIMessage::Ponder),
+        ("ponder", "stop") => Ok(UIMessage::StopPondering),
+        ("analyze", moves) => Ok(UIMessage::AnalyzePosition(moves.to_owned())),
+        ("hint", _) => Ok(UIMessage::RequestHint),
+        _ => Err(format!("Unknown command '{}'", line)),
+    }
+}
+
+/// Writes an engine message as a line of the protocol, without the newline.
+pub fn write_engine_message(message: &EngineMessage) -> String {
+    match message {
+        EngineMessage::MoveReceipt {
+            column,
+            game_state,
+            move_scores,
+            move_confidences,
+            tree_size,
+            orientation_flipped,
+            events,
+            winning_line,
+        } => format!(
+            "receipt {} {:?} flipped {} {}{}{}",
+            column,
+            game_state,
+            orientation_flipped,
+            write_winning_line(winning_line),
+            write_analysis(move_scores, move_confidences, tree_size),
+            write_events(events)
+        ),
+        EngineMessage::InvalidMove(error) => format!("invalid {}", error.replace('\n', " ")),
+        EngineMessage::Update {
+            move_scores,
+            move_confidences,
+            tree_size,
+            power_up_scores,
+            principal_variation,
+            events,
+            search_stats,
+        } => {
+            let line: Vec<String> = principal_variation
+                .iter()
+                .map(|column| column.to_string())
+                .collect();
+
+            format!(
+                "update {} line {}",
+                write_analysis(move_scores, move_confidences, tree_size),
+                line.join(",")
+            )
+            .trim_end()
+            .to_owned()
+                + &write_power_up_scores(power_up_scores)
+                + " stats "
+                + &write_search_stats(search_stats)
+                + &write_events(events)
+        }
+        EngineMessage::BackendFailed(error) => format!("failed {}", error.replace('\n', " ")),
+        EngineMessage::EngineCrashed(error) => format!("crashed {}", error.replace('\n', " ")),
+        EngineMessage::AnalysisResult {
+            moves,
+            move_scores,
+            move_confidences,
+            tree_size,
+            best_column,
+        } => format!(
+            "analysis {} best {} {}",
+            // The starting position has no moves, which would leave a gap in the line
+            if moves.is_empty() {
+                START_POSITION
+            } else {
+                moves
+            },
+            best_column.map_or(NO_COLUMN.to_owned(), |column| column.to_string()),
+            write_analysis(move_scores, move_confidences, tree_size)
+        ),
+        EngineMessage::Hint(hint) => match hint {
+            Some(hint) => format!(
+                "hint {} {}",
+                hint.column,
+                hint.explanation.replace('\n', " ")
+            ),
+            None => format!("hint {}", NO_COLUMN),
+        },
+    }
+}
+
+/// Parses a line of the protocol written by write_engine_message.
+pub fn parse_engine_message(line: &str) -> Result<EngineMessage, String> {
+    let (command, argument) = split_command(line);
+
+    match command {
+        "receipt" => {
+            let words: Vec<&str> = argument.split_whitespace().collect();
+            let [column, game_state, "flipped", flipped, rest @ ..] = &words[..] else {
+                return Err(format!("Malformed receipt '{}'", line));
+            };
+
+            let (winning_line, rest) = match rest {
+                ["winning", spaces, rest @ ..] => (parse_winning_line(spaces)?, rest),
+                _ => (Vec::new(), rest),
+            };
+            let (analysis, events) = split_events(rest)?;
+            let (move_scores, move_confidences, tree_size) = parse_analysis(analysis)?;
+
+            Ok(EngineMessage::MoveReceipt {
+                column: column
+                    .parse()
+                    .map_err(|_| format!("'{}' isn't a column", column))?,
+                game_state: parse_game_state(game_state)?,
+                move_scores,
+                move_confidences,
+                tree_size,
+                orientation_flipped: *flipped == "true",
+                events,
+                winning_line,
+            })
+        }
+        "update" => {
+            let words: Vec<&str> = argument.split_whitespace().collect();
+            let (words, events) = split_events(&words)?;
+            // Engines from before the statistics were added don't send them
+            let (words, search_stats) = match words.iter().position(|word| *word == "stats") {
+                Some(index) => match &words[index..] {
+                    [_, stats] => (&words[..index], parse_search_stats(stats)?),
+                    _ => return Err(format!("Malformed update '{}'", line)),
+                },
+                None => (words, SearchStats::default()),
+            };
+            let (words, power_up_scores) = match words.iter().position(|word| *word == "powerups") {
+                Some(index) => match &words[index..] {
+                    [_, scores] => (&words[..index], parse_power_up_scores(scores)?),
+                    _ => return Err(format!("Malformed update '{}'", line)),
+                },
+                None => (words, HashMap::new()),
+            };
+            let (analysis, line) = match words.iter().position(|word| *word == "line") {
+                Some(index) => (&words[..index], words.get(index + 1).copied()),
+                None => (words, None),
+            };
+
+            let (move_scores, move_confidences, tree_size) = parse_analysis(analysis)?;
+            let principal_variation = line
+                .unwrap_or("")
+                .split(',')
+                .filter(|column| !column.is_empty())
+                .map(|column| {
+                    column
+                        .parse()
+                        .map_err(|_| format!("'{}' isn't a column", column))
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+
+            Ok(EngineMessage::Update {
+                move_scores,
+                move_confidences,
+                tree_size,
+                power_up_scores,
+                principal_variation,
+                events,
+                search_stats,
+            })
+        }
+        "analysis" => {
+            let words: Vec<&str> = argument.split_whitespace().collect();
+            let [moves, "best", best_column, analysis @ ..] = &words[..] else {
+                return Err(format!("Malformed analysis '{}'", line));
+            };
+
+            let (move_scores, move_confidences, tree_size) = parse_analysis(analysis)?;
+            Ok(EngineMessage::AnalysisResult {
+                moves: match *moves {
+                    START_POSITION => String::new(),
+                    moves => moves.to_owned(),
+                },
+                move_scores,
+                move_confidences,
+                tree_size,
+                best_column: match *best_column {
+                    NO_COLUMN => None,
+                    column => Some(
+                        column
+                            .parse()
+                            .map_err(|_| format!("'{}' isn't a column", column))?,
+                    ),
+                },
+            })
+        }
+        "hint" => match argument.split_once(' ') {
+            _ if argument == NO_COLUMN => Ok(EngineMessage::Hint(None)),
+            Some((column, explanation)) => Ok(EngineMessage::Hint(Some(Hint {
+                column: column
+                    .parse()
+                    .map_err(|_| format!("'{}' isn't a column", column))?,
+                explanation: explanation.to_owned(),
+            }))),
+            None => Err(format!("Malformed hint '{}'", line)),
+        },
+        "invalid" => Ok(EngineMessage::InvalidMove(argument.to_owned())),
+        "failed" => Ok(EngineMessage::BackendFailed(argument.to_owned())),
+        "crashed" => Ok(EngineMessage::EngineCrashed(argument.to_owned())),
+        _ => Err(format!("Unknown command '{}'", line)),
+    }
+}
+
+/// Splits a line into its command word and the rest of the line.
+fn split_command(line: &str) -> (&str, &str) {
+    let line = line.trim();
+
+    match line.split_once(' ') {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (line, ""),
+    }
+}
+
+/// Writes the engine's analysis, e.g. `depth 4 size 2401 memory 65536 scores 3:12:s4,4:win:p`.
+fn write_analysis(
+    move_scores: &HashMap<u8, isize>,
+    move_confidences: &HashMap<u8, ScoreConfidence>,
+    tree_size: &TreeSize,
+) -> String {
+    let mut columns: Vec<&u8> = move_scores.keys().collect();
+    columns.sort();
+
+    let scores: Vec<String> = columns
+        .into_iter()
+        .map(|column| {
+            let confidence = match move_confidences.get(column) {
+                Some(ScoreConfidence::Proven) => "p".to_owned(),
+                Some(ScoreConfidence::Searched(depth)) => format!("s{}", depth),
+                Some(ScoreConfidence::Heuristic) | None => "h".to_owned(),
+            };
+
+            format!(
+                "{}:{}:{}",
+                column,
+                format_score(move_scores[column]),
+                confidence
+            )
+        })
+        .collect();
+
+    format!(
+        "depth {} size {} memory {} scores {}",
+        tree_size.depth,
+        tree_size.size,
+        tree_size.memory,
+        scores.join(",")
+    )
+    .trim_end()
+    .to_owned()
+}
+
+/// The move scores, move confidences and tree size sent along with engine messages.
+type Analysis = (HashMap<u8, isize>, HashMap<u8, ScoreConfidence>, TreeSize);
+
+/// Parses the engine's analysis written by write_analysis, already split into words.
+fn parse_analysis(words: &[&str]) -> Result<Analysis, String> {
+    let malformed = || format!("Malformed analysis '{}'", words.join(" "));
+    let number = |text: &str| text.parse::<usize>().map_err(|_| malformed());
+
+    let ["depth", depth, "size", size, "memory", memory, scores @ ..] = words else {
+        return Err(malformed());
+    };
+    let tree_size = TreeSize {
+        depth: number(depth)?,
+        size: number(size)?,
+        memory: number(memory)?,
+    };
+
+    let mut move_scores = HashMap::new();
+    let mut move_confidences = HashMap::new();
+
+    let scores = match scores {
+        [] | ["scores"] => "",
+        ["scores", scores] => scores,
+        _ => return Err(malformed()),
+    };
+    for entry in scores.split(',').filter(|entry| !entry.is_empty()) {
+        let mut parts = entry.split(':');
+        let (Some(column), Some(score), Some(confidence), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed());
+        };
+
+        let column = column.parse::<u8>().map_err(|_| malformed())?;
+        let score = parse_score(score).ok_or_else(malformed)?;
+        let confidence = match confidence {
+            "p" => ScoreConfidence::Proven,
+            "h" => ScoreConfidence::Heuristic,
+            depth => ScoreConfidence::Searched(
+                depth
+                    .strip_prefix('s')
+                    .and_then(|depth| depth.parse().ok())
+                    .ok_or_else(malformed)?,
+            ),
+        };
+
+        move_scores.insert(column, score);
+        move_confidences.insert(column, confidence);
+    }
+
+    Ok((move_scores, move_confidences, tree_size))
+}
+
+/// Writes the scores of spending a power-up sent along with updates, e.g. ` powerups 3:W1,5:12`,
+/// or nothing if there aren't any.
+fn write_power_up_scores(power_up_scores: &HashMap<u8, isize>) -> String {
+    if power_up_scores.is_empty() {
+        return String::new();
+    }
+
+    let mut columns: Vec<&u8> = power_up_scores.keys().collect();
+    columns.sort();
+    let scores: Vec<String> = columns
+        .into_iter()
+        .map(|column| format!("{}:{}", column, format_score(power_up_scores[column])))
+        .collect();
+
+    format!(" powerups {}", scores.join(","))
+}
+
+/// Parses the scores of spending a power-up written by write_power_up_scores.
+fn parse_power_up_scores(text: &str) -> Result<HashMap<u8, isize>, String> {
+    let malformed = || format!("Malformed power-up scores '{}'", text);
+
+    text.split(',')
+        .map(|entry| {
+            let (column, score) = entry.split_once(':').ok_or_else(malformed)?;
+            let column = column.parse::<u8>().map_err(|_| malformed())?;
+            let score = parse_score(score).ok_or_else(malformed)?;
+            Ok((column, score))
+        })
+        .collect()
+}
+
+/// Writes the events sent along with engine messages, e.g. ` events threat:1:2+6,block:2:2`,
+/// or nothing if there aren't any. Players are numbered from 1.
+fn write_events(events: &[MoveEvent]) -> String {
+    if events.is_empty() {
+        return String::new();
+    }
+
+    let player = |player: &bool| *player as u8 + 1;
+    let events: Vec<String> = events
+        .iter()
+        .map(|event| match event {
+            MoveEvent::ThreatCreated { player: p, columns } => {
+                let columns: Vec<String> = columns.iter().map(|col| col.to_string()).collect();
+                format!("threat:{}:{}", player(p), columns.join("+"))
+            }
+            MoveEvent::ThreatBlocked { player: p, column } => {
+                format!("block:{}:{}", player(p), column)
+            }
+            MoveEvent::WinMissed { player: p, column } => {
+                format!("missedwin:{}:{}", player(p), column)
+            }
+            MoveEvent::BlockMissed { player: p, column } => {
+                format!("missedblock:{}:{}", player(p), column)
+            }
+            MoveEvent::ForcedWinDetected { player: p } => format!("forcedwin:{}", player(p)),
+            MoveEvent::ZugzwangReached { player: p } => format!("zugzwang:{}", player(p)),
+        })
+        .collect();
+
+    format!(" events {}", events.join(","))
+}
+
+/// Writes the search statistics, e.g. `40,160,20,90,1500,300` for the states expanded,
+/// lookups, hits, frontier, and the microseconds spent generating and evaluating.
+fn write_search_stats(stats: &SearchStats) -> String {
+    format!(
+        "{},{},{},{},{},{}",
+        stats.expanded,
+        stats.lookups,
+        stats.hits,
+        stats.frontier,
+        stats.generation_time.as_micros(),
+        stats.evaluation_time.as_micros()
+    )
+}
+
+/// Parses the search statistics written by write_search_stats.
+fn parse_search_stats(text: &str) -> Result<SearchStats, String> {
+    let numbers = text
+        .split(',')
+        .map(|number| number.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>()
+        .map_err(|_| format!("Malformed stats '{}'", text))?;
+    let [expanded, lookups, hits, frontier, generation, evaluation] = numbers[..] else {
+        return Err(format!("Malformed stats '{}'", text));
+    };
+
+    Ok(SearchStats {
+        expanded: expanded as usize,
+        lookups: lookups as usize,
+        hits: hits as usize,
+        frontier: frontier as usize,
+        generation_time: Duration::from_micros(generation),
+        evaluation_time: Duration::from_micros(evaluation),
+    })
+}
+
+/// Splits the events written by write_events off the end of a message's words.
+///
+/// Returns the words before the events, along with the events.
+fn split_events<'a>(words: &'a [&'a str]) -> Result<(&'a [&'a str], Vec<MoveEvent>), String> {
+    let Some(index) = words.iter().position(|word| *word == "events") else {
+        return Ok((words, Vec::new()));
+    };
+    let malformed = || format!("Malformed events '{}'", words[index..].join(" "));
+    let [_, events] = &words[index..] else {
+        return Err(malformed());
+    };
+
+    let player = |text: &str| match text {
+        "1" => Ok(false),
+        "2" => Ok(true),
+        _ => Err(malformed()),
+    };
+    let column = |text: &str| text.parse::<u8>().map_err(|_| malformed());
+
+    let events = events
+        .split(',')
+        .map(|event| {
+            let parts: Vec<&str> = event.split(':').collect();
+            match parts[..] {
+                ["threat", p, columns] => Ok(MoveEvent::ThreatCreated {
+                    player: player(p)?,
+                    columns: columns
+                        .split('+')
+                        .map(column)
+                        .collect::<Result<Vec<u8>, String>>()?,
+                }),
+                ["block", p, col] => Ok(MoveEvent::ThreatBlocked {
+                    player: player(p)?,
+                    column: column(col)?,
+                }),
+                ["missedwin", p, col] => Ok(MoveEvent::WinMissed {
+                    player: player(p)?,
+                    column: column(col)?,
+                }),
+                ["missedblock", p, col] => Ok(MoveEvent::BlockMissed {
+                    player: player(p)?,
+                    column: column(col)?,
+                }),
+                ["forcedwin", p] => Ok(MoveEvent::ForcedWinDetected { player: player(p)? }),
+                ["zugzwang", p] => Ok(MoveEvent::ZugzwangReached { player: player(p)? }),
+                _ => Err(malformed()),
+            }
+        })
+        .collect::<Result<Vec<MoveEvent>, String>>()?;
+
+    Ok((&words[..index], events))
+}
+
+/// Writes the spaces of a connect four, followed by a space, or nothing if there are none.
+fn write_winning_line(winning_line: &[(u8, u8)]) -> String {
+    if winning_line.is_empty() {
+        return String::new();
+    }
+
+    let spaces: Vec<String> = winning_line
+        .iter()
+        .map(|(column, row)| format!("{}:{}", column, row))
+        .collect();
+    format!("winning {} ", spaces.join(","))
+}
+
+/// Parses the spaces of a connect four written by write_winning_line.
+fn parse_winning_line(text: &str) -> Result<Vec<(u8, u8)>, String> {
+    text.split(',')
+        .map(|space| {
+            space
+                .split_once(':')
+                .and_then(|(column, row)| Some((column.parse().ok()?, row.parse().ok()?)))
+                .ok_or_else(|| format!("'{}' isn't a space", space))
+        })
+        .collect()
+}
+
+/// Parses the debug name of a GameOver, e.g. "OneWins".
+fn parse_game_state(text: &str) -> Result<GameOver, String> {
+    match text {
+        "NoWin" => Ok(GameOver::NoWin),
+        "Tie" => Ok(GameOver::Tie),
+        "OneWins" => Ok(GameOver::OneWins),
+        "TwoWins" => Ok(GameOver::TwoWins),
+        _ => Err(format!("'{}' isn't a game state", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::{
+        game_engine::mate_distance::{loss_in, win_in},
+        user_interface::{
+            engine_interface::{
+                EngineAlgorithm, EngineMessage, GameOver, Hint, MoveEvent, PowerUpKind,
+                ScoreConfidence, SearchStats, TreeSize, UIMessage, VariantRules,
+            },
+            engine_protocol::{
+                parse_engine_message, parse_ui_message, write_engine_message, write_ui_message,
+            },
+        },
+    };
+
+    #[test]
+    fn ui_messages_round_trip() {
+        for message in [
+            UIMessage::MakeMove(3),
+            UIMessage::ResetGame,
+            UIMessage::LoadPosition("4453".to_owned()),
+            UIMessage::LoadPosition(String::new()),
+            UIMessage::RequestUpdate,
+            UIMessage::SetPowerSaving(true),
+            UIMessage::SetAlgorithm(EngineAlgorithm::Mcts),
+            UIMessage::SetAlgorithm(EngineAlgorithm::Hybrid),
+            UIMessage::SetVariant(VariantRules::Misere),
+            UIMessage::SetWinLength(5),
+            UIMessage::SetPowerUps(Some(PowerUpKind::Clear)),
+            UIMessage::SetPowerUps(Some(PowerUpKind::Anvil)),
+            UIMessage::SetPowerUps(None),
+            UIMessage::SetStrength(1300),
+            UIMessage::LoadPosition("4445x4".to_owned()),
+            UIMessage::LoadPosition("4445a4".to_owned()),
+            UIMessage::Ponder,
+            UIMessage::StopPondering,
+            UIMessage::AnalyzePosition("4453".to_owned()),
+            UIMessage::AnalyzePosition(String::new()),
+            UIMessage::RequestHint,
+        ] {
+            let line = write_ui_message(&message);
+            let parsed = parse_ui_message(&line).unwrap();
+
+            assert_eq!(format!("{:?}", parsed), format!("{:?}", message));
+        }
+
+        assert!(parse_ui_message("move left").is_err());
+        assert!(parse_ui_message("quit").is_err());
+        assert!(parse_ui_message("algorithm minimax").is_err());
+        assert!(parse_ui_message("strength grandmaster").is_err());
+        assert!(parse_ui_message("winlength four").is_err());
+    }
+
+    #[test]
+    fn engine_messages_round_trip() {
+        let move_scores = HashMap::from([(3, 12), (4, win_in(0)), (5, loss_in(3))]);
+        let move_confidences = HashMap::from([
+            (3, ScoreConfidence::Searched(4)),
+            (4, ScoreConfidence::Proven),
+            (5, ScoreConfidence::Heuristic),
+        ]);
+        let tree_size = TreeSize {
+            depth: 4,
+            size: 2401,
+            memory: 65536,
+        };
+
+        let update = EngineMessage::Update {
+            move_scores: move_scores.clone(),
+            move_confidences: move_confidences.clone(),
+            tree_size,
+            power_up_scores: HashMap::from([(2, win_in(0))]),
+            principal_variation: vec![3, 3, 4],
+            events: Vec::new(),
+            search_stats: SearchStats {
+                expanded: 40,
+                lookups: 160,
+                hits: 20,
+                frontier: 90,
+                generation_time: Duration::from_micros(1500),
+                evaluation_time: Duration::from_micros(300),
+            },
+        };
+        let line = write_engine_message(&update);
+        assert_eq!(
+            line,
+            "update depth 4 size 2401 memory 65536 scores 3:12:s4,4:W1:p,5:L2:h line 3,3,4 \
+             powerups 2:W1 stats 40,160,20,90,1500,300"
+        );
+        let EngineMessage::Update {
+            move_scores: parsed_scores,
+            move_confidences: parsed_confidences,
+            tree_size: parsed_size,
+            power_up_scores,
+            principal_variation: parsed_line,
+            search_stats,
+            ..
+        } = parse_engine_message(&line).unwrap()
+        else {
+            panic!("Parsed the wrong message from '{}'", line);
+        };
+        assert_eq!(parsed_scores, move_scores);
+        assert_eq!(parsed_confidences, move_confidences);
+        assert_eq!(parsed_size.size, 2401);
+        assert_eq!(parsed_line, vec![3, 3, 4]);
+        assert_eq!(power_up_scores, HashMap::from([(2, win_in(0))]));
+        assert_eq!(search_stats.generation_time, Duration::from_micros(1500));
+        assert_eq!(search_stats.hits, 20);
+
+        let receipt = EngineMessage::MoveReceipt {
+            column: 6,
+            game_state: GameOver::TwoWins,
+            move_scores: HashMap::new(),
+            move_confidences: HashMap::new(),
+            tree_size,
+            orientation_flipped: true,
+            events: vec![
+                MoveEvent::ThreatCreated {
+                    player: true,
+                    columns: vec![2, 6],
+                },
+                MoveEvent::BlockMissed {
+                    player: true,
+                    column: 6,
+                },
+                MoveEvent::ZugzwangReached { player: false },
+            ],
+            winning_line: vec![(3, 0), (4, 1), (5, 2), (6, 3)],
+        };
+        let line = write_engine_message(&receipt);
+        assert!(line.starts_with("receipt 6 TwoWins flipped true winning 3:0,4:1,5:2,6:3 depth"));
+        let parsed = parse_engine_message(&line).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", receipt));
+
+        // Events found during the search come along with updates, even without a line
+        let update = EngineMessage::Update {
+            move_scores: HashMap::new(),
+            move_confidences: HashMap::new(),
+            tree_size,
+            power_up_scores: HashMap::new(),
+            principal_variation: Vec::new(),
+            events: vec![MoveEvent::ForcedWinDetected { player: false }],
+            search_stats: SearchStats::default(),
+        };
+        let line = write_engine_message(&update);
+        assert_eq!(
+            line,
+            "update depth 4 size 2401 memory 65536 scores line stats 0,0,0,0,0,0 events \
+             forcedwin:1"
+        );
+        let parsed = parse_engine_message(&line).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", update));
+
+        // Engines that don't send statistics are still understood
+        let parsed = parse_engine_message(
+            "update depth 4 size 2401 memory 65536 scores line events forcedwin:1",
+        )
+        .unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", update));
+        assert!(parse_engine_message("update depth 4 size 2401 memory 65536 stats 1,2").is_err());
+        assert!(parse_engine_message("update depth 4 size 2401 memory 65536 powerups 3").is_err());
+
+        let analysis = EngineMessage::AnalysisResult {
+            moves: "4453".to_owned(),
+            move_scores: move_scores.clone(),
+            move_confidences: HashMap::from([(3, ScoreConfidence::Searched(4))]),
+            tree_size,
+            best_column: Some(4),
+        };
+        let line = write_engine_message(&analysis);
+        assert!(line.starts_with("analysis 4453 best 4 depth 4"));
+        let EngineMessage::AnalysisResult {
+            moves,
+            move_scores: parsed_scores,
+            best_column,
+            ..
+        } = parse_engine_message(&line).unwrap()
+        else {
+            panic!("Parsed the wrong message from '{}'", line);
+        };
+        assert_eq!(moves, "4453");
+        assert_eq!(parsed_scores, move_scores);
+        assert_eq!(best_column, Some(4));
+
+        let analysis = EngineMessage::AnalysisResult {
+            moves: String::new(),
+            move_scores: HashMap::new(),
+            move_confidences: HashMap::new(),
+            tree_size,
+            best_column: None,
+        };
+        let line = write_engine_message(&analysis);
+        assert_eq!(
+            line,
+            "analysis start best none depth 4 size 2401 memory 65536 scores"
+        );
+        let parsed = parse_engine_message(&line).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", analysis));
+
+        let hint = EngineMessage::Hint(Some(Hint {
+            column: 0,
+            explanation: "blocks the opponent's vertical threat at column 1".to_owned(),
+        }));
+        let line = write_engine_message(&hint);
+        assert_eq!(
+            line,
+            "hint 0 blocks the opponent's vertical threat at column 1"
+        );
+        let parsed = parse_engine_message(&line).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", hint));
+        let parsed = parse_engine_message(&write_engine_message(&EngineMessage::Hint(None)));
+        assert!(matches!(parsed, Ok(EngineMessage::Hint(None))));
+
+        // A panic's message can span several lines
+        let crashed = EngineMessage::EngineCrashed("index out of bounds\nat column 9".to_owned());
+        let line = write_engine_message(&crashed);
+        assert_eq!(line, "crashed index out of bounds at column 9");
+        assert!(matches!(
+            parse_engine_message(&line),
+            Ok(EngineMessage::EngineCrashed(reason)) if reason == "index out of bounds at column 9"
+        ));
+
+        assert!(parse_engine_message("hint left wins").is_err());
+        assert!(parse_engine_message("analysis 44 best left depth 4 size 1 memory 1").is_err());
+        assert!(parse_engine_message("update depth four").is_err());
+        assert!(parse_engine_message("receipt 3 Sideways flipped false").is_err());
+        assert!(parse_engine_message("update depth 4 size 1 memory 1 events threat:3:2").is_err());
+    }
+}
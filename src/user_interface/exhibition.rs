@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+
+use egui::{ComboBox, Context, Id, Pos2, Slider, Ui};
+
+use crate::{
+    game_engine::{
+        column_label::ColumnLabel,
+        game_record::{GameRecord, SETTINGS_TAG},
+        win_probability::{sparkline, win_probability},
+    },
+    log::{log_message, LogType},
+    user_interface::{
+        animation_scheduler::{Animation, AnimationScheduler},
+        board::Board,
+        commentary::Commentary,
+        engine_interface::{
+            EngineBackend, EngineHandle, EngineMessage, GameOver, TreeSize, UIMessage,
+        },
+        locale::Locale,
+        settings::{Difficulty, Settings},
+        turn_manager::{thinking_column, SpectatorTurnManager},
+    },
+};
+
+/// The names of the two engines, by the color they play.
+const ENGINE_NAMES: [&str; 2] = ["Red", "Blue"];
+
+/// How many moves of the game the sparkline in the window title covers.
+const TITLE_SPARKLINE_LENGTH: usize = 24;
+
+/// One of the engines playing in an exhibition, along with its latest analysis.
+struct ExhibitionEngine {
+    handle: EngineHandle,
+    difficulty: Difficulty,
+    move_scores: HashMap<u8, isize>,
+    tree_size: TreeSize,
+}
+
+impl ExhibitionEngine {
+    /// Spawns a new engine thread that will play at the given difficulty.
+    fn new(ctx: &Context, difficulty: Difficulty) -> ExhibitionEngine {
+        let ctx = ctx.clone();
+        let handle = EngineHandle::spawn(move || ctx.request_repaint(), EngineBackend::Thread);
+        handle.send(UIMessage::SetStrength(difficulty.rating()));
+
+        ExhibitionEngine {
+            handle,
+            difficulty,
+            move_scores: HashMap::new(),
+            tree_size: Default::default(),
+        }
+    }
+}
+
+/// A game where two engines, each running in its own thread, play against each other
+/// while the user watches.
+pub struct Exhibition {
+    engines: [ExhibitionEngine; 2],
+    board: Board,
+    turn_manager: SpectatorTurnManager,
+    /// How many seconds each engine thinks before making its move.
+    delay: f32,
+    game_state: GameOver,
+    /// The moves played so far, as a string of columns numbered from 1.
+    moves: String,
+    /// Red's chance of winning after each move, by the evaluation of the engine that played it.
+    win_chances: Vec<f64>,
+    commentary: Commentary,
+    /// The seed the engines' random choices are made with.
+    seed: u64,
+    /// The moves of the game being reproduced, which are played before the engines choose
+    /// their own.
+    script: Vec<usize>,
+    /// Why the game stopped, if the engines and the board fell out of step. It stays stopped
+    /// until it's restarted.
+    failure: Option<String>,
+}
+
+impl Exhibition {
+    /// Starts a new exhibition between engines of the given difficulties.
+    pub fn new(ctx: &Context, difficulties: [Difficulty; 2], delay: f32) -> Exhibition {
+        Exhibition::with_script(ctx, difficulties, delay, rand::random(), Vec::new())
+    }
+
+    /// Plays a saved game again, with the difficulty, delay and seed it was stamped with.
+    ///
+    /// Engines play both sides, making the game's moves while they have the same analysis
+    /// to show as an exhibition, and carry on choosing their own moves if the game was
+    /// left unfinished.
+    pub fn reproduce(ctx: &Context, record: &GameRecord) -> Result<Exhibition, String> {
+        let mut settings = Settings::new();
+        if let Some(value) = record.get_tag(SETTINGS_TAG) {
+            settings.apply_tag_value(value)?;
+        }
+        let seed = record.seed()?.unwrap_or_else(rand::random);
+        let script = record
+            .moves
+            .iter()
+            .map(|recorded| recorded.column as usize)
+            .collect();
+
+        Ok(Exhibition::with_script(
+            ctx,
+            [settings.difficulty; 2],
+            settings.delay,
+            seed,
+            script,
+        ))
+    }
+
+    /// Starts an exhibition that plays the script's moves before the engines choose their
+    /// own.
+    fn with_script(
+        ctx: &Context,
+        difficulties: [Difficulty; 2],
+        delay: f32,
+        seed: u64,
+        script: Vec<usize>,
+    ) -> Exhibition {
+        let mut board = Exhibition::new_board();
+        board.lock();
+
+        Exhibition {
+            engines: [
+                ExhibitionEngine::new(ctx, difficulties[0]),
+                ExhibitionEngine::new(ctx, difficulties[1]),
+            ],
+            board,
+            turn_manager: SpectatorTurnManager::new(seed, script.clone()),
+            delay,
+            game_state: GameOver::NoWin,
+            moves: String::new(),
+            win_chances: Vec::new(),
+            commentary: Commentary::new(),
+            seed,
+            script,
+            failure: None,
+        }
+    }
+
+    /// Creates the board the exhibition is played on.
+    fn new_board() -> Board {
+        Board::new(Id::new("Exhibition Board"), Pos2 { x: 0.0, y: 0.0 })
+    }
+
+    /// Starts the exhibition over from an empty board, keeping the engines' settings.
+    ///
+    /// A reproduced game is played again from the start, while a regular exhibition gets a
+    /// new seed.
+    pub fn restart(&mut self) {
+        for engine in self.engines.iter_mut() {
+            engine.handle.send(UIMessage::ResetGame);
+            engine.move_scores.clear();
+            engine.tree_size = Default::default();
+        }
+
+        self.board = Exhibition::new_board();
+        self.board.lock();
+        if self.script.is_empty() {
+            self.seed = rand::random();
+        }
+        self.turn_manager = SpectatorTurnManager::new(self.seed, self.script.clone());
+        self.game_state = GameOver::NoWin;
+        self.moves.clear();
+        self.win_chances.clear();
+        self.commentary.clear();
+        self.failure = None;
+    }
+
+    /// Returns whether the game is over, and who won if it is.
+    pub fn game_state(&self) -> GameOver {
+        self.game_state
+    }
+
+    /// Summarises how the game is going in a line short enough for the window title, so it
+    /// can be followed with the app minimised, e.g. "Red 62% ▄▅▅▆▇".
+    pub fn title_summary(&self) -> String {
+        let Some(chance) = self.win_chances.last() else {
+            return format!("{} vs {}", ENGINE_NAMES[0], ENGINE_NAMES[1]);
+        };
+
+        format!(
+            "{} {:.0}% {}",
+            ENGINE_NAMES[0],
+            chance * 100.0,
+            sparkline(&self.win_chances, TITLE_SPARKLINE_LENGTH)
+        )
+    }
+
+    /// Renders the running commentary on the game.
+    pub fn render_commentary(&self, ui: &mut Ui) {
+        self.commentary.render(ui);
+    }
+
+    /// Processes messages from both engines, advances the game, and renders the board,
+    /// scheduling its animations' next frames.
+    pub fn update(&mut self, ctx: &Context, ui: &mut Ui, animations: &mut AnimationScheduler) {
+        for index in 0..self.engines.len() {
+            if let Some(message) = self.engines[index].handle.try_recv() {
+                self.process_message(index, message, ctx);
+            }
+        }
+        self.replace_failed_engines(ctx);
+
+        let [red, blue] = &self.engines;
+        if self.failure.is_none() {
+            if let Err(error) = self.turn_manager.process_turn(
+                ctx,
+                &mut self.board,
+                self.delay,
+                [&red.handle, &blue.handle],
+            ) {
+                self.fail(error);
+            }
+        }
+
+        // The board is always locked, so none of the columns can be clicked
+        for _ in self.board.render(ctx, ui) {}
+
+        if let Some(progress) = self.turn_manager.thinking_progress(self.delay) {
+            let engine = &self.engines[self.turn_manager.current_engine()];
+            self.board
+                .render_thinking_ring(ui, thinking_column(&engine.move_scores), progress);
+            animations.request(Animation::ThinkingIndicator);
+        }
+    }
+
+    /// Replaces any engine that has crashed or hung up with a new one for the next game,
+    /// stopping this one.
+    fn replace_failed_engines(&mut self, ctx: &Context) {
+        for (index, name) in ENGINE_NAMES.iter().enumerate() {
+            if let Some(failure) = self.engines[index].handle.failure() {
+                let difficulty = self.engines[index].difficulty;
+                self.engines[index] = ExhibitionEngine::new(ctx, difficulty);
+                self.fail(format!("{} stopped - {}", name, failure));
+            }
+        }
+    }
+
+    /// Stops the game, after the engines and the board fell out of step.
+    fn fail(&mut self, error: String) {
+        log_message(LogType::Error, format!("Exhibition stopped - {}", error));
+        self.failure = Some(error);
+    }
+
+    /// Handles a message sent by one of the engines.
+    fn process_message(&mut self, index: usize, message: EngineMessage, ctx: &Context) {
+        log_message(
+            LogType::AsyncMessage,
+            format!("Exhibition EngineMessage Received - {:?}", message),
+        );
+
+        let engine = &mut self.engines[index];
+
+        match message {
+            EngineMessage::MoveReceipt {
+                column,
+                game_state,
+                move_scores,
+                tree_size,
+                events,
+                ..
+            } => {
+                // Both engines acknowledge each move, so it's only commented on once, using
+                //  the scores the engine that played it had for the position
+                if index == self.turn_manager.current_engine() {
+                    self.commentary.move_made(
+                        &self.moves,
+                        column,
+                        &engine.move_scores,
+                        game_state,
+                        &events,
+                    );
+                    self.moves.push_str(&ColumnLabel::new(column).to_string());
+
+                    let chance = match game_state {
+                        GameOver::NoWin => {
+                            best_move(&engine.move_scores).map(|(_, score)| win_probability(score))
+                        }
+                        GameOver::Tie => Some(0.5),
+                        GameOver::OneWins | GameOver::TwoWins => Some(1.0),
+                    };
+                    if let Some(chance) = chance {
+                        // The engine's chances are its own, which are Blue's every other move
+                        self.win_chances
+                            .push(if index == 0 { chance } else { 1.0 - chance });
+                    }
+                }
+
+                engine.move_scores = move_scores;
+                engine.tree_size = tree_size;
+                self.game_state = game_state;
+
+                self.turn_manager.move_receipt(game_state);
+            }
+            EngineMessage::InvalidMove(error) => self.fail(format!(
+                "{} rejected the move - {}",
+                ENGINE_NAMES[index], error
+            )),
+            // Exhibition engines are never asked for analysis or hints
+            EngineMessage::AnalysisResult { .. } | EngineMessage::Hint(_) => (),
+            // The engine's handle records these, and the engine is replaced once they've
+            //  been handled
+            EngineMessage::BackendFailed(_) | EngineMessage::EngineCrashed(_) => (),
+            EngineMessage::Update {
+                move_scores,
+                tree_size,
+                events,
+                ..
+            } => {
+                engine.move_scores = move_scores;
+                engine.tree_size = tree_size;
+                // Both engines search the same position, so only the one to move is heard
+                if index == self.turn_manager.current_engine() {
+                    self.commentary.events_found(&self.moves, &events);
+                }
+
+                self.turn_manager.update_received(
+                    index,
+                    &engine.move_scores,
+                    &engine.difficulty,
+                    ctx,
+                    &mut self.board,
+                );
+            }
+        }
+    }
+
+    /// Renders the controls for the exhibition's speed and engines, along with each
+    /// engine's live evaluation of the game.
+    pub fn render_controls(&mut self, ui: &mut Ui, locale: Locale) {
+        ui.heading("Exhibition");
+
+        ui.add(Slider::new(&mut self.delay, 0.0..=5.0).text("Seconds per move"));
+
+        for (index, engine) in self.engines.iter_mut().enumerate() {
+            ui.separator();
+
+            let name = ENGINE_NAMES[index];
+            ComboBox::from_label(format!("{} engine", name))
+                .selected_text(format!("{:?}", engine.difficulty))
+                .show_ui(ui, |ui| {
+                    for difficulty in Difficulty::ALL {
+                        ui.selectable_value(
+                            &mut engine.difficulty,
+                            difficulty,
+                            format!("{:?}", difficulty),
+                        );
+                    }
+                });
+
+            match best_move(&engine.move_scores) {
+                Some((column, score)) => {
+                    ui.label(format!(
+                        "Prefers column {} ({})",
+                        ColumnLabel::new(column),
+                        score
+                    ));
+                }
+                None => {
+                    ui.label("Thinking...");
+                }
+            }
+            ui.label(format!(
+                "Depth {}, {} positions",
+                engine.tree_size.depth,
+                locale.format_integer(engine.tree_size.size)
+            ));
+        }
+
+        ui.separator();
+
+        match (self.game_state, &self.failure) {
+            (_, Some(failure)) => {
+                ui.label(format!("The game stopped - {}", failure));
+            }
+            (GameOver::NoWin, None) => {
+                let name = ENGINE_NAMES[self.turn_manager.current_engine()];
+                ui.label(format!("{} to move", name));
+            }
+            (GameOver::Tie, None) => {
+                ui.label("The game is a tie");
+            }
+            (GameOver::OneWins, None) => {
+                ui.label(format!("{} wins", ENGINE_NAMES[0]));
+            }
+            (GameOver::TwoWins, None) => {
+                ui.label(format!("{} wins", ENGINE_NAMES[1]));
+            }
+        }
+
+        if ui.button("Restart").clicked() {
+            self.restart();
+        }
+    }
+}
+
+/// Returns the highest scoring column and its score, if there are any scores.
+fn best_move(move_scores: &HashMap<u8, isize>) -> Option<(u8, isize)> {
+    move_scores
+        .iter()
+        .max_by_key(|(_, score)| **score)
+        .map(|(column, score)| (*column, *score))
+}
@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+use egui::Context;
+
+use crate::log::{log_message, LogType};
+
+/// How long a frame can take while keeping the app at 60 frames per second.
+pub const FRAME_BUDGET: Duration = Duration::from_millis(16);
+
+/// Keeps track of how much of the current frame's time has been used, so work that isn't
+/// needed right away can be put off to a later frame.
+pub struct FrameBudget {
+    frame_start: Instant,
+    /// Whether any work was put off this frame, and needs another frame to catch up.
+    deferred_work: bool,
+    frames: usize,
+    slow_frames: usize,
+}
+
+impl Default for FrameBudget {
+    fn default() -> FrameBudget {
+        FrameBudget {
+            frame_start: Instant::now(),
+            deferred_work: false,
+            frames: 0,
+            slow_frames: 0,
+        }
+    }
+}
+
+impl FrameBudget {
+    /// Creates a new FrameBudget.
+    pub fn new() -> FrameBudget {
+        Default::default()
+    }
+
+    /// Starts timing a new frame.
+    pub fn start_frame(&mut self) {
+        self.frame_start = Instant::now();
+        self.deferred_work = false;
+    }
+
+    /// Returns whether work expected to take the given time fits in what's left of the frame.
+    pub fn can_afford(&self, estimate: Duration) -> bool {
+        self.frame_start.elapsed() + estimate <= FRAME_BUDGET
+    }
+
+    /// Finishes timing the frame, logging it if it went over budget, and makes sure there's
+    /// another frame to catch up on any work that was put off.
+    pub fn end_frame(&mut self, ctx: &Context) {
+        let frame_time = self.frame_start.elapsed();
+        self.frames += 1;
+
+        if frame_time > FRAME_BUDGET {
+            self.slow_frames += 1;
+            log_message(
+                LogType::Performance,
+                format!(
+                    "Frame took {:.1}ms, {} of {} frames have gone over budget",
+                    frame_time.as_secs_f64() * 1000.0,
+                    self.slow_frames,
+                    self.frames
+                ),
+            );
+        }
+
+        if self.deferred_work {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// A value that's expensive to work out, which is only recalculated when its inputs change
+/// and there's time left in the frame to do it.
+///
+/// Until then the value worked out from the old inputs is used.
+pub struct Deferred<K, T> {
+    /// The inputs the value was worked out from.
+    key: Option<K>,
+    value: Option<T>,
+    /// How long the value took to work out last time.
+    cost: Duration,
+}
+
+impl<K, T> Default for Deferred<K, T> {
+    fn default() -> Deferred<K, T> {
+        Deferred {
+            key: None,
+            value: None,
+            cost: Duration::ZERO,
+        }
+    }
+}
+
+impl<K: PartialEq, T> Deferred<K, T> {
+    /// Creates a Deferred value that hasn't been worked out yet.
+    pub fn new() -> Deferred<K, T> {
+        Default::default()
+    }
+
+    /// Returns the value for the given inputs, working it out with compute if the budget
+    /// allows, or else the value for the last inputs it was worked out from, if any.
+    pub fn get(
+        &mut self,
+        key: K,
+        budget: &mut FrameBudget,
+        compute: impl FnOnce() -> T,
+    ) -> Option<&T> {
+        if self.key.as_ref() != Some(&key) {
+            if budget.can_afford(self.cost) {
+                let start = Instant::now();
+                self.value = Some(compute());
+                self.cost = start.elapsed();
+                self.key = Some(key);
+            } else {
+                budget.deferred_work = true;
+            }
+        }
+
+        self.value.as_ref()
+    }
+}
@@ -0,0 +1,732 @@
+//! Plays games against people on a hobby Connect Four server, over a websocket carrying JSON
+//! messages.
+//!
+//! The client looks for a game with `{"type":"seek"}`, or takes up someone else's open seek
+//! with `{"type":"accept","seek":"abc"}`. Once the server starts a game, with
+//! `{"type":"start","game":"g1","color":"red"}` saying which side the client plays, both
+//! sides pass moves as `{"type":"move","game":"g1","column":3}` and results as
+//! `{"type":"result","game":"g1","result":"Red wins"}`. Columns are numbered from 0, and
+//! the server reports problems as `{"type":"error","message":"..."}`.
+//!
+//! Only plain `ws://` servers can be reached, as there's no TLS to speak `wss://` with.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    mem,
+    net::TcpStream,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::log::{log_message, LogType};
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// The most bytes a frame from the server, or a message put back together from its frames,
+/// can have. Anything longer is treated as a broken connection rather than allocated.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Appended to the client's key before hashing it into the key the server has to send back,
+/// proving it speaks websockets.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Which side of a game a player has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    /// Moves first.
+    Red,
+    Blue,
+}
+
+impl Color {
+    /// Returns 0 for red and 1 for blue, the side's index into the settings' players.
+    pub fn index(&self) -> usize {
+        match self {
+            Color::Red => 0,
+            Color::Blue => 1,
+        }
+    }
+}
+
+/// Something the client tells the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ClientMessage {
+    /// Asks for a game against whoever else is looking for one.
+    Seek,
+    /// Takes up someone's open seek.
+    Accept { seek: String },
+    /// Plays in the column.
+    Move { game: String, column: usize },
+    /// Says how the game ended, e.g. "Red wins", as the client saw it.
+    Result { game: String, result: String },
+}
+
+/// Something the server tells the client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ServerMessage {
+    /// Someone is looking for a game, which can be accepted.
+    Seek { seek: String, player: String },
+    /// A game has started, with the client playing the color.
+    Start { game: String, color: Color },
+    /// The opponent played in the column.
+    Move { game: String, column: usize },
+    /// The game is over, e.g. "Red wins", or was abandoned.
+    Result { game: String, result: String },
+    /// The server couldn't do what it was asked.
+    Error { message: String },
+}
+
+/// Something that happened on the connection to the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerEvent {
+    /// The server sent a message.
+    Message(ServerMessage),
+    /// The connection to the server was lost.
+    Disconnected(String),
+}
+
+/// Writes a message as the JSON sent to the server.
+pub fn write_client_message(message: &ClientMessage) -> String {
+    serde_json::to_string(message).expect("Client messages are always valid JSON")
+}
+
+/// Parses the JSON of a message sent by the server.
+pub fn parse_server_message(text: &str) -> Result<ServerMessage, String> {
+    serde_json::from_str(text)
+        .map_err(|error| format!("'{}' isn't a server message - {}", text, error))
+}
+
+/// A websocket frame, with its payload unmasked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Frame {
+    /// Whether this is the last frame of its message.
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Encodes a single frame message as sent by a client, which has to mask its payload.
+fn encode_frame(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+
+    match payload.len() {
+        length @ 0..=125 => frame.push(0x80 | length as u8),
+        length @ 126..=0xFFFF => {
+            frame.push(0x80 | 126);
+            frame.extend((length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(0x80 | 127);
+            frame.extend((length as u64).to_be_bytes());
+        }
+    }
+    frame.extend(mask);
+    frame.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| byte ^ mask[index % 4]),
+    );
+
+    frame
+}
+
+/// Reads the next frame from the server.
+fn read_frame(reader: &mut impl Read) -> Result<Frame, String> {
+    let mut read_bytes = |count: usize| -> Result<Vec<u8>, String> {
+        let mut bytes = vec![0; count];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|error| error.to_string())?;
+        Ok(bytes)
+    };
+
+    let header = read_bytes(2)?;
+    // Longer lengths follow in the next 2 or 8 bytes, most significant first
+    let length = match header[1] & 0x7F {
+        126 => read_bytes(2)?,
+        127 => read_bytes(8)?,
+        length => vec![length],
+    }
+    .iter()
+    .fold(0u64, |length, byte| length << 8 | *byte as u64);
+    if length > MAX_MESSAGE_SIZE as u64 {
+        return Err(format!(
+            "The server sent a {} byte frame, over the limit of {}",
+            length, MAX_MESSAGE_SIZE
+        ));
+    }
+    // Servers shouldn't mask their frames, but unmasking costs nothing
+    let mask = match header[1] & 0x80 {
+        0 => None,
+        _ => Some(read_bytes(4)?),
+    };
+    let mut payload = read_bytes(length as usize)?;
+    if let Some(mask) = mask {
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+    }
+
+    Ok(Frame {
+        fin: header[0] & 0x80 != 0,
+        opcode: header[0] & 0x0F,
+        payload,
+    })
+}
+
+/// Splits a `ws://host:port/path` URL into the address to connect to, the host to name in
+/// the handshake, and the path, with the port defaulting to 80.
+fn parse_url(url: &str) -> Result<(String, String, String), String> {
+    let Some(rest) = url.strip_prefix("ws://") else {
+        return Err(format!("'{}' isn't a ws:// URL", url));
+    };
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return Err(format!("'{}' has no host", url));
+    }
+
+    let address = if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{}:80", host)
+    };
+    Ok((address, host.to_owned(), path.to_owned()))
+}
+
+/// Encodes bytes as standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, byte)| {
+            bits | (*byte as u32) << (16 - 8 * index)
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Hashes bytes with SHA-1, which websockets use to check the server read the handshake.
+fn sha1(bytes: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // The message is padded out to a whole number of blocks, ending with its length in bits
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().expect("Blocks split into 4 bytes"));
+        }
+        for index in 16..80 {
+            words[index] =
+                (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16])
+                    .rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (mixed, constant) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let next = a
+                .rotate_left(5)
+                .wrapping_add(mixed)
+                .wrapping_add(e)
+                .wrapping_add(constant)
+                .wrapping_add(*word);
+            (a, b, c, d, e) = (next, a, b.rotate_left(30), c, d);
+        }
+
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+/// Returns the Sec-WebSocket-Accept a server has to answer the client's key with.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()))
+}
+
+/// Upgrades the HTTP connection to a websocket, sending key as the Sec-WebSocket-Key.
+///
+/// Fails unless the server switches protocols and answers with the Sec-WebSocket-Accept
+/// that matches the key, so that anything else listening on the port isn't mistaken for a
+/// game server.
+fn handshake(
+    writer: &mut impl Write,
+    reader: &mut impl BufRead,
+    host: &str,
+    path: &str,
+    key: &str,
+) -> Result<(), String> {
+    write!(
+        writer,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        path, host, key
+    )
+    .map_err(|error| error.to_string())?;
+
+    let mut status = String::new();
+    reader
+        .read_line(&mut status)
+        .map_err(|error| error.to_string())?;
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(format!(
+            "The server refused the websocket - {}",
+            status.trim()
+        ));
+    }
+
+    // The headers end with an empty line, after which the frames start
+    let mut accept = None;
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) => return Err("The server hung up during the handshake".to_owned()),
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => (),
+            Err(error) => return Err(error.to_string()),
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Sec-WebSocket-Accept") {
+                accept = Some(value.trim().to_owned());
+            }
+        }
+    }
+
+    if accept.as_deref() != Some(accept_key(key).as_str()) {
+        return Err("The server didn't accept the websocket's key".to_owned());
+    }
+    Ok(())
+}
+
+/// A connection to a game server, with threads reading its messages and writing the
+/// client's to it.
+pub struct GameServer {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<ServerEvent>,
+}
+
+impl GameServer {
+    /// Connects to the server at the URL, e.g. `ws://localhost:8080/play`.
+    ///
+    /// poke is called whenever the server sends a message, e.g. to wake up the UI.
+    pub fn connect(url: &str, poke: impl Fn() + Send + 'static) -> Result<Self, String> {
+        let (address, host, path) = parse_url(url)?;
+        let mut stream = TcpStream::connect(&address)
+            .map_err(|error| format!("Couldn't connect to {} - {}", address, error))?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(|error| error.to_string())?);
+
+        let key = base64(&rand::random::<[u8; 16]>());
+        handshake(&mut stream, &mut reader, &host, &path, &key)?;
+        Ok(GameServer::from_streams(reader, stream, poke))
+    }
+
+    /// Talks to a server whose frames come from reader and that is written to by writer,
+    /// once the handshake is done.
+    fn from_streams(
+        mut reader: impl Read + Send + 'static,
+        mut writer: impl Write + Send + 'static,
+        poke: impl Fn() + Send + 'static,
+    ) -> Self {
+        let (frame_sender, frame_receiver) = channel::<Vec<u8>>();
+        let (event_sender, event_receiver) = channel();
+        let pong_sender = frame_sender.clone();
+
+        thread::spawn(move || {
+            // Write failures mean the server is gone, which the reading thread reports
+            for frame in frame_receiver {
+                if writer
+                    .write_all(&frame)
+                    .and_then(|_| writer.flush())
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        thread::spawn(move || {
+            let mut message = Vec::new();
+            let error = loop {
+                let frame = match read_frame(&mut reader) {
+                    Ok(frame) => frame,
+                    Err(error) => break error,
+                };
+                match frame.opcode {
+                    OPCODE_CLOSE => break "The server closed the connection".to_owned(),
+                    OPCODE_PING => {
+                        let pong = encode_frame(OPCODE_PONG, &frame.payload, rand::random());
+                        let _ = pong_sender.send(pong);
+                        continue;
+                    }
+                    OPCODE_TEXT | OPCODE_CONTINUATION => {
+                        if message.len() + frame.payload.len() > MAX_MESSAGE_SIZE {
+                            break format!(
+                                "The server sent a message over the limit of {} bytes",
+                                MAX_MESSAGE_SIZE
+                            );
+                        }
+                        message.extend(frame.payload)
+                    }
+                    // Pongs and binary messages aren't part of the protocol
+                    _ => continue,
+                }
+                if !frame.fin {
+                    continue;
+                }
+
+                let text = String::from_utf8_lossy(&mem::take(&mut message)).into_owned();
+                match parse_server_message(&text) {
+                    Ok(message) => {
+                        if event_sender.send(ServerEvent::Message(message)).is_err() {
+                            return;
+                        }
+                        poke();
+                    }
+                    Err(error) => log_message(LogType::Error, error),
+                }
+            };
+
+            let _ = event_sender.send(ServerEvent::Disconnected(error));
+            poke();
+        });
+
+        GameServer {
+            sender: frame_sender,
+            receiver: event_receiver,
+        }
+    }
+
+    /// Sends a message to the server. Failures show up as a Disconnected event.
+    pub fn send(&self, message: &ClientMessage) {
+        let text = write_client_message(message);
+        let _ = self
+            .sender
+            .send(encode_frame(OPCODE_TEXT, text.as_bytes(), rand::random()));
+    }
+
+    /// Returns the next event from the server, if there is one.
+    pub fn try_recv(&self) -> Option<ServerEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Cursor, Write},
+        net::TcpListener,
+        thread,
+        time::Duration,
+    };
+
+    use crate::user_interface::game_server::{
+        accept_key, base64, encode_frame, handshake, parse_server_message, parse_url, read_frame,
+        sha1, write_client_message, ClientMessage, Color, Frame, GameServer, ServerEvent,
+        ServerMessage, MAX_MESSAGE_SIZE, OPCODE_CLOSE, OPCODE_TEXT,
+    };
+
+    /// Encodes an unmasked frame, as a server sends them.
+    fn server_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![if fin { 0x80 } else { 0 } | opcode, payload.len() as u8];
+        frame.extend(payload);
+        frame
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            write_client_message(&ClientMessage::Seek),
+            r#"{"type":"seek"}"#
+        );
+        assert_eq!(
+            write_client_message(&ClientMessage::Move {
+                game: "g1".to_owned(),
+                column: 3
+            }),
+            r#"{"type":"move","game":"g1","column":3}"#
+        );
+
+        assert_eq!(
+            parse_server_message(r#"{"type":"start","game":"g1","color":"blue"}"#),
+            Ok(ServerMessage::Start {
+                game: "g1".to_owned(),
+                color: Color::Blue
+            })
+        );
+        assert_eq!(
+            parse_server_message(r#"{"type":"result","game":"g1","result":"Red wins"}"#),
+            Ok(ServerMessage::Result {
+                game: "g1".to_owned(),
+                result: "Red wins".to_owned()
+            })
+        );
+        assert!(parse_server_message(r#"{"type":"resign"}"#).is_err());
+        assert!(parse_server_message("move 3").is_err());
+    }
+
+    #[test]
+    fn frames() {
+        // Client frames are masked, and longer payloads get an extended length
+        for length in [5, 200, MAX_MESSAGE_SIZE] {
+            let payload: Vec<u8> = (0..length).map(|index| index as u8).collect();
+            let encoded = encode_frame(OPCODE_TEXT, &payload, [1, 2, 3, 4]);
+
+            assert_ne!(encoded[encoded.len() - length..], payload[..]);
+            assert_eq!(
+                read_frame(&mut Cursor::new(encoded)),
+                Ok(Frame {
+                    fin: true,
+                    opcode: OPCODE_TEXT,
+                    payload
+                })
+            );
+        }
+
+        assert!(read_frame(&mut Cursor::new(vec![0x81, 5, b'a'])).is_err());
+
+        // Frames too long to be a message aren't read, however many bytes follow
+        let mut too_long = vec![0x81, 127];
+        too_long.extend((MAX_MESSAGE_SIZE as u64 + 1).to_be_bytes());
+        assert!(read_frame(&mut Cursor::new(too_long)).is_err());
+        let mut huge = vec![0x81, 127];
+        huge.extend(u64::MAX.to_be_bytes());
+        assert!(read_frame(&mut Cursor::new(huge)).is_err());
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            parse_url("ws://localhost:8080/play"),
+            Ok((
+                "localhost:8080".to_owned(),
+                "localhost:8080".to_owned(),
+                "/play".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse_url("ws://example.com"),
+            Ok((
+                "example.com:80".to_owned(),
+                "example.com".to_owned(),
+                "/".to_owned()
+            ))
+        );
+
+        assert!(parse_url("wss://example.com").is_err());
+        assert!(parse_url("ws:///play").is_err());
+    }
+
+    #[test]
+    fn base64_encoding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn sha1_hashes() {
+        let hex = |digest: [u8; 20]| -> String {
+            digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+        };
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        // Long enough for the padding to spill into a second block
+        assert_eq!(
+            hex(sha1(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn handshakes() {
+        // The example from the websocket RFC
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        assert_eq!(accept_key(key), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+
+        let mut request = Vec::new();
+        let mut response = Cursor::new(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             sec-websocket-accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n\u{81}"
+                .as_bytes(),
+        );
+        assert_eq!(
+            handshake(&mut request, &mut response, "localhost", "/play", key),
+            Ok(())
+        );
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("GET /play HTTP/1.1\r\nHost: localhost\r\n"));
+        assert!(request.contains(&format!("Sec-WebSocket-Key: {}\r\n", key)));
+        assert!(request.ends_with("\r\n\r\n"));
+
+        let mut refused = Cursor::new("HTTP/1.1 404 Not Found\r\n\r\n".as_bytes());
+        assert!(handshake(&mut Vec::new(), &mut refused, "localhost", "/", key).is_err());
+
+        // Switching protocols isn't enough without the right key
+        for accept in ["", "Sec-WebSocket-Accept: Zm9vYmFy\r\n"] {
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n{}\r\n",
+                accept
+            );
+            let mut response = Cursor::new(response.as_bytes());
+            assert!(handshake(&mut Vec::new(), &mut response, "localhost", "/", key).is_err());
+        }
+    }
+
+    #[test]
+    fn loopback_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                request.push(line);
+            }
+            let key = request
+                .iter()
+                .find_map(|line| line.strip_prefix("Sec-WebSocket-Key:"))
+                .unwrap()
+                .trim();
+            write!(
+                stream,
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept_key(key)
+            )
+            .unwrap();
+
+            stream
+                .write_all(&server_frame(
+                    true,
+                    OPCODE_TEXT,
+                    br#"{"type":"start","game":"g1","color":"red"}"#,
+                ))
+                .unwrap();
+            let reply = read_frame(&mut reader).unwrap();
+
+            stream
+                .write_all(&server_frame(true, OPCODE_CLOSE, b""))
+                .unwrap();
+            (request[0].clone(), reply)
+        });
+
+        let client = GameServer::connect(&format!("ws://127.0.0.1:{}/play", port), || ()).unwrap();
+        client.send(&ClientMessage::Seek);
+
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            match client.try_recv() {
+                Some(event) => events.push(event),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        assert_eq!(
+            events,
+            [
+                ServerEvent::Message(ServerMessage::Start {
+                    game: "g1".to_owned(),
+                    color: Color::Red
+                }),
+                ServerEvent::Disconnected("The server closed the connection".to_owned())
+            ]
+        );
+
+        // The client's frames arrive unmasked by the server
+        let (request_line, reply) = server.join().unwrap();
+        assert_eq!(request_line, "GET /play HTTP/1.1\r\n");
+        assert_eq!(
+            reply,
+            Frame {
+                fin: true,
+                opcode: OPCODE_TEXT,
+                payload: br#"{"type":"seek"}"#.to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn receiving_messages() {
+        let mut frames = server_frame(false, OPCODE_TEXT, br#"{"type":"move","#);
+        frames.extend(server_frame(true, 0, br#""game":"g1","column":2}"#));
+        frames.extend(server_frame(true, OPCODE_TEXT, b"not json"));
+        frames.extend(server_frame(true, OPCODE_CLOSE, b""));
+
+        let server = GameServer::from_streams(Cursor::new(frames), Vec::new(), || ());
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            match server.try_recv() {
+                Some(event) => events.push(event),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+
+        // Messages split over frames are put back together, and junk is skipped
+        assert_eq!(
+            events,
+            [
+                ServerEvent::Message(ServerMessage::Move {
+                    game: "g1".to_owned(),
+                    column: 2
+                }),
+                ServerEvent::Disconnected("The server closed the connection".to_owned())
+            ]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use egui::{Button, CollapsingHeader, Context, ProgressBar, TextEdit, Ui};
+
+use crate::archive::{Archive, ImportSummary};
+
+/// An import running in the background.
+struct ImportJob {
+    /// How many files have been checked, and how many there are in total.
+    checked: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+    handle: JoinHandle<io::Result<ImportSummary>>,
+}
+
+/// Imports a folder of games into the archive in the background, showing its progress.
+pub struct Importer {
+    directory: PathBuf,
+    /// The folder to import the games from, as the user typed it.
+    folder: String,
+    job: Option<ImportJob>,
+    /// The summary of the last import, or why it failed.
+    result: Option<Result<ImportSummary, String>>,
+}
+
+impl Importer {
+    /// Creates an Importer that adds games to the archive in the given directory.
+    pub fn new(directory: PathBuf) -> Importer {
+        Importer {
+            directory,
+            folder: String::new(),
+            job: None,
+            result: None,
+        }
+    }
+
+    /// Starts importing the games in the folder on another thread.
+    fn start(&mut self, ctx: &Context) {
+        let checked = Arc::new(AtomicUsize::new(0));
+        let total = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let (checked, total, cancelled) = (checked.clone(), total.clone(), cancelled.clone());
+            let source = PathBuf::from(self.folder.trim());
+            let directory = self.directory.clone();
+            let ctx = ctx.clone();
+
+            thread::spawn(move || {
+                Archive::import_folder(&source, &directory, |done, count| {
+                    checked.store(done, Ordering::Relaxed);
+                    total.store(count, Ordering::Relaxed);
+                    ctx.request_repaint();
+
+                    !cancelled.load(Ordering::Relaxed)
+                })
+            })
+        };
+
+        self.result = None;
+        self.job = Some(ImportJob {
+            checked,
+            total,
+            cancelled,
+            handle,
+        });
+    }
+
+    /// Collects the summary of the running import if it's finished.
+    fn poll(&mut self) {
+        if !self
+            .job
+            .as_ref()
+            .is_some_and(|job| job.handle.is_finished())
+        {
+            return;
+        }
+
+        let job = self.job.take().expect("The job was just checked");
+        self.result = Some(match job.handle.join() {
+            Ok(result) => result.map_err(|error| error.to_string()),
+            Err(_) => Err("The import stopped unexpectedly".to_owned()),
+        });
+    }
+
+    /// Renders the folder to import from, and the progress or summary of the import.
+    pub fn render(&mut self, ui: &mut Ui, ctx: &Context) {
+        self.poll();
+
+        CollapsingHeader::new("Import games").show(ui, |ui| {
+            match &self.job {
+                Some(job) => {
+                    let checked = job.checked.load(Ordering::Relaxed);
+                    let total = job.total.load(Ordering::Relaxed).max(1);
+
+                    ui.add(
+                        ProgressBar::new(checked as f32 / total as f32)
+                            .text(format!("Checked {} of {} files", checked, total)),
+                    );
+                    if ui.button("Cancel").clicked() {
+                        job.cancelled.store(true, Ordering::Relaxed);
+                    }
+                }
+                None => {
+                    ui.add(
+                        TextEdit::singleline(&mut self.folder)
+                            .hint_text("Folder of .c4 and .c4b files"),
+                    );
+
+                    let has_folder = Path::new(self.folder.trim()).is_dir();
+                    if ui.add_enabled(has_folder, Button::new("Import")).clicked() {
+                        self.start(ctx);
+                    }
+                }
+            }
+
+            match &self.result {
+                Some(Ok(summary)) => {
+                    ui.label(summary.to_string());
+                    for (path, error) in &summary.invalid {
+                        ui.label(format!("{}: {}", path.display(), error));
+                    }
+                }
+                Some(Err(error)) => {
+                    ui.label(format!("Couldn't import the games: {}", error));
+                }
+                None => (),
+            }
+        });
+    }
+}
@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How many seconds there are in a day.
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The conventions numbers, durations and dates are written in for the user.
+///
+/// Everything the app shows in its stats, clocks and dashboards goes through here, so they
+/// all follow the chosen locale.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Locale {
+    /// e.g. "1,234.5" and "01/31/2024".
+    #[default]
+    EnglishUs,
+    /// e.g. "1,234.5" and "31/01/2024".
+    EnglishUk,
+    /// e.g. "1.234,5" and "31.01.2024".
+    German,
+    /// e.g. "1 234,5" and "31/01/2024".
+    French,
+}
+
+impl Locale {
+    /// Every locale, in the order they're offered to the user.
+    pub const ALL: [Locale; 4] = [
+        Locale::EnglishUs,
+        Locale::EnglishUk,
+        Locale::German,
+        Locale::French,
+    ];
+
+    /// Describes the locale for the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::EnglishUs => "English (US)",
+            Locale::EnglishUk => "English (UK)",
+            Locale::German => "Deutsch",
+            Locale::French => "Français",
+        }
+    }
+
+    /// The character between groups of three digits.
+    fn group_separator(&self) -> char {
+        match self {
+            Locale::EnglishUs | Locale::EnglishUk => ',',
+            Locale::German => '.',
+            Locale::French => ' ',
+        }
+    }
+
+    /// The character between the whole and fractional parts of a number.
+    fn decimal_separator(&self) -> char {
+        match self {
+            Locale::EnglishUs | Locale::EnglishUk => '.',
+            Locale::German | Locale::French => ',',
+        }
+    }
+
+    /// The space between a number and its unit, e.g. "53 %" rather than "53%".
+    fn unit_space(&self) -> &'static str {
+        match self {
+            Locale::EnglishUs | Locale::EnglishUk => "",
+            Locale::German | Locale::French => " ",
+        }
+    }
+
+    /// Writes a whole number, with its digits grouped in threes, e.g. "1,234,567".
+    pub fn format_integer(&self, value: usize) -> String {
+        let digits = value.to_string();
+        let mut formatted = String::new();
+
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                formatted.push(self.group_separator());
+            }
+            formatted.push(digit);
+        }
+
+        formatted
+    }
+
+    /// Writes a number rounded to the given number of decimal places, e.g. "1,234.50".
+    pub fn format_decimal(&self, value: f64, decimals: usize) -> String {
+        let rounded = format!("{:.*}", decimals, value.abs());
+        let (whole, fraction) = rounded.split_once('.').unwrap_or((&rounded, ""));
+        let whole = whole.parse::<usize>().unwrap_or(0);
+
+        let mut formatted = String::new();
+        // Values that round to zero shouldn't keep their sign
+        if value < 0.0 && rounded.chars().any(|digit| ('1'..='9').contains(&digit)) {
+            formatted.push('-');
+        }
+        formatted.push_str(&self.format_integer(whole));
+        if !fraction.is_empty() {
+            formatted.push(self.decimal_separator());
+            formatted.push_str(fraction);
+        }
+
+        formatted
+    }
+
+    /// Writes a fraction between 0 and 1 as a whole percentage, e.g. "53%".
+    pub fn format_percent(&self, fraction: f64) -> String {
+        format!(
+            "{}{}%",
+            self.format_decimal(fraction * 100.0, 0),
+            self.unit_space()
+        )
+    }
+
+    /// Writes a duration, in seconds with the given number of decimal places under a minute,
+    /// e.g. "2.5s", and in minutes and whole seconds from a minute up, e.g. "1:05".
+    pub fn format_duration(&self, duration: Duration, decimals: usize) -> String {
+        let seconds = duration.as_secs_f64();
+
+        if seconds < 60.0 {
+            let formatted = self.format_decimal(seconds, decimals);
+            // Rounding up to a whole minute is written as one
+            if formatted != self.format_integer(60) {
+                return format!("{}{}s", formatted, self.unit_space());
+            }
+        }
+
+        let seconds = seconds.round() as u64;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    /// Writes the day of a time, given in seconds since the Unix epoch, e.g. "01/31/2024".
+    pub fn format_date(&self, seconds_since_epoch: u64) -> String {
+        let (year, month, day) = civil_date(seconds_since_epoch / SECONDS_PER_DAY);
+
+        match self {
+            Locale::EnglishUs => format!("{:02}/{:02}/{}", month, day, year),
+            Locale::EnglishUk | Locale::French => format!("{:02}/{:02}/{}", day, month, year),
+            Locale::German => format!("{:02}.{:02}.{}", day, month, year),
+        }
+    }
+}
+
+/// Returns the year, month and day of the given number of days since the Unix epoch, in the
+/// proleptic Gregorian calendar.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    // Counting from 0000-03-01 puts leap days at the end of each year, and the calendar
+    // repeats itself every 400 years, or 146097 days
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months counted from March, each five months being 153 days
+    let month_from_march = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::user_interface::locale::Locale;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(Locale::EnglishUs.format_integer(0), "0");
+        assert_eq!(Locale::EnglishUs.format_integer(999), "999");
+        assert_eq!(Locale::EnglishUs.format_integer(1234567), "1,234,567");
+        assert_eq!(Locale::German.format_integer(1234567), "1.234.567");
+        assert_eq!(Locale::French.format_integer(1234), "1 234");
+
+        assert_eq!(Locale::EnglishUk.format_decimal(1234.5, 2), "1,234.50");
+        assert_eq!(Locale::German.format_decimal(1234.5, 1), "1.234,5");
+        assert_eq!(Locale::French.format_decimal(0.125, 0), "0");
+        assert_eq!(Locale::EnglishUs.format_decimal(-3.25, 1), "-3.2");
+        assert_eq!(Locale::EnglishUs.format_decimal(-0.01, 1), "0.0");
+
+        assert_eq!(Locale::EnglishUs.format_percent(0.534), "53%");
+        assert_eq!(Locale::German.format_percent(1.0), "100 %");
+    }
+
+    #[test]
+    fn durations() {
+        let locale = Locale::EnglishUs;
+        assert_eq!(
+            locale.format_duration(Duration::from_millis(2500), 1),
+            "2.5s"
+        );
+        assert_eq!(locale.format_duration(Duration::from_secs(12), 0), "12s");
+        assert_eq!(locale.format_duration(Duration::from_secs(65), 1), "1:05");
+        assert_eq!(
+            locale.format_duration(Duration::from_millis(59_800), 0),
+            "1:00"
+        );
+        assert_eq!(
+            locale.format_duration(Duration::from_secs(3600), 0),
+            "60:00"
+        );
+
+        assert_eq!(
+            Locale::French.format_duration(Duration::from_millis(2500), 1),
+            "2,5 s"
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(Locale::EnglishUs.format_date(0), "01/01/1970");
+        // 2024-02-29, a leap day
+        let leap_day = 1_709_164_800;
+        assert_eq!(Locale::EnglishUs.format_date(leap_day), "02/29/2024");
+        assert_eq!(Locale::EnglishUk.format_date(leap_day), "29/02/2024");
+        assert_eq!(Locale::German.format_date(leap_day + 86_399), "29.02.2024");
+        assert_eq!(Locale::French.format_date(leap_day + 86_400), "01/03/2024");
+        // 2000-12-31, the end of a leap century
+        assert_eq!(Locale::EnglishUs.format_date(978_220_800), "12/31/2000");
+    }
+}
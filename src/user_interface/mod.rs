@@ -1,4 +1,51 @@
+#[cfg(feature = "gui")]
+pub mod animation_scheduler;
+#[cfg(feature = "gui")]
+pub mod archive_search;
+#[cfg(feature = "gui")]
+pub mod archive_stats;
+#[cfg(feature = "gui")]
+pub mod attract;
+#[cfg(feature = "gui")]
 pub mod board;
+#[cfg(feature = "gui")]
+pub mod board_geometry;
+#[cfg(feature = "gui")]
+pub mod bookmarks;
+pub mod challenge;
+#[cfg(feature = "gui")]
+pub mod commentary;
 pub mod engine_interface;
+pub mod engine_protocol;
+#[cfg(feature = "gui")]
+pub mod exhibition;
+#[cfg(feature = "gui")]
+pub mod frame_budget;
+pub mod game_server;
+#[cfg(feature = "gui")]
+pub mod importer;
+pub mod locale;
+#[cfg(feature = "gui")]
+pub mod notation_input;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+pub mod physical_board;
+#[cfg(feature = "gui")]
+pub mod puzzle_rush;
+#[cfg(feature = "gui")]
+pub mod qr_code;
+pub mod recognition;
+#[cfg(feature = "gui")]
+pub mod review;
+#[cfg(feature = "gui")]
+pub mod rules_reference;
+#[cfg(feature = "gui")]
+pub mod screenshot_import;
+#[cfg(feature = "gui")]
+pub mod session;
 pub mod settings;
+#[cfg(feature = "gui")]
+pub mod teacher;
+#[cfg(feature = "gui")]
 pub mod turn_manager;
+pub mod view_model;
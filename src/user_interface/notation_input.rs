@@ -0,0 +1,105 @@
+use egui::{Color32, RichText, TextEdit, Ui};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::game_manager::GameManager,
+    user_interface::{board::PieceState, engine_interface::GameOver},
+};
+
+/// A position that was set up from a move string.
+pub struct LoadedPosition {
+    pub moves: String,
+    /// The position as array[row][col].
+    pub position: [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+    /// The player who is about to make a move.
+    pub current_player: PieceState,
+    pub game_state: GameOver,
+}
+
+/// A text field where a move string (e.g. "4445") can be typed or pasted to quickly
+/// set up a position.
+#[derive(Default)]
+pub struct NotationInput {
+    text: String,
+    /// Describes the last entered move string, or why it isn't valid.
+    feedback: Option<Result<String, String>>,
+}
+
+impl NotationInput {
+    /// Creates a new, empty NotationInput.
+    pub fn new() -> NotationInput {
+        Default::default()
+    }
+
+    /// Renders the text field and feedback on whether its contents are valid.
+    ///
+    /// Returns the resulting position whenever the contents change to a valid move string.
+    pub fn render(&mut self, ui: &mut Ui) -> Option<LoadedPosition> {
+        let mut loaded_position = None;
+
+        ui.horizontal(|ui| {
+            ui.label("Moves:");
+
+            let response = ui.add(
+                TextEdit::singleline(&mut self.text)
+                    .hint_text("e.g. 4445")
+                    .desired_width(200.0),
+            );
+
+            if response.changed() {
+                loaded_position = self.validate();
+            }
+
+            match &self.feedback {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(message)) => {
+                    ui.label(RichText::new(message).color(Color32::RED));
+                }
+                None => (),
+            }
+        });
+
+        loaded_position
+    }
+
+    /// Replaces the text with the given move string, as if the user had entered it.
+    ///
+    /// Returns the resulting position if the move string is valid.
+    pub fn load(&mut self, moves: &str) -> Option<LoadedPosition> {
+        self.text = moves.to_owned();
+
+        self.validate()
+    }
+
+    /// Checks whether the current text is a legal move string, updating the feedback.
+    ///
+    /// Returns the resulting position if it is.
+    fn validate(&mut self) -> Option<LoadedPosition> {
+        match GameManager::from_moves(&self.text) {
+            Ok(manager) => {
+                let state = manager.state(None);
+                let current_player = if state.turn {
+                    PieceState::PlayerTwo
+                } else {
+                    PieceState::PlayerOne
+                };
+
+                self.feedback = Some(Ok(format!("Loaded {} moves", state.moves_played)));
+
+                Some(LoadedPosition {
+                    moves: self.text.clone(),
+                    position: manager.get_position(),
+                    current_player,
+                    game_state: state.result,
+                })
+            }
+            Err(error_message) => {
+                self.feedback = Some(Err(error_message));
+
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Serves the state of the game being played as JSON over a small local HTTP server, so
+//! streamers can build browser source overlays that stay in sync with the app.
+//!
+//! `GET /state` returns the latest OverlayState, and is allowed from any origin so that
+//! overlays can be opened straight from a file.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use serde::Serialize;
+
+use crate::{
+    game_engine::{
+        game_manager::{GameManager, GameOver},
+        win_probability,
+    },
+    log::{log_message, LogType},
+    user_interface::view_model::BoardView,
+};
+
+/// The port the overlay is served on, at http://127.0.0.1:7878/state.
+pub const DEFAULT_OVERLAY_PORT: u16 = 7878;
+
+/// The engine's evaluation of playing in a column, for the player about to move.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColumnEvaluation {
+    pub score: isize,
+    /// The chance of winning after playing there, from 0 to 1.
+    pub win_probability: f64,
+}
+
+/// Everything an overlay needs to show the game.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OverlayState {
+    /// The moves played, as columns numbered from 1.
+    pub moves: String,
+    pub board: BoardView,
+    /// How the game ended, e.g. "Red wins", or None if it's still going.
+    pub result: Option<String>,
+    /// The names of Red and Blue.
+    pub players: [String; 2],
+    /// The engine's evaluation of each column, numbered from 0, for the player about to
+    /// move.
+    pub evaluations: BTreeMap<u8, ColumnEvaluation>,
+    /// How many seconds the player about to move has left, if moves are timed.
+    pub seconds_remaining: Option<f32>,
+}
+
+impl OverlayState {
+    /// Describes the game after the given moves, as columns numbered from 1.
+    pub fn new(
+        moves: &str,
+        move_scores: &HashMap<u8, isize>,
+        players: [String; 2],
+        seconds_remaining: Option<f32>,
+    ) -> Result<OverlayState, String> {
+        let result = match GameManager::from_moves(moves)?.state(None).result {
+            GameOver::NoWin => None,
+            GameOver::Tie => Some("Draw"),
+            GameOver::OneWins => Some("Red wins"),
+            GameOver::TwoWins => Some("Blue wins"),
+        };
+
+        Ok(OverlayState {
+            moves: moves.to_owned(),
+            // Evaluations are given separately, rather than as hints on the board
+            board: BoardView::from_moves(moves, &Default::default(), false)?,
+            result: result.map(str::to_owned),
+            players,
+            evaluations: move_scores
+                .iter()
+                .map(|(column, score)| {
+                    let evaluation = ColumnEvaluation {
+                        score: *score,
+                        win_probability: win_probability::win_probability(*score),
+                    };
+
+                    (*column, evaluation)
+                })
+                .collect(),
+            seconds_remaining,
+        })
+    }
+}
+
+/// A local HTTP server answering with the latest state it's been given.
+pub struct OverlayServer {
+    state: Arc<Mutex<String>>,
+}
+
+impl OverlayServer {
+    /// Starts serving on the port, on the local machine only.
+    pub fn start(port: u16) -> Result<OverlayServer, String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .map_err(|error| format!("Couldn't serve the overlay on port {} - {}", port, error))?;
+        let state = Arc::new(Mutex::new("null".to_owned()));
+
+        let served_state = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(error) = answer(stream, &served_state) {
+                    log_message(
+                        LogType::Error,
+                        format!("Couldn't answer an overlay request - {}", error),
+                    );
+                }
+            }
+        });
+
+        Ok(OverlayServer { state })
+    }
+
+    /// Replaces the state served to overlays.
+    pub fn publish(&self, state: &OverlayState) {
+        match serde_json::to_string(state) {
+            Ok(json) => *self.state.lock().expect("Overlay state poisoned") = json,
+            Err(error) => log_message(
+                LogType::Error,
+                format!("Couldn't serialize the overlay state - {}", error),
+            ),
+        }
+    }
+}
+
+/// Reads a request from the stream and writes the response to it.
+fn answer(stream: TcpStream, state: &Mutex<String>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // The headers don't change the answer, but are read so the client sees a clean close
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let body = state.lock().expect("Overlay state poisoned").clone();
+    reader
+        .into_inner()
+        .write_all(response(&request_line, &body).as_bytes())
+}
+
+/// Returns the HTTP response to a request, given its first line, e.g. "GET /state HTTP/1.1".
+fn response(request_line: &str, body: &str) -> String {
+    let mut words = request_line.split_whitespace();
+
+    let (status, content_type, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/state")) => ("200 OK", "application/json", body),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not found"),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Only GET is supported",
+        ),
+    };
+
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use crate::user_interface::overlay::{response, OverlayServer, OverlayState};
+
+    fn players() -> [String; 2] {
+        ["Human".to_owned(), "Computer".to_owned()]
+    }
+
+    #[test]
+    fn states() {
+        let state = OverlayState::new(
+            "445",
+            &HashMap::from([(3, 0), (4, isize::MAX)]),
+            players(),
+            Some(12.0),
+        )
+        .unwrap();
+        assert_eq!(state.result, None);
+        assert_eq!(state.board.pieces.len(), 3);
+        assert_eq!(state.evaluations[&3].win_probability, 0.5);
+        assert_eq!(state.evaluations[&4].win_probability, 1.0);
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.contains("\"moves\":\"445\""));
+        assert!(json.contains("\"players\":[\"Human\",\"Computer\"]"));
+        assert!(json.contains("\"seconds_remaining\":12.0"));
+
+        let state = OverlayState::new("1212121", &HashMap::new(), players(), None).unwrap();
+        assert_eq!(state.result.as_deref(), Some("Red wins"));
+
+        assert!(OverlayState::new("8", &HashMap::new(), players(), None).is_err());
+    }
+
+    #[test]
+    fn responses() {
+        let ok = response("GET /state HTTP/1.1\r\n", "{}");
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(ok.contains("Content-Length: 2\r\n"));
+        assert!(ok.ends_with("\r\n\r\n{}"));
+
+        assert!(response("GET / HTTP/1.1", "{}").starts_with("HTTP/1.1 404"));
+        assert!(response("POST /state HTTP/1.1", "{}").starts_with("HTTP/1.1 405"));
+    }
+
+    #[test]
+    fn serving() {
+        // Any free port will do
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = OverlayServer::start(port).unwrap();
+        server.publish(&OverlayState::new("4", &HashMap::new(), players(), None).unwrap());
+
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream
+            .write_all(b"GET /state HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+
+        assert!(answer.starts_with("HTTP/1.1 200 OK"));
+        assert!(answer.contains("\"moves\":\"4\""));
+    }
+}
@@ -0,0 +1,317 @@
+//! Mirrors the game onto a physical LED Connect Four board, and takes moves from its column
+//! buttons, over a line based text protocol on a serial port.
+//!
+//! The board sends `press 3` when a column's button is pressed and `newgame` when its reset
+//! button is, and is sent `clear`, `drop 3 1` to light up the next space in a column for a
+//! player, and `win 0,0 1,1 2,2 3,3` to light up a connect four as (column, row from the
+//! bottom) pairs. Columns are numbered from 0, and anything else the board sends is logged.
+//!
+//! The serial port is opened like a file, e.g. `/dev/ttyUSB0`, so its speed has to be set
+//! beforehand, e.g. with `stty -F /dev/ttyUSB0 115200 raw`. Boards wired to GPIO pins need a
+//! microcontroller, or a small program, speaking the protocol in between.
+
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use crate::{
+    consts::BOARD_WIDTH,
+    game_engine::{
+        column_label::{split_moves, ColumnLabel},
+        game_manager::GameManager,
+        power_ups::uses_power_ups,
+    },
+    log::{log_message, LogType},
+    user_interface::view_model::{winning_line, Player},
+};
+
+/// Something that happened on the physical board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardEvent {
+    /// A column's button was pressed, asking to play there.
+    ColumnPressed(usize),
+    /// The reset button was pressed, asking for a new game.
+    NewGamePressed,
+    /// The connection to the board was lost.
+    Disconnected(String),
+}
+
+/// Something the physical board should show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardCommand {
+    /// Turns every light off.
+    Clear,
+    /// Lights up the lowest empty space in the column for the player.
+    Drop { column: usize, player: Player },
+    /// Lights up the spaces making up a connect four, as (column, row from the bottom).
+    Win(Vec<(usize, usize)>),
+}
+
+/// Writes a command as a line of the protocol, without the newline.
+pub fn write_board_command(command: &BoardCommand) -> String {
+    match command {
+        BoardCommand::Clear => "clear".to_owned(),
+        BoardCommand::Drop { column, player } => format!(
+            "drop {} {}",
+            column,
+            match player {
+                Player::One => 1,
+                Player::Two => 2,
+            }
+        ),
+        BoardCommand::Win(line) => {
+            let spaces: Vec<String> = line
+                .iter()
+                .map(|(column, row)| format!("{},{}", column, row))
+                .collect();
+
+            format!("win {}", spaces.join(" "))
+        }
+    }
+}
+
+/// Parses a line sent by the physical board.
+pub fn parse_board_event(line: &str) -> Result<BoardEvent, String> {
+    let mut words = line.split_whitespace();
+
+    match (words.next(), words.next()) {
+        (Some("press"), Some(column)) => match column.parse() {
+            Ok(column) if column < BOARD_WIDTH as usize => Ok(BoardEvent::ColumnPressed(column)),
+            _ => Err(format!("'{}' isn't a column", column)),
+        },
+        (Some("newgame"), None) => Ok(BoardEvent::NewGamePressed),
+        _ => Err(format!("Unknown event '{}'", line)),
+    }
+}
+
+/// Returns the commands that make the physical board show the position after the moves, as
+/// columns numbered from 1.
+pub fn position_commands(moves: &str) -> Result<Vec<BoardCommand>, String> {
+    let manager = GameManager::from_moves(moves)?;
+    if uses_power_ups(moves) {
+        return Err("The physical board can't clear a column".to_owned());
+    }
+
+    let mut commands = vec![BoardCommand::Clear];
+    let labels = split_moves(moves)
+        .into_iter()
+        .map(|written| ColumnLabel::parse(&written));
+    for (index, label) in labels.flatten().enumerate() {
+        commands.push(BoardCommand::Drop {
+            column: label.index() as usize,
+            player: if index.is_multiple_of(2) {
+                Player::One
+            } else {
+                Player::Two
+            },
+        });
+    }
+    if let Some(line) = winning_line(&manager.get_position()) {
+        commands.push(BoardCommand::Win(line));
+    }
+
+    Ok(commands)
+}
+
+/// A connection to a physical board, with threads reading its events and writing commands
+/// to it.
+pub struct PhysicalBoard {
+    sender: Sender<BoardCommand>,
+    receiver: Receiver<BoardEvent>,
+}
+
+impl PhysicalBoard {
+    /// Connects to the board on the serial port at the path, e.g. `/dev/ttyUSB0`.
+    ///
+    /// poke is called whenever the board sends an event, e.g. to wake up the UI.
+    pub fn connect(path: &Path, poke: impl Fn() + Send + 'static) -> Result<Self, String> {
+        let port = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|error| format!("Couldn't open {} - {}", path.display(), error))?;
+        let writer = port.try_clone().map_err(|error| error.to_string())?;
+
+        Ok(PhysicalBoard::from_streams(port, writer, poke))
+    }
+
+    /// Talks to a board that sends its events to reader and is sent commands by writer.
+    fn from_streams(
+        reader: impl Read + Send + 'static,
+        mut writer: impl Write + Send + 'static,
+        poke: impl Fn() + Send + 'static,
+    ) -> Self {
+        let (command_sender, command_receiver) = channel::<BoardCommand>();
+        let (event_sender, event_receiver) = channel();
+
+        thread::spawn(move || {
+            // Write failures mean the board is gone, which the reading thread reports
+            for command in command_receiver {
+                let _ = writeln!(writer, "{}", write_board_command(&command));
+                let _ = writer.flush();
+            }
+        });
+
+        thread::spawn(move || {
+            let mut error = "The board hung up".to_owned();
+            for line in BufReader::new(reader).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(read_error) => {
+                        error = read_error.to_string();
+                        break;
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match parse_board_event(&line) {
+                    Ok(event) => {
+                        if event_sender.send(event).is_err() {
+                            return;
+                        }
+                        poke();
+                    }
+                    // Anything that isn't an event is the board's own logging
+                    Err(_) => log_message(LogType::AsyncMessage, format!("Board - {}", line)),
+                }
+            }
+
+            let _ = event_sender.send(BoardEvent::Disconnected(error));
+            poke();
+        });
+
+        PhysicalBoard {
+            sender: command_sender,
+            receiver: event_receiver,
+        }
+    }
+
+    /// Sends commands to the board. Failures show up as a Disconnected event.
+    pub fn send(&self, commands: impl IntoIterator<Item = BoardCommand>) {
+        for command in commands {
+            let _ = self.sender.send(command);
+        }
+    }
+
+    /// Returns the next event from the board, if there is one.
+    pub fn try_recv(&self) -> Option<BoardEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Cursor, Write},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::user_interface::{
+        physical_board::{
+            parse_board_event, position_commands, write_board_command, BoardCommand, BoardEvent,
+            PhysicalBoard,
+        },
+        view_model::Player,
+    };
+
+    /// Collects everything written to it, for checking what was sent to a board.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn protocol() {
+        assert_eq!(
+            parse_board_event("press 3"),
+            Ok(BoardEvent::ColumnPressed(3))
+        );
+        assert_eq!(parse_board_event("newgame"), Ok(BoardEvent::NewGamePressed));
+        assert!(parse_board_event("press 7").is_err());
+        assert!(parse_board_event("press").is_err());
+        assert!(parse_board_event("booting").is_err());
+
+        assert_eq!(write_board_command(&BoardCommand::Clear), "clear");
+        assert_eq!(
+            write_board_command(&BoardCommand::Drop {
+                column: 3,
+                player: Player::Two
+            }),
+            "drop 3 2"
+        );
+        assert_eq!(
+            write_board_command(&BoardCommand::Win(vec![(0, 0), (0, 1), (0, 2), (0, 3)])),
+            "win 0,0 0,1 0,2 0,3"
+        );
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(
+            position_commands("45").unwrap(),
+            vec![
+                BoardCommand::Clear,
+                BoardCommand::Drop {
+                    column: 3,
+                    player: Player::One
+                },
+                BoardCommand::Drop {
+                    column: 4,
+                    player: Player::Two
+                },
+            ]
+        );
+
+        let commands = position_commands("1212121").unwrap();
+        assert_eq!(commands.len(), 9);
+        assert!(matches!(commands.last(), Some(BoardCommand::Win(_))));
+
+        assert!(position_commands("8").is_err());
+        assert!(position_commands("4445x4").is_err());
+    }
+
+    #[test]
+    fn connection() {
+        let written = SharedBuffer::default();
+        let board = PhysicalBoard::from_streams(
+            Cursor::new("booting\npress 2\n\nnewgame\n"),
+            written.clone(),
+            || (),
+        );
+
+        board.send([BoardCommand::Clear]);
+
+        let mut events = Vec::new();
+        while !matches!(events.last(), Some(BoardEvent::Disconnected(_))) {
+            match board.try_recv() {
+                Some(event) => events.push(event),
+                None => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        assert_eq!(
+            events[..2],
+            [BoardEvent::ColumnPressed(2), BoardEvent::NewGamePressed]
+        );
+
+        while written.0.lock().unwrap().is_empty() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(written.0.lock().unwrap().as_slice(), b"clear\n");
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use egui::{Context, Id, Pos2, Ui};
+use rand::seq::SliceRandom;
+
+use crate::{
+    game_engine::puzzle::{
+        load_puzzle_database, Puzzle, PuzzleEntry, PuzzleLevel, PuzzleProgress,
+        DEFAULT_PUZZLE_DATABASE,
+    },
+    profile::{load_puzzle_rush_high_score, save_puzzle_rush_high_score},
+    user_interface::board::{Board, PieceState},
+};
+
+/// How long a puzzle rush lasts.
+const RUSH_TIME: Duration = Duration::from_secs(180);
+
+/// How many puzzles can be missed before a puzzle rush ends early.
+const MAX_MISSES: usize = 3;
+
+/// Solving as many puzzles as possible against the clock, with the puzzles getting harder
+/// as more are solved, first needing longer wins and then on larger boards.
+///
+/// Puzzles are drawn from the puzzle database when it has any of the right level, and
+/// generated otherwise. The most puzzles solved is kept as a high score in the profile.
+pub struct PuzzleRush {
+    board: Board,
+    /// The puzzle being solved, or None between puzzles.
+    puzzle: Option<Puzzle>,
+    /// The next puzzle, being generated in the background.
+    next_puzzle: Receiver<Result<Puzzle, String>>,
+    database: Arc<Vec<PuzzleEntry>>,
+    /// The other player's reply to the last move, played once that move's piece has landed.
+    pending_reply: Option<u8>,
+    started: Instant,
+    solved: usize,
+    missed: usize,
+    /// Why the rush ended, once it has.
+    finished: Option<String>,
+    directory: PathBuf,
+    high_score: usize,
+}
+
+impl PuzzleRush {
+    /// Starts a puzzle rush, keeping the high score in the given profile directory.
+    pub fn new(ctx: &Context, directory: PathBuf) -> PuzzleRush {
+        // Without a database every puzzle is generated
+        let database =
+            Arc::new(load_puzzle_database(Path::new(DEFAULT_PUZZLE_DATABASE)).unwrap_or_default());
+
+        PuzzleRush {
+            board: Board::new(Id::new("Puzzle Rush Board"), Pos2 { x: 0.0, y: 0.0 }),
+            puzzle: None,
+            next_puzzle: generate(ctx, PuzzleLevel::for_rush(0), database.clone()),
+            database,
+            pending_reply: None,
+            started: Instant::now(),
+            solved: 0,
+            missed: 0,
+            finished: None,
+            high_score: load_puzzle_rush_high_score(&directory),
+            directory,
+        }
+    }
+
+    /// Returns how long is left on the clock.
+    fn time_left(&self) -> Duration {
+        RUSH_TIME.saturating_sub(self.started.elapsed())
+    }
+
+    /// Ends the rush, saving the score if it's a new high score.
+    fn finish(&mut self, reason: &str) {
+        self.puzzle = None;
+        self.board.lock();
+
+        let mut message = format!("{} You solved {}.", reason, self.solved);
+        if self.solved > self.high_score {
+            self.high_score = self.solved;
+            message.push_str(" That's a new high score!");
+            if let Err(error) = save_puzzle_rush_high_score(&self.directory, self.high_score) {
+                message.push_str(&format!(" Couldn't save it - {}", error));
+            }
+        }
+        self.finished = Some(message);
+    }
+
+    /// Shows the next puzzle once it's been generated and the last move has landed.
+    fn show_next_puzzle(&mut self) {
+        if self.board.is_piece_falling() {
+            return;
+        }
+
+        let puzzle = match self.next_puzzle.try_recv() {
+            Ok(Ok(puzzle)) => puzzle,
+            Ok(Err(error)) => {
+                self.finish(&format!("Couldn't make a puzzle - {}.", error));
+                return;
+            }
+            Err(_) => return,
+        };
+
+        let board = puzzle.board();
+        self.board = Board::with_config(
+            Id::new("Puzzle Rush Board"),
+            Pos2 { x: 0.0, y: 0.0 },
+            board.config(),
+        );
+        self.board
+            .set_position(board.to_rows(), player_piece(puzzle.turn()));
+        self.board.unlock();
+        self.puzzle = Some(puzzle);
+    }
+
+    /// Plays the column in the puzzle being solved, moving on to the next puzzle once it's
+    /// solved or missed.
+    fn play(&mut self, ctx: &Context, column: usize) {
+        let Some(puzzle) = &mut self.puzzle else {
+            return;
+        };
+        if !self.board.accepts_drop(column) {
+            return;
+        }
+        let player = player_piece(puzzle.turn());
+
+        let progress = match puzzle.play(column as u8) {
+            Ok(progress) => progress,
+            Err(_) => return,
+        };
+        if let Err(error) = self.board.drop_piece(ctx, column, player) {
+            self.finish(&format!("The board couldn't show the move - {}.", error));
+            return;
+        }
+        self.board.lock();
+
+        match progress {
+            PuzzleProgress::Continues(reply) => self.pending_reply = Some(reply),
+            PuzzleProgress::Solved | PuzzleProgress::Missed => {
+                if progress == PuzzleProgress::Solved {
+                    self.solved += 1;
+                } else {
+                    self.missed += 1;
+                }
+                self.puzzle = None;
+
+                if self.missed == MAX_MISSES {
+                    self.finish(&format!("{} puzzles missed.", MAX_MISSES));
+                } else {
+                    self.next_puzzle = generate(
+                        ctx,
+                        PuzzleLevel::for_rush(self.solved),
+                        self.database.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Renders the board, playing clicked columns and the other player's replies.
+    pub fn update(&mut self, ctx: &Context, ui: &mut Ui) {
+        if self.finished.is_none() {
+            if self.time_left().is_zero() {
+                self.finish("Time's up!");
+            } else if self.puzzle.is_none() {
+                self.show_next_puzzle();
+            }
+            // Keeps the clock ticking
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
+
+        if !self.board.is_piece_falling() {
+            if let (Some(reply), Some(puzzle)) = (self.pending_reply.take(), &self.puzzle) {
+                match self
+                    .board
+                    .drop_piece(ctx, reply as usize, player_piece(!puzzle.turn()))
+                {
+                    Ok(()) => self.board.unlock(),
+                    Err(error) => {
+                        self.finish(&format!("The board couldn't show the reply - {}.", error))
+                    }
+                }
+            }
+        }
+
+        let mut clicked_column = None;
+        for (column, response) in self.board.render(ctx, ui) {
+            if response.clicked() {
+                clicked_column = Some(column);
+            }
+        }
+        if let Some(column) = clicked_column {
+            self.play(ctx, column);
+        }
+    }
+
+    /// Renders the clock, the score, and how hard the current puzzle is.
+    ///
+    /// Returns whether the user asked for another rush once this one is over.
+    pub fn render_controls(&mut self, ui: &mut Ui) -> bool {
+        ui.heading("Puzzle Rush");
+
+        let seconds = self.time_left().as_secs();
+        ui.label(format!("Time left: {}:{:02}", seconds / 60, seconds % 60));
+        ui.label(format!("Solved: {}", self.solved));
+        ui.label(format!("Missed: {} of {}", self.missed, MAX_MISSES));
+        ui.label(format!("High score: {}", self.high_score));
+
+        ui.separator();
+
+        if let Some(message) = &self.finished {
+            ui.label(message);
+            return ui.button("Play again").clicked();
+        }
+
+        match &self.puzzle {
+            Some(puzzle) => {
+                let config = puzzle.board().config();
+                let player = if puzzle.turn() { "Blue" } else { "Red" };
+                ui.label(format!(
+                    "{} to play and win in {} on a {}x{} board.",
+                    player,
+                    puzzle.moves_to_win(),
+                    config.width,
+                    config.height
+                ));
+            }
+            None => {
+                ui.label("Setting up the next puzzle...");
+            }
+        }
+
+        false
+    }
+}
+
+/// Starts finding a puzzle of the given level in the background, repainting once it's ready.
+///
+/// A random puzzle of the level is taken from the database if there's one, otherwise one is
+/// generated.
+fn generate(
+    ctx: &Context,
+    level: PuzzleLevel,
+    database: Arc<Vec<PuzzleEntry>>,
+) -> Receiver<Result<Puzzle, String>> {
+    let (sender, receiver) = channel();
+    let ctx = ctx.clone();
+
+    thread::spawn(move || {
+        let mut rng = rand::thread_rng();
+        let entries: Vec<&PuzzleEntry> = database
+            .iter()
+            .filter(|entry| entry.is_level(level))
+            .collect();
+
+        let puzzle = match entries.choose(&mut rng) {
+            Some(entry) => Puzzle::from_moves(&entry.moves),
+            None => Puzzle::generate(level, &mut rng),
+        };
+        // The rush may have ended, hanging up, while the puzzle was being made
+        let _ = sender.send(puzzle);
+        ctx.request_repaint();
+    });
+
+    receiver
+}
+
+/// Returns the piece the given player plays with, false for player one and true for player
+/// two.
+fn player_piece(player: bool) -> PieceState {
+    if player {
+        PieceState::PlayerTwo
+    } else {
+        PieceState::PlayerOne
+    }
+}
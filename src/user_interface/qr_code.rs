@@ -0,0 +1,45 @@
+use egui::{Color32, Pos2, Rect, Sense, Ui, Vec2};
+use qrcode::{Color, QrCode};
+
+/// How many light modules of margin surround the code, which scanners need to find it.
+const QUIET_ZONE: usize = 4;
+
+/// Draws the text as a QR code, sized to fit within a square of the given width.
+///
+/// Shows an explanation instead if the text is too long to fit in a QR code.
+pub fn render_qr_code(ui: &mut Ui, text: &str, width: f32) {
+    let code = match QrCode::new(text) {
+        Ok(code) => code,
+        Err(error) => {
+            ui.label(format!("Couldn't make a QR code: {}", error));
+            return;
+        }
+    };
+
+    let modules = code.width() + 2 * QUIET_ZONE;
+    let module_size = (width / modules as f32).floor().max(1.0);
+    let (response, painter) =
+        ui.allocate_painter(Vec2::splat(module_size * modules as f32), Sense::hover());
+    let origin = response.rect.min;
+
+    painter.rect_filled(response.rect, 0.0, Color32::WHITE);
+    for (index, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Light {
+            continue;
+        }
+
+        let x = (index % code.width() + QUIET_ZONE) as f32 * module_size;
+        let y = (index / code.width() + QUIET_ZONE) as f32 * module_size;
+        painter.rect_filled(
+            Rect::from_min_size(
+                Pos2 {
+                    x: origin.x + x,
+                    y: origin.y + y,
+                },
+                Vec2::splat(module_size),
+            ),
+            0.0,
+            Color32::BLACK,
+        );
+    }
+}
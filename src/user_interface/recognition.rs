@@ -0,0 +1,339 @@
+//! Recognizes the position on a Connect Four board in a screenshot, so it can be set up for
+//! analysis without typing in the moves.
+//!
+//! The board is found as the most common bright color in the image, and is split
+//! into an even grid of spaces, whose centers are classified by color. Recognition only has
+//! to be close, since the position can be corrected by hand before it's used.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::column_label::ColumnLabel,
+    user_interface::{
+        settings::{color_difference, PieceColors},
+        view_model::winning_line,
+    },
+};
+
+/// A position as array[row][col], with 0 for empty, 1 for Red's and 2 for Blue's pieces.
+pub type Position = [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+
+/// The red and yellow of a physical board's pieces, which screenshots of other apps tend to
+/// use too.
+const CLASSIC_PIECE_COLORS: PieceColors = PieceColors {
+    player_one: [220, 30, 40],
+    player_two: [245, 210, 30],
+};
+
+/// How different a space's color can be from a piece's color, as the CIE76 difference, for
+/// the space to still hold that piece.
+const PIECE_TOLERANCE: f32 = 50.0;
+/// How different a pixel's color can be from the board's, for it to still be the board.
+const BOARD_TOLERANCE: f32 = 25.0;
+/// How far apart a color's channels have to be for it to be bright enough to be the board.
+const MIN_BOARD_SATURATION: u8 = 60;
+/// The smallest each space can be in the screenshot, in pixels.
+const MIN_SPACE_SIZE: usize = 4;
+
+/// Finds the board in an image, given as rows of RGBA pixels, and reads the position off
+/// it. Pieces are recognized in the given colors, or in red and yellow.
+pub fn recognize_position(
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+    colors: PieceColors,
+) -> Result<Position, String> {
+    if rgba.len() != width * height * 4 {
+        return Err("The image's size doesn't match its pixels".to_owned());
+    }
+    let pixel = |x: usize, y: usize| {
+        let start = (y * width + x) * 4;
+        [rgba[start], rgba[start + 1], rgba[start + 2]]
+    };
+
+    // The board's frame covers more of it than either player's pieces, so it's the most
+    // common bright color. Colors are counted in coarse buckets so shading doesn't split it up
+    let mut buckets: HashMap<[u8; 3], (usize, [usize; 3])> = HashMap::new();
+    for y in 0..height {
+        for x in 0..width {
+            let color = pixel(x, y);
+            let saturation = color.iter().max().unwrap() - color.iter().min().unwrap();
+            if saturation < MIN_BOARD_SATURATION {
+                continue;
+            }
+
+            let (count, sum) = buckets
+                .entry(color.map(|channel| channel >> 4))
+                .or_default();
+            *count += 1;
+            for channel in 0..3 {
+                sum[channel] += color[channel] as usize;
+            }
+        }
+    }
+    let (count, sum) = buckets
+        .into_values()
+        .max_by_key(|(count, _)| *count)
+        .ok_or_else(|| "Couldn't find a board in the image".to_owned())?;
+    let board_color = sum.map(|channel| (channel / count) as u8);
+
+    // Stray pixels of the board's color, e.g. in the rest of a screenshot, are ignored by
+    // only counting rows and columns that are mostly board
+    let mut column_counts = vec![0; width];
+    let mut row_counts = vec![0; height];
+    for (y, row_count) in row_counts.iter_mut().enumerate() {
+        for (x, column_count) in column_counts.iter_mut().enumerate() {
+            if color_difference(pixel(x, y), board_color) < BOARD_TOLERANCE {
+                *column_count += 1;
+                *row_count += 1;
+            }
+        }
+    }
+    let (left, right) = busiest_span(&column_counts);
+    let (top, bottom) = busiest_span(&row_counts);
+
+    let space_width = (right + 1 - left) as f32 / BOARD_WIDTH as f32;
+    let space_height = (bottom + 1 - top) as f32 / BOARD_HEIGHT as f32;
+    if space_width < MIN_SPACE_SIZE as f32 || space_height < MIN_SPACE_SIZE as f32 {
+        return Err("The board in the image is too small to read".to_owned());
+    }
+
+    let mut position = [[0; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+    for (row, pieces) in position.iter_mut().enumerate() {
+        for (column, piece) in pieces.iter_mut().enumerate() {
+            let center_x = left as f32 + space_width * (column as f32 + 0.5);
+            let center_y = top as f32 + space_height * (row as f32 + 0.5);
+            // Sampling a patch around the center, so that a single stray pixel can't decide it
+            let reach = (space_width.min(space_height) / 8.0) as usize;
+
+            let mut sum = [0; 3];
+            let mut samples = 0;
+            for y in center_y as usize - reach..=center_y as usize + reach {
+                for x in center_x as usize - reach..=center_x as usize + reach {
+                    let color = pixel(x, y);
+                    for channel in 0..3 {
+                        sum[channel] += color[channel] as usize;
+                    }
+                    samples += 1;
+                }
+            }
+
+            *piece = piece_in(sum.map(|channel| (channel / samples) as u8), colors).unwrap_or(0);
+        }
+    }
+
+    Ok(position)
+}
+
+/// Returns which player's piece, 1 or 2, is the given color, if either.
+fn piece_in(color: [u8; 3], colors: PieceColors) -> Option<u8> {
+    let difference = |player_colors: [[u8; 3]; 2]| {
+        player_colors
+            .iter()
+            .map(|player_color| color_difference(color, *player_color))
+            .fold(f32::MAX, f32::min)
+    };
+    let one = difference([colors.player_one, CLASSIC_PIECE_COLORS.player_one]);
+    let two = difference([colors.player_two, CLASSIC_PIECE_COLORS.player_two]);
+
+    match (one < PIECE_TOLERANCE, two < PIECE_TOLERANCE) {
+        (true, true) if one <= two => Some(1),
+        (true, true) => Some(2),
+        (true, false) => Some(1),
+        (false, true) => Some(2),
+        (false, false) => None,
+    }
+}
+
+/// Returns the first and last index whose count is at least a quarter of the largest count.
+fn busiest_span(counts: &[usize]) -> (usize, usize) {
+    let threshold = counts.iter().max().copied().unwrap_or(0).div_ceil(4).max(1);
+
+    let first = counts.iter().position(|count| *count >= threshold);
+    let last = counts.iter().rposition(|count| *count >= threshold);
+
+    (first.unwrap_or(0), last.unwrap_or(0))
+}
+
+/// Finds an order the pieces could have been played in to reach the position, as columns
+/// numbered from 1.
+///
+/// Fails if the position couldn't come up in a game, e.g. if a piece is floating, the
+/// players have the wrong numbers of pieces, or the game would have ended sooner.
+pub fn moves_for_position(position: &Position) -> Result<String, String> {
+    let mut counts = [0; 3];
+    for column in 0..BOARD_WIDTH as usize {
+        let mut seen_piece = false;
+        for pieces in position {
+            let piece = pieces[column];
+            if piece > 2 {
+                return Err(format!("Unknown piece {}", piece));
+            }
+            if piece == 0 && seen_piece {
+                return Err(format!(
+                    "There's a gap under a piece in column {}",
+                    ColumnLabel::new(column as u8)
+                ));
+            }
+            seen_piece |= piece != 0;
+            counts[piece as usize] += 1;
+        }
+    }
+    if counts[1] != counts[2] && counts[1] != counts[2] + 1 {
+        return Err("Red should have as many pieces as Blue, or one more".to_owned());
+    }
+
+    let mut position = *position;
+    let mut moves = Vec::new();
+    if !take_back(
+        &mut position,
+        counts[1] + counts[2],
+        &mut moves,
+        &mut HashSet::new(),
+    ) {
+        return Err("The game would have ended before reaching this position".to_owned());
+    }
+
+    Ok(ColumnLabel::move_string(moves))
+}
+
+/// Takes back the position's pieces one at a time, last move first, without passing through
+/// a position with a connect four. Once every piece is taken back, the columns are pushed to
+/// moves in the order they were played.
+///
+/// Positions that turned out to be dead ends are remembered in failed. Returns whether every
+/// piece could be taken back, leaving the position as it was if not.
+fn take_back(
+    position: &mut Position,
+    pieces: usize,
+    moves: &mut Vec<u8>,
+    failed: &mut HashSet<Position>,
+) -> bool {
+    if pieces == 0 {
+        return true;
+    }
+    if failed.contains(position) {
+        return false;
+    }
+
+    // Red moves first, so an odd number of pieces means Red moved last
+    let last_player = if pieces.is_multiple_of(2) { 2 } else { 1 };
+    for column in 0..BOARD_WIDTH as usize {
+        let Some(row) = (0..BOARD_HEIGHT as usize).find(|row| position[*row][column] != 0) else {
+            continue;
+        };
+        if position[row][column] != last_player {
+            continue;
+        }
+
+        position[row][column] = 0;
+        if winning_line(position).is_none() && take_back(position, pieces - 1, moves, failed) {
+            moves.push(column as u8);
+            return true;
+        }
+        position[row][column] = last_player;
+    }
+
+    failed.insert(*position);
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        consts::{BOARD_HEIGHT, BOARD_WIDTH},
+        game_engine::game_manager::GameManager,
+        user_interface::{
+            recognition::{moves_for_position, recognize_position, Position},
+            settings::PieceColors,
+        },
+    };
+
+    /// The size of each space in the drawn boards, in pixels.
+    const SPACE: usize = 20;
+    /// The space around the drawn boards, in pixels.
+    const MARGIN: usize = 15;
+
+    /// Draws the position as a gold board with square holes on a white background, as rows
+    /// of RGBA pixels, returning the image's width and height along with them.
+    fn draw(position: &Position, piece_colors: [[u8; 3]; 2]) -> (usize, usize, Vec<u8>) {
+        let width = BOARD_WIDTH as usize * SPACE + MARGIN * 2;
+        let height = BOARD_HEIGHT as usize * SPACE + MARGIN * 2;
+
+        let mut rgba = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let on_board =
+                    (MARGIN..width - MARGIN).contains(&x) && (MARGIN..height - MARGIN).contains(&y);
+                let color = if on_board {
+                    let (column, row) = ((x - MARGIN) / SPACE, (y - MARGIN) / SPACE);
+                    let (within_x, within_y) = ((x - MARGIN) % SPACE, (y - MARGIN) % SPACE);
+
+                    if (4..SPACE - 4).contains(&within_x) && (4..SPACE - 4).contains(&within_y) {
+                        match position[row][column] {
+                            0 => [255, 255, 255],
+                            piece => piece_colors[piece as usize - 1],
+                        }
+                    } else {
+                        [255, 215, 0]
+                    }
+                } else {
+                    [255, 255, 255]
+                };
+
+                rgba.extend_from_slice(&color);
+                rgba.push(255);
+            }
+        }
+
+        (width, height, rgba)
+    }
+
+    #[test]
+    fn recognizes_positions() {
+        let position = GameManager::from_moves("4453").unwrap().get_position();
+
+        let (width, height, rgba) = draw(&position, [[255, 0, 0], [0, 0, 255]]);
+        assert_eq!(
+            recognize_position(width, height, &rgba, PieceColors::default()),
+            Ok(position)
+        );
+
+        // Red and yellow pieces are recognized whatever the app's colors are
+        let (width, height, rgba) = draw(&position, [[230, 30, 30], [250, 220, 40]]);
+        assert_eq!(
+            recognize_position(width, height, &rgba, PieceColors::default()),
+            Ok(position)
+        );
+
+        assert!(recognize_position(2, 2, &[255; 16], PieceColors::default()).is_err());
+        assert!(recognize_position(2, 2, &[255; 4], PieceColors::default()).is_err());
+    }
+
+    #[test]
+    fn finds_moves() {
+        for moves in ["", "4453", "1212121", "44444455"] {
+            let position = GameManager::from_moves(moves).unwrap().get_position();
+            let found = moves_for_position(&position).unwrap();
+
+            assert_eq!(
+                GameManager::from_moves(&found).unwrap().get_position(),
+                position
+            );
+        }
+
+        let mut floating = [[0; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+        floating[0][0] = 1;
+        assert!(moves_for_position(&floating).is_err());
+
+        let mut too_many_blue = GameManager::from_moves("44").unwrap().get_position();
+        too_many_blue[5][4] = 2;
+        assert!(moves_for_position(&too_many_blue).is_err());
+
+        // Both players can't have connected four
+        let mut both_won = GameManager::from_moves("1212121").unwrap().get_position();
+        both_won[2][1] = 2;
+        assert!(moves_for_position(&both_won).is_err());
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::{HashMap, HashSet};
+
+use egui::{Context, Id, Pos2, ProgressBar, ScrollArea, Ui};
+
+use crate::{
+    game_engine::{
+        column_label::ColumnLabel,
+        game_manager::GameManager,
+        game_record::{GameRecord, MovePath, RecordedMove},
+        win_probability::win_probability,
+    },
+    log::{log_message, LogType},
+    user_interface::{
+        board::{Board, PieceState},
+        engine_interface::{EngineBackend, EngineHandle, EngineMessage, GameOver, UIMessage},
+        settings::ScoreDisplay,
+    },
+};
+
+/// How far each level of variation is indented in the move history.
+const VARIATION_INDENT: f32 = 16.0;
+
+/// Steps through a finished game, where any column can be played at any point to branch
+/// off into a variation, and the engine analyses each position shown, with an evaluation
+/// bar and its preferred column highlighted on the board.
+pub struct Review {
+    record: GameRecord,
+    /// The move the board is showing the position after, or None for the empty board.
+    cursor: Option<MovePath>,
+    board: Board,
+    engine: EngineHandle,
+    /// The engine's scores for the position being shown, once it's been analysed.
+    move_scores: HashMap<u8, isize>,
+    /// The column the engine prefers in the position being shown, once it's been analysed.
+    best_column: Option<u8>,
+    status_message: Option<String>,
+    /// The moves whose variations are hidden in the move history.
+    collapsed: HashSet<MovePath>,
+}
+
+impl Review {
+    /// Starts reviewing a game from its first move, with its own engine for analysis.
+    pub fn new(ctx: &Context, record: GameRecord) -> Review {
+        let engine = Review::spawn_engine(ctx);
+
+        let mut review = Review {
+            record,
+            cursor: None,
+            board: Board::new(Id::new("Review Board"), Pos2 { x: 0.0, y: 0.0 }),
+            engine,
+            move_scores: HashMap::new(),
+            best_column: None,
+            status_message: None,
+            collapsed: HashSet::new(),
+        };
+        review.go_to(None);
+
+        review
+    }
+
+    /// Spawns the engine the review's positions are analysed with.
+    fn spawn_engine(ctx: &Context) -> EngineHandle {
+        let engine_ctx = ctx.clone();
+        EngineHandle::spawn(move || engine_ctx.request_repaint(), EngineBackend::Thread)
+    }
+
+    /// Returns the game being reviewed, along with any variations added to it.
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// Shows a message under the navigation buttons, e.g. the result of exporting the game.
+    pub fn set_status_message(&mut self, message: String) {
+        self.status_message = Some(message);
+    }
+
+    /// Returns the moves leading to the position being shown, as columns numbered from 1.
+    fn current_moves(&self) -> String {
+        self.cursor
+            .as_ref()
+            .and_then(|cursor| self.record.moves_to(cursor))
+            .unwrap_or_else(|| self.record.setup().to_owned())
+    }
+
+    /// Shows the position after the last move of the main line.
+    pub fn show_last_move(&mut self) {
+        let last = self
+            .record
+            .moves
+            .len()
+            .checked_sub(1)
+            .map(MovePath::main_line);
+        self.go_to(last);
+    }
+
+    /// Shows the position after the given move, or the empty board if there's none.
+    fn go_to(&mut self, cursor: Option<MovePath>) {
+        self.cursor = cursor;
+        let moves = self.current_moves();
+        let state = GameManager::from_moves(&moves)
+            .expect("Recorded moves should be legal")
+            .state(None);
+
+        let next_player = if state.turn {
+            PieceState::PlayerTwo
+        } else {
+            PieceState::PlayerOne
+        };
+        self.board.set_position(&state.rows, next_player);
+        if state.result == GameOver::NoWin {
+            self.board.unlock();
+        } else {
+            self.board.lock();
+        }
+
+        self.analyze(&moves);
+    }
+
+    /// Has the engine start analysing the position after the moves, clearing the old
+    /// analysis.
+    fn analyze(&mut self, moves: &str) {
+        self.move_scores.clear();
+        self.best_column = None;
+        self.engine
+            .send(UIMessage::AnalyzePosition(moves.to_owned()));
+    }
+
+    /// Returns Red's chance of winning from the position being shown, by the engine's
+    /// score for the best move, or None until the engine has analysed it.
+    fn red_win_chance(&self) -> Option<f64> {
+        let best_score = *self.move_scores.values().max()?;
+        let chance = win_probability(best_score);
+
+        // The scores are for the player about to move
+        match self.current_moves().len().is_multiple_of(2) {
+            true => Some(chance),
+            false => Some(1.0 - chance),
+        }
+    }
+
+    /// Plays a column from the position being shown, branching into a variation if the
+    /// game went another way.
+    fn play(&mut self, column: usize, ctx: &Context) {
+        let path = match self.record.add_move(self.cursor.as_ref(), column as u8) {
+            Ok(path) => path,
+            Err(error) => {
+                self.status_message = Some(error);
+                return;
+            }
+        };
+        let player = if self.current_moves().len().is_multiple_of(2) {
+            PieceState::PlayerOne
+        } else {
+            PieceState::PlayerTwo
+        };
+        if let Err(error) = self.board.drop_piece(ctx, column, player) {
+            self.status_message = Some(error);
+            return;
+        }
+        self.status_message = None;
+
+        self.cursor = Some(path);
+        let moves = self.current_moves();
+        let manager = GameManager::from_moves(&moves).expect("Recorded moves should be legal");
+        if manager.is_game_over() != GameOver::NoWin {
+            self.board.lock();
+        }
+
+        self.analyze(&moves);
+    }
+
+    /// Moves the variation being shown up into the main line, keeping every line it passes
+    /// as a variation.
+    fn promote_to_main_line(&mut self) {
+        let mut cursor = match self.cursor.clone() {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        while cursor.depth() > 0 {
+            cursor = match self.record.promote_variation(&cursor) {
+                Ok(promoted) => promoted,
+                Err(error) => {
+                    self.status_message = Some(error);
+                    return;
+                }
+            };
+        }
+
+        // The moves have moved, so what was collapsed no longer lines up
+        self.collapsed.clear();
+        self.cursor = Some(cursor);
+    }
+
+    /// Removes the variation being shown, going back to the move it was an alternative to.
+    fn delete_variation(&mut self) {
+        let cursor = match &self.cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        match self.record.delete_variation(cursor) {
+            Ok(replaced) => {
+                self.collapsed.clear();
+                self.go_to(Some(replaced));
+            }
+            Err(error) => self.status_message = Some(error),
+        }
+    }
+
+    /// Returns the move after the one being shown in the same line, if there is one.
+    fn next_move(&self) -> Option<MovePath> {
+        match &self.cursor {
+            Some(cursor) => {
+                let next = cursor.next();
+                self.record.get(&next).map(|_| next)
+            }
+            None => self.record.moves.first().map(|_| MovePath::main_line(0)),
+        }
+    }
+
+    /// Processes the engine's analysis and renders the board, along with the analysis
+    /// as hints above each column, shown as score_display chooses, and the engine's
+    /// preferred column highlighted.
+    pub fn update(&mut self, ctx: &Context, ui: &mut Ui, score_display: ScoreDisplay) {
+        if let Some(message) = self.engine.try_recv() {
+            log_message(
+                LogType::AsyncMessage,
+                format!("Review EngineMessage Received - {:?}", message),
+            );
+
+            match message {
+                EngineMessage::AnalysisResult {
+                    moves,
+                    move_scores,
+                    best_column,
+                    ..
+                } => {
+                    // The user may have stepped on before the engine finished looking
+                    if moves == self.current_moves() {
+                        self.move_scores = move_scores;
+                        self.best_column = best_column;
+                    }
+                }
+                EngineMessage::Update {
+                    move_scores,
+                    principal_variation,
+                    ..
+                } => {
+                    self.move_scores = move_scores;
+                    self.best_column = principal_variation.first().copied();
+                }
+                EngineMessage::InvalidMove(error) => {
+                    self.status_message = Some(error);
+                }
+                // The engine's handle records these, and the engine is restarted below
+                EngineMessage::BackendFailed(_) | EngineMessage::EngineCrashed(_) => (),
+                EngineMessage::MoveReceipt { .. } | EngineMessage::Hint(_) => (),
+            }
+        }
+
+        // A new engine picks up the analysis of the position being shown
+        if let Some(failure) = self.engine.failure() {
+            self.engine = Review::spawn_engine(ctx);
+            self.analyze(&self.current_moves());
+            self.status_message = Some(format!("The engine was restarted - {}", failure));
+        }
+
+        let mut clicked_column = None;
+        for (column, response) in self.board.render(ctx, ui) {
+            if response.clicked() {
+                clicked_column = Some(column);
+            }
+        }
+        if let Some(column) = clicked_column {
+            self.play(column, ctx);
+        }
+        if let Some(column) = self.best_column {
+            self.board.render_column_preview(ui, column as usize);
+        }
+
+        let hints: Vec<(usize, String, f32)> = self
+            .move_scores
+            .iter()
+            .map(|(column, score)| (*column as usize, score_display.format(*score), 1.0))
+            .collect();
+        self.board.render_hints(ui, &hints);
+    }
+
+    /// Renders the navigation buttons and the move history, with each variation indented
+    /// under the move it's an alternative to.
+    pub fn render_controls(&mut self, ui: &mut Ui) {
+        ui.heading("Analysis");
+
+        match self.red_win_chance() {
+            Some(chance) => {
+                ui.add(
+                    ProgressBar::new(chance as f32)
+                        .text(format!("Red {:.0}%", chance * 100.0))
+                        .desired_width(ui.available_width()),
+                )
+                .on_hover_text("Red's chance of winning, by the engine's evaluation");
+            }
+            None => {
+                ui.label("Analysing...");
+            }
+        }
+
+        let mut go_to = None;
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("Start").clicked() {
+                go_to = Some(None);
+            }
+            if ui.button("◀").on_hover_text("Back").clicked() {
+                go_to = Some(self.cursor.as_ref().and_then(MovePath::previous));
+            }
+            if let Some(next) = self.next_move() {
+                if ui.button("▶").on_hover_text("Forward").clicked() {
+                    go_to = Some(Some(next));
+                }
+            }
+            if ui.button("Analyze").clicked() {
+                self.engine.send(UIMessage::RequestUpdate);
+            }
+        });
+
+        let in_variation = self
+            .cursor
+            .as_ref()
+            .is_some_and(|cursor| cursor.depth() > 0);
+        ui.add_enabled_ui(in_variation, |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Promote to main line")
+                    .on_hover_text("Make this variation the main line of the game")
+                    .clicked()
+                {
+                    self.promote_to_main_line();
+                }
+                if ui
+                    .button("Delete variation")
+                    .on_hover_text("Remove this variation and every variation within it")
+                    .clicked()
+                {
+                    self.delete_variation();
+                }
+            });
+        });
+
+        if let Some(message) = &self.status_message {
+            ui.label(message);
+        }
+        ui.label("Click a column to try a different move.");
+
+        ui.separator();
+
+        ScrollArea::vertical()
+            .id_source("Review History")
+            .show(ui, |ui| {
+                render_line(
+                    ui,
+                    &self.record.moves,
+                    &mut Vec::new(),
+                    self.record.first_ply(),
+                    self.cursor.as_ref(),
+                    &mut self.collapsed,
+                    &mut go_to,
+                );
+            });
+
+        if let Some(cursor) = go_to {
+            self.go_to(cursor);
+        }
+    }
+}
+
+/// Renders a line of moves in the move history, followed by each move's variations unless
+/// they've been collapsed.
+///
+/// steps lead to the line, and first_ply is the number of moves played before it. Sets
+/// go_to to the path of a move if the user clicks it.
+fn render_line(
+    ui: &mut Ui,
+    line: &[RecordedMove],
+    steps: &mut Vec<usize>,
+    first_ply: usize,
+    cursor: Option<&MovePath>,
+    collapsed: &mut HashSet<MovePath>,
+    go_to: &mut Option<Option<MovePath>>,
+) {
+    let depth = steps.len() / 2;
+
+    for (index, recorded) in line.iter().enumerate() {
+        steps.push(index);
+        let path = MovePath::from_steps(steps.clone());
+
+        ui.horizontal(|ui| {
+            ui.add_space(VARIATION_INDENT * depth as f32);
+
+            let ply = first_ply + index + 1;
+            let name = if ply % 2 == 1 { "Red" } else { "Blue" };
+            let text = format!("{}. {} {}", ply, name, ColumnLabel::new(recorded.column));
+
+            if ui.selectable_label(cursor == Some(&path), text).clicked() {
+                *go_to = Some(Some(path.clone()));
+            }
+
+            if !recorded.variations.is_empty() {
+                let (icon, hover_text) = if collapsed.contains(&path) {
+                    ("▸", "Show variations")
+                } else {
+                    ("▾", "Hide variations")
+                };
+                if ui.small_button(icon).on_hover_text(hover_text).clicked()
+                    && !collapsed.remove(&path)
+                {
+                    collapsed.insert(path.clone());
+                }
+            }
+        });
+
+        if !collapsed.contains(&path) {
+            for (variation_index, variation) in recorded.variations.iter().enumerate() {
+                steps.push(variation_index);
+                render_line(
+                    ui,
+                    variation,
+                    steps,
+                    first_ply + index,
+                    cursor,
+                    collapsed,
+                    go_to,
+                );
+                steps.pop();
+            }
+        }
+
+        steps.pop();
+    }
+}
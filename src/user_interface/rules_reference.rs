@@ -0,0 +1,90 @@
+use egui::{Context, Slider, Window};
+
+use crate::game_engine::{clock::TimeControl, house_rules::HouseRules};
+
+/// The standard rules of Connect Four, shown at the top of the rules reference.
+const STANDARD_RULES: &str = "Red and Blue take turns dropping a piece into one of the seven \
+columns, where it falls to the lowest empty space. Red moves first. The first player to line \
+up four of their pieces horizontally, vertically or diagonally wins. If the board fills up \
+without anyone connecting four, the game is a draw.";
+
+/// Explains the announce threats house rule.
+const ANNOUNCE_THREATS_RULE: &str = "A move that threatens to win next turn has to be \
+announced before it's played.";
+
+/// Explains the timed moves house rule.
+const TIMED_MOVES_RULE: &str = "Each player has a limited number of seconds to make each \
+move, and loses if they run out.";
+
+/// Explains the game clock house rule.
+const GAME_CLOCK_RULE: &str = "Each player has a budget of minutes for the whole game, \
+topped up by a few seconds after each of their moves, and loses if it runs out. The computer \
+spends its own clock's time thinking. A new clock applies from the next game.";
+
+/// The time limit a timed game starts with.
+const DEFAULT_TIME_LIMIT: u32 = 30;
+/// The time control a game with a clock starts with.
+const DEFAULT_TIME_CONTROL: TimeControl = TimeControl {
+    minutes: 3,
+    increment: 2,
+};
+
+/// A window explaining the rules of the game, where house rules can be toggled.
+#[derive(Default)]
+pub struct RulesReference {
+    pub open: bool,
+}
+
+impl RulesReference {
+    /// Creates a new, closed, RulesReference.
+    pub fn new() -> RulesReference {
+        Default::default()
+    }
+
+    /// Renders the rules reference if it's open, letting the user change the house rules.
+    pub fn render(&mut self, ctx: &Context, house_rules: &mut HouseRules) {
+        Window::new("Rules")
+            .open(&mut self.open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.heading("Standard rules");
+                ui.label(STANDARD_RULES);
+
+                ui.separator();
+                ui.heading("House rules");
+
+                ui.checkbox(&mut house_rules.announce_threats, "Announce threats");
+                ui.label(ANNOUNCE_THREATS_RULE);
+
+                let mut timed = house_rules.move_time_limit.is_some();
+                ui.checkbox(&mut timed, "Timed moves");
+                ui.label(TIMED_MOVES_RULE);
+
+                house_rules.move_time_limit = match (timed, house_rules.move_time_limit) {
+                    (false, _) => None,
+                    (true, None) => Some(DEFAULT_TIME_LIMIT),
+                    (true, Some(mut seconds)) => {
+                        ui.add(Slider::new(&mut seconds, 5..=120).text("Seconds per move"));
+                        Some(seconds)
+                    }
+                };
+
+                let mut clocked = house_rules.time_control.is_some();
+                ui.checkbox(&mut clocked, "Game clock");
+                ui.label(GAME_CLOCK_RULE);
+
+                house_rules.time_control = match (clocked, house_rules.time_control) {
+                    (false, _) => None,
+                    (true, None) => Some(DEFAULT_TIME_CONTROL),
+                    (true, Some(mut time_control)) => {
+                        ui.add(Slider::new(&mut time_control.minutes, 1..=30).text("Minutes"));
+                        ui.add(
+                            Slider::new(&mut time_control.increment, 0..=30)
+                                .text("Seconds added per move"),
+                        );
+                        Some(time_control)
+                    }
+                };
+            });
+    }
+}
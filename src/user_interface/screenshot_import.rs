@@ -0,0 +1,125 @@
+use egui::{Color32, Context, RichText, Sense, Ui, Vec2, Window};
+
+use crate::user_interface::{
+    recognition::{moves_for_position, recognize_position, Position},
+    settings::PieceColors,
+};
+
+/// The size of each space in the position being corrected.
+const SPACE_SIZE: f32 = 28.0;
+
+/// Sets up a position for analysis from a screenshot of a board pasted from the clipboard,
+/// letting the user correct any spaces that were misread first.
+#[derive(Default)]
+pub struct ScreenshotImport {
+    /// The position read from the screenshot, while it's being corrected.
+    position: Option<Position>,
+    /// Why the last screenshot couldn't be read.
+    error: Option<String>,
+}
+
+impl ScreenshotImport {
+    /// Creates a new ScreenshotImport, with nothing pasted.
+    pub fn new() -> ScreenshotImport {
+        Default::default()
+    }
+
+    /// Reads the position from the screenshot in the clipboard, looking for pieces in the
+    /// given colors as well as red and yellow.
+    fn paste(&mut self, colors: PieceColors) {
+        let position = read_clipboard_image()
+            .and_then(|(width, height, rgba)| recognize_position(width, height, &rgba, colors));
+
+        match position {
+            Ok(position) => {
+                self.position = Some(position);
+                self.error = None;
+            }
+            Err(error) => self.error = Some(error),
+        }
+    }
+
+    /// Renders the paste button, and a window where the position read from the screenshot
+    /// can be corrected, with pieces drawn in the given colors.
+    ///
+    /// Returns the moves reaching the position once the user chooses to analyse it.
+    pub fn render(&mut self, ui: &mut Ui, ctx: &Context, colors: PieceColors) -> Option<String> {
+        if ui
+            .button("Paste screenshot")
+            .on_hover_text("Set up the position from a screenshot of a board in the clipboard")
+            .clicked()
+        {
+            self.paste(colors);
+        }
+        if let Some(error) = &self.error {
+            ui.label(error);
+        }
+
+        let position = self.position.as_mut()?;
+        let mut open = true;
+        let mut analyze = None;
+
+        Window::new("Pasted position")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("Click a space to change what's in it.");
+
+                for row in position.iter_mut() {
+                    ui.horizontal(|ui| {
+                        for piece in row.iter_mut() {
+                            let (rect, response) =
+                                ui.allocate_exact_size(Vec2::splat(SPACE_SIZE), Sense::click());
+                            if response.clicked() {
+                                *piece = (*piece + 1) % 3;
+                            }
+
+                            let fill = match piece {
+                                0 => ui.visuals().extreme_bg_color,
+                                1 => to_color32(colors.player_one),
+                                _ => to_color32(colors.player_two),
+                            };
+                            ui.painter().circle(
+                                rect.center(),
+                                SPACE_SIZE * 0.4,
+                                fill,
+                                ui.visuals().widgets.inactive.fg_stroke,
+                            );
+                        }
+                    });
+                }
+
+                match moves_for_position(position) {
+                    Ok(moves) => {
+                        if ui.button("Analyze").clicked() {
+                            analyze = Some(moves);
+                        }
+                    }
+                    Err(error) => {
+                        ui.label(RichText::new(error).color(Color32::RED));
+                    }
+                }
+            });
+
+        if !open || analyze.is_some() {
+            self.position = None;
+        }
+
+        analyze
+    }
+}
+
+/// Converts one of the settings' colors for egui.
+fn to_color32(color: [u8; 3]) -> Color32 {
+    Color32::from_rgb(color[0], color[1], color[2])
+}
+
+/// Reads the image in the clipboard, returning its width and height along with its rows of
+/// RGBA pixels.
+fn read_clipboard_image() -> Result<(usize, usize, Vec<u8>), String> {
+    let image = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_image())
+        .map_err(|error| format!("Couldn't paste a screenshot - {}", error))?;
+
+    Ok((image.width, image.height, image.bytes.into_owned()))
+}
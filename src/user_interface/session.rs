@@ -0,0 +1,87 @@
+use eframe::Storage;
+use serde::{Deserialize, Serialize};
+
+use crate::user_interface::{
+    locale::Locale,
+    settings::{PieceColors, ScoreDisplay, Settings, ThinkingIndicator},
+};
+
+/// The key the session layout is stored under in eframe's storage.
+const SESSION_KEY: &str = "session_layout";
+
+/// The parts of the app's layout that are restored the next time it's opened.
+///
+/// eframe already restores the window's size and position, along with egui's own state like
+/// which collapsing headers are open, so this only covers the app's own state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionLayout {
+    pub rules_open: bool,
+    pub show_challenge_qr_code: bool,
+    /// The chosen theme, or None to keep eframe's default.
+    pub dark_mode: Option<bool>,
+    pub show_hints: bool,
+    pub show_evaluation_bar: bool,
+    pub show_threats: bool,
+    pub show_sharpness: bool,
+    pub score_display: ScoreDisplay,
+    pub thinking_indicator: ThinkingIndicator,
+    pub preview_computer_move: bool,
+    pub piece_colors: PieceColors,
+    pub attract_minutes: Option<f32>,
+    pub locale: Locale,
+}
+
+impl Default for SessionLayout {
+    fn default() -> SessionLayout {
+        let settings = Settings::new();
+
+        SessionLayout {
+            rules_open: false,
+            show_challenge_qr_code: false,
+            dark_mode: None,
+            show_hints: settings.show_hints,
+            show_evaluation_bar: settings.show_evaluation_bar,
+            show_threats: settings.show_threats,
+            show_sharpness: settings.show_sharpness,
+            score_display: settings.score_display,
+            thinking_indicator: settings.thinking_indicator,
+            preview_computer_move: settings.preview_computer_move,
+            piece_colors: settings.piece_colors,
+            attract_minutes: settings.attract_minutes,
+            locale: settings.locale,
+        }
+    }
+}
+
+impl SessionLayout {
+    /// Loads the layout the app was last left in, or the default layout if there isn't one.
+    pub fn load(storage: Option<&dyn Storage>) -> SessionLayout {
+        storage
+            .and_then(|storage| eframe::get_value(storage, SESSION_KEY))
+            .unwrap_or_default()
+    }
+
+    /// Saves the layout, to be restored the next time the app is opened.
+    pub fn save(&self, storage: &mut dyn Storage) {
+        eframe::set_value(storage, SESSION_KEY, self);
+    }
+
+    /// Applies the layout's display options to the settings.
+    pub fn apply_to(&self, settings: &mut Settings) {
+        settings.show_hints = self.show_hints;
+        settings.show_evaluation_bar = self.show_evaluation_bar;
+        settings.show_threats = self.show_threats;
+        settings.show_sharpness = self.show_sharpness;
+        settings.score_display = self.score_display;
+        settings.thinking_indicator = self.thinking_indicator;
+        settings.preview_computer_move = self.preview_computer_move;
+        settings.attract_minutes = self.attract_minutes;
+        settings.locale = self.locale;
+
+        // Stored colors could have been edited by hand, so they're checked again
+        if self.piece_colors.validate().is_ok() {
+            settings.piece_colors = self.piece_colors;
+        }
+    }
+}
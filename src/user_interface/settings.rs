@@ -1,19 +1,321 @@
-#[derive(Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts::NUMBER_TO_WIN,
+    game_engine::{
+        board_config::BoardConfig, game_record::format_score, house_rules::HouseRules,
+        mate_distance::format_mate_distance, power_ups::PowerUpKind, strength::FULL_STRENGTH,
+        variant_rules::VariantRules, win_probability::format_win_probability,
+    },
+    user_interface::{
+        engine_interface::{EngineAlgorithm, EngineBackend},
+        locale::Locale,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PlayerType {
     Human,
     Computer,
+    /// Someone playing over a game server, whose moves arrive from it.
+    Remote,
+}
+
+/// Who plays each side, named the way players think of it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Matchup {
+    HumanVsComputer,
+    ComputerVsHuman,
+    /// Two humans taking turns on the same board.
+    Hotseat,
+    /// Two computers playing each other, with nobody needing to touch the board.
+    Demo,
 }
 
+impl Matchup {
+    /// Every matchup, in the order they're offered to the user.
+    pub const ALL: [Matchup; 4] = [
+        Matchup::HumanVsComputer,
+        Matchup::ComputerVsHuman,
+        Matchup::Hotseat,
+        Matchup::Demo,
+    ];
+
+    /// Returns the matchup the players make up, or None for a game against someone on a
+    /// game server, which the server sets up.
+    pub fn of(players: [PlayerType; 2]) -> Option<Matchup> {
+        match players {
+            [PlayerType::Human, PlayerType::Computer] => Some(Matchup::HumanVsComputer),
+            [PlayerType::Computer, PlayerType::Human] => Some(Matchup::ComputerVsHuman),
+            [PlayerType::Human, PlayerType::Human] => Some(Matchup::Hotseat),
+            [PlayerType::Computer, PlayerType::Computer] => Some(Matchup::Demo),
+            _ => None,
+        }
+    }
+
+    /// Returns who plays each side, red first.
+    pub fn players(&self) -> [PlayerType; 2] {
+        match self {
+            Matchup::HumanVsComputer => [PlayerType::Human, PlayerType::Computer],
+            Matchup::ComputerVsHuman => [PlayerType::Computer, PlayerType::Human],
+            Matchup::Hotseat => [PlayerType::Human; 2],
+            Matchup::Demo => [PlayerType::Computer; 2],
+        }
+    }
+
+    /// Returns the name the matchup is offered to the user with.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Matchup::HumanVsComputer => "Human (red) vs computer",
+            Matchup::ComputerVsHuman => "Computer (red) vs human",
+            Matchup::Hotseat => "Hotseat: human vs human",
+            Matchup::Demo => "Demo: computer vs computer",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Difficulty {
     Easy,
     Medium,
     Hard,
+    /// Makes the kinds of mistakes people do, like missing diagonal threats.
+    HumanLike,
+}
+
+impl Difficulty {
+    /// Every difficulty, in the order they're offered to the user.
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::HumanLike,
+    ];
+
+    /// Returns the rating the engine plays at for the difficulty.
+    ///
+    /// Easier engines think less, rather than picking worse moves from a full strength
+    /// analysis. Human-like engines think fully and then make people's mistakes.
+    pub fn rating(&self) -> u16 {
+        match self {
+            Difficulty::Easy => 900,
+            Difficulty::Medium => 1300,
+            Difficulty::Hard | Difficulty::HumanLike => FULL_STRENGTH,
+        }
+    }
+}
+
+/// How the engine's evaluations are shown to the user.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum ScoreDisplay {
+    /// The engine's raw scores, e.g. "12".
+    #[default]
+    Score,
+    /// The chance of winning, e.g. "53%".
+    WinProbability,
+}
+
+impl ScoreDisplay {
+    /// Writes one of the engine's scores for the user. Forced wins and losses are written
+    /// as how many moves they take either way, e.g. "W3", since they're certain.
+    pub fn format(&self, score: isize) -> String {
+        match self {
+            ScoreDisplay::Score => format_score(score),
+            ScoreDisplay::WinProbability => {
+                format_mate_distance(score).unwrap_or_else(|| format_win_probability(score))
+            }
+        }
+    }
+}
+
+/// How the app shows that the computer is thinking about its move.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum ThinkingIndicator {
+    /// A ring over the column the engine currently rates best, filling up as the computer's
+    /// thinking time runs out.
+    #[default]
+    ProgressRing,
+    /// Dots in the status bar, along with how far the engine's search has got.
+    StatusDots,
+    /// Nothing until the computer's piece drops.
+    None,
 }
 
+impl ThinkingIndicator {
+    /// Every indicator, in the order they're offered to the user.
+    pub const ALL: [ThinkingIndicator; 3] = [
+        ThinkingIndicator::ProgressRing,
+        ThinkingIndicator::StatusDots,
+        ThinkingIndicator::None,
+    ];
+
+    /// Describes the indicator for the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThinkingIndicator::ProgressRing => "Progress ring",
+            ThinkingIndicator::StatusDots => "Status dots",
+            ThinkingIndicator::None => "None",
+        }
+    }
+}
+
+/// The smallest difference allowed between the two players' piece colors, as a CIE76 delta-E.
+/// Around 2.3 is the smallest difference people can notice at all, so this keeps the pieces
+/// easy to tell apart at a glance.
+pub const MIN_PIECE_COLOR_DIFFERENCE: f32 = 40.0;
+
+/// The colors each player's pieces are drawn in, as sRGB.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PieceColors {
+    pub player_one: [u8; 3],
+    pub player_two: [u8; 3],
+}
+
+impl Default for PieceColors {
+    fn default() -> PieceColors {
+        PieceColors {
+            player_one: [255, 0, 0],
+            player_two: [0, 0, 255],
+        }
+    }
+}
+
+impl PieceColors {
+    /// Checks the two colors are different enough to tell the players' pieces apart.
+    pub fn validate(&self) -> Result<(), String> {
+        let difference = color_difference(self.player_one, self.player_two);
+
+        if difference < MIN_PIECE_COLOR_DIFFERENCE {
+            Err(format!(
+                "The piece colors are too similar to tell apart (a difference of {:.0}, at least {:.0} is needed)",
+                difference, MIN_PIECE_COLOR_DIFFERENCE
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the colors with the players' colors exchanged.
+    pub fn swapped(&self) -> PieceColors {
+        PieceColors {
+            player_one: self.player_two,
+            player_two: self.player_one,
+        }
+    }
+}
+
+/// Measures how different two sRGB colors look, as the CIE76 delta-E between them.
+pub fn color_difference(first: [u8; 3], second: [u8; 3]) -> f32 {
+    let (first, second) = (to_lab(first), to_lab(second));
+
+    first
+        .iter()
+        .zip(second.iter())
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Converts an sRGB color to CIELAB, under the D65 white point.
+fn to_lab(color: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = color.map(|channel| {
+        let channel = channel as f32 / 255.0;
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    });
+
+    // Relative to the white point, so white comes out as 1.0 in each
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.089;
+
+    let [fx, fy, fz] = [x, y, z].map(|t| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    });
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// How many minutes the app is left alone before the demo starts, when it's first turned on.
+pub const DEFAULT_ATTRACT_MINUTES: f32 = 3.0;
+
+/// The suffix of the settings tag value for the computer's delay, e.g. "3s delay".
+const DELAY_SUFFIX: &str = "s delay";
+/// The settings tag value for a practice game.
+const PRACTICE: &str = "Practice";
+/// The settings tag value for teacher mode.
+const TEACHER_MODE: &str = "Teacher mode";
+/// The settings tag value for a game played with power-ups.
+const POWER_UPS: &str = "Power-ups";
+/// The settings tag value for a game played with anvil discs.
+const ANVILS: &str = "Anvil discs";
+/// Starts the settings tag value for a game won by other than four in a row, e.g.
+/// "Connect 5".
+const CONNECT_PREFIX: &str = "Connect ";
+
+/// How many pieces in a row games can be won with, e.g. connect three or connect five.
+pub const WIN_LENGTHS: [u8; 3] = [3, NUMBER_TO_WIN, 5];
+
+#[derive(Clone)]
 pub struct Settings {
     pub players: [PlayerType; 2],
     pub delay: f32,
     pub difficulty: Difficulty,
+    /// Whether the board mirrors itself into place when the engine recognizes a position
+    /// as the mirror image of one it has already analysed.
+    pub animate_flips: bool,
+    /// Whether the engine's evaluation of each move is shown above the board.
+    pub show_hints: bool,
+    /// Whether each player's chance of winning is shown in a bar beside the board, and the
+    /// chance each move leaves them above its column.
+    pub show_evaluation_bar: bool,
+    /// Whether each player's threats are counted below the board.
+    pub show_threats: bool,
+    /// Whether the position's sharpness is shown as flames below the board.
+    pub show_sharpness: bool,
+    pub score_display: ScoreDisplay,
+    /// How the app shows that the computer is thinking.
+    pub thinking_indicator: ThinkingIndicator,
+    /// Whether the column the computer is leaning towards is faintly highlighted while it
+    /// thinks.
+    pub preview_computer_move: bool,
+    /// Optional rules being played with, which the TurnManager enforces.
+    pub house_rules: HouseRules,
+    /// Whether the engine and animations are throttled to save energy, e.g. on a laptop.
+    pub power_saving: bool,
+    /// Whether the engine keeps searching during a human's turn, beneath the reply it
+    /// expects them to make.
+    pub pondering: bool,
+    /// Where the engine runs.
+    pub engine_backend: EngineBackend,
+    /// How the engine scores moves.
+    pub engine_algorithm: EngineAlgorithm,
+    /// The variant played, which changes who a connect four goes to.
+    pub variant: VariantRules,
+    /// How many pieces in a row win the game, on the standard board.
+    pub number_to_win: u8,
+    /// Whether each player can spend a power-up once a game.
+    pub power_ups: bool,
+    /// The power-up each player gets when power_ups is on.
+    pub power_up_kind: PowerUpKind,
+    /// Whether the game pauses to show how a human's mistake could be punished.
+    pub teacher_mode: bool,
+    pub piece_colors: PieceColors,
+    /// How many minutes the app can be left alone before it starts playing demo games, or
+    /// None to never play them. Useful for kiosks.
+    pub attract_minutes: Option<f32>,
+    /// Whether the game is for practice, where moves can be taken back with a keypress,
+    /// hints and threats are always shown, and the computer explains its moves.
+    pub practice: bool,
+    /// How numbers, durations and dates are written for the user.
+    pub locale: Locale,
 }
 
 impl Settings {
@@ -22,6 +324,246 @@ impl Settings {
             players: [PlayerType::Human, PlayerType::Computer],
             delay: 3.0,
             difficulty: Difficulty::Hard,
+            animate_flips: false,
+            show_hints: false,
+            show_evaluation_bar: false,
+            show_threats: false,
+            show_sharpness: false,
+            score_display: ScoreDisplay::Score,
+            thinking_indicator: ThinkingIndicator::ProgressRing,
+            preview_computer_move: false,
+            house_rules: HouseRules::default(),
+            power_saving: false,
+            pondering: false,
+            engine_backend: EngineBackend::Thread,
+            engine_algorithm: EngineAlgorithm::AlphaBeta,
+            variant: VariantRules::Standard,
+            number_to_win: NUMBER_TO_WIN,
+            power_ups: false,
+            power_up_kind: PowerUpKind::Clear,
+            teacher_mode: false,
+            piece_colors: PieceColors::default(),
+            attract_minutes: None,
+            practice: false,
+            locale: Locale::default(),
         }
     }
+
+    /// Describes the settings that decide how a game is played, in the form stored in a
+    /// GameRecord's settings tag, e.g. "Red Human, Blue Computer, Hard, 3s delay". The
+    /// engine's algorithm is only mentioned when it isn't alpha-beta, e.g. "Monte Carlo".
+    ///
+    /// The house rules have a tag of their own, and settings that only change how the game
+    /// is shown are left out.
+    pub fn to_tag_value(&self) -> String {
+        let mut settings = vec![
+            format!("Red {:?}", self.players[0]),
+            format!("Blue {:?}", self.players[1]),
+            format!("{:?}", self.difficulty),
+            format!("{}{}", self.delay, DELAY_SUFFIX),
+        ];
+        if self.engine_algorithm != EngineAlgorithm::AlphaBeta {
+            settings.push(self.engine_algorithm.name().to_owned());
+        }
+        if self.variant != VariantRules::Standard {
+            settings.push(self.variant.name().to_owned());
+        }
+        if self.number_to_win != NUMBER_TO_WIN {
+            settings.push(format!("{}{}", CONNECT_PREFIX, self.number_to_win));
+        }
+        match self.enabled_power_up() {
+            Some(PowerUpKind::Clear) => settings.push(POWER_UPS.to_owned()),
+            Some(PowerUpKind::Anvil) => settings.push(ANVILS.to_owned()),
+            None => (),
+        }
+        if self.practice {
+            settings.push(PRACTICE.to_owned());
+        }
+        if self.teacher_mode {
+            settings.push(TEACHER_MODE.to_owned());
+        }
+
+        settings.join(", ")
+    }
+
+    /// Applies settings written by to_tag_value, leaving any others as they are.
+    pub fn apply_tag_value(&mut self, value: &str) -> Result<(), String> {
+        let parse_player = |player: &str| match player {
+            "Human" => Ok(PlayerType::Human),
+            "Computer" => Ok(PlayerType::Computer),
+            _ => Err(format!("Unknown player '{}'", player)),
+        };
+
+        for setting in value.split(',').map(|setting| setting.trim()) {
+            if let Some(player) = setting.strip_prefix("Red ") {
+                self.players[0] = parse_player(player)?;
+            } else if let Some(player) = setting.strip_prefix("Blue ") {
+                self.players[1] = parse_player(player)?;
+            } else if let Some(delay) = setting.strip_suffix(DELAY_SUFFIX) {
+                self.delay = delay
+                    .parse()
+                    .map_err(|_| format!("Invalid delay '{}'", setting))?;
+            } else if setting == PRACTICE {
+                self.practice = true;
+            } else if setting == TEACHER_MODE {
+                self.teacher_mode = true;
+            } else if setting == POWER_UPS {
+                self.power_ups = true;
+                self.power_up_kind = PowerUpKind::Clear;
+            } else if setting == ANVILS {
+                self.power_ups = true;
+                self.power_up_kind = PowerUpKind::Anvil;
+            } else if let Some(number_to_win) = setting.strip_prefix(CONNECT_PREFIX) {
+                let number_to_win = number_to_win
+                    .parse()
+                    .map_err(|_| format!("Invalid win length '{}'", setting))?;
+                BoardConfig::STANDARD.with_number_to_win(number_to_win)?;
+                self.number_to_win = number_to_win;
+            } else if let Some(difficulty) = Difficulty::ALL
+                .into_iter()
+                .find(|difficulty| format!("{:?}", difficulty) == setting)
+            {
+                self.difficulty = difficulty;
+            } else if let Some(algorithm) = EngineAlgorithm::ALL
+                .into_iter()
+                .find(|algorithm| algorithm.name() == setting)
+            {
+                self.engine_algorithm = algorithm;
+            } else if let Ok(variant) = VariantRules::from_tag_value(setting) {
+                self.variant = variant;
+            } else if !setting.is_empty() {
+                return Err(format!("Unknown setting '{}'", setting));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the power-up games are played with, or None if power-ups are off.
+    pub fn enabled_power_up(&self) -> Option<PowerUpKind> {
+        self.power_ups.then_some(self.power_up_kind)
+    }
+
+    /// Returns the board games are played on, the standard board won by number_to_win in a
+    /// row.
+    pub fn board_config(&self) -> BoardConfig {
+        BoardConfig::STANDARD
+            .with_number_to_win(self.number_to_win)
+            .unwrap_or_default()
+    }
+
+    /// Switches to the practice preset, against the computer with the standard rules.
+    pub fn start_practice(&mut self) {
+        self.practice = true;
+        self.players = [PlayerType::Human, PlayerType::Computer];
+        self.house_rules = HouseRules::default();
+        self.show_hints = true;
+        self.show_threats = true;
+        self.teacher_mode = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game_engine::{
+            power_ups::PowerUpKind, strength::FULL_STRENGTH, variant_rules::VariantRules,
+        },
+        user_interface::{
+            engine_interface::EngineAlgorithm,
+            settings::{color_difference, Difficulty, Matchup, PieceColors, PlayerType, Settings},
+        },
+    };
+
+    #[test]
+    fn tag_values() {
+        let mut settings = Settings::new();
+        assert_eq!(
+            settings.to_tag_value(),
+            "Red Human, Blue Computer, Hard, 3s delay"
+        );
+
+        settings.players = [PlayerType::Computer; 2];
+        settings.difficulty = Difficulty::HumanLike;
+        settings.delay = 0.5;
+        settings.teacher_mode = true;
+        settings.engine_algorithm = EngineAlgorithm::Hybrid;
+        settings.variant = VariantRules::Misere;
+        settings.number_to_win = 5;
+        settings.power_ups = true;
+        assert_eq!(
+            settings.to_tag_value(),
+            "Red Computer, Blue Computer, HumanLike, 0.5s delay, Hybrid, Misère, Connect 5, \
+             Power-ups, Teacher mode"
+        );
+        let mut parsed = Settings::new();
+        parsed.apply_tag_value(&settings.to_tag_value()).unwrap();
+        assert_eq!(parsed.to_tag_value(), settings.to_tag_value());
+        assert_eq!(parsed.players, [PlayerType::Computer; 2]);
+        assert_eq!(parsed.difficulty, Difficulty::HumanLike);
+        assert_eq!(parsed.delay, 0.5);
+        assert!(parsed.teacher_mode && !parsed.practice);
+        assert_eq!(parsed.engine_algorithm, EngineAlgorithm::Hybrid);
+        assert_eq!(parsed.variant, VariantRules::Misere);
+        assert_eq!(parsed.board_config().number_to_win, 5);
+        assert!(parsed.power_ups);
+
+        settings.power_up_kind = PowerUpKind::Anvil;
+        assert!(settings.to_tag_value().contains("Anvil discs"));
+        parsed.apply_tag_value(&settings.to_tag_value()).unwrap();
+        assert_eq!(parsed.enabled_power_up(), Some(PowerUpKind::Anvil));
+        settings.power_ups = false;
+        assert_eq!(settings.enabled_power_up(), None);
+
+        assert!(Settings::new().apply_tag_value("Red Robot").is_err());
+        assert!(Settings::new().apply_tag_value("Gravity off").is_err());
+        assert!(Settings::new().apply_tag_value("fasts delay").is_err());
+        assert!(Settings::new().apply_tag_value("Connect 9").is_err());
+    }
+
+    #[test]
+    fn matchups() {
+        for matchup in Matchup::ALL {
+            assert_eq!(Matchup::of(matchup.players()), Some(matchup));
+        }
+        assert_eq!(
+            Matchup::of(Settings::new().players),
+            Some(Matchup::HumanVsComputer)
+        );
+        assert_eq!(Matchup::of([PlayerType::Human, PlayerType::Remote]), None);
+        assert_eq!(Matchup::Hotseat.players(), [PlayerType::Human; 2]);
+        assert_eq!(Matchup::Demo.players(), [PlayerType::Computer; 2]);
+    }
+
+    #[test]
+    fn difficulty_ratings() {
+        assert!(Difficulty::Easy.rating() < Difficulty::Medium.rating());
+        assert!(Difficulty::Medium.rating() < Difficulty::Hard.rating());
+        assert_eq!(Difficulty::Hard.rating(), FULL_STRENGTH);
+        assert_eq!(Difficulty::HumanLike.rating(), FULL_STRENGTH);
+    }
+
+    #[test]
+    fn color_differences() {
+        assert_eq!(color_difference([12, 34, 56], [12, 34, 56]), 0.0);
+        assert!((color_difference([0, 0, 0], [255, 255, 255]) - 100.0).abs() < 0.1);
+        assert!(color_difference([255, 0, 0], [0, 0, 255]) > 150.0);
+        assert!(color_difference([255, 0, 0], [250, 10, 5]) < 10.0);
+    }
+
+    #[test]
+    fn validates_piece_colors() {
+        assert!(PieceColors::default().validate().is_ok());
+        assert!(PieceColors::default().swapped().validate().is_ok());
+
+        let similar = PieceColors {
+            player_one: [255, 0, 0],
+            player_two: [230, 20, 10],
+        };
+        assert!(similar.validate().is_err());
+
+        let swapped = PieceColors::default().swapped();
+        assert_eq!(swapped.player_one, [0, 0, 255]);
+        assert_eq!(swapped.player_two, [255, 0, 0]);
+    }
 }
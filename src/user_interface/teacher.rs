@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use egui::{Context, Window};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::{
+        column_label::ColumnLabel, game_manager::GameManager, game_record::MoveAnnotation,
+    },
+    user_interface::{
+        board::{Board, PieceState},
+        engine_interface::MoveEvent,
+        settings::ScoreDisplay,
+    },
+};
+
+/// How long the engine gets to look into a mistake before its refutation is shown.
+const THINKING_TIME: Duration = Duration::from_secs(1);
+/// How long each move of the refutation stays on the board before the next one is played.
+const STEP_TIME: Duration = Duration::from_millis(800);
+
+/// What the player chose to do after being shown a lesson.
+pub enum TeacherAction {
+    /// Go back to the position before the mistake, given as a move string.
+    TakeBack(String),
+    /// Keep playing from the position after the mistake.
+    Continue,
+}
+
+/// The stages of explaining a mistake.
+enum LessonStage {
+    /// Giving the engine time to look into the position after the mistake.
+    Thinking { since: Instant, requested: bool },
+    /// Playing the engine's expected line out on the board.
+    Showing {
+        line: Vec<u8>,
+        shown: usize,
+        last_step: Instant,
+    },
+}
+
+/// An explanation of why a move was a mistake.
+struct Lesson {
+    /// The moves played before the mistake, as columns numbered from 1.
+    moves_before: String,
+    played: u8,
+    annotation: MoveAnnotation,
+    /// What the engine reported the mistake missed, once it's acknowledged the move.
+    missed: Option<Vec<String>>,
+    stage: LessonStage,
+}
+
+impl Lesson {
+    /// Returns the position after the mistake, as array[row][col], along with who moves next.
+    fn position_after(
+        &self,
+    ) -> (
+        [[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+        PieceState,
+    ) {
+        let moves = format!("{}{}", self.moves_before, ColumnLabel::new(self.played));
+        let manager = GameManager::from_moves(&moves).expect("The moves played should be legal");
+        let next_player = if manager.get_turn() {
+            PieceState::PlayerTwo
+        } else {
+            PieceState::PlayerOne
+        };
+
+        (manager.get_position(), next_player)
+    }
+}
+
+/// Pauses the game when a human makes a mistake, and shows them how the engine would
+/// punish it, with the option to take the move back.
+#[derive(Default)]
+pub struct Teacher {
+    lesson: Option<Lesson>,
+}
+
+impl Teacher {
+    /// Creates a new Teacher, with no lesson underway.
+    pub fn new() -> Teacher {
+        Default::default()
+    }
+
+    /// Returns whether a lesson is underway, during which the game should be paused.
+    pub fn is_teaching(&self) -> bool {
+        self.lesson.is_some()
+    }
+
+    /// Checks a move a human is playing against the engine's move scores, starting a lesson
+    /// if it's a blunder.
+    ///
+    /// moves_before are the moves played before it, as columns numbered from 1.
+    pub fn move_played(
+        &mut self,
+        moves_before: &str,
+        column: usize,
+        move_scores: &HashMap<u8, isize>,
+    ) {
+        let column = column as u8;
+        let Some(&score) = move_scores.get(&column) else {
+            return;
+        };
+        let Some((&best_column, &best_score)) = move_scores.iter().max_by_key(|(_, score)| **score)
+        else {
+            return;
+        };
+
+        let annotation = MoveAnnotation {
+            score,
+            best_column,
+            best_score,
+        };
+        if !annotation.is_blunder() {
+            return;
+        }
+
+        self.lesson = Some(Lesson {
+            moves_before: moves_before.to_owned(),
+            played: column,
+            annotation,
+            missed: None,
+            stage: LessonStage::Thinking {
+                since: Instant::now(),
+                requested: false,
+            },
+        });
+    }
+
+    /// Takes note of the wins and blocks the engine reports the mistake missed, from the
+    /// events it sent with its receipt for the move.
+    pub fn events_received(&mut self, events: &[MoveEvent]) {
+        let Some(lesson) = &mut self.lesson else {
+            return;
+        };
+        if lesson.missed.is_some() {
+            return;
+        }
+
+        let missed = events
+            .iter()
+            .filter_map(|event| match event {
+                MoveEvent::WinMissed { column, .. } => Some(format!(
+                    "You could have won on column {}.",
+                    ColumnLabel::new(*column)
+                )),
+                MoveEvent::BlockMissed { column, .. } => Some(format!(
+                    "It leaves your opponent a win on column {}.",
+                    ColumnLabel::new(*column)
+                )),
+                _ => None,
+            })
+            .collect();
+        lesson.missed = Some(missed);
+    }
+
+    /// Returns whether the engine should be asked for an update, which happens once it's
+    /// had time to think about the mistake.
+    pub fn wants_update(&mut self) -> bool {
+        if let Some(Lesson {
+            stage: LessonStage::Thinking { since, requested },
+            ..
+        }) = &mut self.lesson
+        {
+            if !*requested && since.elapsed() > THINKING_TIME {
+                *requested = true;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Starts playing out the engine's expected line, if a lesson is waiting on it.
+    pub fn line_received(&mut self, line: &[u8], board: &mut Board) {
+        let Some(lesson) = &mut self.lesson else {
+            return;
+        };
+        if let LessonStage::Thinking {
+            requested: true, ..
+        } = lesson.stage
+        {
+            let (position, next_player) = lesson.position_after();
+            board.set_position(position, next_player);
+            board.lock();
+
+            lesson.stage = LessonStage::Showing {
+                line: line.to_vec(),
+                shown: 0,
+                last_step: Instant::now(),
+            };
+        }
+    }
+
+    /// Plays the next move of the line when it's due, and shows the lesson's explanation.
+    ///
+    /// Returns what the player chose to do once they're done with the lesson.
+    pub fn render(
+        &mut self,
+        ctx: &Context,
+        board: &mut Board,
+        score_display: ScoreDisplay,
+    ) -> Option<TeacherAction> {
+        let lesson = self.lesson.as_mut()?;
+        let (position_after, next_player) = lesson.position_after();
+        let mut action = None;
+
+        Window::new("Teacher")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Column {} ({}) wasn't your best move, column {} ({}) was.",
+                    ColumnLabel::new(lesson.played),
+                    score_display.format(lesson.annotation.score),
+                    ColumnLabel::new(lesson.annotation.best_column),
+                    score_display.format(lesson.annotation.best_score)
+                ));
+                for remark in lesson.missed.iter().flatten() {
+                    ui.label(remark);
+                }
+
+                match &mut lesson.stage {
+                    LessonStage::Thinking { .. } => {
+                        ui.label("Looking for the refutation...");
+                        ctx.request_repaint_after(THINKING_TIME);
+                    }
+                    LessonStage::Showing {
+                        line,
+                        shown,
+                        last_step,
+                    } => {
+                        if line.is_empty() {
+                            ui.label("The engine hasn't found a refutation yet.");
+                        } else {
+                            let line_text: Vec<String> = line
+                                .iter()
+                                .map(|column| ColumnLabel::new(*column).to_string())
+                                .collect();
+                            ui.label(format!("Here's how it could go: {}", line_text.join(" ")));
+                        }
+
+                        // Stepping through the line, one piece at a time
+                        if *shown < line.len()
+                            && last_step.elapsed() > STEP_TIME
+                            && !board.is_piece_falling()
+                        {
+                            let player = if (lesson.moves_before.len() + 1 + *shown) % 2 == 0 {
+                                PieceState::PlayerOne
+                            } else {
+                                PieceState::PlayerTwo
+                            };
+                            match board.drop_piece(ctx, line[*shown] as usize, player) {
+                                Ok(()) => *shown += 1,
+                                // A line the board can't follow is only listed above
+                                Err(_) => *shown = line.len(),
+                            }
+                            *last_step = Instant::now();
+                        }
+                        if *shown < line.len() {
+                            ctx.request_repaint_after(STEP_TIME);
+                        } else if !line.is_empty() && ui.button("Replay").clicked() {
+                            board.set_position(position_after, next_player);
+                            *shown = 0;
+                            *last_step = Instant::now();
+                        }
+                    }
+                }
+
+                // The move is only acknowledged by the engine once it's sent the line
+                if let LessonStage::Thinking { .. } = lesson.stage {
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Take back").clicked() {
+                        action = Some(TeacherAction::TakeBack(lesson.moves_before.clone()));
+                    }
+                    if ui.button("Continue").clicked() {
+                        board.set_position(position_after, next_player);
+                        action = Some(TeacherAction::Continue);
+                    }
+                });
+            });
+
+        if action.is_some() {
+            self.lesson = None;
+        }
+
+        action
+    }
+}
@@ -1,13 +1,22 @@
-use std::{collections::HashMap, sync::mpsc::Sender, time::Instant};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
 
 use egui::Context;
-use rand::seq::SliceRandom;
+use rand::{rngs::StdRng, SeedableRng};
 
 use crate::{
     consts::BOARD_WIDTH,
+    game_engine::{
+        clock::GameClock,
+        human_policy::choose_human_like_move,
+        power_ups::{choose_power_up, Move, PowerUpKind, POWER_UP_MARKERS},
+    },
     user_interface::{
         board::{Board, PieceState},
-        engine_interface::{GameOver, UIMessage},
+        engine_interface::{EngineHandle, GameOver, UIMessage},
         settings::{Difficulty, PlayerType, Settings},
     },
 };
@@ -15,59 +24,95 @@ use crate::{
 /// The turn manager devides a computer's turn up into multiple stages.
 ///
 /// WaitingForMoveReceipt is the default stage of waiting to receive notice that a move has been made.
-/// Delay allows for some delay before the computer makes its move.
+/// Delay is the computer thinking for a while before it makes its move.
 /// WaitingForUpdate is when the turn manager is no longer delaying and now wants to make a move.
-/// AnimateToChosenColumn is after the turn manager knows what move it wants to make.
+/// AnimateToChosenColumn is after the turn manager knows what move it wants to make, which
+/// spends its power-up in the column if power_up is given.
 /// GameOver is when the turn manager is no longer processing due to the game being over.
 #[derive(Debug, PartialEq, Eq)]
 enum TurnStage {
     WaitingForMoveReceipt,
     Delay {
         start: Instant,
-        animating_to_column: usize,
-    },
-    WaitingForUpdate {
-        animating_to_column: usize,
     },
+    WaitingForUpdate,
     AnimateToChosenColumn {
         chosen_column: usize,
+        power_up: Option<PowerUpKind>,
     },
     GameOver,
 }
 
+/// Something that happened while processing a turn, which the app has to act on.
+#[derive(Clone, Copy)]
+pub enum TurnEvent {
+    /// The player ran out of time, losing the game.
+    TimedOut(PieceState),
+    /// The computer spent its power-up with the move, which the app plays by loading the
+    /// position with it, starting the next turn.
+    PowerUpSpent(Move),
+}
+
 /// Handles transitioning a board between being open for player input and waiting for
 /// the computer to make a move.
 pub struct TurnManager {
     pub current_player: PieceState,
     current_player_type: PlayerType,
     stage: TurnStage,
+    /// When the current player started thinking about their move, until they commit to it.
+    turn_start: Option<Instant>,
+    /// The players' clocks, if the game is played with a time control.
+    clock: Option<GameClock>,
+    /// When the computer committed to its move and how long it took to, while its piece is
+    /// animating to the chosen column.
+    committed: Option<(Instant, Duration)>,
+    /// How the computer's last move was timed.
+    last_computer_move: Option<MoveTiming>,
+    /// Whether the current player has announced that their move threatens to win.
+    threat_announced: bool,
+    /// The column the engine has been preferring while the computer delays its move.
+    previewed_column: Option<usize>,
+    /// The seed the computer's random choices are made with, so the game can be played
+    /// again.
+    seed: u64,
+    rng: StdRng,
 }
 
 impl TurnManager {
-    /// Creates a new TurnManager.
+    /// Creates a new TurnManager, whose computer makes its random choices with a random seed.
     pub fn new(players: [PlayerType; 2]) -> TurnManager {
         let current_player_type = players[0];
+        let seed = rand::random();
         TurnManager {
             current_player: PieceState::PlayerOne,
             current_player_type,
             // We're assuming the first player to go is a human by default
             stage: match current_player_type {
-                PlayerType::Human => TurnStage::WaitingForMoveReceipt,
-                PlayerType::Computer => TurnStage::Delay { start: Instant::now(), animating_to_column: 6 },
+                PlayerType::Human | PlayerType::Remote => TurnStage::WaitingForMoveReceipt,
+                PlayerType::Computer => TurnStage::Delay {
+                    start: Instant::now(),
+                },
             },
+            turn_start: Some(Instant::now()),
+            clock: None,
+            committed: None,
+            last_computer_move: None,
+            threat_announced: false,
+            previewed_column: None,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
         }
     }
 
+    /// Returns the seed the computer's random choices are made with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     /// Alerts the TurnManager that a move has been made.
     ///
     /// This method handles transitioning between players's turns.
-    pub fn move_receipt(
-        &mut self,
-        game_state: GameOver,
-        ctx: &Context,
-        board: &mut Board,
-        settings: &Settings,
-    ) {
+    pub fn move_receipt(&mut self, game_state: GameOver, board: &mut Board, settings: &Settings) {
         if self.stage != TurnStage::WaitingForMoveReceipt {
             panic!(
                 "Received move receipt while in turn stage: {:?}",
@@ -83,111 +128,379 @@ impl TurnManager {
 
         // It is now the other player's turn
         self.current_player = self.current_player.reverse();
+        self.start_turn(board, settings);
+    }
 
+    /// Alerts the TurnManager that a new position has been loaded into the game.
+    ///
+    /// current_player is the player who is about to make a move in the new position.
+    pub fn position_loaded(
+        &mut self,
+        current_player: PieceState,
+        game_state: GameOver,
+        board: &mut Board,
+        settings: &Settings,
+    ) {
+        self.current_player = current_player;
+
+        if self.is_game_over(game_state) {
+            board.lock();
+            self.turn_start = None;
+            self.stage = TurnStage::GameOver;
+            return;
+        }
+
+        self.start_turn(board, settings);
+    }
+
+    /// Announces that the current player's next move threatens to win, as required by the
+    /// announce threats house rule.
+    pub fn announce_threat(&mut self) {
+        self.threat_announced = true;
+    }
+
+    /// Checks that the house rules allow the current human player to make a move, stopping
+    /// their clock if they do.
+    ///
+    /// creates_threat is whether the move would let the player win on their next turn.
+    pub fn try_human_move(
+        &mut self,
+        creates_threat: bool,
+        settings: &Settings,
+    ) -> Result<(), String> {
+        if settings.house_rules.announce_threats && creates_threat && !self.threat_announced {
+            return Err("That move threatens to win, so it has to be announced first".to_owned());
+        }
+
+        self.stop_clock();
+        Ok(())
+    }
+
+    /// Stops the remote player's clock as their move arrives from the game server, once
+    /// the TurnManager awaits it.
+    pub fn remote_move_played(&mut self) {
+        self.stop_clock();
+    }
+
+    /// Returns how many seconds the current player has left to make their move, if moves
+    /// are timed, by the move time limit or their game clock, whichever runs out first.
+    ///
+    /// The clock stops once the player commits to a move, so the computer isn't charged
+    /// for the time its piece spends animating to the column.
+    pub fn time_remaining(&self, settings: &Settings) -> Option<f32> {
+        let elapsed = self.turn_start?.elapsed();
+        let move_left = settings
+            .house_rules
+            .move_time_limit
+            .map(|limit| Duration::from_secs(limit as u64).saturating_sub(elapsed));
+        let clock_left = self
+            .clock_times()
+            .zip(self.player_index())
+            .map(|(times, player)| times[player]);
+
+        move_left
+            .into_iter()
+            .chain(clock_left)
+            .min()
+            .map(|left| left.as_secs_f32())
+    }
+
+    /// Starts the players' clocks over for a new game, if the house rules give them a time
+    /// control. A time control chosen during a game applies from the next one.
+    pub fn reset_clock(&mut self, settings: &Settings) {
+        self.clock = settings.house_rules.time_control.map(GameClock::new);
+    }
+
+    /// Returns how long each player has left on their clock, player one first, if the game
+    /// is played with a time control.
+    pub fn clock_times(&self) -> Option<[Duration; 2]> {
+        let clock = self.clock.as_ref()?;
+        let mut times = [clock.remaining(0), clock.remaining(1)];
+
+        if let (Some(start), Some(player)) = (self.turn_start, self.player_index()) {
+            times[player] = times[player].saturating_sub(start.elapsed());
+        }
+        Some(times)
+    }
+
+    /// Stops the current player's clock as they commit to their move, charging the time
+    /// they took to their game clock.
+    ///
+    /// Returns how long they took, if their clock was running.
+    fn stop_clock(&mut self) -> Option<Duration> {
+        let spent = self.turn_start.take()?.elapsed();
+
+        let player = self.player_index();
+        if let (Some(clock), Some(player)) = (&mut self.clock, player) {
+            clock.charge(player, spent);
+        }
+        Some(spent)
+    }
+
+    /// Returns 0 if player one is to move and 1 if player two is.
+    fn player_index(&self) -> Option<usize> {
+        match self.current_player {
+            PieceState::PlayerOne => Some(0),
+            PieceState::PlayerTwo => Some(1),
+            PieceState::Empty => None,
+        }
+    }
+
+    /// Returns how many seconds the computer thinks before moving, which in a game with a
+    /// time control is as long as it can afford from its clock rather than the delay.
+    fn thinking_time(&self, settings: &Settings) -> f32 {
+        match (&self.clock, self.player_index()) {
+            (Some(clock), Some(player)) => clock.search_budget(player).as_secs_f32(),
+            _ => settings.delay,
+        }
+    }
+
+    /// Returns the moves to go back to when the human takes back their last move, if it
+    /// can be taken back.
+    ///
+    /// Takebacks are only allowed in practice games, while a human is deciding on their
+    /// move or once the game is over. Moves are taken back until it's a human's turn again,
+    /// so the computer's reply goes along with the human's move.
+    pub fn takeback(&self, moves: &str, settings: &Settings) -> Option<String> {
+        let human_deciding =
+            self.current_player_type == PlayerType::Human && self.turn_start.is_some();
+        if !settings.practice || !(human_deciding || self.stage == TurnStage::GameOver) {
+            return None;
+        }
+
+        let mut moves = moves.to_owned();
+        loop {
+            moves.pop()?;
+            // A power-up is written with a marker in front of its column
+            if moves.ends_with(POWER_UP_MARKERS) {
+                moves.pop();
+            }
+
+            let played = moves
+                .chars()
+                .filter(|c| !POWER_UP_MARKERS.contains(c))
+                .count();
+            let player = if played.is_multiple_of(2) { 0 } else { 1 };
+            if settings.players[player] == PlayerType::Human {
+                return Some(moves);
+            }
+        }
+    }
+
+    /// Alerts the TurnManager that the settings' player types have changed.
+    ///
+    /// If the current player has changed type and hasn't committed to a move yet, their turn
+    /// starts over as the new type. Otherwise the change applies from the next turn.
+    ///
+    /// Returns whether the current turn was started over.
+    pub fn players_changed(&mut self, board: &mut Board, settings: &Settings) -> bool {
+        let player_type = match self.current_player {
+            PieceState::PlayerOne => settings.players[0],
+            PieceState::PlayerTwo => settings.players[1],
+            PieceState::Empty => return false,
+        };
+        let deciding = match self.stage {
+            TurnStage::Delay { .. } | TurnStage::WaitingForUpdate => true,
+            TurnStage::WaitingForMoveReceipt => {
+                self.awaits_human_move() || self.awaits_remote_move()
+            }
+            TurnStage::AnimateToChosenColumn { .. } | TurnStage::GameOver => false,
+        };
+        if player_type == self.current_player_type || !deciding {
+            return false;
+        }
+
+        self.start_turn(board, settings);
+        true
+    }
+
+    /// Returns how long the computer took to commit to its last move, and how long its
+    /// piece then spent animating, if it's made a move.
+    pub fn last_computer_move(&self) -> Option<MoveTiming> {
+        self.last_computer_move
+    }
+
+    /// Sets up the board and turn stage for the current player to make a move.
+    fn start_turn(&mut self, board: &mut Board, settings: &Settings) {
         self.current_player_type = match self.current_player {
             PieceState::PlayerOne => settings.players[0],
             PieceState::PlayerTwo => settings.players[1],
             PieceState::Empty => panic!("Current player is empty"),
         };
+        self.threat_announced = false;
+        self.previewed_column = None;
+        self.turn_start = Some(Instant::now());
+        self.committed = None;
 
         if self.current_player_type == PlayerType::Human {
             board.unlock();
 
-            // We stay waiting for a receipt
+            // We wait for a receipt of the human's move
+            self.stage = TurnStage::WaitingForMoveReceipt;
+            return;
+        }
+        if self.current_player_type == PlayerType::Remote {
+            board.lock();
+
+            // The remote player's move arrives from the game server
+            self.stage = TurnStage::WaitingForMoveReceipt;
             return;
         }
 
-        // If the computer is going next, we can start the delay animation
-        board.animate_floater(ctx, 0, 0.0);
+        // If the computer is going next, it thinks for a while before moving
+        board.lock();
 
         self.stage = TurnStage::Delay {
             start: Instant::now(),
-            animating_to_column: BOARD_WIDTH as usize - 1,
         };
     }
 
+    /// Returns how far through its thinking time the computer is, from 0.0 to 1.0, or None
+    /// if it isn't deciding on a move.
+    pub fn thinking_progress(&self, settings: &Settings) -> Option<f32> {
+        thinking_progress(&self.stage, self.thinking_time(settings))
+    }
+
     /// Returns whether the game state indicates that the game is over.
     fn is_game_over(&self, game_state: GameOver) -> bool {
-        match game_state {
-            GameOver::NoWin => false,
-            GameOver::Tie => {
-                println!("Tie!");
-                true
-            }
-            GameOver::OneWins => {
-                println!("Player One Wins!");
-                true
-            }
-            GameOver::TwoWins => {
-                println!("Player Two Wins!");
-                true
-            }
+        game_state != GameOver::NoWin
+    }
+
+    /// Returns whether a human is to move and hasn't committed to their move yet.
+    pub fn awaits_human_move(&self) -> bool {
+        self.current_player_type == PlayerType::Human
+            && self.stage == TurnStage::WaitingForMoveReceipt
+            && self.turn_start.is_some()
+    }
+
+    /// Returns whether a remote player is to move and their move hasn't arrived yet.
+    pub fn awaits_remote_move(&self) -> bool {
+        self.current_player_type == PlayerType::Remote
+            && self.stage == TurnStage::WaitingForMoveReceipt
+            && self.turn_start.is_some()
+    }
+
+    /// Returns the column the engine currently prefers while the computer delays its move,
+    /// once it's sent an update during the delay.
+    pub fn previewed_column(&self) -> Option<usize> {
+        match self.stage {
+            TurnStage::Delay { .. } | TurnStage::WaitingForUpdate => self.previewed_column,
+            _ => None,
         }
     }
 
     /// Alerts the Turn Manager that the computer has sent an update.
+    ///
+    /// Updates during the delay only change which column is previewed, and the update that
+    /// follows the delay decides the computer's move. The computer spends its power-up
+    /// instead of dropping a piece when power_up_scores rates it higher than any drop.
     pub fn update_received(
         &mut self,
         move_scores: &HashMap<u8, isize>,
+        power_up_scores: &HashMap<u8, isize>,
         ctx: &Context,
         board: &mut Board,
         settings: &Settings,
     ) {
-        if let TurnStage::WaitingForUpdate {
-            animating_to_column: _,
-        } = self.stage
-        {
+        if let TurnStage::Delay { .. } = self.stage {
+            if !move_scores.is_empty() {
+                self.previewed_column = Some(thinking_column(move_scores));
+            }
+        }
+
+        if let TurnStage::WaitingForUpdate = self.stage {
             board.cancel_animation(ctx);
 
-            self.stage = TurnStage::AnimateToChosenColumn {
-                chosen_column: choose_computer_move(move_scores, settings),
+            // Choosing the column commits the computer to its move, stopping its clock
+            let thinking = self.stop_clock();
+            self.committed = Some((Instant::now(), thinking.unwrap_or_default()));
+
+            let power_up = settings
+                .enabled_power_up()
+                .zip(choose_power_up(move_scores, power_up_scores));
+            self.stage = match power_up {
+                Some((kind, column)) => TurnStage::AnimateToChosenColumn {
+                    chosen_column: column as usize,
+                    power_up: Some(kind),
+                },
+                None => TurnStage::AnimateToChosenColumn {
+                    chosen_column: choose_computer_move(
+                        move_scores,
+                        &settings.difficulty,
+                        board,
+                        self.current_player,
+                        &mut self.rng,
+                    ),
+                    power_up: None,
+                },
             };
         }
     }
 
     /// Handles the main logic for processing a turn.
+    ///
+    /// Returns the player who lost on time, if the current player just ran out of time, or
+    /// the power-up the computer spent, which is left for the caller to play.
+    ///
+    /// Returns an error if the computer's move couldn't be played on the board, which leaves
+    /// the turn stalled until the caller loads the position again.
     pub fn process_turn(
         &mut self,
         ctx: &Context,
         board: &mut Board,
         settings: &Settings,
-        sender: &Sender<UIMessage>,
-    ) {
+        engine: &EngineHandle,
+    ) -> Result<Option<TurnEvent>, String> {
+        if self.time_remaining(settings) == Some(0.0) {
+            board.lock();
+            self.stop_clock();
+            self.stage = TurnStage::GameOver;
+            return Ok(Some(TurnEvent::TimedOut(self.current_player)));
+        }
+
         let mut next_stage = None;
+        let thinking_time = self.thinking_time(settings);
 
         match &mut self.stage {
             TurnStage::WaitingForMoveReceipt => (), // continue
-            TurnStage::Delay {
-                start,
-                animating_to_column,
-            } => {
-                passively_animate_floater(ctx, board, animating_to_column);
-
-                if start.elapsed().as_secs_f32() > settings.delay {
-                    sender
-                        .send(UIMessage::RequestUpdate)
-                        .expect("Couldn't send RequestUpdate");
+            TurnStage::Delay { start } => {
+                if start.elapsed().as_secs_f32() > thinking_time {
+                    engine.send(UIMessage::RequestUpdate);
 
-                    next_stage = Some(TurnStage::WaitingForUpdate {
-                        animating_to_column: *animating_to_column,
-                    });
+                    next_stage = Some(TurnStage::WaitingForUpdate);
                 }
             }
-            TurnStage::WaitingForUpdate {
-                animating_to_column,
+            TurnStage::WaitingForUpdate => (), // continue
+            TurnStage::AnimateToChosenColumn {
+                chosen_column,
+                power_up,
             } => {
-                passively_animate_floater(ctx, board, animating_to_column);
-            }
-            TurnStage::AnimateToChosenColumn { chosen_column } => {
                 let completed_animation = board.animate_floater(ctx, *chosen_column, 1.0);
 
                 if completed_animation {
                     board.cancel_animation(ctx);
-                    board.drop_piece(ctx, *chosen_column, self.current_player);
-
-                    sender
-                        .send(UIMessage::MakeMove(*chosen_column))
-                        .expect("Couldn't send move to interface");
+                    let (column, power_up) = (*chosen_column, *power_up);
+                    if power_up.is_none() {
+                        if let Err(error) = board.drop_piece(ctx, column, self.current_player) {
+                            self.stage = TurnStage::WaitingForMoveReceipt;
+                            return Err(format!("Couldn't play the computer's move - {}", error));
+                        }
+                    }
+
+                    if let Some((committed_at, thinking)) = self.committed.take() {
+                        self.last_computer_move = Some(MoveTiming {
+                            thinking,
+                            animation: committed_at.elapsed(),
+                        });
+                    }
+
+                    // Loading the position with the power-up spent starts the next turn
+                    if let Some(kind) = power_up {
+                        self.stage = TurnStage::WaitingForMoveReceipt;
+                        return Ok(Some(TurnEvent::PowerUpSpent(kind.spend(column as u8))));
+                    }
+
+                    engine.send(UIMessage::MakeMove(column));
 
                     next_stage = Some(TurnStage::WaitingForMoveReceipt);
                 }
@@ -198,67 +511,250 @@ impl TurnManager {
         if let Some(stage) = next_stage {
             self.stage = stage;
         }
+
+        Ok(None)
     }
 }
 
-/// Animates the floater piece as going left and right.
+/// How long the computer spent on a move, split at the moment it committed to a column.
 ///
-/// animating_to_column will be modified as the floater changes which direction it's floating.
-fn passively_animate_floater(ctx: &Context, board: &mut Board, animating_to_column: &mut usize) {
-    let completed_animation = board.animate_floater(ctx, *animating_to_column, 1.5);
+/// Only the thinking counts against the computer's clock in timed games.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveTiming {
+    /// From the start of the computer's turn until it chose its column.
+    pub thinking: Duration,
+    /// From choosing its column until its piece had animated there and was dropped.
+    pub animation: Duration,
+}
 
-    if completed_animation {
-        *animating_to_column = BOARD_WIDTH as usize - 1 - *animating_to_column;
-    }
+/// Handles alternating turns between two computer players in a game that is only being
+/// watched, such as an engine vs engine exhibition.
+///
+/// Each player is backed by its own engine, so every move needs to be sent to both engines
+/// and acknowledged by both before the next turn can start.
+pub struct SpectatorTurnManager {
+    pub current_player: PieceState,
+    stage: TurnStage,
+    /// How many engines have yet to acknowledge the last move.
+    pending_receipts: usize,
+    /// Moves to play instead of the engines' choices, in order, when replaying a game.
+    script: VecDeque<usize>,
+    rng: StdRng,
 }
 
-/// Chooses a move based on the difficulty setting and the engine's move scores.
-fn choose_computer_move(move_scores: &HashMap<u8, isize>, settings: &Settings) -> usize {
-    if move_scores.len() == 0 {
-        panic!("Trying to pick a move when no moves are valid");
+impl SpectatorTurnManager {
+    /// Creates a new SpectatorTurnManager, with player one about to move.
+    ///
+    /// The script's moves are played first, in order, and then the engines choose their own
+    /// moves, making their random choices with the seed.
+    pub fn new(seed: u64, script: Vec<usize>) -> SpectatorTurnManager {
+        SpectatorTurnManager {
+            current_player: PieceState::PlayerOne,
+            stage: TurnStage::Delay {
+                start: Instant::now(),
+            },
+            pending_receipts: 0,
+            script: script.into(),
+            rng: StdRng::seed_from_u64(seed),
+        }
     }
 
-    // Highest scoring moves at the end
-    let mut sorted_moves = move_scores
-        .iter()
-        .map(|(column, score)| (*score, *column))
-        .collect::<Vec<(isize, u8)>>();
-    sorted_moves.sort();
+    /// Returns the index of the engine that is playing for the current player.
+    pub fn current_engine(&self) -> usize {
+        match self.current_player {
+            PieceState::PlayerTwo => 1,
+            _ => 0,
+        }
+    }
+
+    /// Alerts the SpectatorTurnManager that one of the engines has acknowledged a move.
+    ///
+    /// Once both engines have acknowledged the move, the other player's turn starts.
+    pub fn move_receipt(&mut self, game_state: GameOver) {
+        if self.stage != TurnStage::WaitingForMoveReceipt || self.pending_receipts == 0 {
+            return;
+        }
+
+        self.pending_receipts -= 1;
+        if self.pending_receipts > 0 {
+            return;
+        }
+
+        if game_state != GameOver::NoWin {
+            self.stage = TurnStage::GameOver;
+            return;
+        }
+
+        self.current_player = self.current_player.reverse();
+
+        self.stage = TurnStage::Delay {
+            start: Instant::now(),
+        };
+    }
+
+    /// Returns how far through its thinking time the current engine is, from 0.0 to 1.0,
+    /// or None if it isn't deciding on a move.
+    ///
+    /// delay is how long each engine thinks before moving.
+    pub fn thinking_progress(&self, delay: f32) -> Option<f32> {
+        thinking_progress(&self.stage, delay)
+    }
+
+    /// Alerts the SpectatorTurnManager that one of the engines has sent an update.
+    pub fn update_received(
+        &mut self,
+        engine: usize,
+        move_scores: &HashMap<u8, isize>,
+        difficulty: &Difficulty,
+        ctx: &Context,
+        board: &mut Board,
+    ) {
+        if engine != self.current_engine() || move_scores.is_empty() {
+            return;
+        }
+
+        if let TurnStage::WaitingForUpdate = self.stage {
+            board.cancel_animation(ctx);
+
+            let chosen_column = self.script.pop_front().unwrap_or_else(|| {
+                choose_computer_move(
+                    move_scores,
+                    difficulty,
+                    board,
+                    self.current_player,
+                    &mut self.rng,
+                )
+            });
+            self.stage = TurnStage::AnimateToChosenColumn {
+                chosen_column,
+                power_up: None,
+            };
+        }
+    }
+
+    /// Handles the main logic for processing a turn.
+    ///
+    /// delay is how long each engine thinks before moving, and engines are the engines
+    /// for player one and player two respectively.
+    ///
+    /// Returns an error if an engine's move couldn't be played on the board, which leaves
+    /// the game stalled.
+    pub fn process_turn(
+        &mut self,
+        ctx: &Context,
+        board: &mut Board,
+        delay: f32,
+        engines: [&EngineHandle; 2],
+    ) -> Result<(), String> {
+        let mut next_stage = None;
+        let current_engine = self.current_engine();
+
+        match &mut self.stage {
+            TurnStage::WaitingForMoveReceipt => (), // continue
+            TurnStage::Delay { start } => {
+                if start.elapsed().as_secs_f32() > delay {
+                    engines[current_engine].send(UIMessage::RequestUpdate);
+
+                    next_stage = Some(TurnStage::WaitingForUpdate);
+                }
+            }
+            TurnStage::WaitingForUpdate => (), // continue
+            TurnStage::AnimateToChosenColumn { chosen_column, .. } => {
+                // Faster exhibitions get faster animations
+                let animation_time = delay.clamp(0.1, 1.0);
+                let completed_animation =
+                    board.animate_floater(ctx, *chosen_column, animation_time);
+
+                if completed_animation {
+                    board.cancel_animation(ctx);
+                    let column = *chosen_column;
+                    if let Err(error) = board.drop_piece(ctx, column, self.current_player) {
+                        self.stage = TurnStage::WaitingForMoveReceipt;
+                        return Err(format!("Couldn't play the engine's move - {}", error));
+                    }
+
+                    for engine in engines {
+                        engine.send(UIMessage::MakeMove(column));
+                    }
 
-    match settings.difficulty {
-        Difficulty::Easy => easy_choose_move(sorted_moves) as usize,
-        Difficulty::Medium => medium_choose_move(sorted_moves) as usize,
-        Difficulty::Hard => sorted_moves.pop().unwrap().1 as usize,
+                    self.pending_receipts = engines.len();
+                    next_stage = Some(TurnStage::WaitingForMoveReceipt);
+                }
+            }
+            TurnStage::GameOver => (), // continue
+        }
+
+        if let Some(stage) = next_stage {
+            self.stage = stage;
+        }
+
+        Ok(())
     }
 }
 
-/// Picks one of the moves in the sorted_moves Vector.
+impl Default for SpectatorTurnManager {
+    fn default() -> Self {
+        Self::new(rand::random(), Vec::new())
+    }
+}
+
+/// Returns how far through a computer's thinking time of delay seconds the turn stage is,
+/// from 0.0 to 1.0, or None if the computer isn't deciding on a move.
 ///
-/// Higher rated moves are more likely to be picked.
-fn easy_choose_move(sorted_moves: Vec<(isize, u8)>) -> u8 {
-    let mut weighted_moves = Vec::new();
-    for (index, (_, column)) in sorted_moves.into_iter().enumerate() {
-        for _ in 0..(index + 1) {
-            weighted_moves.push(column);
+/// Once the time is up the computer still has to hear back from its engine, so waiting
+/// for the update counts as having used all of it.
+fn thinking_progress(stage: &TurnStage, delay: f32) -> Option<f32> {
+    match stage {
+        TurnStage::Delay { start } if delay > 0.0 => {
+            Some((start.elapsed().as_secs_f32() / delay).min(1.0))
         }
+        TurnStage::Delay { .. } | TurnStage::WaitingForUpdate => Some(1.0),
+        _ => None,
     }
+}
 
-    *weighted_moves.choose(&mut rand::thread_rng()).unwrap()
+/// Returns the column the engine currently rates best for the player about to move, which
+/// is where the computer is leaning, or the middle column before there are any scores.
+pub fn thinking_column(move_scores: &HashMap<u8, isize>) -> usize {
+    move_scores
+        .iter()
+        .max_by_key(|(column, score)| (**score, Reverse(**column)))
+        .map(|(column, _)| *column as usize)
+        .unwrap_or(BOARD_WIDTH as usize / 2)
 }
 
-/// Picks one of the moves in the sorted_moves Vector.
+/// Chooses a move based on the difficulty setting and the engine's move scores.
 ///
-/// Higher rated moves are more likely to be picked and losing moves will not be considered.
-fn medium_choose_move(sorted_moves: Vec<(isize, u8)>) -> u8 {
-    let backup_move = sorted_moves[0].1;
+/// player is the player making the move on the board, and rng makes any random choices.
+fn choose_computer_move(
+    move_scores: &HashMap<u8, isize>,
+    difficulty: &Difficulty,
+    board: &Board,
+    player: PieceState,
+    rng: &mut StdRng,
+) -> usize {
+    if move_scores.len() == 0 {
+        panic!("Trying to pick a move when no moves are valid");
+    }
 
-    let no_losing_moves = sorted_moves
-        .into_iter()
-        .filter(|(score, _)| *score != isize::MIN)
+    // Highest scoring moves at the end
+    let mut sorted_moves = move_scores
+        .iter()
+        .map(|(column, score)| (*score, *column))
         .collect::<Vec<(isize, u8)>>();
-    if no_losing_moves.len() == 0 {
-        return backup_move;
-    }
+    sorted_moves.sort();
 
-    easy_choose_move(no_losing_moves)
+    match difficulty {
+        // Easier engines are weakened by the engine itself, so they play their best move too
+        Difficulty::Easy | Difficulty::Medium | Difficulty::Hard => {
+            sorted_moves.pop().unwrap().1 as usize
+        }
+        Difficulty::HumanLike => choose_human_like_move(
+            board.get_position(),
+            matches!(player, PieceState::PlayerTwo),
+            move_scores,
+            rng,
+        )
+        .unwrap() as usize,
+    }
 }
@@ -0,0 +1,357 @@
+//! Describes what the board should look like as plain data, so that any frontend can draw it
+//! without reimplementing how the game is displayed.
+//!
+//! A BoardView is built from the moves played, and two views can be diffed so a frontend
+//! only has to redraw what changed. Every type can be serialized, e.g. to JSON for a web
+//! frontend. Columns are numbered from 0, and rows count up from the bottom of the board.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts::{BOARD_HEIGHT, BOARD_WIDTH},
+    game_engine::{game_manager::GameManager, power_ups::parse_moves},
+};
+
+/// How many seconds a dropped piece takes to fall past each row, including the row above
+/// the board it's dropped from.
+pub const FALL_TIME_PER_ROW: f32 = 0.12;
+
+/// How many pieces in a row win the game.
+const WINNING_LENGTH: usize = 4;
+
+/// One of the two players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Player {
+    /// Red, who moves first.
+    One,
+    /// Blue.
+    Two,
+}
+
+/// A piece on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PieceView {
+    pub column: usize,
+    pub row: usize,
+    pub player: Player,
+}
+
+/// A piece that should be shown falling into place.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FallingPiece {
+    pub piece: PieceView,
+    /// How many seconds the fall should take, starting from just above the board.
+    pub duration: f32,
+}
+
+/// Something on the board that should be drawn attention to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Highlight {
+    /// The space the last move was played in.
+    LastMove { column: usize, row: usize },
+    /// The spaces making up a connect four.
+    WinningLine(Vec<(usize, usize)>),
+    /// An empty space that would win the game for the player.
+    Threat {
+        column: usize,
+        row: usize,
+        player: Player,
+        playable: bool,
+    },
+    /// The engine's score for playing in the column, for the player about to move.
+    Hint { column: usize, score: isize },
+}
+
+/// Everything a frontend needs to draw the board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoardView {
+    pub width: usize,
+    pub height: usize,
+    /// Every piece on the board, including the falling one.
+    pub pieces: Vec<PieceView>,
+    pub falling: Option<FallingPiece>,
+    pub highlights: Vec<Highlight>,
+    /// The player about to move, or None if the game is over.
+    pub next_player: Option<Player>,
+}
+
+/// A change between two views of the board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ViewChange {
+    PieceAdded(PieceView),
+    PieceRemoved(PieceView),
+    /// A piece started falling into place.
+    PieceFalling(FallingPiece),
+    HighlightAdded(Highlight),
+    HighlightRemoved(Highlight),
+    NextPlayerChanged(Option<Player>),
+}
+
+impl BoardView {
+    /// Builds the view of the board after the given moves, as columns numbered from 1.
+    ///
+    /// The last move is shown falling into place. Hints are shown for each of the
+    /// move_scores, and threats are only highlighted if show_threats is set.
+    pub fn from_moves(
+        moves: &str,
+        move_scores: &HashMap<u8, isize>,
+        show_threats: bool,
+    ) -> Result<BoardView, String> {
+        let manager = GameManager::from_moves(moves)?;
+        let position = manager.get_position();
+
+        let mut pieces = Vec::new();
+        for (index, row) in position.iter().enumerate() {
+            for (column, piece) in row.iter().enumerate() {
+                let player = match piece {
+                    0 => continue,
+                    1 => Player::One,
+                    _ => Player::Two,
+                };
+
+                pieces.push(PieceView {
+                    column,
+                    row: BOARD_HEIGHT as usize - 1 - index,
+                    player,
+                });
+            }
+        }
+        pieces.sort_by_key(|piece| (piece.column, piece.row));
+
+        let mut highlights = Vec::new();
+
+        let last_column = parse_moves(moves)?
+            .last()
+            .map(|last| last.column() as usize);
+        let falling = last_column.and_then(|column| {
+            let piece = *pieces
+                .iter()
+                .filter(|piece| piece.column == column)
+                .max_by_key(|piece| piece.row)?;
+            highlights.push(Highlight::LastMove {
+                column,
+                row: piece.row,
+            });
+
+            Some(FallingPiece {
+                piece,
+                duration: FALL_TIME_PER_ROW * (BOARD_HEIGHT as usize - piece.row) as f32,
+            })
+        });
+
+        let winning_line = winning_line(&position);
+        let game_over = winning_line.is_some() || pieces.len() == pieces_to_fill_board();
+        if let Some(line) = winning_line {
+            highlights.push(Highlight::WinningLine(line));
+        }
+
+        if show_threats && !game_over {
+            for (player, turn) in [(Player::One, false), (Player::Two, true)] {
+                for threat in manager.get_threats(turn) {
+                    highlights.push(Highlight::Threat {
+                        column: threat.column as usize,
+                        row: threat.row as usize,
+                        player,
+                        playable: threat.playable,
+                    });
+                }
+            }
+        }
+
+        let mut hints: Vec<(&u8, &isize)> = move_scores.iter().collect();
+        hints.sort();
+        for (column, score) in hints {
+            highlights.push(Highlight::Hint {
+                column: *column as usize,
+                score: *score,
+            });
+        }
+
+        Ok(BoardView {
+            width: BOARD_WIDTH as usize,
+            height: BOARD_HEIGHT as usize,
+            pieces,
+            falling,
+            highlights,
+            next_player: match (game_over, manager.get_turn()) {
+                (true, _) => None,
+                (false, false) => Some(Player::One),
+                (false, true) => Some(Player::Two),
+            },
+        })
+    }
+
+    /// Returns the changes that turn the previous view into this one.
+    pub fn diff(&self, previous: &BoardView) -> Vec<ViewChange> {
+        let mut changes = Vec::new();
+
+        for piece in &previous.pieces {
+            if !self.pieces.contains(piece) {
+                changes.push(ViewChange::PieceRemoved(*piece));
+            }
+        }
+        for piece in &self.pieces {
+            if !previous.pieces.contains(piece) {
+                changes.push(ViewChange::PieceAdded(*piece));
+            }
+        }
+
+        if let Some(falling) = self.falling {
+            if previous.falling.map(|previous| previous.piece) != Some(falling.piece) {
+                changes.push(ViewChange::PieceFalling(falling));
+            }
+        }
+
+        for highlight in &previous.highlights {
+            if !self.highlights.contains(highlight) {
+                changes.push(ViewChange::HighlightRemoved(highlight.clone()));
+            }
+        }
+        for highlight in &self.highlights {
+            if !previous.highlights.contains(highlight) {
+                changes.push(ViewChange::HighlightAdded(highlight.clone()));
+            }
+        }
+
+        if self.next_player != previous.next_player {
+            changes.push(ViewChange::NextPlayerChanged(self.next_player));
+        }
+
+        changes
+    }
+}
+
+/// Returns how many pieces there are on a full board.
+fn pieces_to_fill_board() -> usize {
+    BOARD_WIDTH as usize * BOARD_HEIGHT as usize
+}
+
+/// Finds a connect four in the position, given as array[row][col], returning its spaces as
+/// (column, row from the bottom).
+pub fn winning_line(
+    position: &[[u8; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+) -> Option<Vec<(usize, usize)>> {
+    let height = BOARD_HEIGHT as isize;
+    let width = BOARD_WIDTH as isize;
+
+    for row in 0..height {
+        for column in 0..width {
+            let player = position[row as usize][column as usize];
+            if player == 0 {
+                continue;
+            }
+
+            // Right, down, down and to the right, and down and to the left
+            for (row_step, column_step) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+                let line: Vec<(isize, isize)> = (0..WINNING_LENGTH as isize)
+                    .map(|step| (row + row_step * step, column + column_step * step))
+                    .collect();
+
+                let complete = line.iter().all(|(row, column)| {
+                    (0..height).contains(row)
+                        && (0..width).contains(column)
+                        && position[*row as usize][*column as usize] == player
+                });
+                if complete {
+                    return Some(
+                        line.into_iter()
+                            .map(|(row, column)| (column as usize, (height - 1 - row) as usize))
+                            .collect(),
+                    );
+                }
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::user_interface::view_model::{BoardView, Highlight, PieceView, Player, ViewChange};
+
+    #[test]
+    fn board_views() {
+        let view = BoardView::from_moves("445", &HashMap::from([(3, 5)]), false).unwrap();
+        assert_eq!(view.pieces.len(), 3);
+        assert!(view.pieces.contains(&PieceView {
+            column: 3,
+            row: 1,
+            player: Player::Two,
+        }));
+        assert_eq!(view.next_player, Some(Player::Two));
+
+        let falling = view.falling.unwrap();
+        assert_eq!(
+            falling.piece,
+            PieceView {
+                column: 4,
+                row: 0,
+                player: Player::One,
+            }
+        );
+        assert!(view
+            .highlights
+            .contains(&Highlight::LastMove { column: 4, row: 0 }));
+        assert!(view.highlights.contains(&Highlight::Hint {
+            column: 3,
+            score: 5
+        }));
+
+        // Red wins vertically in the first column
+        let view = BoardView::from_moves("1212121", &HashMap::new(), true).unwrap();
+        assert_eq!(view.next_player, None);
+        assert!(view.highlights.contains(&Highlight::WinningLine(vec![
+            (0, 3),
+            (0, 2),
+            (0, 1),
+            (0, 0)
+        ])));
+        assert!(!view
+            .highlights
+            .iter()
+            .any(|highlight| matches!(highlight, Highlight::Threat { .. })));
+
+        // Red threatens to win in the first column
+        let view = BoardView::from_moves("121212", &HashMap::new(), true).unwrap();
+        assert!(view.highlights.contains(&Highlight::Threat {
+            column: 0,
+            row: 3,
+            player: Player::One,
+            playable: true,
+        }));
+
+        assert!(BoardView::from_moves("8", &HashMap::new(), false).is_err());
+    }
+
+    #[test]
+    fn diffs() {
+        let before = BoardView::from_moves("44", &HashMap::new(), false).unwrap();
+        let after = BoardView::from_moves("445", &HashMap::new(), false).unwrap();
+        let changes = after.diff(&before);
+
+        let added = PieceView {
+            column: 4,
+            row: 0,
+            player: Player::One,
+        };
+        assert!(changes.contains(&ViewChange::PieceAdded(added)));
+        assert!(changes.contains(&ViewChange::PieceFalling(after.falling.unwrap())));
+        assert!(
+            changes.contains(&ViewChange::HighlightRemoved(Highlight::LastMove {
+                column: 3,
+                row: 1,
+            }))
+        );
+        assert!(changes.contains(&ViewChange::NextPlayerChanged(Some(Player::Two))));
+        assert!(!changes
+            .iter()
+            .any(|change| matches!(change, ViewChange::PieceRemoved(_))));
+
+        assert!(after.diff(&after).is_empty());
+    }
+}